@@ -116,6 +116,10 @@ pub struct ScaleComponentCommand {
     /// Normally this is implemented by the receiver (ex. wasmcloud host) as a *separate* update component call
     /// being made shortly after this command (scale) is processed.
     pub(crate) allow_update: bool,
+    /// The maximum amount of fuel a single invocation of this component can consume. Only
+    /// enforced by hosts with fuel metering enabled.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) fuel_budget: Option<u64>,
 }
 
 impl ScaleComponentCommand {
@@ -154,6 +158,11 @@ impl ScaleComponentCommand {
         &self.host_id
     }
 
+    #[must_use]
+    pub fn fuel_budget(&self) -> Option<u64> {
+        self.fuel_budget
+    }
+
     #[must_use]
     pub fn builder() -> ScaleComponentCommandBuilder {
         ScaleComponentCommandBuilder::default()
@@ -171,6 +180,7 @@ pub struct ScaleComponentCommandBuilder {
     host_id: Option<String>,
     config: Option<Vec<String>>,
     allow_update: Option<bool>,
+    fuel_budget: Option<u64>,
 }
 
 impl ScaleComponentCommandBuilder {
@@ -221,6 +231,12 @@ impl ScaleComponentCommandBuilder {
         self
     }
 
+    #[must_use]
+    pub fn fuel_budget(mut self, v: u64) -> Self {
+        self.fuel_budget = Some(v);
+        self
+    }
+
     pub fn build(self) -> Result<ScaleComponentCommand> {
         Ok(ScaleComponentCommand {
             component_ref: self
@@ -236,6 +252,7 @@ impl ScaleComponentCommandBuilder {
                 .ok_or_else(|| "host id is required for scaling hosts host".to_string())?,
             config: self.config.unwrap_or_default(),
             allow_update: self.allow_update.unwrap_or_default(),
+            fuel_budget: self.fuel_budget,
         })
     }
 }
@@ -618,6 +635,7 @@ mod tests {
                 allow_update: true,
                 annotations: Some(BTreeMap::from([("a".into(), "b".into())])),
                 max_instances: 1,
+                fuel_budget: Some(1_000_000),
             },
             ScaleComponentCommand::builder()
                 .component_ref("component_ref")
@@ -627,6 +645,7 @@ mod tests {
                 .allow_update(true)
                 .annotations(BTreeMap::from([("a".into(), "b".into())]))
                 .max_instances(1)
+                .fuel_budget(1_000_000)
                 .build()
                 .unwrap()
         )
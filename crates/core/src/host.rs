@@ -43,9 +43,19 @@ pub struct HostData {
     pub instance_id: String,
     /// initial list of links for provider
     pub link_definitions: Vec<InterfaceLinkDefinition>,
-    /// list of cluster issuers.
+    /// list of cluster issuers. If not empty, the provider only accepts invocations carrying
+    /// invocation claims signed by one of them
     #[serde(default)]
     pub cluster_issuers: Vec<String>,
+    /// The seed of the key the provider signs the invocation claims of its invocations with,
+    /// empty if invocations are not signed. This is a key generated for the provider, never the
+    /// host key
+    #[serde(default)]
+    pub invocation_seed: String,
+    /// The delegation signed by the host authorizing the provider's invocation key to sign
+    /// invocations, sent along with them
+    #[serde(default)]
+    pub invocation_delegation: String,
     /// Merged named configuration set for this provider at runtime
     #[serde(default)]
     pub config: HashMap<String, String>,
@@ -76,5 +86,6 @@ impl ZeroizeOnDrop for HostData {}
 impl Zeroize for HostData {
     fn zeroize(&mut self) {
         self.provider_xkey_private_key.zeroize();
+        self.invocation_seed.zeroize();
     }
 }
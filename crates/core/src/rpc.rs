@@ -7,7 +7,37 @@
 //!
 //! [docs-wasmcloud-rpc]: <https://wasmcloud.com/docs/hosts/lattice-protocols/rpc>
 
+use core::mem;
+use core::pin::Pin;
+use core::task::{ready, Context, Poll};
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::{bail, ensure, Context as _};
+use bytes::Bytes;
 use serde::{Deserialize, Serialize};
+use sha2::{Digest as _, Sha256};
+use tokio::io::{AsyncRead, ReadBuf};
+use wascap::jwt::{validate_token, Claims, Cluster, Invocation};
+use wascap::prelude::KeyPair;
+
+/// Name of the header carrying the signed [`Invocation`] claims of a wRPC invocation
+pub const INVOCATION_CLAIMS_HEADER: &str = "wasmcloud-invocation-claims";
+
+/// Name of the header carrying the delegation authorizing the issuer of the [`Invocation`]
+/// claims of a wRPC invocation to sign them, see [`delegate_invocations`]
+pub const INVOCATION_DELEGATION_HEADER: &str = "wasmcloud-invocation-delegation";
+
+/// How long signed [`Invocation`] claims are valid for, in seconds. Claims only need to outlive
+/// the delivery of the invocation, so they are kept short-lived to limit replays.
+pub const INVOCATION_CLAIMS_VALIDITY_SECS: u64 = 60;
+
+/// How long delegations issued by [`delegate_invocations`] are valid for, in seconds. Hosts renew
+/// the delegations of the providers they run well before they expire, so that a leaked provider
+/// key can no longer be used shortly after the provider stops.
+pub const INVOCATION_DELEGATION_VALIDITY_SECS: u64 = 60 * 60;
 
 #[derive(Clone, Debug, Default, Deserialize, Serialize)]
 pub struct HealthCheckRequest {}
@@ -66,3 +96,789 @@ pub fn shutdown_subject(lattice: &str, provider_key: &str, link_name: &str) -> S
 pub fn provider_config_update_subject(lattice: &str, provider_key: &str) -> String {
     format!("wasmbus.rpc.{lattice}.{provider_key}.config.update")
 }
+
+/// Generate the wasmbus RPC subject for delivering updated secrets to a given provider
+///
+/// When a secret referenced by a provider is rotated in its secrets backend, hosts publish the
+/// provider's secrets, encrypted with the provider's xkey, on this subject.
+///
+/// NOTE that the NATS message body limits (default 1MiB) apply to these messages
+#[must_use]
+pub fn provider_secrets_update_subject(lattice: &str, provider_key: &str) -> String {
+    format!("wasmbus.rpc.{lattice}.{provider_key}.secrets.update")
+}
+
+/// Generate the wasmbus RPC subject for delivering renewed invocation delegations to a given
+/// provider, see [`INVOCATION_DELEGATION_VALIDITY_SECS`]
+#[must_use]
+pub fn provider_invocation_delegation_subject(lattice: &str, provider_key: &str) -> String {
+    format!("wasmbus.rpc.{lattice}.{provider_key}.invocations.delegation")
+}
+
+/// Generate the URL identifying the origin of an invocation in [`Invocation`] claims
+#[must_use]
+pub fn invocation_origin_url(lattice: &str, source_id: &str) -> String {
+    format!("wasmbus://{lattice}/{source_id}")
+}
+
+/// Generate the URL identifying the target of an invocation in [`Invocation`] claims
+#[must_use]
+pub fn invocation_target_url(lattice: &str, target_id: &str, instance: &str, func: &str) -> String {
+    format!("wasmbus://{lattice}/{target_id}/{instance}.{func}")
+}
+
+/// Encode the hash of the parameters of an invocation in [`Invocation`] claims, which includes
+/// their length so that receivers know how much of the parameter stream is covered by it
+fn params_hash(params: &[u8]) -> String {
+    format!(
+        "sha256:{}:{}",
+        params.len(),
+        hex::encode(Sha256::digest(params))
+    )
+}
+
+/// Decode a hash encoded with [`params_hash`] into the length and digest of the parameters
+fn parse_params_hash(hash: &str) -> anyhow::Result<(usize, Vec<u8>)> {
+    let Some(("sha256", hash)) = hash.split_once(':') else {
+        bail!("unsupported invocation parameters hash `{hash}`");
+    };
+    let (len, digest) = hash
+        .split_once(':')
+        .context("invocation parameters hash is missing the length")?;
+    let len = len
+        .parse()
+        .context("invalid invocation parameters length")?;
+    let digest = hex::decode(digest)
+        .map_err(|e| anyhow::anyhow!("invalid invocation parameters digest: {e}"))?;
+    Ok((len, digest))
+}
+
+fn now() -> anyhow::Result<u64> {
+    Ok(SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .context("system time is before the Unix epoch")?
+        .as_secs())
+}
+
+/// Sign the [`Invocation`] claims of an invocation of `func` in `instance` of `target_id` by
+/// `source_id`, with `params` as the encoded parameters, returning the encoded JWT to send in the
+/// [`INVOCATION_CLAIMS_HEADER`] header.
+///
+/// # Errors
+///
+/// Returns an error if the claims could not be signed with `key`
+pub fn sign_invocation(
+    key: &KeyPair,
+    lattice: &str,
+    source_id: &str,
+    target_id: &str,
+    instance: &str,
+    func: &str,
+    params: &[u8],
+) -> anyhow::Result<String> {
+    Claims::<Invocation>::with_dates(
+        key.public_key(),
+        source_id.to_string(),
+        None,
+        Some(now()? + INVOCATION_CLAIMS_VALIDITY_SECS),
+        &invocation_target_url(lattice, target_id, instance, func),
+        &invocation_origin_url(lattice, source_id),
+        &params_hash(params),
+    )
+    .encode(key)
+    .context("failed to sign invocation claims")
+}
+
+/// Generate a key for `source_id`, e.g. a provider, to sign the [`Invocation`] claims of its
+/// invocations with, and a delegation signed by `issuer` authorizing it to do so, which is sent
+/// in the [`INVOCATION_DELEGATION_HEADER`] header.
+///
+/// This allows processes other than the host, which must not have access to the host key, to
+/// sign invocations accepted by receivers trusting `issuer`. Delegations expire after
+/// [`INVOCATION_DELEGATION_VALIDITY_SECS`], so they must be renewed using
+/// [`sign_invocation_delegation`] for as long as the key is used.
+///
+/// # Errors
+///
+/// Returns an error if the delegation could not be signed with `issuer`
+pub fn delegate_invocations(
+    issuer: &KeyPair,
+    source_id: &str,
+) -> anyhow::Result<(KeyPair, String)> {
+    let key = KeyPair::new_service();
+    let delegation = sign_invocation_delegation(issuer, source_id, &key.public_key())?;
+    Ok((key, delegation))
+}
+
+/// Sign a delegation by `issuer` authorizing `signer` to sign the [`Invocation`] claims of
+/// invocations by `source_id`, valid for [`INVOCATION_DELEGATION_VALIDITY_SECS`]
+///
+/// # Errors
+///
+/// Returns an error if the delegation could not be signed with `issuer`
+pub fn sign_invocation_delegation(
+    issuer: &KeyPair,
+    source_id: &str,
+    signer: &str,
+) -> anyhow::Result<String> {
+    let mut delegation = Claims::<Cluster>::new(
+        format!("invocations of {source_id}"),
+        issuer.public_key(),
+        source_id.to_string(),
+        vec![signer.to_string()],
+    );
+    delegation.expires = Some(now()? + INVOCATION_DELEGATION_VALIDITY_SECS);
+    delegation
+        .encode(issuer)
+        .context("failed to sign invocation delegation")
+}
+
+/// Verify that `delegation` authorizes `signer` to sign invocations of `source_id` on behalf of
+/// one of `allowed_issuers`, see [`delegate_invocations`]
+fn verify_delegation(
+    delegation: &str,
+    allowed_issuers: &[String],
+    source_id: &str,
+    signer: &str,
+) -> anyhow::Result<()> {
+    let validation = validate_token::<Cluster>(delegation)
+        .context("failed to validate invocation delegation")?;
+    ensure!(
+        validation.signature_valid,
+        "invocation delegation signature is invalid"
+    );
+    ensure!(!validation.expired, "invocation delegation is expired");
+    ensure!(
+        !validation.cannot_use_yet,
+        "invocation delegation cannot be used yet"
+    );
+    let claims =
+        Claims::<Cluster>::decode(delegation).context("failed to decode invocation delegation")?;
+    ensure!(
+        claims.expires.is_some(),
+        "invocation delegation does not expire"
+    );
+    ensure!(
+        allowed_issuers.contains(&claims.issuer),
+        "invocation delegation issuer `{}` is not allowed",
+        claims.issuer
+    );
+    ensure!(
+        claims.subject == source_id,
+        "invocation delegation subject `{}` does not match source `{source_id}`",
+        claims.subject
+    );
+    ensure!(
+        claims
+            .metadata
+            .and_then(|cluster| cluster.valid_signers)
+            .is_some_and(|signers| signers.iter().any(|s| s == signer)),
+        "invocation claims issuer `{signer}` is not delegated"
+    );
+    Ok(())
+}
+
+/// Verify the [`Invocation`] claims `token` received with an invocation of `func` in `instance`
+/// of `target_id` by `source_id`.
+///
+/// The claims must be validly signed by one of `allowed_issuers`, or by a key that `delegation`
+/// issued by one of them authorizes, must not be expired and must match the origin and target of
+/// the invocation. The parameters of the invocation are streamed, so they are verified as they are
+/// read using [`VerifiedParams`].
+///
+/// # Errors
+///
+/// Returns an error if the claims are invalid or do not authorize the invocation
+#[allow(clippy::too_many_arguments)]
+pub fn verify_invocation(
+    token: &str,
+    delegation: Option<&str>,
+    allowed_issuers: &[String],
+    lattice: &str,
+    source_id: &str,
+    target_id: &str,
+    instance: &str,
+    func: &str,
+) -> anyhow::Result<Claims<Invocation>> {
+    let validation =
+        validate_token::<Invocation>(token).context("failed to validate invocation claims")?;
+    ensure!(
+        validation.signature_valid,
+        "invocation claims signature is invalid"
+    );
+    ensure!(!validation.expired, "invocation claims are expired");
+    ensure!(
+        !validation.cannot_use_yet,
+        "invocation claims cannot be used yet"
+    );
+    let claims =
+        Claims::<Invocation>::decode(token).context("failed to decode invocation claims")?;
+    if !allowed_issuers.contains(&claims.issuer) {
+        let delegation = delegation.with_context(|| {
+            format!(
+                "invocation claims issuer `{}` is not allowed",
+                claims.issuer
+            )
+        })?;
+        verify_delegation(delegation, allowed_issuers, source_id, &claims.issuer)?;
+    }
+    ensure!(
+        claims.subject == source_id,
+        "invocation claims subject `{}` does not match source `{source_id}`",
+        claims.subject
+    );
+    let invocation = claims
+        .metadata
+        .as_ref()
+        .context("invocation claims are missing invocation metadata")?;
+    ensure!(
+        invocation.origin_url == invocation_origin_url(lattice, source_id),
+        "invocation claims origin `{}` does not match source `{source_id}`",
+        invocation.origin_url
+    );
+    ensure!(
+        invocation.target_url == invocation_target_url(lattice, target_id, instance, func),
+        "invocation claims target `{}` does not match invocation of `{instance}.{func}` on `{target_id}`",
+        invocation.target_url
+    );
+    Ok(claims)
+}
+
+/// Signs the [`Invocation`] claims of the invocations sent by a component or provider
+#[derive(Clone, Debug)]
+pub struct InvocationSigner {
+    key: Arc<KeyPair>,
+    /// Delegation sent along with invocations, shared by clones of the signer so that renewing
+    /// it applies to all of them
+    delegation: Option<Arc<RwLock<Arc<str>>>>,
+}
+
+impl InvocationSigner {
+    /// Sign invocations with `key`, which receivers must accept as an issuer
+    #[must_use]
+    pub fn new(key: Arc<KeyPair>) -> Self {
+        Self {
+            key,
+            delegation: None,
+        }
+    }
+
+    /// Sign invocations with `key`, authorized by `delegation`, see [`delegate_invocations`]
+    #[must_use]
+    pub fn delegated(key: KeyPair, delegation: String) -> Self {
+        Self {
+            key: Arc::new(key),
+            delegation: Some(Arc::new(RwLock::new(delegation.into()))),
+        }
+    }
+
+    /// Replace the delegation of a delegated signer with a renewed `delegation`, which must
+    /// authorize the key of the signer, see [`sign_invocation_delegation`]
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the signer is not delegated, or if `delegation` does not authorize its key
+    pub fn renew_delegation(&self, delegation: &str) -> anyhow::Result<()> {
+        let current = self
+            .delegation
+            .as_ref()
+            .context("invocation signer is not delegated")?;
+        let claims = Claims::<Cluster>::decode(delegation)
+            .context("failed to decode invocation delegation")?;
+        let key = self.key.public_key();
+        ensure!(
+            claims
+                .metadata
+                .and_then(|cluster| cluster.valid_signers)
+                .is_some_and(|signers| signers.contains(&key)),
+            "invocation delegation does not authorize `{key}`"
+        );
+        *current
+            .write()
+            .map_err(|_| anyhow::anyhow!("invocation delegation lock poisoned"))? =
+            delegation.into();
+        Ok(())
+    }
+
+    /// Sign an invocation, adding the claims and delegation, if any, to `headers`
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the claims could not be signed
+    #[allow(clippy::too_many_arguments)]
+    pub fn sign(
+        &self,
+        headers: &mut async_nats::HeaderMap,
+        lattice: &str,
+        source_id: &str,
+        target_id: &str,
+        instance: &str,
+        func: &str,
+        params: &[u8],
+    ) -> anyhow::Result<()> {
+        let claims = sign_invocation(
+            &self.key, lattice, source_id, target_id, instance, func, params,
+        )?;
+        headers.insert(INVOCATION_CLAIMS_HEADER, claims.as_str());
+        if let Some(delegation) = &self.delegation {
+            let delegation = delegation
+                .read()
+                .map_err(|_| anyhow::anyhow!("invocation delegation lock poisoned"))?;
+            headers.insert(INVOCATION_DELEGATION_HEADER, delegation.as_ref());
+        }
+        Ok(())
+    }
+}
+
+/// Verifies the [`Invocation`] claims of the invocations received by a component or provider.
+///
+/// Claims are bound to the source, target, function and parameters of an invocation and are
+/// short-lived, and the IDs of the claims accepted by a verifier are remembered until they expire
+/// to reject replays. Replays are not detected across verifiers, i.e. an invocation captured on the
+/// lattice may be replayed to another host or provider instance serving the same target within
+/// [`INVOCATION_CLAIMS_VALIDITY_SECS`]. Values of asynchronous parameters, which are sent on
+/// nested streams, are not covered by the hash of the parameters.
+#[derive(Debug)]
+pub struct InvocationVerifier {
+    lattice: Arc<str>,
+    allowed_issuers: Arc<[String]>,
+    /// IDs of accepted claims, mapped to the time they expire at
+    seen: Mutex<HashMap<String, u64>>,
+}
+
+impl InvocationVerifier {
+    /// Verify that invocations in `lattice` are signed by one of `allowed_issuers`
+    #[must_use]
+    pub fn new(lattice: impl Into<Arc<str>>, allowed_issuers: impl Into<Arc<[String]>>) -> Self {
+        Self {
+            lattice: lattice.into(),
+            allowed_issuers: allowed_issuers.into(),
+            seen: Mutex::default(),
+        }
+    }
+
+    /// Remember the ID of accepted claims, returning whether it was accepted before
+    fn is_replay(&self, claims: &Claims<Invocation>) -> anyhow::Result<bool> {
+        let expires = claims.expires.context("invocation claims do not expire")?;
+        let now = now()?;
+        ensure!(
+            expires <= now.saturating_add(INVOCATION_CLAIMS_VALIDITY_SECS),
+            "invocation claims are valid for longer than {INVOCATION_CLAIMS_VALIDITY_SECS} seconds"
+        );
+        let mut seen = self
+            .seen
+            .lock()
+            .map_err(|_| anyhow::anyhow!("invocation replay cache lock poisoned"))?;
+        seen.retain(|_, expires| *expires >= now);
+        Ok(seen.insert(claims.id.clone(), expires).is_some())
+    }
+
+    /// Verify the claims carried in the `headers` of an invocation of `func` in `instance` of
+    /// `target_id`, see [`verify_invocation`], returning `params` wrapped to verify the
+    /// parameters of the invocation as they are read. The source of the invocation is read from
+    /// the `source-id` header.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the headers do not carry claims authorizing the invocation, or if the
+    /// claims were already used
+    pub fn verify<R>(
+        &self,
+        headers: Option<&async_nats::HeaderMap>,
+        target_id: &str,
+        instance: &str,
+        func: &str,
+        params: R,
+    ) -> anyhow::Result<VerifiedParams<R>> {
+        let headers = headers.context("invocation is missing headers")?;
+        let token = headers
+            .get(INVOCATION_CLAIMS_HEADER)
+            .context("invocation is missing invocation claims")?;
+        let source_id = headers
+            .get("source-id")
+            .context("invocation is missing source ID")?;
+        let claims = verify_invocation(
+            token.as_str(),
+            headers
+                .get(INVOCATION_DELEGATION_HEADER)
+                .map(|delegation| delegation.as_str()),
+            &self.allowed_issuers,
+            &self.lattice,
+            source_id.as_str(),
+            target_id,
+            instance,
+            func,
+        )?;
+        ensure!(
+            !self.is_replay(&claims)?,
+            "invocation claims were already used"
+        );
+        let hash = claims
+            .metadata
+            .map(|invocation| invocation.invocation_hash)
+            .unwrap_or_default();
+        let (len, digest) = parse_params_hash(&hash)?;
+        Ok(VerifiedParams {
+            inner: params,
+            pending: Some(PendingParams {
+                buf: vec![0; len],
+                read: 0,
+                digest,
+            }),
+            verified: Bytes::new(),
+            signed: true,
+        })
+    }
+}
+
+/// Parameters of an invocation read, but not yet verified, by [`VerifiedParams`]
+struct PendingParams {
+    buf: Vec<u8>,
+    read: usize,
+    digest: Vec<u8>,
+}
+
+/// The incoming stream of an invocation which parameters are verified against the hash in its
+/// [`Invocation`] claims before any of them are returned, see [`InvocationVerifier::verify`].
+/// Bytes following the signed parameters are rejected.
+pub struct VerifiedParams<R> {
+    inner: R,
+    pending: Option<PendingParams>,
+    verified: Bytes,
+    /// Whether the stream is covered by invocation claims, so it must end with the signed parameters
+    signed: bool,
+}
+
+impl<R> VerifiedParams<R> {
+    /// Wrap the incoming stream of an invocation that is not verified
+    pub fn unverified(inner: R) -> Self {
+        Self {
+            inner,
+            pending: None,
+            verified: Bytes::new(),
+            signed: false,
+        }
+    }
+}
+
+impl<R: AsyncRead + Unpin> AsyncRead for VerifiedParams<R> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        let this = self.get_mut();
+        if let Some(pending) = &mut this.pending {
+            while pending.read < pending.buf.len() {
+                let mut rb = ReadBuf::new(&mut pending.buf[pending.read..]);
+                ready!(Pin::new(&mut this.inner).poll_read(cx, &mut rb))?;
+                let n = rb.filled().len();
+                if n == 0 {
+                    return Poll::Ready(Err(std::io::Error::new(
+                        std::io::ErrorKind::UnexpectedEof,
+                        "invocation parameters are shorter than signed in invocation claims",
+                    )));
+                }
+                pending.read += n;
+            }
+            if Sha256::digest(&pending.buf).as_slice() != pending.digest {
+                return Poll::Ready(Err(std::io::Error::new(
+                    std::io::ErrorKind::PermissionDenied,
+                    "invocation parameters do not match invocation claims",
+                )));
+            }
+            this.verified = Bytes::from(mem::take(&mut pending.buf));
+            this.pending = None;
+        }
+        if !this.verified.is_empty() {
+            let n = this.verified.len().min(buf.remaining());
+            buf.put_slice(&this.verified.split_to(n));
+            return Poll::Ready(Ok(()));
+        }
+        if this.signed {
+            let mut trailing = [0; 1];
+            let mut rb = ReadBuf::new(&mut trailing);
+            ready!(Pin::new(&mut this.inner).poll_read(cx, &mut rb))?;
+            if !rb.filled().is_empty() {
+                return Poll::Ready(Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    "invocation parameters are longer than signed in invocation claims",
+                )));
+            }
+            return Poll::Ready(Ok(()));
+        }
+        Pin::new(&mut this.inner).poll_read(cx, buf)
+    }
+}
+
+impl<R: wrpc_transport::Index<R>> wrpc_transport::Index<Self> for VerifiedParams<R> {
+    fn index(&self, path: &[usize]) -> anyhow::Result<Self> {
+        self.inner.index(path).map(Self::unverified)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use tokio::io::AsyncReadExt as _;
+    use wascap::prelude::KeyPair;
+
+    use super::{
+        delegate_invocations, now, sign_invocation, sign_invocation_delegation, verify_invocation,
+        InvocationSigner, InvocationVerifier, VerifiedParams, INVOCATION_DELEGATION_HEADER,
+        INVOCATION_DELEGATION_VALIDITY_SECS,
+    };
+    use wascap::jwt::{Claims, Cluster};
+
+    const INSTANCE: &str = "wasi:http/incoming-handler@0.2.0";
+
+    /// Ensure that invocation claims are only accepted from allowed issuers for the signed invocation
+    #[test]
+    fn test_invocation_claims() -> Result<()> {
+        let host = KeyPair::new_server();
+        let token = sign_invocation(
+            &host, "default", "source", "target", INSTANCE, "handle", b"params",
+        )?;
+        let allowed = [host.public_key()];
+
+        let claims = verify_invocation(
+            &token, None, &allowed, "default", "source", "target", INSTANCE, "handle",
+        )?;
+        assert_eq!(claims.issuer, host.public_key());
+        assert_eq!(claims.subject, "source");
+
+        let other = [KeyPair::new_server().public_key()];
+        assert!(verify_invocation(
+            &token, None, &other, "default", "source", "target", INSTANCE, "handle",
+        )
+        .is_err());
+        assert!(verify_invocation(
+            &token, None, &allowed, "default", "spoofed", "target", INSTANCE, "handle",
+        )
+        .is_err());
+        assert!(verify_invocation(
+            &token,
+            None,
+            &allowed,
+            "default",
+            "source",
+            "other-target",
+            INSTANCE,
+            "handle",
+        )
+        .is_err());
+        Ok(())
+    }
+
+    /// Ensure that invocation claims signed with a delegated key are only accepted for the
+    /// delegated source
+    #[test]
+    fn test_delegated_invocation_claims() -> Result<()> {
+        let host = KeyPair::new_server();
+        let allowed = [host.public_key()];
+        let (key, delegation) = delegate_invocations(&host, "provider")?;
+        let token = sign_invocation(
+            &key, "default", "provider", "target", INSTANCE, "handle", b"params",
+        )?;
+
+        let claims = verify_invocation(
+            &token,
+            Some(&delegation),
+            &allowed,
+            "default",
+            "provider",
+            "target",
+            INSTANCE,
+            "handle",
+        )?;
+        assert_eq!(claims.issuer, key.public_key());
+        // The delegated key is not an issuer by itself
+        assert!(verify_invocation(
+            &token, None, &allowed, "default", "provider", "target", INSTANCE, "handle",
+        )
+        .is_err());
+
+        // Delegations are bound to their source
+        let spoofed = sign_invocation(
+            &key, "default", "other", "target", INSTANCE, "handle", b"params",
+        )?;
+        assert!(verify_invocation(
+            &spoofed,
+            Some(&delegation),
+            &allowed,
+            "default",
+            "other",
+            "target",
+            INSTANCE,
+            "handle",
+        )
+        .is_err());
+
+        // Delegations must be issued by an allowed issuer
+        let (other_key, other_delegation) =
+            delegate_invocations(&KeyPair::new_server(), "provider")?;
+        let token = sign_invocation(
+            &other_key, "default", "provider", "target", INSTANCE, "handle", b"params",
+        )?;
+        assert!(verify_invocation(
+            &token,
+            Some(&other_delegation),
+            &allowed,
+            "default",
+            "provider",
+            "target",
+            INSTANCE,
+            "handle",
+        )
+        .is_err());
+        Ok(())
+    }
+
+    /// Ensure that delegations expire, and that renewed delegations are sent with invocations
+    #[test]
+    fn test_invocation_delegation_expiry() -> Result<()> {
+        let host = KeyPair::new_server();
+        let allowed = [host.public_key()];
+        let (key, delegation) = delegate_invocations(&host, "provider")?;
+        let claims = Claims::<Cluster>::decode(&delegation)?;
+        let expires = claims.expires.expect("delegation should expire");
+        assert!(expires <= now()? + INVOCATION_DELEGATION_VALIDITY_SECS);
+
+        let token = sign_invocation(
+            &key, "default", "provider", "target", INSTANCE, "handle", b"params",
+        )?;
+        let verify = |delegation: &str| {
+            verify_invocation(
+                &token,
+                Some(delegation),
+                &allowed,
+                "default",
+                "provider",
+                "target",
+                INSTANCE,
+                "handle",
+            )
+        };
+
+        // Expired delegations and delegations without an expiry are rejected
+        let mut expired = Claims::<Cluster>::new(
+            "invocations of provider".into(),
+            host.public_key(),
+            "provider".into(),
+            vec![key.public_key()],
+        );
+        expired.expires = Some(now()? - 1);
+        assert!(verify(&expired.encode(&host)?).is_err());
+        let unexpiring = Claims::<Cluster>::new(
+            "invocations of provider".into(),
+            host.public_key(),
+            "provider".into(),
+            vec![key.public_key()],
+        );
+        assert!(verify(&unexpiring.encode(&host)?).is_err());
+
+        // Signers send the renewed delegation, which must authorize their key
+        let signer = InvocationSigner::delegated(key.clone(), expired.encode(&host)?);
+        let renewed = sign_invocation_delegation(&host, "provider", &key.public_key())?;
+        verify(&renewed)?;
+        signer.clone().renew_delegation(&renewed)?;
+        let headers = signed_headers(&signer, b"params")?;
+        assert_eq!(
+            headers
+                .get(INVOCATION_DELEGATION_HEADER)
+                .map(|delegation| delegation.as_str()),
+            Some(renewed.as_str())
+        );
+        let other =
+            sign_invocation_delegation(&host, "provider", &KeyPair::new_service().public_key())?;
+        assert!(signer.renew_delegation(&other).is_err());
+        assert!(InvocationSigner::new(host.into())
+            .renew_delegation(&renewed)
+            .is_err());
+        Ok(())
+    }
+
+    fn signed_headers(signer: &InvocationSigner, params: &[u8]) -> Result<async_nats::HeaderMap> {
+        let mut headers = async_nats::HeaderMap::new();
+        headers.insert("source-id", "provider");
+        signer.sign(
+            &mut headers,
+            "default",
+            "provider",
+            "target",
+            INSTANCE,
+            "handle",
+            params,
+        )?;
+        Ok(headers)
+    }
+
+    /// Ensure that invocations signed by a provider are verified end to end, including their
+    /// parameters, and cannot be replayed
+    #[tokio::test]
+    async fn test_invocation_round_trip() -> Result<()> {
+        let host = KeyPair::new_server();
+        let (key, delegation) = delegate_invocations(&host, "provider")?;
+        let signer = InvocationSigner::delegated(key, delegation);
+        let verifier = InvocationVerifier::new("default", vec![host.public_key()]);
+
+        let headers = signed_headers(&signer, b"params")?;
+        let mut rx =
+            verifier.verify(Some(&headers), "target", INSTANCE, "handle", &b"params"[..])?;
+        let mut buf = Vec::new();
+        rx.read_to_end(&mut buf).await?;
+        assert_eq!(buf, b"params");
+
+        // Claims can only be used once
+        assert!(verifier
+            .verify(Some(&headers), "target", INSTANCE, "handle", &b"params"[..])
+            .is_err());
+
+        // Parameters must match the signed hash
+        let headers = signed_headers(&signer, b"params")?;
+        let mut rx =
+            verifier.verify(Some(&headers), "target", INSTANCE, "handle", &b"tamper"[..])?;
+        let err = rx
+            .read_to_end(&mut Vec::new())
+            .await
+            .expect_err("tampered parameters should be rejected");
+        assert_eq!(err.kind(), std::io::ErrorKind::PermissionDenied);
+
+        let headers = signed_headers(&signer, b"params")?;
+        let mut rx = verifier.verify(Some(&headers), "target", INSTANCE, "handle", &b"par"[..])?;
+        let err = rx
+            .read_to_end(&mut Vec::new())
+            .await
+            .expect_err("truncated parameters should be rejected");
+        assert_eq!(err.kind(), std::io::ErrorKind::UnexpectedEof);
+
+        // Bytes following the signed parameters are not covered by the claims
+        let headers = signed_headers(&signer, b"params")?;
+        let mut rx = verifier.verify(
+            Some(&headers),
+            "target",
+            INSTANCE,
+            "handle",
+            &b"paramsrest"[..],
+        )?;
+        let err = rx
+            .read_to_end(&mut Vec::new())
+            .await
+            .expect_err("trailing parameters should be rejected");
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+
+        // Unsigned invocations are rejected
+        let mut headers = async_nats::HeaderMap::new();
+        headers.insert("source-id", "provider");
+        assert!(verifier
+            .verify(Some(&headers), "target", INSTANCE, "handle", &b""[..])
+            .is_err());
+
+        // Unverified streams are passed through
+        let mut rx = VerifiedParams::unverified(&b"params"[..]);
+        let mut buf = Vec::new();
+        rx.read_to_end(&mut buf).await?;
+        assert_eq!(buf, b"params");
+        Ok(())
+    }
+}
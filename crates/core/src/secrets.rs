@@ -2,7 +2,7 @@ use std::fmt::{Debug, Formatter, Result};
 
 use serde::{Deserialize, Serialize};
 
-#[derive(Deserialize, Serialize, Clone)]
+#[derive(Deserialize, Serialize, Clone, PartialEq, Eq)]
 // This tagging allows deserializers to know whether the secret is a string or bytes.
 // This is especially necessary for languages where strings and bytes are treated very similarly.
 #[serde(tag = "kind", content = "value")]
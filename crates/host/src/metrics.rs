@@ -1,4 +1,88 @@
-use wasmcloud_tracing::{Counter, Histogram, KeyValue, Meter, Unit};
+use core::fmt;
+use core::time::Duration;
+
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, PoisonError};
+
+use wasmcloud_tracing::{
+    AsyncInstrument, Counter, Histogram, KeyValue, Meter, ObservableGauge, Unit, UpDownCounter,
+};
+
+/// Guard tracking a value added to a gauge, which is subtracted from the gauge again on drop
+#[derive(Debug)]
+#[must_use]
+pub(crate) struct GaugeGuard {
+    counter: UpDownCounter<i64>,
+    attributes: Arc<[KeyValue]>,
+    value: i64,
+}
+
+impl Drop for GaugeGuard {
+    fn drop(&mut self) {
+        self.counter.add(-self.value, &self.attributes);
+    }
+}
+
+/// A series of an observable gauge, with the function reading its current value
+type Series = (Arc<[KeyValue]>, Box<dyn Fn() -> u64 + Send + Sync>);
+
+/// Series observed by an observable gauge
+#[derive(Default)]
+struct Observations {
+    next_id: AtomicU64,
+    series: Mutex<HashMap<u64, Series>>,
+}
+
+impl fmt::Debug for Observations {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Observations").finish_non_exhaustive()
+    }
+}
+
+impl Observations {
+    fn insert(self: &Arc<Self>, series: Series) -> ObservationGuard {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        self.series
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert(id, series);
+        ObservationGuard {
+            observations: Arc::clone(self),
+            id,
+        }
+    }
+
+    fn observe(&self, observer: &dyn AsyncInstrument<u64>) {
+        let series = self.series.lock().unwrap_or_else(PoisonError::into_inner);
+        for (attributes, observe) in series.values() {
+            observer.observe(observe(), attributes);
+        }
+    }
+}
+
+/// Guard keeping a series observed by an observable gauge, the series is no longer reported
+/// once the guard is dropped
+#[derive(Debug)]
+#[must_use]
+pub(crate) struct ObservationGuard {
+    observations: Arc<Observations>,
+    id: u64,
+}
+
+impl Drop for ObservationGuard {
+    fn drop(&mut self) {
+        self.observations
+            .series
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .remove(&self.id);
+    }
+}
+
+fn nanos(elapsed: Duration) -> u64 {
+    u64::try_from(elapsed.as_nanos()).unwrap_or(u64::MAX)
+}
 
 /// `HostMetrics` encapsulates the set of metrics emitted by the wasmcloud host
 #[derive(Clone, Debug)]
@@ -10,6 +94,41 @@ pub struct HostMetrics {
     pub component_invocations: Counter<u64>,
     /// The count of the number of times an component invocation resulted in an error.
     pub component_errors: Counter<u64>,
+    /// The count of the number of times a secret was served from the secrets cache.
+    pub secrets_cache_hits: Counter<u64>,
+    /// The count of the number of times a secret had to be fetched from its secrets backend
+    /// because it was not in the secrets cache or had expired.
+    pub secrets_cache_misses: Counter<u64>,
+    /// The number of instances of a component currently handling an invocation.
+    pub component_active_instances: UpDownCounter<i64>,
+    /// The maximum number of instances of a component that can run at once.
+    pub component_max_instances: UpDownCounter<i64>,
+    /// The number of invocations of a component waiting for an instance to become available.
+    pub component_queued_invocations: UpDownCounter<i64>,
+    /// The largest linear memory size in bytes reached by any instance of a running component,
+    /// observed when metrics are collected.
+    pub component_memory_high_water: ObservableGauge<u64>,
+    /// The amount of fuel consumed by a component.
+    pub component_fuel_consumed: Counter<u64>,
+    /// The count of the number of times a component invocation exhausted its fuel budget.
+    pub component_fuel_exhausted: Counter<u64>,
+    /// Represents the time it took to compile a component in nanoseconds.
+    pub component_compile_duration_ns: Histogram<u64>,
+    /// Represents the time it took to instantiate a component in nanoseconds.
+    pub component_instantiation_duration_ns: Histogram<u64>,
+    /// Represents the time it took to fetch a component or provider artifact in nanoseconds.
+    pub oci_fetch_duration_ns: Histogram<u64>,
+    /// The number of bytes of component and provider artifacts fetched.
+    pub oci_fetch_bytes: Counter<u64>,
+    /// The count of the number of times a provider was started again on this host.
+    pub provider_restarts: Counter<u64>,
+    /// The count of the number of provider health checks, by result.
+    pub provider_health_checks: Counter<u64>,
+
+    /// Providers started on this host, used to tell restarts from first starts
+    started_providers: Arc<Mutex<HashSet<String>>>,
+    /// Memory high-water marks observed by `component_memory_high_water`
+    memory_observations: Arc<Observations>,
 
     /// The host's ID.
     // TODO this is actually configured as an InstrumentationScope attribute on the global meter,
@@ -42,10 +161,106 @@ impl HostMetrics {
             .with_description("Number of component errors")
             .init();
 
+        let secrets_cache_hit_count = meter
+            .u64_counter("wasmcloud_host.secrets.cache.hits")
+            .with_description("Number of secrets served from the secrets cache")
+            .init();
+
+        let secrets_cache_miss_count = meter
+            .u64_counter("wasmcloud_host.secrets.cache.misses")
+            .with_description("Number of secrets fetched from a secrets backend on a cache miss")
+            .init();
+
+        let component_active_instances = meter
+            .i64_up_down_counter("wasmcloud_host.component.active_instances")
+            .with_description("Number of component instances handling an invocation")
+            .init();
+
+        let component_max_instances = meter
+            .i64_up_down_counter("wasmcloud_host.component.max_instances")
+            .with_description("Maximum number of component instances that can run at once")
+            .init();
+
+        let component_queued_invocations = meter
+            .i64_up_down_counter("wasmcloud_host.component.queued_invocations")
+            .with_description("Number of component invocations waiting for an instance")
+            .init();
+
+        let memory_observations = Arc::<Observations>::default();
+        let component_memory_high_water = meter
+            .u64_observable_gauge("wasmcloud_host.component.memory.high_water")
+            .with_description("Largest linear memory size of running component instances")
+            .with_unit(Unit::new("bytes"))
+            .with_callback({
+                let observations = Arc::clone(&memory_observations);
+                move |observer| observations.observe(observer)
+            })
+            .init();
+
+        let component_fuel_consumed = meter
+            .u64_counter("wasmcloud_host.component.fuel.consumed")
+            .with_description("Amount of fuel consumed by components")
+            .init();
+
+        let component_fuel_exhausted = meter
+            .u64_counter("wasmcloud_host.component.fuel.exhausted")
+            .with_description("Number of component invocations that exhausted their fuel budget")
+            .init();
+
+        let component_compile_duration_ns = meter
+            .u64_histogram("wasmcloud_host.component.compile.duration")
+            .with_description("Duration in nanoseconds each component compilation took")
+            .with_unit(Unit::new("nanoseconds"))
+            .init();
+
+        let component_instantiation_duration_ns = meter
+            .u64_histogram("wasmcloud_host.component.instantiation.duration")
+            .with_description("Duration in nanoseconds each component instantiation took")
+            .with_unit(Unit::new("nanoseconds"))
+            .init();
+
+        let oci_fetch_duration_ns = meter
+            .u64_histogram("wasmcloud_host.oci.fetch.duration")
+            .with_description("Duration in nanoseconds each artifact fetch took")
+            .with_unit(Unit::new("nanoseconds"))
+            .init();
+
+        let oci_fetch_bytes = meter
+            .u64_counter("wasmcloud_host.oci.fetch.bytes")
+            .with_description("Number of bytes of fetched artifacts")
+            .with_unit(Unit::new("bytes"))
+            .init();
+
+        let provider_restarts = meter
+            .u64_counter("wasmcloud_host.provider.restarts")
+            .with_description("Number of times a provider was started again")
+            .init();
+
+        let provider_health_checks = meter
+            .u64_counter("wasmcloud_host.provider.health_checks")
+            .with_description("Number of provider health checks")
+            .init();
+
         Self {
             handle_rpc_message_duration_ns: wasmcloud_host_handle_rpc_message_duration_ns,
             component_invocations: component_invocation_count,
             component_errors: component_error_count,
+            secrets_cache_hits: secrets_cache_hit_count,
+            secrets_cache_misses: secrets_cache_miss_count,
+            component_active_instances,
+            component_max_instances,
+            component_queued_invocations,
+            component_memory_high_water,
+            component_fuel_consumed,
+            component_fuel_exhausted,
+            component_compile_duration_ns,
+            component_instantiation_duration_ns,
+            oci_fetch_duration_ns,
+            oci_fetch_bytes,
+            provider_restarts,
+            provider_health_checks,
+            started_providers: Arc::default(),
+            memory_observations,
             host_id,
             lattice_id,
         }
@@ -65,4 +280,285 @@ impl HostMetrics {
             self.component_errors.add(1, attributes);
         }
     }
+
+    /// Record a lookup of a secret from `backend` in the secrets cache, and whether it was a hit.
+    pub(crate) fn record_secrets_cache_lookup(&self, backend: &str, hit: bool) {
+        let attributes = [
+            KeyValue::new("host", self.host_id.clone()),
+            KeyValue::new("lattice", self.lattice_id.clone()),
+            KeyValue::new("backend", backend.to_string()),
+        ];
+        if hit {
+            self.secrets_cache_hits.add(1, &attributes);
+        } else {
+            self.secrets_cache_misses.add(1, &attributes);
+        }
+    }
+
+    /// Attributes identifying a component in the recorded metrics
+    pub(crate) fn component_attributes(
+        &self,
+        component_id: &str,
+        component_ref: &str,
+    ) -> Vec<KeyValue> {
+        vec![
+            KeyValue::new("host", self.host_id.clone()),
+            KeyValue::new("lattice", self.lattice_id.clone()),
+            KeyValue::new("component.id", component_id.to_string()),
+            KeyValue::new("component.ref", component_ref.to_string()),
+        ]
+    }
+
+    /// Attributes identifying a provider in the recorded metrics
+    pub(crate) fn provider_attributes(
+        &self,
+        provider_id: &str,
+        provider_ref: &str,
+    ) -> Vec<KeyValue> {
+        vec![
+            KeyValue::new("host", self.host_id.clone()),
+            KeyValue::new("lattice", self.lattice_id.clone()),
+            KeyValue::new("provider.id", provider_id.to_string()),
+            KeyValue::new("provider.ref", provider_ref.to_string()),
+        ]
+    }
+
+    fn track(counter: &UpDownCounter<i64>, attributes: Arc<[KeyValue]>, value: i64) -> GaugeGuard {
+        counter.add(value, &attributes);
+        GaugeGuard {
+            counter: counter.clone(),
+            attributes,
+            value,
+        }
+    }
+
+    /// Track the maximum number of instances of a component for as long as the returned guard is held.
+    pub(crate) fn track_max_instances(
+        &self,
+        attributes: Arc<[KeyValue]>,
+        max_instances: usize,
+    ) -> GaugeGuard {
+        Self::track(
+            &self.component_max_instances,
+            attributes,
+            i64::try_from(max_instances).unwrap_or(i64::MAX),
+        )
+    }
+
+    /// Track an invocation waiting for a component instance for as long as the returned guard is held.
+    pub(crate) fn track_queued_invocation(&self, attributes: Arc<[KeyValue]>) -> GaugeGuard {
+        Self::track(&self.component_queued_invocations, attributes, 1)
+    }
+
+    /// Track a component instance handling an invocation for as long as the returned guard is held.
+    pub(crate) fn track_active_instance(&self, attributes: Arc<[KeyValue]>) -> GaugeGuard {
+        Self::track(&self.component_active_instances, attributes, 1)
+    }
+
+    /// Observe the linear memory high-water mark of a component in bytes, as returned by
+    /// `high_water`, for as long as the returned guard is held.
+    pub(crate) fn observe_component_memory_high_water(
+        &self,
+        attributes: Arc<[KeyValue]>,
+        high_water: impl Fn() -> u64 + Send + Sync + 'static,
+    ) -> ObservationGuard {
+        self.memory_observations
+            .insert((attributes, Box::new(high_water)))
+    }
+
+    /// Record the amount of fuel consumed by a component since it was last recorded.
+    pub(crate) fn record_component_fuel_consumed(&self, attributes: &[KeyValue], fuel: u64) {
+        if fuel == 0 {
+            return;
+        }
+        self.component_fuel_consumed.add(fuel, attributes);
+    }
+
+    /// Record a component invocation that exhausted its fuel budget.
+    pub(crate) fn record_component_fuel_exhausted(&self, attributes: &[KeyValue]) {
+        self.component_fuel_exhausted.add(1, attributes);
+    }
+
+    /// Record the time it took to compile a component.
+    pub(crate) fn record_component_compile(&self, elapsed: Duration, attributes: &[KeyValue]) {
+        self.component_compile_duration_ns
+            .record(nanos(elapsed), attributes);
+    }
+
+    /// Record the time it took to instantiate a component.
+    pub(crate) fn record_component_instantiation(
+        &self,
+        elapsed: Duration,
+        attributes: &[KeyValue],
+    ) {
+        self.component_instantiation_duration_ns
+            .record(nanos(elapsed), attributes);
+    }
+
+    /// Record the time it took to fetch a component or provider artifact and its size in bytes.
+    pub(crate) fn record_oci_fetch(&self, elapsed: Duration, bytes: u64, attributes: &[KeyValue]) {
+        self.oci_fetch_duration_ns
+            .record(nanos(elapsed), attributes);
+        self.oci_fetch_bytes.add(bytes, attributes);
+    }
+
+    /// Record the start of a provider, counting it as a restart if `provider_id` was started on
+    /// this host before.
+    pub(crate) fn record_provider_start(&self, provider_id: &str, attributes: &[KeyValue]) {
+        let restarted = !self
+            .started_providers
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert(provider_id.to_string());
+        if restarted {
+            self.provider_restarts.add(1, attributes);
+        }
+    }
+
+    /// Record the result of a provider health check, which is one of `healthy`, `unhealthy` or
+    /// `error` if the provider did not respond with a valid health check response.
+    pub(crate) fn record_provider_health_check(
+        &self,
+        attributes: &[KeyValue],
+        result: &'static str,
+    ) {
+        let attributes: Vec<_> = attributes
+            .iter()
+            .cloned()
+            .chain([KeyValue::new("result", result)])
+            .collect();
+        self.provider_health_checks.add(1, &attributes);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use opentelemetry::metrics::MeterProvider as _;
+    use opentelemetry_sdk::metrics::SdkMeterProvider;
+    use wasmcloud_tracing::{KeyValue, PrometheusReader};
+
+    use super::HostMetrics;
+
+    const LABELS: &str =
+        r#"{component_id="component",component_ref="ref",host="host",lattice="lattice"}"#;
+
+    /// Create host metrics recorded by a meter provider, which must be kept alive for metrics to be
+    /// collected by the returned reader
+    fn metrics() -> (HostMetrics, PrometheusReader, SdkMeterProvider) {
+        let reader = PrometheusReader::new();
+        let provider = SdkMeterProvider::builder()
+            .with_reader(reader.clone())
+            .build();
+        let metrics = HostMetrics::new(&provider.meter("test"), "host".into(), "lattice".into());
+        (metrics, reader, provider)
+    }
+
+    fn assert_series(encoded: &str, name: &str, value: &str) {
+        let series = format!("{name}{LABELS} {value}");
+        assert!(
+            encoded.lines().any(|line| line == series),
+            "`{series}` not found in:\n{encoded}"
+        );
+    }
+
+    #[test]
+    fn invocations_are_exported() -> anyhow::Result<()> {
+        let (metrics, reader, _provider) = metrics();
+        let attributes = metrics.component_attributes("component", "ref");
+        metrics.record_component_invocation(1_000, &attributes, false);
+        metrics.record_component_invocation(2_000, &attributes, true);
+        metrics.record_component_fuel_consumed(&attributes, 0);
+        metrics.record_component_fuel_consumed(&attributes, 42);
+        metrics.record_component_fuel_exhausted(&attributes);
+
+        let encoded = reader.encode()?;
+        assert!(encoded.contains("# TYPE wasmcloud_host_component_invocations_total counter"));
+        assert_series(&encoded, "wasmcloud_host_component_invocations_total", "2");
+        assert_series(
+            &encoded,
+            "wasmcloud_host_component_invocation_errors_total",
+            "1",
+        );
+        assert_series(
+            &encoded,
+            "wasmcloud_host_component_fuel_consumed_total",
+            "42",
+        );
+        assert_series(
+            &encoded,
+            "wasmcloud_host_component_fuel_exhausted_total",
+            "1",
+        );
+        assert!(encoded
+            .contains("# TYPE wasmcloud_host_handle_rpc_message_duration_nanoseconds histogram"));
+        assert_series(
+            &encoded,
+            "wasmcloud_host_handle_rpc_message_duration_nanoseconds_sum",
+            "3000",
+        );
+        assert_series(
+            &encoded,
+            "wasmcloud_host_handle_rpc_message_duration_nanoseconds_count",
+            "2",
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn gauges_follow_guards() -> anyhow::Result<()> {
+        let (metrics, reader, _provider) = metrics();
+        let attributes: Arc<[KeyValue]> = metrics.component_attributes("component", "ref").into();
+        let max_instances = metrics.track_max_instances(Arc::clone(&attributes), 4);
+        let active = metrics.track_active_instance(Arc::clone(&attributes));
+        let queued = metrics.track_queued_invocation(Arc::clone(&attributes));
+        let memory =
+            metrics.observe_component_memory_high_water(Arc::clone(&attributes), || 65_536);
+
+        let encoded = reader.encode()?;
+        assert_series(&encoded, "wasmcloud_host_component_max_instances", "4");
+        assert_series(&encoded, "wasmcloud_host_component_active_instances", "1");
+        assert_series(&encoded, "wasmcloud_host_component_queued_invocations", "1");
+        assert!(encoded.contains("# TYPE wasmcloud_host_component_memory_high_water_bytes gauge"));
+        assert_series(
+            &encoded,
+            "wasmcloud_host_component_memory_high_water_bytes",
+            "65536",
+        );
+
+        drop((max_instances, active, queued, memory));
+        let encoded = reader.encode()?;
+        assert_series(&encoded, "wasmcloud_host_component_max_instances", "0");
+        assert_series(&encoded, "wasmcloud_host_component_active_instances", "0");
+        assert_series(&encoded, "wasmcloud_host_component_queued_invocations", "0");
+        // Components that are no longer running are no longer observed
+        assert!(!encoded.contains("wasmcloud_host_component_memory_high_water_bytes{"));
+        Ok(())
+    }
+
+    #[test]
+    fn provider_restarts_are_counted() -> anyhow::Result<()> {
+        let (metrics, reader, _provider) = metrics();
+        let attributes = metrics.provider_attributes("provider", "ref");
+        metrics.record_provider_start("provider", &attributes);
+        metrics.record_provider_health_check(&attributes, "healthy");
+        let encoded = reader.encode()?;
+        assert!(!encoded.contains("wasmcloud_host_provider_restarts_total{"));
+
+        metrics.record_provider_start("provider", &attributes);
+        metrics.record_provider_health_check(&attributes, "unhealthy");
+        let encoded = reader.encode()?;
+        let labels = r#"host="host",lattice="lattice",provider_id="provider",provider_ref="ref""#;
+        assert!(encoded
+            .lines()
+            .any(|line| line == format!("wasmcloud_host_provider_restarts_total{{{labels}}} 1")));
+        for result in ["healthy", "unhealthy"] {
+            assert!(encoded.lines().any(|line| line
+                == format!(
+                    "wasmcloud_host_provider_health_checks_total{{{labels},result=\"{result}\"}} 1"
+                )));
+        }
+        Ok(())
+    }
 }
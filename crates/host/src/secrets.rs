@@ -1,17 +1,224 @@
 //! Module with structs for use in managing and accessing secrets in a wasmCloud lattice
+use std::collections::hash_map::Entry;
 use std::collections::HashMap;
 use std::sync::Arc;
+use std::time::Duration;
 
 use anyhow::{bail, ensure, Context as _};
 use async_nats::{jetstream::kv::Store, Client};
 use futures::stream;
 use futures::stream::{StreamExt, TryStreamExt};
+use nkeys::XKey;
 use secrecy::Secret;
-use tokio::sync::RwLock;
-use tracing::instrument;
+use tokio::sync::{broadcast, Mutex, RwLock};
+use tokio::task::JoinSet;
+use tokio::time::Instant;
+use tracing::{debug, instrument, warn};
 use wasmcloud_runtime::capability::secrets::store::SecretValue;
-use wasmcloud_secrets_client::Client as WasmcloudSecretsClient;
-use wasmcloud_secrets_types::{Secret as WasmcloudSecret, SecretConfig};
+use wasmcloud_secrets_client::{Client as WasmcloudSecretsClient, SecretClientError};
+use wasmcloud_secrets_types::{
+    Secret as WasmcloudSecret, SecretConfig, SecretUpdate, SECRET_PREFIX,
+};
+
+use crate::metrics::HostMetrics;
+
+/// Capacity of the channel used to broadcast [`BackendSecretUpdate`]s to subscribers
+const SECRET_UPDATES_CAPACITY: usize = 64;
+
+/// A [`SecretUpdate`] received from a secrets backend
+#[derive(Clone, Debug)]
+pub struct BackendSecretUpdate {
+    /// The name of the backend the secret was written to, e.g. nats-kv or vault
+    pub backend: String,
+    /// The update published by the backend
+    pub update: SecretUpdate,
+}
+
+/// The secret references an entity fetched its secrets with, and the identity it fetched them
+/// with, used to fetch them again when they are rotated in their secrets backend.
+#[derive(Clone, Debug, Default)]
+pub struct SecretReferences {
+    /// The names of the secret references in the config store
+    pub names: Vec<String>,
+    /// The JWT of the entity the secrets were fetched for
+    pub entity_jwt: Option<String>,
+    /// The name of the application the entity is a part of, if any
+    pub application: Option<String>,
+}
+
+impl SecretReferences {
+    /// Create the secret references of an entity configured with `config_names`, which may
+    /// include the names of both configuration and secret references.
+    pub fn new(
+        config_names: &[String],
+        entity_jwt: Option<&String>,
+        application: Option<&String>,
+    ) -> Self {
+        Self {
+            names: config_names
+                .iter()
+                .filter(|name| name.starts_with(SECRET_PREFIX))
+                .cloned()
+                .collect(),
+            entity_jwt: entity_jwt.cloned(),
+            application: application.cloned(),
+        }
+    }
+}
+
+/// The key of a secret in the [`SecretsCache`].
+///
+/// Secrets are cached per entity and application they were fetched for, as backends authorize
+/// every request using them: a secret fetched for one entity must never be served to another.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+struct SecretsCacheKey {
+    backend: String,
+    key: String,
+    version: Option<String>,
+    field: Option<String>,
+    entity_jwt: String,
+    application: Option<String>,
+}
+
+impl SecretsCacheKey {
+    fn new(config: &SecretConfig, entity_jwt: &str, application: Option<&String>) -> Self {
+        Self {
+            backend: config.backend.clone(),
+            key: config.key.clone(),
+            version: config.version.clone(),
+            field: config.field.clone(),
+            entity_jwt: entity_jwt.to_string(),
+            application: application.cloned(),
+        }
+    }
+}
+
+/// A secret in the [`SecretsCache`], sealed with the cache's own xkey
+#[derive(Debug)]
+struct SecretsCacheEntry {
+    sealed: Vec<u8>,
+    fetched_at: Instant,
+}
+
+/// The result of looking up a secret in the [`SecretsCache`]
+enum CachedSecret {
+    /// The secret was fetched less than the TTL of the cache ago
+    Fresh(WasmcloudSecret),
+    /// The secret has expired, but may still be used if its backend is unavailable
+    Stale(WasmcloudSecret),
+}
+
+/// An in-memory cache of secrets fetched from secrets backends.
+///
+/// Secrets are kept encrypted with an xkey generated for the lifetime of the cache, and are
+/// fetched again once they are older than the configured TTL. If fetching an expired secret
+/// fails because its backend is unavailable, e.g. during an outage, the expired secret is used
+/// instead for at most the configured maximum staleness, after which it is evicted. Any other
+/// error, such as the entity no longer being authorized, is returned. Secrets referring to the
+/// latest version of a key are evicted when the backend publishes an update for it.
+#[derive(Debug)]
+pub struct SecretsCache {
+    ttl: Duration,
+    max_stale: Duration,
+    xkey: XKey,
+    entries: RwLock<HashMap<SecretsCacheKey, SecretsCacheEntry>>,
+    metrics: HostMetrics,
+}
+
+impl SecretsCache {
+    /// Create a new secrets cache, which serves secrets fetched less than `ttl` ago, falls back
+    /// to secrets expired less than `max_stale` ago if their backend is unavailable and records
+    /// hits and misses with `metrics`
+    #[must_use]
+    pub fn new(ttl: Duration, max_stale: Duration, metrics: HostMetrics) -> Self {
+        Self {
+            ttl,
+            max_stale,
+            xkey: XKey::new(),
+            entries: RwLock::default(),
+            metrics,
+        }
+    }
+
+    /// Returns whether a secret fetched at `fetched_at` is too old to be used at all
+    fn is_evictable(&self, fetched_at: Instant) -> bool {
+        fetched_at.elapsed() >= self.ttl.saturating_add(self.max_stale)
+    }
+
+    /// Look up a secret, recording whether it was a hit
+    async fn get(&self, key: &SecretsCacheKey) -> Option<CachedSecret> {
+        let cached = self.entries.read().await.get(key).and_then(|entry| {
+            if self.is_evictable(entry.fetched_at) {
+                return None;
+            }
+            let secret = self
+                .xkey
+                .open(&entry.sealed, &self.xkey)
+                .context("failed to open cached secret")
+                .and_then(|secret| {
+                    serde_json::from_slice(&secret).context("failed to decode cached secret")
+                });
+            match secret {
+                Ok(secret) if entry.fetched_at.elapsed() < self.ttl => {
+                    Some(CachedSecret::Fresh(secret))
+                }
+                Ok(secret) => Some(CachedSecret::Stale(secret)),
+                Err(err) => {
+                    warn!(
+                        ?err,
+                        backend = key.backend,
+                        key = key.key,
+                        "ignoring cached secret"
+                    );
+                    None
+                }
+            }
+        });
+        self.metrics.record_secrets_cache_lookup(
+            &key.backend,
+            matches!(cached, Some(CachedSecret::Fresh(..))),
+        );
+        cached
+    }
+
+    /// Cache a secret fetched from its backend
+    async fn insert(&self, key: SecretsCacheKey, secret: &WasmcloudSecret) {
+        let sealed = serde_json::to_vec(secret)
+            .context("failed to encode secret")
+            .and_then(|secret| {
+                self.xkey
+                    .seal(&secret, &self.xkey)
+                    .context("failed to seal secret")
+            });
+        match sealed {
+            Ok(sealed) => {
+                let mut entries = self.entries.write().await;
+                // Evict secrets which are too old to be used, e.g. those of stopped entities
+                entries.retain(|_, entry| !self.is_evictable(entry.fetched_at));
+                entries.insert(
+                    key,
+                    SecretsCacheEntry {
+                        sealed,
+                        fetched_at: Instant::now(),
+                    },
+                );
+            }
+            Err(err) => warn!(
+                ?err,
+                backend = key.backend,
+                key = key.key,
+                "failed to cache secret"
+            ),
+        }
+    }
+
+    /// Evict all secrets referring to the latest version of `key` in `backend`
+    async fn invalidate(&self, backend: &str, key: &str) {
+        self.entries.write().await.retain(|cached, _| {
+            cached.backend != backend || cached.key != key || cached.version.is_some()
+        });
+    }
+}
 
 #[derive(Debug)]
 /// A manager for fetching secrets from a secret store, caching secrets clients for efficiency.
@@ -22,6 +229,12 @@ pub struct Manager {
     nats_client: Client,
     /// A map of backend names, e.g. nats-kv or vault, to secrets clients, used to cache clients for efficiency.
     backend_clients: Arc<RwLock<HashMap<String, Arc<WasmcloudSecretsClient>>>>,
+    /// Sender used to broadcast secret updates received from the backends of cached clients
+    updates: broadcast::Sender<BackendSecretUpdate>,
+    /// Tasks forwarding secret updates from each backend to [`Self::updates`]
+    update_tasks: Mutex<JoinSet<()>>,
+    /// Cache of fetched secrets, if enabled
+    cache: Option<Arc<SecretsCache>>,
 }
 
 impl Manager {
@@ -40,9 +253,66 @@ impl Manager {
             secret_store_topic: secret_store_topic.cloned(),
             nats_client: nats_client.clone(),
             backend_clients: Arc::new(RwLock::new(HashMap::new())),
+            updates: broadcast::channel(SECRET_UPDATES_CAPACITY).0,
+            update_tasks: Mutex::default(),
+            cache: None,
+        }
+    }
+
+    /// Cache the secrets fetched by this manager in `cache`
+    #[must_use]
+    pub fn with_cache(self, cache: SecretsCache) -> Self {
+        Self {
+            cache: Some(Arc::new(cache)),
+            ..self
         }
     }
 
+    /// Subscribe to the secret updates published by secrets backends.
+    ///
+    /// Only backends which secrets were fetched from by this manager are watched for updates, which
+    /// covers every backend referenced by a running component or provider.
+    pub fn subscribe_updates(&self) -> broadcast::Receiver<BackendSecretUpdate> {
+        self.updates.subscribe()
+    }
+
+    /// Returns whether any of the secret references named by `secret_names` is affected by `update`,
+    /// i.e. refers to the latest version of the updated secret.
+    ///
+    /// Secret references that cannot be read from the config store are ignored, as they would
+    /// have failed to be fetched in the first place.
+    pub async fn is_affected_by(
+        &self,
+        secret_names: &[String],
+        update: &BackendSecretUpdate,
+    ) -> bool {
+        stream::iter(secret_names)
+            .any(|secret_name| async move {
+                match self.config_store.get(secret_name).await {
+                    Ok(Some(secret)) => match serde_json::from_slice::<SecretConfig>(&secret) {
+                        Ok(SecretConfig {
+                            backend,
+                            key,
+                            version: None,
+                            ..
+                        }) => backend == update.backend && key == update.update.key,
+                        // Secrets pinned to a version are not affected by newer versions
+                        Ok(_) => false,
+                        Err(err) => {
+                            warn!(?err, secret_name, "failed to deserialize secret reference");
+                            false
+                        }
+                    },
+                    Ok(None) => false,
+                    Err(err) => {
+                        warn!(?err, secret_name, "failed to read secret reference");
+                        false
+                    }
+                }
+            })
+            .await
+    }
+
     /// Get the secrets client for the provided backend, creating a new client if one does not already exist.
     ///
     /// Returns an error if the secret store topic is not configured, or if the client could not be created.
@@ -73,13 +343,58 @@ impl Manager {
             }
         };
 
-        self.backend_clients
+        // Subscribe before taking the write lock, the subscription is dropped if another client
+        // was cached for the backend in the meantime
+        let updates = client.subscribe_updates().await;
+        match self
+            .backend_clients
             .write()
             .await
-            .insert(backend.to_string(), client.clone());
+            .entry(backend.to_string())
+        {
+            Entry::Occupied(entry) => return Ok(entry.get().clone()),
+            Entry::Vacant(entry) => entry.insert(client.clone()),
+        };
+        match updates {
+            Ok(updates) => self.forward_updates(backend, updates).await,
+            Err(err) => warn!(
+                ?err,
+                backend,
+                "failed to subscribe to secret updates, rotated secrets will not be updated"
+            ),
+        }
         Ok(client)
     }
 
+    /// Forward the secret updates received on `updates` from `backend` to subscribers of
+    /// [`Self::subscribe_updates`]
+    async fn forward_updates(&self, backend: &str, mut updates: async_nats::Subscriber) {
+        let backend = backend.to_string();
+        let tx = self.updates.clone();
+        let cache = self.cache.clone();
+        self.update_tasks.lock().await.spawn(async move {
+            while let Some(msg) = updates.next().await {
+                let update = match serde_json::from_slice::<SecretUpdate>(&msg.payload) {
+                    Ok(update) => update,
+                    Err(err) => {
+                        warn!(?err, backend, "received invalid secret update");
+                        continue;
+                    }
+                };
+                debug!(backend, key = update.key, version = ?update.version, "received secret update");
+                // Evict the secret before notifying subscribers, which fetch it again
+                if let Some(cache) = &cache {
+                    cache.invalidate(&backend, &update.key).await;
+                }
+                // Sending only fails if there are no subscribers, in which case there is nothing to update
+                let _ = tx.send(BackendSecretUpdate {
+                    backend: backend.clone(),
+                    update,
+                });
+            }
+        });
+    }
+
     /// Fetches secret references from the CONFIGDATA bucket by name and then fetches the actual secrets
     /// from the configured secret store. Any error returned from this function should result in a failure
     /// to start a component, start a provider, or establish a link as a missing secret is a critical
@@ -130,16 +445,39 @@ impl Manager {
             })
             // Retrieve the actual secret from the secrets backend
             .and_then(|secret_config| async move {
-                let secrets_client = self
-                    .get_or_create_secrets_client(&secret_config.backend)
-                    .await?;
                 let secret_name = secret_config.name.clone();
-                let request = secret_config.try_into_request(entity_jwt, host_jwt, application).context("failed to create secret request")?;
-                secrets_client
-                    .get(request, nkeys::XKey::new())
-                    .await
-                    .map(|secret| (secret_name, secret))
-                    .map_err(|e| anyhow::anyhow!(e))
+                let cache_key = SecretsCacheKey::new(&secret_config, entity_jwt, application);
+                let stale = match &self.cache {
+                    Some(cache) => match cache.get(&cache_key).await {
+                        Some(CachedSecret::Fresh(secret)) => return Ok((secret_name, secret)),
+                        Some(CachedSecret::Stale(secret)) => Some(secret),
+                        None => None,
+                    },
+                    None => None,
+                };
+                let secret: anyhow::Result<_> = async {
+                    let secrets_client = self
+                        .get_or_create_secrets_client(&secret_config.backend)
+                        .await?;
+                    let request = secret_config.try_into_request(entity_jwt, host_jwt, application).context("failed to create secret request")?;
+                    Ok(secrets_client
+                        .get(request, nkeys::XKey::new())
+                        .await?)
+                }
+                .await;
+                match (secret, stale) {
+                    (Ok(secret), _) => {
+                        if let Some(cache) = &self.cache {
+                            cache.insert(cache_key, &secret).await;
+                        }
+                        Ok((secret_name, secret))
+                    }
+                    (Err(err), Some(stale)) if is_unavailable(&err) => {
+                        warn!(?err, secret_name, "secrets backend unavailable, using expired cached secret");
+                        Ok((secret_name, stale))
+                    }
+                    (Err(err), _) => Err(err),
+                }
             })
             // Build the map of secrets depending on if the secret is a string or bytes
             .try_fold(HashMap::new(), |mut secrets, (secret_name, secret_result)| async move {
@@ -172,3 +510,117 @@ impl Manager {
         Ok(secrets)
     }
 }
+
+/// Returns whether `err` was caused by a secrets backend being unavailable
+fn is_unavailable(err: &anyhow::Error) -> bool {
+    err.downcast_ref::<SecretClientError>()
+        .is_some_and(SecretClientError::is_unavailable)
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use wasmcloud_secrets_client::SecretClientError;
+    use wasmcloud_secrets_types::{GetSecretError, Secret, SecretConfig};
+    use wasmcloud_tracing::global;
+
+    use super::{is_unavailable, CachedSecret, SecretsCache, SecretsCacheKey};
+    use crate::HostMetrics;
+
+    fn cache(ttl: Duration) -> SecretsCache {
+        cache_with_max_stale(ttl, Duration::from_secs(60))
+    }
+
+    fn cache_with_max_stale(ttl: Duration, max_stale: Duration) -> SecretsCache {
+        let metrics = HostMetrics::new(&global::meter("test"), "host".into(), "lattice".into());
+        SecretsCache::new(ttl, max_stale, metrics)
+    }
+
+    fn cache_key(version: Option<&str>) -> SecretsCacheKey {
+        let config = SecretConfig::new(
+            "secret".into(),
+            "nats-kv".into(),
+            "key".into(),
+            None,
+            version.map(String::from),
+            Default::default(),
+        );
+        SecretsCacheKey::new(&config, "jwt", None)
+    }
+
+    fn secret(value: &str) -> Secret {
+        Secret {
+            version: "1".into(),
+            string_secret: Some(value.into()),
+            binary_secret: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn caches_secrets_until_expired() {
+        let fresh = cache(Duration::from_secs(60));
+        assert!(fresh.get(&cache_key(None)).await.is_none());
+        fresh.insert(cache_key(None), &secret("value")).await;
+        assert!(matches!(
+            fresh.get(&cache_key(None)).await,
+            Some(CachedSecret::Fresh(Secret { string_secret: Some(value), .. })) if value == "value"
+        ));
+        // Secrets are cached per entity
+        let other_entity = SecretsCacheKey {
+            entity_jwt: "other".into(),
+            ..cache_key(None)
+        };
+        assert!(fresh.get(&other_entity).await.is_none());
+
+        let expired = cache(Duration::ZERO);
+        expired.insert(cache_key(None), &secret("value")).await;
+        assert!(matches!(
+            expired.get(&cache_key(None)).await,
+            Some(CachedSecret::Stale(Secret { string_secret: Some(value), .. })) if value == "value"
+        ));
+    }
+
+    #[tokio::test]
+    async fn invalidates_latest_versions() {
+        let cache = cache(Duration::from_secs(60));
+        cache.insert(cache_key(None), &secret("latest")).await;
+        cache.insert(cache_key(Some("1")), &secret("pinned")).await;
+        cache.invalidate("nats-kv", "key").await;
+        assert!(cache.get(&cache_key(None)).await.is_none());
+        assert!(cache.get(&cache_key(Some("1"))).await.is_some());
+    }
+
+    #[tokio::test]
+    async fn evicts_secrets_older_than_max_stale() {
+        let cache = cache_with_max_stale(Duration::ZERO, Duration::ZERO);
+        cache.insert(cache_key(None), &secret("old")).await;
+        assert!(cache.get(&cache_key(None)).await.is_none());
+        // Inserting any secret evicts those which are too old to be used
+        cache.insert(cache_key(Some("1")), &secret("new")).await;
+        let entries = cache.entries.read().await;
+        assert_eq!(entries.len(), 1);
+        assert!(entries.contains_key(&cache_key(Some("1"))));
+    }
+
+    #[test]
+    fn only_unavailable_backends_allow_stale_secrets() {
+        let unavailable = anyhow::Error::from(SecretClientError::Backend(
+            GetSecretError::UpstreamError("connection refused".into()),
+        ))
+        .context("failed to fetch secret");
+        assert!(is_unavailable(&unavailable));
+
+        for err in [
+            GetSecretError::Unauthorized,
+            GetSecretError::SecretNotFound,
+            GetSecretError::InvalidEntityJWT("expired".into()),
+        ] {
+            let err = anyhow::Error::from(SecretClientError::Backend(err));
+            assert!(!is_unavailable(&err));
+        }
+        let err =
+            anyhow::anyhow!("invalid secret reference").context("failed to create secret request");
+        assert!(!is_unavailable(&err));
+    }
+}
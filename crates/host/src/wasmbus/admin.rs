@@ -0,0 +1,378 @@
+//! Local HTTP administration endpoint of the host, serving health probes, a read-only JSON view
+//! of the host state and, if enabled, its metrics in the Prometheus text format
+
+use core::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, PoisonError, Weak};
+
+use bytes::Bytes;
+use futures::TryStreamExt as _;
+use http::{header, Method, Response, StatusCode};
+use http_body_util::Full;
+use hyper_util::rt::{TokioExecutor, TokioIo};
+use serde::Serialize;
+use tokio::net::TcpListener;
+use tokio::spawn;
+use tokio::time::Instant;
+use tracing::error;
+use wasmcloud_control_interface::Link;
+use wasmcloud_secrets_types::SECRET_PREFIX;
+use wasmcloud_tracing::PrometheusReader;
+
+use super::Host;
+
+const OK: &str = r#"{"status":"ok"}"#;
+const FAIL: &str = r#"{"status":"failure"}"#;
+
+/// An invocation of a component received over the lattice, which is currently being handled
+#[derive(Debug)]
+pub(crate) struct InFlightInvocation {
+    pub component_id: String,
+    pub operation: String,
+    pub source_id: Option<String>,
+    pub start_at: Instant,
+}
+
+/// The invocations of components currently being handled by the host
+#[derive(Debug, Default)]
+pub(crate) struct InFlightInvocations {
+    next_id: AtomicU64,
+    invocations: Mutex<HashMap<u64, InFlightInvocation>>,
+}
+
+impl InFlightInvocations {
+    /// Track `invocation` until the returned guard is dropped
+    pub(crate) fn track(self: &Arc<Self>, invocation: InFlightInvocation) -> InFlightGuard {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        self.invocations
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert(id, invocation);
+        InFlightGuard {
+            id,
+            invocations: Arc::clone(self),
+        }
+    }
+
+    fn list(&self) -> Vec<InFlightInvocationResponse> {
+        let mut invocations: Vec<_> = self
+            .invocations
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .values()
+            .map(|invocation| InFlightInvocationResponse {
+                component_id: invocation.component_id.clone(),
+                operation: invocation.operation.clone(),
+                source_id: invocation.source_id.clone(),
+                elapsed_ms: invocation
+                    .start_at
+                    .elapsed()
+                    .as_millis()
+                    .try_into()
+                    .unwrap_or(u64::MAX),
+            })
+            .collect();
+        invocations.sort_by(|a, b| b.elapsed_ms.cmp(&a.elapsed_ms));
+        invocations
+    }
+}
+
+/// Guard removing an [`InFlightInvocation`] from [`InFlightInvocations`] when dropped
+#[derive(Debug)]
+pub(crate) struct InFlightGuard {
+    id: u64,
+    invocations: Arc<InFlightInvocations>,
+}
+
+impl Drop for InFlightGuard {
+    fn drop(&mut self) {
+        self.invocations
+            .invocations
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .remove(&self.id);
+    }
+}
+
+#[derive(Serialize)]
+struct InFlightInvocationResponse {
+    component_id: String,
+    operation: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    source_id: Option<String>,
+    elapsed_ms: u64,
+}
+
+#[derive(Serialize)]
+struct ComponentInstancesResponse {
+    id: String,
+    image_ref: String,
+    max_instances: usize,
+    /// The number of instances of the component currently handling invocations
+    active_instances: usize,
+}
+
+fn respond(
+    status: StatusCode,
+    content_type: &str,
+    body: impl Into<Bytes>,
+) -> Response<Full<Bytes>> {
+    let mut res = Response::new(Full::new(body.into()));
+    *res.status_mut() = status;
+    if let Ok(content_type) = content_type.parse() {
+        res.headers_mut().insert(header::CONTENT_TYPE, content_type);
+    }
+    res
+}
+
+fn respond_json(body: &impl Serialize) -> Response<Full<Bytes>> {
+    match serde_json::to_vec(body) {
+        Ok(body) => respond(StatusCode::OK, "application/json", body),
+        Err(err) => {
+            error!(?err, "failed to serialize admin response");
+            respond(StatusCode::INTERNAL_SERVER_ERROR, "application/json", FAIL)
+        }
+    }
+}
+
+fn respond_not_found(path: &str) -> Response<Full<Bytes>> {
+    respond(
+        StatusCode::NOT_FOUND,
+        "text/plain",
+        format!("path `{path}` not found"),
+    )
+}
+
+fn respond_metrics(reader: &PrometheusReader) -> Response<Full<Bytes>> {
+    match reader.encode() {
+        Ok(metrics) => respond(StatusCode::OK, "text/plain; version=0.0.4", metrics),
+        Err(err) => {
+            error!(?err, "failed to encode metrics");
+            respond(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "text/plain",
+                "failed to encode metrics",
+            )
+        }
+    }
+}
+
+async fn handle(
+    host: &Weak<Host>,
+    ready: &AtomicBool,
+    method: &Method,
+    path: &str,
+) -> Response<Full<Bytes>> {
+    if method != Method::GET {
+        return respond(
+            StatusCode::METHOD_NOT_ALLOWED,
+            "text/plain",
+            format!("method `{method}` not supported for path `{path}`"),
+        );
+    }
+    match path {
+        "/livez" => return respond(StatusCode::OK, "application/json", OK),
+        "/readyz" if ready.load(Ordering::Relaxed) => {
+            return respond(StatusCode::OK, "application/json", OK)
+        }
+        "/readyz" => return respond(StatusCode::SERVICE_UNAVAILABLE, "application/json", FAIL),
+        _ => {}
+    }
+    let Some(host) = host.upgrade() else {
+        return respond(
+            StatusCode::SERVICE_UNAVAILABLE,
+            "text/plain",
+            "host is not running",
+        );
+    };
+    match path {
+        "/inventory" => respond_json(&host.inventory().await),
+        "/links" => {
+            let links = host.links.read().await;
+            let links: Vec<&Link> = links.values().flatten().collect();
+            respond_json(&links)
+        }
+        "/config" => {
+            let names = match host.config_data.keys().await {
+                Ok(keys) => {
+                    keys.map_err(anyhow::Error::from)
+                        .try_filter(|name| futures::future::ready(!name.starts_with(SECRET_PREFIX)))
+                        .try_collect::<Vec<_>>()
+                        .await
+                }
+                Err(err) => Err(anyhow::Error::from(err)),
+            };
+            match names {
+                Ok(mut names) => {
+                    names.sort();
+                    respond_json(&names)
+                }
+                Err(err) => {
+                    error!(?err, "failed to list config names");
+                    respond(StatusCode::INTERNAL_SERVER_ERROR, "application/json", FAIL)
+                }
+            }
+        }
+        "/claims" => respond_json(&host.claims().await),
+        "/components" => {
+            let components = host.components.read().await;
+            let mut components: Vec<_> = components
+                .iter()
+                .map(|(id, component)| ComponentInstancesResponse {
+                    id: id.clone(),
+                    image_ref: component.image_reference.to_string(),
+                    max_instances: component.max_instances.get(),
+                    active_instances: component
+                        .max_instances
+                        .get()
+                        .saturating_sub(component.pool.available_instances()),
+                })
+                .collect();
+            components.sort_by(|a, b| a.id.cmp(&b.id));
+            respond_json(&components)
+        }
+        "/invocations" => respond_json(&host.in_flight_invocations.list()),
+        "/metrics" => match &host.host_config.http_admin_metrics {
+            Some(reader) => respond_metrics(reader),
+            None => respond_not_found(path),
+        },
+        _ => respond_not_found(path),
+    }
+}
+
+/// Serve the administration endpoint on `socket` for `host`, reporting readiness using `ready`
+pub(crate) async fn serve(socket: TcpListener, host: Weak<Host>, ready: Arc<AtomicBool>) {
+    let svc = hyper::service::service_fn(move |req: http::Request<hyper::body::Incoming>| {
+        let host = host.clone();
+        let ready = Arc::clone(&ready);
+        async move {
+            let (http::request::Parts { method, uri, .. }, _) = req.into_parts();
+            Ok::<_, core::convert::Infallible>(handle(&host, &ready, &method, uri.path()).await)
+        }
+    });
+    let srv = hyper_util::server::conn::auto::Builder::new(TokioExecutor::new());
+    loop {
+        let stream = match socket.accept().await {
+            Ok((stream, _)) => stream,
+            Err(err) => {
+                error!(?err, "failed to accept health endpoint connection");
+                continue;
+            }
+        };
+        let svc = svc.clone();
+        let srv = srv.clone();
+        spawn(async move {
+            if let Err(err) = srv.serve_connection(TokioIo::new(stream), svc).await {
+                error!(?err, "failed to serve connection");
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use core::sync::atomic::{AtomicBool, Ordering};
+
+    use std::sync::{Arc, Weak};
+
+    use http::{Method, StatusCode};
+    use http_body_util::BodyExt as _;
+    use opentelemetry::metrics::MeterProvider as _;
+    use opentelemetry_sdk::metrics::SdkMeterProvider;
+    use tokio::time::Instant;
+    use wasmcloud_tracing::PrometheusReader;
+
+    use super::{handle, respond_metrics, InFlightInvocation, InFlightInvocations};
+
+    async fn body(res: http::Response<http_body_util::Full<bytes::Bytes>>) -> String {
+        let body = res
+            .into_body()
+            .collect()
+            .await
+            .expect("failed to collect body")
+            .to_bytes();
+        String::from_utf8(body.to_vec()).expect("body is not valid UTF-8")
+    }
+
+    #[tokio::test]
+    async fn probes_do_not_require_a_running_host() {
+        let ready = AtomicBool::new(false);
+        let res = handle(&Weak::new(), &ready, &Method::GET, "/livez").await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let res = handle(&Weak::new(), &ready, &Method::GET, "/readyz").await;
+        assert_eq!(res.status(), StatusCode::SERVICE_UNAVAILABLE);
+
+        ready.store(true, Ordering::Relaxed);
+        let res = handle(&Weak::new(), &ready, &Method::GET, "/readyz").await;
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(body(res).await, r#"{"status":"ok"}"#);
+
+        // Other paths require the host, which has stopped
+        let res = handle(&Weak::new(), &ready, &Method::GET, "/inventory").await;
+        assert_eq!(res.status(), StatusCode::SERVICE_UNAVAILABLE);
+        let res = handle(&Weak::new(), &ready, &Method::POST, "/livez").await;
+        assert_eq!(res.status(), StatusCode::METHOD_NOT_ALLOWED);
+    }
+
+    #[tokio::test]
+    async fn metrics_are_served_from_the_reader() {
+        let reader = PrometheusReader::new();
+        let provider = SdkMeterProvider::builder()
+            .with_reader(reader.clone())
+            .build();
+        provider
+            .meter("test")
+            .u64_counter("test.requests")
+            .init()
+            .add(3, &[]);
+
+        let res = respond_metrics(&reader);
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(
+            res.headers().get(http::header::CONTENT_TYPE),
+            Some(&http::HeaderValue::from_static("text/plain; version=0.0.4"))
+        );
+        assert!(body(res)
+            .await
+            .lines()
+            .any(|line| line == "test_requests_total 3"));
+
+        drop(provider);
+        let res = respond_metrics(&reader);
+        assert_eq!(res.status(), StatusCode::INTERNAL_SERVER_ERROR);
+    }
+
+    #[test]
+    fn in_flight_invocations_are_tracked_until_dropped() {
+        let invocations = Arc::new(InFlightInvocations::default());
+        let first = invocations.track(InFlightInvocation {
+            component_id: "first".into(),
+            operation: "wasi:http/incoming-handler.handle".into(),
+            source_id: Some("source".into()),
+            start_at: Instant::now(),
+        });
+        let second = invocations.track(InFlightInvocation {
+            component_id: "second".into(),
+            operation: "wasi:http/incoming-handler.handle".into(),
+            source_id: None,
+            start_at: Instant::now(),
+        });
+        let mut ids: Vec<_> = invocations
+            .list()
+            .into_iter()
+            .map(|invocation| invocation.component_id)
+            .collect();
+        ids.sort();
+        assert_eq!(ids, ["first", "second"]);
+
+        drop(first);
+        let list = invocations.list();
+        assert_eq!(list.len(), 1);
+        assert_eq!(list[0].component_id, "second");
+        assert_eq!(list[0].source_id, None);
+
+        drop(second);
+        assert!(invocations.list().is_empty());
+    }
+}
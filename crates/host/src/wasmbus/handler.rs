@@ -12,6 +12,7 @@ use bytes::Bytes;
 use secrecy::Secret;
 use tokio::sync::RwLock;
 use tracing::{error, instrument, warn};
+use wasmcloud_core::rpc::InvocationSigner;
 use wasmcloud_runtime::capability::logging::logging;
 use wasmcloud_runtime::capability::secrets::store::SecretValue;
 use wasmcloud_runtime::capability::{
@@ -26,6 +27,8 @@ use wasmcloud_tracing::context::TraceContextInjector;
 use wrpc_transport::InvokeExt as _;
 use wrpc_transport_nats::ParamWriter;
 
+use crate::secrets::SecretReferences;
+
 use super::config::ConfigBundle;
 use super::{injector_to_headers, Features};
 
@@ -52,6 +55,9 @@ pub struct Handler {
     /// backend for each request. The [`SecretValue`] is wrapped in the [`Secret`] type from the `secrecy`
     /// crate to ensure that it is not accidentally logged or exposed in error messages.
     pub secrets: Arc<RwLock<HashMap<String, Secret<SecretValue>>>>,
+    /// The secret references `secrets` were fetched with, used to fetch them again when they are
+    /// rotated in their secrets backend
+    pub secret_references: Arc<RwLock<SecretReferences>>,
     /// The lattice this handler will use for RPC
     pub lattice: Arc<str>,
     /// The identifier of the component that this handler is associated with
@@ -77,6 +83,8 @@ pub struct Handler {
     pub invocation_timeout: Duration,
     /// Experimental features enabled in the host for gating handler functionality
     pub experimental_features: Features,
+    /// Signer of the invocation claims of invocations sent over the lattice, if enabled
+    pub invocation_signer: Option<InvocationSigner>,
 }
 
 impl Handler {
@@ -88,6 +96,7 @@ impl Handler {
             nats: self.nats.clone(),
             config_data: self.config_data.clone(),
             secrets: self.secrets.clone(),
+            secret_references: self.secret_references.clone(),
             lattice: self.lattice.clone(),
             component_id: self.component_id.clone(),
             targets: Arc::default(),
@@ -95,6 +104,7 @@ impl Handler {
             messaging_links: self.messaging_links.clone(),
             invocation_timeout: self.invocation_timeout,
             experimental_features: self.experimental_features,
+            invocation_signer: self.invocation_signer.clone(),
         }
     }
 }
@@ -443,6 +453,17 @@ impl wrpc_transport::Invoke for Handler {
         let mut headers = injector_to_headers(&TraceContextInjector::default_with_span());
         headers.insert("source-id", &*self.component_id);
         headers.insert("link-name", link_name);
+        if let Some(signer) = &self.invocation_signer {
+            signer.sign(
+                &mut headers,
+                &self.lattice,
+                &self.component_id,
+                id,
+                instance,
+                func,
+                &params,
+            )?;
+        }
         let nats = wrpc_transport_nats::Client::new(
             Arc::clone(&self.nats),
             format!("{}.{id}", &self.lattice),
@@ -10,6 +10,7 @@ use nkeys::KeyPair;
 use url::Url;
 use wasmcloud_core::{logging::Level as LogLevel, OtelConfig};
 use wasmcloud_runtime::{MAX_COMPONENTS, MAX_COMPONENT_SIZE, MAX_LINEAR_MEMORY};
+use wasmcloud_tracing::PrometheusReader;
 
 use crate::wasmbus::experimental::Features;
 
@@ -63,6 +64,18 @@ pub struct Host {
     pub policy_service_config: PolicyService,
     /// topic for wasmCloud secrets backend
     pub secrets_topic_prefix: Option<String>,
+    /// How long secrets fetched from secrets backends are cached for, caching is disabled if not set
+    pub secrets_cache_ttl: Option<Duration>,
+    /// How long secrets that expired in the cache are still used for if their backend is
+    /// unavailable
+    pub secrets_cache_max_stale: Duration,
+    /// Whether to sign the invocation claims of invocations sent over the lattice by components
+    /// and providers with the host key
+    pub sign_invocations: bool,
+    /// Public keys of the issuers invocation claims are accepted from. If not empty, components
+    /// and providers only accept invocations carrying invocation claims signed by one of them or
+    /// by this host
+    pub invocation_issuers: Vec<String>,
     /// The semver version of the host. This is used by a consumer of this crate to indicate the
     /// host version (which may differ from the crate version)
     pub version: String,
@@ -74,12 +87,20 @@ pub struct Host {
     pub max_component_size: u64,
     /// The maximum number of components that can be run simultaneously
     pub max_components: u32,
+    /// Whether to meter the fuel consumed by components, which allows limiting the fuel a single
+    /// invocation of a component can consume with a fuel budget
+    pub fuel_metering: bool,
     /// The interval at which the Host will send heartbeats
     pub heartbeat_interval: Option<Duration>,
     /// Experimental features that can be enabled in the host
     pub experimental_features: Features,
     /// HTTP administration endpoint address
     pub http_admin: Option<SocketAddr>,
+    /// Reader of the metrics collected by the OpenTelemetry meter provider, if set, they are
+    /// served in the Prometheus text format on `/metrics` of the HTTP administration endpoint.
+    /// The reader must be registered with the global meter provider, see
+    /// [`wasmcloud_tracing::configure_observability`]
+    pub http_admin_metrics: Option<PrometheusReader>,
 }
 
 /// Configuration for wasmCloud policy service
@@ -121,6 +142,10 @@ impl Default for Host {
             otel_config: OtelConfig::default(),
             policy_service_config: PolicyService::default(),
             secrets_topic_prefix: None,
+            secrets_cache_ttl: None,
+            secrets_cache_max_stale: Duration::from_secs(60 * 60),
+            sign_invocations: false,
+            invocation_issuers: Vec::default(),
             version: env!("CARGO_PKG_VERSION").to_string(),
             max_execution_time: Duration::from_millis(10 * 60 * 1000),
             // 10 MB
@@ -128,9 +153,11 @@ impl Default for Host {
             // 50 MB
             max_component_size: MAX_COMPONENT_SIZE,
             max_components: MAX_COMPONENTS,
+            fuel_metering: false,
             heartbeat_interval: None,
             experimental_features: Features::default(),
             http_admin: None,
+            http_admin_metrics: None,
         }
     }
 }
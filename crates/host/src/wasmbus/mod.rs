@@ -1,5 +1,6 @@
 #![allow(clippy::type_complexity)]
 
+use core::iter;
 use core::sync::atomic::Ordering;
 
 use std::collections::btree_map::Entry as BTreeMapEntry;
@@ -15,7 +16,7 @@ use std::pin::Pin;
 use std::process::Stdio;
 use std::str::FromStr;
 use std::sync::atomic::AtomicBool;
-use std::sync::Arc;
+use std::sync::{Arc, Weak};
 use std::task::{Context, Poll};
 use std::time::Duration;
 
@@ -28,14 +29,13 @@ use cloudevents::{EventBuilder, EventBuilderV10};
 use futures::future::Either;
 use futures::stream::{AbortHandle, Abortable, SelectAll};
 use futures::{join, stream, try_join, Stream, StreamExt, TryFutureExt, TryStreamExt};
-use hyper_util::rt::{TokioExecutor, TokioIo};
 use nkeys::{KeyPair, KeyPairType, XKey};
 use secrecy::Secret;
 use serde::{Deserialize, Serialize};
 use serde_json::json;
 use tokio::io::{AsyncWrite, AsyncWriteExt};
 use tokio::net::TcpListener;
-use tokio::sync::{broadcast, mpsc, watch, RwLock, Semaphore};
+use tokio::sync::{broadcast, mpsc, watch, AcquireError, OwnedSemaphorePermit, RwLock, Semaphore};
 use tokio::task::{JoinHandle, JoinSet};
 use tokio::time::{interval_at, Instant};
 use tokio::{process, select, spawn};
@@ -51,22 +51,27 @@ use wasmcloud_control_interface::{
     StartProviderCommand, StopHostCommand, StopProviderCommand, UpdateComponentCommand,
 };
 use wasmcloud_core::{
-    provider_config_update_subject, ComponentId, HealthCheckResponse, HostData,
-    InterfaceLinkDefinition, OtelConfig, CTL_API_VERSION_1,
+    delegate_invocations, provider_config_update_subject, provider_invocation_delegation_subject,
+    provider_secrets_update_subject, sign_invocation_delegation, ComponentId, HealthCheckResponse, HostData, InterfaceLinkDefinition, InvocationSigner,
+    InvocationVerifier, OtelConfig, VerifiedParams, CTL_API_VERSION_1,
+    INVOCATION_DELEGATION_VALIDITY_SECS,
 };
 use wasmcloud_runtime::capability::secrets::store::SecretValue;
-use wasmcloud_runtime::component::WrpcServeEvent;
+use wasmcloud_runtime::component::{InvocationErrorKind, WrpcServeEvent};
 use wasmcloud_runtime::Runtime;
 use wasmcloud_secrets_types::SECRET_PREFIX;
 use wasmcloud_tracing::context::TraceContextInjector;
 use wasmcloud_tracing::{global, KeyValue};
 
+use crate::metrics::{GaugeGuard, ObservationGuard};
 use crate::registry::RegistryCredentialExt;
+use crate::secrets::{BackendSecretUpdate, SecretReferences, SecretsCache};
 use crate::{
     fetch_component, HostMetrics, OciConfig, PolicyHostInfo, PolicyManager, PolicyResponse,
     RegistryAuth, RegistryConfig, RegistryType, ResourceRef, SecretsManager,
 };
 
+mod admin;
 mod event;
 mod experimental;
 mod handler;
@@ -79,12 +84,43 @@ pub mod host_config;
 pub use self::experimental::Features;
 pub use self::host_config::Host as HostConfig;
 
+use self::admin::{InFlightGuard, InFlightInvocation, InFlightInvocations};
 use self::config::{BundleGenerator, ConfigBundle};
 use self::handler::Handler;
 
 const MAX_INVOCATION_CHANNEL_SIZE: usize = 5000;
 const MIN_INVOCATION_CHANNEL_SIZE: usize = 256;
 
+/// Annotation setting the maximum amount of fuel a single invocation of a component can consume
+const FUEL_BUDGET_ANNOTATION: &str = "wasmcloud.dev/fuel-budget";
+
+/// Parses the per-invocation fuel budget of a component from its annotations
+fn fuel_budget(annotations: &Annotations) -> anyhow::Result<Option<u64>> {
+    annotations
+        .get(FUEL_BUDGET_ANNOTATION)
+        .map(|budget| {
+            budget
+                .parse()
+                .with_context(|| format!("invalid `{FUEL_BUDGET_ANNOTATION}` annotation `{budget}`"))
+        })
+        .transpose()
+}
+
+/// Collects the annotations of the component scaled by `cmd`. The fuel budget of the command is
+/// kept with the annotations, so it's retained across scaling and reported in the host inventory
+fn scale_annotations(cmd: &ScaleComponentCommand) -> Annotations {
+    let mut annotations: Annotations = cmd
+        .annotations()
+        .cloned()
+        .unwrap_or_default()
+        .into_iter()
+        .collect();
+    if let Some(fuel_budget) = cmd.fuel_budget() {
+        annotations.insert(FUEL_BUDGET_ANNOTATION.into(), fuel_budget.to_string());
+    }
+    annotations
+}
+
 #[derive(Debug)]
 struct Queue {
     all_streams: SelectAll<async_nats::Subscriber>,
@@ -205,9 +241,69 @@ struct Component {
     annotations: Annotations,
     /// Maximum number of instances of this component that can be running at once
     max_instances: NonZeroUsize,
+    /// Maximum amount of fuel a single invocation of this component can consume
+    fuel_budget: Option<u64>,
     image_reference: Arc<str>,
     events: mpsc::Sender<WrpcServeEvent<<WrpcServer as wrpc_transport::Serve>::Context>>,
+    pool: Arc<InstancePool>,
+}
+
+/// Pool of instances of a component, which limits the number of instances handling invocations
+/// at once to `max_instances` and records its usage to the host metrics
+#[derive(Debug)]
+struct InstancePool {
     permits: Arc<Semaphore>,
+    metrics: Arc<HostMetrics>,
+    attributes: Arc<[KeyValue]>,
+    _max_instances: GaugeGuard,
+    _memory_high_water: ObservationGuard,
+}
+
+/// Permit to handle an invocation with an instance from an [`InstancePool`]
+struct InstancePermit {
+    _permit: OwnedSemaphorePermit,
+    _active: GaugeGuard,
+}
+
+impl InstancePool {
+    /// Create a pool of `max_instances` instances, which linear memory high-water mark is
+    /// returned by `memory_high_water`
+    fn new(
+        max_instances: NonZeroUsize,
+        metrics: Arc<HostMetrics>,
+        attributes: Arc<[KeyValue]>,
+        memory_high_water: impl Fn() -> u64 + Send + Sync + 'static,
+    ) -> Self {
+        let max_instances = usize::from(max_instances).min(Semaphore::MAX_PERMITS);
+        Self {
+            permits: Arc::new(Semaphore::new(max_instances)),
+            _max_instances: metrics.track_max_instances(Arc::clone(&attributes), max_instances),
+            _memory_high_water: metrics
+                .observe_component_memory_high_water(Arc::clone(&attributes), memory_high_water),
+            metrics,
+            attributes,
+        }
+    }
+
+    /// Wait for an instance to become available, the invocation is counted as queued until then
+    async fn acquire(&self) -> Result<InstancePermit, AcquireError> {
+        let queued = self
+            .metrics
+            .track_queued_invocation(Arc::clone(&self.attributes));
+        let permit = Arc::clone(&self.permits).acquire_owned().await?;
+        drop(queued);
+        Ok(InstancePermit {
+            _permit: permit,
+            _active: self
+                .metrics
+                .track_active_instance(Arc::clone(&self.attributes)),
+        })
+    }
+
+    /// Number of instances available to handle an invocation
+    fn available_instances(&self) -> usize {
+        self.permits.available_permits()
+    }
 }
 
 impl Deref for Component {
@@ -227,12 +323,18 @@ struct WrpcServer {
     annotations: Arc<Annotations>,
     policy_manager: Arc<PolicyManager>,
     metrics: Arc<HostMetrics>,
+    /// Verifier of the invocation claims of invocations, invocations are not verified if `None`
+    invocation_verifier: Option<Arc<InvocationVerifier>>,
+    in_flight_invocations: Arc<InFlightInvocations>,
 }
 
 struct InvocationContext {
     start_at: Instant,
     attributes: Vec<KeyValue>,
     span: tracing::Span,
+    /// Tracks the invocation as in flight until the context is dropped, `None` for invocations
+    /// made by builtin providers
+    _in_flight: Option<InFlightGuard>,
 }
 
 impl Deref for InvocationContext {
@@ -246,7 +348,8 @@ impl Deref for InvocationContext {
 impl wrpc_transport::Serve for WrpcServer {
     type Context = InvocationContext;
     type Outgoing = <wrpc_transport_nats::Client as wrpc_transport::Serve>::Outgoing;
-    type Incoming = <wrpc_transport_nats::Client as wrpc_transport::Serve>::Incoming;
+    type Incoming =
+        VerifiedParams<<wrpc_transport_nats::Client as wrpc_transport::Serve>::Incoming>;
 
     #[instrument(
         level = "info",
@@ -276,6 +379,8 @@ impl wrpc_transport::Serve for WrpcServer {
         let metrics = Arc::clone(&self.metrics);
         let policy_manager = Arc::clone(&self.policy_manager);
         let claims = self.claims.clone();
+        let invocation_verifier = self.invocation_verifier.clone();
+        let in_flight_invocations = Arc::clone(&self.in_flight_invocations);
         Ok(invocations.and_then(move |(cx, tx, rx)| {
             let annotations = Arc::clone(&annotations);
             let claims = claims.clone();
@@ -285,6 +390,8 @@ impl wrpc_transport::Serve for WrpcServer {
             let instance = Arc::clone(&instance);
             let metrics = Arc::clone(&metrics);
             let policy_manager = Arc::clone(&policy_manager);
+            let invocation_verifier = invocation_verifier.clone();
+            let in_flight_invocations = Arc::clone(&in_flight_invocations);
             let span = tracing::info_span!("component_invocation", func = %func, id = %id, instance = %instance);
             async move {
                 if let Some(ref cx) = cx {
@@ -302,6 +409,13 @@ impl wrpc_transport::Serve for WrpcServer {
                     span.set_parent(wasmcloud_tracing::context::get_span_context(&trace_context));
                 }
 
+                let rx = match invocation_verifier {
+                    Some(verifier) => verifier
+                        .verify(cx.as_ref(), &id, &instance, &func, rx)
+                        .context("invocation is not authorized")?,
+                    None => VerifiedParams::unverified(rx),
+                };
+
                 let PolicyResponse {
                     request_id,
                     permitted,
@@ -322,17 +436,28 @@ impl wrpc_transport::Serve for WrpcServer {
                     "policy denied request to invoke component `{request_id}`: `{message:?}`",
                 );
 
+                let in_flight = in_flight_invocations.track(InFlightInvocation {
+                    component_id: id.to_string(),
+                    operation: format!("{instance}/{func}"),
+                    source_id: cx
+                        .as_ref()
+                        .and_then(|cx| cx.get("source-id"))
+                        .map(ToString::to_string),
+                    start_at: Instant::now(),
+                });
                 Ok((
                     InvocationContext{
                         start_at: Instant::now(),
                         // TODO(metrics): insert information about the source once we have concrete context data
                         attributes: vec![
+                            KeyValue::new("component.id", id),
                             KeyValue::new("component.ref", image_reference),
                             KeyValue::new("lattice", metrics.lattice_id.clone()),
                             KeyValue::new("host", metrics.host_id.clone()),
                             KeyValue::new("operation", format!("{instance}/{func}")),
                         ],
                         span,
+                        _in_flight: Some(in_flight),
                     },
                     tx,
                     rx,
@@ -349,6 +474,8 @@ struct Provider {
     claims_token: Option<jwt::Token<jwt::CapabilityProvider>>,
     xkey: XKey,
     annotations: Annotations,
+    /// The secret references the provider was started with
+    secret_references: SecretReferences,
     #[allow(unused)]
     /// Config bundle for the aggregated configuration being watched by the provider
     config: Arc<RwLock<ConfigBundle>>,
@@ -393,6 +520,13 @@ pub struct Host {
     component_claims: Arc<RwLock<HashMap<ComponentId, jwt::Claims<jwt::Component>>>>, // TODO: use a single map once Claims is an enum
     provider_claims: Arc<RwLock<HashMap<String, jwt::Claims<jwt::CapabilityProvider>>>>,
     metrics: Arc<HostMetrics>,
+    /// Issuers whose invocation claims are accepted, invocations are not verified if empty
+    invocation_issuers: Arc<[String]>,
+    /// Verifier of the invocation claims of invocations received by components, shared to
+    /// detect replays across components
+    invocation_verifier: Option<Arc<InvocationVerifier>>,
+    /// Invocations of components received over the lattice which are currently being handled
+    in_flight_invocations: Arc<InFlightInvocations>,
     max_execution_time: Duration,
     messaging_links:
         Arc<RwLock<HashMap<Arc<str>, Arc<RwLock<HashMap<Box<str>, async_nats::Client>>>>>>,
@@ -820,6 +954,7 @@ impl Host {
         let (stop_tx, stop_rx) = watch::channel(None);
 
         let (runtime, _epoch) = Runtime::builder()
+            .fuel_metering(config.fuel_metering)
             .max_execution_time(config.max_execution_time)
             .max_linear_memory(config.max_linear_memory)
             .max_components(config.max_components)
@@ -843,6 +978,7 @@ impl Host {
         let (queue_abort, queue_abort_reg) = AbortHandle::new_pair();
         let (heartbeat_abort, heartbeat_abort_reg) = AbortHandle::new_pair();
         let (data_watch_abort, data_watch_abort_reg) = AbortHandle::new_pair();
+        let (secret_updates_abort, secret_updates_abort_reg) = AbortHandle::new_pair();
 
         let supplemental_config = if config.config_service_enabled {
             load_supplemental_config(&ctl_nats, &config.lattice, &labels).await?
@@ -876,12 +1012,12 @@ impl Host {
                     .is_some_and(|topic| !topic.is_empty()),
             "secrets topic prefix must be non-empty"
         );
-
-        let secrets_manager = Arc::new(SecretsManager::new(
-            &config_data,
-            config.secrets_topic_prefix.as_ref(),
-            &ctl_nats,
-        ));
+        // Hosts verifying invocations must sign their own, otherwise invocations between their
+        // components and providers would be rejected
+        ensure!(
+            config.invocation_issuers.is_empty() || config.sign_invocations,
+            "invocation issuers require invocations to be signed"
+        );
 
         let meter = global::meter_with_version(
             "wasmcloud-host",
@@ -894,6 +1030,20 @@ impl Host {
         );
         let metrics = HostMetrics::new(&meter, host_key.public_key(), config.lattice.to_string());
 
+        let mut secrets_manager = SecretsManager::new(
+            &config_data,
+            config.secrets_topic_prefix.as_ref(),
+            &ctl_nats,
+        );
+        if let Some(ttl) = config.secrets_cache_ttl {
+            secrets_manager = secrets_manager.with_cache(SecretsCache::new(
+                ttl,
+                config.secrets_cache_max_stale,
+                metrics.clone(),
+            ));
+        }
+        let secrets_manager = Arc::new(secrets_manager);
+
         let config_generator = BundleGenerator::new(config_data.clone());
 
         let max_execution_time_ms = config.max_execution_time;
@@ -902,58 +1052,36 @@ impl Host {
 
         let mut tasks = JoinSet::new();
         let ready = Arc::<AtomicBool>::default();
-        if let Some(addr) = config.http_admin {
-            let socket = TcpListener::bind(addr)
-                .await
-                .context("failed to start health endpoint")?;
-            let ready = Arc::clone(&ready);
-            let svc = hyper::service::service_fn(move |req| {
-                const OK: &str = r#"{"status":"ok"}"#;
-                const FAIL: &str = r#"{"status":"failure"}"#;
-                let ready = Arc::clone(&ready);
-                async move {
-                    let (http::request::Parts { method, uri, .. }, _) = req.into_parts();
-                    match (method.as_str(), uri.path_and_query().map(|pq| pq.as_str())) {
-                        ("GET", Some("/livez")) => Ok(http::Response::new(
-                            http_body_util::Full::new(Bytes::from(OK)),
-                        )),
-                        ("GET", Some("/readyz")) => {
-                            if ready.load(Ordering::Relaxed) {
-                                Ok(http::Response::new(http_body_util::Full::new(Bytes::from(
-                                    OK,
-                                ))))
-                            } else {
-                                Ok(http::Response::new(http_body_util::Full::new(Bytes::from(
-                                    FAIL,
-                                ))))
-                            }
-                        }
-                        (method, Some(path)) => {
-                            Err(format!("method `{method}` not supported for path `{path}`"))
-                        }
-                        (method, None) => Err(format!("method `{method}` not supported")),
-                    }
-                }
-            });
-            let srv = hyper_util::server::conn::auto::Builder::new(TokioExecutor::new());
-            tasks.spawn(async move {
-                loop {
-                    let stream = match socket.accept().await {
-                        Ok((stream, _)) => stream,
-                        Err(err) => {
-                            error!(?err, "failed to accept health endpoint connection");
-                            continue;
-                        }
-                    };
-                    let svc = svc.clone();
-                    if let Err(err) = srv.serve_connection(TokioIo::new(stream), svc).await {
-                        error!(?err, "failed to serve connection");
-                    }
-                }
-            });
-        }
+        let http_admin = if let Some(addr) = config.http_admin {
+            Some(
+                TcpListener::bind(addr)
+                    .await
+                    .context("failed to start health endpoint")?,
+            )
+        } else {
+            None
+        };
 
-        let host = Host {
+        // Hosts always accept the invocations they signed themselves
+        let invocation_issuers = if config.invocation_issuers.is_empty() {
+            Arc::default()
+        } else {
+            iter::once(host_key.public_key())
+                .chain(config.invocation_issuers.iter().cloned())
+                .collect()
+        };
+        let invocation_verifier = (!invocation_issuers.is_empty()).then(|| {
+            Arc::new(InvocationVerifier::new(
+                config.lattice.as_ref(),
+                Arc::clone(&invocation_issuers),
+            ))
+        });
+
+        let host = Arc::new_cyclic(|host| {
+            if let Some(socket) = http_admin {
+                tasks.spawn(admin::serve(socket, Weak::clone(host), Arc::clone(&ready)));
+            }
+            Host {
             components: Arc::default(),
             event_builder,
             friendly_name,
@@ -984,13 +1112,16 @@ impl Host {
             component_claims: Arc::default(),
             provider_claims: Arc::default(),
             metrics: Arc::new(metrics),
+            invocation_issuers,
+            invocation_verifier,
+            in_flight_invocations: Arc::default(),
             max_execution_time: max_execution_time_ms,
             messaging_links: Arc::default(),
             ready: Arc::clone(&ready),
             tasks,
-        };
+        }
+        });
 
-        let host = Arc::new(host);
         let queue = spawn({
             let host = Arc::clone(&host);
             async move {
@@ -1090,6 +1221,25 @@ impl Host {
             }
         });
 
+        spawn({
+            let host = Arc::clone(&host);
+            let mut updates = host.secrets_manager.subscribe_updates();
+            Abortable::new(
+                async move {
+                    loop {
+                        match updates.recv().await {
+                            Ok(update) => host.handle_secret_update(update).await,
+                            Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                                warn!(skipped, "secret updates task lagged, skipped updates");
+                            }
+                            Err(broadcast::error::RecvError::Closed) => break,
+                        }
+                    }
+                },
+                secret_updates_abort_reg,
+            )
+        });
+
         // Process existing data without emitting events
         data.keys()
             .await
@@ -1122,6 +1272,7 @@ impl Host {
             heartbeat_abort.abort();
             queue_abort.abort();
             data_watch_abort.abort();
+            secret_updates_abort.abort();
             host.policy_manager.policy_changes.abort();
             let _ = try_join!(queue, data_watch, heartbeat).context("failed to await tasks")?;
             host.publish_event(
@@ -1288,8 +1439,19 @@ impl Host {
             "instantiating component"
         );
 
+        let start_at = Instant::now();
+        let attributes = self.metrics.component_attributes(&id, &image_reference);
+
         let max_execution_time = self.max_execution_time;
         component.set_max_execution_time(max_execution_time);
+        let fuel_budget = fuel_budget(annotations)?;
+        if fuel_budget.is_some() && !self.host_config.fuel_metering {
+            warn!(
+                component_id = ?id,
+                "fuel metering is disabled on this host, ignoring the fuel budget of the component"
+            );
+        }
+        component.set_fuel_budget(fuel_budget);
 
         let (events_tx, mut events_rx) = mpsc::channel(
             max_instances
@@ -1313,32 +1475,41 @@ impl Host {
                     annotations: Arc::new(annotations.clone()),
                     policy_manager: Arc::clone(&self.policy_manager),
                     metrics: Arc::clone(&self.metrics),
+                    invocation_verifier: self.invocation_verifier.clone(),
+                    in_flight_invocations: Arc::clone(&self.in_flight_invocations),
                 },
                 handler.clone(),
                 events_tx.clone(),
             )
             .await?;
-        let permits = Arc::new(Semaphore::new(
-            usize::from(max_instances).min(Semaphore::MAX_PERMITS),
+        self.metrics
+            .record_component_instantiation(start_at.elapsed(), &attributes);
+        let served = component.clone();
+        let pool = Arc::new(InstancePool::new(
+            max_instances,
+            Arc::clone(&self.metrics),
+            attributes.into(),
+            move || u64::try_from(served.memory_high_water()).unwrap_or(u64::MAX),
         ));
         let metrics = Arc::clone(&self.metrics);
+        let served = component.clone();
         Ok(Arc::new(Component {
             component,
             id,
             handler,
             events: events_tx,
-            permits: Arc::clone(&permits),
+            pool: Arc::clone(&pool),
             exports: spawn(async move {
+                let attributes = Arc::clone(&pool.attributes);
                 join!(
                     async move {
                         let mut exports = stream::select_all(exports);
                         loop {
-                            let permits = Arc::clone(&permits);
                             if let Some(fut) = exports.next().await {
                                 match fut {
                                     Ok(fut) => {
                                         debug!("accepted invocation, acquiring permit");
-                                        let permit = permits.acquire_owned().await;
+                                        let permit = pool.acquire().await;
                                         spawn(async move {
                                             let _permit = permit;
                                             debug!("handling invocation");
@@ -1362,6 +1533,7 @@ impl Host {
                         }
                     },
                     async move {
+                        let mut fuel_consumed = 0;
                         while let Some(evt) = events_rx.recv().await {
                             match evt {
                                 WrpcServeEvent::HttpIncomingHandlerHandleReturned {
@@ -1372,6 +1544,7 @@ impl Host {
                                             ..
                                         },
                                     success,
+                                    error,
                                 }
                                 | WrpcServeEvent::MessagingHandlerHandleMessageReturned {
                                     context:
@@ -1381,6 +1554,7 @@ impl Host {
                                             ..
                                         },
                                     success,
+                                    error,
                                 }
                                 | WrpcServeEvent::DynamicExportReturned {
                                     context:
@@ -1390,13 +1564,28 @@ impl Host {
                                             ..
                                         },
                                     success,
-                                } => metrics.record_component_invocation(
-                                    u64::try_from(start_at.elapsed().as_nanos())
-                                        .unwrap_or_default(),
-                                    attributes,
-                                    !success,
-                                ),
+                                    error,
+                                } => {
+                                    metrics.record_component_invocation(
+                                        u64::try_from(start_at.elapsed().as_nanos())
+                                            .unwrap_or_default(),
+                                        attributes,
+                                        !success,
+                                    );
+                                    if error == Some(InvocationErrorKind::OutOfFuel) {
+                                        metrics.record_component_fuel_exhausted(attributes);
+                                    }
+                                }
                             }
+                            // Fuel is accounted for when an invocation's store is dropped, which
+                            // may happen after its return event is sent, so record the difference
+                            // to the total consumed by the component
+                            let consumed = served.fuel_consumed();
+                            metrics.record_component_fuel_consumed(
+                                &attributes,
+                                consumed.saturating_sub(fuel_consumed),
+                            );
+                            fuel_consumed = consumed;
                         }
                         debug!("serving event stream is done");
                     },
@@ -1405,6 +1594,7 @@ impl Host {
             }),
             annotations: annotations.clone(),
             max_instances,
+            fuel_budget,
             image_reference,
         }))
     }
@@ -1422,6 +1612,7 @@ impl Host {
         annotations: &Annotations,
         config: ConfigBundle,
         secrets: HashMap<String, Secret<SecretValue>>,
+        secret_references: SecretReferences,
     ) -> anyhow::Result<&'a mut Arc<Component>> {
         debug!(?component_ref, ?max_instances, "starting new component");
 
@@ -1446,6 +1637,7 @@ impl Host {
             lattice: Arc::clone(&self.host_config.lattice),
             component_id: Arc::clone(&component_id),
             secrets: Arc::new(RwLock::new(secrets)),
+            secret_references: Arc::new(RwLock::new(secret_references)),
             targets: Arc::default(),
             instance_links: Arc::new(RwLock::new(component_import_links(&component_spec.links))),
             messaging_links: {
@@ -1454,8 +1646,19 @@ impl Host {
             },
             invocation_timeout: Duration::from_secs(10), // TODO: Make this configurable
             experimental_features: self.experimental_features,
+            invocation_signer: self
+                .host_config
+                .sign_invocations
+                .then(|| InvocationSigner::new(Arc::clone(&self.host_key))),
         };
+        let start_at = Instant::now();
         let component = wasmcloud_runtime::Component::new(&self.runtime, &wasm)?;
+        self.metrics.record_component_compile(
+            start_at.elapsed(),
+            &self
+                .metrics
+                .component_attributes(&component_id, &component_ref),
+        );
         let component = self
             .instantiate_component(
                 annotations,
@@ -1573,16 +1776,29 @@ impl Host {
     }
 
     #[instrument(level = "trace", skip_all)]
-    async fn fetch_component(&self, component_ref: &str) -> anyhow::Result<Vec<u8>> {
+    async fn fetch_component(
+        &self,
+        component_id: &str,
+        component_ref: &str,
+    ) -> anyhow::Result<Vec<u8>> {
         let registry_config = self.registry_config.read().await;
-        fetch_component(
+        let start_at = Instant::now();
+        let wasm = fetch_component(
             component_ref,
             self.host_config.allow_file_load,
             &self.host_config.oci_opts.additional_ca_paths,
             &registry_config,
         )
         .await
-        .context("failed to fetch component")
+        .context("failed to fetch component")?;
+        self.metrics.record_oci_fetch(
+            start_at.elapsed(),
+            u64::try_from(wasm.len()).unwrap_or(u64::MAX),
+            &self
+                .metrics
+                .component_attributes(component_id, component_ref),
+        );
+        Ok(wasm)
     }
 
     #[instrument(level = "trace", skip_all)]
@@ -1652,7 +1868,6 @@ impl Host {
             .context("failed to deserialize component scale command")?;
         let component_ref = cmd.component_ref();
         let component_id = cmd.component_id();
-        let annotations = cmd.annotations();
         let max_instances = cmd.max_instances();
         let config = cmd.config().clone();
         let allow_update = cmd.allow_update();
@@ -1663,11 +1878,7 @@ impl Host {
         );
 
         let host_id = host_id.to_string();
-        let annotations: Annotations = annotations
-            .cloned()
-            .unwrap_or_default()
-            .into_iter()
-            .collect();
+        let annotations = scale_annotations(&cmd);
 
         // Basic validation to ensure that the component is running and that the image reference matches
         // If it doesn't match, we can still successfully scale, but we won't be updating the image reference
@@ -1711,7 +1922,7 @@ impl Host {
         spawn(async move {
             // Fetch the component from the reference
             let component_and_claims =
-                self.fetch_component(&component_ref)
+                self.fetch_component(&component_id, &component_ref)
                     .await
                     .map(|component_bytes| {
                         // Pull the claims token from the component, this returns an error only if claims are embedded
@@ -1857,6 +2068,11 @@ impl Host {
             ),
             // No component is running and we requested to scale to some amount, start with specified max
             (hash_map::Entry::Vacant(entry), Some(max)) => {
+                let secret_references = SecretReferences::new(
+                    &config,
+                    claims_token.as_ref().map(|c| &c.jwt),
+                    annotations.get("wasmcloud.dev/appspec"),
+                );
                 let (config, secrets) = self
                     .fetch_config_and_secrets(
                         &config,
@@ -1875,6 +2091,7 @@ impl Host {
                     annotations,
                     config,
                     secrets,
+                    secret_references,
                 )
                 .await?;
 
@@ -1921,11 +2138,20 @@ impl Host {
                     &component.id,
                 );
 
-                // Modify scale only if the requested max differs from the current max or if the configuration has changed
-                if component.max_instances != max || config_changed {
+                // Modify scale only if the requested max or fuel budget differs from the current one
+                // or if the configuration has changed
+                if component.max_instances != max
+                    || component.fuel_budget != fuel_budget(annotations)?
+                    || config_changed
+                {
                     // We must partially clone the handler as we can't be sharing the targets between components
                     let handler = component.handler.copy_for_new();
                     if config_changed {
+                        let secret_references = SecretReferences::new(
+                            &config,
+                            claims_token.as_ref().map(|c| &c.jwt),
+                            annotations.get("wasmcloud.dev/appspec"),
+                        );
                         let (config, secrets) = self
                             .fetch_config_and_secrets(
                                 &config,
@@ -1935,6 +2161,7 @@ impl Host {
                             .await?;
                         *handler.config_data.write().await = config;
                         *handler.secrets.write().await = secrets;
+                        *handler.secret_references.write().await = secret_references;
                     }
                     let instance = self
                         .instantiate_component(
@@ -2055,9 +2282,19 @@ impl Host {
                 return Ok(());
             }
 
-            let new_component = self.fetch_component(&new_component_ref).await?;
+            let new_component = self
+                .fetch_component(&component_id, &new_component_ref)
+                .await?;
+            let new_claims_token = wasmcloud_runtime::component::claims_token(&new_component);
+            let start_at = Instant::now();
             let new_component = wasmcloud_runtime::Component::new(&self.runtime, &new_component)
                 .context("failed to initialize component")?;
+            self.metrics.record_component_compile(
+                start_at.elapsed(),
+                &self
+                    .metrics
+                    .component_attributes(&component_id, &new_component_ref),
+            );
             let new_claims = new_component.claims().cloned();
             if let Some(ref claims) = new_claims {
                 self.store_claims(Claims::Component(claims.clone()))
@@ -2079,6 +2316,11 @@ impl Host {
             else {
                 bail!("failed to instantiate component from new reference");
             };
+            // Rotated secrets are fetched with the identity of the new component
+            if let Ok(claims_token) = new_claims_token {
+                component.handler.secret_references.write().await.entity_jwt =
+                    claims_token.map(|token| token.jwt);
+            }
 
             info!(%new_component_ref, "component updated");
             self.publish_event(
@@ -2189,6 +2431,7 @@ impl Host {
         config: Arc<RwLock<ConfigBundle>>,
         path: PathBuf,
         provider_id: &str,
+        provider_ref: &str,
         host_id: &str,
     ) -> anyhow::Result<()> {
         let health_subject = async_nats::Subject::from(format!(
@@ -2239,6 +2482,18 @@ impl Host {
             // reason, we should bail.
             bail!("failed to generate seed for provider xkey")
         };
+        // Providers sign their invocations with a key of their own, which the host delegates to,
+        // so that the host key never leaves the host
+        let (invocation_key, invocation_seed, invocation_delegation) =
+            if self.host_config.sign_invocations {
+                let (key, delegation) = delegate_invocations(&self.host_key, provider_id)?;
+                let seed = key
+                    .seed()
+                    .context("failed to generate seed for provider invocation key")?;
+                (Some(key.public_key()), seed, delegation)
+            } else {
+                (None, String::new(), String::new())
+            };
         let host_data = HostData {
             host_id: self.host_key.public_key(),
             lattice_rpc_prefix: self.host_config.lattice.to_string(),
@@ -2254,7 +2509,9 @@ impl Host {
             secrets,
             provider_xkey_private_key,
             host_xkey_public_key: self.secrets_xkey.public_key(),
-            cluster_issuers: vec![],
+            cluster_issuers: self.invocation_issuers.to_vec(),
+            invocation_seed,
+            invocation_delegation,
             default_rpc_timeout_ms,
             log_level: Some(self.host_config.log_level.clone()),
             structured_logging: self.host_config.enable_structured_logging,
@@ -2321,6 +2578,10 @@ impl Host {
         });
         let mut exit_health_rx = exit_rx.resubscribe();
 
+        let metrics = Arc::clone(&self.metrics);
+        let attributes = metrics.provider_attributes(provider_id, provider_ref);
+        metrics.record_provider_start(provider_id, &attributes);
+
         let lattice = Arc::clone(&self.host_config.lattice);
         let rpc_nats = Arc::clone(&self.rpc_nats);
         let host_id = host_id.to_string();
@@ -2346,7 +2607,13 @@ impl Host {
                                 health_subject.clone(),
                                 request,
                                 ).await {
-                                    match (serde_json::from_slice::<HealthCheckResponse>(&payload), previous_healthy) {
+                                    let response = serde_json::from_slice::<HealthCheckResponse>(&payload);
+                                    metrics.record_provider_health_check(&attributes, match response {
+                                        Ok(HealthCheckResponse { healthy: true, .. }) => "healthy",
+                                        Ok(HealthCheckResponse { healthy: false, .. }) => "unhealthy",
+                                        Err(_) => "error",
+                                    });
+                                    match (response, previous_healthy) {
                                         (Ok(HealthCheckResponse { healthy: true, ..}), false) => {
                                             trace!(?provider_id, "provider health check succeeded");
                                             previous_healthy = true;
@@ -2413,6 +2680,7 @@ impl Host {
                                     }
                                 }
                                 else {
+                                    metrics.record_provider_health_check(&attributes, "error");
                                     warn!(?provider_id, "failed to request provider health, retrying in 30 seconds");
                                 }
                         }
@@ -2427,6 +2695,42 @@ impl Host {
             }
         });
 
+        // Delegations expire, so spawn off a task renewing the delegation of the invocation key of
+        // the provider for as long as it runs
+        if let Some(invocation_key) = invocation_key {
+            let mut exit_delegation_rx = exit_rx.resubscribe();
+            let host_key = Arc::clone(&self.host_key);
+            let rpc_nats = Arc::clone(&rpc_nats);
+            let provider_id = Arc::clone(&provider_id);
+            let subject = provider_invocation_delegation_subject(&self.host_config.lattice, &provider_id);
+            tasks.spawn(async move {
+                let period = Duration::from_secs(INVOCATION_DELEGATION_VALIDITY_SECS / 2);
+                let mut renewal = interval_at(Instant::now() + period, period);
+                loop {
+                    select! {
+                        _ = renewal.tick() => {
+                            let delegation = match sign_invocation_delegation(&host_key, &provider_id, &invocation_key) {
+                                Ok(delegation) => delegation,
+                                Err(err) => {
+                                    error!(?err, ?provider_id, "failed to renew invocation delegation");
+                                    continue;
+                                }
+                            };
+                            if let Err(err) = rpc_nats.publish(subject.clone(), delegation.into()).await {
+                                error!(%err, ?provider_id, "failed to publish renewed invocation delegation");
+                            }
+                        }
+                        exit = exit_delegation_rx.recv() => {
+                            if let Err(err) = exit {
+                                warn!(%err, ?provider_id, "failed to receive exit in invocation delegation task");
+                            }
+                            break;
+                        }
+                    }
+                }
+            });
+        }
+
         // Spawn off a task to watch for config bundle updates and forward them to
         // the provider that we're spawning and managing
         let mut exit_config_tx = exit_rx;
@@ -2487,6 +2791,7 @@ impl Host {
         let (path, claims_token) = match &provider_ref {
             ResourceRef::Builtin(..) => (None, None),
             _ => {
+                let start_at = Instant::now();
                 let (path, claims_token) = crate::fetch_provider(
                     &provider_ref,
                     host_id,
@@ -2495,6 +2800,16 @@ impl Host {
                 )
                 .await
                 .context("failed to fetch provider")?;
+                let size = tokio::fs::metadata(&path)
+                    .await
+                    .map_or(0, |metadata| metadata.len());
+                self.metrics.record_oci_fetch(
+                    start_at.elapsed(),
+                    size,
+                    &self
+                        .metrics
+                        .provider_attributes(provider_id, provider_ref.as_ref()),
+                );
                 (Some(path), claims_token)
             }
         };
@@ -2534,6 +2849,11 @@ impl Host {
         self.store_component_spec(&provider_id, &component_specification)
             .await?;
 
+        let secret_references = SecretReferences::new(
+            config,
+            claims_token.as_ref().map(|t| &t.jwt),
+            annotations.get("wasmcloud.dev/appspec"),
+        );
         let (config, secrets) = self
             .fetch_config_and_secrets(
                 config,
@@ -2587,24 +2907,7 @@ impl Host {
                 .collect::<Vec<wasmcloud_core::InterfaceLinkDefinition>>()
                 .await;
 
-            let secrets = {
-                // NOTE(brooksmtownsend): This trait import is used here to ensure we're only exposing secret
-                // values when we need them.
-                use secrecy::ExposeSecret;
-                secrets
-                    .iter()
-                    .map(|(k, v)| match v.expose_secret() {
-                        SecretValue::String(s) => (
-                            k.clone(),
-                            wasmcloud_core::secrets::SecretValue::String(s.to_owned()),
-                        ),
-                        SecretValue::Bytes(b) => (
-                            k.clone(),
-                            wasmcloud_core::secrets::SecretValue::Bytes(b.to_owned()),
-                        ),
-                    })
-                    .collect()
-            };
+            let secrets = provider_secret_values(&secrets);
             let host_config = config.get_config().await.clone();
             let config = Arc::new(RwLock::new(config));
             let mut tasks = JoinSet::new();
@@ -2619,6 +2922,7 @@ impl Host {
                         Arc::clone(&config),
                         path,
                         provider_id,
+                        provider_ref.as_ref(),
                         host_id,
                     )
                     .await?
@@ -2680,6 +2984,7 @@ impl Host {
                 claims_token,
                 image_ref: provider_ref.as_ref().to_string(),
                 xkey,
+                secret_references,
                 config,
             });
         } else {
@@ -2760,7 +3065,11 @@ impl Host {
     #[instrument(level = "trace", skip_all)]
     async fn handle_claims(&self) -> anyhow::Result<CtlResponse<Vec<HashMap<String, String>>>> {
         trace!("handling claims");
+        Ok(CtlResponse::ok(self.claims().await))
+    }
 
+    /// The claims of all components and providers started on this host
+    async fn claims(&self) -> Vec<HashMap<String, String>> {
         let (component_claims, provider_claims) =
             join!(self.component_claims.read(), self.provider_claims.read());
         let component_claims = component_claims.values().cloned().map(Claims::Component);
@@ -2770,9 +3079,7 @@ impl Host {
             .flat_map(TryFrom::try_from)
             .collect();
 
-        Ok(CtlResponse::ok(
-            claims.into_iter().map(std::convert::Into::into).collect(),
-        ))
+        claims.into_iter().map(std::convert::Into::into).collect()
     }
 
     #[instrument(level = "trace", skip_all)]
@@ -3740,6 +4047,153 @@ impl Host {
         Ok((config, secrets))
     }
 
+    /// Fetches the secrets of all components, providers and links referencing a secret that was
+    /// rotated in its secrets backend again. Components see the new values on their next call to
+    /// `wasmcloud:secrets/store`, while providers are sent their secrets, encrypted with their xkey,
+    /// and links are put again to the providers they apply to.
+    #[instrument(level = "debug", skip(self))]
+    async fn handle_secret_update(&self, update: BackendSecretUpdate) {
+        let components: Vec<_> = self.components.read().await.values().cloned().collect();
+        for component in components {
+            let references = component.handler.secret_references.read().await.clone();
+            if !self
+                .secrets_manager
+                .is_affected_by(&references.names, &update)
+                .await
+            {
+                continue;
+            }
+            match self
+                .secrets_manager
+                .fetch_secrets(
+                    references.names,
+                    references.entity_jwt.as_ref(),
+                    &self.host_token.jwt,
+                    references.application.as_ref(),
+                )
+                .await
+            {
+                Ok(secrets) => {
+                    *component.handler.secrets.write().await = secrets;
+                    info!(component_id = %component.id, key = update.update.key, "updated component secrets");
+                }
+                Err(err) => {
+                    error!(?err, component_id = %component.id, "failed to fetch rotated secrets for component");
+                }
+            }
+        }
+
+        let providers: Vec<_> = self
+            .providers
+            .read()
+            .await
+            .iter()
+            .map(|(id, provider)| {
+                (
+                    id.clone(),
+                    provider.xkey.public_key(),
+                    provider.secret_references.clone(),
+                )
+            })
+            .collect();
+        for (provider_id, provider_xkey, references) in providers {
+            if !self
+                .secrets_manager
+                .is_affected_by(&references.names, &update)
+                .await
+            {
+                continue;
+            }
+            if let Err(err) = self
+                .send_provider_secrets(&provider_id, &provider_xkey, references)
+                .await
+            {
+                error!(
+                    ?err,
+                    provider_id, "failed to send rotated secrets to provider"
+                );
+            } else {
+                info!(
+                    provider_id,
+                    key = update.update.key,
+                    "updated provider secrets"
+                );
+            }
+        }
+
+        // Links carry secrets of their own, so links referencing the rotated secret are put again
+        // to the providers running on this host that they apply to
+        let links: Vec<Link> = self
+            .links
+            .read()
+            .await
+            .values()
+            .flatten()
+            .cloned()
+            .collect();
+        let providers = self.providers.read().await;
+        for link in links {
+            if !self
+                .secrets_manager
+                .is_affected_by(&link_secret_names(&link), &update)
+                .await
+            {
+                continue;
+            }
+            for provider_id in [link.source_id(), link.target()] {
+                let Some(provider) = providers.get(provider_id) else {
+                    continue;
+                };
+                if let Err(err) = self.put_provider_link(provider, &link).await {
+                    error!(
+                        ?err,
+                        provider_id, "failed to put link with rotated secrets to provider"
+                    );
+                } else {
+                    info!(
+                        provider_id,
+                        source_id = link.source_id(),
+                        target = link.target(),
+                        key = update.update.key,
+                        "updated link secrets"
+                    );
+                }
+            }
+        }
+    }
+
+    /// Fetches the secrets referenced by a provider and publishes them, encrypted with the provider's
+    /// xkey, on the provider's secrets update subject.
+    async fn send_provider_secrets(
+        &self,
+        provider_id: &str,
+        provider_xkey: &str,
+        references: SecretReferences,
+    ) -> anyhow::Result<()> {
+        let secrets = self
+            .secrets_manager
+            .fetch_secrets(
+                references.names,
+                references.entity_jwt.as_ref(),
+                &self.host_token.jwt,
+                references.application.as_ref(),
+            )
+            .await?;
+        let secrets = provider_secret_values(&secrets);
+        let provider_xkey =
+            XKey::from_public_key(provider_xkey).context("failed to parse provider xkey")?;
+        let secrets = serde_json::to_vec(&secrets)
+            .map(|secrets| self.secrets_xkey.seal(&secrets, &provider_xkey))
+            .context("failed to serialize and encrypt secrets")??;
+        self.rpc_nats
+            .publish(
+                provider_secrets_update_subject(&self.host_config.lattice, provider_id),
+                secrets.into(),
+            )
+            .await
+            .context("failed to publish secrets update")
+    }
+
     /// Validates that the provided configuration names exist in the store and are valid.
     ///
     /// For any configuration that starts with `SECRET_`, the configuration is expected to be a secret reference.
@@ -3801,37 +4255,8 @@ impl Host {
 
         let source_config = source_bundle.get_config().await;
         let target_config = target_bundle.get_config().await;
-        // NOTE(brooksmtownsend): This trait import is used here to ensure we're only exposing secret
-        // values when we need them.
-        use secrecy::ExposeSecret;
-        let source_secrets_map: HashMap<String, wasmcloud_core::secrets::SecretValue> =
-            raw_source_secrets
-                .iter()
-                .map(|(k, v)| match v.expose_secret() {
-                    SecretValue::String(s) => (
-                        k.clone(),
-                        wasmcloud_core::secrets::SecretValue::String(s.to_owned()),
-                    ),
-                    SecretValue::Bytes(b) => (
-                        k.clone(),
-                        wasmcloud_core::secrets::SecretValue::Bytes(b.to_owned()),
-                    ),
-                })
-                .collect();
-        let target_secrets_map: HashMap<String, wasmcloud_core::secrets::SecretValue> =
-            raw_target_secrets
-                .iter()
-                .map(|(k, v)| match v.expose_secret() {
-                    SecretValue::String(s) => (
-                        k.clone(),
-                        wasmcloud_core::secrets::SecretValue::String(s.to_owned()),
-                    ),
-                    SecretValue::Bytes(b) => (
-                        k.clone(),
-                        wasmcloud_core::secrets::SecretValue::Bytes(b.to_owned()),
-                    ),
-                })
-                .collect();
+        let source_secrets_map = provider_secret_values(&raw_source_secrets);
+        let target_secrets_map = provider_secret_values(&raw_target_secrets);
         // Serializing & sealing an empty map results in a non-empty Vec, which is difficult to tell the
         // difference between an empty map and an encrypted empty map. To avoid this, we explicitly handle
         // the case where the map is empty.
@@ -3869,6 +4294,39 @@ impl Host {
     }
 }
 
+/// Converts secrets fetched from secrets backends into the [`wasmcloud_core::secrets::SecretValue`]s
+/// sent to providers, encrypted with their xkey.
+fn provider_secret_values(
+    secrets: &HashMap<String, Secret<SecretValue>>,
+) -> HashMap<String, wasmcloud_core::secrets::SecretValue> {
+    // NOTE(brooksmtownsend): This trait import is used here to ensure we're only exposing secret
+    // values when we need them.
+    use secrecy::ExposeSecret;
+    secrets
+        .iter()
+        .map(|(k, v)| match v.expose_secret() {
+            SecretValue::String(s) => (
+                k.clone(),
+                wasmcloud_core::secrets::SecretValue::String(s.to_owned()),
+            ),
+            SecretValue::Bytes(b) => (
+                k.clone(),
+                wasmcloud_core::secrets::SecretValue::Bytes(b.to_owned()),
+            ),
+        })
+        .collect()
+}
+
+/// Returns the names of the secret references in the source and target configuration of `link`
+fn link_secret_names(link: &Link) -> Vec<String> {
+    link.source_config()
+        .iter()
+        .chain(link.target_config())
+        .filter(|name| name.starts_with(SECRET_PREFIX))
+        .cloned()
+        .collect()
+}
+
 /// Helper function to transform a Vec of [`Link`]s into the structure components expect to be able
 /// to quickly look up the desired target for a given interface
 ///
@@ -4304,4 +4762,161 @@ mod test {
 
         assert_eq!(links_map, expected_result);
     }
+
+    // Ensure that only the secret references of a link, from both its source and target
+    // configuration, are considered when a secret is rotated
+    #[test]
+    fn link_secret_names_include_source_and_target_secrets() {
+        use wasmcloud_control_interface::Link;
+
+        let link = Link::builder()
+            .source_id("http")
+            .target("component")
+            .wit_namespace("wasi")
+            .wit_package("http")
+            .interfaces(vec!["incoming-handler".into()])
+            .name("default")
+            .source_config(vec!["address".into(), "SECRET_tls-key".into()])
+            .target_config(vec!["SECRET_api-token".into(), "settings".into()])
+            .build()
+            .expect("failed to build link");
+        assert_eq!(
+            super::link_secret_names(&link),
+            vec!["SECRET_tls-key".to_string(), "SECRET_api-token".to_string()]
+        );
+
+        let link = Link::builder()
+            .source_id("component")
+            .target("kv")
+            .wit_namespace("wasi")
+            .wit_package("keyvalue")
+            .interfaces(vec!["store".into()])
+            .name("default")
+            .target_config(vec!["bucket".into()])
+            .build()
+            .expect("failed to build link");
+        assert!(super::link_secret_names(&link).is_empty());
+    }
+
+    // Ensure that the instance pool of a component records invocations waiting for an instance as
+    // queued, and the ones holding an instance as active
+    #[tokio::test]
+    async fn instance_pool_accounts_for_queued_and_active_invocations() -> anyhow::Result<()> {
+        use core::num::NonZeroUsize;
+        use std::sync::Arc;
+
+        use anyhow::Context as _;
+        use opentelemetry::metrics::MeterProvider as _;
+        use opentelemetry_sdk::metrics::SdkMeterProvider;
+        use wasmcloud_tracing::PrometheusReader;
+
+        use crate::metrics::HostMetrics;
+
+        let reader = PrometheusReader::new();
+        let provider = SdkMeterProvider::builder()
+            .with_reader(reader.clone())
+            .build();
+        let metrics = Arc::new(HostMetrics::new(
+            &provider.meter("test"),
+            "host".into(),
+            "lattice".into(),
+        ));
+        let pool = Arc::new(super::InstancePool::new(
+            NonZeroUsize::MIN,
+            Arc::clone(&metrics),
+            metrics.component_attributes("component", "ref").into(),
+            || 1024,
+        ));
+        let gauge = |name: &str| -> anyhow::Result<String> {
+            let encoded = reader.encode()?;
+            encoded
+                .lines()
+                .find_map(|line| {
+                    let (_, value) = line
+                        .strip_prefix(name)?
+                        .strip_prefix('{')?
+                        .split_once("} ")?;
+                    Some(value.to_string())
+                })
+                .with_context(|| format!("`{name}` not found in:\n{encoded}"))
+        };
+
+        assert_eq!(gauge("wasmcloud_host_component_max_instances")?, "1");
+        assert_eq!(
+            gauge("wasmcloud_host_component_memory_high_water_bytes")?,
+            "1024"
+        );
+
+        let first = pool.acquire().await?;
+        assert_eq!(pool.available_instances(), 0);
+        assert_eq!(gauge("wasmcloud_host_component_active_instances")?, "1");
+        assert_eq!(gauge("wasmcloud_host_component_queued_invocations")?, "0");
+
+        let second = tokio::spawn({
+            let pool = Arc::clone(&pool);
+            async move { pool.acquire().await.map(drop) }
+        });
+        for _ in 0..100 {
+            if gauge("wasmcloud_host_component_queued_invocations")? == "1" {
+                break;
+            }
+            tokio::task::yield_now().await;
+        }
+        assert_eq!(gauge("wasmcloud_host_component_queued_invocations")?, "1");
+        assert_eq!(gauge("wasmcloud_host_component_active_instances")?, "1");
+
+        drop(first);
+        second.await??;
+        assert_eq!(pool.available_instances(), 1);
+        assert_eq!(gauge("wasmcloud_host_component_active_instances")?, "0");
+        assert_eq!(gauge("wasmcloud_host_component_queued_invocations")?, "0");
+
+        drop(pool);
+        assert_eq!(gauge("wasmcloud_host_component_max_instances")?, "0");
+        assert!(gauge("wasmcloud_host_component_memory_high_water_bytes").is_err());
+        Ok(())
+    }
+
+    // Ensure that the fuel budget of a scale command, or one set in the annotations, is the fuel
+    // budget the component is instantiated with
+    #[test]
+    fn scale_command_fuel_budget_is_kept_in_annotations() -> anyhow::Result<()> {
+        use std::collections::BTreeMap;
+
+        use wasmcloud_control_interface::{ScaleComponentCommand, ScaleComponentCommandBuilder};
+
+        use super::{fuel_budget, scale_annotations, FUEL_BUDGET_ANNOTATION};
+
+        let scale = |cmd: ScaleComponentCommandBuilder| {
+            scale_annotations(&cmd.build().expect("failed to build scale command"))
+        };
+        let cmd = ScaleComponentCommand::builder()
+            .component_ref("component_ref")
+            .component_id("component_id")
+            .host_id("host_id")
+            .annotations(BTreeMap::from([("a".into(), "b".into())]));
+
+        let annotations = scale(cmd.clone().fuel_budget(1_000_000));
+        assert_eq!(annotations.get("a").map(String::as_str), Some("b"));
+        assert_eq!(fuel_budget(&annotations)?, Some(1_000_000));
+        assert_eq!(fuel_budget(&scale(cmd.clone()))?, None);
+
+        // The budget of the command takes precedence over the annotation
+        let cmd = cmd.annotations(BTreeMap::from([(
+            FUEL_BUDGET_ANNOTATION.into(),
+            "500".into(),
+        )]));
+        assert_eq!(fuel_budget(&scale(cmd.clone()))?, Some(500));
+        assert_eq!(
+            fuel_budget(&scale(cmd.clone().fuel_budget(1_000)))?,
+            Some(1_000)
+        );
+
+        let cmd = cmd.annotations(BTreeMap::from([(
+            FUEL_BUDGET_ANNOTATION.into(),
+            "lots".into(),
+        )]));
+        assert!(fuel_budget(&scale(cmd)).is_err());
+        Ok(())
+    }
 }
@@ -116,7 +116,7 @@ impl wasmcloud_provider_sdk::Provider for Provider {
                         )
                         .context("invalid request")?;
                     let _permit = component
-                        .permits
+                        .pool
                         .acquire()
                         .instrument(trace_span!("acquire_permit"))
                         .await
@@ -126,8 +126,10 @@ impl wasmcloud_provider_sdk::Provider for Provider {
                         .handle(
                             InvocationContext {
                                 span: Span::current(),
+                                _in_flight: None,
                                 start_at: Instant::now(),
                                 attributes: vec![
+                                    KeyValue::new("component.id", Arc::clone(&component.id)),
                                     KeyValue::new(
                                         "component.ref",
                                         Arc::clone(&component.image_reference),
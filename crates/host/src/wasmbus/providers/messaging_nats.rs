@@ -106,7 +106,7 @@ async fn handle_message(
         Arc::clone(component)
     };
     let _permit = match component
-        .permits
+        .pool
         .acquire()
         .instrument(trace_span!("acquire_message_permit"))
         .await
@@ -122,8 +122,10 @@ async fn handle_message(
         .handle_message(
             InvocationContext {
                 span: Span::current(),
+                _in_flight: None,
                 start_at: Instant::now(),
                 attributes: vec![
+                    KeyValue::new("component.id", Arc::clone(&component.id)),
                     KeyValue::new("component.ref", Arc::clone(&component.image_reference)),
                     KeyValue::new("lattice", lattice_id),
                     KeyValue::new("host", host_id),
@@ -2,15 +2,19 @@
 
 use core::future::Future;
 use core::pin::Pin;
+use core::time::Duration;
 
 use std::collections::HashMap;
 use std::sync::Arc;
 
 use anyhow::{bail, Context as _, Result};
-use azure_storage::CloudLocation;
+use azure_core::request_options::{IfMatchCondition, Metadata};
+use azure_storage::prelude::BlobSasPermissions;
+use azure_storage::{CloudLocation, ErrorKind};
 use azure_storage_blobs::prelude::*;
 use bytes::{Bytes, BytesMut};
 use futures::{Stream, StreamExt as _};
+use time::OffsetDateTime;
 use tokio::sync::{mpsc, RwLock};
 use tokio_stream::wrappers::ReceiverStream;
 use tracing::{error, instrument};
@@ -20,14 +24,30 @@ use wasmcloud_provider_sdk::{
 };
 use wrpc_interface_blobstore::bindings::{
     exports::wrpc::blobstore::blobstore::Handler,
-    serve,
     wrpc::blobstore::types::{ContainerMetadata, ObjectId, ObjectMetadata},
 };
 
+use bindings::exports::wasmcloud::blobstore::metadata;
 use config::StorageConfig;
 
 mod config;
 
+mod bindings {
+    wit_bindgen_wrpc::generate!({
+        world: "interfaces",
+        with: {
+            "wasi:blobstore/types@0.2.0-draft": wrpc_interface_blobstore::bindings::wasi::blobstore::types,
+            "wasi:io/error@0.2.0": wrpc_interface_blobstore::bindings::wasi::io::error,
+            "wasi:io/poll@0.2.0": wrpc_interface_blobstore::bindings::wasi::io::poll,
+            "wasi:io/streams@0.2.0": wrpc_interface_blobstore::bindings::wasi::io::streams,
+            "wrpc:blobstore/blobstore@0.2.0": wrpc_interface_blobstore::bindings::exports::wrpc::blobstore::blobstore,
+            "wrpc:blobstore/types@0.2.0": wrpc_interface_blobstore::bindings::wrpc::blobstore::types,
+            "wasmcloud:blobstore/metadata@0.1.0-draft": generate,
+            "wasmcloud:blobstore/presign@0.1.0-draft": generate,
+        },
+    });
+}
+
 /// Blobstore Azblob provider
 ///
 /// This struct will be the target of generated implementations (via wit-provider-bindgen)
@@ -106,7 +126,7 @@ impl BlobstoreAzblobProvider {
         let wrpc = connection
             .get_wrpc_client(connection.provider_key())
             .await?;
-        serve_provider_exports(&wrpc, provider, shutdown, serve)
+        serve_provider_exports(&wrpc, provider, shutdown, bindings::serve)
             .await
             .context("failed to serve provider exports")
     }
@@ -125,6 +145,207 @@ impl BlobstoreAzblobProvider {
             )
         }
     }
+
+    /// Generate a URL for the given blob, signed with a SAS token granting `permissions` until
+    /// `expires_in` from now
+    async fn presign(
+        &self,
+        context: Option<&Context>,
+        container: String,
+        object: String,
+        permissions: BlobSasPermissions,
+        expires_in: Duration,
+    ) -> anyhow::Result<String> {
+        let client = self
+            .get_config(context)
+            .await
+            .context("failed to retrieve azure blobstore client")?;
+        let client = client.container_client(container).blob_client(object);
+        let sas = client
+            .shared_access_signature(permissions, OffsetDateTime::now_utc() + expires_in)
+            .await
+            .context("failed to generate shared access signature")?;
+        let url = client
+            .generate_signed_blob_url(&sas)
+            .context("failed to generate signed blob URL")?;
+        Ok(url.to_string())
+    }
+}
+
+impl bindings::exports::wasmcloud::blobstore::presign::Handler<Option<Context>>
+    for BlobstoreAzblobProvider
+{
+    #[instrument(level = "trace", skip(self))]
+    async fn presign_get(
+        &self,
+        cx: Option<Context>,
+        container: String,
+        object: String,
+        expires_in_secs: u64,
+    ) -> anyhow::Result<Result<String, String>> {
+        Ok(async {
+            propagate_trace_for_ctx!(cx);
+            self.presign(
+                cx.as_ref(),
+                container,
+                object,
+                BlobSasPermissions {
+                    read: true,
+                    ..Default::default()
+                },
+                Duration::from_secs(expires_in_secs),
+            )
+            .await
+        }
+        .await
+        .map_err(|err| format!("{err:#}")))
+    }
+
+    #[instrument(level = "trace", skip(self))]
+    async fn presign_put(
+        &self,
+        cx: Option<Context>,
+        container: String,
+        object: String,
+        expires_in_secs: u64,
+    ) -> anyhow::Result<Result<String, String>> {
+        Ok(async {
+            propagate_trace_for_ctx!(cx);
+            self.presign(
+                cx.as_ref(),
+                container,
+                object,
+                BlobSasPermissions {
+                    create: true,
+                    write: true,
+                    ..Default::default()
+                },
+                Duration::from_secs(expires_in_secs),
+            )
+            .await
+        }
+        .await
+        .map_err(|err| format!("{err:#}")))
+    }
+}
+
+impl bindings::exports::wasmcloud::blobstore::metadata::Handler<Option<Context>>
+    for BlobstoreAzblobProvider
+{
+    #[instrument(level = "trace", skip(self))]
+    async fn get_object_metadata(
+        &self,
+        cx: Option<Context>,
+        container: String,
+        object: String,
+    ) -> anyhow::Result<Result<metadata::ObjectMetadata, String>> {
+        Ok(async {
+            propagate_trace_for_ctx!(cx);
+            let client = self
+                .get_config(cx.as_ref())
+                .await
+                .context("failed to retrieve azure blobstore client")?;
+
+            let Blob {
+                properties,
+                metadata,
+                ..
+            } = client
+                .container_client(container)
+                .blob_client(object)
+                .get_properties()
+                .await
+                .context("failed to get blob properties")?
+                .blob;
+            anyhow::Ok(metadata::ObjectMetadata {
+                size: properties.content_length,
+                last_modified: properties
+                    .last_modified
+                    .unix_timestamp()
+                    .try_into()
+                    .context("failed to convert last_modified date to u64")?,
+                etag: properties.etag.to_string(),
+                content_type: Some(properties.content_type).filter(|v| !v.is_empty()),
+                metadata: metadata.unwrap_or_default().into_iter().collect(),
+            })
+        }
+        .await
+        .map_err(|err| format!("{err:#}")))
+    }
+
+    #[instrument(level = "trace", skip(self, data))]
+    async fn write_object(
+        &self,
+        cx: Option<Context>,
+        container: String,
+        object: String,
+        data: Bytes,
+        metadata::WriteOptions {
+            content_type,
+            metadata,
+            precondition,
+        }: metadata::WriteOptions,
+    ) -> anyhow::Result<Result<String, metadata::WriteError>> {
+        propagate_trace_for_ctx!(cx);
+        let client = match self.get_config(cx.as_ref()).await {
+            Ok(client) => client,
+            Err(err) => {
+                return Ok(Err(metadata::WriteError::Other(format!(
+                    "{:#}",
+                    err.context("failed to retrieve azure blobstore client")
+                ))))
+            }
+        };
+        let mut req = client
+            .container_client(container)
+            .blob_client(object)
+            .put_block_blob(data);
+        if let Some(content_type) = content_type {
+            req = req.content_type(content_type);
+        }
+        if !metadata.is_empty() {
+            let mut m = Metadata::new();
+            for (k, v) in metadata {
+                m.insert(k, v);
+            }
+            req = req.metadata(m);
+        }
+        match precondition {
+            Some(metadata::Precondition::IfMatch(etag)) => {
+                req = req.if_match(IfMatchCondition::Match(etag));
+            }
+            Some(metadata::Precondition::IfNoneMatch(etag)) => {
+                req = req.if_match(IfMatchCondition::NotMatch(etag));
+            }
+            None => {}
+        }
+        match req.await {
+            Ok(res) => Ok(Ok(res.etag)),
+            Err(err) if is_precondition_failure(&err) => {
+                Ok(Err(metadata::WriteError::PreconditionFailed))
+            }
+            Err(err) => Ok(Err(metadata::WriteError::Other(format!(
+                "{:#}",
+                anyhow::Error::from(err).context("failed to write blob")
+            )))),
+        }
+    }
+}
+
+/// Check whether a write failed because its precondition did not hold.
+///
+/// Azure returns 412 `ConditionNotMet` if an `If-Match` or `If-None-Match` condition does not
+/// hold, except for `If-None-Match: *` on an existing blob, which fails with 409
+/// `BlobAlreadyExists`. Other 409 and 412 responses, e.g. due to a lease on the blob, are not
+/// precondition failures.
+fn is_precondition_failure(err: &azure_core::Error) -> bool {
+    let ErrorKind::HttpResponse { status, error_code } = err.kind() else {
+        return false;
+    };
+    matches!(
+        (u16::from(*status), error_code.as_deref()),
+        (412, Some("ConditionNotMet")) | (409, Some("BlobAlreadyExists"))
+    )
 }
 
 impl Handler<Option<Context>> for BlobstoreAzblobProvider {
@@ -602,3 +823,209 @@ impl Handler<Option<Context>> for BlobstoreAzblobProvider {
         .map_err(|err| format!("{err:#}")))
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use azure_core::request_options::LeaseDuration;
+    use azure_core::{StatusCode, Url};
+    use azure_storage::StorageCredentials;
+    use bindings::exports::wasmcloud::blobstore::presign;
+    use wasmcloud_test_util::testcontainers::{AsyncRunner as _, Azurite};
+
+    #[test]
+    fn precondition_failures() {
+        let error = |status, code: &str| {
+            azure_core::Error::new(
+                ErrorKind::HttpResponse {
+                    status,
+                    error_code: Some(code.into()),
+                },
+                "request failed",
+            )
+        };
+        assert!(is_precondition_failure(&error(
+            StatusCode::PreconditionFailed,
+            "ConditionNotMet"
+        )));
+        assert!(is_precondition_failure(&error(
+            StatusCode::Conflict,
+            "BlobAlreadyExists"
+        )));
+        assert!(!is_precondition_failure(&error(
+            StatusCode::PreconditionFailed,
+            "LeaseIdMissing"
+        )));
+        assert!(!is_precondition_failure(&error(
+            StatusCode::Conflict,
+            "LeaseAlreadyPresent"
+        )));
+        assert!(!is_precondition_failure(&azure_core::Error::new(
+            ErrorKind::Io,
+            "connection reset"
+        )));
+    }
+
+    #[tokio::test]
+    async fn presigned_urls() {
+        let provider = BlobstoreAzblobProvider::default();
+        provider.config.write().await.insert(
+            "component".into(),
+            ClientBuilder::with_location(
+                CloudLocation::Emulator {
+                    address: "127.0.0.1".into(),
+                    port: 10000,
+                },
+                StorageCredentials::emulator(),
+            )
+            .blob_service_client(),
+        );
+        let cx = || {
+            Some(Context {
+                component: Some("component".into()),
+                ..Default::default()
+            })
+        };
+        let query = |url: String| -> HashMap<String, String> {
+            let url = Url::parse(&url).expect("invalid URL");
+            assert_eq!(url.host_str(), Some("127.0.0.1"));
+            assert_eq!(url.port(), Some(10000));
+            assert_eq!(url.path(), "/devstoreaccount1/container/dir/object");
+            url.query_pairs().into_owned().collect()
+        };
+
+        let now = OffsetDateTime::now_utc();
+        let get = query(
+            presign::Handler::presign_get(
+                &provider,
+                cx(),
+                "container".into(),
+                "dir/object".into(),
+                300,
+            )
+            .await
+            .unwrap()
+            .expect("failed to presign GET"),
+        );
+        let put = query(
+            presign::Handler::presign_put(
+                &provider,
+                cx(),
+                "container".into(),
+                "dir/object".into(),
+                3600,
+            )
+            .await
+            .unwrap()
+            .expect("failed to presign PUT"),
+        );
+        for (query, permissions, expires_in) in [(&get, "r", 300), (&put, "cw", 3600)] {
+            assert_eq!(query["sp"], permissions);
+            assert_eq!(query["sr"], "b");
+            assert!(!query["sig"].is_empty());
+            let expiry = azure_core::date::parse_rfc3339(&query["se"]).expect("invalid expiry");
+            let expected = now + Duration::from_secs(expires_in);
+            assert!(
+                (expiry - expected).abs() < time::Duration::seconds(5),
+                "{expiry} is not close to {expected}"
+            );
+        }
+
+        // Links are required to presign URLs
+        assert!(presign::Handler::presign_get(
+            &provider,
+            None,
+            "container".into(),
+            "dir/object".into(),
+            300
+        )
+        .await
+        .unwrap()
+        .is_err());
+    }
+
+    // This test is ignored by default as it requires a container runtime to be installed
+    // to run the testcontainer. In GitHub Actions CI, this is only works on `linux`
+    #[ignore]
+    #[tokio::test]
+    async fn conditional_writes() {
+        let azurite = Azurite::default()
+            .start()
+            .await
+            .expect("should start azurite");
+        let client = ClientBuilder::with_location(
+            CloudLocation::Emulator {
+                address: azurite.get_host().await.unwrap().to_string(),
+                port: azurite.get_host_port_ipv4(10000).await.unwrap(),
+            },
+            StorageCredentials::emulator(),
+        )
+        .blob_service_client();
+        client
+            .container_client("conditional")
+            .create()
+            .await
+            .expect("should create container");
+
+        let provider = BlobstoreAzblobProvider::default();
+        provider
+            .config
+            .write()
+            .await
+            .insert("component".into(), client.clone());
+        let write = |data: &'static str, precondition| {
+            metadata::Handler::write_object(
+                &provider,
+                Some(Context {
+                    component: Some("component".into()),
+                    ..Default::default()
+                }),
+                "conditional".into(),
+                "object".into(),
+                Bytes::from(data),
+                metadata::WriteOptions {
+                    content_type: None,
+                    metadata: vec![],
+                    precondition,
+                },
+            )
+        };
+
+        let etag = write("1", Some(metadata::Precondition::IfNoneMatch("*".into())))
+            .await
+            .unwrap()
+            .expect("blob should have been created");
+        assert!(matches!(
+            write("2", Some(metadata::Precondition::IfNoneMatch("*".into())))
+                .await
+                .unwrap(),
+            Err(metadata::WriteError::PreconditionFailed)
+        ));
+        assert!(matches!(
+            write("2", Some(metadata::Precondition::IfMatch("\"0x0\"".into())))
+                .await
+                .unwrap(),
+            Err(metadata::WriteError::PreconditionFailed)
+        ));
+        let new_etag = write("2", Some(metadata::Precondition::IfMatch(etag.clone())))
+            .await
+            .unwrap()
+            .expect("blob should have been replaced");
+        assert_ne!(new_etag, etag);
+
+        // A lease on the blob is not a precondition failure
+        client
+            .container_client("conditional")
+            .blob_client("object")
+            .acquire_lease(LeaseDuration::Infinite)
+            .await
+            .expect("should acquire lease");
+        assert!(matches!(
+            write("3", Some(metadata::Precondition::IfMatch(new_etag)))
+                .await
+                .unwrap(),
+            Err(metadata::WriteError::Other(..))
+        ));
+    }
+}
@@ -22,16 +22,38 @@ use tokio::sync::{mpsc, RwLock};
 use tokio_stream::wrappers::{ReadDirStream, ReceiverStream};
 use tokio_util::io::{ReaderStream, StreamReader};
 use tracing::{debug, error, info, instrument, trace};
+use url::Url;
 use wasmcloud_provider_sdk::{
-    get_connection, initialize_observability, propagate_trace_for_ctx, run_provider,
-    serve_provider_exports, Context, LinkConfig, LinkDeleteInfo, Provider,
+    get_connection, initialize_observability, load_host_data, propagate_trace_for_ctx,
+    run_provider, serve_provider_exports, Context, HostData, LinkConfig, LinkDeleteInfo, Provider,
 };
 use wrpc_interface_blobstore::bindings::{
     exports::wrpc::blobstore::blobstore::Handler,
-    serve,
     wrpc::blobstore::types::{ContainerMetadata, ObjectId, ObjectMetadata},
 };
 
+use metadata::{ObjectLocks, METADATA_DIR};
+use presign::Presigner;
+
+mod metadata;
+mod presign;
+
+mod bindings {
+    wit_bindgen_wrpc::generate!({
+        world: "interfaces",
+        with: {
+            "wasi:blobstore/types@0.2.0-draft": wrpc_interface_blobstore::bindings::wasi::blobstore::types,
+            "wasi:io/error@0.2.0": wrpc_interface_blobstore::bindings::wasi::io::error,
+            "wasi:io/poll@0.2.0": wrpc_interface_blobstore::bindings::wasi::io::poll,
+            "wasi:io/streams@0.2.0": wrpc_interface_blobstore::bindings::wasi::io::streams,
+            "wrpc:blobstore/blobstore@0.2.0": wrpc_interface_blobstore::bindings::exports::wrpc::blobstore::blobstore,
+            "wrpc:blobstore/types@0.2.0": wrpc_interface_blobstore::bindings::wrpc::blobstore::types,
+            "wasmcloud:blobstore/metadata@0.1.0-draft": generate,
+            "wasmcloud:blobstore/presign@0.1.0-draft": generate,
+        },
+    });
+}
+
 #[derive(Default, Debug, Clone)]
 struct FsProviderConfig {
     root: Arc<PathBuf>,
@@ -41,6 +63,10 @@ struct FsProviderConfig {
 #[derive(Default, Clone)]
 pub struct FsProvider {
     config: Arc<RwLock<HashMap<String, FsProviderConfig>>>,
+    /// Signer of presigned URLs, present when the presigned URL server is enabled
+    presigner: Option<Arc<Presigner>>,
+    /// Locks serializing writes of the same object
+    object_locks: Arc<ObjectLocks>,
 }
 
 pub async fn run() -> anyhow::Result<()> {
@@ -54,17 +80,45 @@ impl FsProvider {
             std::env::var_os("PROVIDER_BLOBSTORE_FS_FLAMEGRAPH_PATH")
         );
 
-        let provider = Self::default();
+        let HostData { config, .. } = load_host_data().context("failed to load host data")?;
+        let mut provider = Self::default();
+        let listener = if let Some(addr) = config.get("PRESIGN_ADDRESS") {
+            let listener = tokio::net::TcpListener::bind(addr)
+                .await
+                .with_context(|| format!("failed to bind presigned URL server on [{addr}]"))?;
+            let base_url = match config.get("PRESIGN_BASE_URL") {
+                Some(url) => url.parse(),
+                None => Url::parse(&format!("http://{}", listener.local_addr()?)),
+            }
+            .context("failed to parse presign base URL")?;
+            info!(%base_url, "serving presigned URLs");
+            provider.presigner = Some(Arc::new(Presigner::new(base_url)?));
+            Some(listener)
+        } else {
+            None
+        };
         let shutdown = run_provider(provider.clone(), "blobstore-fs-provider")
             .await
             .context("failed to run provider")?;
+        let presign_server = listener.map(|listener| {
+            let provider = provider.clone();
+            tokio::spawn(async move {
+                if let Err(err) = presign::serve(listener, provider).await {
+                    error!(?err, "presigned URL server failed");
+                }
+            })
+        });
         let connection = get_connection();
         let wrpc = connection
             .get_wrpc_client(connection.provider_key())
             .await?;
-        serve_provider_exports(&wrpc, provider, shutdown, serve)
+        let res = serve_provider_exports(&wrpc, provider, shutdown, bindings::serve)
             .await
-            .context("failed to serve provider exports")
+            .context("failed to serve provider exports");
+        if let Some(presign_server) = presign_server {
+            presign_server.abort();
+        }
+        res
     }
 }
 
@@ -98,21 +152,72 @@ fn resolve_subpath(root: &Path, path: impl AsRef<Path>) -> Result<PathBuf, std::
     Ok(joined)
 }
 
+/// Resolve the path of a container below the given root, rejecting the metadata directory
+fn resolve_container(root: &Path, container: impl AsRef<Path>) -> anyhow::Result<PathBuf> {
+    let path = resolve_subpath(root, container).context("failed to resolve subpath")?;
+    if path.starts_with(root.join(METADATA_DIR)) {
+        bail!("container name `{METADATA_DIR}` is reserved");
+    }
+    Ok(path)
+}
+
 impl FsProvider {
     async fn get_root(&self, context: Option<Context>) -> anyhow::Result<Arc<PathBuf>> {
         if let Some(ref source_id) = context.and_then(|Context { component, .. }| component) {
-            self.config
-                .read()
-                .await
-                .get(source_id)
-                .with_context(|| format!("failed to lookup {source_id} configuration"))
-                .map(|FsProviderConfig { root }| Arc::clone(root))
+            self.get_root_for_component(source_id).await
         } else {
             // TODO: Support a default here
             bail!("failed to lookup invocation source ID")
         }
     }
 
+    async fn get_root_for_component(&self, source_id: &str) -> anyhow::Result<Arc<PathBuf>> {
+        self.config
+            .read()
+            .await
+            .get(source_id)
+            .with_context(|| format!("failed to lookup {source_id} configuration"))
+            .map(|FsProviderConfig { root }| Arc::clone(root))
+    }
+
+    /// Generate a presigned URL granting `method` access to an object
+    async fn presign(
+        &self,
+        context: Option<Context>,
+        method: &axum::http::Method,
+        container: String,
+        object: String,
+        expires_in_secs: u64,
+    ) -> anyhow::Result<String> {
+        let presigner = self
+            .presigner
+            .as_ref()
+            .context("presigned URLs are disabled, set `PRESIGN_ADDRESS` to enable them")?;
+        let Some(source_id) = context
+            .as_ref()
+            .and_then(|Context { component, .. }| component.clone())
+        else {
+            bail!("failed to lookup invocation source ID")
+        };
+        // Reject objects outside of the root before signing
+        self.get_object(
+            context,
+            ObjectId {
+                container: container.clone(),
+                object: object.clone(),
+            },
+        )
+        .await?;
+        let url = presigner.presign(
+            method,
+            &source_id,
+            &container,
+            &object,
+            Duration::from_secs(expires_in_secs),
+        )?;
+        Ok(url.to_string())
+    }
+
     async fn get_container(
         &self,
         context: Option<Context>,
@@ -122,7 +227,7 @@ impl FsProvider {
             .get_root(context)
             .await
             .context("failed to get container root")?;
-        resolve_subpath(&root, container).context("failed to resolve subpath")
+        resolve_container(&root, container)
     }
 
     async fn get_object(
@@ -138,6 +243,110 @@ impl FsProvider {
     }
 }
 
+impl bindings::exports::wasmcloud::blobstore::presign::Handler<Option<Context>> for FsProvider {
+    #[instrument(level = "trace", skip(self))]
+    async fn presign_get(
+        &self,
+        cx: Option<Context>,
+        container: String,
+        object: String,
+        expires_in_secs: u64,
+    ) -> anyhow::Result<Result<String, String>> {
+        Ok(async {
+            propagate_trace_for_ctx!(cx);
+            self.presign(
+                cx,
+                &axum::http::Method::GET,
+                container,
+                object,
+                expires_in_secs,
+            )
+            .await
+        }
+        .await
+        .map_err(|err| format!("{err:#}")))
+    }
+
+    #[instrument(level = "trace", skip(self))]
+    async fn presign_put(
+        &self,
+        cx: Option<Context>,
+        container: String,
+        object: String,
+        expires_in_secs: u64,
+    ) -> anyhow::Result<Result<String, String>> {
+        Ok(async {
+            propagate_trace_for_ctx!(cx);
+            self.presign(
+                cx,
+                &axum::http::Method::PUT,
+                container,
+                object,
+                expires_in_secs,
+            )
+            .await
+        }
+        .await
+        .map_err(|err| format!("{err:#}")))
+    }
+}
+
+impl bindings::exports::wasmcloud::blobstore::metadata::Handler<Option<Context>> for FsProvider {
+    #[instrument(level = "trace", skip(self))]
+    async fn get_object_metadata(
+        &self,
+        cx: Option<Context>,
+        container: String,
+        object: String,
+    ) -> anyhow::Result<
+        Result<bindings::exports::wasmcloud::blobstore::metadata::ObjectMetadata, String>,
+    > {
+        Ok(async {
+            propagate_trace_for_ctx!(cx);
+            let root = self.get_root(cx).await.context("failed to get root")?;
+            let path = resolve_container(&root, &container)
+                .and_then(|dir| resolve_subpath(&dir, &object).map_err(Into::into))
+                .context("failed to resolve object path")?;
+            metadata::read(&root, &container, &object, &path).await
+        }
+        .await
+        .map_err(|err| format!("{err:#}")))
+    }
+
+    #[instrument(level = "trace", skip(self, data))]
+    async fn write_object(
+        &self,
+        cx: Option<Context>,
+        container: String,
+        object: String,
+        data: Bytes,
+        options: bindings::exports::wasmcloud::blobstore::metadata::WriteOptions,
+    ) -> anyhow::Result<Result<String, bindings::exports::wasmcloud::blobstore::metadata::WriteError>>
+    {
+        propagate_trace_for_ctx!(cx);
+        let resolved = async {
+            let root = self.get_root(cx).await.context("failed to get root")?;
+            let path = resolve_container(&root, &container)
+                .and_then(|dir| resolve_subpath(&dir, &object).map_err(Into::into))
+                .context("failed to resolve object path")?;
+            anyhow::Ok((root, path))
+        }
+        .await;
+        let (root, path) = match resolved {
+            Ok(resolved) => resolved,
+            Err(err) => {
+                return Ok(Err(
+                    bindings::exports::wasmcloud::blobstore::metadata::WriteError::Other(format!(
+                        "{err:#}"
+                    )),
+                ))
+            }
+        };
+        let _lock = self.object_locks.lock(&path).await;
+        Ok(metadata::write(&root, &container, &object, &path, data, options).await)
+    }
+}
+
 impl Handler<Option<Context>> for FsProvider {
     #[instrument(level = "trace", skip(self))]
     async fn clear_container(
@@ -147,6 +356,8 @@ impl Handler<Option<Context>> for FsProvider {
     ) -> anyhow::Result<Result<(), String>> {
         Ok(async {
             propagate_trace_for_ctx!(cx);
+            let root = self.get_root(cx.clone()).await?;
+            metadata::remove_container(&root, &name).await?;
             let path = self.get_container(cx, name).await?;
             debug!("read directory at `{}`", path.display());
             let dir = fs::read_dir(path).await.context("failed to read path")?;
@@ -218,6 +429,8 @@ impl Handler<Option<Context>> for FsProvider {
     ) -> anyhow::Result<Result<(), String>> {
         Ok(async {
             propagate_trace_for_ctx!(cx);
+            let root = self.get_root(cx.clone()).await?;
+            metadata::remove_container(&root, &name).await?;
             let path = self.get_container(cx, name).await?;
             fs::remove_dir_all(path)
                 .await
@@ -327,18 +540,26 @@ impl Handler<Option<Context>> for FsProvider {
         Ok(async {
             propagate_trace_for_ctx!(cx);
             let root = self.get_root(cx).await.context("failed to get root")?;
-            let src_container = resolve_subpath(&root, src.container)
+            let src_container = resolve_container(&root, &src.container)
                 .context("failed to resolve source container path")?;
-            let src = resolve_subpath(&src_container, src.object)
+            let src_path = resolve_subpath(&src_container, &src.object)
                 .context("failed to resolve source object path")?;
 
-            let dest_container = resolve_subpath(&root, dest.container)
+            let dest_container = resolve_container(&root, &dest.container)
                 .context("failed to resolve destination container path")?;
-            let dest = resolve_subpath(&dest_container, dest.object)
+            let dest_path = resolve_subpath(&dest_container, &dest.object)
                 .context("failed to resolve destination object path")?;
-            debug!("copy `{}` to `{}`", src.display(), dest.display());
-            fs::copy(src, dest).await.context("failed to copy")?;
-            anyhow::Ok(())
+            let _lock = self.object_locks.lock(&dest_path).await;
+            debug!("copy `{}` to `{}`", src_path.display(), dest_path.display());
+            fs::copy(&src_path, &dest_path)
+                .await
+                .context("failed to copy")?;
+            metadata::copy(
+                &root,
+                (&src.container, &src.object, &src_path),
+                (&dest.container, &dest.object, &dest_path),
+            )
+            .await
         }
         .await
         .map_err(|err| format!("{err:#}")))
@@ -352,7 +573,12 @@ impl Handler<Option<Context>> for FsProvider {
     ) -> anyhow::Result<Result<(), String>> {
         Ok(async {
             propagate_trace_for_ctx!(cx);
+            let root = self.get_root(cx.clone()).await?;
+            let container = id.container.clone();
+            let object = id.object.clone();
             let path = self.get_object(cx, id).await?;
+            let _lock = self.object_locks.lock(&path).await;
+            metadata::remove(&root, &container, &object).await?;
             debug!("remove file at `{}`", path.display());
             match fs::remove_file(&path).await {
                 Ok(()) => Ok(()),
@@ -376,10 +602,14 @@ impl Handler<Option<Context>> for FsProvider {
     ) -> anyhow::Result<Result<(), String>> {
         Ok(async {
             propagate_trace_for_ctx!(cx);
-            let container = self.get_container(cx, container).await?;
+            let root = self.get_root(cx.clone()).await?;
+            let container_name = container;
+            let container = self.get_container(cx, &container_name).await?;
             for name in objects {
                 let path =
-                    resolve_subpath(&container, name).context("failed to resolve object path")?;
+                    resolve_subpath(&container, &name).context("failed to resolve object path")?;
+                let _lock = self.object_locks.lock(&path).await;
+                metadata::remove(&root, &container_name, &name).await?;
                 debug!("remove file at `{}`", path.display());
                 match fs::remove_file(&path).await {
                     Ok(()) => Ok(()),
@@ -519,19 +749,29 @@ impl Handler<Option<Context>> for FsProvider {
         Ok(async {
             propagate_trace_for_ctx!(cx);
             let root = self.get_root(cx).await.context("failed to get root")?;
-            let src_container = resolve_subpath(&root, src.container)
+            let src_container = resolve_container(&root, &src.container)
                 .context("failed to resolve source container path")?;
-            let src = resolve_subpath(&src_container, src.object)
+            let src_path = resolve_subpath(&src_container, &src.object)
                 .context("failed to resolve source object path")?;
 
-            let dest_container = resolve_subpath(&root, dest.container)
+            let dest_container = resolve_container(&root, &dest.container)
                 .context("failed to resolve destination container path")?;
-            let dest = resolve_subpath(&dest_container, dest.object)
+            let dest_path = resolve_subpath(&dest_container, &dest.object)
                 .context("failed to resolve destination object path")?;
-            debug!("copy `{}` to `{}`", src.display(), dest.display());
-            fs::copy(&src, dest).await.context("failed to copy")?;
-            debug!("remove `{}`", src.display());
-            fs::remove_file(src)
+            let _lock = self.object_locks.lock(&dest_path).await;
+            debug!("copy `{}` to `{}`", src_path.display(), dest_path.display());
+            fs::copy(&src_path, &dest_path)
+                .await
+                .context("failed to copy")?;
+            metadata::copy(
+                &root,
+                (&src.container, &src.object, &src_path),
+                (&dest.container, &dest.object, &dest_path),
+            )
+            .await?;
+            metadata::remove(&root, &src.container, &src.object).await?;
+            debug!("remove `{}`", src_path.display());
+            fs::remove_file(src_path)
                 .await
                 .context("failed to remove source")
         }
@@ -549,7 +789,14 @@ impl Handler<Option<Context>> for FsProvider {
     {
         Ok(async {
             propagate_trace_for_ctx!(cx);
+            let root = self.get_root(cx.clone()).await?;
+            let container = id.container.clone();
+            let object = id.object.clone();
             let path = self.get_object(cx, id).await?;
+            // Held until the data is written, so that concurrent writes are not interleaved
+            let lock = self.object_locks.lock(&path).await;
+            // Metadata is replaced on every write, like in other blobstores
+            metadata::remove(&root, &container, &object).await?;
             if let Some(parent) = path.parent() {
                 info!(parent = ?parent.display(), "creating directory");
                 fs::create_dir_all(parent)
@@ -576,6 +823,7 @@ impl Handler<Option<Context>> for FsProvider {
                 .context("failed to write file")
                 .map_err(|err| format!("{err:#}"))?;
                 debug!(n, path = ?path.display(), "finished writing file");
+                drop(lock);
                 Ok(())
             }) as Pin<Box<dyn Future<Output = _> + Send>>)
         }
@@ -689,7 +937,10 @@ mod tests {
                 root: Arc::new(root_path.clone()),
             },
         );
-        let provider = FsProvider { config };
+        let provider = FsProvider {
+            config,
+            ..Default::default()
+        };
 
         // Create a mock Context and ObjectId
         let context = Some(Context {
@@ -726,4 +977,74 @@ mod tests {
         let contents = tokio::fs::read_to_string(file_path).await.unwrap();
         assert_eq!(contents, "Hello, world!");
     }
+
+    /// Ensure that conditional writes wait for streaming writes of the same object to finish
+    #[tokio::test]
+    async fn conditional_write_waits_for_stream() {
+        use bindings::exports::wasmcloud::blobstore::metadata::{
+            Handler as _, Precondition, WriteOptions,
+        };
+
+        let temp_dir = tempdir().unwrap();
+        let root_path = temp_dir.path().to_path_buf();
+        let provider = FsProvider::default();
+        provider.config.write().await.insert(
+            "test_source".to_string(),
+            FsProviderConfig {
+                root: Arc::new(root_path.clone()),
+            },
+        );
+        let context = || {
+            Some(Context {
+                component: Some("test_source".to_string()),
+                ..Default::default()
+            })
+        };
+
+        let (tx, rx) = mpsc::channel(1);
+        let write = provider
+            .write_container_data(
+                context(),
+                ObjectId {
+                    container: "test_container".to_string(),
+                    object: "object".to_string(),
+                },
+                Box::pin(ReceiverStream::new(rx)),
+            )
+            .await
+            .unwrap()
+            .unwrap();
+        let write = tokio::spawn(write);
+        tx.send(Bytes::from("Hello, ")).await.unwrap();
+
+        let conditional = tokio::spawn({
+            let provider = provider.clone();
+            async move {
+                provider
+                    .write_object(
+                        context(),
+                        "test_container".to_string(),
+                        "object".to_string(),
+                        Bytes::from("replaced"),
+                        WriteOptions {
+                            content_type: None,
+                            metadata: vec![],
+                            precondition: Some(Precondition::IfNoneMatch("\"etag\"".into())),
+                        },
+                    )
+                    .await
+            }
+        });
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert!(!conditional.is_finished());
+
+        tx.send(Bytes::from("world!")).await.unwrap();
+        drop(tx);
+        write.await.unwrap().unwrap();
+        conditional.await.unwrap().unwrap().unwrap();
+        let contents = tokio::fs::read_to_string(root_path.join("test_container/object"))
+            .await
+            .unwrap();
+        assert_eq!(contents, "replaced");
+    }
 }
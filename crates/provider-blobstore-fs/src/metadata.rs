@@ -0,0 +1,448 @@
+//! Object metadata and conditional writes.
+//!
+//! The file system has no notion of content types, user metadata or entity tags, so these are
+//! stored in a JSON sidecar file per object, kept in a separate [`METADATA_DIR`] tree below the
+//! root so that they never show up as objects. Objects written by [`write`] have an ETag derived
+//! from their contents, while for objects written any other way, it is derived from the
+//! modification time and size of the file.
+
+use std::collections::HashMap;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, PoisonError, Weak};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::{anyhow, bail, Context as _};
+use bytes::Bytes;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest as _, Sha256};
+use tokio::fs;
+use tokio::sync::{Mutex, OwnedMutexGuard};
+
+use crate::bindings::exports::wasmcloud::blobstore::metadata::{
+    ObjectMetadata, Precondition, WriteError, WriteOptions,
+};
+use crate::resolve_subpath;
+
+/// Directory below the root in which metadata is stored, which cannot be used as a container
+pub(crate) const METADATA_DIR: &str = ".metadata";
+
+/// Per-object locks serializing writes of the same object, so that a precondition checked by
+/// [`write`] cannot be invalidated by a concurrent write of the object.
+///
+/// Locks are keyed by the resolved path of the object and dropped once nobody holds or waits
+/// for them.
+#[derive(Debug, Default)]
+pub(crate) struct ObjectLocks(std::sync::Mutex<HashMap<PathBuf, Weak<Mutex<()>>>>);
+
+impl ObjectLocks {
+    /// Lock the object at `path`, waiting for writes of it in progress to finish
+    pub(crate) async fn lock(&self, path: &Path) -> OwnedMutexGuard<()> {
+        let lock = {
+            let mut locks = self.0.lock().unwrap_or_else(PoisonError::into_inner);
+            locks.retain(|_, lock| lock.strong_count() > 0);
+            if let Some(lock) = locks.get(path).and_then(Weak::upgrade) {
+                lock
+            } else {
+                let lock = Arc::default();
+                locks.insert(path.to_path_buf(), Arc::downgrade(&lock));
+                lock
+            }
+        };
+        lock.lock_owned().await
+    }
+
+    #[cfg(test)]
+    fn len(&self) -> usize {
+        self.0.lock().unwrap_or_else(PoisonError::into_inner).len()
+    }
+}
+
+/// Contents of a metadata sidecar file
+#[derive(Debug, Default, Deserialize, Serialize)]
+struct Sidecar {
+    content_type: Option<String>,
+    metadata: Vec<(String, String)>,
+    etag: String,
+    /// Size and modification time of the object when the sidecar was written, used to detect
+    /// whether the object has been modified without updating the sidecar since
+    size: u64,
+    modified_nanos: u128,
+}
+
+/// Resolve the path of the metadata directory of a container
+fn container_metadata_path(root: &Path, container: &str) -> io::Result<PathBuf> {
+    resolve_subpath(&root.join(METADATA_DIR), container)
+}
+
+/// Resolve the path of the sidecar file of an object
+fn sidecar_path(root: &Path, container: &str, object: &str) -> io::Result<PathBuf> {
+    let container = container_metadata_path(root, container)?;
+    resolve_subpath(&container, format!("{object}.json"))
+}
+
+fn modified_nanos(md: &std::fs::Metadata) -> u128 {
+    md.modified()
+        .ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map(|d| d.as_nanos())
+        .unwrap_or_default()
+}
+
+/// Compute the ETag of a file without a valid sidecar
+fn file_etag(md: &std::fs::Metadata) -> String {
+    format!("\"{:x}-{:x}\"", modified_nanos(md), md.len())
+}
+
+/// Read the sidecar of an object, returning `None` if there is none or it is out of date
+async fn read_sidecar(
+    root: &Path,
+    container: &str,
+    object: &str,
+    md: &std::fs::Metadata,
+) -> anyhow::Result<Option<Sidecar>> {
+    let path = sidecar_path(root, container, object).context("failed to resolve sidecar path")?;
+    let buf = match fs::read(&path).await {
+        Ok(buf) => buf,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(err) => {
+            return Err(anyhow!(err).context(format!("failed to read `{}`", path.display())))
+        }
+    };
+    let sidecar: Sidecar = serde_json::from_slice(&buf)
+        .with_context(|| format!("failed to parse `{}`", path.display()))?;
+    if sidecar.size != md.len() || sidecar.modified_nanos != modified_nanos(md) {
+        return Ok(None);
+    }
+    Ok(Some(sidecar))
+}
+
+/// Write the sidecar of an object, which must have been written already
+async fn write_sidecar(
+    root: &Path,
+    container: &str,
+    object: &str,
+    object_path: &Path,
+    mut sidecar: Sidecar,
+) -> anyhow::Result<()> {
+    let md = fs::metadata(object_path)
+        .await
+        .context("failed to lookup file metadata")?;
+    sidecar.size = md.len();
+    sidecar.modified_nanos = modified_nanos(&md);
+    let path = sidecar_path(root, container, object).context("failed to resolve sidecar path")?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .await
+            .context("failed to create metadata directory")?;
+    }
+    let buf = serde_json::to_vec(&sidecar).context("failed to encode metadata")?;
+    fs::write(&path, buf)
+        .await
+        .with_context(|| format!("failed to write `{}`", path.display()))
+}
+
+/// Get the metadata of the object at `path`
+pub(crate) async fn read(
+    root: &Path,
+    container: &str,
+    object: &str,
+    path: &Path,
+) -> anyhow::Result<ObjectMetadata> {
+    let md = fs::metadata(path)
+        .await
+        .context("failed to lookup file metadata")?;
+    if !md.is_file() {
+        bail!("`{}` is not a file", path.display())
+    }
+    let last_modified = md
+        .modified()
+        .ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+    let size = md.len();
+    let Sidecar {
+        content_type,
+        metadata,
+        etag,
+        ..
+    } = match read_sidecar(root, container, object, &md).await? {
+        Some(sidecar) => sidecar,
+        None => Sidecar {
+            etag: file_etag(&md),
+            ..Default::default()
+        },
+    };
+    Ok(ObjectMetadata {
+        size,
+        last_modified,
+        etag,
+        content_type,
+        metadata,
+    })
+}
+
+/// Check whether `precondition` holds for an object with the current ETag `etag`, which is
+/// `None` if the object does not exist
+fn precondition_holds(precondition: Option<&Precondition>, etag: Option<&str>) -> bool {
+    match (precondition, etag) {
+        (None, _) => true,
+        (Some(Precondition::IfMatch(_)), None) => false,
+        (Some(Precondition::IfMatch(expected)), Some(etag)) => expected == "*" || expected == etag,
+        (Some(Precondition::IfNoneMatch(_)), None) => true,
+        (Some(Precondition::IfNoneMatch(unexpected)), Some(etag)) => {
+            unexpected != "*" && unexpected != etag
+        }
+    }
+}
+
+/// Write the object at `path` with the given options, returning its new ETag.
+///
+/// Callers must hold the [`ObjectLocks`] lock of the object, since the precondition is checked
+/// before writing.
+pub(crate) async fn write(
+    root: &Path,
+    container: &str,
+    object: &str,
+    path: &Path,
+    data: Bytes,
+    WriteOptions {
+        content_type,
+        metadata,
+        precondition,
+    }: WriteOptions,
+) -> Result<String, WriteError> {
+    let other = |err: anyhow::Error| WriteError::Other(format!("{err:#}"));
+    let current = match fs::metadata(path).await {
+        Ok(md) => {
+            let etag = match read_sidecar(root, container, object, &md)
+                .await
+                .map_err(other)?
+            {
+                Some(Sidecar { etag, .. }) => etag,
+                None => file_etag(&md),
+            };
+            Some(etag)
+        }
+        Err(err) if err.kind() == io::ErrorKind::NotFound => None,
+        Err(err) => {
+            return Err(other(
+                anyhow!(err).context("failed to lookup file metadata"),
+            ))
+        }
+    };
+    if !precondition_holds(precondition.as_ref(), current.as_deref()) {
+        return Err(WriteError::PreconditionFailed);
+    }
+
+    let etag = format!("\"{}\"", hex::encode(Sha256::digest(&data)));
+    async {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .await
+                .context("failed to create parent directories")?;
+        }
+        // Write to a temporary file first, so that the object is replaced atomically
+        let tmp = root.join(METADATA_DIR).join(format!(
+            ".tmp-{:x}-{:x}",
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_nanos())
+                .unwrap_or_default(),
+            rand::random::<u64>(),
+        ));
+        fs::create_dir_all(root.join(METADATA_DIR))
+            .await
+            .context("failed to create metadata directory")?;
+        fs::write(&tmp, &data)
+            .await
+            .context("failed to write temporary file")?;
+        if let Err(err) = fs::rename(&tmp, path).await {
+            _ = fs::remove_file(&tmp).await;
+            return Err(anyhow!(err).context(format!("failed to write `{}`", path.display())));
+        }
+        write_sidecar(
+            root,
+            container,
+            object,
+            path,
+            Sidecar {
+                content_type,
+                metadata,
+                etag: etag.clone(),
+                ..Default::default()
+            },
+        )
+        .await
+    }
+    .await
+    .map_err(other)?;
+    Ok(etag)
+}
+
+/// Remove the metadata of an object, if any
+pub(crate) async fn remove(root: &Path, container: &str, object: &str) -> anyhow::Result<()> {
+    let path = sidecar_path(root, container, object).context("failed to resolve sidecar path")?;
+    match fs::remove_file(&path).await {
+        Ok(()) => Ok(()),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(()),
+        Err(err) => Err(anyhow!(err).context(format!("failed to remove `{}`", path.display()))),
+    }
+}
+
+/// Remove the metadata of all objects in a container
+pub(crate) async fn remove_container(root: &Path, container: &str) -> anyhow::Result<()> {
+    let path =
+        container_metadata_path(root, container).context("failed to resolve metadata path")?;
+    match fs::remove_dir_all(&path).await {
+        Ok(()) => Ok(()),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(()),
+        Err(err) => Err(anyhow!(err).context(format!("failed to remove `{}`", path.display()))),
+    }
+}
+
+/// Copy the metadata of an object to another one, which must have been copied already
+pub(crate) async fn copy(
+    root: &Path,
+    (src_container, src_object, src_path): (&str, &str, &Path),
+    (dest_container, dest_object, dest_path): (&str, &str, &Path),
+) -> anyhow::Result<()> {
+    let md = fs::metadata(src_path)
+        .await
+        .context("failed to lookup file metadata")?;
+    match read_sidecar(root, src_container, src_object, &md).await? {
+        Some(sidecar) => write_sidecar(root, dest_container, dest_object, dest_path, sidecar).await,
+        None => remove(root, dest_container, dest_object).await,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn preconditions() {
+        let if_match = |etag: &str| Some(Precondition::IfMatch(etag.into()));
+        let if_none_match = |etag: &str| Some(Precondition::IfNoneMatch(etag.into()));
+
+        assert!(precondition_holds(None, None));
+        assert!(precondition_holds(None, Some("\"a\"")));
+
+        assert!(precondition_holds(
+            if_match("\"a\"").as_ref(),
+            Some("\"a\"")
+        ));
+        assert!(precondition_holds(if_match("*").as_ref(), Some("\"a\"")));
+        assert!(!precondition_holds(
+            if_match("\"b\"").as_ref(),
+            Some("\"a\"")
+        ));
+        assert!(!precondition_holds(if_match("*").as_ref(), None));
+
+        assert!(precondition_holds(if_none_match("*").as_ref(), None));
+        assert!(!precondition_holds(
+            if_none_match("*").as_ref(),
+            Some("\"a\"")
+        ));
+        assert!(precondition_holds(
+            if_none_match("\"b\"").as_ref(),
+            Some("\"a\"")
+        ));
+        assert!(!precondition_holds(
+            if_none_match("\"a\"").as_ref(),
+            Some("\"a\"")
+        ));
+    }
+
+    #[tokio::test]
+    async fn object_locks() {
+        let locks = ObjectLocks::default();
+        let a = locks.lock(Path::new("/root/container/a")).await;
+        let _b = locks.lock(Path::new("/root/container/b")).await;
+        assert_eq!(locks.len(), 2);
+
+        // Locking the same object waits for the lock to be released
+        let mut waiting = Box::pin(locks.lock(Path::new("/root/container/a")));
+        assert!(futures::poll!(&mut waiting).is_pending());
+        drop(a);
+        let a = waiting.await;
+        drop(a);
+
+        // Released locks are removed
+        let _c = locks.lock(Path::new("/root/container/c")).await;
+        assert_eq!(locks.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn compare_and_swap() {
+        let root = tempfile::tempdir().unwrap();
+        let root = root.path();
+        fs::create_dir(root.join("container")).await.unwrap();
+        let path = root.join("container").join("doc");
+
+        let options = |precondition| WriteOptions {
+            content_type: Some("application/json".into()),
+            metadata: vec![("version".into(), "1".into())],
+            precondition,
+        };
+        let etag = write(
+            root,
+            "container",
+            "doc",
+            &path,
+            Bytes::from("{}"),
+            options(Some(Precondition::IfNoneMatch("*".into()))),
+        )
+        .await
+        .unwrap();
+
+        let md = read(root, "container", "doc", &path).await.unwrap();
+        assert_eq!(md.etag, etag);
+        assert_eq!(md.size, 2);
+        assert_eq!(md.content_type.as_deref(), Some("application/json"));
+        assert_eq!(md.metadata, [("version".into(), "1".into())]);
+
+        assert!(matches!(
+            write(
+                root,
+                "container",
+                "doc",
+                &path,
+                Bytes::from("{\"a\":1}"),
+                options(Some(Precondition::IfNoneMatch("*".into()))),
+            )
+            .await,
+            Err(WriteError::PreconditionFailed)
+        ));
+        let new_etag = write(
+            root,
+            "container",
+            "doc",
+            &path,
+            Bytes::from("{\"a\":1}"),
+            options(Some(Precondition::IfMatch(etag.clone()))),
+        )
+        .await
+        .unwrap();
+        assert_ne!(new_etag, etag);
+        assert!(matches!(
+            write(
+                root,
+                "container",
+                "doc",
+                &path,
+                Bytes::from("{\"a\":2}"),
+                options(Some(Precondition::IfMatch(etag))),
+            )
+            .await,
+            Err(WriteError::PreconditionFailed)
+        ));
+
+        // Writing the object by other means invalidates the metadata
+        fs::write(&path, "{\"a\":30}").await.unwrap();
+        let md = read(root, "container", "doc", &path).await.unwrap();
+        assert_ne!(md.etag, new_etag);
+        assert_eq!(md.content_type, None);
+    }
+}
@@ -0,0 +1,346 @@
+//! Presigned URLs for objects stored by the provider.
+//!
+//! URLs point at a small HTTP server run by the provider, which serves `GET` and `PUT` requests
+//! for `/<component-id>/<container>/<object>` carrying an `expires` timestamp and a `signature`
+//! computed with HMAC-SHA256 over the method, path and expiry. The signing key is generated when
+//! the provider starts, so URLs do not remain valid across provider restarts.
+
+use core::time::Duration;
+
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::{ensure, Context as _};
+use axum::body::Body;
+use axum::extract::{Path, Query, State};
+use axum::http::{Method, StatusCode};
+use axum::routing::get;
+use axum::Router;
+use futures::TryStreamExt as _;
+use hmac::{Hmac, Mac as _};
+use serde::Deserialize;
+use sha2::Sha256;
+use tokio::fs::File;
+use tokio::io;
+use tokio::net::TcpListener;
+use tokio_util::io::{ReaderStream, StreamReader};
+use tracing::{debug, error};
+use url::Url;
+
+use crate::{metadata, resolve_container, resolve_subpath, FsProvider};
+
+/// Signs and verifies presigned URLs
+pub(crate) struct Presigner {
+    key: [u8; 32],
+    base_url: Url,
+}
+
+impl Presigner {
+    /// Construct a [`Presigner`] issuing URLs relative to `base_url`, using a random key
+    pub(crate) fn new(base_url: Url) -> anyhow::Result<Self> {
+        ensure!(
+            !base_url.cannot_be_a_base(),
+            "presign base URL [{base_url}] cannot be a base"
+        );
+        Ok(Self {
+            key: rand::random(),
+            base_url,
+        })
+    }
+
+    /// Compute the MAC over a request for an object
+    fn mac(
+        &self,
+        method: &Method,
+        component: &str,
+        container: &str,
+        object: &str,
+        expires: u64,
+    ) -> Hmac<Sha256> {
+        let mut mac =
+            Hmac::<Sha256>::new_from_slice(&self.key).expect("HMAC accepts keys of any size");
+        for part in [method.as_str(), component, container, object] {
+            mac.update(part.as_bytes());
+            mac.update(b"\n");
+        }
+        mac.update(expires.to_string().as_bytes());
+        mac
+    }
+
+    /// Generate a URL granting `method` access to an object until `expires_in` from now
+    pub(crate) fn presign(
+        &self,
+        method: &Method,
+        component: &str,
+        container: &str,
+        object: &str,
+        expires_in: Duration,
+    ) -> anyhow::Result<Url> {
+        let expires = unix_now()
+            .checked_add(expires_in.as_secs())
+            .context("expiry overflows")?;
+        let signature = self
+            .mac(method, component, container, object, expires)
+            .finalize()
+            .into_bytes();
+        let mut url = self.base_url.clone();
+        url.path_segments_mut()
+            .map_err(|()| anyhow::anyhow!("presign base URL cannot be a base"))?
+            .pop_if_empty()
+            .extend([component, container, object]);
+        url.query_pairs_mut()
+            .append_pair("expires", &expires.to_string())
+            .append_pair("signature", &hex::encode(signature));
+        Ok(url)
+    }
+
+    /// Verify that `signature` grants `method` access to an object and has not expired
+    pub(crate) fn verify(
+        &self,
+        method: &Method,
+        component: &str,
+        container: &str,
+        object: &str,
+        Signature { expires, signature }: &Signature,
+    ) -> bool {
+        if *expires < unix_now() {
+            return false;
+        }
+        let Ok(signature) = hex::decode(signature) else {
+            return false;
+        };
+        self.mac(method, component, container, object, *expires)
+            .verify_slice(&signature)
+            .is_ok()
+    }
+}
+
+/// Query parameters of a presigned URL
+#[derive(Debug, Deserialize)]
+pub(crate) struct Signature {
+    expires: u64,
+    signature: String,
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+type ObjectPath = Path<(String, String, String)>;
+
+/// Serve presigned URLs on `listener` until the task is aborted
+pub(crate) async fn serve(listener: TcpListener, provider: FsProvider) -> anyhow::Result<()> {
+    let router = Router::new()
+        .route(
+            "/:component/:container/:object",
+            get(get_object).put(put_object),
+        )
+        .with_state(provider);
+    axum::serve(listener, router)
+        .await
+        .context("failed to serve presigned URLs")
+}
+
+/// Verify the request and resolve the root of the component and the path of the object it
+/// refers to
+async fn resolve_object(
+    provider: &FsProvider,
+    method: &Method,
+    (component, container, object): &(String, String, String),
+    signature: &Signature,
+) -> Result<(Arc<PathBuf>, PathBuf), StatusCode> {
+    let presigner = provider.presigner.as_ref().ok_or(StatusCode::NOT_FOUND)?;
+    if !presigner.verify(method, component, container, object, signature) {
+        debug!(component, container, object, "rejected presigned URL");
+        return Err(StatusCode::FORBIDDEN);
+    }
+    let root = provider
+        .get_root_for_component(component)
+        .await
+        .map_err(|_| StatusCode::NOT_FOUND)?;
+    let path = resolve_container(&root, container)
+        .and_then(|container| resolve_subpath(&container, object).map_err(Into::into))
+        .map_err(|_| StatusCode::FORBIDDEN)?;
+    Ok((root, path))
+}
+
+async fn get_object(
+    State(provider): State<FsProvider>,
+    Path(path): ObjectPath,
+    Query(signature): Query<Signature>,
+) -> Result<Body, StatusCode> {
+    let (_, path) = resolve_object(&provider, &Method::GET, &path, &signature).await?;
+    let file = File::open(&path).await.map_err(|err| {
+        if err.kind() == io::ErrorKind::NotFound {
+            StatusCode::NOT_FOUND
+        } else {
+            error!(?err, path = %path.display(), "failed to open object");
+            StatusCode::INTERNAL_SERVER_ERROR
+        }
+    })?;
+    Ok(Body::from_stream(ReaderStream::new(file)))
+}
+
+async fn put_object(
+    State(provider): State<FsProvider>,
+    Path(object): ObjectPath,
+    Query(signature): Query<Signature>,
+    body: Body,
+) -> Result<StatusCode, StatusCode> {
+    let (root, path) = resolve_object(&provider, &Method::PUT, &object, &signature).await?;
+    let (_, container, object) = &object;
+    let _lock = provider.object_locks.lock(&path).await;
+    metadata::remove(&root, container, object)
+        .await
+        .map_err(|err| {
+            error!(?err, path = %path.display(), "failed to remove object metadata");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+    if let Some(parent) = path.parent() {
+        tokio::fs::create_dir_all(parent).await.map_err(|err| {
+            error!(?err, path = %path.display(), "failed to create parent directories");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+    }
+    let mut file = File::create(&path).await.map_err(|err| {
+        if err.kind() == io::ErrorKind::NotFound {
+            StatusCode::NOT_FOUND
+        } else {
+            error!(?err, path = %path.display(), "failed to create object");
+            StatusCode::INTERNAL_SERVER_ERROR
+        }
+    })?;
+    let mut body = StreamReader::new(body.into_data_stream().map_err(io::Error::other));
+    io::copy(&mut body, &mut file).await.map_err(|err| {
+        error!(?err, path = %path.display(), "failed to write object");
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    Ok(StatusCode::OK)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn query(url: &Url) -> Signature {
+        let mut expires = None;
+        let mut signature = None;
+        for (k, v) in url.query_pairs() {
+            match k.as_ref() {
+                "expires" => expires = v.parse().ok(),
+                "signature" => signature = Some(v.into_owned()),
+                _ => {}
+            }
+        }
+        Signature {
+            expires: expires.unwrap(),
+            signature: signature.unwrap(),
+        }
+    }
+
+    #[test]
+    fn presigned_url_verifies() {
+        let presigner = Presigner::new("http://localhost:8001/blobs/".parse().unwrap()).unwrap();
+        let url = presigner
+            .presign(
+                &Method::GET,
+                "component",
+                "container",
+                "dir/object",
+                Duration::from_secs(60),
+            )
+            .unwrap();
+        assert_eq!(url.path(), "/blobs/component/container/dir%2Fobject");
+        let signature = query(&url);
+        assert!(presigner.verify(
+            &Method::GET,
+            "component",
+            "container",
+            "dir/object",
+            &signature
+        ));
+        assert!(!presigner.verify(
+            &Method::PUT,
+            "component",
+            "container",
+            "dir/object",
+            &signature
+        ));
+        assert!(!presigner.verify(&Method::GET, "component", "container", "other", &signature));
+        let other = Presigner::new("http://localhost:8001".parse().unwrap()).unwrap();
+        assert!(!other.verify(
+            &Method::GET,
+            "component",
+            "container",
+            "dir/object",
+            &signature
+        ));
+    }
+
+    #[test]
+    fn expired_url_is_rejected() {
+        let presigner = Presigner::new("http://localhost:8001".parse().unwrap()).unwrap();
+        let expires = unix_now() - 1;
+        let signature = Signature {
+            expires,
+            signature: hex::encode(
+                presigner
+                    .mac(&Method::PUT, "component", "container", "object", expires)
+                    .finalize()
+                    .into_bytes(),
+            ),
+        };
+        assert!(!presigner.verify(&Method::PUT, "component", "container", "object", &signature));
+    }
+
+    #[tokio::test]
+    async fn presigned_put_creates_parent_directories() {
+        let root = tempfile::tempdir().unwrap();
+        let provider = FsProvider {
+            presigner: Some(Arc::new(
+                Presigner::new("http://localhost:8001".parse().unwrap()).unwrap(),
+            )),
+            ..Default::default()
+        };
+        provider.config.write().await.insert(
+            "component".to_string(),
+            crate::FsProviderConfig {
+                root: Arc::new(root.path().to_path_buf()),
+            },
+        );
+        let url = provider
+            .presigner
+            .as_ref()
+            .unwrap()
+            .presign(
+                &Method::PUT,
+                "component",
+                "container",
+                "dir/nested/object",
+                Duration::from_secs(60),
+            )
+            .unwrap();
+
+        let status = put_object(
+            State(provider),
+            Path((
+                "component".to_string(),
+                "container".to_string(),
+                "dir/nested/object".to_string(),
+            )),
+            Query(query(&url)),
+            Body::from("data"),
+        )
+        .await
+        .unwrap();
+        assert_eq!(status, StatusCode::OK);
+        let contents = tokio::fs::read_to_string(root.path().join("container/dir/nested/object"))
+            .await
+            .unwrap();
+        assert_eq!(contents, "data");
+    }
+}
@@ -10,6 +10,7 @@
 use core::future::Future;
 use core::pin::Pin;
 use core::str::FromStr;
+use core::time::Duration;
 
 use std::collections::HashMap;
 use std::env;
@@ -23,12 +24,17 @@ use aws_config::sts::AssumeRoleProvider;
 use aws_sdk_s3::config::{Region, SharedCredentialsProvider};
 use aws_sdk_s3::error::{ProvideErrorMetadata, SdkError};
 use aws_sdk_s3::operation::create_bucket::{CreateBucketError, CreateBucketOutput};
+use aws_sdk_s3::operation::create_multipart_upload::CreateMultipartUploadOutput;
 use aws_sdk_s3::operation::get_object::GetObjectOutput;
 use aws_sdk_s3::operation::head_bucket::HeadBucketError;
 use aws_sdk_s3::operation::head_object::{HeadObjectError, HeadObjectOutput};
 use aws_sdk_s3::operation::list_objects_v2::ListObjectsV2Output;
+use aws_sdk_s3::operation::put_object::PutObjectOutput;
+use aws_sdk_s3::operation::upload_part::UploadPartOutput;
+use aws_sdk_s3::presigning::PresigningConfig;
 use aws_sdk_s3::types::{
-    BucketLocationConstraint, CreateBucketConfiguration, Delete, Object, ObjectIdentifier,
+    BucketLocationConstraint, CompletedMultipartUpload, CompletedPart, CreateBucketConfiguration,
+    Delete, Object, ObjectIdentifier,
 };
 use aws_smithy_runtime::client::http::hyper_014::HyperClientBuilder;
 use base64::Engine as _;
@@ -37,6 +43,7 @@ use futures::{stream, Stream, StreamExt as _};
 use serde::Deserialize;
 use tokio::io::AsyncReadExt as _;
 use tokio::sync::{mpsc, RwLock};
+use tokio::task::JoinSet;
 use tokio_stream::wrappers::ReceiverStream;
 use tokio_util::io::ReaderStream;
 use tracing::{debug, error, instrument, warn};
@@ -48,13 +55,38 @@ use wasmcloud_provider_sdk::{
 };
 use wrpc_interface_blobstore::bindings::{
     exports::wrpc::blobstore::blobstore::Handler,
-    serve,
     wrpc::blobstore::types::{ContainerMetadata, ObjectId, ObjectMetadata},
 };
 
+mod bindings {
+    wit_bindgen_wrpc::generate!({
+        world: "interfaces",
+        with: {
+            "wasi:blobstore/types@0.2.0-draft": wrpc_interface_blobstore::bindings::wasi::blobstore::types,
+            "wasi:io/error@0.2.0": wrpc_interface_blobstore::bindings::wasi::io::error,
+            "wasi:io/poll@0.2.0": wrpc_interface_blobstore::bindings::wasi::io::poll,
+            "wasi:io/streams@0.2.0": wrpc_interface_blobstore::bindings::wasi::io::streams,
+            "wrpc:blobstore/blobstore@0.2.0": wrpc_interface_blobstore::bindings::exports::wrpc::blobstore::blobstore,
+            "wrpc:blobstore/types@0.2.0": wrpc_interface_blobstore::bindings::wrpc::blobstore::types,
+            "wasmcloud:blobstore/metadata@0.1.0-draft": generate,
+            "wasmcloud:blobstore/presign@0.1.0-draft": generate,
+        },
+    });
+}
+
+use bindings::exports::wasmcloud::blobstore::metadata;
+
 const ALIAS_PREFIX: &str = "alias_";
 const DEFAULT_STS_SESSION: &str = "blobstore_s3_provider";
 
+/// Minimum size of all but the last part of a multipart upload, imposed by S3
+const MIN_MULTIPART_PART_SIZE: usize = 5 * 1024 * 1024;
+const DEFAULT_MULTIPART_THRESHOLD: usize = 16 * 1024 * 1024;
+const DEFAULT_MULTIPART_PART_SIZE: usize = 8 * 1024 * 1024;
+const DEFAULT_MULTIPART_CONCURRENCY: usize = 4;
+/// Maximum number of parts of a multipart upload, imposed by S3
+const MAX_MULTIPART_PARTS: usize = 10_000;
+
 /// Configuration for connecting to S3-compatible storage
 ///
 /// This value is meant to be parsed from link configuration, and can
@@ -82,6 +114,14 @@ pub struct StorageConfig {
     pub aliases: HashMap<String, String>,
     /// Region in which buckets will be created
     pub bucket_region: Option<String>,
+    /// Size in bytes above which objects are uploaded using multipart upload
+    pub multipart_threshold: Option<usize>,
+    /// Size in bytes of the parts of multipart uploads, at least 5 MiB
+    pub multipart_part_size: Option<usize>,
+    /// Maximum number of parts of a single multipart upload being uploaded concurrently
+    pub multipart_concurrency: Option<usize>,
+    /// Maximum number of parts of a single multipart upload, at most 10,000
+    pub multipart_max_parts: Option<usize>,
 }
 
 #[derive(Clone, Debug, Default, Deserialize)]
@@ -134,6 +174,30 @@ impl StorageConfig {
             storage_config.bucket_region = Some(region.into());
         }
 
+        // Top level multipart upload settings override the ones in the encoded config
+        for (key, value) in [
+            (
+                "MULTIPART_THRESHOLD",
+                &mut storage_config.multipart_threshold,
+            ),
+            (
+                "MULTIPART_PART_SIZE",
+                &mut storage_config.multipart_part_size,
+            ),
+            (
+                "MULTIPART_CONCURRENCY",
+                &mut storage_config.multipart_concurrency,
+            ),
+            (
+                "MULTIPART_MAX_PARTS",
+                &mut storage_config.multipart_max_parts,
+            ),
+        ] {
+            if let Some(v) = config.get(key) {
+                *value = Some(v.parse().with_context(|| format!("invalid {key}"))?);
+            }
+        }
+
         if let Ok(arn) = env::var("AWS_ROLE_ARN") {
             let mut sts_config = storage_config.sts_config.unwrap_or_default();
             sts_config.role = arn;
@@ -158,12 +222,22 @@ impl StorageConfig {
     }
 }
 
+/// Settings of multipart uploads
+#[derive(Clone, Copy, Debug)]
+struct MultipartConfig {
+    threshold: usize,
+    part_size: usize,
+    concurrency: usize,
+    max_parts: usize,
+}
+
 #[derive(Clone)]
 pub struct StorageClient {
     s3_client: aws_sdk_s3::Client,
     aliases: Arc<HashMap<String, String>>,
     /// Preferred region for bucket creation
     bucket_region: Option<BucketLocationConstraint>,
+    multipart: MultipartConfig,
 }
 
 impl StorageClient {
@@ -178,6 +252,10 @@ impl StorageClient {
             endpoint,
             mut aliases,
             bucket_region,
+            multipart_threshold,
+            multipart_part_size,
+            multipart_concurrency,
+            multipart_max_parts,
         }: StorageConfig,
         config_values: &HashMap<String, String>,
     ) -> Self {
@@ -272,10 +350,29 @@ impl StorageClient {
             }
         }
 
+        let mut part_size = multipart_part_size.unwrap_or(DEFAULT_MULTIPART_PART_SIZE);
+        if part_size < MIN_MULTIPART_PART_SIZE {
+            warn!(
+                part_size,
+                "multipart part size is below the S3 minimum of {MIN_MULTIPART_PART_SIZE} bytes, using the minimum instead"
+            );
+            part_size = MIN_MULTIPART_PART_SIZE;
+        }
+
         StorageClient {
             s3_client,
             aliases: Arc::new(aliases),
             bucket_region: bucket_region.and_then(|v| BucketLocationConstraint::from_str(&v).ok()),
+            multipart: MultipartConfig {
+                threshold: multipart_threshold.unwrap_or(DEFAULT_MULTIPART_THRESHOLD),
+                part_size,
+                concurrency: multipart_concurrency
+                    .unwrap_or(DEFAULT_MULTIPART_CONCURRENCY)
+                    .max(1),
+                max_parts: multipart_max_parts
+                    .unwrap_or(MAX_MULTIPART_PARTS)
+                    .clamp(1, MAX_MULTIPART_PARTS),
+            },
         }
     }
 
@@ -539,6 +636,211 @@ impl StorageClient {
             },
         }
     }
+
+    /// Create a SigV4-presigned URL for downloading the object
+    #[instrument(level = "debug", skip(self))]
+    pub async fn presign_get(
+        &self,
+        bucket: &str,
+        key: &str,
+        expires_in: Duration,
+    ) -> anyhow::Result<String> {
+        let req = self
+            .s3_client
+            .get_object()
+            .bucket(bucket)
+            .key(key)
+            .presigned(PresigningConfig::expires_in(expires_in).context("invalid expiry")?)
+            .await
+            .context("failed to presign `get_object` request")?;
+        Ok(req.uri().to_string())
+    }
+
+    /// Create a SigV4-presigned URL for uploading the object
+    #[instrument(level = "debug", skip(self))]
+    pub async fn presign_put(
+        &self,
+        bucket: &str,
+        key: &str,
+        expires_in: Duration,
+    ) -> anyhow::Result<String> {
+        let req = self
+            .s3_client
+            .put_object()
+            .bucket(bucket)
+            .key(key)
+            .presigned(PresigningConfig::expires_in(expires_in).context("invalid expiry")?)
+            .await
+            .context("failed to presign `put_object` request")?;
+        Ok(req.uri().to_string())
+    }
+
+    /// Upload an object from a stream of data.
+    ///
+    /// Objects smaller than the multipart threshold are uploaded with a single `PutObject`,
+    /// larger ones are streamed using multipart upload, which is aborted on failure.
+    #[instrument(level = "debug", skip(self, data))]
+    pub async fn put_object_stream(
+        &self,
+        bucket: &str,
+        key: &str,
+        mut data: impl Stream<Item = Bytes> + Unpin,
+    ) -> anyhow::Result<()> {
+        let mut buf = BytesMut::new();
+        while buf.len() <= self.multipart.threshold {
+            let Some(chunk) = data.next().await else {
+                self.s3_client
+                    .put_object()
+                    .bucket(bucket)
+                    .key(key)
+                    .body(buf.freeze().into())
+                    .send()
+                    .await
+                    .context("failed to put object")?;
+                return Ok(());
+            };
+            buf.extend_from_slice(&chunk);
+        }
+
+        let CreateMultipartUploadOutput { upload_id, .. } = self
+            .s3_client
+            .create_multipart_upload()
+            .bucket(bucket)
+            .key(key)
+            .send()
+            .await
+            .context("failed to create multipart upload")?;
+        let upload_id = upload_id.context("multipart upload ID missing")?;
+        match self.upload_parts(bucket, key, &upload_id, buf, data).await {
+            Ok(parts) => {
+                self.s3_client
+                    .complete_multipart_upload()
+                    .bucket(bucket)
+                    .key(key)
+                    .upload_id(&upload_id)
+                    .multipart_upload(
+                        CompletedMultipartUpload::builder()
+                            .set_parts(Some(parts))
+                            .build(),
+                    )
+                    .send()
+                    .await
+                    .context("failed to complete multipart upload")?;
+                Ok(())
+            }
+            Err(err) => {
+                if let Err(abort_err) = self
+                    .s3_client
+                    .abort_multipart_upload()
+                    .bucket(bucket)
+                    .key(key)
+                    .upload_id(&upload_id)
+                    .send()
+                    .await
+                {
+                    error!(?abort_err, upload_id, "failed to abort multipart upload");
+                }
+                Err(err)
+            }
+        }
+    }
+
+    /// Upload the parts of a multipart upload, starting with the contents of `buf`, with at most
+    /// the configured number of parts in flight.
+    ///
+    /// Each part is uploaded on its own task, so that uploads make progress while the next part
+    /// is read from `data`. On failure, all parts still in flight are cancelled.
+    async fn upload_parts(
+        &self,
+        bucket: &str,
+        key: &str,
+        upload_id: &str,
+        buf: BytesMut,
+        data: impl Stream<Item = Bytes> + Unpin,
+    ) -> anyhow::Result<Vec<CompletedPart>> {
+        let mut in_flight = JoinSet::new();
+        let res = self
+            .spawn_parts(&mut in_flight, bucket, key, upload_id, buf, data)
+            .await;
+        if res.is_err() {
+            in_flight.shutdown().await;
+        }
+        res
+    }
+
+    async fn spawn_parts(
+        &self,
+        in_flight: &mut JoinSet<anyhow::Result<CompletedPart>>,
+        bucket: &str,
+        key: &str,
+        upload_id: &str,
+        mut buf: BytesMut,
+        mut data: impl Stream<Item = Bytes> + Unpin,
+    ) -> anyhow::Result<Vec<CompletedPart>> {
+        let MultipartConfig {
+            part_size,
+            concurrency,
+            max_parts,
+            ..
+        } = self.multipart;
+        let bucket: Arc<str> = bucket.into();
+        let key: Arc<str> = key.into();
+        let upload_id: Arc<str> = upload_id.into();
+        let mut parts = Vec::new();
+        let mut part_number = 0;
+        let mut done = false;
+        while !done {
+            // Fill a part, or take what's left once the stream is exhausted
+            while buf.len() < part_size {
+                let Some(chunk) = data.next().await else {
+                    done = true;
+                    break;
+                };
+                buf.extend_from_slice(&chunk);
+            }
+            if buf.is_empty() {
+                break;
+            }
+            if part_number >= max_parts {
+                bail!("object exceeds the maximum of {max_parts} parts of {part_size} bytes");
+            }
+            let body = buf.split_to(part_size.min(buf.len())).freeze();
+            if in_flight.len() >= concurrency {
+                if let Some(part) = in_flight.join_next().await {
+                    parts.push(part.context("part upload task failed")??);
+                }
+            }
+            part_number += 1;
+            let part_number = i32::try_from(part_number).context("part number overflow")?;
+            let s3_client = self.s3_client.clone();
+            let bucket = Arc::clone(&bucket);
+            let key = Arc::clone(&key);
+            let upload_id = Arc::clone(&upload_id);
+            in_flight.spawn(async move {
+                let UploadPartOutput { e_tag, .. } = s3_client
+                    .upload_part()
+                    .bucket(bucket.as_ref())
+                    .key(key.as_ref())
+                    .upload_id(upload_id.as_ref())
+                    .part_number(part_number)
+                    .body(body.into())
+                    .send()
+                    .await
+                    .with_context(|| format!("failed to upload part {part_number}"))?;
+                anyhow::Ok(
+                    CompletedPart::builder()
+                        .part_number(part_number)
+                        .set_e_tag(e_tag)
+                        .build(),
+                )
+            });
+        }
+        while let Some(part) = in_flight.join_next().await {
+            parts.push(part.context("part upload task failed")??);
+        }
+        parts.sort_by_key(CompletedPart::part_number);
+        Ok(parts)
+    }
 }
 
 /// Blobstore S3 provider
@@ -570,7 +872,7 @@ impl BlobstoreS3Provider {
         let wrpc = connection
             .get_wrpc_client(connection.provider_key())
             .await?;
-        serve_provider_exports(&wrpc, provider, shutdown, serve)
+        serve_provider_exports(&wrpc, provider, shutdown, bindings::serve)
             .await
             .context("failed to serve provider exports")
     }
@@ -882,20 +1184,11 @@ impl Handler<Option<Context>> for BlobstoreS3Provider {
         Ok(async {
             propagate_trace_for_ctx!(cx);
             let client = self.client(cx).await?;
-            let req = client
-                .s3_client
-                .put_object()
-                .bucket(client.unalias(&id.container))
-                .key(&id.object);
-            anyhow::Ok(Box::pin(async {
-                // TODO: Stream data to S3
-                let data: BytesMut = data.collect().await;
-                req.body(data.freeze().into())
-                    .send()
+            anyhow::Ok(Box::pin(async move {
+                client
+                    .put_object_stream(client.unalias(&id.container), &id.object, data)
                     .await
-                    .context("failed to put object")
-                    .map_err(|err| format!("{err:#}"))?;
-                Ok(())
+                    .map_err(|err| format!("{err:#}"))
             }) as Pin<Box<dyn Future<Output = _> + Send>>)
         }
         .await
@@ -903,6 +1196,149 @@ impl Handler<Option<Context>> for BlobstoreS3Provider {
     }
 }
 
+impl bindings::exports::wasmcloud::blobstore::metadata::Handler<Option<Context>>
+    for BlobstoreS3Provider
+{
+    #[instrument(level = "trace", skip(self))]
+    async fn get_object_metadata(
+        &self,
+        cx: Option<Context>,
+        container: String,
+        object: String,
+    ) -> anyhow::Result<Result<metadata::ObjectMetadata, String>> {
+        Ok(async {
+            propagate_trace_for_ctx!(cx);
+            let client = self.client(cx).await?;
+            let HeadObjectOutput {
+                content_length,
+                last_modified,
+                e_tag,
+                content_type,
+                metadata,
+                ..
+            } = client
+                .s3_client
+                .head_object()
+                .bucket(client.unalias(&container))
+                .key(&object)
+                .send()
+                .await
+                .context("failed to head object")?;
+            anyhow::Ok(metadata::ObjectMetadata {
+                size: content_length
+                    .and_then(|v| v.try_into().ok())
+                    .unwrap_or_default(),
+                last_modified: last_modified
+                    .and_then(|v| v.secs().try_into().ok())
+                    .unwrap_or_default(),
+                etag: e_tag.unwrap_or_default(),
+                content_type,
+                metadata: metadata.unwrap_or_default().into_iter().collect(),
+            })
+        }
+        .await
+        .map_err(|err| format!("{err:#}")))
+    }
+
+    #[instrument(level = "trace", skip(self, data))]
+    async fn write_object(
+        &self,
+        cx: Option<Context>,
+        container: String,
+        object: String,
+        data: Bytes,
+        metadata::WriteOptions {
+            content_type,
+            metadata,
+            precondition,
+        }: metadata::WriteOptions,
+    ) -> anyhow::Result<Result<String, metadata::WriteError>> {
+        propagate_trace_for_ctx!(cx);
+        let client = match self.client(cx).await {
+            Ok(client) => client,
+            Err(err) => return Ok(Err(metadata::WriteError::Other(format!("{err:#}")))),
+        };
+        let mut req = client
+            .s3_client
+            .put_object()
+            .bucket(client.unalias(&container))
+            .key(&object)
+            .set_content_type(content_type)
+            .set_metadata(Some(metadata.into_iter().collect()))
+            .body(data.into());
+        match precondition {
+            Some(metadata::Precondition::IfMatch(etag)) => req = req.if_match(etag),
+            Some(metadata::Precondition::IfNoneMatch(etag)) => req = req.if_none_match(etag),
+            None => {}
+        }
+        match req.send().await {
+            Ok(PutObjectOutput { e_tag, .. }) => Ok(Ok(e_tag.unwrap_or_default())),
+            Err(SdkError::ServiceError(err))
+                if matches!(
+                    err.err().code(),
+                    Some("PreconditionFailed" | "ConditionalRequestConflict")
+                ) =>
+            {
+                Ok(Err(metadata::WriteError::PreconditionFailed))
+            }
+            Err(err) => Ok(Err(metadata::WriteError::Other(format!(
+                "{:#}",
+                anyhow!(err).context("failed to put object")
+            )))),
+        }
+    }
+}
+
+impl bindings::exports::wasmcloud::blobstore::presign::Handler<Option<Context>>
+    for BlobstoreS3Provider
+{
+    #[instrument(level = "trace", skip(self))]
+    async fn presign_get(
+        &self,
+        cx: Option<Context>,
+        container: String,
+        object: String,
+        expires_in_secs: u64,
+    ) -> anyhow::Result<Result<String, String>> {
+        Ok(async {
+            propagate_trace_for_ctx!(cx);
+            let client = self.client(cx).await?;
+            client
+                .presign_get(
+                    client.unalias(&container),
+                    &object,
+                    Duration::from_secs(expires_in_secs),
+                )
+                .await
+        }
+        .await
+        .map_err(|err| format!("{err:#}")))
+    }
+
+    #[instrument(level = "trace", skip(self))]
+    async fn presign_put(
+        &self,
+        cx: Option<Context>,
+        container: String,
+        object: String,
+        expires_in_secs: u64,
+    ) -> anyhow::Result<Result<String, String>> {
+        Ok(async {
+            propagate_trace_for_ctx!(cx);
+            let client = self.client(cx).await?;
+            client
+                .presign_put(
+                    client.unalias(&container),
+                    &object,
+                    Duration::from_secs(expires_in_secs),
+                )
+                .await
+        }
+        .await
+        .map_err(|err| format!("{err:#}")))
+    }
+}
+
 /// Handle provider control commands
 /// `put_link` (new component link command), `del_link` (remove link command), and shutdown
 impl Provider for BlobstoreS3Provider {
@@ -952,6 +1388,8 @@ impl Provider for BlobstoreS3Provider {
 mod test {
     use super::*;
 
+    use wasmcloud_test_util::testcontainers::{AsyncRunner as _, ImageExt as _, LocalStack};
+
     #[tokio::test]
     async fn aliases() {
         let client = StorageClient::new(
@@ -969,4 +1407,141 @@ mod test {
         // undefined alias
         assert_eq!(client.unalias(&format!("{ALIAS_PREFIX}baz")), "baz");
     }
+
+    #[tokio::test]
+    async fn presigned_urls() {
+        let client = StorageClient::new(
+            StorageConfig {
+                endpoint: Some("http://127.0.0.1:9000".into()),
+                access_key_id: Some("test".into()),
+                secret_access_key: Some("test".into()),
+                region: Some("us-east-1".into()),
+                ..Default::default()
+            },
+            &HashMap::new(),
+        )
+        .await;
+        let query = |url: &str| -> HashMap<String, String> {
+            let (base, query) = url.split_once('?').expect("URL should have a query");
+            assert_eq!(base, "http://127.0.0.1:9000/bucket/dir/object");
+            query
+                .split('&')
+                .filter_map(|pair| pair.split_once('='))
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect()
+        };
+
+        let get = query(
+            &client
+                .presign_get("bucket", "dir/object", Duration::from_secs(300))
+                .await
+                .expect("failed to presign GET"),
+        );
+        let put = query(
+            &client
+                .presign_put("bucket", "dir/object", Duration::from_secs(3600))
+                .await
+                .expect("failed to presign PUT"),
+        );
+        for (query, id, expires) in [(&get, "GetObject", "300"), (&put, "PutObject", "3600")] {
+            assert_eq!(query["x-id"], id);
+            assert_eq!(query["X-Amz-Algorithm"], "AWS4-HMAC-SHA256");
+            assert_eq!(query["X-Amz-Expires"], expires);
+            assert!(query["X-Amz-Credential"].starts_with("test%2F"));
+            assert!(query["X-Amz-Credential"].ends_with("%2Fus-east-1%2Fs3%2Faws4_request"));
+            assert!(!query["X-Amz-Signature"].is_empty());
+        }
+        assert_ne!(get["X-Amz-Signature"], put["X-Amz-Signature"]);
+
+        // SigV4 signatures are valid for at most a week
+        assert!(client
+            .presign_get(
+                "bucket",
+                "dir/object",
+                Duration::from_secs(8 * 24 * 60 * 60)
+            )
+            .await
+            .is_err());
+    }
+    /// Conditional writes, against the S3-compatible store at `AWS_ENDPOINT` or LocalStack
+    #[tokio::test]
+    async fn conditional_writes() {
+        let (endpoint, _container) = if let Ok(endpoint) = env::var("AWS_ENDPOINT") {
+            (endpoint, None)
+        } else {
+            let node = LocalStack::default()
+                .with_env_var("SERVICES", "s3")
+                .start()
+                .await
+                .expect("should have started localstack");
+            let host = node.get_host().await.unwrap();
+            let port = node.get_host_port_ipv4(4566).await.unwrap();
+            (format!("http://{host}:{port}"), Some(node))
+        };
+        let client = StorageClient::new(
+            StorageConfig {
+                endpoint: Some(endpoint),
+                access_key_id: Some(env::var("AWS_ACCESS_KEY_ID").unwrap_or("test".into())),
+                secret_access_key: Some(env::var("AWS_SECRET_ACCESS_KEY").unwrap_or("test".into())),
+                region: Some("us-east-1".into()),
+                ..Default::default()
+            },
+            &HashMap::new(),
+        )
+        .await;
+        let bucket = format!("test.conditional.{}", rand::random::<u64>());
+        client.create_container(&bucket).await.unwrap();
+
+        let provider = BlobstoreS3Provider::default();
+        provider
+            .actors
+            .write()
+            .await
+            .insert("component".into(), client);
+        let write = |data: &'static str, precondition| {
+            metadata::Handler::write_object(
+                &provider,
+                Some(Context {
+                    component: Some("component".into()),
+                    ..Default::default()
+                }),
+                bucket.clone(),
+                "object".into(),
+                Bytes::from(data),
+                metadata::WriteOptions {
+                    content_type: None,
+                    metadata: vec![],
+                    precondition,
+                },
+            )
+        };
+
+        let etag = write("1", Some(metadata::Precondition::IfNoneMatch("*".into())))
+            .await
+            .unwrap()
+            .expect("object should have been created");
+        assert!(matches!(
+            write("2", Some(metadata::Precondition::IfNoneMatch("*".into())))
+                .await
+                .unwrap(),
+            Err(metadata::WriteError::PreconditionFailed)
+        ));
+        assert!(matches!(
+            write("2", Some(metadata::Precondition::IfMatch("\"0\"".into())))
+                .await
+                .unwrap(),
+            Err(metadata::WriteError::PreconditionFailed)
+        ));
+        let new_etag = write("2", Some(metadata::Precondition::IfMatch(etag.clone())))
+            .await
+            .unwrap()
+            .expect("object should have been replaced");
+        assert_ne!(new_etag, etag);
+        assert!(matches!(
+            write("3", Some(metadata::Precondition::IfMatch(etag)))
+                .await
+                .unwrap(),
+            Err(metadata::WriteError::PreconditionFailed)
+        ));
+    }
 }
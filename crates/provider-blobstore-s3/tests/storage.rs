@@ -32,9 +32,14 @@ use std::collections::HashMap;
 use std::env;
 
 use anyhow::{Context as _, Result};
+use aws_sdk_s3::config::{Credentials, Region};
+use bytes::Bytes;
+use futures::stream;
 use wasmcloud_provider_blobstore_s3::{StorageClient, StorageConfig};
 use wasmcloud_test_util::testcontainers::{AsyncRunner as _, ContainerAsync, ImageExt, LocalStack};
 
+const MIB: usize = 1024 * 1024;
+
 struct TestEnv {
     _container: Option<ContainerAsync<LocalStack>>,
     endpoint: String,
@@ -67,8 +72,8 @@ impl TestEnv {
         })
     }
 
-    pub async fn configure_test_client(&self) -> StorageClient {
-        let conf = StorageConfig {
+    fn test_config(&self) -> StorageConfig {
+        StorageConfig {
             endpoint: Some(self.endpoint.clone()),
             access_key_id: Self::env_var_or_default("AWS_ACCESS_KEY_ID", Some("test".to_string())),
             secret_access_key: Self::env_var_or_default(
@@ -81,11 +86,49 @@ impl TestEnv {
             session_token: None,
             sts_config: None,
             bucket_region: Self::env_var_or_default("BUCKET_REGION", None),
-        };
+            ..Default::default()
+        }
+    }
 
+    pub async fn configure_test_client(&self) -> StorageClient {
+        StorageClient::new(self.test_config(), &HashMap::new()).await
+    }
+
+    pub async fn configure_multipart_test_client(&self, max_parts: usize) -> StorageClient {
+        let conf = StorageConfig {
+            multipart_threshold: Some(MIB),
+            multipart_part_size: Some(5 * MIB),
+            multipart_concurrency: Some(2),
+            multipart_max_parts: Some(max_parts),
+            ..self.test_config()
+        };
         StorageClient::new(conf, &HashMap::new()).await
     }
 
+    /// Plain S3 client, used to inspect the bucket independently of [`StorageClient`]
+    pub fn s3_client(&self) -> aws_sdk_s3::Client {
+        let StorageConfig {
+            access_key_id,
+            secret_access_key,
+            region,
+            ..
+        } = self.test_config();
+        let conf = aws_sdk_s3::Config::builder()
+            .behavior_version_latest()
+            .endpoint_url(&self.endpoint)
+            .force_path_style(true)
+            .region(region.map(Region::new))
+            .credentials_provider(Credentials::new(
+                access_key_id.unwrap_or_default(),
+                secret_access_key.unwrap_or_default(),
+                None,
+                None,
+                "test",
+            ))
+            .build();
+        aws_sdk_s3::Client::from_conf(conf)
+    }
+
     fn env_var_or_default(key: &str, default: Option<String>) -> Option<String> {
         std::env::var(key).ok().or(default)
     }
@@ -116,3 +159,64 @@ async fn test_create_container() {
         "Container should exist"
     );
 }
+
+/// Tests
+/// - put_object_stream, below and above the multipart threshold
+/// - the uploaded contents
+/// - aborting a multipart upload exceeding the maximum number of parts
+#[tokio::test]
+async fn test_put_object_stream() {
+    let env = TestEnv::new()
+        .await
+        .expect("should have setup the test environment");
+
+    let s3 = env.configure_test_client().await;
+    let num = rand::random::<u64>();
+    let bucket = format!("test.multipart.{num}");
+    s3.create_container(&bucket).await.unwrap();
+
+    let raw = env.s3_client();
+    let get_object = |key: &'static str| {
+        let req = raw.get_object().bucket(&bucket).key(key).send();
+        async move {
+            let object = req.await.expect("should have gotten object");
+            object.body.collect().await.unwrap().into_bytes()
+        }
+    };
+
+    // Below the threshold, uploaded with a single `PutObject`
+    let chunks: Vec<_> = (0..4u8).map(|i| Bytes::from(vec![i; 1024])).collect();
+    s3.put_object_stream(&bucket, "small", stream::iter(chunks.clone()))
+        .await
+        .unwrap();
+    assert_eq!(get_object("small").await, chunks.concat());
+
+    // Above the threshold, uploaded in three parts of which the last is partial
+    let s3 = env.configure_multipart_test_client(3).await;
+    let chunks: Vec<_> = (0..23u8).map(|i| Bytes::from(vec![i; MIB / 2])).collect();
+    s3.put_object_stream(&bucket, "large", stream::iter(chunks.clone()))
+        .await
+        .unwrap();
+    let info = s3.get_object_info(&bucket, "large").await.unwrap();
+    assert_eq!(info.size, 23 * MIB as u64 / 2);
+    assert_eq!(get_object("large").await, chunks.concat());
+
+    // Exceeding the maximum number of parts, the upload is aborted
+    let s3 = env.configure_multipart_test_client(2).await;
+    let err = s3
+        .put_object_stream(&bucket, "too-large", stream::iter(chunks))
+        .await
+        .expect_err("upload exceeding the part limit should fail");
+    assert!(err.to_string().contains("maximum of 2 parts"), "{err:#}");
+    assert!(!s3.has_object(&bucket, "too-large").await.unwrap());
+    let uploads = raw
+        .list_multipart_uploads()
+        .bucket(&bucket)
+        .send()
+        .await
+        .unwrap();
+    assert!(
+        uploads.uploads().is_empty(),
+        "multipart upload should have been aborted"
+    );
+}
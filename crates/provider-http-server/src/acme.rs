@@ -0,0 +1,880 @@
+//! Certificates obtained and renewed using ACME ([RFC 8555]), e.g. from Let's Encrypt
+//!
+//! Control of the domains is proven with either the TLS-ALPN-01 ([RFC 8737]) challenge, answered
+//! by the listener itself, or the HTTP-01 challenge, answered by a temporary listener on
+//! [`ServiceSettings::acme_http_address`].
+//!
+//! [RFC 8555]: https://www.rfc-editor.org/rfc/rfc8555
+//! [RFC 8737]: https://www.rfc-editor.org/rfc/rfc8737
+
+use core::future::Future;
+use core::pin::Pin;
+use core::str::FromStr as _;
+use core::time::Duration;
+
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use std::time::SystemTime;
+
+use anyhow::{bail, Context as _};
+use base64::engine::Engine as _;
+use base64::prelude::BASE64_URL_SAFE_NO_PAD;
+use bytes::Bytes;
+use http_body_util::{BodyExt as _, Collected, Full};
+use instant_acme::{
+    Account, AuthorizationStatus, BytesResponse, ChallengeType, HttpClient, Identifier, NewAccount,
+    NewOrder, Order, OrderStatus,
+};
+use p256::ecdsa::{DerSignature, SigningKey};
+use p256::elliptic_curve::rand_core::{OsRng, RngCore as _};
+use p256::pkcs8::{DecodePrivateKey as _, EncodePrivateKey as _, LineEnding};
+use rustls_0_21::server::{ClientHello, ResolvesServerCert};
+use rustls_0_21::sign::CertifiedKey;
+use sha2::{Digest as _, Sha256};
+use tokio::io::AsyncWriteExt as _;
+use tokio::task::JoinHandle;
+use tracing::{debug, error, info, instrument, warn};
+use x509_cert::builder::{Builder as _, CertificateBuilder, Profile, RequestBuilder};
+use x509_cert::der::asn1::{Ia5String, OctetString};
+use x509_cert::der::oid::{AssociatedOid, ObjectIdentifier};
+use x509_cert::der::{Encode as _, EncodePem as _};
+use x509_cert::ext::pkix::name::GeneralName;
+use x509_cert::ext::pkix::SubjectAltName;
+use x509_cert::ext::AsExtension;
+use x509_cert::name::Name;
+use x509_cert::serial_number::SerialNumber;
+use x509_cert::spki::SubjectPublicKeyInfoOwned;
+use x509_cert::time::Validity;
+
+use crate::{AcmeChallenge, ServiceSettings};
+
+/// Directory used if none is configured
+const LETS_ENCRYPT_DIRECTORY_URL: &str = "https://acme-v02.api.letsencrypt.org/directory";
+
+/// ALPN protocol negotiated by ACME servers validating the TLS-ALPN-01 challenge
+const ACME_TLS_ALPN_PROTOCOL: &[u8] = b"acme-tls/1";
+
+/// Certificates are renewed when they expire within this duration
+const RENEW_BEFORE_EXPIRY: Duration = Duration::from_secs(30 * 24 * 60 * 60);
+
+/// Delay before retrying after failing to obtain a certificate for the first time
+const RETRY_DELAY_MIN: Duration = Duration::from_secs(10);
+
+/// Maximum delay between attempts to obtain a certificate
+const RETRY_DELAY_MAX: Duration = Duration::from_secs(60 * 60);
+
+/// Interval at which pending authorizations and orders are polled
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Number of times pending authorizations and orders are polled before giving up
+const POLL_ATTEMPTS: usize = 30;
+
+/// ACME configuration extracted from [`ServiceSettings`]
+#[derive(Clone, Debug)]
+pub(crate) struct AcmeConfig {
+    domains: Vec<String>,
+    directory_url: String,
+    contact: Option<String>,
+    cache_dir: PathBuf,
+    challenge: AcmeChallenge,
+    http_address: SocketAddr,
+    ca_file: Option<PathBuf>,
+}
+
+impl AcmeConfig {
+    /// Extract the ACME configuration, if ACME is enabled in the given settings
+    pub(crate) fn from_settings(settings: &ServiceSettings) -> Option<Self> {
+        let domains = settings.acme_domains.clone()?;
+        Some(Self {
+            domains,
+            directory_url: settings
+                .acme_directory_url
+                .clone()
+                .unwrap_or_else(|| LETS_ENCRYPT_DIRECTORY_URL.to_string()),
+            contact: settings.acme_contact.as_ref().map(|contact| {
+                if contact.contains(':') {
+                    contact.clone()
+                } else {
+                    format!("mailto:{contact}")
+                }
+            }),
+            cache_dir: settings.acme_cache_dir.clone().unwrap_or_default().into(),
+            challenge: settings.acme_challenge.unwrap_or_default(),
+            http_address: settings
+                .acme_http_address
+                .unwrap_or_else(|| (std::net::Ipv4Addr::UNSPECIFIED, 80).into()),
+            ca_file: settings.acme_ca_file.clone().map(PathBuf::from),
+        })
+    }
+
+    /// Path of the cached account credentials, which are specific to the directory
+    fn account_credentials_path(&self) -> PathBuf {
+        let directory = Sha256::digest(&self.directory_url);
+        let name = BASE64_URL_SAFE_NO_PAD.encode(&directory[..12]);
+        self.cache_dir.join(format!("account-{name}.json"))
+    }
+
+    /// Paths of the cached certificate chain and its key, named after the first domain
+    fn certificate_paths(&self) -> (PathBuf, PathBuf) {
+        let name = self.domains.first().map_or("certificate", String::as_str);
+        (
+            self.cache_dir.join(format!("{name}.crt.pem")),
+            self.cache_dir.join(format!("{name}.key.pem")),
+        )
+    }
+}
+
+/// Start serving the cached certificate, if any, and obtain and renew certificates in the
+/// background, swapping them in without restarting the listener
+///
+/// The returned resolver presents the certificate, and answers TLS-ALPN-01 challenges when the
+/// listener is configured with [`server_config`].
+pub(crate) async fn start(
+    config: AcmeConfig,
+) -> anyhow::Result<(Arc<AcmeCertResolver>, JoinHandle<()>)> {
+    tokio::fs::create_dir_all(&config.cache_dir)
+        .await
+        .with_context(|| {
+            format!(
+                "failed to create ACME cache directory [{}]",
+                config.cache_dir.display()
+            )
+        })?;
+    let resolver = Arc::new(AcmeCertResolver::default());
+    let mut expires = None;
+    match load_cached_certificate(&config).await {
+        Ok(Some((cert, not_after))) => {
+            info!(domains = ?config.domains, "loaded cached ACME certificate");
+            resolver.set_certificate(cert);
+            expires = Some(not_after);
+        }
+        Ok(None) => {}
+        Err(err) => warn!(?err, "failed to load cached ACME certificate"),
+    }
+
+    let task_resolver = Arc::clone(&resolver);
+    let task = tokio::spawn(async move {
+        let mut retry_delay = RETRY_DELAY_MIN;
+        loop {
+            if let Some(not_after) = expires {
+                let renew_at = not_after
+                    .checked_sub(RENEW_BEFORE_EXPIRY)
+                    .unwrap_or(SystemTime::UNIX_EPOCH);
+                if let Ok(wait) = renew_at.duration_since(SystemTime::now()) {
+                    debug!(?wait, "waiting to renew ACME certificate");
+                    tokio::time::sleep(wait).await;
+                }
+            }
+            match obtain_certificate(&config, &task_resolver).await {
+                Ok(not_after) => {
+                    info!(domains = ?config.domains, "obtained ACME certificate");
+                    expires = Some(not_after);
+                    retry_delay = RETRY_DELAY_MIN;
+                }
+                Err(err) => {
+                    error!(?err, domains = ?config.domains, "failed to obtain ACME certificate");
+                    tokio::time::sleep(retry_delay).await;
+                    retry_delay = (retry_delay * 2).min(RETRY_DELAY_MAX);
+                }
+            }
+        }
+    });
+    Ok((resolver, task))
+}
+
+/// Build the configuration of a listener presenting the certificates selected by `resolver`,
+/// which also negotiates the protocol used to validate TLS-ALPN-01 challenges
+pub(crate) fn server_config(resolver: Arc<dyn ResolvesServerCert>) -> rustls_0_21::ServerConfig {
+    let mut tls = rustls_0_21::ServerConfig::builder()
+        .with_safe_defaults()
+        .with_no_client_auth()
+        .with_cert_resolver(resolver);
+    tls.alpn_protocols = vec![
+        b"h2".to_vec(),
+        b"http/1.1".to_vec(),
+        ACME_TLS_ALPN_PROTOCOL.to_vec(),
+    ];
+    tls
+}
+
+/// Whether the client is an ACME server validating a TLS-ALPN-01 challenge
+pub(crate) fn is_challenge(client_hello: &ClientHello) -> bool {
+    client_hello
+        .alpn()
+        .is_some_and(|mut protocols| protocols.any(|p| p == ACME_TLS_ALPN_PROTOCOL))
+}
+
+/// Certificate served by the listener, along with responses to TLS-ALPN-01 challenges
+#[derive(Default)]
+pub(crate) struct AcmeCertResolver {
+    certificate: RwLock<Option<Arc<CertifiedKey>>>,
+    /// Challenge certificates by domain
+    challenges: RwLock<HashMap<String, Arc<CertifiedKey>>>,
+}
+
+impl AcmeCertResolver {
+    /// The certificate obtained for the configured domains, if any
+    pub(crate) fn certificate(&self) -> Option<Arc<CertifiedKey>> {
+        self.certificate.read().ok()?.clone()
+    }
+
+    fn set_certificate(&self, cert: Arc<CertifiedKey>) {
+        if let Ok(mut certificate) = self.certificate.write() {
+            *certificate = Some(cert);
+        }
+    }
+
+    fn set_challenge(&self, domain: &str, cert: Option<Arc<CertifiedKey>>) {
+        if let Ok(mut challenges) = self.challenges.write() {
+            if let Some(cert) = cert {
+                challenges.insert(domain.to_ascii_lowercase(), cert);
+            } else {
+                challenges.remove(&domain.to_ascii_lowercase());
+            }
+        }
+    }
+}
+
+impl ResolvesServerCert for AcmeCertResolver {
+    fn resolve(&self, client_hello: ClientHello) -> Option<Arc<CertifiedKey>> {
+        if is_challenge(&client_hello) {
+            let domain = client_hello.server_name()?.to_ascii_lowercase();
+            return self.challenges.read().ok()?.get(&domain).cloned();
+        }
+        self.certificate()
+    }
+}
+
+/// Load the cached certificate, unless it does not cover all domains
+async fn load_cached_certificate(
+    config: &AcmeConfig,
+) -> anyhow::Result<Option<(Arc<CertifiedKey>, SystemTime)>> {
+    let (cert_path, key_path) = config.certificate_paths();
+    let (Ok(cert_pem), Ok(key_pem)) = (
+        tokio::fs::read(&cert_path).await,
+        tokio::fs::read_to_string(&key_path).await,
+    ) else {
+        return Ok(None);
+    };
+    let key = SigningKey::from_pkcs8_pem(&key_pem).context("invalid cached certificate key")?;
+    let (cert, not_after, names) = certified_key(&cert_pem, &key)?;
+    if config.domains.iter().any(|domain| !names.contains(domain)) {
+        info!("cached ACME certificate does not cover all domains");
+        return Ok(None);
+    }
+    Ok(Some((cert, not_after)))
+}
+
+/// Build a [`CertifiedKey`] from a PEM-encoded certificate chain, returning it along with the
+/// expiry and DNS names of the leaf certificate
+fn certified_key(
+    cert_pem: &[u8],
+    key: &SigningKey,
+) -> anyhow::Result<(Arc<CertifiedKey>, SystemTime, Vec<String>)> {
+    let chain = x509_cert::Certificate::load_pem_chain(cert_pem)
+        .context("invalid PEM certificate chain")?;
+    let leaf = chain.first().context("empty certificate chain")?;
+    let not_after = leaf.tbs_certificate.validity.not_after.to_system_time();
+    let names = leaf
+        .tbs_certificate
+        .extensions
+        .iter()
+        .flatten()
+        .filter(|ext| ext.extn_id == SubjectAltName::OID)
+        .filter_map(|ext| {
+            <SubjectAltName as x509_cert::der::Decode>::from_der(ext.extn_value.as_bytes()).ok()
+        })
+        .flat_map(|san| san.0)
+        .filter_map(|name| match name {
+            GeneralName::DnsName(name) => Some(name.to_string()),
+            _ => None,
+        })
+        .collect();
+    let chain = chain
+        .iter()
+        .map(|cert| cert.to_der().map(rustls_0_21::Certificate))
+        .collect::<Result<Vec<_>, _>>()
+        .context("failed to encode certificate")?;
+    let key = rustls_0_21::sign::any_supported_type(&rustls_0_21::PrivateKey(
+        key.to_pkcs8_der()
+            .context("failed to encode certificate key")?
+            .as_bytes()
+            .to_vec(),
+    ))
+    .context("unsupported certificate key")?;
+    Ok((Arc::new(CertifiedKey::new(chain, key)), not_after, names))
+}
+
+/// Obtain a certificate for the configured domains, serve it and cache it, returning its expiry
+#[instrument(level = "debug", skip_all, fields(domains = ?config.domains))]
+async fn obtain_certificate(
+    config: &AcmeConfig,
+    resolver: &AcmeCertResolver,
+) -> anyhow::Result<SystemTime> {
+    let account = load_or_create_account(config).await?;
+    let identifiers = config
+        .domains
+        .iter()
+        .cloned()
+        .map(Identifier::Dns)
+        .collect::<Vec<_>>();
+    let mut order = account
+        .new_order(&NewOrder {
+            identifiers: &identifiers,
+        })
+        .await
+        .context("failed to create ACME order")?;
+
+    let http_tokens = Arc::new(RwLock::new(HashMap::new()));
+    let http_server = if config.challenge == AcmeChallenge::Http01 {
+        Some(serve_http_challenges(config.http_address, Arc::clone(&http_tokens)).await?)
+    } else {
+        None
+    };
+    let authorized = authorize(&mut order, config.challenge, resolver, &http_tokens).await;
+    if let Some(http_server) = http_server {
+        http_server.abort();
+    }
+    for domain in &config.domains {
+        resolver.set_challenge(domain, None);
+    }
+    authorized?;
+
+    let key = SigningKey::random(&mut OsRng);
+    let csr = certificate_request(&config.domains, &key)?;
+    order
+        .finalize(&csr)
+        .await
+        .context("failed to finalize ACME order")?;
+    let mut attempts = 0;
+    let cert_pem = loop {
+        match order
+            .certificate()
+            .await
+            .context("failed to download certificate")?
+        {
+            Some(cert_pem) => break cert_pem,
+            None if attempts < POLL_ATTEMPTS => {
+                attempts += 1;
+                tokio::time::sleep(POLL_INTERVAL).await;
+            }
+            None => bail!("timed out waiting for ACME order to be valid"),
+        }
+    };
+
+    let (cert, not_after, _) = certified_key(cert_pem.as_bytes(), &key)?;
+    resolver.set_certificate(cert);
+
+    let (cert_path, key_path) = config.certificate_paths();
+    let key_pem = key
+        .to_pkcs8_pem(LineEnding::LF)
+        .context("failed to encode certificate key")?;
+    write_private_file(&key_path, key_pem.as_bytes())
+        .await
+        .with_context(|| format!("failed to cache key at [{}]", key_path.display()))?;
+    tokio::fs::write(&cert_path, &cert_pem)
+        .await
+        .with_context(|| format!("failed to cache certificate at [{}]", cert_path.display()))?;
+    Ok(not_after)
+}
+
+/// Complete the challenges of all pending authorizations, and wait for the order to be ready
+async fn authorize(
+    order: &mut Order,
+    challenge_type: AcmeChallenge,
+    resolver: &AcmeCertResolver,
+    http_tokens: &RwLock<HashMap<String, String>>,
+) -> anyhow::Result<()> {
+    let type_name = match challenge_type {
+        AcmeChallenge::TlsAlpn01 => ChallengeType::TlsAlpn01,
+        AcmeChallenge::Http01 => ChallengeType::Http01,
+    };
+    let authorizations = order
+        .authorizations()
+        .await
+        .context("failed to fetch ACME authorizations")?;
+    for authz in &authorizations {
+        let Identifier::Dns(domain) = &authz.identifier;
+        match authz.status {
+            AuthorizationStatus::Valid => continue,
+            AuthorizationStatus::Pending => {}
+            status => bail!("authorization for [{domain}] is {status:?}"),
+        }
+        let Some(challenge) = authz.challenges.iter().find(|c| c.r#type == type_name) else {
+            bail!("ACME server did not offer a {type_name:?} challenge for [{domain}]");
+        };
+        let key_authorization = order.key_authorization(challenge);
+        match challenge_type {
+            AcmeChallenge::TlsAlpn01 => {
+                let cert = challenge_certificate(domain, key_authorization.digest().as_ref())?;
+                resolver.set_challenge(domain, Some(cert));
+            }
+            AcmeChallenge::Http01 => {
+                if let Ok(mut tokens) = http_tokens.write() {
+                    tokens.insert(
+                        challenge.token.clone(),
+                        key_authorization.as_str().to_string(),
+                    );
+                }
+            }
+        }
+        debug!(domain, ?type_name, "responding to ACME challenge");
+        order
+            .set_challenge_ready(&challenge.url)
+            .await
+            .with_context(|| format!("failed to respond to challenge for [{domain}]"))?;
+    }
+
+    for _ in 0..POLL_ATTEMPTS {
+        let state = order
+            .refresh()
+            .await
+            .context("failed to fetch ACME order")?;
+        match state.status {
+            OrderStatus::Ready | OrderStatus::Valid => return Ok(()),
+            OrderStatus::Pending | OrderStatus::Processing => {
+                tokio::time::sleep(POLL_INTERVAL).await;
+            }
+            OrderStatus::Invalid => {
+                let error = order
+                    .authorizations()
+                    .await
+                    .ok()
+                    .into_iter()
+                    .flatten()
+                    .flat_map(|authz| authz.challenges)
+                    .find_map(|challenge| challenge.error)
+                    .map(|e| e.to_string())
+                    .unwrap_or_default();
+                bail!("ACME order is invalid: {error}")
+            }
+        }
+    }
+    bail!("timed out waiting for ACME authorizations")
+}
+
+/// Answer HTTP-01 challenges on the given address until the returned task is aborted
+async fn serve_http_challenges(
+    address: SocketAddr,
+    tokens: Arc<RwLock<HashMap<String, String>>>,
+) -> anyhow::Result<JoinHandle<()>> {
+    let listener = tokio::net::TcpListener::bind(address)
+        .await
+        .with_context(|| format!("failed to listen for HTTP-01 challenges on [{address}]"))?;
+    let router = axum::Router::new().route(
+        "/.well-known/acme-challenge/:token",
+        axum::routing::get(
+            |axum::extract::Path(token): axum::extract::Path<String>| async move {
+                tokens
+                    .read()
+                    .ok()
+                    .and_then(|tokens| tokens.get(&token).cloned())
+                    .ok_or(http::StatusCode::NOT_FOUND)
+            },
+        ),
+    );
+    Ok(tokio::spawn(async move {
+        if let Err(err) = axum::serve(listener, router).await {
+            error!(?err, "failed to serve HTTP-01 challenges");
+        }
+    }))
+}
+
+/// Restore the ACME account from the cached credentials, or create an account and cache its
+/// credentials
+async fn load_or_create_account(config: &AcmeConfig) -> anyhow::Result<Account> {
+    let http = AcmeHttpClient::new(config).await?;
+    let path = config.account_credentials_path();
+    if let Ok(credentials) = tokio::fs::read(&path).await {
+        let credentials = serde_json::from_slice(&credentials)
+            .with_context(|| format!("invalid ACME account credentials at [{}]", path.display()))?;
+        return Account::from_credentials_and_http(credentials, Box::new(http))
+            .await
+            .context("failed to restore ACME account");
+    }
+    let contact = config.contact.as_slice().iter().map(String::as_str);
+    let (account, credentials) = Account::create_with_http(
+        &NewAccount {
+            contact: &contact.collect::<Vec<_>>(),
+            terms_of_service_agreed: true,
+            only_return_existing: false,
+        },
+        &config.directory_url,
+        None,
+        Box::new(http),
+    )
+    .await
+    .with_context(|| {
+        format!(
+            "failed to create ACME account with [{}]",
+            config.directory_url
+        )
+    })?;
+    let credentials =
+        serde_json::to_vec(&credentials).context("failed to encode ACME account credentials")?;
+    write_private_file(&path, &credentials)
+        .await
+        .with_context(|| {
+            format!(
+                "failed to cache ACME account credentials at [{}]",
+                path.display()
+            )
+        })?;
+    Ok(account)
+}
+
+/// Write a file that is only accessible by the current user, as it contains a private key
+async fn write_private_file(path: &Path, contents: &[u8]) -> std::io::Result<()> {
+    let mut options = tokio::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    options.mode(0o600);
+    let mut file = options.open(path).await?;
+    // The mode only applies to new files, so the permissions of an existing file are set too
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt as _;
+        file.set_permissions(std::fs::Permissions::from_mode(0o600))
+            .await?;
+    }
+    file.write_all(contents).await?;
+    file.flush().await
+}
+
+/// Build a DER-encoded certificate signing request for the domains
+fn certificate_request(domains: &[String], key: &SigningKey) -> anyhow::Result<Vec<u8>> {
+    let first = domains.first().context("no domains")?;
+    let subject = Name::from_str(&format!("CN={first}")).context("invalid domain")?;
+    let mut builder = RequestBuilder::new(subject, key).context("failed to build CSR")?;
+    builder
+        .add_extension(&subject_alt_name(domains)?)
+        .context("failed to build CSR")?;
+    builder
+        .build::<DerSignature>()
+        .context("failed to sign CSR")?
+        .to_der()
+        .context("failed to encode CSR")
+}
+
+/// Build the self-signed certificate answering the TLS-ALPN-01 challenge for a domain, given
+/// the SHA-256 digest of the key authorization
+fn challenge_certificate(
+    domain: &str,
+    key_authorization_digest: &[u8],
+) -> anyhow::Result<Arc<CertifiedKey>> {
+    let key = SigningKey::random(&mut OsRng);
+    let subject = Name::from_str(&format!("CN={domain}")).context("invalid domain")?;
+    let mut serial = [0; 16];
+    OsRng.fill_bytes(&mut serial);
+    serial[0] &= 0x7f;
+    let mut builder = CertificateBuilder::new(
+        Profile::Leaf {
+            issuer: subject.clone(),
+            enable_key_agreement: false,
+            enable_key_encipherment: false,
+        },
+        SerialNumber::new(&serial).context("invalid serial number")?,
+        Validity::from_now(Duration::from_secs(7 * 24 * 60 * 60)).context("invalid validity")?,
+        subject,
+        SubjectPublicKeyInfoOwned::from_key(*key.verifying_key()).context("invalid public key")?,
+        &key,
+    )
+    .context("failed to build challenge certificate")?;
+    builder
+        .add_extension(&subject_alt_name(&[domain.to_string()])?)
+        .context("failed to build challenge certificate")?;
+    builder
+        .add_extension(&AcmeIdentifier(
+            OctetString::new(key_authorization_digest).context("invalid digest")?,
+        ))
+        .context("failed to build challenge certificate")?;
+    let cert = builder
+        .build::<DerSignature>()
+        .context("failed to sign challenge certificate")?
+        .to_pem(LineEnding::LF)
+        .context("failed to encode challenge certificate")?;
+    certified_key(cert.as_bytes(), &key).map(|(cert, ..)| cert)
+}
+
+fn subject_alt_name(domains: &[String]) -> anyhow::Result<SubjectAltName> {
+    domains
+        .iter()
+        .map(|domain| {
+            Ia5String::new(domain)
+                .map(GeneralName::DnsName)
+                .with_context(|| format!("invalid domain [{domain}]"))
+        })
+        .collect::<anyhow::Result<_>>()
+        .map(SubjectAltName)
+}
+
+/// The `acmeIdentifier` extension of a TLS-ALPN-01 challenge certificate, containing the
+/// SHA-256 digest of the key authorization
+struct AcmeIdentifier(OctetString);
+
+impl AssociatedOid for AcmeIdentifier {
+    const OID: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.3.6.1.5.5.7.1.31");
+}
+
+impl x509_cert::der::Encode for AcmeIdentifier {
+    fn encoded_len(&self) -> x509_cert::der::Result<x509_cert::der::Length> {
+        self.0.encoded_len()
+    }
+
+    fn encode(&self, encoder: &mut impl x509_cert::der::Writer) -> x509_cert::der::Result<()> {
+        self.0.encode(encoder)
+    }
+}
+
+impl AsExtension for AcmeIdentifier {
+    fn critical(&self, _: &Name, _: &[x509_cert::ext::Extension]) -> bool {
+        true
+    }
+}
+
+/// [`HttpClient`] sending the requests of the ACME client, trusting the configured CA in
+/// addition to the system roots
+struct AcmeHttpClient(reqwest::Client);
+
+impl AcmeHttpClient {
+    async fn new(config: &AcmeConfig) -> anyhow::Result<Self> {
+        let mut http = reqwest::Client::builder().user_agent(concat!(
+            env!("CARGO_PKG_NAME"),
+            "/",
+            env!("CARGO_PKG_VERSION")
+        ));
+        if let Some(ca_file) = &config.ca_file {
+            let pem = tokio::fs::read(ca_file)
+                .await
+                .with_context(|| format!("failed to read [{}]", ca_file.display()))?;
+            http = http.add_root_certificate(
+                reqwest::Certificate::from_pem(&pem).context("invalid ACME CA certificate")?,
+            );
+        }
+        http.build()
+            .map(Self)
+            .context("failed to build HTTP client")
+    }
+}
+
+impl HttpClient for AcmeHttpClient {
+    fn request(
+        &self,
+        req: http::Request<Full<Bytes>>,
+    ) -> Pin<Box<dyn Future<Output = Result<BytesResponse, instant_acme::Error>> + Send>> {
+        let http = self.0.clone();
+        Box::pin(async move {
+            let (parts, body) = req.into_parts();
+            let Ok(body) = body.collect().await.map(Collected::to_bytes);
+            let res = http
+                .request(parts.method, parts.uri.to_string())
+                .headers(parts.headers)
+                .body(body)
+                .send()
+                .await
+                .map_err(|e| instant_acme::Error::Other(e.into()))?;
+            Ok(BytesResponse::from(http::Response::from(res)))
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::net::Ipv4Addr;
+
+    use axum_server::tls_rustls::RustlsConfig;
+    use wasmcloud_test_util::testcontainers::{
+        AsyncRunner as _, Host, ImageExt as _, Pebble, PEBBLE_HTTP_01_PORT, PEBBLE_TLS_ALPN_01_PORT,
+    };
+
+    use super::*;
+
+    /// Write a CA certificate, and a certificate it issued for `localhost` and its key, to
+    /// `ca.pem`, `cert.pem` and `key.pem` in the given directory
+    fn write_test_certificates(dir: &Path) -> anyhow::Result<()> {
+        let validity = Validity::from_now(Duration::from_secs(24 * 60 * 60))?;
+        let ca_key = SigningKey::random(&mut OsRng);
+        let ca_name = Name::from_str("CN=Test CA")?;
+        let ca = CertificateBuilder::new(
+            Profile::Root,
+            SerialNumber::from(1u32),
+            validity,
+            ca_name.clone(),
+            SubjectPublicKeyInfoOwned::from_key(*ca_key.verifying_key())?,
+            &ca_key,
+        )?
+        .build::<DerSignature>()?;
+
+        let key = SigningKey::random(&mut OsRng);
+        let mut builder = CertificateBuilder::new(
+            Profile::Leaf {
+                issuer: ca_name,
+                enable_key_agreement: false,
+                enable_key_encipherment: false,
+            },
+            SerialNumber::from(2u32),
+            validity,
+            Name::from_str("CN=localhost")?,
+            SubjectPublicKeyInfoOwned::from_key(*key.verifying_key())?,
+            &ca_key,
+        )?;
+        builder.add_extension(&SubjectAltName(vec![
+            GeneralName::DnsName(Ia5String::new("localhost")?),
+            GeneralName::IpAddress(OctetString::new(Ipv4Addr::LOCALHOST.octets())?),
+        ]))?;
+        let cert = builder.build::<DerSignature>()?;
+
+        std::fs::write(dir.join("ca.pem"), ca.to_pem(LineEnding::LF)?)?;
+        std::fs::write(dir.join("cert.pem"), cert.to_pem(LineEnding::LF)?)?;
+        std::fs::write(
+            dir.join("key.pem"),
+            key.to_pkcs8_pem(LineEnding::LF)?.as_bytes(),
+        )?;
+        Ok(())
+    }
+
+    /// Obtain a certificate from Pebble, which validates the challenges against this process
+    /// through the host gateway of the container
+    async fn obtain_certificate_from_pebble(challenge: AcmeChallenge) -> anyhow::Result<()> {
+        const DOMAINS: [&str; 2] = ["example.com", "www.example.com"];
+
+        let dir = tempfile::tempdir()?;
+        write_test_certificates(dir.path())?;
+        let mut pebble = Pebble::new(dir.path())?.with_host(DOMAINS[0], Host::HostGateway);
+        for domain in &DOMAINS[1..] {
+            pebble = pebble.with_host(*domain, Host::HostGateway);
+        }
+        let pebble = pebble.start().await?;
+        let host = pebble.get_host().await?;
+        let port = pebble.get_host_port_ipv4(14000).await?;
+
+        let config = AcmeConfig {
+            domains: DOMAINS.map(String::from).to_vec(),
+            directory_url: format!("https://{host}:{port}/dir"),
+            contact: Some("mailto:admin@example.com".into()),
+            cache_dir: dir.path().join("cache"),
+            challenge,
+            http_address: (Ipv4Addr::UNSPECIFIED, PEBBLE_HTTP_01_PORT).into(),
+            ca_file: Some(dir.path().join("ca.pem")),
+        };
+        tokio::fs::create_dir_all(&config.cache_dir).await?;
+
+        let resolver = Arc::new(AcmeCertResolver::default());
+        // TLS-ALPN-01 challenges are answered by the listener serving the certificate
+        let listener = (challenge == AcmeChallenge::TlsAlpn01).then(|| {
+            let tls = server_config(Arc::clone(&resolver) as Arc<dyn ResolvesServerCert>);
+            tokio::spawn(
+                axum_server::bind_rustls(
+                    (Ipv4Addr::UNSPECIFIED, PEBBLE_TLS_ALPN_01_PORT).into(),
+                    RustlsConfig::from_config(Arc::new(tls)),
+                )
+                .serve(axum::Router::new().into_make_service()),
+            )
+        });
+
+        let not_after = obtain_certificate(&config, &resolver).await?;
+        assert!(not_after > SystemTime::now());
+        assert!(resolver.certificate().is_some());
+
+        // The certificate is cached, along with its key and the account credentials
+        let (cached, cached_not_after) = load_cached_certificate(&config)
+            .await?
+            .context("certificate should have been cached")?;
+        assert_eq!(cached_not_after, not_after);
+        assert_eq!(
+            cached.cert,
+            resolver.certificate().context("missing certificate")?.cert
+        );
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt as _;
+            let (_, key_path) = config.certificate_paths();
+            for path in [key_path, config.account_credentials_path()] {
+                let mode = std::fs::metadata(&path)?.permissions().mode();
+                assert_eq!(mode & 0o777, 0o600, "{}", path.display());
+            }
+        }
+
+        // Renewal reuses the cached account
+        obtain_certificate(&config, &resolver).await?;
+        assert_eq!(std::fs::read_dir(&config.cache_dir)?.count(), 3);
+        if let Some(listener) = listener {
+            listener.abort();
+        }
+        Ok(())
+    }
+
+    // These tests are ignored by default as they require a container runtime to be installed
+    // to run the testcontainer, and bind the challenge ports of Pebble. In GitHub Actions CI,
+    // this only works on `linux`
+    #[ignore]
+    #[tokio::test]
+    async fn obtains_certificate_from_pebble_with_http_01() -> anyhow::Result<()> {
+        obtain_certificate_from_pebble(AcmeChallenge::Http01).await
+    }
+
+    #[ignore]
+    #[tokio::test]
+    async fn obtains_certificate_from_pebble_with_tls_alpn_01() -> anyhow::Result<()> {
+        obtain_certificate_from_pebble(AcmeChallenge::TlsAlpn01).await
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn private_files_are_only_accessible_by_owner() -> anyhow::Result<()> {
+        use std::os::unix::fs::PermissionsExt as _;
+
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("key.pem");
+        write_private_file(&path, b"first").await?;
+        assert_eq!(
+            std::fs::metadata(&path)?.permissions().mode() & 0o777,
+            0o600
+        );
+
+        // Existing files are restricted too
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o644))?;
+        write_private_file(&path, b"second").await?;
+        assert_eq!(
+            std::fs::metadata(&path)?.permissions().mode() & 0o777,
+            0o600
+        );
+        assert_eq!(std::fs::read(&path)?, b"second");
+        Ok(())
+    }
+
+    #[test]
+    fn challenge_certificate_has_acme_identifier() {
+        let digest = Sha256::digest("token.thumbprint");
+        let cert = challenge_certificate("example.com", &digest)
+            .expect("failed to build challenge certificate");
+        let cert = <x509_cert::Certificate as x509_cert::der::Decode>::from_der(&cert.cert[0].0)
+            .expect("invalid certificate");
+        let ext = cert
+            .tbs_certificate
+            .extensions
+            .iter()
+            .flatten()
+            .find(|ext| ext.extn_id == AcmeIdentifier::OID)
+            .expect("missing acmeIdentifier extension");
+        assert!(ext.critical);
+        let digest = <OctetString as x509_cert::der::Decode>::from_der(ext.extn_value.as_bytes())
+            .expect("invalid acmeIdentifier extension");
+        assert_eq!(
+            digest.as_bytes(),
+            Sha256::digest("token.thumbprint").as_slice()
+        );
+    }
+
+    #[test]
+    fn certificate_request_is_signed() {
+        let key = SigningKey::random(&mut OsRng);
+        let csr =
+            certificate_request(&["example.com".to_string()], &key).expect("failed to build CSR");
+        let csr = <x509_cert::request::CertReq as x509_cert::der::Decode>::from_der(&csr)
+            .expect("invalid CSR");
+        assert_eq!(csr.info.subject.to_string(), "CN=example.com");
+    }
+}
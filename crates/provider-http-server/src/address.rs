@@ -12,14 +12,15 @@ use std::sync::Arc;
 use anyhow::{bail, Context as _};
 use axum::extract;
 use axum::handler::Handler;
-use axum_server::tls_rustls::RustlsConfig;
 use tokio::sync::RwLock;
 use tracing::{debug, error, info, instrument};
 use wasmcloud_provider_sdk::core::LinkName;
 use wasmcloud_provider_sdk::provider::WrpcClient;
 use wasmcloud_provider_sdk::{get_connection, HostData, LinkConfig, LinkDeleteInfo, Provider};
 
+use crate::limits::RequestLimits;
 use crate::settings::default_listen_address;
+use crate::tls::ServerTls;
 use crate::{
     build_request, get_cors_layer, get_tcp_listener, invoke_component, load_settings,
     ServiceSettings,
@@ -29,8 +30,14 @@ use crate::{
 ///
 /// Indexed first by socket address to more easily detect duplicates,
 /// with the http server stored, along with a list (order matters) of components that were registered
-type HandlerLookup =
-    HashMap<SocketAddr, (Arc<HttpServerCore>, Vec<(Arc<str>, Arc<str>, WrpcClient)>)>;
+/// and the limits configured on their links
+type HandlerLookup = HashMap<
+    SocketAddr,
+    (
+        Arc<HttpServerCore>,
+        Vec<(Arc<str>, Arc<str>, WrpcClient, Arc<RequestLimits>)>,
+    ),
+>;
 
 /// `wrpc:http/incoming-handler` provider implementation in address mode
 #[derive(Clone)]
@@ -96,6 +103,7 @@ impl Provider for HttpServerProvider {
             }
         };
 
+        let limits = RequestLimits::new(&settings).context("invalid request limits")?;
         let wrpc = get_connection()
             .get_wrpc_client(link_config.target_id)
             .await
@@ -104,6 +112,7 @@ impl Provider for HttpServerProvider {
             Arc::from(link_config.target_id),
             Arc::from(link_config.link_name),
             wrpc,
+            Arc::new(limits),
         );
         let mut sockets_by_link_name = self.sockets_by_link_name.write().await;
         let mut handlers_by_socket = self.handlers_by_socket.write().await;
@@ -211,25 +220,29 @@ async fn handle_request(
         scheme,
         handlers_by_socket,
     }): extract::State<RequestContext>,
+    extract::ConnectInfo(client): extract::ConnectInfo<SocketAddr>,
     extract::Host(authority): extract::Host,
     request: extract::Request,
 ) -> impl axum::response::IntoResponse {
-    let (component_id, wrpc) = {
-        let Some((component_id, wrpc)) = handlers_by_socket
+    let (component_id, wrpc, limits) = {
+        let Some((component_id, wrpc, limits)) = handlers_by_socket
             .read()
             .await
             .get(&server_address)
             .and_then(|v| v.1.first())
-            .map(|(component_id, _, wrpc)| (Arc::clone(component_id), wrpc.clone()))
+            .map(|(component_id, _, wrpc, limits)| {
+                (Arc::clone(component_id), wrpc.clone(), Arc::clone(limits))
+            })
         else {
             return Err((
                 http::StatusCode::INTERNAL_SERVER_ERROR,
                 "no targets for HTTP request",
             ))?;
         };
-        (component_id, wrpc)
+        (component_id, wrpc, limits)
     };
 
+    let (request, permit) = limits.admit(client, request).await?;
     let timeout = settings.timeout_ms.map(Duration::from_millis);
     let req = build_request(request, scheme, authority, &settings)?;
     axum::response::Result::<_, axum::response::ErrorResponse>::Ok(
@@ -239,6 +252,7 @@ async fn handle_request(
             req,
             timeout,
             settings.cache_control.as_ref(),
+            permit,
         )
         .await,
     )
@@ -274,16 +288,12 @@ impl HttpServerCore {
 
         let target = target.to_owned();
         let task_handle = handle.clone();
-        let task = if let (Some(crt), Some(key)) =
-            (&settings.tls_cert_file, &settings.tls_priv_key_file)
-        {
+        let task = if let Some(tls) = ServerTls::new(&settings).await? {
             debug!(?addr, "bind HTTPS listener");
-            let tls = RustlsConfig::from_pem_file(crt, key)
-                .await
-                .context("failed to construct TLS config")?;
-
-            let srv = axum_server::from_tcp_rustls(listener, tls);
+            let srv = axum_server::from_tcp_rustls(listener, tls.config.clone());
             tokio::spawn(async move {
+                // Keep the TLS configuration up to date for as long as the server runs
+                let _tls = tls;
                 if let Err(e) = srv
                     .handle(task_handle)
                     .serve(
@@ -294,7 +304,7 @@ impl HttpServerCore {
                                 scheme: http::uri::Scheme::HTTPS,
                                 handlers_by_socket,
                             })
-                            .into_make_service(),
+                            .into_make_service_with_connect_info::<SocketAddr>(),
                     )
                     .await
                 {
@@ -317,7 +327,7 @@ impl HttpServerCore {
                                 scheme: http::uri::Scheme::HTTP,
                                 handlers_by_socket,
                             })
-                            .into_make_service(),
+                            .into_make_service_with_connect_info::<SocketAddr>(),
                     )
                     .await
                 {
@@ -0,0 +1,424 @@
+//! This module contains the implementation of the `wrpc:http/incoming-handler` provider in host-based mode.
+//!
+//! In host-based mode, the HTTP server listens on a single address and routes requests to different components
+//! based on the `Host` header of the request. When serving HTTPS, each link may supply its own certificate,
+//! which is selected using the server name indicated by the client (SNI).
+
+use core::time::Duration;
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::BufReader;
+use std::net::SocketAddr;
+use std::str::FromStr;
+use std::sync::Arc;
+
+use anyhow::{bail, Context as _};
+use axum::extract::{self};
+use axum::handler::Handler;
+use axum_server::tls_rustls::RustlsConfig;
+use axum_server::Handle;
+use rustls_0_21::server::{ClientHello, ResolvesServerCert};
+use rustls_0_21::sign::CertifiedKey;
+use tokio::sync::RwLock;
+use tokio::task::JoinHandle;
+use tracing::{debug, error, info, instrument};
+use wasmcloud_provider_sdk::provider::WrpcClient;
+use wasmcloud_provider_sdk::{get_connection, HostData, LinkConfig, LinkDeleteInfo, Provider};
+
+use crate::acme::{self, AcmeCertResolver, AcmeConfig};
+use crate::limits::RequestLimits;
+use crate::{
+    build_request, get_cors_layer, get_tcp_listener, invoke_component, load_settings,
+    ServiceSettings,
+};
+
+/// This struct holds both the forward and reverse mappings for host-based routing
+/// so that they can be modified by just acquiring a single lock in the [`HttpServerProvider`]
+#[derive(Default)]
+struct Router {
+    /// Lookup from a (lowercase) host name to the component ID that is handling that host, and
+    /// the limits configured on its link
+    hosts: HashMap<Arc<str>, (Arc<str>, WrpcClient, Arc<RequestLimits>)>,
+    /// Reverse lookup to find the host for a (component,link_name) pair
+    components: HashMap<(Arc<str>, Arc<str>), Arc<str>>,
+}
+
+/// `wrpc:http/incoming-handler` provider implementation with host-based routing
+#[derive(Clone)]
+pub struct HttpServerProvider {
+    /// Struct that holds the routing information based on host/component_id
+    host_router: Arc<RwLock<Router>>,
+    /// Certificates supplied by links, if serving HTTPS
+    certs: Option<Arc<SniCertResolver>>,
+    /// Settings of the listener, which provide default limits for links
+    settings: Arc<ServiceSettings>,
+    /// [`Handle`] to the server task
+    handle: Handle,
+    /// Task handle for the server task
+    task: Arc<JoinHandle<()>>,
+    /// Task obtaining and renewing the ACME certificate, if enabled
+    acme_task: Option<Arc<JoinHandle<()>>>,
+}
+
+impl Drop for HttpServerProvider {
+    fn drop(&mut self) {
+        self.handle.shutdown();
+        self.task.abort();
+        if let Some(acme_task) = &self.acme_task {
+            acme_task.abort();
+        }
+    }
+}
+
+impl HttpServerProvider {
+    pub(crate) async fn new(host_data: &HostData) -> anyhow::Result<Self> {
+        let default_address = host_data
+            .config
+            .get("default_address")
+            .map(|s| SocketAddr::from_str(s))
+            .transpose()
+            .context("failed to parse default_address")?;
+        let settings = load_settings(default_address, &host_data.config)
+            .context("failed to load settings in host mode")?;
+        let settings = Arc::new(settings);
+        let sni_enabled = host_data
+            .config
+            .get("tls_sni_enabled")
+            .is_some_and(|v| v.parse().unwrap_or(false));
+
+        let host_router = Arc::default();
+
+        let addr = settings.address;
+        info!(
+            %addr,
+            "httpserver starting listener in host-based mode",
+        );
+        let cors = get_cors_layer(&settings)?;
+        let listener = get_tcp_listener(&settings)?;
+        let service = handle_request.layer(cors);
+
+        let handle = axum_server::Handle::new();
+        let task_handle = handle.clone();
+        let task_router = Arc::clone(&host_router);
+        let task_settings = Arc::clone(&settings);
+        let default_cert = match (&settings.tls_cert_file, &settings.tls_priv_key_file) {
+            (Some(crt), Some(key)) => Some(load_certified_key(crt, key)?),
+            _ => None,
+        };
+        let (acme, acme_task) = match AcmeConfig::from_settings(&settings) {
+            Some(config) => {
+                let (resolver, task) = acme::start(config)
+                    .await
+                    .context("failed to start ACME certificate management")?;
+                (Some(resolver), Some(Arc::new(task)))
+            }
+            None => (None, None),
+        };
+        let (certs, task) = if sni_enabled || default_cert.is_some() || acme.is_some() {
+            debug!(?addr, "bind HTTPS listener");
+            let certs = Arc::new(SniCertResolver {
+                certs: std::sync::RwLock::default(),
+                default: default_cert,
+                acme,
+            });
+            let tls = if certs.acme.is_some() {
+                acme::server_config(Arc::clone(&certs) as Arc<dyn ResolvesServerCert>)
+            } else {
+                let mut tls = rustls_0_21::ServerConfig::builder()
+                    .with_safe_defaults()
+                    .with_no_client_auth()
+                    .with_cert_resolver(Arc::clone(&certs) as Arc<dyn ResolvesServerCert>);
+                tls.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+                tls
+            };
+            let tls = RustlsConfig::from_config(Arc::new(tls));
+
+            let task = tokio::spawn(async move {
+                if let Err(e) = axum_server::from_tcp_rustls(listener, tls)
+                    .handle(task_handle)
+                    .serve(
+                        service
+                            .with_state(RequestContext {
+                                router: task_router,
+                                scheme: http::uri::Scheme::HTTPS,
+                                settings: task_settings,
+                            })
+                            .into_make_service_with_connect_info::<SocketAddr>(),
+                    )
+                    .await
+                {
+                    error!(error = %e, "failed to serve HTTPS for host-based mode");
+                }
+            });
+            (Some(certs), task)
+        } else {
+            debug!(?addr, "bind HTTP listener");
+
+            let task = tokio::spawn(async move {
+                if let Err(e) = axum_server::from_tcp(listener)
+                    .handle(task_handle)
+                    .serve(
+                        service
+                            .with_state(RequestContext {
+                                router: task_router,
+                                scheme: http::uri::Scheme::HTTP,
+                                settings: task_settings,
+                            })
+                            .into_make_service_with_connect_info::<SocketAddr>(),
+                    )
+                    .await
+                {
+                    error!(error = %e, "failed to serve HTTP for host-based mode");
+                }
+            });
+            (None, task)
+        };
+
+        Ok(Self {
+            host_router,
+            certs,
+            settings,
+            handle,
+            task: Arc::new(task),
+            acme_task,
+        })
+    }
+}
+
+impl Provider for HttpServerProvider {
+    /// This is called when the HTTP server provider is linked to a component
+    ///
+    /// This HTTP server mode will register the host in the link for routing to the target
+    /// component when a request for that host is received on the listen address, along with the
+    /// certificate for the host, if any.
+    async fn receive_link_config_as_source(
+        &self,
+        link_config: LinkConfig<'_>,
+    ) -> anyhow::Result<()> {
+        let Some(host) = link_config
+            .config
+            .get("host")
+            .map(|h| h.to_ascii_lowercase())
+        else {
+            error!(?link_config.config, ?link_config.target_id, "host not found in link config, cannot register host");
+            bail!(
+                "host not found in link config, cannot register host for component {}",
+                link_config.target_id
+            );
+        };
+
+        // Limits may be set on the link, falling back to those set on the provider
+        let link_settings =
+            load_settings(None, link_config.config).context("failed to load settings for link")?;
+        let limits = RequestLimits::for_link(&link_settings, &self.settings)
+            .context("invalid request limits")?;
+        let cert = match (
+            &link_settings.tls_cert_file,
+            &link_settings.tls_priv_key_file,
+        ) {
+            (Some(crt), Some(key)) if self.certs.is_some() => Some(load_certified_key(crt, key)?),
+            (Some(_), Some(_)) => {
+                bail!("link for host {host} supplies a certificate, but the listener does not serve HTTPS")
+            }
+            _ => None,
+        };
+
+        let target = Arc::from(link_config.target_id);
+        let name = Arc::from(link_config.link_name);
+
+        let key = (Arc::clone(&target), Arc::clone(&name));
+
+        let mut host_router = self.host_router.write().await;
+        if host_router.components.contains_key(&key) {
+            // When we can return errors from links, tell the host this was invalid
+            bail!("Component {target} already has a host registered with link name {name}");
+        }
+        if host_router.hosts.contains_key(host.as_str()) {
+            // When we can return errors from links, tell the host this was invalid
+            bail!("Host {host} already in use by a different component");
+        }
+
+        let wrpc = get_connection()
+            .get_wrpc_client(link_config.target_id)
+            .await
+            .context("failed to construct wRPC client")?;
+
+        if let (Some(certs), Some(cert)) = (&self.certs, cert) {
+            certs.insert(&host, cert);
+        }
+        let host = Arc::from(host);
+        // Insert the host into the hosts map for future lookups
+        host_router.components.insert(key, Arc::clone(&host));
+        host_router
+            .hosts
+            .insert(host, (target, wrpc, Arc::new(limits)));
+
+        Ok(())
+    }
+
+    /// Remove the host (and its certificate) for a particular component/link_name pair
+    #[instrument(level = "debug", skip_all, fields(target_id = info.get_target_id()))]
+    async fn delete_link_as_source(&self, info: impl LinkDeleteInfo) -> anyhow::Result<()> {
+        debug!(
+            source = info.get_source_id(),
+            target = info.get_target_id(),
+            link = info.get_link_name(),
+            "deleting http host link"
+        );
+        let component_id = info.get_target_id();
+        let link_name = info.get_link_name();
+
+        let mut host_router = self.host_router.write().await;
+        let host = host_router
+            .components
+            .remove(&(Arc::from(component_id), Arc::from(link_name)));
+        if let Some(host) = host {
+            host_router.hosts.remove(&host);
+            if let Some(certs) = &self.certs {
+                certs.remove(&host);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Handle shutdown request by shutting down the http server task
+    async fn shutdown(&self) -> anyhow::Result<()> {
+        self.handle.shutdown();
+        self.task.abort();
+
+        Ok(())
+    }
+}
+
+#[derive(Clone)]
+struct RequestContext {
+    router: Arc<RwLock<Router>>,
+    scheme: http::uri::Scheme,
+    settings: Arc<ServiceSettings>,
+}
+
+/// Handle an HTTP request by looking up the component ID for the host and invoking the component
+#[instrument(level = "debug", skip(router, settings))]
+async fn handle_request(
+    extract::State(RequestContext {
+        router,
+        scheme,
+        settings,
+    }): extract::State<RequestContext>,
+    extract::ConnectInfo(client): extract::ConnectInfo<SocketAddr>,
+    extract::Host(authority): extract::Host,
+    request: extract::Request,
+) -> impl axum::response::IntoResponse {
+    let host = host_name(&authority).to_ascii_lowercase();
+    let Some((target_component, wrpc, limits)) =
+        router.read().await.hosts.get(host.as_str()).cloned()
+    else {
+        Err((http::StatusCode::NOT_FOUND, "host not found"))?
+    };
+    let timeout = settings.timeout_ms.map(Duration::from_millis);
+    let req = build_request(request, scheme, authority, &settings)?;
+    let (req, permit) = limits.admit(client, req).await?;
+    axum::response::Result::<_, axum::response::ErrorResponse>::Ok(
+        invoke_component(
+            &wrpc,
+            &target_component,
+            req,
+            timeout,
+            settings.cache_control.as_ref(),
+            permit,
+        )
+        .await,
+    )
+}
+
+/// Strip the port, if any, from an authority
+fn host_name(authority: &str) -> &str {
+    if authority.starts_with('[') {
+        // IPv6 literal, e.g. `[::1]:8080`
+        return authority
+            .split_once(']')
+            .map_or(authority, |(host, _)| &authority[..=host.len()]);
+    }
+    authority
+        .rsplit_once(':')
+        .map_or(authority, |(host, _)| host)
+}
+
+/// Certificates for each host, selected by the server name indicated by the client
+struct SniCertResolver {
+    certs: std::sync::RwLock<HashMap<String, Arc<CertifiedKey>>>,
+    /// Certificate used when no certificate matches the server name
+    default: Option<Arc<CertifiedKey>>,
+    /// Certificate obtained using ACME, used when no certificate matches the server name, which
+    /// also answers TLS-ALPN-01 challenges
+    acme: Option<Arc<AcmeCertResolver>>,
+}
+
+impl SniCertResolver {
+    fn insert(&self, host: &str, cert: Arc<CertifiedKey>) {
+        if let Ok(mut certs) = self.certs.write() {
+            certs.insert(host.to_string(), cert);
+        }
+    }
+
+    fn remove(&self, host: &str) {
+        if let Ok(mut certs) = self.certs.write() {
+            certs.remove(host);
+        }
+    }
+}
+
+impl ResolvesServerCert for SniCertResolver {
+    fn resolve(&self, client_hello: ClientHello) -> Option<Arc<CertifiedKey>> {
+        if let Some(acme) = &self.acme {
+            if acme::is_challenge(&client_hello) {
+                return acme.resolve(client_hello);
+            }
+        }
+        client_hello
+            .server_name()
+            .and_then(|name| {
+                self.certs
+                    .read()
+                    .ok()?
+                    .get(&name.to_ascii_lowercase())
+                    .cloned()
+            })
+            .or_else(|| self.default.clone())
+            .or_else(|| self.acme.as_ref()?.certificate())
+    }
+}
+
+/// Load a PEM-encoded certificate chain and private key
+fn load_certified_key(cert_file: &str, key_file: &str) -> anyhow::Result<Arc<CertifiedKey>> {
+    let certs = rustls_pemfile::certs(&mut BufReader::new(
+        File::open(cert_file).with_context(|| format!("failed to open [{cert_file}]"))?,
+    ))
+    .map(|cert| cert.map(|cert| rustls_0_21::Certificate(cert.to_vec())))
+    .collect::<Result<Vec<_>, _>>()
+    .with_context(|| format!("failed to parse certificates in [{cert_file}]"))?;
+    let key = rustls_pemfile::private_key(&mut BufReader::new(
+        File::open(key_file).with_context(|| format!("failed to open [{key_file}]"))?,
+    ))
+    .with_context(|| format!("failed to parse private key in [{key_file}]"))?
+    .with_context(|| format!("no private key found in [{key_file}]"))?;
+    let key =
+        rustls_0_21::sign::any_supported_type(&rustls_0_21::PrivateKey(key.secret_der().to_vec()))
+            .with_context(|| format!("unsupported private key in [{key_file}]"))?;
+    Ok(Arc::new(CertifiedKey::new(certs, key)))
+}
+
+#[cfg(test)]
+mod test {
+    use super::host_name;
+
+    #[test]
+    fn host_name_strips_port() {
+        assert_eq!(host_name("example.com"), "example.com");
+        assert_eq!(host_name("example.com:8443"), "example.com");
+        assert_eq!(host_name("[::1]:8080"), "[::1]");
+        assert_eq!(host_name("[::1]"), "[::1]");
+    }
+}
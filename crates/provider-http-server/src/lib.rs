@@ -13,9 +13,11 @@
 //!   work as-is for development purposes, and may need refinement
 //!   for production if a more secure configuration is required.
 //! - All settings can be specified at runtime, using per-component link settings:
-//!   - bind path/address
-//!   - TLS
+//!   - bind path/address/host
+//!   - TLS, with a certificate per host (SNI) when routing by host
+//!   - Certificates obtained and renewed with ACME, or reloaded when their files change
 //!   - Cors
+//!   - Request body size, concurrency and rate limits
 //! - Flexible confiuration loading: from host, or from local toml or json file.
 //! - Fully asynchronous, using tokio lightweight "green" threads
 //! - Thread pool (for managing a pool of OS threads). The default
@@ -35,6 +37,7 @@ use axum::extract;
 use bytes::Bytes;
 use futures::Stream;
 use pin_project_lite::pin_project;
+use tokio::sync::OwnedSemaphorePermit;
 use tokio::task::JoinHandle;
 use tokio::{spawn, time};
 use tower_http::cors::{self, CorsLayer};
@@ -43,10 +46,14 @@ use wasmcloud_provider_sdk::provider::WrpcClient;
 use wasmcloud_provider_sdk::{initialize_observability, load_host_data, run_provider};
 use wrpc_interface_http::InvokeIncomingHandler as _;
 
+mod acme;
 mod address;
+mod host;
+mod limits;
 mod path;
 mod settings;
-pub use settings::{default_listen_address, load_settings, ServiceSettings};
+mod tls;
+pub use settings::{default_listen_address, load_settings, AcmeChallenge, ServiceSettings};
 
 pub async fn run() -> anyhow::Result<()> {
     initialize_observability!(
@@ -76,6 +83,17 @@ pub async fn run() -> anyhow::Result<()> {
             .await?
             .await;
         }
+        // Run provider in host mode
+        Some("host") => {
+            run_provider(
+                host::HttpServerProvider::new(host_data).await.context(
+                    "failed to create host-mode HTTP server provider from hostdata configuration",
+                )?,
+                "http-server-provider",
+            )
+            .await?
+            .await;
+        }
         Some(other) => bail!("unknown routing_mode: {other}"),
     };
 
@@ -138,12 +156,15 @@ pub(crate) fn build_request(
 }
 
 /// Invoke a component with the given request
+///
+/// The `permit` for the request, if any, is held until the response body has been sent.
 pub(crate) async fn invoke_component(
     wrpc: &WrpcClient,
     target: &str,
     req: http::Request<axum::body::Body>,
     timeout: Option<Duration>,
     cache_control: Option<&String>,
+    permit: Option<OwnedSemaphorePermit>,
 ) -> impl axum::response::IntoResponse {
     // Create a new wRPC client with all headers from the current span injected
     let mut cx = async_nats::HeaderMap::new();
@@ -181,6 +202,7 @@ pub(crate) async fn invoke_component(
         body,
         errors,
         io,
+        _permit: permit,
     }))
 }
 
@@ -316,6 +338,7 @@ pin_project! {
         errors: Box<dyn Stream<Item = wrpc_interface_http::HttpBodyError<axum::Error>> + Send + Unpin>,
         #[pin]
         io: Option<JoinHandle<anyhow::Result<()>>>,
+        _permit: Option<OwnedSemaphorePermit>,
     }
 }
 
@@ -0,0 +1,315 @@
+//! Limits on the requests forwarded to a linked component, configured per link via
+//! [`ServiceSettings`].
+//!
+//! Requests are checked against the rate limit first (429), then the body size limit (413) and
+//! finally the concurrency limit (503).
+
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use anyhow::Context as _;
+use axum::extract;
+use axum::response::ErrorResponse;
+use http_body_util::{BodyExt as _, LengthLimitError, Limited};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+use tracing::debug;
+
+use crate::ServiceSettings;
+
+/// Number of rate limiting keys tracked before keys with full buckets are forgotten
+const MAX_TRACKED_KEYS: usize = 10_000;
+
+/// Limits applied to the requests forwarded to a single linked component
+#[derive(Debug, Default)]
+pub(crate) struct RequestLimits {
+    /// Maximum size of a request body, in bytes
+    max_body_bytes: Option<u64>,
+    /// Permits for requests in flight to the component
+    in_flight: Option<Arc<Semaphore>>,
+    /// Limiter for the rate of requests from each client
+    rate_limiter: Option<RateLimiter>,
+}
+
+impl RequestLimits {
+    /// Construct the limits configured in the given [`ServiceSettings`]
+    pub(crate) fn new(settings: &ServiceSettings) -> anyhow::Result<Self> {
+        let rate_limiter = settings
+            .rate_limit_rps
+            .map(|rps| {
+                let key_header = settings
+                    .rate_limit_key_header
+                    .as_deref()
+                    .map(http::HeaderName::try_from)
+                    .transpose()
+                    .context("invalid rate_limit_key_header")?;
+                anyhow::Ok(RateLimiter::new(
+                    rps,
+                    settings.rate_limit_burst.unwrap_or(rps),
+                    key_header,
+                ))
+            })
+            .transpose()?;
+        Ok(Self {
+            max_body_bytes: settings.max_body_bytes,
+            in_flight: settings
+                .max_concurrent_requests
+                .map(|max| Arc::new(Semaphore::new(max))),
+            rate_limiter,
+        })
+    }
+
+    /// Construct the limits configured on a link, falling back to the limits in `defaults` for
+    /// any that are not set
+    pub(crate) fn for_link(
+        link_settings: &ServiceSettings,
+        defaults: &ServiceSettings,
+    ) -> anyhow::Result<Self> {
+        Self::new(&ServiceSettings {
+            max_body_bytes: link_settings.max_body_bytes.or(defaults.max_body_bytes),
+            max_concurrent_requests: link_settings
+                .max_concurrent_requests
+                .or(defaults.max_concurrent_requests),
+            rate_limit_rps: link_settings.rate_limit_rps.or(defaults.rate_limit_rps),
+            rate_limit_burst: link_settings.rate_limit_burst.or(defaults.rate_limit_burst),
+            rate_limit_key_header: link_settings
+                .rate_limit_key_header
+                .clone()
+                .or_else(|| defaults.rate_limit_key_header.clone()),
+            ..ServiceSettings::default()
+        })
+    }
+
+    /// Check a request received from `client` against the limits, returning the request to
+    /// forward and a permit to hold until its response has been sent.
+    ///
+    /// If the body size is limited and the request does not declare a `Content-Length`, the body
+    /// is read before the request is forwarded, so that it can be rejected as a whole once it
+    /// grows past the limit.
+    pub(crate) async fn admit(
+        &self,
+        client: SocketAddr,
+        request: extract::Request,
+    ) -> Result<(extract::Request, Option<OwnedSemaphorePermit>), ErrorResponse> {
+        if let Some(rate_limiter) = &self.rate_limiter {
+            if !rate_limiter.try_acquire(client, request.headers(), Instant::now()) {
+                debug!(%client, "request rate limit exceeded");
+                Err((http::StatusCode::TOO_MANY_REQUESTS, "rate limit exceeded"))?;
+            }
+        }
+
+        let request = if let Some(max_body_bytes) = self.max_body_bytes {
+            let content_length = request
+                .headers()
+                .get(http::header::CONTENT_LENGTH)
+                .and_then(|v| v.to_str().ok())
+                .and_then(|v| v.parse::<u64>().ok());
+            if content_length.is_some_and(|len| len > max_body_bytes) {
+                debug!(?content_length, max_body_bytes, "request body too large");
+                Err((
+                    http::StatusCode::PAYLOAD_TOO_LARGE,
+                    "request body too large",
+                ))?;
+            }
+            if content_length.is_some() {
+                request
+            } else {
+                let (parts, body) = request.into_parts();
+                let limit = usize::try_from(max_body_bytes).unwrap_or(usize::MAX);
+                let body = match Limited::new(body, limit).collect().await {
+                    Ok(body) => body.to_bytes(),
+                    Err(err) if err.is::<LengthLimitError>() => {
+                        debug!(max_body_bytes, "request body too large");
+                        Err((
+                            http::StatusCode::PAYLOAD_TOO_LARGE,
+                            "request body too large",
+                        ))?
+                    }
+                    Err(err) => {
+                        debug!(?err, "failed to read request body");
+                        Err((http::StatusCode::BAD_REQUEST, "failed to read request body"))?
+                    }
+                };
+                extract::Request::from_parts(parts, axum::body::Body::from(body))
+            }
+        } else {
+            request
+        };
+
+        let permit = self
+            .in_flight
+            .as_ref()
+            .map(|in_flight| {
+                Arc::clone(in_flight).try_acquire_owned().map_err(|_| {
+                    debug!("too many requests in flight");
+                    (
+                        http::StatusCode::SERVICE_UNAVAILABLE,
+                        "too many requests in flight",
+                    )
+                })
+            })
+            .transpose()?;
+        Ok((request, permit))
+    }
+}
+
+/// Token bucket rate limiter, keeping one bucket per client IP or value of a configured header
+#[derive(Debug)]
+struct RateLimiter {
+    /// Tokens added to each bucket per second
+    rate: f64,
+    /// Maximum number of tokens in a bucket
+    burst: f64,
+    /// Header keying the buckets, instead of the client IP
+    key_header: Option<http::HeaderName>,
+    buckets: Mutex<HashMap<String, TokenBucket>>,
+}
+
+#[derive(Debug)]
+struct TokenBucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+impl TokenBucket {
+    /// Add the tokens accumulated since the last update, returning the new number of tokens
+    fn refill(&mut self, now: Instant, rate: f64, burst: f64) -> f64 {
+        let elapsed = now.saturating_duration_since(self.updated);
+        self.tokens = (self.tokens + elapsed.as_secs_f64() * rate).min(burst);
+        self.updated = now;
+        self.tokens
+    }
+}
+
+impl RateLimiter {
+    fn new(rate: u32, burst: u32, key_header: Option<http::HeaderName>) -> Self {
+        Self {
+            rate: rate.into(),
+            burst: burst.max(1).into(),
+            key_header,
+            buckets: Mutex::default(),
+        }
+    }
+
+    /// Take a token from the bucket for the request, returning `false` if it is empty
+    ///
+    /// Requests missing the configured key header are keyed by client IP.
+    fn try_acquire(&self, client: SocketAddr, headers: &http::HeaderMap, now: Instant) -> bool {
+        let key = self
+            .key_header
+            .as_ref()
+            .and_then(|name| headers.get(name))
+            .and_then(|v| v.to_str().ok())
+            .map_or_else(|| client.ip().to_string(), str::to_string);
+
+        let Ok(mut buckets) = self.buckets.lock() else {
+            return true;
+        };
+        if buckets.len() >= MAX_TRACKED_KEYS {
+            // Full buckets are equivalent to new ones, so can be dropped
+            buckets.retain(|_, bucket| bucket.refill(now, self.rate, self.burst) < self.burst);
+        }
+        let bucket = buckets.entry(key).or_insert(TokenBucket {
+            tokens: self.burst,
+            updated: now,
+        });
+        if bucket.refill(now, self.rate, self.burst) < 1.0 {
+            return false;
+        }
+        bucket.tokens -= 1.0;
+        true
+    }
+
+    /// Time needed to refill a single token
+    #[cfg(test)]
+    fn token_interval(&self) -> core::time::Duration {
+        core::time::Duration::from_secs_f64(1.0 / self.rate)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::net::{Ipv4Addr, SocketAddr};
+    use std::time::Instant;
+
+    use axum::response::IntoResponse as _;
+    use http_body_util::BodyExt as _;
+
+    use super::{RateLimiter, RequestLimits};
+    use crate::ServiceSettings;
+
+    /// Build a request with a streamed body and no `Content-Length`, as sent with chunked
+    /// transfer encoding
+    fn chunked_request(chunks: &[&'static str]) -> axum::extract::Request {
+        let chunks = chunks
+            .iter()
+            .map(|chunk| Ok::<_, std::io::Error>(bytes::Bytes::from_static(chunk.as_bytes())))
+            .collect::<Vec<_>>();
+        http::Request::builder()
+            .method(http::Method::POST)
+            .uri("/")
+            .body(axum::body::Body::from_stream(futures::stream::iter(chunks)))
+            .expect("failed to build request")
+    }
+
+    #[tokio::test]
+    async fn chunked_body_over_limit_is_rejected() {
+        let limits = RequestLimits::new(&ServiceSettings {
+            max_body_bytes: Some(8),
+            ..ServiceSettings::default()
+        })
+        .expect("failed to build limits");
+        let client = SocketAddr::from((Ipv4Addr::LOCALHOST, 1234));
+
+        let (request, _) = limits
+            .admit(client, chunked_request(&["1234", "5678"]))
+            .await
+            .expect("request within the limit should be admitted");
+        let body = request
+            .into_body()
+            .collect()
+            .await
+            .expect("failed to read body");
+        assert_eq!(body.to_bytes(), "12345678");
+
+        let res = limits
+            .admit(client, chunked_request(&["1234", "5678", "9"]))
+            .await
+            .map(|_| ());
+        assert_eq!(
+            res.into_response().status(),
+            http::StatusCode::PAYLOAD_TOO_LARGE
+        );
+    }
+
+    #[test]
+    fn rate_limiter_refills() {
+        let limiter = RateLimiter::new(2, 3, None);
+        let client = SocketAddr::from((Ipv4Addr::LOCALHOST, 1234));
+        let headers = http::HeaderMap::new();
+        let now = Instant::now();
+        for _ in 0..3 {
+            assert!(limiter.try_acquire(client, &headers, now));
+        }
+        assert!(!limiter.try_acquire(client, &headers, now));
+        assert!(limiter.try_acquire(client, &headers, now + limiter.token_interval()));
+        assert!(!limiter.try_acquire(client, &headers, now + limiter.token_interval()));
+    }
+
+    #[test]
+    fn rate_limiter_keys_by_header() {
+        let limiter = RateLimiter::new(1, 1, Some(http::HeaderName::from_static("x-api-key")));
+        let client = SocketAddr::from((Ipv4Addr::LOCALHOST, 1234));
+        let now = Instant::now();
+        let mut headers = http::HeaderMap::new();
+        headers.insert("x-api-key", http::HeaderValue::from_static("one"));
+        assert!(limiter.try_acquire(client, &headers, now));
+        assert!(!limiter.try_acquire(client, &headers, now));
+        headers.insert("x-api-key", http::HeaderValue::from_static("two"));
+        assert!(limiter.try_acquire(client, &headers, now));
+        // Without the header, the client IP is used
+        assert!(limiter.try_acquire(client, &http::HeaderMap::new(), now));
+        assert!(!limiter.try_acquire(client, &http::HeaderMap::new(), now));
+    }
+}
@@ -13,7 +13,6 @@ use std::sync::Arc;
 use anyhow::{bail, Context as _};
 use axum::extract::{self};
 use axum::handler::Handler;
-use axum_server::tls_rustls::RustlsConfig;
 use axum_server::Handle;
 use tokio::sync::RwLock;
 use tokio::task::JoinHandle;
@@ -21,6 +20,8 @@ use tracing::{debug, error, info, instrument};
 use wasmcloud_provider_sdk::provider::WrpcClient;
 use wasmcloud_provider_sdk::{get_connection, HostData, LinkConfig, LinkDeleteInfo, Provider};
 
+use crate::limits::RequestLimits;
+use crate::tls::ServerTls;
 use crate::{
     build_request, get_cors_layer, get_tcp_listener, invoke_component, load_settings,
     ServiceSettings,
@@ -30,8 +31,9 @@ use crate::{
 /// so that they can be modified by just acquiring a single lock in the [`HttpServerProvider`]
 #[derive(Default)]
 struct Router {
-    /// Lookup from a path to the component ID that is handling that path
-    paths: HashMap<Arc<str>, (Arc<str>, WrpcClient)>,
+    /// Lookup from a path to the component ID that is handling that path, and the limits
+    /// configured on its link
+    paths: HashMap<Arc<str>, (Arc<str>, WrpcClient, Arc<RequestLimits>)>,
     /// Reverse lookup to find the path for a (component,link_name) pair
     components: HashMap<(Arc<str>, Arc<str>), Arc<str>>,
 }
@@ -41,6 +43,8 @@ struct Router {
 pub struct HttpServerProvider {
     /// Struct that holds the routing information based on path/component_id
     path_router: Arc<RwLock<Router>>,
+    /// Settings of the listener, which provide default limits for links
+    settings: Arc<ServiceSettings>,
     /// [`Handle`] to the server task
     handle: Handle,
     /// Task handle for the server task
@@ -80,25 +84,20 @@ impl HttpServerProvider {
         let handle = axum_server::Handle::new();
         let task_handle = handle.clone();
         let task_router = Arc::clone(&path_router);
-        let task = if let (Some(crt), Some(key)) =
-            (&settings.tls_cert_file, &settings.tls_priv_key_file)
-        {
+        let task_settings = Arc::clone(&settings);
+        let task = if let Some(tls) = ServerTls::new(&settings).await? {
             debug!(?addr, "bind HTTPS listener");
-            let tls = RustlsConfig::from_pem_file(crt, key)
-                .await
-                .context("failed to construct TLS config")?;
-
             tokio::spawn(async move {
-                if let Err(e) = axum_server::from_tcp_rustls(listener, tls)
+                if let Err(e) = axum_server::from_tcp_rustls(listener, tls.config.clone())
                     .handle(task_handle)
                     .serve(
                         service
                             .with_state(RequestContext {
                                 router: task_router,
                                 scheme: http::uri::Scheme::HTTPS,
-                                settings: Arc::clone(&settings),
+                                settings: Arc::clone(&task_settings),
                             })
-                            .into_make_service(),
+                            .into_make_service_with_connect_info::<SocketAddr>(),
                     )
                     .await
                 {
@@ -116,9 +115,9 @@ impl HttpServerProvider {
                             .with_state(RequestContext {
                                 router: task_router,
                                 scheme: http::uri::Scheme::HTTP,
-                                settings: Arc::clone(&settings),
+                                settings: Arc::clone(&task_settings),
                             })
-                            .into_make_service(),
+                            .into_make_service_with_connect_info::<SocketAddr>(),
                     )
                     .await
                 {
@@ -129,6 +128,7 @@ impl HttpServerProvider {
 
         Ok(Self {
             path_router,
+            settings,
             handle,
             task: Arc::new(task),
         })
@@ -152,6 +152,12 @@ impl Provider for HttpServerProvider {
             );
         };
 
+        // Limits may be set on the link, falling back to those set on the provider
+        let link_settings =
+            load_settings(None, link_config.config).context("failed to load settings for link")?;
+        let limits = RequestLimits::for_link(&link_settings, &self.settings)
+            .context("invalid request limits")?;
+
         let target = Arc::from(link_config.target_id);
         let name = Arc::from(link_config.link_name);
 
@@ -175,7 +181,9 @@ impl Provider for HttpServerProvider {
         let path = Arc::from(path.clone());
         // Insert the path into the paths map for future lookups
         path_router.components.insert(key, Arc::clone(&path));
-        path_router.paths.insert(path, (target, wrpc));
+        path_router
+            .paths
+            .insert(path, (target, wrpc, Arc::new(limits)));
 
         Ok(())
     }
@@ -227,15 +235,18 @@ async fn handle_request(
         scheme,
         settings,
     }): extract::State<RequestContext>,
+    extract::ConnectInfo(client): extract::ConnectInfo<SocketAddr>,
     extract::Host(authority): extract::Host,
     request: extract::Request,
 ) -> impl axum::response::IntoResponse {
     let timeout = settings.timeout_ms.map(Duration::from_millis);
     let req = build_request(request, scheme, authority, &settings)?;
     let path = req.uri().path();
-    let Some((target_component, wrpc)) = router.read().await.paths.get(path).cloned() else {
+    let Some((target_component, wrpc, limits)) = router.read().await.paths.get(path).cloned()
+    else {
         Err((http::StatusCode::NOT_FOUND, "path not found"))?
     };
+    let (req, permit) = limits.admit(client, req).await?;
     axum::response::Result::<_, axum::response::ErrorResponse>::Ok(
         invoke_component(
             &wrpc,
@@ -243,6 +254,7 @@ async fn handle_request(
             req,
             timeout,
             settings.cache_control.as_ref(),
+            permit,
         )
         .await,
     )
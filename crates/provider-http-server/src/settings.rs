@@ -83,6 +83,47 @@ pub struct ServiceSettings {
     pub cors: Cors,
     #[serde(default)]
     pub disable_keepalive: Option<bool>,
+    /// Maximum size (bytes) of a request body. Larger requests receive a 413 response
+    #[serde(default)]
+    pub max_body_bytes: Option<u64>,
+    /// Maximum number of requests in flight to the component. Requests over the limit
+    /// receive a 503 response
+    #[serde(default)]
+    pub max_concurrent_requests: Option<usize>,
+    /// Sustained number of requests per second allowed from each client. Requests over the
+    /// limit receive a 429 response
+    #[serde(default)]
+    pub rate_limit_rps: Option<u32>,
+    /// Number of requests a client may burst above `rate_limit_rps`. Defaults to `rate_limit_rps`
+    #[serde(default)]
+    pub rate_limit_burst: Option<u32>,
+    /// Header identifying the client for rate limiting. If not set, or missing from a request,
+    /// the client IP address is used
+    #[serde(default)]
+    pub rate_limit_key_header: Option<String>,
+    /// Domains to obtain a certificate for using ACME, instead of loading
+    /// `tls_cert_file` and `tls_priv_key_file`
+    #[serde(default)]
+    pub acme_domains: Option<Vec<String>>,
+    /// URL of the ACME directory. Defaults to Let's Encrypt
+    #[serde(default)]
+    pub acme_directory_url: Option<String>,
+    /// Contact for the ACME account, e.g. `mailto:admin@example.com`
+    #[serde(default)]
+    pub acme_contact: Option<String>,
+    /// Directory where the ACME account key and certificates are cached
+    #[serde(default)]
+    pub acme_cache_dir: Option<String>,
+    /// Challenge used to prove control of the domains. Defaults to [`AcmeChallenge::TlsAlpn01`]
+    #[serde(default)]
+    pub acme_challenge: Option<AcmeChallenge>,
+    /// Address to answer HTTP-01 challenges on, which must be reachable on port 80 of the domains
+    #[serde(default)]
+    pub acme_http_address: Option<SocketAddr>,
+    /// path to a PEM-encoded CA certificate to trust for the ACME directory, in addition to the
+    /// system roots, e.g. for a test ACME server
+    #[serde(default)]
+    pub acme_ca_file: Option<String>,
 }
 
 impl Default for ServiceSettings {
@@ -103,6 +144,18 @@ impl Default for ServiceSettings {
             tls: Tls::default(),
             cors: Cors::default(),
             disable_keepalive: None,
+            max_body_bytes: None,
+            max_concurrent_requests: None,
+            rate_limit_rps: None,
+            rate_limit_burst: None,
+            rate_limit_key_header: None,
+            acme_domains: None,
+            acme_directory_url: None,
+            acme_contact: None,
+            acme_cache_dir: None,
+            acme_challenge: None,
+            acme_http_address: None,
+            acme_ca_file: None,
         }
     }
 }
@@ -129,6 +182,18 @@ impl ServiceSettings {
                 tls: Tls::default(),
                 cors: Cors::default(),
                 disable_keepalive: s.disable_keepalive,
+                max_body_bytes: s.max_body_bytes,
+                max_concurrent_requests: s.max_concurrent_requests,
+                rate_limit_rps: s.rate_limit_rps,
+                rate_limit_burst: s.rate_limit_burst,
+                rate_limit_key_header: s.rate_limit_key_header,
+                acme_domains: s.acme_domains,
+                acme_directory_url: s.acme_directory_url,
+                acme_contact: s.acme_contact,
+                acme_cache_dir: s.acme_cache_dir,
+                acme_challenge: s.acme_challenge,
+                acme_http_address: s.acme_http_address,
+                acme_ca_file: s.acme_ca_file,
             })
             .map_err(|e| HttpServerError::Settings(format!("invalid json: {e}")))
     }
@@ -176,6 +241,34 @@ impl ServiceSettings {
                 errors.push(format!("Invalid Cache Control header : '{cache_control}'"));
             }
         }
+        if self.max_concurrent_requests == Some(0) {
+            errors.push("'max_concurrent_requests' must be greater than zero".to_string());
+        }
+        if self.rate_limit_rps == Some(0) {
+            errors.push("'rate_limit_rps' must be greater than zero".to_string());
+        }
+        if self.rate_limit_burst.is_some() && self.rate_limit_rps.is_none() {
+            errors.push("'rate_limit_burst' requires 'rate_limit_rps' to be set".to_string());
+        }
+        if let Some(header) = self.rate_limit_key_header.as_ref() {
+            if http::HeaderName::try_from(header.as_str()).is_err() {
+                errors.push(format!("invalid rate_limit_key_header: '{header}'"));
+            }
+        }
+        if let Some(domains) = self.acme_domains.as_ref() {
+            if domains.is_empty() {
+                errors.push("'acme_domains' must contain at least one domain".to_string());
+            }
+            if self.acme_cache_dir.is_none() {
+                errors.push("for acme, 'acme_cache_dir' must be set".to_string());
+            }
+            if self.tls_cert_file.is_some() || self.tls_priv_key_file.is_some() {
+                errors.push(
+                    "'acme_domains' cannot be combined with 'tls_cert_file' and 'tls_priv_key_file'"
+                        .to_string(),
+                );
+            }
+        }
         if !errors.is_empty() {
             Err(HttpServerError::Settings(format!(
                 "\nInvalid httpserver settings: \n{}\n",
@@ -301,6 +394,67 @@ pub fn load_settings(
         settings.disable_keepalive = Some(disable_keepalive.parse().unwrap_or(false));
     }
 
+    // Limits
+    if let Some(max_body_bytes) = values.get(&UniCase::new("max_body_bytes")) {
+        settings.max_body_bytes = Some(max_body_bytes.parse().map_err(|_| {
+            HttpServerError::InvalidParameter(format!("invalid max_body_bytes: {max_body_bytes}"))
+        })?);
+    }
+    if let Some(max_concurrent) = values.get(&UniCase::new("max_concurrent_requests")) {
+        settings.max_concurrent_requests = Some(max_concurrent.parse().map_err(|_| {
+            HttpServerError::InvalidParameter(format!(
+                "invalid max_concurrent_requests: {max_concurrent}"
+            ))
+        })?);
+    }
+    if let Some(rate_limit_rps) = values.get(&UniCase::new("rate_limit_rps")) {
+        settings.rate_limit_rps = Some(rate_limit_rps.parse().map_err(|_| {
+            HttpServerError::InvalidParameter(format!("invalid rate_limit_rps: {rate_limit_rps}"))
+        })?);
+    }
+    if let Some(rate_limit_burst) = values.get(&UniCase::new("rate_limit_burst")) {
+        settings.rate_limit_burst = Some(rate_limit_burst.parse().map_err(|_| {
+            HttpServerError::InvalidParameter(format!(
+                "invalid rate_limit_burst: {rate_limit_burst}"
+            ))
+        })?);
+    }
+    if let Some(header) = values.get(&UniCase::new("rate_limit_key_header")) {
+        settings.rate_limit_key_header = Some(header.to_string());
+    }
+
+    // ACME
+    if let Some(domains) = values.get(&UniCase::new("acme_domains")) {
+        settings.acme_domains = Some(
+            domains
+                .split(',')
+                .map(str::trim)
+                .filter(|domain| !domain.is_empty())
+                .map(String::from)
+                .collect(),
+        );
+    }
+    if let Some(directory_url) = values.get(&UniCase::new("acme_directory_url")) {
+        settings.acme_directory_url = Some(directory_url.to_string());
+    }
+    if let Some(contact) = values.get(&UniCase::new("acme_contact")) {
+        settings.acme_contact = Some(contact.to_string());
+    }
+    if let Some(cache_dir) = values.get(&UniCase::new("acme_cache_dir")) {
+        settings.acme_cache_dir = Some(cache_dir.to_string());
+    }
+    if let Some(challenge) = values.get(&UniCase::new("acme_challenge")) {
+        settings.acme_challenge = Some(AcmeChallenge::from_str(challenge)?);
+    }
+    if let Some(addr) = values.get(&UniCase::new("acme_http_address")) {
+        settings.acme_http_address = Some(SocketAddr::from_str(addr).map_err(|_| {
+            HttpServerError::InvalidParameter(format!("invalid acme_http_address: {addr}"))
+        })?);
+    }
+    if let Some(ca_file) = values.get(&UniCase::new("acme_ca_file")) {
+        settings.acme_ca_file = Some(ca_file.to_string());
+    }
+
     settings.validate()?;
     Ok(settings)
 }
@@ -483,6 +637,32 @@ impl Default for ExposedHeaders {
     }
 }
 
+/// ACME challenge used to prove control of a domain
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub enum AcmeChallenge {
+    /// Respond with a certificate negotiated via the `acme-tls/1` ALPN protocol on the listener
+    #[default]
+    #[serde(rename = "tls-alpn-01")]
+    TlsAlpn01,
+    /// Respond to an HTTP request for `/.well-known/acme-challenge/<token>`
+    #[serde(rename = "http-01")]
+    Http01,
+}
+
+impl FromStr for AcmeChallenge {
+    type Err = HttpServerError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "tls-alpn-01" => Ok(Self::TlsAlpn01),
+            "http-01" => Ok(Self::Http01),
+            _ => Err(HttpServerError::InvalidParameter(format!(
+                "invalid acme_challenge: {s}, expected 'tls-alpn-01' or 'http-01'"
+            ))),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 #[serde(rename_all = "UPPERCASE")]
 pub enum HttpMethod {
@@ -530,9 +710,10 @@ where
 
 #[cfg(test)]
 mod test {
+    use std::collections::HashMap;
     use std::str::FromStr;
 
-    use crate::settings::{CorsOrigin, ServiceSettings};
+    use crate::settings::{load_settings, AcmeChallenge, CorsOrigin, ServiceSettings};
 
     const GOOD_ORIGINS: &[&str] = &[
         // origins that should be parsed correctly
@@ -622,4 +803,66 @@ mod test {
             assert!(o.is_err(), "from_str '{bad}' (expect err)");
         }
     }
+
+    #[test]
+    fn settings_limits() {
+        let values = HashMap::from([
+            ("max_body_bytes".to_string(), "1024".to_string()),
+            ("MAX_CONCURRENT_REQUESTS".to_string(), "8".to_string()),
+            ("rate_limit_rps".to_string(), "10".to_string()),
+            ("rate_limit_key_header".to_string(), "x-api-key".to_string()),
+        ]);
+        let s = load_settings(None, &values).expect("load_settings");
+        assert_eq!(s.max_body_bytes, Some(1024));
+        assert_eq!(s.max_concurrent_requests, Some(8));
+        assert_eq!(s.rate_limit_rps, Some(10));
+        assert_eq!(s.rate_limit_burst, None);
+        assert_eq!(s.rate_limit_key_header.as_deref(), Some("x-api-key"));
+
+        let values = HashMap::from([("rate_limit_burst".to_string(), "5".to_string())]);
+        assert!(load_settings(None, &values).is_err());
+        let values = HashMap::from([("rate_limit_rps".to_string(), "0".to_string())]);
+        assert!(load_settings(None, &values).is_err());
+        let values = HashMap::from([("max_concurrent_requests".to_string(), "0".to_string())]);
+        assert!(load_settings(None, &values).is_err());
+        let values = HashMap::from([("max_body_bytes".to_string(), "lots".to_string())]);
+        assert!(load_settings(None, &values).is_err());
+    }
+
+    #[test]
+    fn settings_acme() {
+        let values = HashMap::from([
+            (
+                "acme_domains".to_string(),
+                "example.com, www.example.com".to_string(),
+            ),
+            ("acme_cache_dir".to_string(), "/var/cache/acme".to_string()),
+            ("acme_challenge".to_string(), "http-01".to_string()),
+        ]);
+        let s = load_settings(None, &values).expect("load_settings");
+        assert_eq!(
+            s.acme_domains,
+            Some(vec![
+                "example.com".to_string(),
+                "www.example.com".to_string()
+            ])
+        );
+        assert_eq!(s.acme_challenge, Some(AcmeChallenge::Http01));
+
+        let s = ServiceSettings::from_json(
+            r#"{"acme_domains": ["example.com"], "acme_cache_dir": "/tmp", "acme_challenge": "tls-alpn-01"}"#,
+        )
+        .expect("parse_json");
+        assert_eq!(s.acme_challenge, Some(AcmeChallenge::TlsAlpn01));
+
+        // The cache directory is required
+        let values = HashMap::from([("acme_domains".to_string(), "example.com".to_string())]);
+        assert!(load_settings(None, &values).is_err());
+        let values = HashMap::from([
+            ("acme_domains".to_string(), "example.com".to_string()),
+            ("acme_cache_dir".to_string(), "/tmp".to_string()),
+            ("acme_challenge".to_string(), "dns-01".to_string()),
+        ]);
+        assert!(load_settings(None, &values).is_err());
+    }
 }
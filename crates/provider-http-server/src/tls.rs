@@ -0,0 +1,113 @@
+//! TLS configuration of listeners, which is either obtained using ACME or loaded from the
+//! `tls_cert_file` and `tls_priv_key_file` and reloaded whenever either file changes.
+
+use core::time::Duration;
+
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use anyhow::Context as _;
+use axum_server::tls_rustls::RustlsConfig;
+use notify::{RecursiveMode, Watcher as _};
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tracing::{debug, error, info};
+
+use crate::acme::{self, AcmeConfig};
+use crate::ServiceSettings;
+
+/// Delay after a change to the certificate files before reloading them, so that the certificate
+/// and key are not reloaded while only one of them has been replaced
+const RELOAD_DEBOUNCE: Duration = Duration::from_millis(500);
+
+/// TLS configuration of a listener, along with the task keeping it up to date
+pub(crate) struct ServerTls {
+    pub(crate) config: RustlsConfig,
+    task: JoinHandle<()>,
+}
+
+impl Drop for ServerTls {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+impl ServerTls {
+    /// Construct the TLS configuration for the given settings, returning `None` if the listener
+    /// should serve plain HTTP
+    pub(crate) async fn new(settings: &ServiceSettings) -> anyhow::Result<Option<Self>> {
+        if let Some(acme) = AcmeConfig::from_settings(settings) {
+            let (resolver, task) = acme::start(acme)
+                .await
+                .context("failed to start ACME certificate management")?;
+            let config = RustlsConfig::from_config(Arc::new(acme::server_config(resolver)));
+            return Ok(Some(Self { config, task }));
+        }
+        let (Some(crt), Some(key)) = (&settings.tls_cert_file, &settings.tls_priv_key_file) else {
+            return Ok(None);
+        };
+        let config = RustlsConfig::from_pem_file(crt, key)
+            .await
+            .context("failed to construct TLS config")?;
+        let task = watch_pem_files(config.clone(), crt.into(), key.into())
+            .context("failed to watch TLS certificate files")?;
+        Ok(Some(Self { config, task }))
+    }
+}
+
+/// Reload the configuration whenever the certificate or key file changes, e.g. when renewed by
+/// an external tool
+fn watch_pem_files(
+    config: RustlsConfig,
+    crt: PathBuf,
+    key: PathBuf,
+) -> anyhow::Result<JoinHandle<()>> {
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    let mut watcher = notify::recommended_watcher(move |event| {
+        let _ = tx.send(event);
+    })
+    .context("failed to create file watcher")?;
+    // Parent directories are watched, since files are commonly replaced rather than modified
+    for dir in [&crt, &key].into_iter().filter_map(|path| path.parent()) {
+        let dir = if dir == Path::new("") {
+            Path::new(".")
+        } else {
+            dir
+        };
+        watcher
+            .watch(dir, RecursiveMode::NonRecursive)
+            .with_context(|| format!("failed to watch [{}]", dir.display()))?;
+    }
+    let file_names = [crt.file_name(), key.file_name()].map(|name| name.map(ToOwned::to_owned));
+    Ok(tokio::spawn(async move {
+        // The watcher stops when dropped, so it is owned by the task
+        let _watcher = watcher;
+        while let Some(event) = rx.recv().await {
+            let event: notify::Event = match event {
+                Ok(event) => event,
+                Err(err) => {
+                    error!(?err, "failed to watch TLS certificate files");
+                    continue;
+                }
+            };
+            if event.kind.is_access()
+                || !event
+                    .paths
+                    .iter()
+                    .any(|path| file_names.contains(&path.file_name().map(ToOwned::to_owned)))
+            {
+                continue;
+            }
+            tokio::time::sleep(RELOAD_DEBOUNCE).await;
+            while rx.try_recv().is_ok() {}
+            debug!(crt = %crt.display(), "reloading TLS certificate");
+            match config.reload_from_pem_file(&crt, &key).await {
+                Ok(()) => info!(crt = %crt.display(), "reloaded TLS certificate"),
+                Err(err) => error!(
+                    ?err,
+                    "failed to reload TLS certificate, keeping previous one"
+                ),
+            }
+        }
+    }))
+}
@@ -9,13 +9,15 @@
 
 use std::collections::HashMap;
 use std::sync::Arc;
+use std::time::Duration;
 
 use anyhow::{anyhow, bail, Context as _};
 use bytes::Bytes;
 use futures::{StreamExt as _, TryStreamExt as _};
 use tokio::fs;
 use tokio::sync::RwLock;
-use tracing::{debug, error, info, instrument, warn};
+use tokio::task::JoinHandle;
+use tracing::{debug, error, info, instrument, warn, Instrument as _};
 use wascap::prelude::KeyPair;
 use wasmcloud_provider_sdk::core::HostData;
 use wasmcloud_provider_sdk::{
@@ -32,10 +34,14 @@ mod bindings {
             "wrpc:keyvalue/atomics@0.2.0-draft": generate,
             "wrpc:keyvalue/batch@0.2.0-draft": generate,
             "wrpc:keyvalue/store@0.2.0-draft": generate,
+            "wasmcloud:keyvalue/ttl@0.1.0-draft": generate,
+            "wasmcloud:keyvalue/watcher@0.1.0-draft": generate,
         }
     });
 }
+use bindings::exports::wasmcloud::keyvalue::ttl;
 use bindings::exports::wrpc::keyvalue;
+use bindings::wasmcloud::keyvalue::watcher;
 
 type Result<T, E = keyvalue::store::Error> = core::result::Result<T, E>;
 
@@ -46,13 +52,34 @@ pub async fn run() -> anyhow::Result<()> {
 /// The `atomic::increment` function's exponential backoff base interval
 const EXPONENTIAL_BACKOFF_BASE_INTERVAL: u64 = 5; // milliseconds
 
+/// Link configuration key for the keys to watch, which may contain NATS wildcards
+const CONFIG_WATCH_KEYS: &str = "watch_keys";
+
+/// Header used to set the TTL of a single message, on streams that allow it (NATS 2.11+)
+const NATS_TTL_HEADER: &str = "Nats-TTL";
+
+/// An opened NATS Kv store, along with the JetStream context it was opened with, which is needed
+/// to write values with a per-key TTL
+#[derive(Clone, Debug)]
+struct NatsKvStore {
+    store: async_nats::jetstream::kv::Store,
+    jetstream: async_nats::jetstream::Context,
+    /// The JetStream API prefix of `jetstream`, which `async-nats` does not expose
+    api_prefix: String,
+}
+
 /// [`NatsKvStores`] holds the handles to opened NATS Kv Stores, and their respective identifiers.
-type NatsKvStores = HashMap<String, async_nats::jetstream::kv::Store>;
+type NatsKvStores = HashMap<String, NatsKvStore>;
+
+/// Tasks watching keys on behalf of (handler) components, by target ID & link name
+type Watchers = HashMap<(String, String), JoinHandle<()>>;
 
 /// NATS implementation for wasi:keyvalue (via wrpc:keyvalue)
 #[derive(Default, Clone)]
 pub struct KvNatsProvider {
     consumer_components: Arc<RwLock<HashMap<String, NatsKvStores>>>,
+    /// Tasks watching Kv stores on behalf of (handler) components, by target ID & link name
+    watchers: Arc<RwLock<Watchers>>,
     default_config: NatsConnectionConfig,
 }
 /// Implement the [`KvNatsProvider`] and [`Provider`] traits
@@ -98,7 +125,7 @@ impl KvNatsProvider {
         &self,
         cfg: NatsConnectionConfig,
         link_cfg: &LinkConfig<'_>,
-    ) -> anyhow::Result<async_nats::jetstream::kv::Store> {
+    ) -> anyhow::Result<NatsKvStore> {
         let mut opts = match (cfg.auth_jwt, cfg.auth_seed) {
             (Some(jwt), Some(seed)) => {
                 let seed = KeyPair::from_seed(&seed).context("failed to parse seed key pair")?;
@@ -130,10 +157,13 @@ impl KvNatsProvider {
             .await?;
 
         // Get the JetStream context based on js_domain
-        let js_context = if let Some(domain) = &cfg.js_domain {
-            async_nats::jetstream::with_domain(client.clone(), domain.clone())
+        let (js_context, api_prefix) = if let Some(domain) = &cfg.js_domain {
+            (
+                async_nats::jetstream::with_domain(client.clone(), domain.clone()),
+                format!("$JS.{domain}.API"),
+            )
         } else {
-            async_nats::jetstream::new(client.clone())
+            (async_nats::jetstream::new(client.clone()), "$JS.API".into())
         };
 
         // If bucket auto-creation was specified in the link configuration,
@@ -143,10 +173,22 @@ impl KvNatsProvider {
             .get("enable_bucket_auto_create")
             .is_some_and(|v| v.to_lowercase() == "true")
         {
+            // Values in the bucket expire after the max age, if one was given
+            let max_age = link_cfg
+                .config
+                .get("bucket_max_age")
+                .map(|v| {
+                    v.parse()
+                        .map(Duration::from_secs)
+                        .with_context(|| format!("invalid bucket_max_age [{v}]"))
+                })
+                .transpose()?
+                .unwrap_or_default();
             // Get the JetStream context based on js_domain
             if let Err(e) = js_context
                 .create_key_value(async_nats::jetstream::kv::Config {
                     bucket: cfg.bucket.clone(),
+                    max_age,
                     ..Default::default()
                 })
                 .await
@@ -160,7 +202,11 @@ impl KvNatsProvider {
         info!(%cfg.bucket, "NATS Kv store opened");
 
         // Return the handle to the opened NATS Kv store
-        Ok(store)
+        Ok(NatsKvStore {
+            store,
+            jetstream: js_context,
+            api_prefix,
+        })
     }
 
     /// Helper function to lookup and return the NATS Kv store handle, from the client component's context
@@ -169,6 +215,18 @@ impl KvNatsProvider {
         context: Option<Context>,
         bucket_id: String,
     ) -> Result<async_nats::jetstream::kv::Store, keyvalue::store::Error> {
+        self.get_nats_kv_store(context, bucket_id)
+            .await
+            .map(|kv| kv.store)
+    }
+
+    /// Helper function to lookup and return the NATS Kv store handle along with its JetStream
+    /// context, from the client component's context
+    async fn get_nats_kv_store(
+        &self,
+        context: Option<Context>,
+        bucket_id: String,
+    ) -> Result<NatsKvStore, keyvalue::store::Error> {
         if let Some(ref source_id) = context
             .as_ref()
             .and_then(|Context { component, .. }| component.clone())
@@ -287,6 +345,111 @@ impl Provider for KvNatsProvider {
         Ok(())
     }
 
+    /// Start watching the configured Kv store, invoking `wasmcloud:keyvalue/watcher` on the
+    /// target component for every change.
+    #[instrument(level = "debug", skip_all, fields(target_id = link_config.target_id))]
+    async fn receive_link_config_as_source(
+        &self,
+        link_config: LinkConfig<'_>,
+    ) -> anyhow::Result<()> {
+        let nats_config = if link_config.config.is_empty() {
+            self.default_config.clone()
+        } else {
+            match NatsConnectionConfig::from_config_and_secrets(
+                link_config.config,
+                link_config.secrets,
+            ) {
+                Ok(ncc) => self.default_config.merge(&ncc),
+                Err(e) => {
+                    error!("Failed to build NATS connection configuration: {e:?}");
+                    return Err(anyhow!(e).context("failed to build NATS connection configuration"));
+                }
+            }
+        };
+        let watch_keys = link_config
+            .config
+            .get(CONFIG_WATCH_KEYS)
+            .map_or(">", String::as_str);
+
+        let LinkConfig {
+            target_id,
+            link_name,
+            ..
+        } = link_config;
+
+        let kv_store = match self.connect(nats_config, &link_config).await {
+            Ok(b) => b,
+            Err(e) => {
+                error!("Failed to connect to NATS: {e:?}");
+                bail!(anyhow!(e).context("failed to connect to NATS"))
+            }
+        };
+        let watch = kv_store
+            .store
+            .watch(watch_keys)
+            .await
+            .context("failed to watch NATS Kv store")?;
+        let wrpc = get_connection()
+            .get_wrpc_client(target_id)
+            .await
+            .context("failed to construct wRPC client")?;
+
+        let bucket = link_name.to_string();
+        let task = tokio::spawn(
+            async move {
+                let mut watch = watch;
+                while let Some(entry) = watch.next().await {
+                    let entry = match entry {
+                        Ok(entry) => entry,
+                        Err(err) => {
+                            error!(?err, "failed to receive NATS Kv watch entry");
+                            continue;
+                        }
+                    };
+                    let res = match entry.operation {
+                        async_nats::jetstream::kv::Operation::Put => {
+                            watcher::on_set(&wrpc, None, &bucket, &entry.key, &entry.value).await
+                        }
+                        async_nats::jetstream::kv::Operation::Delete
+                        | async_nats::jetstream::kv::Operation::Purge => {
+                            watcher::on_delete(&wrpc, None, &bucket, &entry.key).await
+                        }
+                    };
+                    match res {
+                        Ok(Ok(())) => {}
+                        Ok(Err(err)) => warn!(key = entry.key, err, "watcher returned an error"),
+                        Err(err) => error!(key = entry.key, ?err, "failed to invoke watcher"),
+                    }
+                }
+                debug!("NATS Kv watch ended");
+            }
+            .in_current_span(),
+        );
+
+        let mut watchers = self.watchers.write().await;
+        if let Some(previous) =
+            watchers.insert((target_id.to_string(), link_name.to_string()), task)
+        {
+            previous.abort();
+        }
+
+        Ok(())
+    }
+
+    /// Stop watching on behalf of the target component
+    #[instrument(level = "info", skip_all, fields(target_id = info.get_target_id()))]
+    async fn delete_link_as_source(&self, info: impl LinkDeleteInfo) -> anyhow::Result<()> {
+        let key = (
+            info.get_target_id().to_string(),
+            info.get_link_name().to_string(),
+        );
+        if let Some(task) = self.watchers.write().await.remove(&key) {
+            task.abort();
+        }
+        debug!(component_id = key.0, "stopped watching NATS Kv store");
+        Ok(())
+    }
+
     /// Provider should perform any operations needed for a link deletion, including cleaning up
     /// per-component resources.
     #[instrument(level = "info", skip_all, fields(source_id = info.get_source_id()))]
@@ -311,10 +474,146 @@ impl Provider for KvNatsProvider {
         let mut consumers = self.consumer_components.write().await;
         consumers.clear();
 
+        // stop all watchers
+        for (_, task) in self.watchers.write().await.drain() {
+            task.abort();
+        }
+
         Ok(())
     }
 }
 
+/// Implement the 'wasmcloud:keyvalue/ttl' capability provider interface
+///
+/// Values expire individually if the stream backing the bucket allows per-message TTLs (NATS
+/// 2.11+). Otherwise NATS Kv only supports expiry for a whole bucket (its max age), and a TTL is
+/// only accepted if it matches the max age of the bucket.
+impl ttl::Handler<Option<Context>> for KvNatsProvider {
+    #[instrument(level = "debug", skip(self, value))]
+    async fn set(
+        &self,
+        context: Option<Context>,
+        bucket: String,
+        key: String,
+        value: Bytes,
+        ttl_ms: u64,
+    ) -> anyhow::Result<Result<(), ttl::Error>> {
+        propagate_trace_for_ctx!(context);
+
+        let NatsKvStore {
+            store,
+            jetstream,
+            api_prefix,
+        } = match self.get_nats_kv_store(context, bucket.clone()).await {
+            Ok(store) => store,
+            Err(keyvalue::store::Error::NoSuchStore) => return Ok(Err(ttl::Error::NoSuchStore)),
+            Err(keyvalue::store::Error::AccessDenied) => return Ok(Err(ttl::Error::AccessDenied)),
+            Err(keyvalue::store::Error::Other(err)) => return Ok(Err(ttl::Error::Other(err))),
+        };
+        if let Err(err) = validate_ttl(ttl_ms) {
+            return Ok(Err(ttl::Error::Other(err)));
+        }
+        match allows_message_ttl(&jetstream, &store.stream_name).await {
+            Ok(true) => {}
+            Ok(false) => {
+                let max_age = match store.status().await {
+                    Ok(status) => status.max_age(),
+                    Err(err) => {
+                        error!(%bucket, "failed to get bucket status: {err:?}");
+                        return Ok(Err(ttl::Error::Other(err.to_string())));
+                    }
+                };
+                if max_age.is_zero() || max_age != Duration::from_millis(ttl_ms) {
+                    return Ok(Err(ttl::Error::Other(format!(
+                        "bucket [{bucket}] does not allow per-key TTLs (`allow_msg_ttl`, NATS 2.11+): TTL of {ttl_ms}ms does not match the max age of the bucket ({}ms)",
+                        max_age.as_millis()
+                    ))));
+                }
+                return match store.put(key.clone(), value).await {
+                    Ok(_) => Ok(Ok(())),
+                    Err(err) => {
+                        error!(%key, "failed to set key value: {err:?}");
+                        Ok(Err(ttl::Error::Other(err.to_string())))
+                    }
+                };
+            }
+            Err(err) => {
+                error!(%bucket, "failed to get bucket stream info: {err:?}");
+                return Ok(Err(ttl::Error::Other(err.to_string())));
+            }
+        }
+
+        // Publish directly to the subject of the key, as `async-nats` cannot put with headers
+        if !is_valid_key(&key) {
+            return Ok(Err(ttl::Error::Other(format!("invalid key [{key}]"))));
+        }
+        let mut subject = String::new();
+        if store.use_jetstream_prefix {
+            subject.push_str(&api_prefix);
+            subject.push('.');
+        }
+        subject.push_str(store.put_prefix.as_ref().unwrap_or(&store.prefix));
+        subject.push_str(&key);
+        let mut headers = async_nats::HeaderMap::new();
+        headers.insert(NATS_TTL_HEADER, format!("{ttl_ms}ms"));
+        let ack = match jetstream
+            .publish_with_headers(subject, headers, value)
+            .await
+        {
+            Ok(ack) => ack.await.map(|_| ()).map_err(|err| err.to_string()),
+            Err(err) => Err(err.to_string()),
+        };
+        match ack {
+            Ok(()) => Ok(Ok(())),
+            Err(err) => {
+                error!(%key, "failed to set key value with TTL: {err}");
+                Ok(Err(ttl::Error::Other(err)))
+            }
+        }
+    }
+}
+
+/// Check a TTL can be honored by NATS, which expires messages with a granularity of one second
+fn validate_ttl(ttl_ms: u64) -> core::result::Result<(), String> {
+    if ttl_ms < 1000 {
+        return Err(format!(
+            "TTL of {ttl_ms}ms is too short, NATS expires values after at least one second"
+        ));
+    }
+    Ok(())
+}
+
+/// Whether a key is valid for NATS Kv, mirroring the (private) validation in `async-nats`
+fn is_valid_key(key: &str) -> bool {
+    !key.is_empty()
+        && !key.starts_with('.')
+        && !key.ends_with('.')
+        && key
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '/' | '_' | '=' | '.'))
+}
+
+/// Whether the stream backing a bucket allows per-message TTLs. `async-nats` does not know about
+/// the `allow_msg_ttl` stream setting yet, so the stream info is requested directly.
+async fn allows_message_ttl(
+    jetstream: &async_nats::jetstream::Context,
+    stream_name: &str,
+) -> anyhow::Result<bool> {
+    let info: async_nats::jetstream::response::Response<serde_json::Value> = jetstream
+        .request(format!("STREAM.INFO.{stream_name}"), &())
+        .await
+        .context("failed to request stream info")?;
+    match info {
+        async_nats::jetstream::response::Response::Ok(info) => Ok(info
+            .pointer("/config/allow_msg_ttl")
+            .and_then(serde_json::Value::as_bool)
+            .unwrap_or_default()),
+        async_nats::jetstream::response::Response::Err { error } => {
+            bail!("failed to get stream info: {error}")
+        }
+    }
+}
+
 /// Implement the 'wasi:keyvalue/store' capability provider interface
 impl keyvalue::store::Handler<Option<Context>> for KvNatsProvider {
     // Get the last revision of a value, for a given key, from the key-value store
@@ -650,4 +949,18 @@ mod test {
         let opts = add_tls_ca(tls_ca, opts);
         assert!(opts.is_ok())
     }
+
+    // Verify that TTLs and keys are checked before publishing directly to a key's subject
+    #[test]
+    fn test_ttl_validation() {
+        assert!(validate_ttl(999).is_err());
+        assert!(validate_ttl(1000).is_ok());
+        assert!(is_valid_key("foo/bar.baz-1=2_3"));
+        assert!(!is_valid_key(""));
+        assert!(!is_valid_key(".foo"));
+        assert!(!is_valid_key("foo."));
+        assert!(!is_valid_key("foo.*"));
+        assert!(!is_valid_key("foo.>"));
+        assert!(!is_valid_key("foo bar"));
+    }
 }
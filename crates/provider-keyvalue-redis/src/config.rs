@@ -0,0 +1,310 @@
+//! Connection settings for the Redis provider, parsed from link or provider configuration
+
+use core::num::NonZeroUsize;
+
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use anyhow::{bail, Context as _};
+use wasmcloud_provider_sdk::core::secrets::SecretValue;
+
+use crate::{CONFIG_REDIS_URL_KEY, DEFAULT_CONNECT_URL};
+
+/// Configuration key selecting how to connect (`standalone`, `cluster` or `sentinel`)
+const CONFIG_REDIS_MODE_KEY: &str = "MODE";
+
+/// Configuration key for the name of the master monitored by the sentinels
+const CONFIG_REDIS_SENTINEL_MASTER_NAME_KEY: &str = "SENTINEL_MASTER_NAME";
+
+/// Configuration key for the username used to authenticate with the sentinel-managed master
+const CONFIG_REDIS_SENTINEL_MASTER_USERNAME_KEY: &str = "SENTINEL_MASTER_USERNAME";
+
+/// Configuration key for the password used to authenticate with the sentinel-managed master
+const CONFIG_REDIS_SENTINEL_MASTER_PASSWORD_KEY: &str = "SENTINEL_MASTER_PASSWORD";
+
+/// Configuration key for a PEM encoded TLS client certificate
+const CONFIG_REDIS_TLS_CLIENT_CERT_KEY: &str = "TLS_CLIENT_CERT";
+
+/// Configuration key for the PEM encoded private key of the TLS client certificate
+const CONFIG_REDIS_TLS_CLIENT_KEY_KEY: &str = "TLS_CLIENT_KEY";
+
+/// Configuration key for a PEM encoded root certificate, used instead of the bundled roots
+const CONFIG_REDIS_TLS_CA_KEY: &str = "TLS_CA";
+
+/// Configuration key for the number of connections to open
+const CONFIG_REDIS_POOL_SIZE_KEY: &str = "POOL_SIZE";
+
+/// Configuration key for the glob-style pattern of keys to watch
+const CONFIG_REDIS_WATCH_KEYS_KEY: &str = "WATCH_KEYS";
+
+/// How the provider connects to Redis
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Mode {
+    /// A single Redis server, at `URL`
+    #[default]
+    Standalone,
+    /// A Redis Cluster, discovered from one or more comma separated node URLs in `URL`
+    Cluster,
+    /// The master of a Redis Sentinel deployment, discovered from one or more comma separated
+    /// sentinel URLs in `URL`
+    Sentinel,
+}
+
+impl FromStr for Mode {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "standalone" => Ok(Self::Standalone),
+            "cluster" => Ok(Self::Cluster),
+            "sentinel" => Ok(Self::Sentinel),
+            other => bail!(
+                "invalid Redis mode [{other}], expected one of standalone, cluster or sentinel"
+            ),
+        }
+    }
+}
+
+/// PEM encoded TLS material used when connecting with a `rediss://` URL
+#[derive(Clone, Default, PartialEq, Eq)]
+pub struct TlsConfig {
+    /// Client certificate and private key, if mutual TLS is used
+    pub client_cert: Option<(Vec<u8>, Vec<u8>)>,
+    /// Root certificate, if the bundled roots should not be used
+    pub root_cert: Option<Vec<u8>>,
+}
+
+impl core::fmt::Debug for TlsConfig {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("TlsConfig")
+            .field(
+                "client_cert",
+                &self.client_cert.as_ref().map(|_| "redacted"),
+            )
+            .field("root_cert", &self.root_cert.is_some())
+            .finish()
+    }
+}
+
+/// Settings for connecting to Redis
+#[derive(Clone, PartialEq, Eq)]
+pub struct RedisConfig {
+    /// How to connect
+    pub mode: Mode,
+    /// Server URL (standalone), node URLs (cluster) or sentinel URLs (sentinel)
+    pub urls: Vec<String>,
+    /// Name of the master monitored by the sentinels
+    pub sentinel_master_name: Option<String>,
+    /// Username used to authenticate with the sentinel-managed master
+    pub sentinel_master_username: Option<String>,
+    /// Password used to authenticate with the sentinel-managed master
+    pub sentinel_master_password: Option<String>,
+    /// TLS material, if any was supplied
+    pub tls: Option<TlsConfig>,
+    /// Number of connections to open and use in turn
+    pub pool_size: NonZeroUsize,
+}
+
+impl core::fmt::Debug for RedisConfig {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("RedisConfig")
+            .field("mode", &self.mode)
+            .field("urls", &self.urls.len())
+            .field("sentinel_master_name", &self.sentinel_master_name)
+            .field("tls", &self.tls)
+            .field("pool_size", &self.pool_size)
+            .finish_non_exhaustive()
+    }
+}
+
+impl RedisConfig {
+    /// Build a [`RedisConfig`] from config and secrets, preferring values found in secrets
+    ///
+    /// Keys are matched case-insensitively. If no `URL` is present, the default URL is used.
+    pub fn from_config_and_secrets(
+        config: &HashMap<String, String>,
+        secrets: &HashMap<String, SecretValue>,
+    ) -> anyhow::Result<Self> {
+        let mode = find_value(config, secrets, CONFIG_REDIS_MODE_KEY)
+            .map(|v| v.parse())
+            .transpose()?
+            .unwrap_or_default();
+        let url = find_value(config, secrets, CONFIG_REDIS_URL_KEY)
+            .unwrap_or_else(|| DEFAULT_CONNECT_URL.to_string());
+        let urls = url
+            .split(',')
+            .map(str::trim)
+            .filter(|u| !u.is_empty())
+            .map(String::from)
+            .collect::<Vec<_>>();
+        match (mode, urls.len()) {
+            (_, 0) => bail!("no Redis URL was supplied"),
+            (Mode::Standalone, n) if n > 1 => {
+                bail!("multiple Redis URLs were supplied, but mode is not cluster or sentinel")
+            }
+            _ => {}
+        }
+
+        let sentinel_master_name =
+            find_value(config, secrets, CONFIG_REDIS_SENTINEL_MASTER_NAME_KEY);
+        if mode == Mode::Sentinel && sentinel_master_name.is_none() {
+            bail!("[{CONFIG_REDIS_SENTINEL_MASTER_NAME_KEY}] is required in sentinel mode");
+        }
+
+        let client_cert = find_value(config, secrets, CONFIG_REDIS_TLS_CLIENT_CERT_KEY);
+        let client_key = find_value(config, secrets, CONFIG_REDIS_TLS_CLIENT_KEY_KEY);
+        let client_cert = match (client_cert, client_key) {
+            (Some(cert), Some(key)) => Some((cert.into_bytes(), key.into_bytes())),
+            (None, None) => None,
+            _ => bail!("a TLS client certificate and key must be supplied together"),
+        };
+        let root_cert =
+            find_value(config, secrets, CONFIG_REDIS_TLS_CA_KEY).map(String::into_bytes);
+        let tls = (client_cert.is_some() || root_cert.is_some()).then_some(TlsConfig {
+            client_cert,
+            root_cert,
+        });
+
+        let pool_size = find_value(config, secrets, CONFIG_REDIS_POOL_SIZE_KEY)
+            .map(|v| {
+                v.trim()
+                    .parse()
+                    .with_context(|| format!("invalid [{CONFIG_REDIS_POOL_SIZE_KEY}] value [{v}]"))
+            })
+            .transpose()?
+            .unwrap_or(NonZeroUsize::MIN);
+
+        Ok(Self {
+            mode,
+            urls,
+            sentinel_master_name,
+            sentinel_master_username: find_value(
+                config,
+                secrets,
+                CONFIG_REDIS_SENTINEL_MASTER_USERNAME_KEY,
+            ),
+            sentinel_master_password: find_value(
+                config,
+                secrets,
+                CONFIG_REDIS_SENTINEL_MASTER_PASSWORD_KEY,
+            ),
+            tls,
+            pool_size,
+        })
+    }
+}
+
+/// Find the glob-style pattern of keys to watch, defaulting to all keys
+pub fn watch_keys(
+    config: &HashMap<String, String>,
+    secrets: &HashMap<String, SecretValue>,
+) -> String {
+    find_value(config, secrets, CONFIG_REDIS_WATCH_KEYS_KEY).unwrap_or_else(|| "*".into())
+}
+
+/// Find a value by case-insensitive key, looking in secrets before config
+fn find_value(
+    config: &HashMap<String, String>,
+    secrets: &HashMap<String, SecretValue>,
+    key: &str,
+) -> Option<String> {
+    secrets
+        .iter()
+        .find(|(k, _)| k.eq_ignore_ascii_case(key))
+        .and_then(|(_, v)| match (v.as_string(), v.as_bytes()) {
+            (Some(s), _) => Some(s.to_string()),
+            (_, Some(b)) => String::from_utf8(b.to_vec()).ok(),
+            _ => None,
+        })
+        .or_else(|| {
+            config
+                .iter()
+                .find(|(k, _)| k.eq_ignore_ascii_case(key))
+                .map(|(_, v)| v.to_string())
+        })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn config(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn defaults_to_standalone() {
+        let cfg = RedisConfig::from_config_and_secrets(&config(&[]), &HashMap::new()).unwrap();
+        assert_eq!(cfg.mode, Mode::Standalone);
+        assert_eq!(cfg.urls, vec![DEFAULT_CONNECT_URL.to_string()]);
+        assert_eq!(cfg.pool_size.get(), 1);
+        assert!(cfg.tls.is_none());
+    }
+
+    #[test]
+    fn parses_cluster_and_sentinel() {
+        let cfg = RedisConfig::from_config_and_secrets(
+            &config(&[
+                ("mode", "Cluster"),
+                ("url", "redis://a:6379, redis://b:6379"),
+                ("pool_size", "4"),
+            ]),
+            &HashMap::new(),
+        )
+        .unwrap();
+        assert_eq!(cfg.mode, Mode::Cluster);
+        assert_eq!(cfg.urls, vec!["redis://a:6379", "redis://b:6379"]);
+        assert_eq!(cfg.pool_size.get(), 4);
+
+        assert!(RedisConfig::from_config_and_secrets(
+            &config(&[("MODE", "sentinel"), ("URL", "redis://s:26379")]),
+            &HashMap::new(),
+        )
+        .is_err());
+        let cfg = RedisConfig::from_config_and_secrets(
+            &config(&[
+                ("MODE", "sentinel"),
+                ("URL", "redis://s:26379"),
+                ("SENTINEL_MASTER_NAME", "mymaster"),
+            ]),
+            &HashMap::from([(
+                "SENTINEL_MASTER_PASSWORD".to_string(),
+                SecretValue::String("hunter2".into()),
+            )]),
+        )
+        .unwrap();
+        assert_eq!(cfg.sentinel_master_name.as_deref(), Some("mymaster"));
+        assert_eq!(cfg.sentinel_master_password.as_deref(), Some("hunter2"));
+    }
+
+    #[test]
+    fn rejects_invalid_config() {
+        for pairs in [
+            &[("MODE", "ring")][..],
+            &[("URL", "redis://a:6379,redis://b:6379")][..],
+            &[("POOL_SIZE", "0")][..],
+            &[("TLS_CLIENT_CERT", "cert")][..],
+        ] {
+            assert!(
+                RedisConfig::from_config_and_secrets(&config(pairs), &HashMap::new()).is_err(),
+                "{pairs:?} should be rejected"
+            );
+        }
+    }
+
+    #[test]
+    fn secrets_take_precedence() {
+        let cfg = RedisConfig::from_config_and_secrets(
+            &config(&[("URL", "redis://config:6379")]),
+            &HashMap::from([(
+                "url".to_string(),
+                SecretValue::String("redis://secret:6379".into()),
+            )]),
+        )
+        .unwrap();
+        assert_eq!(cfg.urls, vec!["redis://secret:6379"]);
+    }
+}
@@ -0,0 +1,207 @@
+//! Redis connections for the standalone, cluster and sentinel modes, and a simple pool of them
+
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use std::sync::Arc;
+
+use anyhow::{bail, Context as _};
+use redis::aio::{ConnectionLike, ConnectionManager, PubSub};
+use redis::cluster::ClusterClientBuilder;
+use redis::cluster_async::ClusterConnection;
+use redis::sentinel::{Sentinel, SentinelNodeConnectionInfo};
+use redis::{
+    ClientTlsConfig, Cmd, IntoConnectionInfo as _, Pipeline, RedisConnectionInfo, RedisFuture,
+    TlsCertificates, TlsMode, Value,
+};
+use tracing::{debug, instrument};
+
+use crate::config::{Mode, RedisConfig, TlsConfig};
+
+/// Prefix of keyspace notification channels, followed by the database and `__:`
+pub const KEYSPACE_CHANNEL_PREFIX: &str = "__keyspace@";
+
+/// A connection to a standalone or sentinel-managed Redis server, or to a Redis Cluster
+#[derive(Clone)]
+pub enum RedisConnection {
+    /// A (reconnecting) connection to a single server
+    Standalone(ConnectionManager),
+    /// A connection to a cluster, routing commands to the node owning the keys
+    Cluster(ClusterConnection),
+}
+
+impl ConnectionLike for RedisConnection {
+    fn req_packed_command<'a>(&'a mut self, cmd: &'a Cmd) -> RedisFuture<'a, Value> {
+        match self {
+            Self::Standalone(conn) => conn.req_packed_command(cmd),
+            Self::Cluster(conn) => conn.req_packed_command(cmd),
+        }
+    }
+
+    fn req_packed_commands<'a>(
+        &'a mut self,
+        cmd: &'a Pipeline,
+        offset: usize,
+        count: usize,
+    ) -> RedisFuture<'a, Vec<Value>> {
+        match self {
+            Self::Standalone(conn) => conn.req_packed_commands(cmd, offset, count),
+            Self::Cluster(conn) => conn.req_packed_commands(cmd, offset, count),
+        }
+    }
+
+    fn get_db(&self) -> i64 {
+        match self {
+            Self::Standalone(conn) => conn.get_db(),
+            Self::Cluster(conn) => conn.get_db(),
+        }
+    }
+}
+
+/// A fixed set of connections, handed out in turn
+#[derive(Clone)]
+pub struct ConnectionPool {
+    connections: Arc<[RedisConnection]>,
+    next: Arc<AtomicUsize>,
+}
+
+impl ConnectionPool {
+    /// Open [`RedisConfig::pool_size`] connections using the given configuration
+    #[instrument(level = "debug", skip_all, fields(mode = ?config.mode))]
+    pub async fn connect(config: &RedisConfig) -> anyhow::Result<Self> {
+        let mut connections = Vec::with_capacity(config.pool_size.get());
+        for _ in 0..config.pool_size.get() {
+            connections.push(connect(config).await?);
+        }
+        debug!(size = connections.len(), "opened Redis connections");
+        Ok(Self {
+            connections: connections.into(),
+            next: Arc::default(),
+        })
+    }
+
+    /// Get the next connection from the pool
+    pub fn get(&self) -> RedisConnection {
+        let idx = self.next.fetch_add(1, Ordering::Relaxed) % self.connections.len();
+        self.connections[idx].clone()
+    }
+}
+
+/// Open a single connection using the given configuration
+async fn connect(config: &RedisConfig) -> anyhow::Result<RedisConnection> {
+    if config.mode == Mode::Cluster {
+        let mut builder = ClusterClientBuilder::new(config.urls.clone());
+        if let Some(tls) = &config.tls {
+            builder = builder.certs(tls_certificates(tls));
+        }
+        let conn = builder
+            .build()
+            .context("failed to construct Redis Cluster client")?
+            .get_async_connection()
+            .await
+            .context("failed to connect to Redis Cluster")?;
+        return Ok(RedisConnection::Cluster(conn));
+    }
+    let conn = client(config)
+        .await?
+        .get_connection_manager()
+        .await
+        .context("failed to construct Redis connection manager")?;
+    Ok(RedisConnection::Standalone(conn))
+}
+
+/// Subscribe to keyspace notifications for keys matching `pattern`
+///
+/// Keyspace notifications are only published by the node holding the key, so this is not
+/// supported in cluster mode. Notifications must be enabled on the server using the
+/// `notify-keyspace-events` setting.
+pub async fn subscribe_keyspace(config: &RedisConfig, pattern: &str) -> anyhow::Result<PubSub> {
+    if config.mode == Mode::Cluster {
+        bail!("watching keys is not supported in cluster mode");
+    }
+    let client = client(config).await?;
+    let db = client.get_connection_info().redis.db;
+    let mut pubsub = client
+        .get_async_pubsub()
+        .await
+        .context("failed to open Redis pub/sub connection")?;
+    pubsub
+        .psubscribe(format!("{KEYSPACE_CHANNEL_PREFIX}{db}__:{pattern}"))
+        .await
+        .context("failed to subscribe to keyspace notifications")?;
+    Ok(pubsub)
+}
+
+/// Construct a client for a standalone server or the master of a sentinel deployment
+async fn client(config: &RedisConfig) -> anyhow::Result<redis::Client> {
+    match config.mode {
+        Mode::Cluster => bail!("a single client cannot be used in cluster mode"),
+        Mode::Standalone => {
+            let url = config.urls.first().context("missing Redis URL")?;
+            match &config.tls {
+                Some(tls) => redis::Client::build_with_tls(url.as_str(), tls_certificates(tls)),
+                None => redis::Client::open(url.as_str()),
+            }
+            .context("failed to construct Redis client")
+        }
+        Mode::Sentinel => {
+            let master_name = config
+                .sentinel_master_name
+                .as_deref()
+                .context("missing sentinel master name")?;
+            // Sentinels and the master share a TLS setting, which follows the first sentinel URL
+            let tls_mode = match config
+                .urls
+                .first()
+                .map(|url| url.as_str().into_connection_info())
+                .transpose()
+                .context("invalid sentinel URL")?
+                .map(|info| info.addr)
+            {
+                Some(redis::ConnectionAddr::TcpTls { insecure, .. }) => Some(if insecure {
+                    TlsMode::Insecure
+                } else {
+                    TlsMode::Secure
+                }),
+                _ => None,
+            };
+            let mut sentinel =
+                Sentinel::build(config.urls.clone()).context("failed to build sentinel client")?;
+            let client = sentinel
+                .async_master_for(
+                    master_name,
+                    Some(&SentinelNodeConnectionInfo {
+                        tls_mode,
+                        redis_connection_info: Some(RedisConnectionInfo {
+                            username: config.sentinel_master_username.clone(),
+                            password: config.sentinel_master_password.clone(),
+                            ..Default::default()
+                        }),
+                    }),
+                )
+                .await
+                .with_context(|| format!("failed to find master [{master_name}] via sentinels"))?;
+            match &config.tls {
+                Some(tls) => redis::Client::build_with_tls(
+                    client.get_connection_info().clone(),
+                    tls_certificates(tls),
+                )
+                .context("failed to construct Redis client for sentinel master"),
+                None => Ok(client),
+            }
+        }
+    }
+}
+
+/// Convert PEM material into the form expected by the Redis client
+fn tls_certificates(tls: &TlsConfig) -> TlsCertificates {
+    TlsCertificates {
+        client_tls: tls
+            .client_cert
+            .as_ref()
+            .map(|(client_cert, client_key)| ClientTlsConfig {
+                client_cert: client_cert.clone(),
+                client_key: client_key.clone(),
+            }),
+        root_cert: tls.root_cert.clone(),
+    }
+}
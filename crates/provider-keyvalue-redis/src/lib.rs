@@ -14,48 +14,66 @@ use std::sync::Arc;
 
 use anyhow::{bail, Context as _};
 use bytes::Bytes;
-use redis::aio::ConnectionManager;
+use futures::StreamExt as _;
 use redis::{Cmd, FromRedisValue};
 use tokio::sync::RwLock;
-use tracing::{debug, error, info, instrument, warn};
+use tokio::task::JoinHandle;
+use tracing::{debug, error, info, instrument, warn, Instrument as _};
 use wasmcloud_provider_sdk::{
     get_connection, load_host_data, propagate_trace_for_ctx, run_provider, Context, LinkConfig,
     LinkDeleteInfo, Provider,
 };
 use wasmcloud_provider_sdk::{initialize_observability, serve_provider_exports};
 
+mod config;
+mod connection;
+
+use config::{watch_keys, RedisConfig};
+use connection::{ConnectionPool, RedisConnection};
+
 mod bindings {
     wit_bindgen_wrpc::generate!({
         with: {
             "wrpc:keyvalue/atomics@0.2.0-draft": generate,
             "wrpc:keyvalue/batch@0.2.0-draft": generate,
             "wrpc:keyvalue/store@0.2.0-draft": generate,
+            "wasmcloud:keyvalue/ttl@0.1.0-draft": generate,
+            "wasmcloud:keyvalue/watcher@0.1.0-draft": generate,
         }
     });
 }
+use bindings::exports::wasmcloud::keyvalue::ttl;
 use bindings::exports::wrpc::keyvalue;
+use bindings::wasmcloud::keyvalue::watcher;
 
 /// Default URL to use to connect to Redis
-const DEFAULT_CONNECT_URL: &str = "redis://127.0.0.1:6379/";
+pub(crate) const DEFAULT_CONNECT_URL: &str = "redis://127.0.0.1:6379/";
 
 /// Configuration key that will be used to search for Redis config
-const CONFIG_REDIS_URL_KEY: &str = "URL";
+pub(crate) const CONFIG_REDIS_URL_KEY: &str = "URL";
 
 type Result<T, E = keyvalue::store::Error> = core::result::Result<T, E>;
 
 #[derive(Clone)]
 pub enum DefaultConnection {
     ClientConfig(HashMap<String, String>),
-    Conn(ConnectionManager),
+    Conn(ConnectionPool),
 }
 
+/// Tasks watching keys on behalf of (handler) components, by target ID & link name
+type Watchers = HashMap<(String, String), JoinHandle<()>>;
+
 /// Redis `wrpc:keyvalue` provider implementation.
 #[derive(Clone)]
 pub struct KvRedisProvider {
     // store redis connections per source ID & link name
-    sources: Arc<RwLock<HashMap<(String, String), ConnectionManager>>>,
+    sources: Arc<RwLock<HashMap<(String, String), ConnectionPool>>>,
     // default connection, which may be uninitialized
     default_connection: Arc<RwLock<DefaultConnection>>,
+    // configuration the default connection is built from, also used by watchers
+    default_config: Arc<HashMap<String, String>>,
+    // tasks watching keys on behalf of target components, per target ID & link name
+    watchers: Arc<RwLock<Watchers>>,
 }
 
 pub async fn run() -> anyhow::Result<()> {
@@ -93,13 +111,15 @@ impl KvRedisProvider {
         KvRedisProvider {
             sources: Arc::default(),
             default_connection: Arc::new(RwLock::new(DefaultConnection::ClientConfig(
-                initial_config,
+                initial_config.clone(),
             ))),
+            default_config: Arc::new(initial_config),
+            watchers: Arc::default(),
         }
     }
 
     #[instrument(level = "trace", skip_all)]
-    async fn get_default_connection(&self) -> anyhow::Result<ConnectionManager> {
+    async fn get_default_connection(&self) -> anyhow::Result<ConnectionPool> {
         // NOTE: The read lock is only held for the duration of the `if let` block so we can acquire
         // the write lock to update the default connection if needed.
         if let DefaultConnection::Conn(conn) = &*self.default_connection.read().await {
@@ -110,11 +130,11 @@ impl KvRedisProvider {
         match &mut *default_conn {
             DefaultConnection::Conn(conn) => Ok(conn.clone()),
             DefaultConnection::ClientConfig(cfg) => {
-                let conn = redis::Client::open(retrieve_default_url(cfg))
-                    .context("failed to construct default Redis client")?
-                    .get_connection_manager()
+                let cfg = RedisConfig::from_config_and_secrets(cfg, &HashMap::new())
+                    .context("invalid default Redis configuration")?;
+                let conn = ConnectionPool::connect(&cfg)
                     .await
-                    .context("failed to construct Redis connection manager")?;
+                    .context("failed to connect to default Redis")?;
                 *default_conn = DefaultConnection::Conn(conn.clone());
                 Ok(conn)
             }
@@ -122,14 +142,18 @@ impl KvRedisProvider {
     }
 
     #[instrument(level = "debug", skip(self))]
-    async fn invocation_conn(&self, context: Option<Context>) -> anyhow::Result<ConnectionManager> {
+    async fn invocation_conn(&self, context: Option<Context>) -> anyhow::Result<RedisConnection> {
         let ctx = context.context("unexpectedly missing context")?;
 
         let Some(ref source_id) = ctx.component else {
-            return self.get_default_connection().await.map_err(|err| {
-                error!(error = ?err, "failed to get default connection for invocation");
-                err
-            });
+            return self
+                .get_default_connection()
+                .await
+                .map(|pool| pool.get())
+                .map_err(|err| {
+                    error!(error = ?err, "failed to get default connection for invocation");
+                    err
+                });
         };
 
         let sources = self.sources.read().await;
@@ -138,7 +162,7 @@ impl KvRedisProvider {
             bail!("No Redis connection found for component [{source_id}]. Please ensure the URL supplied in the link definition is a valid Redis URL")
         };
 
-        Ok(conn.clone())
+        Ok(conn.get())
     }
 
     /// Execute Redis async command
@@ -317,6 +341,33 @@ impl keyvalue::batch::Handler<Option<Context>> for KvRedisProvider {
     }
 }
 
+impl ttl::Handler<Option<Context>> for KvRedisProvider {
+    /// Sets a value which expires after the given number of milliseconds, using `PSETEX`
+    #[instrument(level = "debug", skip(self, value))]
+    async fn set(
+        &self,
+        context: Option<Context>,
+        bucket: String,
+        key: String,
+        value: Bytes,
+        ttl_ms: u64,
+    ) -> anyhow::Result<Result<(), ttl::Error>> {
+        propagate_trace_for_ctx!(context);
+        check_bucket_name(&bucket);
+        if ttl_ms == 0 {
+            return Ok(Err(ttl::Error::Other("TTL must be non-zero".into())));
+        }
+        Ok(self
+            .exec_cmd(context, &mut Cmd::pset_ex(key, value.to_vec(), ttl_ms))
+            .await
+            .map_err(|err| match err {
+                keyvalue::store::Error::NoSuchStore => ttl::Error::NoSuchStore,
+                keyvalue::store::Error::AccessDenied => ttl::Error::AccessDenied,
+                keyvalue::store::Error::Other(err) => ttl::Error::Other(err),
+            }))
+    }
+}
+
 /// Handle provider control commands
 impl Provider for KvRedisProvider {
     /// Provider should perform any operations needed for a new link,
@@ -333,40 +384,40 @@ impl Provider for KvRedisProvider {
             ..
         }: LinkConfig<'_>,
     ) -> anyhow::Result<()> {
-        let url = secrets
+        if secrets
             .keys()
-            .find(|k| k.eq_ignore_ascii_case(CONFIG_REDIS_URL_KEY))
-            .and_then(|url_key| config.get(url_key))
-            .or_else(|| {
-                warn!("redis connection URLs can be sensitive. Please consider using secrets to pass this value");
-                config
-                    .keys()
-                    .find(|k| k.eq_ignore_ascii_case(CONFIG_REDIS_URL_KEY))
-                    .and_then(|url_key| config.get(url_key))
-            });
-
-        let conn = if let Some(url) = url {
-            match redis::Client::open(url.to_string()) {
-                Ok(client) => match client.get_connection_manager().await {
-                    Ok(conn) => {
-                        info!(url, "established link");
-                        conn
-                    }
-                    Err(err) => {
-                        warn!(
-                            url,
-                            ?err,
-                        "Could not create Redis connection manager for source [{source_id}], keyvalue operations will fail",
-                    );
-                        bail!("failed to create redis connection manager");
-                    }
-                },
+            .all(|k| !k.eq_ignore_ascii_case(CONFIG_REDIS_URL_KEY))
+            && config
+                .keys()
+                .any(|k| k.eq_ignore_ascii_case(CONFIG_REDIS_URL_KEY))
+        {
+            warn!("redis connection URLs can be sensitive. Please consider using secrets to pass this value");
+        }
+
+        // Only links that configure a connection get their own; all others share the default
+        let conn = if config
+            .keys()
+            .chain(secrets.keys())
+            .any(|k| k.eq_ignore_ascii_case(CONFIG_REDIS_URL_KEY))
+        {
+            let cfg = RedisConfig::from_config_and_secrets(config, secrets).map_err(|err| {
+                warn!(
+                    ?err,
+                    "Invalid Redis configuration for source [{source_id}], keyvalue operations will fail",
+                );
+                err
+            })?;
+            match ConnectionPool::connect(&cfg).await {
+                Ok(conn) => {
+                    info!(mode = ?cfg.mode, "established link");
+                    conn
+                }
                 Err(err) => {
                     warn!(
                         ?err,
-                        "Could not create Redis client for source [{source_id}], keyvalue operations will fail",
+                        "Could not connect to Redis for source [{source_id}], keyvalue operations will fail",
                     );
-                    bail!("failed to create redis client");
+                    bail!("failed to connect to Redis");
                 }
             }
         } else {
@@ -381,6 +432,107 @@ impl Provider for KvRedisProvider {
         Ok(())
     }
 
+    /// Start watching keys using keyspace notifications, invoking `wasmcloud:keyvalue/watcher`
+    /// on the target component for every change.
+    #[instrument(level = "debug", skip_all, fields(target_id))]
+    async fn receive_link_config_as_source(
+        &self,
+        LinkConfig {
+            target_id,
+            config,
+            secrets,
+            link_name,
+            ..
+        }: LinkConfig<'_>,
+    ) -> anyhow::Result<()> {
+        let cfg = if config
+            .keys()
+            .chain(secrets.keys())
+            .any(|k| k.eq_ignore_ascii_case(CONFIG_REDIS_URL_KEY))
+        {
+            RedisConfig::from_config_and_secrets(config, secrets)
+        } else {
+            RedisConfig::from_config_and_secrets(&self.default_config, &HashMap::new())
+        }
+        .context("invalid Redis configuration")?;
+        let pattern = watch_keys(config, secrets);
+
+        let pubsub = connection::subscribe_keyspace(&cfg, &pattern).await?;
+        let pool = ConnectionPool::connect(&cfg).await?;
+        let wrpc = get_connection()
+            .get_wrpc_client(target_id)
+            .await
+            .context("failed to construct wRPC client")?;
+        info!(pattern, "watching keys");
+
+        let bucket = link_name.to_string();
+        let task = tokio::spawn(
+            async move {
+                let mut messages = pubsub.into_on_message();
+                while let Some(msg) = messages.next().await {
+                    let Some((_, key)) = msg.get_channel_name().split_once("__:") else {
+                        continue;
+                    };
+                    let Ok(event) = msg.get_payload::<String>() else {
+                        continue;
+                    };
+                    let res = match KeyspaceEvent::from_event(&event) {
+                        Some(KeyspaceEvent::Set) => {
+                            match Cmd::get(key)
+                                .query_async::<_, Option<Vec<u8>>>(&mut pool.get())
+                                .await
+                            {
+                                Ok(Some(value)) => {
+                                    watcher::on_set(&wrpc, None, &bucket, key, &value.into()).await
+                                }
+                                // The key was deleted before its value could be read, which will
+                                // be notified separately
+                                Ok(None) => continue,
+                                Err(err) => {
+                                    error!(key, ?err, "failed to get value of changed key");
+                                    continue;
+                                }
+                            }
+                        }
+                        Some(KeyspaceEvent::Delete) => {
+                            watcher::on_delete(&wrpc, None, &bucket, key).await
+                        }
+                        None => continue,
+                    };
+                    match res {
+                        Ok(Ok(())) => {}
+                        Ok(Err(err)) => warn!(key, err, "watcher returned an error"),
+                        Err(err) => error!(key, ?err, "failed to invoke watcher"),
+                    }
+                }
+                debug!("keyspace notification stream ended");
+            }
+            .in_current_span(),
+        );
+
+        let mut watchers = self.watchers.write().await;
+        if let Some(previous) =
+            watchers.insert((target_id.to_string(), link_name.to_string()), task)
+        {
+            previous.abort();
+        }
+        Ok(())
+    }
+
+    /// Stop watching keys on behalf of the target component
+    #[instrument(level = "info", skip_all, fields(target_id = info.get_target_id()))]
+    async fn delete_link_as_source(&self, info: impl LinkDeleteInfo) -> anyhow::Result<()> {
+        let key = (
+            info.get_target_id().to_string(),
+            info.get_link_name().to_string(),
+        );
+        if let Some(task) = self.watchers.write().await.remove(&key) {
+            task.abort();
+        }
+        debug!(component_id = key.0, "stopped watching keys for component");
+        Ok(())
+    }
+
     /// Handle notification that a link is dropped - close the connection
     #[instrument(level = "info", skip_all, fields(source_id = info.get_source_id()))]
     async fn delete_link_as_target(&self, info: impl LinkDeleteInfo) -> anyhow::Result<()> {
@@ -402,10 +554,35 @@ impl Provider for KvRedisProvider {
         for (_, conn) in aw.drain() {
             drop(conn);
         }
+        for (_, task) in self.watchers.write().await.drain() {
+            task.abort();
+        }
         Ok(())
     }
 }
 
+/// A change to a key, as reported by a keyspace notification
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum KeyspaceEvent {
+    /// The value of the key was written
+    Set,
+    /// The key was deleted, or expired or was evicted
+    Delete,
+}
+
+impl KeyspaceEvent {
+    /// Classify the event name published as the payload of a keyspace notification, ignoring
+    /// events which do not change the value of a string key
+    fn from_event(event: &str) -> Option<Self> {
+        match event {
+            "set" | "setrange" | "append" | "incrby" | "incrbyfloat" | "rename_to" | "copy_to"
+            | "restore" => Some(Self::Set),
+            "del" | "expired" | "evicted" | "rename_from" | "move_from" => Some(Self::Delete),
+            _ => None,
+        }
+    }
+}
+
 /// Fetch the default URL to use for connecting to Redis from the configuration, defaulting
 /// to `DEFAULT_CONNECT_URL` if no URL is found in the configuration.
 pub fn retrieve_default_url(config: &HashMap<String, String>) -> String {
@@ -436,10 +613,28 @@ fn check_bucket_name(bucket: &str) {
 mod test {
     use std::collections::HashMap;
 
-    use crate::retrieve_default_url;
+    use crate::{retrieve_default_url, KeyspaceEvent};
 
     const PROPER_URL: &str = "redis://127.0.0.1:6379";
 
+    #[test]
+    fn classifies_keyspace_events() {
+        assert_eq!(KeyspaceEvent::from_event("set"), Some(KeyspaceEvent::Set));
+        assert_eq!(
+            KeyspaceEvent::from_event("incrby"),
+            Some(KeyspaceEvent::Set)
+        );
+        assert_eq!(
+            KeyspaceEvent::from_event("del"),
+            Some(KeyspaceEvent::Delete)
+        );
+        assert_eq!(
+            KeyspaceEvent::from_event("expired"),
+            Some(KeyspaceEvent::Delete)
+        );
+        assert_eq!(KeyspaceEvent::from_event("expire"), None);
+    }
+
     #[test]
     fn can_deserialize_config_case_insensitive() {
         let lowercase_config = HashMap::from_iter([("url".to_string(), PROPER_URL.to_string())]);
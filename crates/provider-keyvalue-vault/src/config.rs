@@ -5,8 +5,9 @@ use core::time::Duration;
 
 use std::collections::HashMap;
 use std::env;
+use std::path::PathBuf;
 
-use anyhow::{Context, Result};
+use anyhow::{bail, Context, Result};
 use tracing::warn;
 use url::Url;
 use wasmcloud_provider_sdk::{core::secrets::SecretValue, LinkConfig};
@@ -17,12 +18,37 @@ use crate::TOKEN_REFRESH_INTERVAL;
 /// used if unspecified by configuration
 const DEFAULT_VAULT_ADDR: &str = "http://127.0.0.1:8200";
 
+/// Default path of the service account token used for Kubernetes authentication
+const DEFAULT_KUBERNETES_JWT_PATH: &str = "/var/run/secrets/kubernetes.io/serviceaccount/token";
+
+/// Method used to authenticate with Vault
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Auth {
+    /// Static token, which is periodically renewed
+    Token(String),
+    /// [AppRole](https://developer.hashicorp.com/vault/docs/auth/approle) role and secret ID
+    AppRole {
+        /// Mount point of the auth method, defaults to "approle"
+        mount: String,
+        role_id: String,
+        secret_id: String,
+    },
+    /// [Kubernetes](https://developer.hashicorp.com/vault/docs/auth/kubernetes) service account
+    /// token, which is read from `jwt_path` on every login
+    Kubernetes {
+        /// Mount point of the auth method, defaults to "kubernetes"
+        mount: String,
+        role: String,
+        jwt_path: PathBuf,
+    },
+}
+
 /// KV-Vault configuration
 #[derive(Clone, Debug)]
 pub struct Config {
-    /// Token for connecting to vault, can be set in environment with VAULT_TOKEN.
-    /// Required
-    pub token: String,
+    /// Authentication method, can be set in environment with VAULT_AUTH_METHOD.
+    /// Defaults to a token, which can be set in environment with VAULT_TOKEN
+    pub auth: Auth,
     /// Url for connecting to vault, can be set in environment with VAULT_ADDR.
     /// Defaults to 'http://127.0.0.1:8200'
     pub addr: Url,
@@ -33,6 +59,12 @@ pub struct Config {
     /// The linkdef value `certs` and the environment variable `VAULT_CERTS`
     /// are parsed as a comma-separated string of file paths to generate this list.
     pub certs: Vec<String>,
+    /// Mount point of the transit secrets engine, can be set in environment with
+    /// VAULT_TRANSIT_MOUNT. Defaults to "transit"
+    pub transit_mount: String,
+    /// Mount point of the database secrets engine, can be set in environment with
+    /// VAULT_DATABASE_MOUNT. Defaults to "database"
+    pub database_mount: String,
 
     /// Renewal TTL for tokens used by this provider. Defaults to 72 hours.
     pub token_increment_ttl: Option<String>,
@@ -53,17 +85,19 @@ impl Config {
     pub fn from_link_config(link_config: &LinkConfig) -> Result<Config> {
         let mut map = HashMap::clone(link_config.config);
 
-        // Attempt to retrieve the vault token from secrets
-        if let Some(token) = env::var("VAULT_TOKEN").ok().or_else(|| {
-            link_config
+        // Attempt to retrieve the credentials of the auth method from secrets
+        for (key, env_key) in [("token", "VAULT_TOKEN"), ("secret_id", "VAULT_SECRET_ID")] {
+            if let Some(value) = link_config
                 .secrets
-                .get("token")
+                .get(key)
                 .and_then(SecretValue::as_string)
-                .map(String::from)
-        }) {
-            map.insert("VAULT_TOKEN".into(), token);
-        } else {
-            warn!("Secret value [token] (ENV: VAULT_TOKEN) was not found in env or secrets. Please prefer ENV variables or secrets for sensitive values.")
+            {
+                map.insert(key.into(), value.into());
+            } else if env::var(env_key).is_err()
+                && (map.contains_key(key) || map.contains_key(&key.to_uppercase()))
+            {
+                warn!("Secret value [{key}] (ENV: {env_key}) was not found in env or secrets. Please prefer ENV variables or secrets for sensitive values.")
+            }
         }
 
         Self::from_values(&map)
@@ -73,8 +107,23 @@ impl Config {
     ///
     /// NOTE: Prefer [`Self::from_link_config`] rather than this method directly
     pub fn from_values(values: &HashMap<String, String>) -> Result<Config> {
-        let addr = env::var("VAULT_ADDR")
-            .ok()
+        Self::from_values_and_env(values, |key| env::var(key).ok())
+    }
+
+    /// Initialize from linkdef values, the environment variables returned by `env_var`, and
+    /// defaults
+    fn from_values_and_env(
+        values: &HashMap<String, String>,
+        env_var: impl Fn(&str) -> Option<String>,
+    ) -> Result<Config> {
+        // Look up a setting in the environment, falling back to the lowercase and uppercase link
+        // configuration values
+        let lookup = |key: &str, env_key: &str| {
+            env_var(env_key)
+                .or_else(|| values.get(key).cloned())
+                .or_else(|| values.get(&key.to_uppercase()).cloned())
+        };
+        let addr = env_var("VAULT_ADDR")
             .or_else(|| values.get("addr").cloned())
             .or_else(|| values.get("ADDR").cloned())
             .unwrap_or_else(|| DEFAULT_VAULT_ADDR.to_string());
@@ -84,33 +133,57 @@ impl Config {
             );
             DEFAULT_VAULT_ADDR.parse().unwrap()
         });
-        let token = env::var("VAULT_TOKEN")
-            .ok()
-            .or_else(|| values.get("token").cloned())
-            .or_else(|| values.get("TOKEN").cloned())
-            .context("missing setting for 'token' or VAULT_TOKEN")?;
-        let mount = env::var("VAULT_MOUNT")
-            .ok()
+        let auth = match lookup("auth_method", "VAULT_AUTH_METHOD")
+            .as_deref()
+            .map(str::to_lowercase)
+            .as_deref()
+        {
+            None | Some("token") => Auth::Token(
+                lookup("token", "VAULT_TOKEN")
+                    .context("missing setting for 'token' or VAULT_TOKEN")?,
+            ),
+            Some("approle") => Auth::AppRole {
+                mount: lookup("auth_mount", "VAULT_AUTH_MOUNT").unwrap_or_else(|| "approle".into()),
+                role_id: lookup("role_id", "VAULT_ROLE_ID")
+                    .context("missing setting for 'role_id' or VAULT_ROLE_ID")?,
+                secret_id: lookup("secret_id", "VAULT_SECRET_ID")
+                    .context("missing setting for 'secret_id' or VAULT_SECRET_ID")?,
+            },
+            Some("kubernetes") => Auth::Kubernetes {
+                mount: lookup("auth_mount", "VAULT_AUTH_MOUNT")
+                    .unwrap_or_else(|| "kubernetes".into()),
+                role: lookup("role", "VAULT_ROLE")
+                    .context("missing setting for 'role' or VAULT_ROLE")?,
+                jwt_path: lookup("jwt_path", "VAULT_JWT_PATH")
+                    .unwrap_or_else(|| DEFAULT_KUBERNETES_JWT_PATH.into())
+                    .into(),
+            },
+            Some(method) => bail!(
+                "unsupported auth method '{method}', expected 'token', 'approle' or 'kubernetes'"
+            ),
+        };
+        let mount = env_var("VAULT_MOUNT")
             .or_else(|| values.get("mount").cloned())
             .or_else(|| values.get("MOUNT").cloned())
             .unwrap_or_else(|| "secret".to_string());
-        let certs = env::var("VAULT_CERTS")
-            .ok()
+        let certs = env_var("VAULT_CERTS")
             .or_else(|| values.get("certs").cloned())
             .or_else(|| values.get("CERTS").cloned())
             .map(|certs| certs.split(',').map(|s| s.trim().to_string()).collect())
             .unwrap_or_default();
         Ok(Config {
             addr,
-            token,
+            auth,
             mount,
             certs,
-            token_increment_ttl: env::var("VAULT_TOKEN_INCREMENT_TTL")
-                .ok()
+            transit_mount: lookup("transit_mount", "VAULT_TRANSIT_MOUNT")
+                .unwrap_or_else(|| "transit".into()),
+            database_mount: lookup("database_mount", "VAULT_DATABASE_MOUNT")
+                .unwrap_or_else(|| "database".into()),
+            token_increment_ttl: env_var("VAULT_TOKEN_INCREMENT_TTL")
                 .or_else(|| values.get("token_increment_ttl").cloned())
                 .or_else(|| values.get("TOKEN_INCREMENT_TTL").cloned()),
-            token_refresh_interval: match env::var("VAULT_TOKEN_REFRESH_INTERVAL")
-                .ok()
+            token_refresh_interval: match env_var("VAULT_TOKEN_REFRESH_INTERVAL")
                 .or_else(|| values.get("token_refresh_interval").cloned())
                 .or_else(|| values.get("TOKEN_REFRESH_INTERVAL").cloned())
             {
@@ -129,3 +202,62 @@ impl Config {
         })
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn auth_methods() {
+        // Ignore the environment, which may configure e.g. VAULT_TOKEN
+        let config = |values: &[(&str, &str)]| {
+            Config::from_values_and_env(
+                &values
+                    .iter()
+                    .map(|(k, v)| (k.to_string(), v.to_string()))
+                    .collect(),
+                |_| None,
+            )
+        };
+
+        assert_eq!(
+            config(&[("token", "root")]).unwrap().auth,
+            Auth::Token("root".into())
+        );
+        assert_eq!(
+            config(&[
+                ("AUTH_METHOD", "AppRole"),
+                ("role_id", "role"),
+                ("secret_id", "secret")
+            ])
+            .unwrap()
+            .auth,
+            Auth::AppRole {
+                mount: "approle".into(),
+                role_id: "role".into(),
+                secret_id: "secret".into(),
+            }
+        );
+        assert_eq!(
+            config(&[
+                ("auth_method", "kubernetes"),
+                ("auth_mount", "k8s"),
+                ("role", "app")
+            ])
+            .unwrap()
+            .auth,
+            Auth::Kubernetes {
+                mount: "k8s".into(),
+                role: "app".into(),
+                jwt_path: DEFAULT_KUBERNETES_JWT_PATH.into(),
+            }
+        );
+        assert!(config(&[("auth_method", "approle"), ("role_id", "role")]).is_err());
+        assert!(config(&[("auth_method", "userpass")]).is_err());
+        assert!(config(&[]).is_err());
+
+        let config = config(&[("token", "root"), ("transit_mount", "crypto")]).unwrap();
+        assert_eq!(config.transit_mount, "crypto");
+        assert_eq!(config.database_mount, "database");
+    }
+}
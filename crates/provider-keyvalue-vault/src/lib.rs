@@ -1,4 +1,4 @@
-pub(crate) mod config;
+pub mod config;
 
 use core::str;
 use core::time::Duration;
@@ -10,9 +10,14 @@ use std::sync::Arc;
 use anyhow::{anyhow, bail, Context as _};
 use base64::Engine as _;
 use bytes::Bytes;
+use rustify::Endpoint as _;
 use tokio::sync::{Mutex, RwLock};
 use tokio::task::JoinHandle;
 use tracing::{debug, error, info, instrument, warn};
+use vaultrs::api::database::requests::GenerateCredentialsRequest;
+use vaultrs::api::database::responses::GenerateCredentialsResponse;
+use vaultrs::api::transit::requests::VerifySignedDataRequest;
+use vaultrs::api::EndpointResult;
 use vaultrs::client::{Client as _, VaultClient, VaultClientSettings};
 use wasmcloud_provider_sdk::{
     get_connection, load_host_data, propagate_trace_for_ctx, run_provider, Context, LinkConfig,
@@ -20,25 +25,42 @@ use wasmcloud_provider_sdk::{
 };
 use wasmcloud_provider_sdk::{initialize_observability, serve_provider_exports};
 
-use crate::config::Config;
+use crate::config::{Auth, Config};
 
 mod bindings {
     wit_bindgen_wrpc::generate!({
+        world: "interfaces",
         with: {
             "wrpc:keyvalue/store@0.2.0-draft": generate,
+            "wasmcloud:vault/database@0.1.0-draft": generate,
+            "wasmcloud:vault/transit@0.1.0-draft": generate,
         }
     });
 }
+use bindings::exports::wasmcloud::vault::{database, transit};
 use bindings::exports::wrpc::keyvalue;
 
 type Result<T, E = keyvalue::store::Error> = core::result::Result<T, E>;
 
+/// Credentials generated for a database role
+#[derive(Clone, Debug)]
+pub struct DatabaseCredentials {
+    pub username: String,
+    pub password: String,
+    /// ID of the Vault lease of the credentials
+    pub lease_id: String,
+    /// Duration after which the lease expires and the credentials are revoked
+    pub lease_duration: Duration,
+}
+
 /// Vault HTTP api version. As of Vault 1.9.x (Feb 2022), all http api calls use version 1
 const API_VERSION: u8 = 1;
 
 /// Default TTL for tokens used by this provider. Defaults to 72 hours.
 pub const TOKEN_INCREMENT_TTL: &str = "72h";
 pub const TOKEN_REFRESH_INTERVAL: Duration = Duration::from_secs(60 * 60 * 12); // 12 hours
+/// Interval at which a failed login is retried
+const LOGIN_RETRY_INTERVAL: Duration = Duration::from_secs(10);
 
 pub async fn run() -> anyhow::Result<()> {
     KvVaultProvider::run().await
@@ -47,8 +69,12 @@ pub async fn run() -> anyhow::Result<()> {
 /// Vault client connection information.
 #[derive(Clone)]
 pub struct Client {
-    inner: Arc<vaultrs::client::VaultClient>,
+    /// Client using the current token, which is replaced on every login
+    inner: Arc<RwLock<Arc<VaultClient>>>,
+    auth: Auth,
     namespace: String,
+    transit_mount: String,
+    database_mount: String,
     token_increment_ttl: String,
     token_refresh_interval: Duration,
     renew_task: Arc<Mutex<Option<JoinHandle<()>>>>,
@@ -59,9 +85,14 @@ impl Client {
     ///
     /// Note that this constructor does not attempt to connect to the vault server,
     /// so the vault server does not need to be running at the time a `LinkDefinition` to this provider is created.
+    /// Auth methods other than a static token require a call to [`Client::login`] before use.
     pub fn new(config: Config) -> Result<Self, vaultrs::error::ClientError> {
+        let token = match &config.auth {
+            Auth::Token(token) => token.clone(),
+            Auth::AppRole { .. } | Auth::Kubernetes { .. } => String::new(),
+        };
         let client = VaultClient::new(VaultClientSettings {
-            token: config.token,
+            token,
             address: config.addr,
             ca_certs: config.certs,
             verify: false,
@@ -72,8 +103,11 @@ impl Client {
             identity: None,
         })?;
         Ok(Self {
-            inner: Arc::new(client),
+            inner: Arc::new(RwLock::new(Arc::new(client))),
+            auth: config.auth,
             namespace: config.mount,
+            transit_mount: config.transit_mount,
+            database_mount: config.database_mount,
             token_increment_ttl: config
                 .token_increment_ttl
                 .unwrap_or(TOKEN_INCREMENT_TTL.into()),
@@ -84,9 +118,112 @@ impl Client {
         })
     }
 
+    /// Returns the client using the current token
+    async fn vault(&self) -> Arc<VaultClient> {
+        self.inner.read().await.clone()
+    }
+
+    /// Logs in using the configured auth method, replacing the token used by the client.
+    /// Returns the lease duration of the new token, or `None` if a static token is used.
+    pub async fn login(&self) -> anyhow::Result<Option<Duration>> {
+        login(&self.inner, &self.auth).await
+    }
+
+    /// Encrypts `plaintext` using the named transit key
+    pub async fn encrypt(&self, key: &str, plaintext: &[u8]) -> anyhow::Result<String> {
+        let plaintext = base64::engine::general_purpose::STANDARD.encode(plaintext);
+        let res = vaultrs::transit::data::encrypt(
+            self.vault().await.as_ref(),
+            &self.transit_mount,
+            key,
+            &plaintext,
+            None,
+        )
+        .await
+        .context("failed to encrypt data")?;
+        Ok(res.ciphertext)
+    }
+
+    /// Decrypts `ciphertext` using the named transit key
+    pub async fn decrypt(&self, key: &str, ciphertext: &str) -> anyhow::Result<Vec<u8>> {
+        let res = vaultrs::transit::data::decrypt(
+            self.vault().await.as_ref(),
+            &self.transit_mount,
+            key,
+            ciphertext,
+            None,
+        )
+        .await
+        .context("failed to decrypt data")?;
+        base64::engine::general_purpose::STANDARD
+            .decode(res.plaintext)
+            .context("failed to decode plaintext")
+    }
+
+    /// Signs `input` using the named transit key
+    pub async fn sign(&self, key: &str, input: &[u8]) -> anyhow::Result<String> {
+        let input = base64::engine::general_purpose::STANDARD.encode(input);
+        let res = vaultrs::transit::data::sign(
+            self.vault().await.as_ref(),
+            &self.transit_mount,
+            key,
+            &input,
+            None,
+        )
+        .await
+        .context("failed to sign data")?;
+        Ok(res.signature)
+    }
+
+    /// Verifies that `signature` is a signature of `input` made with the named transit key
+    pub async fn verify(&self, key: &str, input: &[u8], signature: &str) -> anyhow::Result<bool> {
+        let input = base64::engine::general_purpose::STANDARD.encode(input);
+        let res = vaultrs::transit::data::verify(
+            self.vault().await.as_ref(),
+            &self.transit_mount,
+            key,
+            &input,
+            Some(VerifySignedDataRequest::builder().signature(signature)),
+        )
+        .await
+        .context("failed to verify signature")?;
+        Ok(res.valid)
+    }
+
+    /// Generates credentials for the named database role
+    pub async fn database_credentials(&self, role: &str) -> anyhow::Result<DatabaseCredentials> {
+        let vault = self.vault().await;
+        let req = GenerateCredentialsRequest::builder()
+            .mount(&self.database_mount)
+            .name(role)
+            .build()
+            .context("failed to build database credentials request")?;
+        // `vaultrs::database::role::creds` strips the lease from the response, so the request is
+        // executed directly
+        let EndpointResult {
+            data,
+            lease_id,
+            lease_duration,
+            ..
+        } = req
+            .with_middleware(vault.middle())
+            .exec(vault.http())
+            .await
+            .and_then(|res| res.wrap::<EndpointResult<_>>())
+            .context("failed to generate database credentials")?;
+        let GenerateCredentialsResponse { username, password } =
+            data.context("database credentials missing from response")?;
+        Ok(DatabaseCredentials {
+            username,
+            password,
+            lease_id,
+            lease_duration: Duration::from_secs(lease_duration.into()),
+        })
+    }
+
     /// Reads value of secret using namespace and key path
     pub async fn read_secret(&self, path: &str) -> Result<Option<HashMap<String, String>>> {
-        match vaultrs::kv2::read(self.inner.as_ref(), &self.namespace, path).await {
+        match vaultrs::kv2::read(self.vault().await.as_ref(), &self.namespace, path).await {
             Err(vaultrs::error::ClientError::APIError {
                 code: 404,
                 errors: _,
@@ -104,7 +241,7 @@ impl Client {
 
     /// Writes value of secret using namespace and key path
     pub async fn write_secret(&self, path: &str, data: &HashMap<String, String>) -> Result<()> {
-        let md = vaultrs::kv2::set(self.inner.as_ref(), &self.namespace, path, data)
+        let md = vaultrs::kv2::set(self.vault().await.as_ref(), &self.namespace, path, data)
             .await
             .map_err(|err| {
                 error!(error = %err, "failed to write secret");
@@ -117,7 +254,8 @@ impl Client {
         Ok(())
     }
 
-    /// Sets up a background task to renew the token at the configured interval. This function
+    /// Sets up a background task to renew the token at the configured interval. Tokens obtained
+    /// by logging in are instead replaced by logging in again before they expire. This function
     /// attempts to lock the `renew_task` mutex and will deadlock if called without first ensuring
     /// the lock is available.
     pub async fn set_renewal(&self) {
@@ -125,16 +263,34 @@ impl Client {
         if let Some(handle) = renew_task.take() {
             handle.abort();
         }
-        let client = self.inner.clone();
+        let inner = self.inner.clone();
+        let auth = self.auth.clone();
         let interval = self.token_refresh_interval;
         let ttl = self.token_increment_ttl.clone();
 
         *renew_task = Some(tokio::spawn(async move {
-            let mut next_interval = tokio::time::interval(interval);
+            if let Auth::Token(..) = auth {
+                let mut next_interval = tokio::time::interval(interval);
+                loop {
+                    next_interval.tick().await;
+                    let client = inner.read().await.clone();
+                    // NOTE(brooksmtownsend): Errors are appropriately logged in the function
+                    let _ = renew_self(&client, ttl.as_str()).await;
+                }
+            }
             loop {
-                next_interval.tick().await;
-                // NOTE(brooksmtownsend): Errors are appropriately logged in the function
-                let _ = renew_self(&client, ttl.as_str()).await;
+                let client = inner.read().await.clone();
+                let delay = match client.lookup().await {
+                    // Log in again once two thirds of the token TTL have elapsed
+                    Ok(info) if info.ttl > 0 => Duration::from_secs(info.ttl) * 2 / 3,
+                    Ok(_) => interval,
+                    // The token is missing or expired, e.g. because the last login failed
+                    Err(_) => LOGIN_RETRY_INTERVAL,
+                };
+                tokio::time::sleep(delay).await;
+                if let Err(err) = login(&inner, &auth).await {
+                    error!("error logging in to vault: {err:#}");
+                }
             }
         }));
     }
@@ -151,6 +307,44 @@ impl Drop for Client {
     }
 }
 
+/// Helper function to log in using `auth`, replacing the client with one using the new token
+async fn login(inner: &RwLock<Arc<VaultClient>>, auth: &Auth) -> anyhow::Result<Option<Duration>> {
+    let client = inner.read().await.clone();
+    let info = match auth {
+        Auth::Token(..) => return Ok(None),
+        Auth::AppRole {
+            mount,
+            role_id,
+            secret_id,
+        } => vaultrs::auth::approle::login(client.as_ref(), mount, role_id, secret_id)
+            .await
+            .context("failed to log in with AppRole")?,
+        Auth::Kubernetes {
+            mount,
+            role,
+            jwt_path,
+        } => {
+            let jwt = tokio::fs::read_to_string(jwt_path).await.with_context(|| {
+                format!(
+                    "failed to read service account token from `{}`",
+                    jwt_path.display()
+                )
+            })?;
+            vaultrs::auth::kubernetes::login(client.as_ref(), mount, role, jwt.trim())
+                .await
+                .context("failed to log in with Kubernetes service account token")?
+        }
+    };
+    let client = VaultClient::new(VaultClientSettings {
+        token: info.client_token,
+        ..client.settings.clone()
+    })
+    .context("failed to create client with new token")?;
+    *inner.write().await = Arc::new(client);
+    info!(accessor = %info.accessor, lease_duration = info.lease_duration, "logged in to vault");
+    Ok(Some(Duration::from_secs(info.lease_duration)))
+}
+
 /// Helper function to renew a client's token, incrementing the validity by `increment`
 async fn renew_self(
     client: &VaultClient,
@@ -206,22 +400,29 @@ impl KvVaultProvider {
     }
 
     /// Retrieve a client for a given context (determined by `source_id`)
-    async fn get_client(&self, ctx: Option<Context>) -> Result<Arc<Client>> {
+    async fn client(&self, ctx: Option<Context>) -> anyhow::Result<Arc<Client>> {
         let ctx = ctx.ok_or_else(|| {
             warn!("invocation context missing");
-            keyvalue::store::Error::Other("invocation context missing".into())
+            anyhow!("invocation context missing")
         })?;
         let source_id = ctx.component.as_ref().ok_or_else(|| {
             warn!("source ID missing");
-            keyvalue::store::Error::Other("source ID missing".into())
+            anyhow!("source ID missing")
         })?;
         let links = self.components.read().await;
         links.get(source_id).cloned().ok_or_else(|| {
             warn!(source_id, "source ID not linked");
-            keyvalue::store::Error::Other("source ID not linked".into())
+            anyhow!("source ID not linked")
         })
     }
 
+    /// Retrieve a client for a given context, for use by `wrpc:keyvalue/store`
+    async fn get_client(&self, ctx: Option<Context>) -> Result<Arc<Client>> {
+        self.client(ctx)
+            .await
+            .map_err(|err| keyvalue::store::Error::Other(format!("{err:#}")))
+    }
+
     /// Gets a value for a specified key. Deserialize the value as json
     /// If it's any other map, the entire map is returned as a serialized json string
     /// If the stored value is a plain string, returns the plain value
@@ -395,6 +596,101 @@ impl keyvalue::store::Handler<Option<Context>> for KvVaultProvider {
     }
 }
 
+impl transit::Handler<Option<Context>> for KvVaultProvider {
+    #[instrument(level = "debug", skip(self, plaintext))]
+    async fn encrypt(
+        &self,
+        context: Option<Context>,
+        key: String,
+        plaintext: Bytes,
+    ) -> anyhow::Result<Result<String, String>> {
+        Ok(async {
+            propagate_trace_for_ctx!(context);
+            let client = self.client(context).await?;
+            client.encrypt(&key, &plaintext).await
+        }
+        .await
+        .map_err(|err| format!("{err:#}")))
+    }
+
+    #[instrument(level = "debug", skip(self, ciphertext))]
+    async fn decrypt(
+        &self,
+        context: Option<Context>,
+        key: String,
+        ciphertext: String,
+    ) -> anyhow::Result<Result<Bytes, String>> {
+        Ok(async {
+            propagate_trace_for_ctx!(context);
+            let client = self.client(context).await?;
+            client.decrypt(&key, &ciphertext).await.map(Bytes::from)
+        }
+        .await
+        .map_err(|err| format!("{err:#}")))
+    }
+
+    #[instrument(level = "debug", skip(self, input))]
+    async fn sign(
+        &self,
+        context: Option<Context>,
+        key: String,
+        input: Bytes,
+    ) -> anyhow::Result<Result<String, String>> {
+        Ok(async {
+            propagate_trace_for_ctx!(context);
+            let client = self.client(context).await?;
+            client.sign(&key, &input).await
+        }
+        .await
+        .map_err(|err| format!("{err:#}")))
+    }
+
+    #[instrument(level = "debug", skip(self, input))]
+    async fn verify(
+        &self,
+        context: Option<Context>,
+        key: String,
+        input: Bytes,
+        signature: String,
+    ) -> anyhow::Result<Result<bool, String>> {
+        Ok(async {
+            propagate_trace_for_ctx!(context);
+            let client = self.client(context).await?;
+            client.verify(&key, &input, &signature).await
+        }
+        .await
+        .map_err(|err| format!("{err:#}")))
+    }
+}
+
+impl database::Handler<Option<Context>> for KvVaultProvider {
+    #[instrument(level = "debug", skip(self))]
+    async fn get_credentials(
+        &self,
+        context: Option<Context>,
+        role: String,
+    ) -> anyhow::Result<Result<database::Credentials, String>> {
+        Ok(async {
+            propagate_trace_for_ctx!(context);
+            let client = self.client(context).await?;
+            let DatabaseCredentials {
+                username,
+                password,
+                lease_id,
+                lease_duration,
+            } = client.database_credentials(&role).await?;
+            Ok(database::Credentials {
+                username,
+                password,
+                lease_id,
+                lease_duration: lease_duration.as_secs(),
+            })
+        }
+        .await
+        .map_err(|err: anyhow::Error| format!("{err:#}")))
+    }
+}
+
 /// Handle provider control commands, the minimum required of any provider on
 /// a wasmcloud lattice
 impl Provider for KvVaultProvider {
@@ -440,6 +736,15 @@ impl Provider for KvVaultProvider {
                 return Err(anyhow!(e).context("failed to create new client config"));
             }
         };
+        // NOTE: Failed logins are retried in the background, so that the vault server does not
+        // need to be reachable when the link is created
+        if let Err(e) = client.login().await {
+            warn!(
+                %source_id,
+                %link_name,
+                "failed to log in to vault, retrying in the background: {e:#}",
+            );
+        }
         client.set_renewal().await;
 
         let mut update_map = self.components.write().await;
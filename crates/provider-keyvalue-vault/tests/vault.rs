@@ -0,0 +1,422 @@
+//! NOTE: the tests in this file start a dev-mode Vault server using docker. To use an existing
+//! Vault server instead, set `VAULT_ADDR` and `VAULT_TOKEN` to its address and root token:
+//!
+//! ```console
+//! vault server -dev -dev-root-token-id=root &
+//! export VAULT_ADDR=http://127.0.0.1:8200
+//! export VAULT_TOKEN=root
+//! cargo test -p wasmcloud-provider-keyvalue-vault --test vault -- --nocapture
+//! ```
+//!
+//! The database test starts Postgres using docker, unless `PGHOST`, `PGPORT` and `PGPASSWORD`
+//! are set to the address and `postgres` superuser password of a server reachable by Vault.
+
+use std::collections::HashMap;
+use std::env;
+use std::io::Write as _;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::{Context as _, Result};
+use axum::http::{header, StatusCode};
+use axum::routing::post;
+use axum::Router;
+use base64::Engine as _;
+use vaultrs::api::auth::approle::requests::SetAppRoleRequest;
+use vaultrs::api::auth::kubernetes::requests::{
+    ConfigureKubernetesAuthRequest, CreateKubernetesRoleRequest,
+};
+use vaultrs::api::database::requests::{PostgreSQLConnectionRequest, SetRoleRequest};
+use vaultrs::api::transit::requests::CreateKeyRequest;
+use vaultrs::api::transit::KeyType;
+use vaultrs::client::{VaultClient, VaultClientSettingsBuilder};
+use vaultrs::error::ClientError;
+use wasmcloud_provider_keyvalue_vault::config::{Auth, Config};
+use wasmcloud_provider_keyvalue_vault::Client;
+use wasmcloud_test_util::testcontainers::{
+    AsyncRunner as _, ContainerAsync, Host, ImageExt as _, Postgres, Vault, POSTGRES_PASSWORD,
+    VAULT_ROOT_TOKEN,
+};
+
+/// Policy granting access to the transit keys created by [`TestEnv::new`]
+const TRANSIT_POLICY: &str = r#"
+path "transit/encrypt/test-aes" { capabilities = ["update"] }
+path "transit/decrypt/test-aes" { capabilities = ["update"] }
+path "transit/sign/test-ed25519" { capabilities = ["update"] }
+path "transit/verify/test-ed25519" { capabilities = ["update"] }
+"#;
+
+/// Name of the service account authenticated by the fake Kubernetes API of
+/// [`serve_token_review`]
+const SERVICE_ACCOUNT: &str = "provider";
+const SERVICE_ACCOUNT_NAMESPACE: &str = "default";
+const SERVICE_ACCOUNT_UID: &str = "6f1c5ad7-9c1e-4a7f-8d4b-4f1b2c3d4e5f";
+
+struct TestEnv {
+    _container: Option<ContainerAsync<Vault>>,
+    addr: String,
+    /// Host of the machine running the tests, as seen by Vault
+    test_host: String,
+    root: VaultClient,
+}
+
+/// Ignore errors due to a secrets engine or auth method being enabled already, e.g. by another
+/// test running against the same Vault server
+fn ignore_in_use(res: Result<(), ClientError>) -> Result<(), ClientError> {
+    match res {
+        Err(ClientError::APIError { code: 400, errors })
+            if errors
+                .iter()
+                .any(|err| err.contains("path is already in use")) =>
+        {
+            Ok(())
+        }
+        res => res,
+    }
+}
+
+/// Enable a secrets engine, unless it is enabled already
+async fn enable_secrets_engine(root: &VaultClient, path: &str, engine: &str) -> Result<()> {
+    ignore_in_use(vaultrs::sys::mount::enable(root, path, engine, None).await)
+        .with_context(|| format!("failed to enable {engine} secrets engine"))
+}
+
+/// Enable an auth method, unless it is enabled already
+async fn enable_auth(root: &VaultClient, path: &str, method: &str) -> Result<()> {
+    ignore_in_use(vaultrs::sys::auth::enable(root, path, method, None).await)
+        .with_context(|| format!("failed to enable {method} auth"))
+}
+
+/// Set up the transit secrets engine and AppRole auth method.
+///
+/// This can be done repeatedly, e.g. by tests running against the same Vault server.
+async fn setup(root: &VaultClient) -> Result<()> {
+    enable_secrets_engine(root, "transit", "transit").await?;
+    // Creating a transit key which exists already has no effect
+    vaultrs::transit::key::create(root, "transit", "test-aes", None)
+        .await
+        .context("failed to create encryption key")?;
+    vaultrs::transit::key::create(
+        root,
+        "transit",
+        "test-ed25519",
+        Some(CreateKeyRequest::builder().key_type(KeyType::Ed25519)),
+    )
+    .await
+    .context("failed to create signing key")?;
+
+    vaultrs::sys::policy::set(root, "transit", TRANSIT_POLICY)
+        .await
+        .context("failed to create policy")?;
+    enable_auth(root, "approle", "approle").await?;
+    vaultrs::auth::approle::role::set(
+        root,
+        "approle",
+        "provider",
+        Some(SetAppRoleRequest::builder().token_policies(vec!["transit".into()])),
+    )
+    .await
+    .context("failed to create approle role")?;
+    Ok(())
+}
+
+impl TestEnv {
+    /// Start Vault and set up the transit secrets engine and AppRole auth method
+    async fn new() -> Result<Self> {
+        let (addr, token, test_host, container) = if let Ok(addr) = env::var("VAULT_ADDR") {
+            let token = env::var("VAULT_TOKEN").context("VAULT_TOKEN must be set")?;
+            (addr, token, "127.0.0.1".to_string(), None)
+        } else {
+            let node = Vault::default()
+                .with_host("host.docker.internal", Host::HostGateway)
+                .start()
+                .await
+                .context("should have started vault")?;
+            let host_ip = node
+                .get_host()
+                .await
+                .context("should have gotten vault ip")?;
+            let host_port = node
+                .get_host_port_ipv4(8200)
+                .await
+                .context("should have gotten vault port")?;
+            (
+                format!("http://{host_ip}:{host_port}"),
+                VAULT_ROOT_TOKEN.to_string(),
+                "host.docker.internal".to_string(),
+                Some(node),
+            )
+        };
+        let root = VaultClient::new(
+            VaultClientSettingsBuilder::default()
+                .address(&addr)
+                .token(token)
+                .build()
+                .context("failed to build vault client settings")?,
+        )
+        .context("failed to build vault client")?;
+
+        setup(&root).await?;
+
+        Ok(Self {
+            _container: container,
+            addr,
+            test_host,
+            root,
+        })
+    }
+
+    /// Build a config using the AppRole auth method
+    async fn approle_config(&self) -> Result<Config> {
+        let role_id = vaultrs::auth::approle::role::read_id(&self.root, "approle", "provider")
+            .await
+            .context("failed to read role ID")?
+            .role_id;
+        let secret_id =
+            vaultrs::auth::approle::role::secret::generate(&self.root, "approle", "provider", None)
+                .await
+                .context("failed to generate secret ID")?
+                .secret_id;
+        Config::from_values(&HashMap::from([
+            ("addr".into(), self.addr.clone()),
+            ("auth_method".into(), "approle".into()),
+            ("role_id".into(), role_id),
+            ("secret_id".into(), secret_id),
+        ]))
+    }
+}
+
+#[tokio::test]
+async fn test_transit_with_approle() -> Result<()> {
+    let env = TestEnv::new().await?;
+    let config = env.approle_config().await?;
+    assert!(matches!(config.auth, Auth::AppRole { .. }));
+    let client = Client::new(config)?;
+
+    // No token is available before logging in
+    assert!(client.encrypt("test-aes", b"hello").await.is_err());
+    let lease = client.login().await?;
+    assert!(lease.is_some());
+
+    let ciphertext = client.encrypt("test-aes", b"hello").await?;
+    assert!(ciphertext.starts_with("vault:v1:"));
+    assert_eq!(client.decrypt("test-aes", &ciphertext).await?, b"hello");
+    assert!(client
+        .decrypt("test-aes", "vault:v1:invalid")
+        .await
+        .is_err());
+
+    let signature = client.sign("test-ed25519", b"hello").await?;
+    assert!(client.verify("test-ed25519", b"hello", &signature).await?);
+    assert!(
+        !client
+            .verify("test-ed25519", b"goodbye", &signature)
+            .await?
+    );
+
+    // The policy only grants access to the keys used above
+    assert!(client.encrypt("test-ed25519", b"hello").await.is_err());
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_approle_invalid_secret_id() -> Result<()> {
+    let env = TestEnv::new().await?;
+    let mut config = env.approle_config().await?;
+    if let Auth::AppRole { secret_id, .. } = &mut config.auth {
+        *secret_id = "invalid".into();
+    }
+    let client = Client::new(config)?;
+    assert!(client.login().await.is_err());
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_setup_is_idempotent() -> Result<()> {
+    let env = TestEnv::new().await?;
+    // Set up the same server again, like tests running against `VAULT_ADDR` do
+    setup(&env.root).await?;
+    enable_auth(&env.root, "kubernetes", "kubernetes").await?;
+    enable_auth(&env.root, "kubernetes", "kubernetes").await?;
+    Ok(())
+}
+
+/// Serve a fake Kubernetes TokenReview API for Vault, which authenticates `jwt` as the
+/// [`SERVICE_ACCOUNT`] service account and rejects all other tokens.
+/// Returns the port the API is served on.
+async fn serve_token_review(jwt: String) -> Result<u16> {
+    let listener = tokio::net::TcpListener::bind("0.0.0.0:0")
+        .await
+        .context("failed to bind TokenReview API")?;
+    let port = listener.local_addr()?.port();
+    let router = Router::new().route(
+        "/apis/authentication.k8s.io/v1/tokenreviews",
+        post(move |body: String| async move {
+            let status = if body.contains(&jwt) {
+                serde_json::json!({
+                    "authenticated": true,
+                    "user": {
+                        "username": format!(
+                            "system:serviceaccount:{SERVICE_ACCOUNT_NAMESPACE}:{SERVICE_ACCOUNT}"
+                        ),
+                        "uid": SERVICE_ACCOUNT_UID,
+                    },
+                })
+            } else {
+                serde_json::json!({ "authenticated": false })
+            };
+            let review = serde_json::json!({
+                "apiVersion": "authentication.k8s.io/v1",
+                "kind": "TokenReview",
+                "status": status,
+            });
+            (
+                StatusCode::CREATED,
+                [(header::CONTENT_TYPE, "application/json")],
+                review.to_string(),
+            )
+        }),
+    );
+    tokio::spawn(async move { axum::serve(listener, router).await });
+    Ok(port)
+}
+
+/// Build a service account token for the [`SERVICE_ACCOUNT`] service account. Its signature is
+/// not verified by Vault, which validates the token using the TokenReview API instead.
+fn service_account_jwt() -> Result<String> {
+    let b64 = |v: serde_json::Value| {
+        base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(v.to_string())
+    };
+    let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+    let header = b64(serde_json::json!({ "alg": "RS256", "typ": "JWT" }));
+    let claims = b64(serde_json::json!({
+        "iss": "kubernetes/serviceaccount",
+        "sub": format!("system:serviceaccount:{SERVICE_ACCOUNT_NAMESPACE}:{SERVICE_ACCOUNT}"),
+        "iat": now,
+        "exp": now + 3600,
+        "kubernetes.io/serviceaccount/namespace": SERVICE_ACCOUNT_NAMESPACE,
+        "kubernetes.io/serviceaccount/service-account.name": SERVICE_ACCOUNT,
+        "kubernetes.io/serviceaccount/service-account.uid": SERVICE_ACCOUNT_UID,
+    }));
+    let signature = base64::engine::general_purpose::URL_SAFE_NO_PAD.encode([0; 256]);
+    Ok(format!("{header}.{claims}.{signature}"))
+}
+
+#[tokio::test]
+async fn test_transit_with_kubernetes() -> Result<()> {
+    let env = TestEnv::new().await?;
+    let jwt = service_account_jwt()?;
+    let port = serve_token_review(jwt.clone()).await?;
+
+    enable_auth(&env.root, "kubernetes", "kubernetes").await?;
+    vaultrs::auth::kubernetes::configure(
+        &env.root,
+        "kubernetes",
+        &format!("http://{}:{port}", env.test_host),
+        Some(ConfigureKubernetesAuthRequest::builder().disable_local_ca_jwt(true)),
+    )
+    .await
+    .context("failed to configure kubernetes auth")?;
+    vaultrs::auth::kubernetes::role::create(
+        &env.root,
+        "kubernetes",
+        "provider",
+        Some(
+            CreateKubernetesRoleRequest::builder()
+                .bound_service_account_names(vec![SERVICE_ACCOUNT.into()])
+                .bound_service_account_namespaces(vec![SERVICE_ACCOUNT_NAMESPACE.into()])
+                .token_policies(vec!["transit".into()]),
+        ),
+    )
+    .await
+    .context("failed to create kubernetes role")?;
+
+    let mut jwt_file = tempfile::NamedTempFile::new()?;
+    jwt_file.write_all(jwt.as_bytes())?;
+    let config = Config::from_values(&HashMap::from([
+        ("addr".into(), env.addr.clone()),
+        ("auth_method".into(), "kubernetes".into()),
+        ("role".into(), "provider".into()),
+        (
+            "jwt_path".into(),
+            jwt_file.path().to_string_lossy().into_owned(),
+        ),
+    ]))?;
+    assert!(matches!(config.auth, Auth::Kubernetes { .. }));
+    let client = Client::new(config)?;
+    let lease = client.login().await?;
+    assert!(lease.is_some());
+    let ciphertext = client.encrypt("test-aes", b"hello").await?;
+    assert_eq!(client.decrypt("test-aes", &ciphertext).await?, b"hello");
+
+    // The token is read again on every login, so a rejected token fails the next login
+    std::fs::write(jwt_file.path(), service_account_jwt()?.replace('.', "x."))?;
+    assert!(client.login().await.is_err());
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_database_credentials() -> Result<()> {
+    let env = TestEnv::new().await?;
+    let (pg_host, pg_port, pg_password, _postgres) = if let Ok(host) = env::var("PGHOST") {
+        let port = env::var("PGPORT").map_or(Ok(5432), |port| port.parse())?;
+        let password = env::var("PGPASSWORD").context("PGPASSWORD must be set")?;
+        (host, port, password, None)
+    } else {
+        let node = Postgres::default()
+            .start()
+            .await
+            .context("should have started postgres")?;
+        let port = node.get_host_port_ipv4(5432).await?;
+        (
+            env.test_host.clone(),
+            port,
+            POSTGRES_PASSWORD.to_string(),
+            Some(node),
+        )
+    };
+
+    enable_secrets_engine(&env.root, "database", "database").await?;
+    vaultrs::database::connection::postgres(
+        &env.root,
+        "database",
+        "postgres",
+        Some(
+            PostgreSQLConnectionRequest::builder()
+                .connection_url(format!(
+                    "postgresql://{{{{username}}}}:{{{{password}}}}@{pg_host}:{pg_port}/postgres?sslmode=disable"
+                ))
+                .username("postgres")
+                .password(pg_password)
+                .allowed_roles(vec!["readonly".into()]),
+        ),
+    )
+    .await
+    .context("failed to configure database connection")?;
+    vaultrs::database::role::set(
+        &env.root,
+        "database",
+        "readonly",
+        Some(
+            SetRoleRequest::builder()
+                .db_name("postgres")
+                .creation_statements(vec![
+                    "CREATE ROLE \"{{name}}\" WITH LOGIN PASSWORD '{{password}}' VALID UNTIL '{{expiration}}';".into(),
+                ])
+                .default_ttl("1h"),
+        ),
+    )
+    .await
+    .context("failed to create database role")?;
+
+    let client = Client::new(Config::from_values(&HashMap::from([
+        ("addr".into(), env.addr.clone()),
+        ("token".into(), VAULT_ROOT_TOKEN.into()),
+    ]))?)?;
+    let credentials = client.database_credentials("readonly").await?;
+    assert!(credentials.username.starts_with("v-"));
+    assert!(!credentials.password.is_empty());
+    assert!(credentials.lease_id.starts_with("database/creds/readonly/"));
+    assert_eq!(credentials.lease_duration.as_secs(), 3600);
+    assert!(client.database_credentials("unknown").await.is_err());
+    Ok(())
+}
@@ -10,6 +10,8 @@ use wasmcloud_control_interface::Client;
 
 use crate::ConnectionConfig;
 
+/// Cache of control interface clients, keyed by the ID of the linked component whose link
+/// configured the lattice and credentials to connect with
 #[derive(Clone)]
 pub(crate) struct ClientCache {
     meta: Arc<RwLock<HashMap<String, ClientMetadata>>>,
@@ -30,7 +32,7 @@ impl ClientMetadata {
 
 impl ClientCache {
     /// Creates a new client cache. Configures and starts the cache item expiration timer
-    pub(crate) async fn new(expire_in_seconds: u64) -> Self {
+    pub(crate) fn new(expire_in_seconds: u64) -> Self {
         let meta = RwLock::new(HashMap::new());
         let clients = RwLock::new(HashMap::new());
 
@@ -56,65 +58,80 @@ impl ClientCache {
         cc
     }
 
-    // At the moment the only thing that uses this function is the test
-    #[allow(dead_code)]
-    pub(crate) async fn remove_config(&self, lattice_id: &str) {
+    /// Removes the connection configuration stored for a given component, along with its client
+    pub(crate) async fn remove_config(&self, component_id: &str) {
         let mut m = self.meta.write().await;
+        m.remove(component_id);
+        drop(m);
 
-        m.remove(lattice_id);
+        self.clients.write().await.remove(component_id);
     }
 
-    /// Stores a connection configuration corresponding to a given lattice. No side effects, does _not_
-    /// create or establish a NATS connection
-    pub(crate) async fn put_config(&self, lattice_id: &str, config: ConnectionConfig) {
+    /// Removes all connection configurations and clients
+    pub(crate) async fn clear(&self) {
+        self.meta.write().await.clear();
+        self.clients.write().await.clear();
+    }
+
+    /// Stores a connection configuration corresponding to a given component. Does _not_
+    /// create or establish a NATS connection, but drops the existing client for the component if the
+    /// configuration changed
+    pub(crate) async fn put_config(&self, component_id: &str, config: ConnectionConfig) {
         let mut m = self.meta.write().await;
 
-        m.insert(
-            lattice_id.to_string(),
+        let previous = m.insert(
+            component_id.to_string(),
             ClientMetadata {
-                config,
+                config: config.clone(),
                 last_accessed: Instant::now(),
             },
         );
+        drop(m);
+
+        if previous.is_some_and(|previous| previous.config != config) {
+            self.clients.write().await.remove(component_id);
+        }
     }
 
     /// Retrieves a client from the cache. If one is already active, this will be returned. If not,
     /// one will be created from the stored connection configuration. If there is no active client
     /// and no suitable configuration, this function returns an error and will _not_ resort to
     /// fallback credentials
-    pub(crate) async fn get_client(&self, lattice_id: &str) -> Result<Client> {
+    pub(crate) async fn get_client(&self, component_id: &str) -> Result<Client> {
         let c = {
             // Don't hold the read lock for the whole func
             let lock = self.clients.read().await;
-            lock.get(lattice_id).cloned()
+            lock.get(component_id).cloned()
         };
         if let Some(c) = c {
-            self.record_access(lattice_id).await;
+            self.record_access(component_id).await;
             Ok(c)
         } else {
             let meta = {
                 // Dispose of lock as soon as we get what we need
                 let lock = self.meta.read().await;
-                lock.get(lattice_id).cloned()
+                lock.get(component_id).cloned()
             };
             if let Some(cfg) = meta {
                 let client = create_client(&cfg.config).await?;
-                self.store_client(lattice_id, client.clone()).await;
+                self.store_client(component_id, client.clone()).await;
+                self.record_access(component_id).await;
                 Ok(client)
             } else {
-                bail!("no client configuration for lattice [{lattice_id}] stored");
+                bail!("no lattice connection configured for component [{component_id}], is it linked?");
             }
         }
     }
 
-    async fn store_client(&self, lattice_id: &str, client: Client) {
+    async fn store_client(&self, component_id: &str, client: Client) {
         let mut conns = self.clients.write().await;
-        conns.insert(lattice_id.to_string(), client);
+        conns.insert(component_id.to_string(), client);
     }
 
-    async fn record_access(&self, lattice_id: &str) {
+    async fn record_access(&self, component_id: &str) {
         let mut meta = self.meta.write().await;
-        meta.entry(lattice_id.to_string()).and_modify(|e| e.touch());
+        meta.entry(component_id.to_string())
+            .and_modify(|e| e.touch());
     }
 }
 
@@ -125,19 +142,24 @@ async fn create_client(config: &ConnectionConfig) -> Result<wasmcloud_control_in
     let lattice = config.lattice.clone();
     let conn = connect(config).await?;
 
-    Ok(wasmcloud_control_interface::ClientBuilder::new(conn)
+    let mut builder = wasmcloud_control_interface::ClientBuilder::new(conn)
         .lattice(lattice)
         .timeout(timeout)
-        .auction_timeout(auction_timeout)
-        .build())
+        .auction_timeout(auction_timeout);
+    if let Some(prefix) = &config.topic_prefix {
+        builder = builder.topic_prefix(prefix);
+    }
+    Ok(builder.build())
 }
 
-/// Create a new nats connection
+/// Create a new nats connection to any of the configured cluster URIs
 async fn connect(cfg: &ConnectionConfig) -> Result<async_nats::Client> {
     let cfg = cfg.clone();
     let opts = match (cfg.auth_jwt, cfg.auth_seed) {
         (Some(jwt), Some(seed)) => {
-            let key_pair = std::sync::Arc::new(KeyPair::from_seed(&seed).context("key init: {e}")?);
+            let key_pair = std::sync::Arc::new(
+                KeyPair::from_seed(&seed).context("failed to parse seed key pair")?,
+            );
             async_nats::ConnectOptions::with_jwt(jwt, move |nonce| {
                 let key_pair = key_pair.clone();
                 async move { key_pair.sign(&nonce).map_err(async_nats::AuthError::new) }
@@ -152,8 +174,6 @@ async fn connect(cfg: &ConnectionConfig) -> Result<async_nats::Client> {
         bail!("No NATS URIs supplied");
     }
 
-    let url = cfg.cluster_uris.first().unwrap();
-
     let conn = opts
         .event_callback(|event| async move {
             // lattice prefix/ID will already be on the span from earlier calls
@@ -166,9 +186,9 @@ async fn connect(cfg: &ConnectionConfig) -> Result<async_nats::Client> {
                 other => debug!("NATS client other event occurred: {other}"),
             }
         })
-        .connect(url)
+        .connect(&cfg.cluster_uris)
         .await
-        .with_context(|| format!("Nats connection to {url}"))?;
+        .with_context(|| format!("Nats connection to {}", cfg.cluster_uris.join(",")))?;
 
     Ok(conn)
 }
@@ -200,24 +220,100 @@ async fn evacuate_cache(
     conns.retain(|k, _v| !expired_keys.contains(k));
 }
 
-/// The test suite below requires an anonymous localhost NATS
-///
-/// You can run one locally using `docker`:
-///
-/// ```console
-/// docker run --rm -p 4222:4222 nats -js
-/// ```
 #[cfg(test)]
 mod test {
     use std::time::Duration;
 
+    use tokio::io::{AsyncBufReadExt as _, AsyncWriteExt as _, BufReader};
+    use tokio::net::TcpListener;
+
     use crate::ConnectionConfig;
 
     use super::ClientCache;
 
+    /// Accept NATS connections on a local port, answering the handshake and pings, and return
+    /// its URI
+    async fn serve_fake_nats() -> String {
+        let listener = TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("failed to bind listener");
+        let addr = listener.local_addr().expect("failed to get address");
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                tokio::spawn(async move {
+                    let (rx, mut tx) = stream.into_split();
+                    let info = format!(
+                        r#"INFO {{"server_id":"test","server_name":"test","version":"2.10.0","host":"{}","port":{},"max_payload":1048576,"proto":1,"headers":true}}"#,
+                        addr.ip(),
+                        addr.port()
+                    );
+                    tx.write_all(format!("{info}\r\n").as_bytes()).await?;
+                    let mut lines = BufReader::new(rx).lines();
+                    while let Some(line) = lines.next_line().await? {
+                        if line == "PING" {
+                            tx.write_all(b"PONG\r\n").await?;
+                        }
+                    }
+                    std::io::Result::Ok(())
+                });
+            }
+        });
+        format!("nats://{addr}")
+    }
+
+    #[tokio::test]
+    async fn connects_to_any_cluster_uri() {
+        // Reserve a port and close it, so that connecting to it is refused
+        let unreachable = TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("failed to bind listener")
+            .local_addr()
+            .expect("failed to get address");
+        let cache = ClientCache::new(60);
+        cache
+            .put_config(
+                "test",
+                ConnectionConfig {
+                    cluster_uris: vec![format!("nats://{unreachable}"), serve_fake_nats().await],
+                    ..Default::default()
+                },
+            )
+            .await;
+        cache
+            .get_client("test")
+            .await
+            .expect("failed to connect to reachable cluster URI");
+
+        cache
+            .put_config(
+                "test",
+                ConnectionConfig {
+                    cluster_uris: vec![format!("nats://{unreachable}")],
+                    ..Default::default()
+                },
+            )
+            .await;
+        assert!(cache.get_client("test").await.is_err());
+
+        cache.remove_config("test").await;
+        let err = cache
+            .get_client("test")
+            .await
+            .expect_err("unconfigured component should fail");
+        assert!(err.to_string().contains("is it linked?"), "{err}");
+    }
+
+    /// This test requires an anonymous localhost NATS
+    ///
+    /// You can run one locally using `docker`:
+    ///
+    /// ```console
+    /// docker run --rm -p 4222:4222 nats -js
+    /// ```
     #[tokio::test]
+    #[ignore]
     async fn test_cache_evacuation() {
-        let cache = ClientCache::new(2).await;
+        let cache = ClientCache::new(2);
         cache.put_config("test", ConnectionConfig::default()).await;
 
         let _client = cache.get_client("test").await.unwrap();
@@ -1,819 +1,667 @@
-// TODO(brooksmtownsend): bring the lattice control capability provider up-to-date with the control interface
-// //! wasmCloud Lattice Control capability provider
-// //!
-// //!
-// use std::collections::HashMap;
-// use std::sync::Arc;
-
-// use tokio::sync::{RwLock, RwLockReadGuard};
-// use tracing::{error, instrument, warn};
-// use wasmcloud_control_interface as interface_client;
-
-// mod client_cache;
-// use client_cache::ClientCache;
-
-// const DEFAULT_NATS_URI: &str = "0.0.0.0:4222";
-// const DEFAULT_TIMEOUT_MS: u64 = 2000;
-
-// // NOTE: Exercise caution when adjusting this value, as it can cause tests and
-// // other examples to fail, due to going against various timeouts set on the host and
-// // cooperating providers/actors, since the *entire* auction duration will be awaited
-// // for operations like `get-hosts`
-// const DEFAULT_AUCTION_TIMEOUT_MS: u64 = 3000;
-// const DEFAULT_LATTICE: &str = "default";
-
-// use wasmcloud_provider_wit_bindgen::deps::{
-//     async_trait::async_trait,
-//     serde::{Deserialize, Serialize},
-//     wasmcloud_provider_sdk::{load_host_data, Context},
-// };
-
-// wasmcloud_provider_wit_bindgen::generate!({
-//     impl_struct: LatticeControllerProvider,
-//     replace_witified_maps: true,
-//     wit_bindgen_cfg: "provider"
-// });
-
-// /// lattice-controller capability provider implementation
-// #[derive(Clone)]
-// pub struct LatticeControllerProvider {
-//     connection_timeout_mins: u64,
-//     connections: Arc<RwLock<Option<ClientCache>>>,
-// }
-
-// impl LatticeControllerProvider {
-//     /// Create a controller provider with a specified cache timeout in minutes
-//     pub fn with_cache_timeout_minutes(mins: u64) -> Self {
-//         Self {
-//             connection_timeout_mins: mins,
-//             connections: Arc::new(RwLock::new(None)),
-//         }
-//     }
-
-//     /// Retrieve the the connection cache, initializing if necessary
-//     ///
-//     /// This is necessary to avoid having to use an async `main()`,
-//     /// as the tokio runtime spawned will clash with an existing running one
-//     async fn get_connections(&self) -> RwLockReadGuard<ClientCache> {
-//         if self.connections.read().await.is_none() {
-//             let cache = ClientCache::new(self.connection_timeout_mins).await;
-//             let mut connections = self.connections.write().await;
-//             *connections = Some(cache);
-//             drop(connections);
-//         }
-//         let guard = self.connections.read().await;
-//         match RwLockReadGuard::try_map(guard, |v| v.as_ref()) {
-//             Ok(v) => v,
-//             Err(_) => unreachable!("the connections cache must have been initialized"),
-//         }
-//     }
-// }
-
-// /// Configuration for connecting a nats client.
-// /// More options are available if you use the json than variables in the values string map.
-// #[derive(Debug, Clone, Serialize, Deserialize)]
-// #[serde(crate = "wasmcloud_provider_wit_bindgen::deps::serde")]
-// struct ConnectionConfig {
-//     /// URIs used to connect to the cluster
-//     #[serde(default)]
-//     cluster_uris: Vec<String>,
-
-//     /// Authentication JWT
-//     #[serde(default)]
-//     auth_jwt: Option<String>,
-
-//     /// Authentication Seed
-//     #[serde(default)]
-//     auth_seed: Option<String>,
-
-//     /// Name of the lattice
-//     #[serde(default)]
-//     lattice: String,
-
-//     /// NATS JetStream domain
-//     #[serde(default)]
-//     js_domain: Option<String>,
-
-//     /// Operation timeout used for the lattice client interface
-//     timeout_ms: u64,
-
-//     /// Auction timeout used for the lattice client interface
-//     auction_timeout_ms: u64,
-// }
-
-// impl Default for ConnectionConfig {
-//     fn default() -> Self {
-//         Self {
-//             cluster_uris: vec![DEFAULT_NATS_URI.to_owned()],
-//             auth_jwt: None,
-//             js_domain: None,
-//             auth_seed: None,
-//             lattice: String::from(DEFAULT_LATTICE),
-//             timeout_ms: DEFAULT_TIMEOUT_MS,
-//             auction_timeout_ms: DEFAULT_AUCTION_TIMEOUT_MS,
-//         }
-//     }
-// }
-
-// /// Implement the basic requirements of a wasmcloud capability provider
-// #[async_trait]
-// impl WasmcloudCapabilityProvider for LatticeControllerProvider {
-//     #[instrument(level = "debug", skip(self, _ld), fields(actor_id = %_ld.actor_id))]
-//     async fn put_link(
-//         &self,
-//         _ld: &wasmcloud_provider_wit_bindgen::deps::wasmcloud_provider_sdk::core::LinkDefinition,
-//     ) -> bool {
-//         // This provider is *externally* multiplexed -- link definitions
-//         // contain no useful data
-//         true
-//     }
-
-//     /// Handle notification that a link is dropped
-//     #[instrument(level = "debug", skip(self), fields(actor_id = ?_actor_id))]
-//     async fn delete_link(&self, _actor_id: &str) {
-//         // Link definitions do not determine the NATS connections
-//         // so there is nothing to clean up per link removal
-//     }
-
-//     /// Handle shutdown request
-//     #[instrument(level = "debug", skip(self))]
-//     async fn shutdown(&self) {
-//         // No cleanup necessary for shutdown, since links do not
-//         // determine/regulate the NATS connections
-//     }
-// }
-
-// /// Implement the lattice-controller-provider provider contract specified in WIT (see provider.wit)
-// #[async_trait]
-// impl WasmcloudLatticeControlLatticeController for LatticeControllerProvider {
-//     /// Sets lattice credentials and stores them in the cache to be used to create a
-//     /// connection in the next operation that requires one
-//     #[instrument(level = "debug", skip_all, fields(actor_id = ?_ctx.component, lattice_id = ?arg.lattice_id))]
-//     async fn set_lattice_credentials(
-//         &self,
-//         _ctx: Context,
-//         arg: SetLatticeCredentialsRequest,
-//     ) -> CtlOperationAck {
-//         let config = ConnectionConfig {
-//             cluster_uris: vec![arg
-//                 .nats_url
-//                 .clone()
-//                 .unwrap_or_else(|| "0.0.0.0:4222".to_string())],
-//             auth_jwt: arg.user_jwt.clone(),
-//             auth_seed: arg.user_seed.clone(),
-//             lattice: arg.lattice_id.to_string(),
-//             js_domain: arg.js_domain.clone(),
-//             ..Default::default()
-//         };
-
-//         // Setting the auction_timeout to a large value may trigger timeouts in distant code.
-//         //
-//         // - host configuration rpc_timeout
-//         // - `LatticeControllerSender`s which have embedded RPC clients w/ internal timeouts
-//         // - httpserver/messaging provider(s) which expect a certain response speed from calling actors
-//         //
-//         // Since auctions will *wait* until the auction_timeout to do operations like gathering hosts,
-//         // we must manually ensure this value is unlikely to cause timeouts.
-//         let host_data = match load_host_data() {
-//             Ok(host_data) => host_data,
-//             Err(e) => {
-//                 error!("failed to load host data: {e}");
-//                 return CtlOperationAck {
-//                     accepted: false,
-//                     error: "failed to load host data".into(),
-//                 };
-//             }
-//         };
-
-//         if host_data
-//             .default_rpc_timeout_ms
-//             .is_some_and(|v| v < config.timeout_ms)
-//         {
-//             warn!(
-//                 host_rpc_timeout_ms = host_data.default_rpc_timeout_ms,
-//                 auction_timeout = config.auction_timeout_ms,
-//                 "host default RPC timeout < auction timeout, operations that rely on auctions are likely to time out"
-//             );
-//         }
-
-//         self.get_connections()
-//             .await
-//             .put_config(&arg.lattice_id, config)
-//             .await;
-
-//         CtlOperationAck {
-//             accepted: true,
-//             error: "".to_string(),
-//         }
-//     }
-
-//     /// Sets registry credentials, storing them in the cache
-//     #[instrument(level = "debug", skip_all, fields(actor_id = ?_ctx.component, lattice_id = ?arg.lattice_id))]
-//     async fn set_registry_credentials(
-//         &self,
-//         _ctx: Context,
-//         arg: SetRegistryCredentialsRequest,
-//     ) -> () {
-//         let client = match self
-//             .get_connections()
-//             .await
-//             .get_client(&arg.lattice_id)
-//             .await
-//         {
-//             Ok(client) => client,
-//             Err(e) => {
-//                 error!("failed to get client for connection: {e}");
-//                 return;
-//             }
-//         };
-
-//         let mut hm = HashMap::new();
-//         if let Some(ref c) = arg.credentials {
-//             for (k, v) in c {
-//                 hm.insert(
-//                     k.to_string(),
-//                     interface_client::RegistryCredential {
-//                         password: v.password.clone(),
-//                         token: v.token.clone(),
-//                         username: v.username.clone(),
-//                         registry_type: "".to_string(),
-//                     },
-//                 );
-//             }
-//         }
-
-//         if let Err(e) = client.put_registries(hm).await {
-//             error!("failed to set registry credentials: {e}");
-//         };
-//     }
-
-//     /// Auction a provider on the lattice
-//     #[instrument(level = "debug", skip_all, fields(actor_id = ?_ctx.component, lattice_id = ?arg.lattice_id))]
-//     async fn auction_provider(
-//         &self,
-//         _ctx: Context,
-//         arg: ProviderAuctionRequest,
-//     ) -> Vec<ProviderAuctionAck> {
-//         let client = match self
-//             .get_connections()
-//             .await
-//             .get_client(&arg.lattice_id)
-//             .await
-//         {
-//             Ok(client) => client,
-//             Err(e) => {
-//                 error!("failed to get client for connection: {e}");
-//                 return Vec::new();
-//             }
-//         };
-
-//         client
-//             .perform_provider_auction(&arg.provider_ref, &arg.link_name, arg.constraints.clone())
-//             .await
-//             .map(|v| {
-//                 v.into_iter()
-//                     .map(|a| ProviderAuctionAck {
-//                         host_id: a.host_id,
-//                         link_name: a.link_name,
-//                         provider_ref: a.provider_ref,
-//                     })
-//                     .collect::<Vec<_>>()
-//             })
-//             .unwrap_or_else(|e| {
-//                 error!("failed to auction provider: {e}");
-//                 Vec::new()
-//             })
-//     }
-
-//     /// Auction an component on the lattice
-//     #[instrument(level = "debug", skip_all, fields(actor_id = ?_ctx.component, lattice_id = ?arg.lattice_id))]
-//     async fn auction_actor(&self, _ctx: Context, arg: ActorAuctionRequest) -> Vec<ActorAuctionAck> {
-//         let client = match self
-//             .get_connections()
-//             .await
-//             .get_client(&arg.lattice_id)
-//             .await
-//         {
-//             Ok(client) => client,
-//             Err(e) => {
-//                 error!("failed to get client for connection: {e}");
-//                 return Vec::new();
-//             }
-//         };
-
-//         client
-//             .perform_actor_auction(&arg.actor_ref, arg.constraints.clone())
-//             .await
-//             .map(|v| {
-//                 v.into_iter()
-//                     .map(|a| ActorAuctionAck {
-//                         actor_ref: a.actor_ref,
-//                         host_id: a.host_id,
-//                     })
-//                     .collect::<Vec<_>>()
-//             })
-//             .unwrap_or_else(|e| {
-//                 error!("failed to auction component: {e}");
-//                 Vec::new()
-//             })
-//     }
-
-//     /// Retrieve all hosts on the lattice
-//     #[instrument(level = "debug", skip_all, fields(actor_id = ?_ctx.component, lattice_id = %lattice_id))]
-//     async fn get_hosts(&self, _ctx: Context, lattice_id: String) -> Vec<Host> {
-//         let client = match self.get_connections().await.get_client(&lattice_id).await {
-//             Ok(client) => client,
-//             Err(e) => {
-//                 error!("failed to get client for connection: {e}");
-//                 return Vec::new();
-//             }
-//         };
-
-//         client
-//             .get_hosts()
-//             .await
-//             .map(|v| {
-//                 v.into_iter()
-//                     .map(|h| {
-//                         let lattice_prefix = h.lattice().cloned();
-//                         Host {
-//                             cluster_issuers: h.cluster_issuers,
-//                             ctl_host: h.ctl_host,
-//                             id: h.id,
-//                             js_domain: h.js_domain,
-//                             labels: h.labels,
-//                             lattice_prefix, // NOTE: the control interface type has been updated to just `lattice`, but the wit type is still `lattice_prefix`
-//                             prov_rpc_host: h.rpc_host.clone(),
-//                             rpc_host: h.rpc_host,
-//                             uptime_human: h.uptime_human,
-//                             uptime_seconds: h.uptime_seconds,
-//                             version: h.version,
-//                         }
-//                     })
-//                     .collect::<Vec<_>>()
-//             })
-//             .unwrap_or_else(|e| {
-//                 error!("failed to get hosts: {e}");
-//                 Vec::new()
-//             })
-//     }
-
-//     /// Retrieve inventory for a given host on the lattice
-//     #[instrument(level = "debug", skip_all, fields(actor_id = ?_ctx.component, host = ?arg.host_id, lattice_id = ?arg.lattice_id))]
-//     async fn get_host_inventory(
-//         &self,
-//         _ctx: Context,
-//         arg: GetHostInventoryRequest,
-//     ) -> HostInventory {
-//         let client = match self
-//             .get_connections()
-//             .await
-//             .get_client(&arg.lattice_id)
-//             .await
-//         {
-//             Ok(client) => client,
-//             Err(e) => {
-//                 error!("failed to get client for connection: {e}");
-//                 return HostInventory {
-//                     host_id: String::default(),
-//                     labels: HashMap::new(),
-//                     actors: Vec::new(),
-//                     providers: Vec::new(),
-//                 };
-//             }
-//         };
-
-//         client
-//             .get_host_inventory(&arg.host_id)
-//             .await
-//             .map(|hi| HostInventory {
-//                 actors: hi
-//                     .actors
-//                     .into_iter()
-//                     .map(|ad| ActorDescription {
-//                         id: ad.id,
-//                         image_ref: ad.image_ref,
-//                         instances: ad
-//                             .instances
-//                             .into_iter()
-//                             .map(|ai| ActorInstance {
-//                                 annotations: ai.annotations,
-//                                 instance_id: ai.instance_id,
-//                                 revision: ai.revision,
-//                             })
-//                             .collect::<Vec<_>>(),
-//                         name: ad.name,
-//                     })
-//                     .collect::<Vec<_>>(),
-//                 host_id: hi.host_id,
-//                 labels: hi.labels,
-//                 providers: hi
-//                     .providers
-//                     .into_iter()
-//                     .map(|pd| ProviderDescription {
-//                         annotations: pd.annotations,
-//                         id: pd.id,
-//                         image_ref: pd.image_ref,
-//                         link_name: pd.link_name,
-//                         name: pd.name,
-//                         revision: pd.revision,
-//                     })
-//                     .collect::<Vec<_>>(),
-//             })
-//             .unwrap_or_else(|e| {
-//                 error!("failed to get host inventory : {e}");
-//                 HostInventory {
-//                     host_id: String::default(),
-//                     labels: HashMap::new(),
-//                     actors: Vec::new(),
-//                     providers: Vec::new(),
-//                 }
-//             })
-//     }
-
-//     /// Retrieve claims for a given client
-//     #[instrument(level = "debug", skip_all, fields(actor_id = ?_ctx.component, lattice_id))]
-//     async fn get_claims(&self, _ctx: Context, lattice_id: String) -> GetClaimsResponse {
-//         let client = match self.get_connections().await.get_client(&lattice_id).await {
-//             Ok(client) => client,
-//             Err(e) => {
-//                 error!("failed to get client for connection: {e}");
-//                 return GetClaimsResponse { claims: Vec::new() };
-//             }
-//         };
-
-//         client
-//             .get_claims()
-//             .await
-//             .map(|claims| GetClaimsResponse { claims })
-//             .unwrap_or_else(|e| {
-//                 error!("failed to get claims: {e}");
-//                 GetClaimsResponse { claims: Vec::new() }
-//             })
-//     }
-
-//     /// Start an component on the lattice
-//     #[instrument(level = "debug", skip_all, fields(actor_id = ?_ctx.component, lattice_id = ?cmd.lattice_id))]
-//     async fn start_actor(&self, _ctx: Context, cmd: StartActorCommand) -> CtlOperationAck {
-//         let count = if cmd.count == 0 {
-//             1_u32
-//         } else {
-//             cmd.count.into()
-//         };
-
-//         let client = match self
-//             .get_connections()
-//             .await
-//             .get_client(&cmd.lattice_id)
-//             .await
-//         {
-//             Ok(client) => client,
-//             Err(e) => {
-//                 error!("failed to get client for connection: {e}");
-//                 return CtlOperationAck {
-//                     accepted: false,
-//                     error: "failed to get client for connection".into(),
-//                 };
-//             }
-//         };
-
-//         client
-//             .scale_actor(
-//                 &cmd.host_id,
-//                 &cmd.actor_ref,
-//                 count,
-//                 Some(cmd.annotations.clone()),
-//             )
-//             .await
-//             .map(|ack| CtlOperationAck {
-//                 accepted: ack.accepted,
-//                 error: ack.error,
-//             })
-//             .unwrap_or_else(|e| {
-//                 error!("failed to start component: {e}");
-//                 CtlOperationAck {
-//                     accepted: false,
-//                     error: "failed to start component".into(),
-//                 }
-//             })
-//     }
-
-//     /// Scale an component on the lattice
-//     #[instrument(level = "debug", skip_all, fields(actor_id = ?_ctx.component, lattice_id = ?cmd.lattice_id))]
-//     async fn scale_actor(&self, _ctx: Context, cmd: ScaleActorCommand) -> CtlOperationAck {
-//         let client = match self
-//             .get_connections()
-//             .await
-//             .get_client(&cmd.lattice_id)
-//             .await
-//         {
-//             Ok(client) => client,
-//             Err(e) => {
-//                 error!("failed to get client for connection: {e}");
-//                 return CtlOperationAck {
-//                     accepted: false,
-//                     error: "failed to get client for connection".into(),
-//                 };
-//             }
-//         };
-
-//         client
-//             .scale_actor(
-//                 &cmd.host_id,
-//                 &cmd.actor_ref,
-//                 cmd.count,
-//                 Some(cmd.annotations.clone()),
-//             )
-//             .await
-//             .map(|a| CtlOperationAck {
-//                 accepted: a.accepted,
-//                 error: a.error,
-//             })
-//             .unwrap_or_else(|e| {
-//                 error!("failed to scale component: {e}");
-//                 CtlOperationAck {
-//                     accepted: false,
-//                     error: "failed to scale component".into(),
-//                 }
-//             })
-//     }
-
-//     /// Advertise a link on the lattice
-//     #[instrument(level = "debug", skip_all, fields(actor_id = ?_ctx.component, lattice_id = ?req.lattice_id))]
-//     async fn advertise_link(&self, _ctx: Context, req: AdvertiseLinkRequest) -> CtlOperationAck {
-//         let client = match self
-//             .get_connections()
-//             .await
-//             .get_client(&req.lattice_id)
-//             .await
-//         {
-//             Ok(client) => client,
-//             Err(e) => {
-//                 error!("failed to get client for connection: {e}");
-//                 return CtlOperationAck {
-//                     accepted: false,
-//                     error: "failed to get client for connection".into(),
-//                 };
-//             }
-//         };
-
-//         client
-//             .put_link(
-//                 &req.link.actor_id,
-//                 &req.link.provider_id,
-//                 &req.link.contract_id,
-//                 &req.link.link_name,
-//                 req.link.values.clone().unwrap_or_default(),
-//             )
-//             .await
-//             .map(|ack| CtlOperationAck {
-//                 accepted: ack.accepted,
-//                 error: ack.error,
-//             })
-//             .unwrap_or_else(|e| {
-//                 error!("failed to advertise link: {e}");
-//                 CtlOperationAck {
-//                     accepted: false,
-//                     error: "failed to advertise link".into(),
-//                 }
-//             })
-//     }
-
-//     /// Remove a link on the lattice
-//     #[instrument(level = "debug", skip_all, fields(actor_id = ?_ctx.component, lattice_id = ?req.lattice_id))]
-//     async fn remove_link(
-//         &self,
-//         _ctx: Context,
-//         req: RemoveLinkDefinitionRequest,
-//     ) -> CtlOperationAck {
-//         let client = match self
-//             .get_connections()
-//             .await
-//             .get_client(&req.lattice_id)
-//             .await
-//         {
-//             Ok(client) => client,
-//             Err(e) => {
-//                 error!("failed to get client for connection: {e}");
-//                 return CtlOperationAck {
-//                     accepted: false,
-//                     error: "failed to start component".into(),
-//                 };
-//             }
-//         };
-
-//         client
-//             .remove_link(&req.actor_id, &req.actor_id, &req.link_name)
-//             .await
-//             .map(|a| CtlOperationAck {
-//                 accepted: a.accepted,
-//                 error: a.error,
-//             })
-//             .unwrap_or_else(|e| {
-//                 error!("failed to remove link: {e}");
-//                 CtlOperationAck {
-//                     accepted: false,
-//                     error: "failed to remove link".into(),
-//                 }
-//             })
-//     }
-
-//     /// Retrieve links on the lattice
-//     #[instrument(level = "debug", skip_all, fields(actor_id = ?_ctx.component, lattice_id))]
-//     async fn get_links(&self, _ctx: Context, lattice_id: String) -> Vec<LinkDefinition> {
-//         let client = match self.get_connections().await.get_client(&lattice_id).await {
-//             Ok(client) => client,
-//             Err(e) => {
-//                 error!("failed to get client for connection: {e}");
-//                 return Vec::new();
-//             }
-//         };
-
-//         client
-//             .query_links()
-//             .await
-//             .map(|links| {
-//                 links
-//                     .into_iter()
-//                     .map(|client_ld| LinkDefinition {
-//                         actor_id: client_ld.actor_id,
-//                         provider_id: client_ld.provider_id,
-//                         contract_id: client_ld.contract_id,
-//                         link_name: client_ld.link_name,
-//                         values: Some(client_ld.values),
-//                     })
-//                     .collect()
-//             })
-//             .unwrap_or_else(|e| {
-//                 error!("failed to get links: {e}");
-//                 Vec::new()
-//             })
-//     }
-
-//     /// Update an component running on the lattice
-//     #[instrument(level = "debug", skip_all, fields(actor_id = ?_ctx.component, lattice_id = ?cmd.lattice_id))]
-//     async fn update_actor(&self, _ctx: Context, cmd: UpdateActorCommand) -> CtlOperationAck {
-//         let client = match self
-//             .get_connections()
-//             .await
-//             .get_client(&cmd.lattice_id)
-//             .await
-//         {
-//             Ok(client) => client,
-//             Err(e) => {
-//                 error!("failed to get client for connection: {e}");
-//                 return CtlOperationAck {
-//                     accepted: false,
-//                     error: "failed to get client for connection".into(),
-//                 };
-//             }
-//         };
-
-//         client
-//             .update_actor(
-//                 &cmd.host_id,
-//                 &cmd.actor_id,
-//                 &cmd.new_actor_ref,
-//                 cmd.annotations.clone(),
-//             )
-//             .await
-//             .map(|a| CtlOperationAck {
-//                 accepted: a.accepted,
-//                 error: a.error,
-//             })
-//             .unwrap_or_else(|e| {
-//                 error!("failed to update component: {e}");
-//                 CtlOperationAck {
-//                     accepted: false,
-//                     error: "failed to update component".into(),
-//                 }
-//             })
-//     }
-
-//     /// Start a provider on the lattice
-//     #[instrument(
-//         level = "debug",
-//         skip_all,
-//         fields(
-//             actor_id = ?_ctx.component,
-//             host_id = %cmd.host_id,
-//             provider_ref = %cmd.provider_ref,
-//             link_name = %cmd.link_name,
-//             lattice_id = ?cmd.lattice_id
-//         )
-//     )]
-//     async fn start_provider(&self, _ctx: Context, cmd: StartProviderCommand) -> CtlOperationAck {
-//         let client = match self
-//             .get_connections()
-//             .await
-//             .get_client(&cmd.lattice_id)
-//             .await
-//         {
-//             Ok(client) => client,
-//             Err(e) => {
-//                 error!("failed to get client for connection: {e}");
-//                 return CtlOperationAck {
-//                     accepted: false,
-//                     error: "failed to start component".into(),
-//                 };
-//             }
-//         };
-
-//         client
-//             .start_provider(
-//                 &cmd.host_id,
-//                 &cmd.provider_ref,
-//                 Some(cmd.link_name.to_owned()),
-//                 Some(cmd.annotations.clone()),
-//                 Some(cmd.configuration.clone()),
-//             )
-//             .await
-//             .map(|a| CtlOperationAck {
-//                 accepted: a.accepted,
-//                 error: a.error,
-//             })
-//             .unwrap_or_else(|e| {
-//                 error!("failed to start provider: {e}");
-//                 CtlOperationAck {
-//                     accepted: false,
-//                     error: "failed to start provider".into(),
-//                 }
-//             })
-//     }
-
-//     /// Stop a provider on the lattice
-//     #[instrument(level = "debug", skip_all, fields(actor_id = ?_ctx.component, lattice_id = ?cmd.lattice_id))]
-//     async fn stop_provider(&self, _ctx: Context, cmd: StopProviderCommand) -> CtlOperationAck {
-//         let client = match self
-//             .get_connections()
-//             .await
-//             .get_client(&cmd.lattice_id)
-//             .await
-//         {
-//             Ok(client) => client,
-//             Err(e) => {
-//                 error!("failed to get client for connection: {e}");
-//                 return CtlOperationAck {
-//                     accepted: false,
-//                     error: "failed to get client for connection".into(),
-//                 };
-//             }
-//         };
-
-//         client
-//             .stop_provider(
-//                 &cmd.host_id,
-//                 &cmd.provider_id,
-//                 &cmd.link_name,
-//                 &cmd.contract_id,
-//                 Some(cmd.annotations.clone()),
-//             )
-//             .await
-//             .map(|a| CtlOperationAck {
-//                 accepted: a.accepted,
-//                 error: a.error,
-//             })
-//             .unwrap_or_else(|e| {
-//                 error!("failed to stop provider: {e}");
-//                 CtlOperationAck {
-//                     accepted: false,
-//                     error: "failed to stop provider".into(),
-//                 }
-//             })
-//     }
-
-//     /// Stop a host on the lattice
-//     #[instrument(level = "debug", skip_all, fields(actor_id = ?_ctx.component, lattice_id = ?cmd.lattice_id))]
-//     async fn stop_host(&self, _ctx: Context, cmd: StopHostCommand) -> CtlOperationAck {
-//         let client = match self
-//             .get_connections()
-//             .await
-//             .get_client(&cmd.lattice_id)
-//             .await
-//         {
-//             Ok(client) => client,
-//             Err(e) => {
-//                 error!("failed to get client for connection: {e}");
-//                 return CtlOperationAck {
-//                     accepted: false,
-//                     error: "failed to start component".into(),
-//                 };
-//             }
-//         };
-
-//         client
-//             .stop_host(&cmd.host_id, cmd.timeout)
-//             .await
-//             .map(|a| CtlOperationAck {
-//                 accepted: a.accepted,
-//                 error: a.error,
-//             })
-//             .unwrap_or_else(|e| {
-//                 error!("failed to stop host: {e}");
-//                 CtlOperationAck {
-//                     accepted: false,
-//                     error: "failed to stop host".into(),
-//                 }
-//             })
-//     }
-// }
+//! wasmCloud Lattice Control capability provider
+//!
+//! Allows components to manage a lattice using the `wasmcloud:lattice-control` contract, which
+//! mirrors the [`wasmcloud_control_interface::Client`]. The lattice and NATS credentials used are
+//! configured on the link from each component to this provider.
+
+use std::collections::{BTreeMap, HashMap};
+
+use anyhow::{bail, Context as _};
+use tracing::{debug, error, instrument, warn};
+use wasmcloud_control_interface::CtlResponse;
+use wasmcloud_provider_sdk::core::secrets::SecretValue;
+use wasmcloud_provider_sdk::{
+    get_connection, initialize_observability, load_host_data, propagate_trace_for_ctx,
+    run_provider, serve_provider_exports, Context, LinkConfig, LinkDeleteInfo, Provider,
+};
+
+mod client_cache;
+use client_cache::ClientCache;
+
+mod bindings {
+    wit_bindgen_wrpc::generate!({
+        with: {
+            "wasmcloud:lattice-control/lattice-controller@0.1.0-draft": generate,
+            "wasmcloud:lattice-control/types@0.1.0-draft": generate,
+        },
+    });
+}
+use bindings::wasmcloud::lattice_control::types::{
+    ComponentDescription, Host, HostInventory, Link, ProviderDescription,
+};
+
+const DEFAULT_NATS_URI: &str = "0.0.0.0:4222";
+const DEFAULT_LATTICE: &str = "default";
+const DEFAULT_TIMEOUT_MS: u64 = 2000;
+
+// NOTE: Exercise caution when adjusting this value, as the *entire* auction duration is awaited
+// for operations like `get-hosts`, which may cause invocations of this provider to time out
+const DEFAULT_AUCTION_TIMEOUT_MS: u64 = 3000;
+
+/// Clients unused for this long are disconnected, and reconnected on next use
+const CLIENT_CACHE_TIMEOUT_SECS: u64 = 30 * 60;
+
+const CONFIG_LATTICE: &str = "lattice";
+const CONFIG_NATS_URI: &str = "cluster_uris";
+const CONFIG_NATS_CLIENT_JWT: &str = "client_jwt";
+const CONFIG_NATS_CLIENT_SEED: &str = "client_seed";
+const CONFIG_TOPIC_PREFIX: &str = "topic_prefix";
+const CONFIG_TIMEOUT_MS: &str = "timeout_ms";
+const CONFIG_AUCTION_TIMEOUT_MS: &str = "auction_timeout_ms";
+
+pub async fn run() -> anyhow::Result<()> {
+    LatticeControllerProvider::run().await
+}
+
+/// Configuration for connecting a control interface client to a lattice
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct ConnectionConfig {
+    /// URIs used to connect to the cluster
+    cluster_uris: Vec<String>,
+
+    /// Authentication JWT
+    auth_jwt: Option<String>,
+
+    /// Authentication Seed
+    auth_seed: Option<String>,
+
+    /// Name of the lattice
+    lattice: String,
+
+    /// Prefix of the control interface topics, if not the default
+    topic_prefix: Option<String>,
+
+    /// Operation timeout used for the lattice client interface
+    timeout_ms: u64,
+
+    /// Auction timeout used for the lattice client interface
+    auction_timeout_ms: u64,
+}
+
+impl Default for ConnectionConfig {
+    fn default() -> Self {
+        Self {
+            cluster_uris: vec![DEFAULT_NATS_URI.to_owned()],
+            auth_jwt: None,
+            auth_seed: None,
+            lattice: String::from(DEFAULT_LATTICE),
+            topic_prefix: None,
+            timeout_ms: DEFAULT_TIMEOUT_MS,
+            auction_timeout_ms: DEFAULT_AUCTION_TIMEOUT_MS,
+        }
+    }
+}
+
+impl ConnectionConfig {
+    /// Construct configuration from the configuration and secrets on a link, preferring secrets
+    /// for the NATS credentials
+    fn from_link_config(
+        LinkConfig {
+            config, secrets, ..
+        }: &LinkConfig,
+    ) -> anyhow::Result<Self> {
+        Self::from_values(config, secrets)
+    }
+
+    fn from_values(
+        config: &HashMap<String, String>,
+        secrets: &HashMap<String, SecretValue>,
+    ) -> anyhow::Result<Self> {
+        let mut cfg = ConnectionConfig::default();
+        if let Some(lattice) = config.get(CONFIG_LATTICE) {
+            cfg.lattice.clone_from(lattice);
+        }
+        if let Some(uris) = config.get(CONFIG_NATS_URI) {
+            cfg.cluster_uris = uris.split(',').map(String::from).collect();
+        }
+        cfg.auth_jwt = secret_or_config(secrets, config, CONFIG_NATS_CLIENT_JWT);
+        cfg.auth_seed = secret_or_config(secrets, config, CONFIG_NATS_CLIENT_SEED);
+        cfg.topic_prefix = config.get(CONFIG_TOPIC_PREFIX).cloned();
+        if let Some(timeout_ms) = config.get(CONFIG_TIMEOUT_MS) {
+            cfg.timeout_ms = timeout_ms
+                .parse()
+                .with_context(|| format!("invalid [{CONFIG_TIMEOUT_MS}]"))?;
+        }
+        if let Some(auction_timeout_ms) = config.get(CONFIG_AUCTION_TIMEOUT_MS) {
+            cfg.auction_timeout_ms = auction_timeout_ms
+                .parse()
+                .with_context(|| format!("invalid [{CONFIG_AUCTION_TIMEOUT_MS}]"))?;
+        }
+        if cfg.auth_jwt.is_some() != cfg.auth_seed.is_some() {
+            bail!("must provide both [{CONFIG_NATS_CLIENT_JWT}] and [{CONFIG_NATS_CLIENT_SEED}] for JWT authentication");
+        }
+        Ok(cfg)
+    }
+}
+
+fn secret_or_config(
+    secrets: &HashMap<String, SecretValue>,
+    config: &HashMap<String, String>,
+    key: &str,
+) -> Option<String> {
+    secrets
+        .get(key)
+        .and_then(SecretValue::as_string)
+        .or_else(|| {
+            let value = config.get(key).map(String::as_str);
+            if value.is_some() {
+                warn!("secret value [{key}] was not found in secrets, but in config. Prefer using secrets for sensitive values.");
+            }
+            value
+        })
+        .map(String::from)
+}
+
+/// lattice-controller capability provider implementation
+#[derive(Clone)]
+pub struct LatticeControllerProvider {
+    /// Control interface clients by the ID of the linked component
+    clients: ClientCache,
+}
+
+impl LatticeControllerProvider {
+    pub async fn run() -> anyhow::Result<()> {
+        initialize_observability!(
+            "lattice-controller-provider",
+            std::env::var_os("PROVIDER_LATTICE_CONTROLLER_FLAMEGRAPH_PATH")
+        );
+
+        let host_data = load_host_data().context("failed to load host data")?;
+        if host_data
+            .default_rpc_timeout_ms
+            .is_some_and(|v| v < DEFAULT_AUCTION_TIMEOUT_MS)
+        {
+            warn!(
+                host_rpc_timeout_ms = host_data.default_rpc_timeout_ms,
+                auction_timeout_ms = DEFAULT_AUCTION_TIMEOUT_MS,
+                "host default RPC timeout < auction timeout, operations that rely on auctions are likely to time out"
+            );
+        }
+        let provider = Self {
+            clients: ClientCache::new(CLIENT_CACHE_TIMEOUT_SECS),
+        };
+        let shutdown = run_provider(provider.clone(), "lattice-controller-provider")
+            .await
+            .context("failed to run provider")?;
+        let connection = get_connection();
+        let wrpc = connection
+            .get_wrpc_client(connection.provider_key())
+            .await?;
+        serve_provider_exports(&wrpc, provider, shutdown, bindings::serve)
+            .await
+            .context("failed to serve provider exports")
+    }
+
+    /// Get the client for the component invoking the provider, failing with the error returned
+    /// to the component if it is not linked or the lattice cannot be reached
+    async fn client(
+        &self,
+        ctx: Option<Context>,
+    ) -> Result<wasmcloud_control_interface::Client, String> {
+        let Some(component_id) = ctx.and_then(|Context { component, .. }| component) else {
+            error!("no component in request");
+            return Err("no component in request".to_string());
+        };
+        self.clients
+            .get_client(&component_id)
+            .await
+            .map_err(|e| format!("{e:#}"))
+    }
+}
+
+impl Provider for LatticeControllerProvider {
+    /// Store the lattice and credentials configured on the link, used to connect to the lattice
+    /// on the first invocation by the component
+    #[instrument(level = "debug", skip_all, fields(source_id = %link_config.source_id))]
+    async fn receive_link_config_as_target(
+        &self,
+        link_config: LinkConfig<'_>,
+    ) -> anyhow::Result<()> {
+        let config = ConnectionConfig::from_link_config(&link_config)
+            .context("failed to build connection configuration")?;
+        debug!(
+            lattice = config.lattice,
+            "storing lattice connection configuration"
+        );
+        self.clients.put_config(link_config.source_id, config).await;
+        Ok(())
+    }
+
+    /// Disconnect from the lattice configured on the link
+    #[instrument(level = "debug", skip_all, fields(source_id = %info.get_source_id()))]
+    async fn delete_link_as_target(&self, info: impl LinkDeleteInfo) -> anyhow::Result<()> {
+        self.clients.remove_config(info.get_source_id()).await;
+        Ok(())
+    }
+
+    /// Handle shutdown request by disconnecting from all lattices
+    async fn shutdown(&self) -> anyhow::Result<()> {
+        self.clients.clear().await;
+        Ok(())
+    }
+}
+
+/// Convert a response of the control interface into the result returned to the component,
+/// failing if the host did not succeed
+fn into_result<T>(
+    res: Result<CtlResponse<T>, Box<dyn std::error::Error + Send + Sync>>,
+) -> Result<Option<T>, String> {
+    let res = res.map_err(|e| e.to_string())?;
+    if res.succeeded() {
+        Ok(res.into_data())
+    } else {
+        Err(res.message().to_string())
+    }
+}
+
+fn into_pairs<'a>(
+    map: impl IntoIterator<Item = (&'a String, &'a String)>,
+) -> Vec<(String, String)> {
+    map.into_iter()
+        .map(|(k, v)| (k.clone(), v.clone()))
+        .collect()
+}
+
+/// Annotations are omitted, rather than set to an empty map, if there are none
+fn into_annotations(annotations: Vec<(String, String)>) -> Option<BTreeMap<String, String>> {
+    (!annotations.is_empty()).then(|| annotations.into_iter().collect())
+}
+
+impl From<wasmcloud_control_interface::Host> for Host {
+    fn from(host: wasmcloud_control_interface::Host) -> Self {
+        Self {
+            id: host.id().to_string(),
+            friendly_name: host.friendly_name().to_string(),
+            labels: into_pairs(host.labels()),
+            lattice: host.lattice().to_string(),
+            version: host.version().map(String::from),
+            uptime_seconds: host.uptime_seconds(),
+            js_domain: host.js_domain().map(String::from),
+        }
+    }
+}
+
+impl From<&wasmcloud_control_interface::ComponentDescription> for ComponentDescription {
+    fn from(component: &wasmcloud_control_interface::ComponentDescription) -> Self {
+        Self {
+            id: component.id().to_string(),
+            image_ref: component.image_ref().to_string(),
+            name: component.name().map(String::from),
+            annotations: component.annotations().map(into_pairs).unwrap_or_default(),
+            revision: component.revision(),
+            max_instances: component.max_instances(),
+        }
+    }
+}
+
+impl From<&wasmcloud_control_interface::ProviderDescription> for ProviderDescription {
+    fn from(provider: &wasmcloud_control_interface::ProviderDescription) -> Self {
+        Self {
+            id: provider.id().to_string(),
+            image_ref: provider.image_ref().map(String::from),
+            name: provider.name().map(String::from),
+            annotations: provider.annotations().map(into_pairs).unwrap_or_default(),
+            revision: provider.revision(),
+        }
+    }
+}
+
+impl From<wasmcloud_control_interface::HostInventory> for HostInventory {
+    fn from(inventory: wasmcloud_control_interface::HostInventory) -> Self {
+        Self {
+            host_id: inventory.host_id().to_string(),
+            friendly_name: inventory.friendly_name().to_string(),
+            labels: into_pairs(inventory.labels()),
+            version: inventory.version().to_string(),
+            uptime_seconds: inventory.uptime_seconds(),
+            components: inventory.components().iter().map(Into::into).collect(),
+            providers: inventory.providers().iter().map(Into::into).collect(),
+        }
+    }
+}
+
+impl From<wasmcloud_control_interface::Link> for Link {
+    fn from(link: wasmcloud_control_interface::Link) -> Self {
+        Self {
+            source_id: link.source_id().to_string(),
+            target: link.target().to_string(),
+            name: link.name().to_string(),
+            wit_namespace: link.wit_namespace().to_string(),
+            wit_package: link.wit_package().to_string(),
+            interfaces: link.interfaces().clone(),
+            source_config: link.source_config().clone(),
+            target_config: link.target_config().clone(),
+        }
+    }
+}
+
+impl TryFrom<Link> for wasmcloud_control_interface::Link {
+    type Error = String;
+
+    fn try_from(link: Link) -> Result<Self, Self::Error> {
+        wasmcloud_control_interface::Link::builder()
+            .source_id(&link.source_id)
+            .target(&link.target)
+            .name(&link.name)
+            .wit_namespace(&link.wit_namespace)
+            .wit_package(&link.wit_package)
+            .interfaces(link.interfaces)
+            .source_config(link.source_config)
+            .target_config(link.target_config)
+            .build()
+            .map_err(|e| e.to_string())
+    }
+}
+
+/// Values of named configuration
+type ConfigValues = Vec<(String, String)>;
+
+/// Implement the 'wasmcloud:lattice-control' capability provider interface
+impl bindings::exports::wasmcloud::lattice_control::lattice_controller::Handler<Option<Context>>
+    for LatticeControllerProvider
+{
+    #[instrument(level = "debug", skip_all)]
+    async fn get_hosts(&self, ctx: Option<Context>) -> anyhow::Result<Result<Vec<Host>, String>> {
+        propagate_trace_for_ctx!(ctx);
+        let client = match self.client(ctx).await {
+            Ok(client) => client,
+            Err(err) => return Ok(Err(err)),
+        };
+        Ok(client
+            .get_hosts()
+            .await
+            .map(|hosts| {
+                hosts
+                    .into_iter()
+                    .filter_map(CtlResponse::into_data)
+                    .map(Into::into)
+                    .collect()
+            })
+            .map_err(|e| e.to_string()))
+    }
+
+    #[instrument(level = "debug", skip(self, ctx))]
+    async fn get_host_inventory(
+        &self,
+        ctx: Option<Context>,
+        host_id: String,
+    ) -> anyhow::Result<Result<HostInventory, String>> {
+        propagate_trace_for_ctx!(ctx);
+        let client = match self.client(ctx).await {
+            Ok(client) => client,
+            Err(err) => return Ok(Err(err)),
+        };
+        Ok(
+            into_result(client.get_host_inventory(&host_id).await).and_then(|inventory| {
+                inventory
+                    .map(Into::into)
+                    .ok_or_else(|| format!("host [{host_id}] did not return its inventory"))
+            }),
+        )
+    }
+
+    #[instrument(level = "debug", skip(self, ctx, annotations, config))]
+    async fn scale_component(
+        &self,
+        ctx: Option<Context>,
+        host_id: String,
+        component_ref: String,
+        component_id: String,
+        max_instances: u32,
+        annotations: Vec<(String, String)>,
+        config: Vec<String>,
+    ) -> anyhow::Result<Result<(), String>> {
+        propagate_trace_for_ctx!(ctx);
+        let client = match self.client(ctx).await {
+            Ok(client) => client,
+            Err(err) => return Ok(Err(err)),
+        };
+        Ok(into_result(
+            client
+                .scale_component(
+                    &host_id,
+                    &component_ref,
+                    &component_id,
+                    max_instances,
+                    into_annotations(annotations),
+                    config,
+                )
+                .await,
+        )
+        .map(|_| ()))
+    }
+
+    #[instrument(level = "debug", skip(self, ctx, annotations, config))]
+    async fn start_provider(
+        &self,
+        ctx: Option<Context>,
+        host_id: String,
+        provider_ref: String,
+        provider_id: String,
+        annotations: Vec<(String, String)>,
+        config: Vec<String>,
+    ) -> anyhow::Result<Result<(), String>> {
+        propagate_trace_for_ctx!(ctx);
+        let client = match self.client(ctx).await {
+            Ok(client) => client,
+            Err(err) => return Ok(Err(err)),
+        };
+        Ok(into_result(
+            client
+                .start_provider(
+                    &host_id,
+                    &provider_ref,
+                    &provider_id,
+                    into_annotations(annotations),
+                    config,
+                )
+                .await,
+        )
+        .map(|_| ()))
+    }
+
+    #[instrument(level = "debug", skip(self, ctx))]
+    async fn stop_provider(
+        &self,
+        ctx: Option<Context>,
+        host_id: String,
+        provider_id: String,
+    ) -> anyhow::Result<Result<(), String>> {
+        propagate_trace_for_ctx!(ctx);
+        let client = match self.client(ctx).await {
+            Ok(client) => client,
+            Err(err) => return Ok(Err(err)),
+        };
+        Ok(into_result(client.stop_provider(&host_id, &provider_id).await).map(|_| ()))
+    }
+
+    #[instrument(level = "debug", skip_all)]
+    async fn get_links(&self, ctx: Option<Context>) -> anyhow::Result<Result<Vec<Link>, String>> {
+        propagate_trace_for_ctx!(ctx);
+        let client = match self.client(ctx).await {
+            Ok(client) => client,
+            Err(err) => return Ok(Err(err)),
+        };
+        Ok(into_result(client.get_links().await)
+            .map(|links| links.into_iter().flatten().map(Into::into).collect()))
+    }
+
+    #[instrument(level = "debug", skip_all, fields(source_id = %link.source_id, target = %link.target))]
+    async fn put_link(
+        &self,
+        ctx: Option<Context>,
+        link: Link,
+    ) -> anyhow::Result<Result<(), String>> {
+        propagate_trace_for_ctx!(ctx);
+        let client = match self.client(ctx).await {
+            Ok(client) => client,
+            Err(err) => return Ok(Err(err)),
+        };
+        let link = match wasmcloud_control_interface::Link::try_from(link) {
+            Ok(link) => link,
+            Err(err) => return Ok(Err(err)),
+        };
+        Ok(into_result(client.put_link(link).await).map(|_| ()))
+    }
+
+    #[instrument(level = "debug", skip(self, ctx))]
+    async fn delete_link(
+        &self,
+        ctx: Option<Context>,
+        source_id: String,
+        name: String,
+        wit_namespace: String,
+        wit_package: String,
+    ) -> anyhow::Result<Result<(), String>> {
+        propagate_trace_for_ctx!(ctx);
+        let client = match self.client(ctx).await {
+            Ok(client) => client,
+            Err(err) => return Ok(Err(err)),
+        };
+        Ok(into_result(
+            client
+                .delete_link(&source_id, &name, &wit_namespace, &wit_package)
+                .await,
+        )
+        .map(|_| ()))
+    }
+
+    #[instrument(level = "debug", skip(self, ctx))]
+    async fn get_config(
+        &self,
+        ctx: Option<Context>,
+        name: String,
+    ) -> anyhow::Result<Result<Option<ConfigValues>, String>> {
+        propagate_trace_for_ctx!(ctx);
+        let client = match self.client(ctx).await {
+            Ok(client) => client,
+            Err(err) => return Ok(Err(err)),
+        };
+        Ok(into_result(client.get_config(&name).await)
+            .map(|config| config.map(|config| config.into_iter().collect())))
+    }
+
+    #[instrument(level = "debug", skip(self, ctx, values))]
+    async fn put_config(
+        &self,
+        ctx: Option<Context>,
+        name: String,
+        values: Vec<(String, String)>,
+    ) -> anyhow::Result<Result<(), String>> {
+        propagate_trace_for_ctx!(ctx);
+        let client = match self.client(ctx).await {
+            Ok(client) => client,
+            Err(err) => return Ok(Err(err)),
+        };
+        Ok(into_result(
+            client
+                .put_config(&name, values.into_iter().collect::<HashMap<_, _>>())
+                .await,
+        )
+        .map(|_| ()))
+    }
+
+    #[instrument(level = "debug", skip(self, ctx))]
+    async fn delete_config(
+        &self,
+        ctx: Option<Context>,
+        name: String,
+    ) -> anyhow::Result<Result<(), String>> {
+        propagate_trace_for_ctx!(ctx);
+        let client = match self.client(ctx).await {
+            Ok(client) => client,
+            Err(err) => return Ok(Err(err)),
+        };
+        Ok(into_result(client.delete_config(&name).await).map(|_| ()))
+    }
+
+    #[instrument(level = "debug", skip(self, ctx, value))]
+    async fn put_label(
+        &self,
+        ctx: Option<Context>,
+        host_id: String,
+        key: String,
+        value: String,
+    ) -> anyhow::Result<Result<(), String>> {
+        propagate_trace_for_ctx!(ctx);
+        let client = match self.client(ctx).await {
+            Ok(client) => client,
+            Err(err) => return Ok(Err(err)),
+        };
+        Ok(into_result(client.put_label(&host_id, &key, &value).await).map(|_| ()))
+    }
+
+    #[instrument(level = "debug", skip(self, ctx))]
+    async fn delete_label(
+        &self,
+        ctx: Option<Context>,
+        host_id: String,
+        key: String,
+    ) -> anyhow::Result<Result<(), String>> {
+        propagate_trace_for_ctx!(ctx);
+        let client = match self.client(ctx).await {
+            Ok(client) => client,
+            Err(err) => return Ok(Err(err)),
+        };
+        Ok(into_result(client.delete_label(&host_id, &key).await).map(|_| ()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use wasmcloud_provider_sdk::core::secrets::SecretValue;
+    use wasmcloud_provider_sdk::Context;
+
+    use super::{ClientCache, ConnectionConfig, LatticeControllerProvider};
+
+    #[test]
+    fn connection_config_from_link() {
+        let config = HashMap::from([
+            ("lattice".to_string(), "ops".to_string()),
+            (
+                "cluster_uris".to_string(),
+                "nats://one:4222,nats://two:4222".to_string(),
+            ),
+            ("client_jwt".to_string(), "jwt".to_string()),
+            ("timeout_ms".to_string(), "500".to_string()),
+        ]);
+        let secrets = HashMap::from([(
+            "client_seed".to_string(),
+            SecretValue::String("seed".to_string()),
+        )]);
+        let cfg = ConnectionConfig::from_values(&config, &secrets).expect("failed to parse config");
+        assert_eq!(cfg.lattice, "ops");
+        assert_eq!(cfg.cluster_uris, ["nats://one:4222", "nats://two:4222"]);
+        assert_eq!(cfg.auth_jwt.as_deref(), Some("jwt"));
+        assert_eq!(cfg.auth_seed.as_deref(), Some("seed"));
+        assert_eq!(cfg.timeout_ms, 500);
+        assert_eq!(cfg.auction_timeout_ms, super::DEFAULT_AUCTION_TIMEOUT_MS);
+
+        let config = HashMap::from([("client_jwt".to_string(), "jwt".to_string())]);
+        assert!(ConnectionConfig::from_values(&config, &HashMap::new()).is_err());
+    }
+
+    #[tokio::test]
+    async fn client_fails_for_unlinked_component() {
+        let provider = LatticeControllerProvider {
+            clients: ClientCache::new(60),
+        };
+        let err = provider
+            .client(None)
+            .await
+            .expect_err("request without component should fail");
+        assert_eq!(err, "no component in request");
+
+        let ctx = Context {
+            component: Some("unlinked".to_string()),
+            ..Default::default()
+        };
+        let err = provider
+            .client(Some(ctx))
+            .await
+            .expect_err("unlinked component should fail");
+        assert!(err.contains("[unlinked]"), "{err}");
+    }
+}
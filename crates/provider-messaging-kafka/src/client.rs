@@ -1,38 +1,63 @@
+use std::time::Duration;
+
 use anyhow::{Context as _, Result};
 use futures::Stream;
-use kafka::client::KafkaClient;
-use kafka::consumer::{Builder as ConsumerBuilder, Consumer, Message};
-use tokio::sync::oneshot::Sender;
+use rdkafka::consumer::{CommitMode, Consumer, StreamConsumer};
+use rdkafka::message::{BorrowedHeaders, BorrowedMessage, Message, OwnedHeaders, ToBytes};
+use rdkafka::producer::{FutureProducer, FutureRecord};
+use rdkafka::{ClientConfig, Offset, TopicPartitionList};
+use tokio::sync::oneshot::{self, Sender};
 use tokio_stream::wrappers::UnboundedReceiverStream;
-use tracing::{error, trace};
+use tracing::{error, trace, warn};
 
-/// An Async Kafka Client built on the [`tokio`] runtime
-pub(crate) struct AsyncKafkaClient(pub(crate) KafkaClient);
+use crate::config::{SecurityConfig, StartOffset};
 
-impl AsyncKafkaClient {
-    /// Build an [`AsyncKafkaClient`] for a list of hosts
-    pub async fn from_hosts(hosts: Vec<String>) -> Result<Self> {
-        Self::from_client(KafkaClient::new(hosts)).await
-    }
+/// Initial delay before redelivering a message that the component failed to handle
+const REDELIVERY_BACKOFF_INITIAL: Duration = Duration::from_millis(500);
 
-    /// Build an [`AsyncKafkaClient`] from an existing [`KafkaClient`]
-    pub async fn from_client(mut kc: KafkaClient) -> Result<Self> {
-        let kc = tokio::task::spawn_blocking(move || {
-            kc.load_metadata_all().context("failed to load metadata")?;
-            Ok::<KafkaClient, anyhow::Error>(kc)
-        })
+/// Maximum delay between redeliveries of a message that the component failed to handle
+const REDELIVERY_BACKOFF_MAX: Duration = Duration::from_secs(30);
+
+/// Time to wait for room in the producer queue when sending a record
+const PRODUCER_QUEUE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Timeout for loading topic metadata and partition offsets from brokers
+const METADATA_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Build the configuration shared by all clients for a list of hosts
+pub(crate) fn client_config(hosts: &[String], security: &SecurityConfig) -> ClientConfig {
+    let mut config = ClientConfig::new();
+    config.set("bootstrap.servers", hosts.join(","));
+    security.apply(&mut config);
+    config
+}
+
+/// Build a [`FutureProducer`] from a client configuration
+pub(crate) fn build_producer(config: &ClientConfig) -> Result<FutureProducer> {
+    config.create().context("failed to build kafka producer")
+}
+
+/// Send a record, waiting until it has been delivered
+pub(crate) async fn send_record<K, P>(
+    producer: &FutureProducer,
+    record: FutureRecord<'_, K, P>,
+) -> Result<()>
+where
+    K: ToBytes + ?Sized,
+    P: ToBytes + ?Sized,
+{
+    producer
+        .send(record, PRODUCER_QUEUE_TIMEOUT)
         .await
-        .context("failed to perform spawn blocking")?
-        .context("failed to load metadata")?;
-        Ok(Self(kc))
-    }
+        .map(|_| ())
+        .map_err(|(e, _)| e.into())
 }
 
-/// An wrapper for easily using a [`kafka::consumer::Consumer`] asynchronously
-pub(crate) struct AsyncKafkaConsumer(Consumer);
+/// An wrapper for easily using a [`StreamConsumer`] asynchronously
+pub(crate) struct AsyncKafkaConsumer(StreamConsumer);
 
 /// A fetched message from a remote Kafka broker for a particular topic & partition.
-#[derive(Debug, Clone)]
+#[derive(Debug)]
 #[allow(dead_code)]
 pub(crate) struct KafkaMessage {
     /// The offset at which this message resides in the remote kafka
@@ -46,76 +71,185 @@ pub(crate) struct KafkaMessage {
     /// The value data of this message.  Empty if there is no such
     /// data for this message.
     pub value: Vec<u8>,
+
+    /// The headers of this message, if any
+    pub headers: Option<OwnedHeaders>,
+
+    /// Acknowledgement of whether this message was handled successfully, present if the
+    /// consumer commits offsets. The message is redelivered until it is acknowledged with `true`.
+    pub ack: Option<Sender<bool>>,
 }
 
-impl<'a> From<&Message<'a>> for KafkaMessage {
-    fn from(Message { offset, key, value }: &Message<'a>) -> Self {
+impl<'a> From<&BorrowedMessage<'a>> for KafkaMessage {
+    fn from(message: &BorrowedMessage<'a>) -> Self {
         Self {
-            offset: *offset,
-            key: Vec::<u8>::from(*key),
-            value: Vec::<u8>::from(*value),
+            offset: message.offset(),
+            key: message.key().map(<[u8]>::to_vec).unwrap_or_default(),
+            value: message.payload().map(<[u8]>::to_vec).unwrap_or_default(),
+            headers: message.headers().map(BorrowedHeaders::detach),
+            ack: None,
         }
     }
 }
 
 impl AsyncKafkaConsumer {
-    /// Build from an [`AsyncKafkaClient`] which is guaranteed to have had metadata loaded at least once (during construction).
-    pub async fn from_async_client(
-        ac: AsyncKafkaClient,
-        builder_fn: impl FnOnce(ConsumerBuilder) -> ConsumerBuilder,
+    /// Build a consumer of `topic` (or only the given `partitions` of it, if any), starting from
+    /// `start_offset`
+    pub fn new(
+        config: &ClientConfig,
+        topic: &str,
+        partitions: &[i32],
+        start_offset: StartOffset,
+        consumer_group: Option<&str>,
     ) -> Result<Self> {
-        let builder = builder_fn(Consumer::from_client(ac.0));
-        let consumer = builder.create().context("failed to create consumer")?;
+        let mut config = config.clone();
+        start_offset.configure(&mut config, consumer_group);
+        let consumer: StreamConsumer = config.create().context("failed to create consumer")?;
+        if partitions.is_empty() {
+            consumer
+                .subscribe(&[topic])
+                .with_context(|| format!("failed to subscribe to topic [{topic}]"))?;
+        } else {
+            let mut assignment = TopicPartitionList::new();
+            for partition in partitions {
+                assignment
+                    .add_partition_offset(topic, *partition, Offset::Stored)
+                    .with_context(|| {
+                        format!("failed to assign partition [{partition}] of topic [{topic}]")
+                    })?;
+            }
+            consumer
+                .assign(&assignment)
+                .with_context(|| format!("failed to assign partitions of topic [{topic}]"))?;
+        }
         Ok(Self(consumer))
     }
 
+    /// Build a consumer of all partitions of `topic`, starting after the latest message
+    /// currently in each of them
+    ///
+    /// Unlike [`StartOffset::Latest`], the offsets are resolved before this returns, so messages
+    /// produced afterwards are never missed.
+    pub async fn from_latest(config: &ClientConfig, topic: &str) -> Result<Self> {
+        let mut config = config.clone();
+        StartOffset::Latest.configure(&mut config, None);
+        let topic = topic.to_string();
+        tokio::task::spawn_blocking(move || {
+            let consumer: StreamConsumer = config.create().context("failed to create consumer")?;
+            let metadata = consumer
+                .fetch_metadata(Some(&topic), METADATA_TIMEOUT)
+                .with_context(|| format!("failed to load metadata for topic [{topic}]"))?;
+            let partitions = metadata
+                .topics()
+                .iter()
+                .find(|t| t.name() == topic)
+                .filter(|t| t.error().is_none())
+                .map(|t| t.partitions())
+                .unwrap_or_default();
+            if partitions.is_empty() {
+                anyhow::bail!("topic [{topic}] does not exist");
+            }
+            let mut assignment = TopicPartitionList::new();
+            for partition in partitions {
+                let (_, high) = consumer
+                    .fetch_watermarks(&topic, partition.id(), METADATA_TIMEOUT)
+                    .with_context(|| {
+                        format!(
+                            "failed to load offsets of partition [{}] of topic [{topic}]",
+                            partition.id()
+                        )
+                    })?;
+                assignment
+                    .add_partition_offset(&topic, partition.id(), Offset::Offset(high))
+                    .context("failed to add partition to assignment")?;
+            }
+            consumer
+                .assign(&assignment)
+                .with_context(|| format!("failed to assign partitions of topic [{topic}]"))?;
+            Ok(Self(consumer))
+        })
+        .await
+        .context("failed to perform spawn blocking")?
+    }
+
     /// Produce an unending stream of messages based on the inner consumer, with a mechanism for stopping
-    pub async fn messages(self) -> Result<(impl Stream<Item = KafkaMessage>, Sender<()>)> {
-        let mut consumer = self.0;
+    ///
+    /// If `commit` is set, messages are delivered one at a time, each carrying an
+    /// [`KafkaMessage::ack`]. A message's offset is only committed once it is acknowledged as
+    /// successfully handled, and it is redelivered (with backoff) until then.
+    pub async fn messages(
+        self,
+        commit: bool,
+    ) -> Result<(impl Stream<Item = KafkaMessage>, Sender<()>)> {
+        let consumer = self.0;
         let (stop_tx, mut stop_rx) = tokio::sync::oneshot::channel::<()>();
         let (msg_tx, msg_rx) = tokio::sync::mpsc::unbounded_channel();
 
         // Listen forever for new messages with the consumer
-        tokio::task::spawn_blocking(move || {
+        tokio::spawn(async move {
             loop {
-                match consumer.poll() {
-                    // If we received message sets, process them
-                    Ok(message_sets) => {
-                        for message_set in message_sets.iter() {
-                            for message in message_set.messages() {
-                                trace!(
-                                    topic = message_set.topic(),
-                                    partition = message_set.partition(),
-                                    offset = message.offset,
-                                    "received message",
-                                );
-                                if let Err(e) = msg_tx
-                                    .send(KafkaMessage::from(message))
-                                    .context("failed to send kafka message")
-                                {
-                                    error!("failed to send kafka message: {e}");
-                                }
-                            }
-                            if let Err(e) = consumer.consume_messageset(message_set) {
-                                error!("failed to consume message set: {e}");
-                            }
-                        }
-                        // Commit all consumed stuff, but only if we're in a group
-                        if !consumer.group().is_empty() {
-                            if let Err(e) = consumer.commit_consumed() {
-                                error!("failed to commit consumed messages: {e}");
-                            }
-                        }
+                let message = tokio::select! {
+                    // If we've been told to stop, then we should stop
+                    _ = &mut stop_rx => {
+                        trace!("received stop, shutting down consumer...");
+                        return;
                     }
+                    message = consumer.recv() => message,
+                };
+                let message = match message {
+                    Ok(message) => message,
                     Err(e) => {
-                        error!("failed to poll: {e}");
+                        error!("failed to receive message: {e}");
+                        continue;
                     }
+                };
+                trace!(
+                    topic = message.topic(),
+                    partition = message.partition(),
+                    offset = message.offset(),
+                    "received message",
+                );
+                if !commit {
+                    if msg_tx.send(KafkaMessage::from(&message)).is_err() {
+                        trace!("message receiver dropped, shutting down consumer...");
+                        return;
+                    }
+                    continue;
                 }
 
-                // If we've been told to stop, then we should stop
-                if let Ok(()) = stop_rx.try_recv() {
-                    trace!("received stop, shutting down consuming thread...");
-                    return Ok(()) as Result<()>;
+                // Deliver the message until the component handles it successfully
+                let mut backoff = REDELIVERY_BACKOFF_INITIAL;
+                loop {
+                    let (ack_tx, ack_rx) = oneshot::channel();
+                    let msg = KafkaMessage {
+                        ack: Some(ack_tx),
+                        ..KafkaMessage::from(&message)
+                    };
+                    if msg_tx.send(msg).is_err() {
+                        trace!("message receiver dropped, shutting down consumer...");
+                        return;
+                    }
+                    if let Ok(true) = ack_rx.await {
+                        break;
+                    }
+                    warn!(
+                        topic = message.topic(),
+                        partition = message.partition(),
+                        offset = message.offset(),
+                        ?backoff,
+                        "message was not handled successfully, redelivering",
+                    );
+                    tokio::select! {
+                        _ = &mut stop_rx => {
+                            trace!("received stop, shutting down consumer...");
+                            return;
+                        }
+                        () = tokio::time::sleep(backoff) => {}
+                    }
+                    backoff = (backoff * 2).min(REDELIVERY_BACKOFF_MAX);
+                }
+                if let Err(e) = consumer.commit_message(&message, CommitMode::Async) {
+                    error!("failed to commit consumed message: {e}");
                 }
             }
         });
@@ -123,3 +257,123 @@ impl AsyncKafkaConsumer {
         Ok((UnboundedReceiverStream::new(msg_rx), stop_tx))
     }
 }
+
+#[cfg(test)]
+mod test {
+    use rdkafka::consumer::BaseConsumer;
+    use rdkafka::mocking::MockCluster;
+    use tokio_stream::StreamExt as _;
+
+    use super::*;
+
+    const TOPIC: &str = "messages";
+
+    /// Start a mock cluster with a single partition topic containing `messages`
+    async fn cluster_with_messages(
+        messages: &[&str],
+    ) -> Result<(
+        MockCluster<'static, rdkafka::producer::DefaultProducerContext>,
+        ClientConfig,
+    )> {
+        let cluster = MockCluster::new(1)?;
+        cluster.create_topic(TOPIC, 1, 1)?;
+        let mut config = ClientConfig::new();
+        config.set("bootstrap.servers", cluster.bootstrap_servers());
+        let producer = build_producer(&config)?;
+        for message in messages {
+            send_record(
+                &producer,
+                FutureRecord::<(), _>::to(TOPIC).payload(*message),
+            )
+            .await?;
+        }
+        Ok((cluster, config))
+    }
+
+    /// Offset committed by `group` for the topic partition
+    fn committed_offset(config: &ClientConfig, group: &str) -> Result<Offset> {
+        let consumer: BaseConsumer = config.clone().set("group.id", group).create()?;
+        let mut partitions = TopicPartitionList::new();
+        partitions.add_partition(TOPIC, 0);
+        let committed = consumer.committed_offsets(partitions, METADATA_TIMEOUT)?;
+        Ok(committed
+            .find_partition(TOPIC, 0)
+            .context("missing partition")?
+            .offset())
+    }
+
+    async fn next_value(messages: &mut (impl Stream<Item = KafkaMessage> + Unpin)) -> KafkaMessage {
+        tokio::time::timeout(Duration::from_secs(10), messages.next())
+            .await
+            .expect("timed out waiting for message")
+            .expect("message stream ended")
+    }
+
+    /// Stop a consumer and wait for it to leave its group, before the cluster is shut down
+    async fn stop_consumer(
+        mut messages: impl Stream<Item = KafkaMessage> + Unpin,
+        stop: Sender<()>,
+    ) {
+        stop.send(()).expect("consumer stopped early");
+        tokio::time::timeout(Duration::from_secs(10), async {
+            while messages.next().await.is_some() {}
+        })
+        .await
+        .expect("timed out waiting for consumer to stop");
+    }
+
+    #[tokio::test]
+    async fn committed_offsets_follow_acknowledgements() -> Result<()> {
+        let (_cluster, config) = cluster_with_messages(&["one", "two"]).await?;
+
+        // Resume the group from the start of the partition
+        let group: BaseConsumer = config.clone().set("group.id", "group").create()?;
+        let mut offsets = TopicPartitionList::new();
+        offsets.add_partition_offset(TOPIC, 0, Offset::Offset(0))?;
+        group.commit(&offsets, CommitMode::Sync)?;
+        drop(group);
+
+        let consumer =
+            AsyncKafkaConsumer::new(&config, TOPIC, &[], StartOffset::Committed, Some("group"))?;
+        let (mut messages, stop) = consumer.messages(true).await?;
+
+        // A message that is not handled successfully is redelivered, and not committed
+        let msg = next_value(&mut messages).await;
+        assert_eq!(msg.value, b"one");
+        msg.ack.expect("missing ack").send(false).unwrap();
+        let msg = next_value(&mut messages).await;
+        assert_eq!(msg.value, b"one");
+        assert_eq!(committed_offset(&config, "group")?, Offset::Offset(0));
+        msg.ack.expect("missing ack").send(true).unwrap();
+
+        let msg = next_value(&mut messages).await;
+        assert_eq!(msg.value, b"two");
+        msg.ack.expect("missing ack").send(true).unwrap();
+
+        let mut committed = Offset::Invalid;
+        for _ in 0..50 {
+            committed = committed_offset(&config, "group")?;
+            if committed == Offset::Offset(2) {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+        assert_eq!(committed, Offset::Offset(2));
+        stop_consumer(messages, stop).await;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn start_offset_applies_to_group_without_commits() -> Result<()> {
+        let (_cluster, config) = cluster_with_messages(&["one"]).await?;
+
+        let consumer =
+            AsyncKafkaConsumer::new(&config, TOPIC, &[], StartOffset::Earliest, Some("group"))?;
+        let (mut messages, stop) = consumer.messages(false).await?;
+        let msg = next_value(&mut messages).await;
+        assert_eq!(msg.value, b"one");
+        assert!(msg.ack.is_none());
+        stop_consumer(messages, stop).await;
+        Ok(())
+    }
+}
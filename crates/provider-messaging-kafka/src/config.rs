@@ -0,0 +1,658 @@
+//! Consumer offset and connection security settings parsed from link configuration
+
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use anyhow::{bail, Context as _, Result};
+use rdkafka::ClientConfig;
+use tracing::warn;
+use wasmcloud_provider_sdk::core::secrets::SecretValue;
+
+/// Config value for where to start consuming (`earliest`, `latest` or `committed`)
+const KAFKA_CONSUMER_START_OFFSET_CONFIG_KEY: &str = "consumer_start_offset";
+
+/// Config value for the protocol used to talk to brokers, named after Kafka's `security.protocol`
+const KAFKA_SECURITY_PROTOCOL_CONFIG_KEY: &str = "security_protocol";
+
+/// PEM encoded client certificate, preferably supplied as a secret
+const KAFKA_TLS_CLIENT_CERT_CONFIG_KEY: &str = "tls_client_cert";
+/// Path to a PEM encoded client certificate
+const KAFKA_TLS_CLIENT_CERT_FILE_CONFIG_KEY: &str = "tls_client_cert_file";
+/// PEM encoded client private key, preferably supplied as a secret
+const KAFKA_TLS_CLIENT_KEY_CONFIG_KEY: &str = "tls_client_key";
+/// Path to a PEM encoded client private key
+const KAFKA_TLS_CLIENT_KEY_FILE_CONFIG_KEY: &str = "tls_client_key_file";
+/// PEM encoded CA certificate(s) used to verify brokers
+const KAFKA_TLS_CA_CONFIG_KEY: &str = "tls_ca";
+/// Path to PEM encoded CA certificate(s) used to verify brokers
+const KAFKA_TLS_CA_FILE_CONFIG_KEY: &str = "tls_ca_file";
+/// Whether broker hostnames and certificates should be verified (defaults to `true`)
+const KAFKA_TLS_VERIFY_CONFIG_KEY: &str = "tls_verify";
+
+/// SASL mechanism, one of `PLAIN`, `SCRAM-SHA-256` or `SCRAM-SHA-512`
+const KAFKA_SASL_MECHANISM_CONFIG_KEY: &str = "sasl_mechanism";
+/// SASL username
+const KAFKA_SASL_USERNAME_CONFIG_KEY: &str = "sasl_username";
+/// SASL password, preferably supplied as a secret
+const KAFKA_SASL_PASSWORD_CONFIG_KEY: &str = "sasl_password";
+
+/// Where a consumer starts reading a topic, and whether it commits what it has processed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum StartOffset {
+    /// Start from the earliest available message, unless the consumer group committed an offset
+    Earliest,
+    /// Start from the next message produced, unless the consumer group committed an offset
+    Latest,
+    /// Resume from the offsets committed by the consumer group (or the latest message if
+    /// nothing was committed yet), committing each message once the component has handled it
+    Committed,
+}
+
+impl FromStr for StartOffset {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "earliest" => Ok(Self::Earliest),
+            "latest" => Ok(Self::Latest),
+            "committed" => Ok(Self::Committed),
+            other => bail!(
+                "invalid [{KAFKA_CONSUMER_START_OFFSET_CONFIG_KEY}] value [{other}], expected one of earliest, latest or committed"
+            ),
+        }
+    }
+}
+
+impl StartOffset {
+    /// Parse the start offset from config, defaulting to [`StartOffset::Committed`] for consumer
+    /// groups and [`StartOffset::Latest`] otherwise
+    pub fn from_config(
+        config: &HashMap<String, String>,
+        consumer_group: Option<&str>,
+    ) -> Result<Self> {
+        let start_offset = match config.get(KAFKA_CONSUMER_START_OFFSET_CONFIG_KEY) {
+            Some(v) => v.parse()?,
+            None if consumer_group.is_some() => Self::Committed,
+            None => Self::Latest,
+        };
+        if start_offset == Self::Committed && consumer_group.is_none() {
+            bail!("a consumer group must be configured to consume from committed offsets");
+        }
+        Ok(start_offset)
+    }
+
+    /// Whether consumed messages are committed once they have been handled successfully
+    pub fn commits(self) -> bool {
+        self == Self::Committed
+    }
+
+    /// Configure a consumer in `consumer_group` (if any) to start from this offset
+    ///
+    /// Offsets are stored in the consumer group, so with [`StartOffset::Earliest`] and
+    /// [`StartOffset::Latest`] the group is still resumed from where it was, and these only apply
+    /// to partitions without a committed offset. Those offsets are committed as messages are
+    /// delivered, rather than once they are handled.
+    ///
+    /// Without a consumer group, the consumer joins a group of its own which never commits, so
+    /// that it always starts from this offset.
+    pub fn configure(self, config: &mut ClientConfig, consumer_group: Option<&str>) {
+        config.set(
+            "auto.offset.reset",
+            match self {
+                Self::Earliest => "earliest",
+                Self::Latest | Self::Committed => "latest",
+            },
+        );
+        match consumer_group {
+            Some(group) => config
+                .set("group.id", group)
+                .set("enable.auto.commit", (!self.commits()).to_string()),
+            None => config
+                .set("group.id", format!("wasmcloud-{}", nuid::next()))
+                .set("enable.auto.commit", "false"),
+        };
+    }
+}
+
+/// Protocol used to talk to brokers
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+enum SecurityProtocol {
+    #[default]
+    Plaintext,
+    Ssl,
+    SaslPlaintext,
+    SaslSsl,
+}
+
+impl SecurityProtocol {
+    /// Value of the `security.protocol` client property
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Plaintext => "plaintext",
+            Self::Ssl => "ssl",
+            Self::SaslPlaintext => "sasl_plaintext",
+            Self::SaslSsl => "sasl_ssl",
+        }
+    }
+
+    fn uses_tls(self) -> bool {
+        matches!(self, Self::Ssl | Self::SaslSsl)
+    }
+
+    fn uses_sasl(self) -> bool {
+        matches!(self, Self::SaslPlaintext | Self::SaslSsl)
+    }
+}
+
+impl FromStr for SecurityProtocol {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "plaintext" => Ok(Self::Plaintext),
+            "ssl" => Ok(Self::Ssl),
+            "sasl_plaintext" => Ok(Self::SaslPlaintext),
+            "sasl_ssl" => Ok(Self::SaslSsl),
+            other => bail!(
+                "invalid [{KAFKA_SECURITY_PROTOCOL_CONFIG_KEY}] value [{other}], expected one of plaintext, ssl, sasl_plaintext or sasl_ssl"
+            ),
+        }
+    }
+}
+
+/// Settings used to secure connections to brokers
+#[derive(Debug, Clone, Default)]
+pub(crate) struct SecurityConfig {
+    protocol: SecurityProtocol,
+    /// TLS settings, present if the protocol uses TLS
+    tls: Option<TlsConfig>,
+    /// SASL credentials, present if the protocol uses SASL
+    sasl: Option<SaslConfig>,
+}
+
+impl SecurityConfig {
+    /// Parse security settings from link config and secrets
+    pub fn from_config_and_secrets(
+        config: &HashMap<String, String>,
+        secrets: &HashMap<String, SecretValue>,
+    ) -> Result<Self> {
+        let protocol = config
+            .get(KAFKA_SECURITY_PROTOCOL_CONFIG_KEY)
+            .map(|v| v.parse::<SecurityProtocol>())
+            .transpose()?
+            .unwrap_or_default();
+        let tls = if protocol.uses_tls() {
+            Some(TlsConfig::from_config_and_secrets(config, secrets)?)
+        } else if [
+            KAFKA_TLS_CLIENT_CERT_CONFIG_KEY,
+            KAFKA_TLS_CLIENT_CERT_FILE_CONFIG_KEY,
+            KAFKA_TLS_CA_CONFIG_KEY,
+            KAFKA_TLS_CA_FILE_CONFIG_KEY,
+        ]
+        .iter()
+        .any(|k| config.contains_key(*k) || secrets.contains_key(*k))
+        {
+            bail!("TLS settings were supplied, but [{KAFKA_SECURITY_PROTOCOL_CONFIG_KEY}] is not set to `ssl` or `sasl_ssl`");
+        } else {
+            None
+        };
+        let sasl = if protocol.uses_sasl() {
+            Some(SaslConfig::from_config_and_secrets(config, secrets)?)
+        } else if [
+            KAFKA_SASL_MECHANISM_CONFIG_KEY,
+            KAFKA_SASL_USERNAME_CONFIG_KEY,
+            KAFKA_SASL_PASSWORD_CONFIG_KEY,
+        ]
+        .iter()
+        .any(|k| config.contains_key(*k) || secrets.contains_key(*k))
+        {
+            bail!("SASL settings were supplied, but [{KAFKA_SECURITY_PROTOCOL_CONFIG_KEY}] is not set to `sasl_plaintext` or `sasl_ssl`");
+        } else {
+            None
+        };
+        Ok(Self {
+            protocol,
+            tls,
+            sasl,
+        })
+    }
+
+    /// Apply these settings to the configuration of a Kafka client, producer or consumer
+    pub fn apply(&self, config: &mut ClientConfig) {
+        config.set("security.protocol", self.protocol.as_str());
+        if let Some(tls) = &self.tls {
+            tls.apply(config);
+        }
+        if let Some(sasl) = &self.sasl {
+            sasl.apply(config);
+        }
+    }
+}
+
+/// TLS settings for connecting to brokers
+#[derive(Clone, Default)]
+struct TlsConfig {
+    /// PEM encoded client certificate (chain)
+    client_cert: Option<String>,
+    /// PEM encoded client private key
+    client_key: Option<String>,
+    /// PEM encoded CA certificate(s), used instead of the system trust store
+    ca: Option<String>,
+    /// Whether broker hostnames and certificates should be verified
+    verify: bool,
+}
+
+impl std::fmt::Debug for TlsConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TlsConfig")
+            .field("client_cert", &self.client_cert.is_some())
+            .field("client_key", &self.client_key.as_ref().map(|_| "redacted"))
+            .field("ca", &self.ca.is_some())
+            .field("verify", &self.verify)
+            .finish()
+    }
+}
+
+impl TlsConfig {
+    /// Parse TLS settings from link config and secrets
+    ///
+    /// Certificates are parsed when a client is created from the settings.
+    fn from_config_and_secrets(
+        config: &HashMap<String, String>,
+        secrets: &HashMap<String, SecretValue>,
+    ) -> Result<Self> {
+        let client_cert = pem_value(
+            config,
+            secrets,
+            KAFKA_TLS_CLIENT_CERT_CONFIG_KEY,
+            KAFKA_TLS_CLIENT_CERT_FILE_CONFIG_KEY,
+        )?;
+        let client_key = pem_value(
+            config,
+            secrets,
+            KAFKA_TLS_CLIENT_KEY_CONFIG_KEY,
+            KAFKA_TLS_CLIENT_KEY_FILE_CONFIG_KEY,
+        )?;
+        if client_cert.is_some() != client_key.is_some() {
+            bail!("a TLS client certificate and key must be supplied together");
+        }
+        let ca = pem_value(
+            config,
+            secrets,
+            KAFKA_TLS_CA_CONFIG_KEY,
+            KAFKA_TLS_CA_FILE_CONFIG_KEY,
+        )?;
+        let verify = config
+            .get(KAFKA_TLS_VERIFY_CONFIG_KEY)
+            .map(|v| !v.trim().eq_ignore_ascii_case("false"))
+            .unwrap_or(true);
+        Ok(Self {
+            client_cert,
+            client_key,
+            ca,
+            verify,
+        })
+    }
+
+    fn apply(&self, config: &mut ClientConfig) {
+        if let (Some(cert), Some(key)) = (&self.client_cert, &self.client_key) {
+            config
+                .set("ssl.certificate.pem", cert)
+                .set("ssl.key.pem", key);
+        }
+        if let Some(ca) = &self.ca {
+            config.set("ssl.ca.pem", ca);
+        }
+        if self.verify {
+            config
+                .set("enable.ssl.certificate.verification", "true")
+                .set("ssl.endpoint.identification.algorithm", "https");
+        } else {
+            config
+                .set("enable.ssl.certificate.verification", "false")
+                .set("ssl.endpoint.identification.algorithm", "none");
+        }
+    }
+}
+
+/// SASL mechanism used to authenticate with brokers
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SaslMechanism {
+    Plain,
+    ScramSha256,
+    ScramSha512,
+}
+
+impl SaslMechanism {
+    /// Value of the `sasl.mechanism` client property
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Plain => "PLAIN",
+            Self::ScramSha256 => "SCRAM-SHA-256",
+            Self::ScramSha512 => "SCRAM-SHA-512",
+        }
+    }
+}
+
+impl FromStr for SaslMechanism {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_ascii_uppercase().replace('_', "-").as_str() {
+            "PLAIN" => Ok(Self::Plain),
+            "SCRAM-SHA-256" => Ok(Self::ScramSha256),
+            "SCRAM-SHA-512" => Ok(Self::ScramSha512),
+            other => bail!(
+                "invalid [{KAFKA_SASL_MECHANISM_CONFIG_KEY}] value [{other}], expected one of PLAIN, SCRAM-SHA-256 or SCRAM-SHA-512"
+            ),
+        }
+    }
+}
+
+/// SASL credentials for connecting to brokers
+#[derive(Clone)]
+struct SaslConfig {
+    mechanism: SaslMechanism,
+    username: String,
+    password: String,
+}
+
+impl std::fmt::Debug for SaslConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SaslConfig")
+            .field("mechanism", &self.mechanism)
+            .field("username", &self.username)
+            .field("password", &"redacted")
+            .finish()
+    }
+}
+
+impl SaslConfig {
+    /// Parse SASL settings from link config and secrets
+    fn from_config_and_secrets(
+        config: &HashMap<String, String>,
+        secrets: &HashMap<String, SecretValue>,
+    ) -> Result<Self> {
+        let mechanism = config
+            .get(KAFKA_SASL_MECHANISM_CONFIG_KEY)
+            .with_context(|| {
+                format!("[{KAFKA_SASL_MECHANISM_CONFIG_KEY}] must be set to use SASL")
+            })?
+            .parse()?;
+        let username = secret_or_config(config, secrets, KAFKA_SASL_USERNAME_CONFIG_KEY)
+            .with_context(|| {
+                format!("[{KAFKA_SASL_USERNAME_CONFIG_KEY}] must be set to use SASL")
+            })?;
+        let password = match secrets
+            .get(KAFKA_SASL_PASSWORD_CONFIG_KEY)
+            .and_then(SecretValue::as_string)
+        {
+            Some(password) => password.to_string(),
+            None => {
+                let password = config
+                    .get(KAFKA_SASL_PASSWORD_CONFIG_KEY)
+                    .with_context(|| {
+                        format!("[{KAFKA_SASL_PASSWORD_CONFIG_KEY}] must be set to use SASL")
+                    })?;
+                warn!("secret value [{KAFKA_SASL_PASSWORD_CONFIG_KEY}] was not found in secrets. Prefer storing sensitive values in secrets");
+                password.clone()
+            }
+        };
+        Ok(Self {
+            mechanism,
+            username,
+            password,
+        })
+    }
+
+    fn apply(&self, config: &mut ClientConfig) {
+        config
+            .set("sasl.mechanism", self.mechanism.as_str())
+            .set("sasl.username", &self.username)
+            .set("sasl.password", &self.password);
+    }
+}
+
+/// Look up a string value in secrets, then config
+fn secret_or_config(
+    config: &HashMap<String, String>,
+    secrets: &HashMap<String, SecretValue>,
+    key: &str,
+) -> Option<String> {
+    secrets
+        .get(key)
+        .and_then(SecretValue::as_string)
+        .map(ToString::to_string)
+        .or_else(|| config.get(key).cloned())
+}
+
+/// Look up a PEM value inline (in secrets, then config) or from the file named by `file_key`
+fn pem_value(
+    config: &HashMap<String, String>,
+    secrets: &HashMap<String, SecretValue>,
+    key: &str,
+    file_key: &str,
+) -> Result<Option<String>> {
+    if let Some(v) = secrets.get(key) {
+        return match (v.as_string(), v.as_bytes()) {
+            (Some(s), _) => Ok(Some(s.to_string())),
+            (_, Some(b)) => String::from_utf8(b.to_vec())
+                .map(Some)
+                .with_context(|| format!("[{key}] is not valid PEM")),
+            _ => Ok(None),
+        };
+    }
+    if let Some(v) = config.get(key) {
+        return Ok(Some(v.clone()));
+    }
+    config
+        .get(file_key)
+        .map(|path| {
+            std::fs::read_to_string(path).with_context(|| format!("failed to read [{path}]"))
+        })
+        .transpose()
+}
+
+#[cfg(test)]
+mod test {
+    use rdkafka::config::RDKafkaLogLevel;
+    use rdkafka::producer::BaseProducer;
+
+    use super::*;
+
+    fn config(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    /// Create a producer with the given security settings, which parses certificates and
+    /// checks that the client supports the protocol and mechanism
+    fn create_producer(security: &SecurityConfig) -> Result<BaseProducer> {
+        // Without bootstrap servers the producer never connects
+        let mut config = ClientConfig::new();
+        config.set_log_level(RDKafkaLogLevel::Error);
+        security.apply(&mut config);
+        Ok(config.create()?)
+    }
+
+    #[test]
+    fn start_offset_defaults() {
+        assert_eq!(
+            StartOffset::from_config(&config(&[]), None).unwrap(),
+            StartOffset::Latest
+        );
+        assert_eq!(
+            StartOffset::from_config(&config(&[]), Some("group")).unwrap(),
+            StartOffset::Committed
+        );
+        assert_eq!(
+            StartOffset::from_config(&config(&[("consumer_start_offset", "Earliest")]), None)
+                .unwrap(),
+            StartOffset::Earliest
+        );
+    }
+
+    #[test]
+    fn start_offset_with_group() {
+        for (value, offset) in [
+            ("earliest", StartOffset::Earliest),
+            ("latest", StartOffset::Latest),
+            ("committed", StartOffset::Committed),
+        ] {
+            assert_eq!(
+                StartOffset::from_config(
+                    &config(&[("consumer_start_offset", value)]),
+                    Some("group")
+                )
+                .unwrap(),
+                offset
+            );
+        }
+
+        let mut client_config = ClientConfig::new();
+        StartOffset::Earliest.configure(&mut client_config, Some("group"));
+        assert_eq!(client_config.get("group.id"), Some("group"));
+        assert_eq!(client_config.get("auto.offset.reset"), Some("earliest"));
+        assert_eq!(client_config.get("enable.auto.commit"), Some("true"));
+
+        let mut client_config = ClientConfig::new();
+        StartOffset::Committed.configure(&mut client_config, Some("group"));
+        assert_eq!(client_config.get("auto.offset.reset"), Some("latest"));
+        assert_eq!(client_config.get("enable.auto.commit"), Some("false"));
+
+        let mut client_config = ClientConfig::new();
+        StartOffset::Latest.configure(&mut client_config, None);
+        assert!(client_config
+            .get("group.id")
+            .is_some_and(|group| group.starts_with("wasmcloud-")));
+        assert_eq!(client_config.get("enable.auto.commit"), Some("false"));
+    }
+
+    #[test]
+    fn start_offset_committed_requires_group() {
+        assert!(
+            StartOffset::from_config(&config(&[("consumer_start_offset", "committed")]), None)
+                .is_err()
+        );
+        assert!(
+            StartOffset::from_config(&config(&[("consumer_start_offset", "middle")]), None)
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn tls_config_parsing() {
+        let secrets = HashMap::new();
+        let security = SecurityConfig::from_config_and_secrets(&config(&[]), &secrets).unwrap();
+        assert_eq!(security.protocol, SecurityProtocol::Plaintext);
+        assert!(security.tls.is_none());
+        let security = SecurityConfig::from_config_and_secrets(
+            &config(&[("security_protocol", "ssl")]),
+            &secrets,
+        )
+        .unwrap();
+        assert!(security.tls.is_some());
+        create_producer(&security).unwrap();
+
+        assert!(SecurityConfig::from_config_and_secrets(
+            &config(&[("tls_ca", "not-pem")]),
+            &secrets
+        )
+        .is_err());
+        assert!(SecurityConfig::from_config_and_secrets(
+            &config(&[("security_protocol", "ssl"), ("tls_client_cert", "cert")]),
+            &secrets
+        )
+        .is_err());
+        let security = SecurityConfig::from_config_and_secrets(
+            &config(&[("security_protocol", "ssl"), ("tls_ca", "not-pem")]),
+            &secrets,
+        )
+        .unwrap();
+        assert!(create_producer(&security).is_err());
+    }
+
+    #[test]
+    fn sasl_config_is_accepted() {
+        let secrets = HashMap::from([(
+            "sasl_password".to_string(),
+            SecretValue::String("hunter2".into()),
+        )]);
+        for (protocol, mechanism) in [
+            ("sasl_plaintext", "PLAIN"),
+            ("SASL_SSL", "scram-sha-256"),
+            ("sasl_ssl", "SCRAM-SHA-512"),
+        ] {
+            let security = SecurityConfig::from_config_and_secrets(
+                &config(&[
+                    ("security_protocol", protocol),
+                    ("sasl_mechanism", mechanism),
+                    ("sasl_username", "alice"),
+                ]),
+                &secrets,
+            )
+            .unwrap();
+            let sasl = security.sasl.as_ref().expect("missing SASL settings");
+            assert_eq!(sasl.username, "alice");
+            assert_eq!(sasl.password, "hunter2");
+            assert_eq!(
+                security.tls.is_some(),
+                protocol.eq_ignore_ascii_case("sasl_ssl")
+            );
+            assert!(!format!("{security:?}").contains("hunter2"));
+
+            let mut client_config = ClientConfig::new();
+            security.apply(&mut client_config);
+            assert_eq!(
+                client_config.get("security.protocol"),
+                Some(protocol.to_ascii_lowercase().as_str())
+            );
+            assert_eq!(
+                client_config.get("sasl.mechanism"),
+                Some(mechanism.to_ascii_uppercase().as_str())
+            );
+            assert_eq!(client_config.get("sasl.password"), Some("hunter2"));
+            create_producer(&security).unwrap();
+        }
+    }
+
+    #[test]
+    fn sasl_config_requires_credentials_and_protocol() {
+        let secrets = HashMap::from([(
+            "sasl_password".to_string(),
+            SecretValue::String("hunter2".into()),
+        )]);
+        // SASL settings without a SASL protocol
+        assert!(SecurityConfig::from_config_and_secrets(&config(&[]), &secrets).is_err());
+        // Missing mechanism, unknown mechanism and missing username
+        for config in [
+            config(&[
+                ("security_protocol", "sasl_ssl"),
+                ("sasl_username", "alice"),
+            ]),
+            config(&[
+                ("security_protocol", "sasl_ssl"),
+                ("sasl_mechanism", "GSSAPI"),
+                ("sasl_username", "alice"),
+            ]),
+            config(&[
+                ("security_protocol", "sasl_ssl"),
+                ("sasl_mechanism", "PLAIN"),
+            ]),
+        ] {
+            assert!(SecurityConfig::from_config_and_secrets(&config, &secrets).is_err());
+        }
+        // Missing password
+        assert!(SecurityConfig::from_config_and_secrets(
+            &config(&[
+                ("security_protocol", "sasl_plaintext"),
+                ("sasl_mechanism", "PLAIN"),
+                ("sasl_username", "alice"),
+            ]),
+            &HashMap::new()
+        )
+        .is_err());
+    }
+}
@@ -0,0 +1,154 @@
+//! Correlation metadata used to implement request-reply on top of Kafka
+//!
+//! The reply topic and correlation ID of a request are carried in record headers.
+
+use rdkafka::message::{Header, Headers, OwnedHeaders};
+
+/// Name of the header carrying the topic a reply should be sent to
+pub(crate) const REPLY_TO_HEADER: &str = "wasmcloud-reply-to";
+
+/// Name of the header carrying the ID that correlates a reply with its request
+pub(crate) const CORRELATION_ID_HEADER: &str = "wasmcloud-correlation-id";
+
+/// Separator between the topic and the correlation ID in reply subjects handed to components.
+///
+/// `#` is not a legal character in Kafka topic names, so it can never clash with a real topic.
+const REPLY_SUBJECT_SEPARATOR: char = '#';
+
+/// Correlation metadata attached to a Kafka record
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub(crate) struct CorrelationHeaders {
+    /// Topic on which the requester is listening for a reply
+    pub reply_to: Option<String>,
+    /// ID used to match a reply with the originating request
+    pub correlation_id: Option<String>,
+}
+
+impl CorrelationHeaders {
+    /// Build headers for an outgoing request
+    pub fn request(reply_to: impl Into<String>, correlation_id: impl Into<String>) -> Self {
+        Self {
+            reply_to: Some(reply_to.into()),
+            correlation_id: Some(correlation_id.into()),
+        }
+    }
+
+    /// Build headers for an outgoing reply
+    pub fn reply(correlation_id: impl Into<String>) -> Self {
+        Self {
+            reply_to: None,
+            correlation_id: Some(correlation_id.into()),
+        }
+    }
+
+    /// Parse record headers, returning `None` if they carry no correlation data
+    pub fn from_headers(headers: &impl Headers) -> Option<Self> {
+        let mut correlation = Self::default();
+        for header in (0..headers.count()).filter_map(|i| headers.try_get(i)) {
+            let Some(value) = header
+                .value
+                .and_then(|v| std::str::from_utf8(v).ok())
+                .filter(|v| !v.is_empty())
+            else {
+                continue;
+            };
+            match header.key {
+                REPLY_TO_HEADER => correlation.reply_to = Some(value.to_string()),
+                CORRELATION_ID_HEADER => correlation.correlation_id = Some(value.to_string()),
+                _ => {}
+            }
+        }
+        correlation.correlation_id.is_some().then_some(correlation)
+    }
+
+    /// Encode into record headers
+    pub fn to_headers(&self) -> OwnedHeaders {
+        let mut headers = OwnedHeaders::new_with_capacity(2);
+        if let Some(reply_to) = &self.reply_to {
+            headers = headers.insert(Header {
+                key: REPLY_TO_HEADER,
+                value: Some(reply_to),
+            });
+        }
+        if let Some(correlation_id) = &self.correlation_id {
+            headers = headers.insert(Header {
+                key: CORRELATION_ID_HEADER,
+                value: Some(correlation_id),
+            });
+        }
+        headers
+    }
+
+    /// Build the `reply_to` subject handed to a component for a received request, if any
+    ///
+    /// Components publish their reply to this subject, which [`split_reply_subject`] turns back
+    /// into a topic and correlation ID.
+    pub fn reply_subject(&self) -> Option<String> {
+        match (&self.reply_to, &self.correlation_id) {
+            (Some(reply_to), Some(correlation_id)) => Some(format!(
+                "{reply_to}{REPLY_SUBJECT_SEPARATOR}{correlation_id}"
+            )),
+            _ => None,
+        }
+    }
+}
+
+/// Split a subject produced by [`CorrelationHeaders::reply_subject`] into topic and correlation ID
+pub(crate) fn split_reply_subject(subject: &str) -> Option<(&str, &str)> {
+    subject
+        .split_once(REPLY_SUBJECT_SEPARATOR)
+        .filter(|(topic, id)| !topic.is_empty() && !id.is_empty())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn request_headers_roundtrip() {
+        let correlation = CorrelationHeaders::request("replies", "abc123");
+        let headers = correlation.to_headers();
+        assert_eq!(headers.count(), 2);
+        assert_eq!(headers.get(0).key, "wasmcloud-reply-to",);
+        assert_eq!(headers.get(1).value, Some(b"abc123".as_slice()),);
+        assert_eq!(
+            CorrelationHeaders::from_headers(&headers),
+            Some(correlation)
+        );
+    }
+
+    #[test]
+    fn unrelated_headers_are_ignored() {
+        let header = |key, value: &'static [u8]| Header {
+            key,
+            value: Some(value),
+        };
+        assert_eq!(CorrelationHeaders::from_headers(&OwnedHeaders::new()), None);
+        assert_eq!(
+            CorrelationHeaders::from_headers(&OwnedHeaders::new().insert(header("user", b"value"))),
+            None
+        );
+        assert_eq!(
+            CorrelationHeaders::from_headers(
+                &OwnedHeaders::new().insert(header("wasmcloud-reply-to", b"replies"))
+            ),
+            None
+        );
+        assert_eq!(
+            CorrelationHeaders::from_headers(
+                &OwnedHeaders::new().insert(header("wasmcloud-correlation-id", &[0xff, 0xfe]))
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn reply_subject_roundtrip() {
+        let headers = CorrelationHeaders::request("replies", "abc123");
+        let subject = headers.reply_subject().expect("missing reply subject");
+        assert_eq!(split_reply_subject(&subject), Some(("replies", "abc123")));
+        assert_eq!(CorrelationHeaders::reply("abc123").reply_subject(), None);
+        assert_eq!(split_reply_subject("wasmcloud.echo.reply"), None);
+        assert_eq!(split_reply_subject("#abc123"), None);
+    }
+}
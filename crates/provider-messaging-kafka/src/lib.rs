@@ -5,14 +5,15 @@ use std::sync::Arc;
 
 use anyhow::{bail, Context as _, Result};
 use bytes::Bytes;
-use kafka::producer::{Producer, Record};
+use rdkafka::producer::{FutureProducer, FutureRecord};
+use rdkafka::ClientConfig;
 use tokio::spawn;
 use tokio::sync::oneshot::Sender;
-use tokio::sync::RwLock;
+use tokio::sync::{oneshot, Mutex, OnceCell, RwLock};
 use tokio::task::JoinHandle;
 use tokio::time::Duration;
 use tokio_stream::StreamExt;
-use tracing::{debug, error, instrument, warn};
+use tracing::{debug, error, instrument, trace, warn};
 use wasmcloud_provider_sdk::{
     get_connection, run_provider, Context, LinkConfig, LinkDeleteInfo, Provider,
 };
@@ -20,7 +21,13 @@ use wasmcloud_provider_sdk::{initialize_observability, serve_provider_exports};
 use wasmcloud_tracing::context::TraceContextInjector;
 
 mod client;
-use client::{AsyncKafkaClient, AsyncKafkaConsumer};
+use client::{build_producer, client_config, send_record, AsyncKafkaConsumer, KafkaMessage};
+
+mod config;
+use config::{SecurityConfig, StartOffset};
+
+mod correlation;
+use correlation::{split_reply_subject, CorrelationHeaders};
 
 mod bindings {
     wit_bindgen_wrpc::generate!({
@@ -52,6 +59,12 @@ const KAFKA_CONSUMER_PARTITIONS_CONFIG_KEY: &str = "consumer_partitions";
 /// to use when producing values
 const KAFKA_PRODUCER_PARTITIONS_CONFIG_KEY: &str = "producer_partitions";
 
+/// Config value for the topic on which replies to `request`s are received
+const KAFKA_REPLY_TOPIC_CONFIG_KEY: &str = "reply_topic";
+
+/// Prefix of the default reply topic, which is suffixed with the provider key
+const DEFAULT_REPLY_TOPIC_PREFIX: &str = "wasmcloud.reply";
+
 /// Number of seconds to wait for a consumer to stop after triggering it
 const CONSUMER_STOP_TIMEOUT_SECS: u64 = 5;
 
@@ -64,8 +77,10 @@ pub async fn run() -> Result<()> {
 struct KafkaConnection {
     /// Hosts that the connection is using
     hosts: Vec<String>,
-    /// Kafka client that can be used for one-off things
-    client: AsyncKafkaClient,
+    /// Configuration (hosts and security settings) shared by the clients of the connection
+    client_config: ClientConfig,
+    /// Producer used to publish messages and requests
+    producer: FutureProducer,
     /// Handle to a tokio consumer task handle
     consumer: JoinHandle<anyhow::Result<()>>,
     /// Stop the consumer
@@ -76,14 +91,47 @@ struct KafkaConnection {
     producer_partitions: Vec<i32>,
     /// Consumer group
     consumer_group: Option<String>,
+    /// Topic on which replies to requests made by the component are received
+    reply_topic: String,
+    /// Listener for replies on [`KafkaConnection::reply_topic`], started on the first request
+    reply_listener: OnceCell<ReplyListener>,
+}
+
+/// A consumer task that routes replies to the requests awaiting them
+struct ReplyListener {
+    /// Handle to a tokio consumer task handle
+    consumer: JoinHandle<anyhow::Result<()>>,
+    /// Stop the consumer
+    consumer_stop_tx: Sender<()>,
+}
+
+impl ReplyListener {
+    /// Signal the reply consumer to stop, then wait for it to close out
+    async fn stop(self) -> Result<()> {
+        if let Err(()) = self.consumer_stop_tx.send(()) {
+            bail!("failed to send stop reply consumer");
+        }
+        let _ = tokio::time::timeout(
+            Duration::from_secs(CONSUMER_STOP_TIMEOUT_SECS),
+            self.consumer,
+        )
+        .await
+        .context("reply consumer task did not exit cleanly")?;
+        Ok(())
+    }
 }
 
+/// Requests awaiting a reply, keyed by correlation ID
+type PendingRequests = Arc<Mutex<HashMap<String, oneshot::Sender<BrokerMessage>>>>;
+
 #[derive(Clone, Default)]
 pub struct KafkaMessagingProvider {
     // Map of Component ID to the JoinHandle where messages are consumed.
     //
     // When a link is put we spawn a tokio::task to handle messages, and on delete the task is closed
     connections: Arc<RwLock<HashMap<String, KafkaConnection>>>,
+    // Map of correlation ID to the sender awaiting the reply to an in-flight request
+    pending_requests: PendingRequests,
 }
 
 impl KafkaMessagingProvider {
@@ -167,6 +215,175 @@ fn extract_topic_from_config(config: &HashMap<String, String>) -> &str {
         .trim()
 }
 
+/// Start consuming replies on `reply_topic`, handing each one to the matching pending request
+async fn start_reply_listener(
+    client_config: &ClientConfig,
+    reply_topic: String,
+    pending_requests: PendingRequests,
+) -> Result<ReplyListener> {
+    debug!(reply_topic, "creating kafka async reply consumer");
+    let consumer = AsyncKafkaConsumer::from_latest(client_config, &reply_topic)
+        .await
+        .with_context(|| format!("failed to build consumer for reply topic [{reply_topic}]"))?;
+    let (mut stream, inner_stop_tx) = consumer
+        .messages(false)
+        .await
+        .context("failed to start listening to reply consumer messages")?;
+
+    let (consumer_stop_tx, mut stop_listener_rx) = oneshot::channel();
+    let consumer = spawn(async move {
+        loop {
+            tokio::select! {
+                _ = &mut stop_listener_rx => {
+                    if let Err(()) = inner_stop_tx.send(()) {
+                        bail!("failed to send stop reply consumer");
+                    }
+                    return Ok(());
+                },
+
+                Some(msg) = stream.next() => {
+                    let Some(correlation_id) = msg
+                        .headers
+                        .as_ref()
+                        .and_then(CorrelationHeaders::from_headers)
+                        .and_then(|headers| headers.correlation_id)
+                    else {
+                        trace!(reply_topic, "ignoring message without correlation ID");
+                        continue;
+                    };
+                    let Some(tx) = pending_requests.lock().await.remove(&correlation_id) else {
+                        trace!(reply_topic, correlation_id, "ignoring reply for unknown request");
+                        continue;
+                    };
+                    if tx.send(BrokerMessage {
+                        body: msg.value.into(),
+                        reply_to: None,
+                        subject: reply_topic.clone(),
+                    }).is_err() {
+                        debug!(correlation_id, "request was dropped before reply arrived");
+                    }
+                }
+            }
+        }
+    });
+
+    Ok(ReplyListener {
+        consumer,
+        consumer_stop_tx,
+    })
+}
+
+/// Build the `reply_to` subject handed to a component for a message received on `subject`
+///
+/// Messages sent with `request` carry their reply topic and correlation ID, otherwise we always
+/// append '.reply' for reply topics
+fn reply_subject(msg: &KafkaMessage, subject: &str) -> String {
+    msg.headers
+        .as_ref()
+        .and_then(CorrelationHeaders::from_headers)
+        .and_then(|headers| headers.reply_subject())
+        .unwrap_or_else(|| format!("{subject}.reply"))
+}
+
+/// Publish a message from a component, to `producer_partitions` of its subject if any
+async fn publish_message(
+    producer: &FutureProducer,
+    producer_partitions: &[i32],
+    msg: &BrokerMessage,
+) -> Result<()> {
+    // Replies to a `request` are sent to the requester's reply topic, tagged with the
+    // correlation ID of the request
+    if let Some((topic, correlation_id)) = split_reply_subject(&msg.subject) {
+        debug!(topic, correlation_id, "sending reply");
+        return send_record(
+            producer,
+            FutureRecord::<(), _>::to(topic)
+                .payload(&msg.body[..])
+                .headers(CorrelationHeaders::reply(correlation_id).to_headers()),
+        )
+        .await
+        .context("failed to send reply record");
+    }
+
+    // For every partition we're listening on, send out a record
+    // if we're listening on *no* partitions, then use the unspecified partition
+    debug!(subject = msg.subject, "sending message");
+    match producer_partitions[..] {
+        // Send to the default ("unspecified") partition
+        [] => {
+            send_record(
+                producer,
+                FutureRecord::<(), _>::to(&msg.subject).payload(&msg.body[..]),
+            )
+            .await
+            .context("failed to send record")?;
+        }
+        // If there are multiple partitions to publish to, then publish to each of them
+        _ => {
+            for partition in producer_partitions {
+                send_record(
+                    producer,
+                    FutureRecord::<(), _>::to(&msg.subject)
+                        .payload(&msg.body[..])
+                        .partition(*partition),
+                )
+                .await
+                .with_context(|| format!("failed to send record to partition [{partition}]"))?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Send a request on `subject` and wait up to `timeout` for a reply on `reply_topic`
+///
+/// Kafka has no native request-reply, so the request is published with the reply topic and a
+/// correlation ID, and the reply is matched back up by a listener on the reply topic, which must
+/// already be running.
+async fn send_request(
+    producer: &FutureProducer,
+    pending_requests: &PendingRequests,
+    reply_topic: &str,
+    subject: &str,
+    body: &[u8],
+    timeout: Duration,
+) -> std::result::Result<BrokerMessage, String> {
+    let correlation_id = nuid::next().to_string();
+    let (reply_tx, reply_rx) = oneshot::channel();
+    pending_requests
+        .lock()
+        .await
+        .insert(correlation_id.clone(), reply_tx);
+
+    debug!(reply_topic, correlation_id, "sending request");
+    if let Err(e) = send_record(
+        producer,
+        FutureRecord::<(), _>::to(subject).payload(body).headers(
+            CorrelationHeaders::request(reply_topic, correlation_id.as_str()).to_headers(),
+        ),
+    )
+    .await
+    {
+        pending_requests.lock().await.remove(&correlation_id);
+        error!("failed to send request: {e:#}");
+        return Err(format!("failed to send request: {e:#}"));
+    }
+
+    match tokio::time::timeout(timeout, reply_rx).await {
+        Ok(Ok(reply)) => Ok(reply),
+        Ok(Err(_)) => Err("reply listener stopped before a reply was received".to_string()),
+        Err(_) => {
+            pending_requests.lock().await.remove(&correlation_id);
+            error!(correlation_id, "kafka request timed out");
+            Err(format!(
+                "kafka request timed out after {}ms",
+                timeout.as_millis()
+            ))
+        }
+    }
+}
+
 impl Provider for KafkaMessagingProvider {
     /// Called when this provider is linked to, when the provider is the *target* of the link.
     #[instrument(skip_all, fields(source_id))]
@@ -204,42 +421,50 @@ impl Provider for KafkaMessagingProvider {
             .iter()
             .filter_map(|v| v.parse::<i32>().ok())
             .collect::<Vec<i32>>();
-
-        // Build client for use with the consumer
-        let client = AsyncKafkaClient::from_hosts(hosts.clone()).await.with_context(|| {
-            warn!(
-                source_id,
-                "failed to create Kafka client for component",
-            );
-            format!("failed to build async kafka client for component [{source_id}], messages won't be received")
-        })?;
-
-        // Build a consumer configured with our given client
-        let _consumer_group = consumer_group.clone();
-        let _consumer_partitions = consumer_partitions.clone();
-        debug!(topic, ?consumer_partitions, "creating kafka async consumer");
-        let consumer = AsyncKafkaConsumer::from_async_client(client, move |mut b| {
-            b = b.with_topic(topic.into());
-            b = b.with_topic_partitions(topic.into(), _consumer_partitions.as_slice());
-            if let Some(g) = _consumer_group {
-                b = b.with_group(g);
-            }
-            b
-        }).await.with_context(|| {
+        let reply_topic = config
+            .get(KAFKA_REPLY_TOPIC_CONFIG_KEY)
+            .map(|t| t.trim().to_string())
+            .unwrap_or_else(|| {
+                format!(
+                    "{DEFAULT_REPLY_TOPIC_PREFIX}.{}",
+                    get_connection().provider_key()
+                )
+            });
+        let start_offset = StartOffset::from_config(config, consumer_group.as_deref())
+            .with_context(|| {
+                format!("invalid consumer configuration for component [{source_id}]")
+            })?;
+        let security = SecurityConfig::from_config_and_secrets(config, link_config.secrets)
+            .with_context(|| {
+                format!("invalid security configuration for component [{source_id}]")
+            })?;
+        let client_config = client_config(&hosts, &security);
+
+        // Build a consumer of the topic configured for the link
+        debug!(
+            topic,
+            ?consumer_partitions,
+            ?start_offset,
+            "creating kafka async consumer"
+        );
+        let consumer = AsyncKafkaConsumer::new(
+            &client_config,
+            topic,
+            &consumer_partitions,
+            start_offset,
+            consumer_group.as_deref(),
+        ).with_context(|| {
             warn!(
                 source_id,
-                "failed to build consumer from Kafka client for component",
+                "failed to build Kafka consumer for component",
             );
-            format!("failed to build consumer from kafka client for component [{source_id}], messages won't be received")
+            format!("failed to build kafka consumer for component [{source_id}], messages won't be received")
         })?;
 
-        // Build a second client to store in the connection
-        let client = AsyncKafkaClient::from_hosts(hosts.clone()).await.with_context(|| {
-            warn!(
-                source_id,
-                "failed to create Kafka client for component",
-            );
-            format!("failed to build async kafka client for component [{source_id}], messages won't be received")
+        // Build a producer to store in the connection
+        let producer = build_producer(&client_config).with_context(|| {
+            warn!(source_id, "failed to create Kafka producer for component");
+            format!("failed to build kafka producer for component [{source_id}]")
         })?;
 
         // Store reusable information for use when processing new messages
@@ -251,7 +476,7 @@ impl Provider for KafkaMessagingProvider {
 
         // Start listening for incoming messages
         let (mut stream, inner_stop_tx) = match consumer
-            .messages()
+            .messages(start_offset.commits())
             .await
             .context("failed to start listening to consumer messages")
         {
@@ -262,8 +487,8 @@ impl Provider for KafkaMessagingProvider {
             }
         };
 
-        // StartOffset::Latest only processes new messages, but Earliest will send every message.
-        // This could be a linkdef tunable value in the future
+        // When committing offsets, the consumer waits for each message to be acknowledged before
+        // delivering the next, so messages are handled in order and committed once handled
         let task = spawn(async move {
             let wrpc = get_connection().get_wrpc_client(&component_id).await?;
 
@@ -286,23 +511,38 @@ impl Provider for KafkaMessagingProvider {
                         let wrpc = wrpc.clone();
                         let subject = Arc::clone(&subject);
                         tokio::spawn(async move {
-                            if let Err(e) = bindings::wasmcloud::messaging::handler::handle_message(
+                            let reply_to = reply_subject(&msg, &subject);
+                            let handled = match bindings::wasmcloud::messaging::handler::handle_message(
                                 &wrpc,
                                 None,
                                 &BrokerMessage {
                                     body: msg.value.into(),
-                                    // By default, we always append '.reply' for reply topics
-                                    reply_to: Some(format!("{subject}.reply")),
+                                    reply_to: Some(reply_to),
                                     subject: subject.to_string(),
                                 },
                             )
                                 .await
                             {
-                                warn!(
-                                    subject = subject.to_string(),
-                                    component_id = component_id.to_string(),
-                                    "unable to send subscription: {e:?}",
-                                );
+                                Ok(Ok(())) => true,
+                                Ok(Err(e)) => {
+                                    warn!(
+                                        subject = subject.to_string(),
+                                        component_id = component_id.to_string(),
+                                        "component failed to handle message: {e}",
+                                    );
+                                    false
+                                }
+                                Err(e) => {
+                                    warn!(
+                                        subject = subject.to_string(),
+                                        component_id = component_id.to_string(),
+                                        "unable to send subscription: {e:?}",
+                                    );
+                                    false
+                                }
+                            };
+                            if let Some(ack) = msg.ack {
+                                let _ = ack.send(handled);
                             }
                         });
                     }
@@ -315,13 +555,16 @@ impl Provider for KafkaMessagingProvider {
         connections.insert(
             source_id.to_string(),
             KafkaConnection {
-                client,
+                client_config,
+                producer,
                 consumer: task,
                 consumer_stop_tx: stop_listener_tx,
                 hosts,
                 consumer_partitions,
                 producer_partitions,
                 consumer_group,
+                reply_topic,
+                reply_listener: OnceCell::new(),
             },
         );
 
@@ -339,6 +582,7 @@ impl Provider for KafkaMessagingProvider {
         let Some(KafkaConnection {
             consumer,
             consumer_stop_tx,
+            reply_listener,
             ..
         }) = connections.remove(component_id)
         else {
@@ -346,6 +590,12 @@ impl Provider for KafkaMessagingProvider {
             return Ok(());
         };
 
+        if let Some(reply_listener) = reply_listener.into_inner() {
+            if let Err(err) = reply_listener.stop().await {
+                error!(?err, "failed to stop reply consumer task cleanly");
+            }
+        }
+
         // Signal the consumer to stop, then wait for it to close out
         if let Err(()) = consumer_stop_tx.send(()) {
             bail!("failed to send stop consumer");
@@ -365,10 +615,16 @@ impl Provider for KafkaMessagingProvider {
             KafkaConnection {
                 consumer,
                 consumer_stop_tx,
+                reply_listener,
                 ..
             },
         ) in connections.drain()
         {
+            if let Some(reply_listener) = reply_listener.into_inner() {
+                if let Err(err) = reply_listener.stop().await {
+                    error!(?err, "failed to stop reply consumer task cleanly");
+                }
+            }
             consumer_stop_tx
                 .send(())
                 .map_err(|_| anyhow::anyhow!("failed to send consumer stop"))?;
@@ -415,10 +671,10 @@ impl bindings::exports::wasmcloud::messaging::consumer::Handler<Option<Context>>
             bail!("context unexpectedly missing component ID");
         };
 
-        // Retrieve a usable Kafka client from the kafka connection for our component
+        // Retrieve the producer from the kafka connection for our component
         let connections = self.connections.read().await;
         let Some(KafkaConnection {
-            hosts,
+            producer,
             producer_partitions,
             ..
         }) = connections.get(component_id)
@@ -428,55 +684,21 @@ impl bindings::exports::wasmcloud::messaging::consumer::Handler<Option<Context>>
                 "failed to get connection for component [{component_id}]"
             )));
         };
+        let producer = producer.clone();
+        let producer_partitions = producer_partitions.clone();
+        drop(connections);
 
-        // Create a producer we'll use to send
-        let mut producer = Producer::from_hosts(hosts.clone())
-            .create()
-            .context("failed to build kafka producer")?;
-
-        // For every partition we're listening on, send out a record
-        // if we're listening on *no* partitions, then use the unspecified partition
-        debug!(subject = msg.subject, "sending message");
-        match producer_partitions[..] {
-            // Send to the default ("unspecified") partition
-            [] => {
-                producer
-                    .send(&Record::<(), Vec<u8>>::from_key_value(
-                        &msg.subject,
-                        (),
-                        msg.body.to_vec(),
-                    ))
-                    .context("failed to send record")?;
-            }
-            // If there are multiple partitions to publish to, then publish to each of them
-            _ => {
-                for partition in producer_partitions {
-                    producer
-                        .send(
-                            &Record::<(), Vec<u8>>::from_key_value(
-                                &msg.subject,
-                                (),
-                                msg.body.to_vec(),
-                            )
-                            .with_partition(*partition),
-                        )
-                        .with_context(|| {
-                            format!("failed to send record to partition [{partition}]")
-                        })?;
-                }
-            }
-        }
-
+        publish_message(&producer, &producer_partitions, &msg).await?;
         Ok(Ok(()))
     }
 
-    #[instrument(skip_all)]
+    #[instrument(skip_all, fields(subject = %subject, timeout_ms))]
     async fn request(
         &self,
         ctx: Option<Context>,
-        _subject: String,
-        _body: Bytes,
-        _timeout_ms: u32,
+        subject: String,
+        body: Bytes,
+        timeout_ms: u32,
     ) -> Result<std::result::Result<BrokerMessage, String>> {
         // Extract tracing information from invocation context, if present
         let trace_ctx = match ctx {
@@ -492,13 +714,172 @@ impl bindings::exports::wasmcloud::messaging::consumer::Handler<Option<Context>>
         };
         wasmcloud_tracing::context::attach_span_context(&trace_ctx);
 
-        // Kafka does not support request-reply in the traditional sense. You can publish to a
-        // topic, and get an acknowledgement that it was received, but you can't get a
-        // reply from a consumer on the other side.
-        error!("not implemented (Kafka does not officially support the request-reply paradigm)");
-        Ok(Err(
-            "not implemented (Kafka does not officially support the request-reply paradigm)"
-                .to_string(),
-        ))
+        let ctx = ctx.as_ref().context("unexpectedly missing context")?;
+        let Some(component_id) = ctx.component.as_ref() else {
+            bail!("context unexpectedly missing component ID");
+        };
+
+        // Replies are matched up by a listener on the connection's reply topic, which is started
+        // on the first request
+        let connections = self.connections.read().await;
+        let Some(KafkaConnection {
+            client_config,
+            producer,
+            reply_topic,
+            reply_listener,
+            ..
+        }) = connections.get(component_id)
+        else {
+            warn!(component_id, "failed to get connection for component");
+            return Ok(Err(format!(
+                "failed to get connection for component [{component_id}]"
+            )));
+        };
+        if let Err(e) = reply_listener
+            .get_or_try_init(|| {
+                start_reply_listener(
+                    client_config,
+                    reply_topic.clone(),
+                    Arc::clone(&self.pending_requests),
+                )
+            })
+            .await
+        {
+            error!(reply_topic, "failed to start reply listener: {e:?}");
+            return Ok(Err(format!(
+                "failed to listen for replies on topic [{reply_topic}]: {e:#}"
+            )));
+        }
+
+        let producer = producer.clone();
+        let reply_topic = reply_topic.clone();
+        drop(connections);
+        Ok(send_request(
+            &producer,
+            &self.pending_requests,
+            &reply_topic,
+            &subject,
+            &body,
+            Duration::from_millis(timeout_ms.into()),
+        )
+        .await)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use rdkafka::mocking::MockCluster;
+
+    use super::*;
+
+    const REQUEST_TOPIC: &str = "requests";
+    const REPLY_TOPIC: &str = "replies";
+
+    /// Start a mock cluster with request and reply topics, returning it with a client configuration
+    fn cluster() -> Result<(
+        MockCluster<'static, rdkafka::producer::DefaultProducerContext>,
+        ClientConfig,
+    )> {
+        let cluster = MockCluster::new(1)?;
+        cluster.create_topic(REQUEST_TOPIC, 1, 1)?;
+        cluster.create_topic(REPLY_TOPIC, 1, 1)?;
+        let mut config = ClientConfig::new();
+        config.set("bootstrap.servers", cluster.bootstrap_servers());
+        Ok((cluster, config))
+    }
+
+    #[tokio::test]
+    async fn request_reply_roundtrip() -> Result<()> {
+        let (_cluster, config) = cluster()?;
+        let producer = build_producer(&config)?;
+        let pending_requests = PendingRequests::default();
+        let listener =
+            start_reply_listener(&config, REPLY_TOPIC.into(), Arc::clone(&pending_requests))
+                .await?;
+
+        // Reply to the request as a component would, by publishing to the `reply_to` subject
+        // it was handed
+        let consumer =
+            AsyncKafkaConsumer::new(&config, REQUEST_TOPIC, &[], StartOffset::Earliest, None)?;
+        let (mut requests, stop_responder) = consumer.messages(false).await?;
+        let responder = spawn({
+            let producer = producer.clone();
+            async move {
+                let request = requests.next().await.context("no request received")?;
+                let reply_to = reply_subject(&request, REQUEST_TOPIC);
+                let mut body = b"re: ".to_vec();
+                body.extend(&request.value);
+                publish_message(
+                    &producer,
+                    &[],
+                    &BrokerMessage {
+                        body: body.into(),
+                        reply_to: None,
+                        subject: reply_to.clone(),
+                    },
+                )
+                .await?;
+                let _ = stop_responder.send(());
+                anyhow::Ok(reply_to)
+            }
+        });
+
+        let reply = send_request(
+            &producer,
+            &pending_requests,
+            REPLY_TOPIC,
+            REQUEST_TOPIC,
+            b"ping",
+            Duration::from_secs(30),
+        )
+        .await
+        .map_err(anyhow::Error::msg)?;
+        assert_eq!(reply.body, b"re: ping".as_slice());
+        assert_eq!(reply.subject, REPLY_TOPIC);
+        assert_eq!(reply.reply_to, None);
+
+        let reply_to = responder.await??;
+        let (topic, correlation_id) =
+            split_reply_subject(&reply_to).context("reply subject is not a reply topic")?;
+        assert_eq!(topic, REPLY_TOPIC);
+        assert!(!correlation_id.is_empty());
+        assert!(pending_requests.lock().await.is_empty());
+        listener.stop().await
+    }
+
+    #[tokio::test]
+    async fn request_times_out_without_reply() -> Result<()> {
+        let (_cluster, config) = cluster()?;
+        let producer = build_producer(&config)?;
+        let pending_requests = PendingRequests::default();
+        let listener =
+            start_reply_listener(&config, REPLY_TOPIC.into(), Arc::clone(&pending_requests))
+                .await?;
+
+        let err = send_request(
+            &producer,
+            &pending_requests,
+            REPLY_TOPIC,
+            REQUEST_TOPIC,
+            b"ping",
+            Duration::from_millis(500),
+        )
+        .await
+        .expect_err("request without a responder should time out");
+        assert_eq!(err, "kafka request timed out after 500ms");
+        assert!(pending_requests.lock().await.is_empty());
+        listener.stop().await
+    }
+
+    #[test]
+    fn messages_without_correlation_reply_to_topic() {
+        let msg = KafkaMessage {
+            offset: 0,
+            key: Vec::default(),
+            value: Vec::default(),
+            headers: None,
+            ack: None,
+        };
+        assert_eq!(reply_subject(&msg, "orders"), "orders.reply");
     }
 }
@@ -271,6 +271,26 @@ pub trait Provider<E = anyhow::Error>: Sync {
         async { Ok(()) }
     }
 
+    /// Process a secrets update for the provider
+    ///
+    /// When a secret the provider was started with is rotated in its secrets backend,
+    /// the host fetches the secrets of the provider again and delivers all of them,
+    /// replacing the secrets provided at startup.
+    ///
+    /// Secrets provided with links are not included. Links referencing a rotated secret are
+    /// instead deleted and received again with their current secrets.
+    ///
+    /// # Arguments
+    ///
+    /// * `secrets` - The current secrets of the provider, by secret name
+    fn on_secrets_update(
+        &self,
+        secrets: &HashMap<String, SecretValue>,
+    ) -> impl Future<Output = Result<(), E>> + Send {
+        let _ = secrets;
+        async { Ok(()) }
+    }
+
     /// Receive and handle a link that has been established on the lattice where this provider is the source.
     ///
     /// Implement this when your provider needs to call other components.
@@ -114,6 +114,7 @@ macro_rules! initialize_observability {
                 $maybe_flamegraphs_path,
                 log_level.as_ref(),
                 Some(&otel_config.trace_level),
+                None,
             )
             .context("failed to configure observability")?;
             dispatch
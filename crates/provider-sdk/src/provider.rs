@@ -14,7 +14,7 @@ use async_nats::HeaderMap;
 use base64::Engine;
 use bytes::Bytes;
 use futures::{stream, Stream, StreamExt as _, TryStreamExt as _};
-use nkeys::XKey;
+use nkeys::{KeyPair, XKey};
 use once_cell::sync::OnceCell;
 use serde::{Deserialize, Serialize};
 use tokio::sync::{broadcast, mpsc, oneshot, RwLock};
@@ -22,11 +22,14 @@ use tokio::task::{spawn_blocking, JoinSet};
 use tokio::{select, spawn, try_join};
 use tracing::{debug, error, info, instrument, trace, warn, Instrument as _};
 use wasmcloud_core::nats::convert_header_map_to_hashmap;
-use wasmcloud_core::rpc::{health_subject, link_del_subject, link_put_subject, shutdown_subject};
+use wasmcloud_core::rpc::{
+    health_subject, link_del_subject, link_put_subject, provider_invocation_delegation_subject,
+    shutdown_subject, InvocationSigner, InvocationVerifier, VerifiedParams,
+};
 use wasmcloud_core::secrets::SecretValue;
 use wasmcloud_core::{
-    provider_config_update_subject, HealthCheckRequest, HealthCheckResponse, HostData,
-    InterfaceLinkDefinition, LatticeTarget,
+    provider_config_update_subject, provider_secrets_update_subject, HealthCheckRequest,
+    HealthCheckResponse, HostData, InterfaceLinkDefinition, LatticeTarget,
 };
 
 #[cfg(feature = "otel")]
@@ -370,12 +373,77 @@ async fn subscribe_config_update(
     Ok(config_update_rx)
 }
 
+/// Subscribe to secrets updates that are passed by the host.
+///
+/// Hosts publish the secrets of the provider again whenever one of them is rotated in its
+/// secrets backend. The secrets are encrypted with the provider xkey, so they are passed on
+/// as-is and decrypted when handled.
+async fn subscribe_secrets_update(
+    nats: Arc<async_nats::Client>,
+    mut quit: broadcast::Receiver<()>,
+    lattice: &str,
+    provider_key: &str,
+) -> ProviderInitResult<mpsc::Receiver<(Bytes, oneshot::Sender<()>)>> {
+    let (secrets_update_tx, secrets_update_rx) = mpsc::channel(1);
+    let mut sub = nats
+        .subscribe(provider_secrets_update_subject(lattice, provider_key).to_subject())
+        .await?;
+    spawn({
+        async move {
+            process_until_quit!(sub, quit, msg, {
+                let (tx, rx) = oneshot::channel();
+                if let Err(err) = secrets_update_tx.send((msg.payload, tx)).await {
+                    error!(%err, "failed to send secrets update");
+                    continue;
+                }
+                if let Err(err) = rx.await.as_ref() {
+                    error!(%err, "failed to receive secrets update response");
+                }
+            });
+        }
+        .instrument(tracing::debug_span!("subscribe_secrets_update"))
+    });
+
+    Ok(secrets_update_rx)
+}
+
+/// Subscribe to the renewed delegations of the invocation key of the provider that are passed by
+/// the host, and sign invocations with them from then on.
+///
+/// Delegations expire, so hosts renew them for as long as the provider runs.
+async fn subscribe_invocation_delegation(
+    nats: Arc<async_nats::Client>,
+    mut quit: broadcast::Receiver<()>,
+    lattice: &str,
+    provider_key: &str,
+    signer: InvocationSigner,
+) -> ProviderInitResult<()> {
+    let mut sub = nats
+        .subscribe(provider_invocation_delegation_subject(lattice, provider_key).to_subject())
+        .await?;
+    spawn({
+        async move {
+            process_until_quit!(sub, quit, msg, {
+                let renewed = core::str::from_utf8(&msg.payload)
+                    .context("invocation delegation is not valid UTF-8")
+                    .and_then(|delegation| signer.renew_delegation(delegation));
+                if let Err(err) = renewed {
+                    error!(%err, "failed to renew invocation delegation");
+                }
+            });
+        }
+        .instrument(tracing::debug_span!("subscribe_invocation_delegation"))
+    });
+    Ok(())
+}
+
 pub struct ProviderCommandReceivers {
     health: mpsc::Receiver<(HealthCheckRequest, oneshot::Sender<HealthCheckResponse>)>,
     shutdown: mpsc::Receiver<oneshot::Sender<()>>,
     link_put: mpsc::Receiver<(InterfaceLinkDefinition, oneshot::Sender<()>)>,
     link_del: mpsc::Receiver<(InterfaceLinkDefinition, oneshot::Sender<()>)>,
     config_update: mpsc::Receiver<(HashMap<String, String>, oneshot::Sender<()>)>,
+    secrets_update: mpsc::Receiver<(Bytes, oneshot::Sender<()>)>,
 }
 
 impl ProviderCommandReceivers {
@@ -387,7 +455,7 @@ impl ProviderCommandReceivers {
         provider_link_put_id: &str,
         host_id: &str,
     ) -> ProviderInitResult<Self> {
-        let (health, shutdown, link_put, link_del, config_update) = try_join!(
+        let (health, shutdown, link_put, link_del, config_update, secrets_update) = try_join!(
             subscribe_health(
                 Arc::clone(&nats),
                 quit_tx.subscribe(),
@@ -419,6 +487,12 @@ impl ProviderCommandReceivers {
                 lattice,
                 provider_key
             ),
+            subscribe_secrets_update(
+                Arc::clone(&nats),
+                quit_tx.subscribe(),
+                lattice,
+                provider_key
+            ),
         )?;
         Ok(Self {
             health,
@@ -426,6 +500,7 @@ impl ProviderCommandReceivers {
             link_put,
             link_del,
             config_update,
+            secrets_update,
         })
     }
 }
@@ -446,6 +521,10 @@ pub(crate) struct ProviderInitState {
     /// Do not attempt to access the [`XKey::seed()`] of this XKey, it will always error.
     host_public_xkey: XKey,
     provider_private_xkey: XKey,
+    /// The signer of invocation claims, if invocations are signed
+    invocation_signer: Option<InvocationSigner>,
+    /// The issuers invocation claims are accepted from, invocations are not verified if empty
+    invocation_issuers: Vec<String>,
 }
 
 #[instrument]
@@ -458,7 +537,7 @@ async fn init_provider(name: &str) -> ProviderInitResult<ProviderInitState> {
         lattice_rpc_url,
         provider_key,
         env_values: _,
-        cluster_issuers: _,
+        cluster_issuers,
         instance_id,
         link_definitions,
         config,
@@ -467,6 +546,8 @@ async fn init_provider(name: &str) -> ProviderInitResult<ProviderInitState> {
         link_name: _link_name,
         host_xkey_public_key,
         provider_xkey_private_key,
+        invocation_seed,
+        invocation_delegation,
         ..
     } = spawn_blocking(load_host_data).await.map_err(|e| {
         ProviderInitError::Initialization(format!("failed to load host data: {e}"))
@@ -497,6 +578,20 @@ async fn init_provider(name: &str) -> ProviderInitResult<ProviderInitState> {
         })?
     };
 
+    let invocation_signer = if invocation_seed.is_empty() {
+        None
+    } else {
+        let key = KeyPair::from_seed(invocation_seed).map_err(|e| {
+            ProviderInitError::Initialization(format!(
+                "failed to create invocation key from seed: {e}"
+            ))
+        })?;
+        Some(InvocationSigner::delegated(
+            key,
+            invocation_delegation.clone(),
+        ))
+    };
+
     // wasmCloud 1.1.0 hosts provide xkeys and publish links to the provider using the xkey public key in the NATS subject.
     // Older hosts will use the provider key in the NATS subject.
     // This allows for backwards compatibility with older hosts.
@@ -539,6 +634,17 @@ async fn init_provider(name: &str) -> ProviderInitResult<ProviderInitState> {
     .await?;
     let nats = Arc::new(nats);
 
+    if let Some(signer) = &invocation_signer {
+        subscribe_invocation_delegation(
+            Arc::clone(&nats),
+            quit_tx.subscribe(),
+            lattice_rpc_prefix,
+            provider_key,
+            signer.clone(),
+        )
+        .await?;
+    }
+
     // Listen and process various provider events/functionality
     let commands = ProviderCommandReceivers::new(
         Arc::clone(&nats),
@@ -561,6 +667,8 @@ async fn init_provider(name: &str) -> ProviderInitResult<ProviderInitState> {
         secrets: secrets.clone(),
         host_public_xkey,
         provider_private_xkey,
+        invocation_signer,
+        invocation_issuers: cluster_issuers.clone(),
         commands,
     })
 }
@@ -673,6 +781,7 @@ pub async fn handle_provider_commands(
         mut link_put,
         mut link_del,
         mut config_update,
+        mut secrets_update,
     }: ProviderCommandReceivers,
 ) {
     loop {
@@ -727,14 +836,32 @@ pub async fn handle_provider_commands(
             }
             req = link_put.recv() => {
                 if let Some((ld, tx)) = req {
-                    // If the link has already been put, return early
+                    // If the link has already been put, return early, unless the host put it again
+                    // because secrets it references were rotated
                     if connection.is_linked(&ld.source_id, &ld.target, &ld.wit_namespace, &ld.wit_package, &ld.name).await {
-                        warn!(
-                            source = &ld.source_id,
-                            target = &ld.target,
-                            link_name = &ld.name,
-                            "Ignoring duplicate link put"
-                        );
+                        if connection.link_secrets_changed(&ld).await {
+                            info!(
+                                source = &ld.source_id,
+                                target = &ld.target,
+                                link_name = &ld.name,
+                                "Relinking component with provider with rotated secrets"
+                            );
+                            // The provider is notified of the link with the rotated secrets as a new
+                            // link after the previous one is deleted
+                            if let Err(e) = delete_link_for_provider(&provider, connection, ld.clone()).await {
+                                error!(error = %e, "failed to delete link for provider");
+                            }
+                            if let Err(e) = receive_link_for_provider(&provider, connection, ld).await {
+                                error!(error = %e, "failed to receive link for provider");
+                            }
+                        } else {
+                            warn!(
+                                source = &ld.source_id,
+                                target = &ld.target,
+                                link_name = &ld.name,
+                                "Ignoring duplicate link put"
+                            );
+                        }
                     } else {
                         info!("Linking component with provider");
                         if let Err(e) = receive_link_for_provider(&provider, connection, ld).await {
@@ -797,6 +924,38 @@ pub async fn handle_provider_commands(
                     return
                 };
             }
+            req = secrets_update.recv() => {
+                if let Some((secrets, tx)) = req {
+                    // Notify the provider that some of its secrets have been rotated
+                    match decrypt_link_secret(
+                        Some(&secrets),
+                        &connection.provider_xkey,
+                        &connection.host_xkey,
+                    ) {
+                        Ok(secrets) => {
+                            if let Err(e) = provider.on_secrets_update(&secrets).await {
+                                error!(error = %e, "failed to pass through secrets update for provider");
+                            }
+                        }
+                        Err(e) => {
+                            error!(error = %e, "failed to decrypt secrets update");
+                        }
+                    }
+
+                    if tx.send(()).is_err() {
+                        error!("failed to send secrets update response");
+                    }
+                } else {
+                    error!("failed to handle secrets update, shutdown");
+                    if let Err(e) = provider.shutdown().await {
+                        error!(error = %e, "failed to shutdown provider");
+                    }
+                    if quit_tx.send(()).is_err() {
+                        error!("failed to send quit");
+                    };
+                    return
+                };
+            }
         }
     }
 }
@@ -829,6 +988,8 @@ pub async fn run_provider(
         secrets: _secrets,
         host_public_xkey: host_xkey,
         provider_private_xkey: provider_xkey,
+        invocation_signer,
+        invocation_issuers,
     } = init_state;
 
     let connection = ProviderConnection::new(
@@ -839,7 +1000,8 @@ pub async fn run_provider(
         config,
         provider_xkey,
         host_xkey,
-    )?;
+    )?
+    .with_invocation_claims(invocation_signer, invocation_issuers);
     CONNECTION.set(connection).map_err(|_| {
         ProviderInitError::Initialization("Provider connection was already initialized".to_string())
     })?;
@@ -946,6 +1108,11 @@ pub struct ProviderConnection {
     pub provider_xkey: Arc<XKey>,
     pub host_xkey: Arc<XKey>,
 
+    /// Signer of the invocation claims of outgoing invocations, if invocations are signed
+    pub invocation_signer: Option<InvocationSigner>,
+    /// Verifier of the invocation claims of incoming invocations, if invocations are verified
+    pub invocation_verifier: Option<Arc<InvocationVerifier>>,
+
     // TODO: Reference this field to get static config
     #[allow(unused)]
     pub config: HashMap<String, String>,
@@ -986,6 +1153,9 @@ pub struct WrpcClient {
     timeout: Duration,
     provider_id: Arc<str>,
     target: Arc<str>,
+    lattice: Arc<str>,
+    invocation_signer: Option<InvocationSigner>,
+    invocation_verifier: Option<Arc<InvocationVerifier>>,
 }
 
 impl wrpc_transport::Invoke for WrpcClient {
@@ -1007,6 +1177,17 @@ impl wrpc_transport::Invoke for WrpcClient {
         let mut headers = cx.unwrap_or_default();
         headers.insert("source-id", &*self.provider_id);
         headers.insert("target-id", &*self.target);
+        if let Some(signer) = &self.invocation_signer {
+            signer.sign(
+                &mut headers,
+                &self.lattice,
+                &self.provider_id,
+                &self.target,
+                instance,
+                func,
+                &params,
+            )?;
+        }
         self.nats
             .timeout(self.timeout)
             .invoke(Some(headers), instance, func, params, paths)
@@ -1017,7 +1198,8 @@ impl wrpc_transport::Invoke for WrpcClient {
 impl wrpc_transport::Serve for WrpcClient {
     type Context = Option<Context>;
     type Outgoing = <wrpc_transport_nats::Client as wrpc_transport::Serve>::Outgoing;
-    type Incoming = <wrpc_transport_nats::Client as wrpc_transport::Serve>::Incoming;
+    type Incoming =
+        VerifiedParams<<wrpc_transport_nats::Client as wrpc_transport::Serve>::Incoming>;
 
     async fn serve(
         &self,
@@ -1030,8 +1212,25 @@ impl wrpc_transport::Serve for WrpcClient {
             + 'static,
     > {
         let invocations = self.nats.serve(instance, func, paths).await?;
-        Ok(invocations.and_then(|(cx, tx, rx)| async move {
-            Ok((cx.as_ref().map(invocation_context), tx, rx))
+        let verifier = self.invocation_verifier.clone();
+        let target = Arc::clone(&self.target);
+        let instance: Arc<str> = Arc::from(instance);
+        let func: Arc<str> = Arc::from(func);
+        Ok(invocations.and_then(move |(cx, tx, rx)| {
+            let verifier = verifier.clone();
+            let target = Arc::clone(&target);
+            let instance = Arc::clone(&instance);
+            let func = Arc::clone(&func);
+            async move {
+                let rx = if let Some(verifier) = verifier {
+                    verifier
+                        .verify(cx.as_ref(), &target, &instance, &func, rx)
+                        .context("invocation is not authorized")?
+                } else {
+                    VerifiedParams::unverified(rx)
+                };
+                Ok((cx.as_ref().map(invocation_context), tx, rx))
+            }
         }))
     }
 }
@@ -1056,9 +1255,33 @@ impl ProviderConnection {
             config,
             provider_xkey: provider_private_xkey.into(),
             host_xkey: host_public_xkey.into(),
+            invocation_signer: None,
+            invocation_verifier: None,
         })
     }
 
+    /// Sign the invocation claims of outgoing invocations with `invocation_signer`, if provided,
+    /// and only accept incoming invocations carrying claims issued by, or delegated by, one of
+    /// `invocation_issuers`, if not empty
+    #[must_use]
+    pub fn with_invocation_claims(
+        self,
+        invocation_signer: Option<InvocationSigner>,
+        invocation_issuers: Vec<String>,
+    ) -> Self {
+        let invocation_verifier = (!invocation_issuers.is_empty()).then(|| {
+            Arc::new(InvocationVerifier::new(
+                Arc::clone(&self.lattice),
+                invocation_issuers,
+            ))
+        });
+        Self {
+            invocation_signer,
+            invocation_verifier,
+            ..self
+        }
+    }
+
     /// Retrieve a wRPC client that can be used based on the NATS client of this connection
     ///
     /// # Arguments
@@ -1092,6 +1315,9 @@ impl ProviderConnection {
             provider_id: Arc::clone(&self.provider_id),
             target: Arc::from(target),
             timeout: timeout.unwrap_or_else(|| Duration::from_secs(10)),
+            lattice: Arc::clone(&self.lattice),
+            invocation_signer: self.invocation_signer.clone(),
+            invocation_verifier: self.invocation_verifier.clone(),
         })
     }
 
@@ -1127,6 +1353,31 @@ impl ProviderConnection {
         }
     }
 
+    /// Returns true if the secrets of `ld` differ from those of the link stored for the same source
+    /// and target, which is the case when the host puts a link again after one of the secrets it
+    /// references was rotated
+    pub(crate) async fn link_secrets_changed(&self, ld: &InterfaceLinkDefinition) -> bool {
+        let (stored, secrets) = if ld.source_id == *self.provider_id {
+            let links = self.source_links.read().await;
+            let stored = links.get(&ld.target).and_then(|l| l.source_secrets.clone());
+            (stored, &ld.source_secrets)
+        } else {
+            let links = self.target_links.read().await;
+            let stored = links
+                .get(&ld.source_id)
+                .and_then(|l| l.target_secrets.clone());
+            (stored, &ld.target_secrets)
+        };
+        // Secrets are encrypted anew every time a link is put, so they are compared decrypted
+        let decrypt = |secrets: Option<&[u8]>| {
+            decrypt_link_secret(secrets, &self.provider_xkey, &self.host_xkey).ok()
+        };
+        match (decrypt(stored.as_deref()), decrypt(secrets.as_deref())) {
+            (Some(stored), Some(secrets)) => stored != secrets,
+            _ => stored != *secrets,
+        }
+    }
+
     /// Returns true if the source is linked to this provider or if the provider is linked to the target
     /// on the given interface and link name
     pub async fn is_linked(
@@ -25,15 +25,29 @@ use uuid::Uuid;
 // Bindgen happens here
 wit_bindgen_wrpc::generate!({
   with: {
-      "wasmcloud:postgres/types@0.1.1-draft": generate,
+      "wasmcloud:postgres/types@0.2.0-draft": generate,
+      "wasmcloud:postgres/query@0.2.0-draft": generate,
+      "wasmcloud:postgres/prepared@0.2.0-draft": generate,
+      "wasmcloud:postgres/transaction@0.2.0-draft": generate,
+      "wasmcloud:postgres/streaming@0.2.0-draft": generate,
+      "wasmcloud:postgres/notification-handler@0.2.0-draft": generate,
+      // The types are unchanged since 0.1.1-draft, so they are shared by both versions
+      "wasmcloud:postgres/types@0.1.1-draft": crate::bindings::wasmcloud::postgres0_2_0_draft::types,
       "wasmcloud:postgres/query@0.1.1-draft": generate,
       "wasmcloud:postgres/prepared@0.1.1-draft": generate,
   },
 });
 
 // Start bindgen-generated type imports
-pub(crate) use exports::wasmcloud::postgres::prepared;
-pub(crate) use exports::wasmcloud::postgres::query;
+pub(crate) use exports::wasmcloud::postgres0_2_0_draft::prepared;
+pub(crate) use exports::wasmcloud::postgres0_2_0_draft::query;
+pub(crate) use exports::wasmcloud::postgres0_2_0_draft::streaming;
+pub(crate) use exports::wasmcloud::postgres0_2_0_draft::transaction;
+pub(crate) use wasmcloud::postgres0_2_0_draft::notification_handler;
+
+pub(crate) use exports::wasmcloud::postgres0_1_1_draft::{
+    prepared as prepared0_1_1_draft, query as query0_1_1_draft,
+};
 
 pub(crate) use query::{PgValue, QueryError, ResultRow};
 
@@ -41,7 +55,9 @@ pub(crate) use prepared::{
     PreparedStatementExecError, PreparedStatementToken, StatementPrepareError,
 };
 
-use crate::bindings::wasmcloud::postgres::types::{
+pub(crate) use transaction::TransactionToken;
+
+use crate::bindings::wasmcloud::postgres0_2_0_draft::types::{
     Date, HashableF64, MacAddressEui48, MacAddressEui64, Numeric, Offset, ResultRowEntry, Time,
     Timestamp, TimestampTz,
 };
@@ -1,8 +1,15 @@
+use core::time::Duration;
+
+use std::collections::HashMap;
+
 use tracing::warn;
 use wasmcloud_provider_sdk::{core::secrets::SecretValue, LinkConfig};
 
 const POSTGRES_DEFAULT_PORT: u16 = 5432;
 
+/// Time after which a transaction that has not been used is rolled back, unless configured
+pub(crate) const DEFAULT_TRANSACTION_IDLE_TIMEOUT: Duration = Duration::from_secs(60);
+
 /// Creation options for a Postgres connection
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub(crate) struct ConnectionCreateOptions {
@@ -93,3 +100,80 @@ pub(crate) fn extract_prefixed_conn_config(
         }
     }
 }
+
+/// Parse the channels to `LISTEN` on from the comma-separated `{prefix}LISTEN_CHANNELS` key
+pub(crate) fn extract_prefixed_listen_channels(
+    prefix: &str,
+    config: &HashMap<String, String>,
+) -> Vec<String> {
+    config
+        .get(&format!("{prefix}LISTEN_CHANNELS"))
+        .map(|channels| {
+            channels
+                .split(',')
+                .map(str::trim)
+                .filter(|channel| !channel.is_empty())
+                .map(String::from)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Parse how long a transaction may go unused before it is rolled back, in seconds, from the
+/// `{prefix}TRANSACTION_IDLE_TIMEOUT_SECS` key
+pub(crate) fn extract_prefixed_transaction_idle_timeout(
+    prefix: &str,
+    config: &HashMap<String, String>,
+) -> Duration {
+    let key = format!("{prefix}TRANSACTION_IDLE_TIMEOUT_SECS");
+    match config.get(&key).map(|v| v.trim().parse::<u64>()) {
+        Some(Ok(secs)) if secs > 0 => Duration::from_secs(secs),
+        Some(_) => {
+            warn!(
+                "invalid [{key}] value, using {}s",
+                DEFAULT_TRANSACTION_IDLE_TIMEOUT.as_secs()
+            );
+            DEFAULT_TRANSACTION_IDLE_TIMEOUT
+        }
+        None => DEFAULT_TRANSACTION_IDLE_TIMEOUT,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Parse the transaction idle timeout from config with the given value for it
+    fn idle_timeout(value: Option<&str>) -> Duration {
+        let config = value
+            .map(|v| ("POSTGRES_TRANSACTION_IDLE_TIMEOUT_SECS".into(), v.into()))
+            .into_iter()
+            .collect();
+        extract_prefixed_transaction_idle_timeout("POSTGRES_", &config)
+    }
+
+    #[test]
+    fn listen_channels() {
+        let channels = |value: &str| {
+            let config = HashMap::from([("POSTGRES_LISTEN_CHANNELS".into(), value.into())]);
+            extract_prefixed_listen_channels("POSTGRES_", &config)
+        };
+        assert!(extract_prefixed_listen_channels("POSTGRES_", &HashMap::new()).is_empty());
+        assert!(channels("").is_empty());
+        assert_eq!(channels("jobs"), ["jobs"]);
+        assert_eq!(
+            channels(" cache_invalidation, jobs ,,Mixed Case "),
+            ["cache_invalidation", "jobs", "Mixed Case"]
+        );
+    }
+
+    #[test]
+    fn transaction_idle_timeout() {
+        assert_eq!(idle_timeout(None), DEFAULT_TRANSACTION_IDLE_TIMEOUT);
+        assert_eq!(idle_timeout(Some("5")), Duration::from_secs(5));
+        assert_eq!(idle_timeout(Some(" 300 ")), Duration::from_secs(300));
+        assert_eq!(idle_timeout(Some("0")), DEFAULT_TRANSACTION_IDLE_TIMEOUT);
+        assert_eq!(idle_timeout(Some("-1")), DEFAULT_TRANSACTION_IDLE_TIMEOUT);
+        assert_eq!(idle_timeout(Some("1m")), DEFAULT_TRANSACTION_IDLE_TIMEOUT);
+    }
+}
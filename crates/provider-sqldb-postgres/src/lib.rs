@@ -7,15 +7,20 @@
 //! use different connections and can run in parallel.
 //!
 
+use core::pin::Pin;
+use core::time::Duration;
+
 use std::collections::HashMap;
 use std::sync::Arc;
 
 use anyhow::{Context as _, Result};
-use deadpool_postgres::Pool;
-use futures::TryStreamExt as _;
-use tokio::sync::RwLock;
+use deadpool_postgres::{Object, Pool};
+use futures::{Stream, StreamExt as _, TryStreamExt as _};
+use tokio::sync::{Mutex, RwLock};
+use tokio::task::JoinHandle;
+use tokio::time::Instant;
 use tokio_postgres::Statement;
-use tracing::{error, instrument, warn};
+use tracing::{debug, error, instrument, warn, Instrument as _};
 use ulid::Ulid;
 
 use wasmcloud_provider_sdk::{
@@ -26,20 +31,55 @@ use wasmcloud_provider_sdk::{initialize_observability, serve_provider_exports};
 mod bindings;
 use bindings::{
     into_result_row, PgValue, PreparedStatementExecError, PreparedStatementToken, QueryError,
-    ResultRow, StatementPrepareError,
+    ResultRow, StatementPrepareError, TransactionToken,
 };
 
 mod config;
-use config::{extract_prefixed_conn_config, ConnectionCreateOptions};
+use config::{
+    extract_prefixed_conn_config, extract_prefixed_listen_channels,
+    extract_prefixed_transaction_idle_timeout, ConnectionCreateOptions,
+    DEFAULT_TRANSACTION_IDLE_TIMEOUT,
+};
+
+mod notifications;
 
 use wasmcloud_provider_sdk::Context;
 
+/// Number of rows sent at a time by [`bindings::streaming::Handler::query_stream`]
+const STREAM_CHUNK_SIZE: usize = 64;
+
+/// A pooled connection held by an open transaction
+struct TransactionConnection {
+    client: Object,
+    /// When a statement last finished running in the transaction
+    last_used: Instant,
+}
+
+/// A transaction that has been started, but not yet committed or rolled back
+struct OpenTransaction {
+    connection: Arc<Mutex<TransactionConnection>>,
+    /// The source ID that started the transaction
+    source_id: String,
+}
+
+/// Rows of a streamed query, sent in chunks
+type RowStream = Pin<Box<dyn Stream<Item = Vec<Result<ResultRow, QueryError>>> + Send>>;
+
+/// Tasks forwarding notifications, indexed by the target ID and link name they deliver to
+type Listeners = HashMap<(String, String), JoinHandle<()>>;
+
 #[derive(Clone, Default)]
 pub struct PostgresProvider {
     /// Database connections indexed by source ID name
     connections: Arc<RwLock<HashMap<String, Pool>>>,
     /// Lookup of prepared statements to the statement and the source ID that prepared them
     prepared_statements: Arc<RwLock<HashMap<PreparedStatementToken, (Statement, String)>>>,
+    /// Lookup of open transactions to their connection and the source ID that started them
+    transactions: Arc<RwLock<HashMap<TransactionToken, OpenTransaction>>>,
+    /// Time after which an unused transaction is rolled back, indexed by source ID
+    transaction_idle_timeouts: Arc<RwLock<HashMap<String, Duration>>>,
+    /// Tasks forwarding notifications to components
+    listeners: Arc<RwLock<Listeners>>,
 }
 
 impl PostgresProvider {
@@ -212,6 +252,227 @@ impl PostgresProvider {
 
         Ok(rows_affected)
     }
+
+    /// Start a transaction on a connection taken from the pool, which is held until the
+    /// transaction ends
+    async fn do_transaction_begin(&self, source_id: &str) -> Result<TransactionToken, QueryError> {
+        let connections = self.connections.read().await;
+        let pool = connections.get(source_id).ok_or_else(|| {
+            QueryError::Unexpected(format!(
+                "missing connection pool for source [{source_id}] while starting transaction"
+            ))
+        })?;
+
+        let client = pool.get().await.map_err(|e| {
+            QueryError::Unexpected(format!("failed to build client from pool: {e}"))
+        })?;
+        client
+            .batch_execute("BEGIN")
+            .await
+            .map_err(|e| QueryError::Unexpected(format!("failed to start transaction: {e}")))?;
+
+        let tx_token = format!("transaction-{}", Ulid::new().to_string());
+        let mut transactions = self.transactions.write().await;
+        transactions.insert(
+            tx_token.clone(),
+            OpenTransaction {
+                connection: Arc::new(Mutex::new(TransactionConnection {
+                    client,
+                    last_used: Instant::now(),
+                })),
+                source_id: source_id.into(),
+            },
+        );
+        drop(transactions);
+
+        let idle_timeout = self
+            .transaction_idle_timeouts
+            .read()
+            .await
+            .get(source_id)
+            .copied()
+            .unwrap_or(DEFAULT_TRANSACTION_IDLE_TIMEOUT);
+        tokio::spawn(
+            self.clone()
+                .expire_idle_transaction(tx_token.clone(), idle_timeout)
+                .in_current_span(),
+        );
+        Ok(tx_token)
+    }
+
+    /// Roll back a transaction once no statement has run in it for `idle_timeout`, so that
+    /// transactions abandoned by components do not hold on to pooled connections
+    async fn expire_idle_transaction(self, tx_token: TransactionToken, idle_timeout: Duration) {
+        loop {
+            let mut transactions = self.transactions.write().await;
+            let Some(tx) = transactions.get(&tx_token) else {
+                // The transaction has ended
+                return;
+            };
+            let deadline = match tx.connection.try_lock() {
+                Ok(connection) => connection.last_used + idle_timeout,
+                // A statement is running in the transaction, so it is not idle
+                Err(_) => Instant::now() + idle_timeout,
+            };
+            if deadline <= Instant::now() {
+                if let Some(tx) = transactions.remove(&tx_token) {
+                    drop(transactions);
+                    warn!(
+                        tx_token,
+                        source_id = tx.source_id,
+                        ?idle_timeout,
+                        "rolling back idle transaction"
+                    );
+                    rollback_transactions([tx]);
+                }
+                return;
+            }
+            drop(transactions);
+            tokio::time::sleep_until(deadline).await;
+        }
+    }
+
+    /// Look up the connection of an open transaction started by the given source
+    async fn transaction_client(
+        &self,
+        source_id: &str,
+        tx_token: &str,
+    ) -> Result<Arc<Mutex<TransactionConnection>>, QueryError> {
+        let transactions = self.transactions.read().await;
+        match transactions.get(tx_token) {
+            Some(tx) if tx.source_id == source_id => Ok(Arc::clone(&tx.connection)),
+            _ => Err(QueryError::Unexpected(format!(
+                "missing transaction with token [{tx_token}]"
+            ))),
+        }
+    }
+
+    /// Perform a query in a transaction
+    async fn do_transaction_query(
+        &self,
+        source_id: &str,
+        tx_token: &str,
+        query: &str,
+        params: Vec<PgValue>,
+    ) -> Result<Vec<ResultRow>, QueryError> {
+        let connection = self.transaction_client(source_id, tx_token).await?;
+        let mut connection = connection.lock().await;
+        let rows = match connection.client.query_raw(query, params).await {
+            Ok(rows) => rows
+                .map_ok(into_result_row)
+                .try_collect::<Vec<_>>()
+                .await
+                .map_err(|e| QueryError::Unexpected(format!("failed to evaluate full row: {e}"))),
+            Err(e) => Err(QueryError::Unexpected(format!(
+                "failed to perform query: {e}"
+            ))),
+        };
+        connection.last_used = Instant::now();
+        rows
+    }
+
+    /// Perform a raw query in a transaction
+    async fn do_transaction_query_batch(
+        &self,
+        source_id: &str,
+        tx_token: &str,
+        query: &str,
+    ) -> Result<(), QueryError> {
+        let connection = self.transaction_client(source_id, tx_token).await?;
+        let mut connection = connection.lock().await;
+        let res = connection
+            .client
+            .batch_execute(query)
+            .await
+            .map_err(|e| QueryError::Unexpected(format!("failed to perform query: {e}")));
+        connection.last_used = Instant::now();
+        res
+    }
+
+    /// End a transaction with `COMMIT` or `ROLLBACK`, returning its connection to the pool
+    async fn do_transaction_end(
+        &self,
+        source_id: &str,
+        tx_token: &str,
+        statement: &str,
+    ) -> Result<(), QueryError> {
+        // Ensure the transaction belongs to the source before removing it
+        self.transaction_client(source_id, tx_token).await?;
+        let Some(tx) = self.transactions.write().await.remove(tx_token) else {
+            return Err(QueryError::Unexpected(format!(
+                "missing transaction with token [{tx_token}]"
+            )));
+        };
+        let connection = tx.connection.lock().await;
+        connection
+            .client
+            .batch_execute(statement)
+            .await
+            .map_err(|e| {
+                QueryError::Unexpected(format!(
+                    "failed to end transaction with token [{tx_token}]: {e}"
+                ))
+            })
+    }
+
+    /// Perform a query, streaming rows as they are read
+    async fn do_query_stream(
+        &self,
+        source_id: &str,
+        query: &str,
+        params: Vec<PgValue>,
+    ) -> Result<impl Stream<Item = Vec<Result<ResultRow, QueryError>>>, QueryError> {
+        let connections = self.connections.read().await;
+        let pool = connections.get(source_id).ok_or_else(|| {
+            QueryError::Unexpected(format!(
+                "missing connection pool for source [{source_id}] while querying"
+            ))
+        })?;
+
+        let client = pool.get().await.map_err(|e| {
+            QueryError::Unexpected(format!("failed to build client from pool: {e}"))
+        })?;
+
+        let rows = client
+            .query_raw(query, params)
+            .await
+            .map_err(|e| QueryError::Unexpected(format!("failed to perform query: {e}")))?;
+
+        // The client is moved into the stream, so the connection is only returned to the pool
+        // once all rows have been sent
+        let mut failed = false;
+        Ok(rows
+            .map(move |row| {
+                let _ = &client;
+                row.map(into_result_row)
+                    .map_err(|e| QueryError::Unexpected(format!("failed to evaluate row: {e}")))
+            })
+            .take_while(move |row| {
+                let done = failed;
+                failed = row.is_err();
+                futures::future::ready(!done)
+            })
+            .ready_chunks(STREAM_CHUNK_SIZE))
+    }
+}
+
+/// Roll back transactions that were not ended by their component, in the background
+///
+/// Each rollback waits for a statement still running in the transaction to finish, and the
+/// connection goes back to the pool once it has been rolled back. A rollback only fails if the
+/// connection was lost, in which case the pool discards the connection instead of reusing it.
+fn rollback_transactions(transactions: impl IntoIterator<Item = OpenTransaction>) {
+    for OpenTransaction { connection, .. } in transactions {
+        tokio::spawn(
+            async move {
+                let connection = connection.lock().await;
+                if let Err(error) = connection.client.batch_execute("ROLLBACK").await {
+                    warn!(?error, "failed to roll back transaction");
+                }
+            }
+            .in_current_span(),
+        );
+    }
 }
 
 impl Provider for PostgresProvider {
@@ -235,7 +496,67 @@ impl Provider for PostgresProvider {
         if let Err(error) = self.ensure_pool(source_id, db_cfg).await {
             error!(?error, source_id, "failed to create connection");
         };
+        let idle_timeout =
+            extract_prefixed_transaction_idle_timeout("POSTGRES_", link_config.config);
+        self.transaction_idle_timeouts
+            .write()
+            .await
+            .insert(source_id.into(), idle_timeout);
+
+        Ok(())
+    }
+
+    /// Handle being linked to a target component, which receives notifications
+    ///
+    /// The link configuration is expected to contain the same `POSTGRES_*` keys used for links
+    /// from components, along with a comma-separated list of channels to `LISTEN` on in
+    /// `POSTGRES_LISTEN_CHANNELS`. Notifications on those channels are delivered to the component
+    /// via `wasmcloud:postgres/notification-handler`.
+    #[instrument(level = "debug", skip_all, fields(target_id))]
+    async fn receive_link_config_as_source(
+        &self,
+        link_config @ LinkConfig {
+            target_id,
+            link_name,
+            ..
+        }: LinkConfig<'_>,
+    ) -> anyhow::Result<()> {
+        let Some(db_cfg) = extract_prefixed_conn_config("POSTGRES_", &link_config) else {
+            warn!(target_id, "no link-level DB configuration");
+            return Ok(());
+        };
+        let channels = extract_prefixed_listen_channels("POSTGRES_", link_config.config);
+        if channels.is_empty() {
+            warn!(target_id, "no channels to listen on");
+            return Ok(());
+        }
 
+        let wrpc = get_connection()
+            .get_wrpc_client(target_id)
+            .await
+            .context("failed to construct wRPC client")?;
+        let task = tokio::spawn(notifications::listen(db_cfg, channels, wrpc).in_current_span());
+
+        let mut listeners = self.listeners.write().await;
+        if let Some(previous) =
+            listeners.insert((target_id.to_string(), link_name.to_string()), task)
+        {
+            previous.abort();
+        }
+        Ok(())
+    }
+
+    /// Stop delivering notifications to the target component
+    #[instrument(level = "info", skip_all, fields(target_id = info.get_target_id()))]
+    async fn delete_link_as_source(&self, info: impl LinkDeleteInfo) -> anyhow::Result<()> {
+        let key = (
+            info.get_target_id().to_string(),
+            info.get_link_name().to_string(),
+        );
+        if let Some(task) = self.listeners.write().await.remove(&key) {
+            task.abort();
+        }
+        debug!(component_id = key.0, "stopped listening for component");
         Ok(())
     }
 
@@ -248,6 +569,17 @@ impl Provider for PostgresProvider {
         let mut prepared_statements = self.prepared_statements.write().await;
         prepared_statements.retain(|_stmt_token, (_conn, src_id)| component_id != *src_id);
         drop(prepared_statements);
+        let mut transactions = self.transactions.write().await;
+        let (ended, open) = transactions
+            .drain()
+            .partition(|(_tx_token, tx)| component_id == tx.source_id);
+        *transactions = open;
+        drop(transactions);
+        rollback_transactions(ended.into_values());
+        self.transaction_idle_timeouts
+            .write()
+            .await
+            .remove(component_id);
         let mut connections = self.connections.write().await;
         connections.remove(component_id);
         drop(connections);
@@ -259,8 +591,13 @@ impl Provider for PostgresProvider {
     async fn shutdown(&self) -> anyhow::Result<()> {
         let mut prepared_statements = self.prepared_statements.write().await;
         prepared_statements.drain();
+        let mut transactions = self.transactions.write().await;
+        rollback_transactions(transactions.drain().map(|(_, tx)| tx));
         let mut connections = self.connections.write().await;
         connections.drain();
+        for (_, task) in self.listeners.write().await.drain() {
+            task.abort();
+        }
         Ok(())
     }
 }
@@ -342,19 +679,369 @@ impl bindings::prepared::Handler<Option<Context>> for PostgresProvider {
     }
 }
 
+/// Implement the `wasmcloud:postgres/query@0.1.1-draft` interface for [`PostgresProvider`], for
+/// components built against the previous version of the package
+impl bindings::query0_1_1_draft::Handler<Option<Context>> for PostgresProvider {
+    async fn query(
+        &self,
+        ctx: Option<Context>,
+        query: String,
+        params: Vec<PgValue>,
+    ) -> Result<Result<Vec<ResultRow>, QueryError>> {
+        bindings::query::Handler::query(self, ctx, query, params).await
+    }
+
+    async fn query_batch(
+        &self,
+        ctx: Option<Context>,
+        query: String,
+    ) -> Result<Result<(), QueryError>> {
+        bindings::query::Handler::query_batch(self, ctx, query).await
+    }
+}
+
+/// Implement the `wasmcloud:postgres/prepared@0.1.1-draft` interface for [`PostgresProvider`],
+/// for components built against the previous version of the package
+impl bindings::prepared0_1_1_draft::Handler<Option<Context>> for PostgresProvider {
+    async fn prepare(
+        &self,
+        ctx: Option<Context>,
+        query: String,
+    ) -> Result<Result<PreparedStatementToken, StatementPrepareError>> {
+        bindings::prepared::Handler::prepare(self, ctx, query).await
+    }
+
+    async fn exec(
+        &self,
+        ctx: Option<Context>,
+        statement_token: PreparedStatementToken,
+        params: Vec<PgValue>,
+    ) -> Result<Result<u64, PreparedStatementExecError>> {
+        bindings::prepared::Handler::exec(self, ctx, statement_token, params).await
+    }
+}
+
+/// Implement the `wasmcloud:postgres/transaction` interface for [`PostgresProvider`]
+impl bindings::transaction::Handler<Option<Context>> for PostgresProvider {
+    #[instrument(level = "debug", skip_all)]
+    async fn begin(&self, ctx: Option<Context>) -> Result<Result<TransactionToken, QueryError>> {
+        propagate_trace_for_ctx!(ctx);
+        let Some(Context {
+            component: Some(source_id),
+            ..
+        }) = ctx
+        else {
+            return Ok(Err(QueryError::Unexpected(
+                "unexpectedly missing source ID".into(),
+            )));
+        };
+        Ok(self.do_transaction_begin(&source_id).await)
+    }
+
+    #[instrument(level = "debug", skip_all, fields(tx_token, query))]
+    async fn query(
+        &self,
+        ctx: Option<Context>,
+        tx_token: TransactionToken,
+        query: String,
+        params: Vec<PgValue>,
+    ) -> Result<Result<Vec<ResultRow>, QueryError>> {
+        propagate_trace_for_ctx!(ctx);
+        let Some(Context {
+            component: Some(source_id),
+            ..
+        }) = ctx
+        else {
+            return Ok(Err(QueryError::Unexpected(
+                "unexpectedly missing source ID".into(),
+            )));
+        };
+        Ok(self
+            .do_transaction_query(&source_id, &tx_token, &query, params)
+            .await)
+    }
+
+    #[instrument(level = "debug", skip_all, fields(tx_token, query))]
+    async fn query_batch(
+        &self,
+        ctx: Option<Context>,
+        tx_token: TransactionToken,
+        query: String,
+    ) -> Result<Result<(), QueryError>> {
+        propagate_trace_for_ctx!(ctx);
+        let Some(Context {
+            component: Some(source_id),
+            ..
+        }) = ctx
+        else {
+            return Ok(Err(QueryError::Unexpected(
+                "unexpectedly missing source ID".into(),
+            )));
+        };
+        Ok(self
+            .do_transaction_query_batch(&source_id, &tx_token, &query)
+            .await)
+    }
+
+    #[instrument(level = "debug", skip_all, fields(tx_token))]
+    async fn commit(
+        &self,
+        ctx: Option<Context>,
+        tx_token: TransactionToken,
+    ) -> Result<Result<(), QueryError>> {
+        propagate_trace_for_ctx!(ctx);
+        let Some(Context {
+            component: Some(source_id),
+            ..
+        }) = ctx
+        else {
+            return Ok(Err(QueryError::Unexpected(
+                "unexpectedly missing source ID".into(),
+            )));
+        };
+        Ok(self
+            .do_transaction_end(&source_id, &tx_token, "COMMIT")
+            .await)
+    }
+
+    #[instrument(level = "debug", skip_all, fields(tx_token))]
+    async fn rollback(
+        &self,
+        ctx: Option<Context>,
+        tx_token: TransactionToken,
+    ) -> Result<Result<(), QueryError>> {
+        propagate_trace_for_ctx!(ctx);
+        let Some(Context {
+            component: Some(source_id),
+            ..
+        }) = ctx
+        else {
+            return Ok(Err(QueryError::Unexpected(
+                "unexpectedly missing source ID".into(),
+            )));
+        };
+        Ok(self
+            .do_transaction_end(&source_id, &tx_token, "ROLLBACK")
+            .await)
+    }
+}
+
+/// Implement the `wasmcloud:postgres/streaming` interface for [`PostgresProvider`]
+impl bindings::streaming::Handler<Option<Context>> for PostgresProvider {
+    #[instrument(level = "debug", skip_all, fields(query))]
+    async fn query_stream(
+        &self,
+        ctx: Option<Context>,
+        query: String,
+        params: Vec<PgValue>,
+    ) -> Result<Result<RowStream, QueryError>> {
+        propagate_trace_for_ctx!(ctx);
+        let Some(Context {
+            component: Some(source_id),
+            ..
+        }) = ctx
+        else {
+            return Ok(Err(QueryError::Unexpected(
+                "unexpectedly missing source ID".into(),
+            )));
+        };
+        Ok(self
+            .do_query_stream(&source_id, &query, params)
+            .await
+            .map(|rows| Box::pin(rows) as RowStream))
+    }
+}
+
 fn create_tls_pool(
     cfg: deadpool_postgres::Config,
     runtime: Option<deadpool_postgres::Runtime>,
 ) -> Result<Pool> {
+    cfg.create_pool(runtime, tls_connector())
+        .context("failed to create TLS-enabled connection pool")
+}
+
+/// Build a TLS connector trusting the Mozilla root certificates
+fn tls_connector() -> tokio_postgres_rustls::MakeRustlsConnect {
     let mut store = rustls::RootCertStore::empty();
     store.extend(webpki_roots::TLS_SERVER_ROOTS.iter().cloned());
-    cfg.create_pool(
-        runtime,
-        tokio_postgres_rustls::MakeRustlsConnect::new(
-            rustls::ClientConfig::builder()
-                .with_root_certificates(store)
-                .with_no_client_auth(),
-        ),
+    tokio_postgres_rustls::MakeRustlsConnect::new(
+        rustls::ClientConfig::builder()
+            .with_root_certificates(store)
+            .with_no_client_auth(),
     )
-    .context("failed to create TLS-enabled connection pool")
+}
+
+#[cfg(test)]
+mod test {
+    //! NOTE: these tests start a Postgres server using docker. To use an existing server instead,
+    //! set `PGHOST`, `PGPORT` and `PGPASSWORD` for its `postgres` superuser.
+
+    use std::env;
+
+    use anyhow::Context as _;
+    use wasmcloud_provider_sdk::InterfaceLinkDefinition;
+    use wasmcloud_test_util::testcontainers::{
+        AsyncRunner as _, ContainerAsync, Postgres, POSTGRES_PASSWORD,
+    };
+
+    use super::*;
+
+    const SOURCE_ID: &str = "test-component";
+
+    /// Start Postgres, or use the server configured in the environment, returning the options to
+    /// connect to it as the `postgres` superuser
+    pub(crate) async fn start_postgres(
+    ) -> anyhow::Result<(ConnectionCreateOptions, Option<ContainerAsync<Postgres>>)> {
+        let (host, port, password, container) = if let Ok(host) = env::var("PGHOST") {
+            let port = env::var("PGPORT").map_or(Ok(5432), |port| port.parse())?;
+            let password = env::var("PGPASSWORD").context("PGPASSWORD must be set")?;
+            (host, port, password, None)
+        } else {
+            let node = Postgres::default()
+                .start()
+                .await
+                .context("should have started postgres")?;
+            let host = node.get_host().await?.to_string();
+            let port = node.get_host_port_ipv4(5432).await?;
+            (host, port, POSTGRES_PASSWORD.to_string(), Some(node))
+        };
+        let create_opts = ConnectionCreateOptions {
+            host,
+            port,
+            username: "postgres".into(),
+            password,
+            database: "postgres".into(),
+            tls_required: false,
+        };
+        Ok((create_opts, container))
+    }
+
+    /// Connect a [`PostgresProvider`] to Postgres for [`SOURCE_ID`], with the given transaction
+    /// idle timeout
+    async fn connect(
+        idle_timeout: Duration,
+    ) -> anyhow::Result<(PostgresProvider, Option<ContainerAsync<Postgres>>)> {
+        let (create_opts, container) = start_postgres().await?;
+        let provider = PostgresProvider::default();
+        provider.ensure_pool(SOURCE_ID, create_opts).await?;
+        provider
+            .transaction_idle_timeouts
+            .write()
+            .await
+            .insert(SOURCE_ID.into(), idle_timeout);
+        Ok((provider, container))
+    }
+
+    /// Create a table with a unique name, so that tests can share a database
+    async fn create_table(provider: &PostgresProvider) -> anyhow::Result<String> {
+        let table = format!("test_{}", Ulid::new().to_string().to_lowercase());
+        provider
+            .do_query_batch(SOURCE_ID, &format!("CREATE TABLE {table} (id INT)"))
+            .await
+            .map_err(|e| anyhow::anyhow!("failed to create table: {e:?}"))?;
+        Ok(table)
+    }
+
+    /// Count the rows of a table outside of any transaction
+    async fn count_rows(provider: &PostgresProvider, table: &str) -> anyhow::Result<usize> {
+        provider
+            .do_query(SOURCE_ID, &format!("SELECT id FROM {table}"), vec![])
+            .await
+            .map(|rows| rows.len())
+            .map_err(|e| anyhow::anyhow!("failed to query table: {e:?}"))
+    }
+
+    /// Wait until every connection of the pool is idle again
+    async fn wait_for_idle_pool(provider: &PostgresProvider) -> anyhow::Result<()> {
+        tokio::time::timeout(Duration::from_secs(5), async {
+            loop {
+                let status = provider.connections.read().await[SOURCE_ID].status();
+                if status.available == status.size {
+                    return;
+                }
+                tokio::time::sleep(Duration::from_millis(50)).await;
+            }
+        })
+        .await
+        .context("connection was not returned to the pool")
+    }
+
+    #[tokio::test]
+    async fn idle_transaction_is_rolled_back() -> anyhow::Result<()> {
+        let (provider, _container) = connect(Duration::from_secs(1)).await?;
+        let table = create_table(&provider).await?;
+
+        let tx = provider.do_transaction_begin(SOURCE_ID).await?;
+        provider
+            .do_transaction_query_batch(SOURCE_ID, &tx, &format!("INSERT INTO {table} VALUES (1)"))
+            .await?;
+        tokio::time::sleep(Duration::from_secs(2)).await;
+
+        assert!(provider.transactions.read().await.is_empty());
+        assert!(provider
+            .do_transaction_end(SOURCE_ID, &tx, "COMMIT")
+            .await
+            .is_err());
+        wait_for_idle_pool(&provider).await?;
+        assert_eq!(count_rows(&provider, &table).await?, 0);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn active_transaction_is_kept_open() -> anyhow::Result<()> {
+        let (provider, _container) = connect(Duration::from_secs(1)).await?;
+        let table = create_table(&provider).await?;
+
+        let tx = provider.do_transaction_begin(SOURCE_ID).await?;
+        for id in 0..4 {
+            provider
+                .do_transaction_query(
+                    SOURCE_ID,
+                    &tx,
+                    &format!("INSERT INTO {table} VALUES ($1)"),
+                    vec![PgValue::Int(id)],
+                )
+                .await?;
+            tokio::time::sleep(Duration::from_millis(500)).await;
+        }
+        provider
+            .do_transaction_end(SOURCE_ID, &tx, "COMMIT")
+            .await?;
+        assert_eq!(count_rows(&provider, &table).await?, 4);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn unlinked_transactions_are_rolled_back() -> anyhow::Result<()> {
+        let (provider, _container) = connect(Duration::from_secs(60)).await?;
+        let table = create_table(&provider).await?;
+
+        let tx = provider.do_transaction_begin(SOURCE_ID).await?;
+        provider
+            .do_transaction_query_batch(SOURCE_ID, &tx, &format!("INSERT INTO {table} VALUES (1)"))
+            .await?;
+        let pool = provider.connections.read().await[SOURCE_ID].clone();
+        provider
+            .delete_link_as_target(&InterfaceLinkDefinition {
+                source_id: SOURCE_ID.into(),
+                ..Default::default()
+            })
+            .await?;
+        assert!(provider.transactions.read().await.is_empty());
+
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while pool.status().available != pool.status().size {
+                tokio::time::sleep(Duration::from_millis(50)).await;
+            }
+        })
+        .await
+        .context("connection was not returned to the pool")?;
+        let rows = pool
+            .get()
+            .await?
+            .query(&format!("SELECT id FROM {table}"), &[])
+            .await?;
+        assert!(rows.is_empty());
+        Ok(())
+    }
 }
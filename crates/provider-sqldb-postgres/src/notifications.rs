@@ -0,0 +1,198 @@
+//! Delivery of Postgres notifications (sent with `NOTIFY`) to components
+//!
+
+use core::time::Duration;
+
+use anyhow::{Context as _, Result};
+use futures::StreamExt as _;
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::sync::mpsc;
+use tokio_postgres::{AsyncMessage, Client, Connection, Notification};
+use tracing::{debug, error, info, warn};
+use wasmcloud_provider_sdk::provider::WrpcClient;
+
+use crate::bindings::notification_handler;
+use crate::config::ConnectionCreateOptions;
+use crate::tls_connector;
+
+/// Delay before reconnecting after the listener connection was lost for the first time
+const RECONNECT_DELAY_MIN: Duration = Duration::from_millis(500);
+
+/// Maximum delay between attempts to reconnect the listener connection
+const RECONNECT_DELAY_MAX: Duration = Duration::from_secs(30);
+
+/// Listen on the given channels over a dedicated connection, forwarding every notification to
+/// the component behind `wrpc` via `wasmcloud:postgres/notification-handler`.
+///
+/// When the connection is lost, it is re-established and the channels are listened on again,
+/// backing off up to [`RECONNECT_DELAY_MAX`] between attempts. This only returns once the task
+/// running it is aborted.
+pub(crate) async fn listen(
+    create_opts: ConnectionCreateOptions,
+    channels: Vec<String>,
+    wrpc: WrpcClient,
+) {
+    let mut delay = RECONNECT_DELAY_MIN;
+    loop {
+        match connect(&create_opts, &channels).await {
+            Ok((client, mut notifications)) => {
+                info!(?channels, "listening for notifications");
+                delay = RECONNECT_DELAY_MIN;
+                while let Some(notification) = notifications.recv().await {
+                    let channel = notification.channel();
+                    match notification_handler::on_notification(
+                        &wrpc,
+                        None,
+                        channel,
+                        notification.payload(),
+                    )
+                    .await
+                    {
+                        Ok(Ok(())) => {}
+                        Ok(Err(err)) => {
+                            warn!(channel, err, "notification handler returned an error")
+                        }
+                        Err(err) => error!(channel, ?err, "failed to invoke notification handler"),
+                    }
+                }
+                // Only drop the client once the connection is gone, as that would close it
+                drop(client);
+                warn!("lost listener connection, reconnecting");
+            }
+            Err(error) => error!(?error, "failed to connect listener"),
+        }
+        tokio::time::sleep(delay).await;
+        delay = (delay * 2).min(RECONNECT_DELAY_MAX);
+    }
+}
+
+/// Open a connection outside of any pool and `LISTEN` on the given channels
+///
+/// Notifications are received on the returned channel, which closes when the connection is lost.
+async fn connect(
+    create_opts: &ConnectionCreateOptions,
+    channels: &[String],
+) -> Result<(Client, mpsc::UnboundedReceiver<Notification>)> {
+    let tls_required = create_opts.tls_required;
+    let cfg = deadpool_postgres::Config::from(create_opts.clone())
+        .get_pg_config()
+        .context("invalid postgres configuration")?;
+    let (tx, rx) = mpsc::unbounded_channel();
+    let client = if tls_required {
+        let (client, connection) = cfg
+            .connect(tls_connector())
+            .await
+            .context("failed to open TLS-enabled listener connection")?;
+        tokio::spawn(forward_notifications(connection, tx));
+        client
+    } else {
+        let (client, connection) = cfg
+            .connect(tokio_postgres::NoTls)
+            .await
+            .context("failed to open non-TLS listener connection")?;
+        tokio::spawn(forward_notifications(connection, tx));
+        client
+    };
+
+    let statements = channels
+        .iter()
+        .map(|channel| format!("LISTEN {}", quote_identifier(channel)))
+        .collect::<Vec<_>>()
+        .join(";");
+    client
+        .batch_execute(&statements)
+        .await
+        .context("failed to listen on channels")?;
+    Ok((client, rx))
+}
+
+/// Drive a connection, sending the notifications it receives to `tx` until it is closed
+async fn forward_notifications<S, T>(
+    mut connection: Connection<S, T>,
+    tx: mpsc::UnboundedSender<Notification>,
+) where
+    S: AsyncRead + AsyncWrite + Unpin,
+    T: AsyncRead + AsyncWrite + Unpin,
+{
+    let mut messages = futures::stream::poll_fn(move |cx| connection.poll_message(cx));
+    while let Some(message) = messages.next().await {
+        match message {
+            Ok(AsyncMessage::Notification(notification)) => {
+                if tx.send(notification).is_err() {
+                    return;
+                }
+            }
+            Ok(AsyncMessage::Notice(notice)) => debug!(%notice, "received notice"),
+            Ok(_) => {}
+            Err(error) => {
+                warn!(?error, "listener connection failed");
+                return;
+            }
+        }
+    }
+}
+
+/// Quote a channel name, which is an identifier rather than a string in `LISTEN`
+fn quote_identifier(identifier: &str) -> String {
+    format!("\"{}\"", identifier.replace('"', "\"\""))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test::start_postgres;
+
+    #[test]
+    fn quote_identifier_escapes_quotes() {
+        assert_eq!(quote_identifier("jobs"), r#""jobs""#);
+        assert_eq!(quote_identifier("Mixed Case"), r#""Mixed Case""#);
+        assert_eq!(
+            quote_identifier(r#"a"; NOTIFY b; --"#),
+            r#""a""; NOTIFY b; --""#
+        );
+    }
+
+    #[tokio::test]
+    async fn notifications_are_received() -> Result<()> {
+        let (create_opts, _container) = start_postgres().await?;
+        let channels = ["jobs".to_string(), r#"Quoted "channel""#.to_string()];
+        let (client, mut notifications) = connect(&create_opts, &channels).await?;
+
+        client
+            .batch_execute(r#"NOTIFY jobs, 'first'; NOTIFY "Quoted ""channel""", 'second'"#)
+            .await?;
+        // Notifications on channels that are not listened on are not forwarded
+        client.batch_execute("NOTIFY other, 'ignored'").await?;
+        client.batch_execute("NOTIFY jobs").await?;
+
+        let mut received = Vec::new();
+        for _ in 0..3 {
+            let notification = tokio::time::timeout(Duration::from_secs(5), notifications.recv())
+                .await
+                .context("timed out waiting for notification")?
+                .context("listener connection closed")?;
+            received.push((
+                notification.channel().to_string(),
+                notification.payload().to_string(),
+            ));
+        }
+        assert_eq!(
+            received,
+            [
+                ("jobs".to_string(), "first".to_string()),
+                (r#"Quoted "channel""#.to_string(), "second".to_string()),
+                ("jobs".to_string(), String::new()),
+            ]
+        );
+
+        // Dropping the client closes the connection, which closes the channel
+        drop(client);
+        assert!(
+            tokio::time::timeout(Duration::from_secs(5), notifications.recv())
+                .await
+                .context("timed out waiting for connection to close")?
+                .is_none()
+        );
+        Ok(())
+    }
+}
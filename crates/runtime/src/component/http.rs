@@ -16,7 +16,9 @@ use wrpc_interface_http::ServeIncomingHandlerWasmtime;
 
 use crate::capability::http::types;
 
-use super::{new_store, Ctx, Handler, Instance, ReplacedInstanceTarget, WrpcServeEvent};
+use super::{
+    new_store, Ctx, Handler, Instance, InvocationErrorKind, ReplacedInstanceTarget, WrpcServeEvent,
+};
 
 pub mod incoming_http_bindings {
     wasmtime::component::bindgen!({
@@ -140,7 +142,13 @@ where
         let scheme = wrpc_interface_http::bindings::wrpc::http::types::Scheme::from(scheme).into();
 
         let (tx, rx) = oneshot::channel();
-        let mut store = new_store(&self.engine, self.handler.clone(), self.max_execution_time);
+        let mut store = new_store(
+            &self.engine,
+            self.handler.clone(),
+            self.max_execution_time,
+            self.fuel,
+            &self.usage,
+        );
         let pre = incoming_http_bindings::IncomingHttpPre::new(self.pre.clone())
             .context("failed to pre-instantiate `wasi:http/incoming-handler`")?;
         trace!("instantiating `wasi:http/incoming-handler`");
@@ -214,11 +222,13 @@ where
         .in_current_span()
         .await;
         let success = res.as_ref().is_ok_and(Result::is_ok);
+        let error = res.as_ref().err().map(InvocationErrorKind::of_export_error);
         if let Err(err) = self
             .events
             .try_send(WrpcServeEvent::HttpIncomingHandlerHandleReturned {
                 context: cx,
                 success,
+                error,
             })
         {
             warn!(
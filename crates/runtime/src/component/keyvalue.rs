@@ -7,6 +7,7 @@ use tracing::instrument;
 use wasmtime::component::Resource;
 
 use crate::capability::keyvalue::{atomics, batch, store};
+use crate::capability::keyvalue_ext::ttl;
 use crate::capability::wrpc;
 
 use super::{Ctx, Handler, ReplacedInstanceTarget};
@@ -23,6 +24,16 @@ impl From<wrpc::wrpc::keyvalue::store::Error> for store::Error {
     }
 }
 
+impl From<wrpc::wasmcloud::keyvalue::ttl::Error> for ttl::Error {
+    fn from(value: wrpc::wasmcloud::keyvalue::ttl::Error) -> Self {
+        match value {
+            wrpc::wasmcloud::keyvalue::ttl::Error::NoSuchStore => Self::NoSuchStore,
+            wrpc::wasmcloud::keyvalue::ttl::Error::AccessDenied => Self::AccessDenied,
+            wrpc::wasmcloud::keyvalue::ttl::Error::Other(other) => Self::Other(other),
+        }
+    }
+}
+
 #[async_trait]
 impl<H> atomics::Host for Ctx<H>
 where
@@ -276,3 +287,34 @@ where
         Ok(())
     }
 }
+
+#[async_trait]
+impl<H> ttl::Host for Ctx<H>
+where
+    H: Handler,
+{
+    #[instrument(skip(value))]
+    async fn set(
+        &mut self,
+        bucket: String,
+        key: String,
+        value: Vec<u8>,
+        ttl_ms: u64,
+    ) -> anyhow::Result<Result<(), ttl::Error>> {
+        self.attach_parent_context();
+        // Route to the provider linked for `wasi:keyvalue/store`, which owns the bucket
+        match wrpc::wasmcloud::keyvalue::ttl::set(
+            &self.handler,
+            Some(ReplacedInstanceTarget::KeyvalueStore),
+            &bucket,
+            &key,
+            &Bytes::from(value),
+            ttl_ms,
+        )
+        .await?
+        {
+            Ok(()) => Ok(Ok(())),
+            Err(err) => Ok(Err(err.into())),
+        }
+    }
+}
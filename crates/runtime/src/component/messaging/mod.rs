@@ -5,7 +5,7 @@ use tracing::{instrument, warn, Span};
 use tracing_opentelemetry::OpenTelemetrySpanExt as _;
 
 use crate::capability::wrpc;
-use crate::component::{new_store, Handler, Instance, WrpcServeEvent};
+use crate::component::{new_store, Handler, Instance, InvocationErrorKind, WrpcServeEvent};
 
 pub mod v0_2;
 pub mod v0_3;
@@ -23,7 +23,13 @@ where
     ) -> anyhow::Result<Result<(), String>> {
         // Set the parent of the current context to the span passed in
         Span::current().set_parent(cx.deref().context());
-        let mut store = new_store(&self.engine, self.handler.clone(), self.max_execution_time);
+        let mut store = new_store(
+            &self.engine,
+            self.handler.clone(),
+            self.max_execution_time,
+            self.fuel,
+            &self.usage,
+        );
 
         // If wasmcloud:messaging@0.3.0 is enabled and we can instantiate the 0.3.0 bindings,
         // handle the message using 0.3.0. Otherwise, use the 0.2.0 bindings.
@@ -42,11 +48,13 @@ where
         };
 
         let success = res.is_ok();
+        let error = res.as_ref().err().map(InvocationErrorKind::of_export_error);
         if let Err(err) =
             self.events
                 .try_send(WrpcServeEvent::MessagingHandlerHandleMessageReturned {
                     context: cx,
                     success,
+                    error,
                 })
         {
             warn!(
@@ -2,8 +2,11 @@ use core::fmt::{self, Debug};
 use core::future::Future;
 use core::ops::Deref;
 use core::pin::Pin;
+use core::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
 use core::time::Duration;
 
+use std::sync::Arc;
+
 use anyhow::{ensure, Context as _};
 use futures::{Stream, TryStreamExt as _};
 use tokio::io::{AsyncRead, AsyncWrite, AsyncReadExt as _};
@@ -16,6 +19,7 @@ use wasi_preview1_component_adapter_provider::{
     WASI_SNAPSHOT_PREVIEW1_ADAPTER_NAME, WASI_SNAPSHOT_PREVIEW1_REACTOR_ADAPTER,
 };
 use wasmtime::component::{types, Linker, ResourceTable, ResourceTableError};
+use wasmtime::CallHook;
 use wasmtime_wasi::{WasiCtx, WasiCtxBuilder, WasiView};
 use wasmtime_wasi_http::WasiHttpCtx;
 use wrpc_runtime_wasmtime::{
@@ -176,6 +180,7 @@ macro_rules! skip_static_instances {
             | "wasi:sockets/udp@0.2.2"
             | "wasmcloud:bus/lattice@1.0.0"
             | "wasmcloud:bus/lattice@2.0.0"
+            | "wasmcloud:keyvalue/ttl@0.1.0-draft"
             | "wasmcloud:messaging/consumer@0.2.0"
             | "wasmcloud:messaging/types@0.2.0"
             | "wasmcloud:secrets/reveal@0.1.0-draft"
@@ -186,6 +191,7 @@ macro_rules! skip_static_instances {
 }
 
 /// This represents a kind of wRPC invocation error
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
 pub enum InvocationErrorKind {
     /// This occurs when the endpoint is not found, for example as would happen when the runtime
     /// would attempt to call `foo:bar/baz@0.2.0`, but the peer served `foo:bar/baz@0.1.0`.
@@ -193,6 +199,21 @@ pub enum InvocationErrorKind {
 
     /// An error kind, which will result in a trap in the component
     Trap,
+
+    /// This occurs when the invoked component exhausts its per-invocation fuel budget
+    OutOfFuel,
+}
+
+impl InvocationErrorKind {
+    /// Classify an error returned by an invocation of a function exported by a [Component]
+    #[must_use]
+    pub fn of_export_error(err: &anyhow::Error) -> Self {
+        if err.downcast_ref::<wasmtime::Trap>() == Some(&wasmtime::Trap::OutOfFuel) {
+            Self::OutOfFuel
+        } else {
+            Self::Trap
+        }
+    }
 }
 
 /// Implementations of this trait are able to introspect an error returned by wRPC invocations
@@ -281,6 +302,9 @@ where
     instance_pre: wasmtime::component::InstancePre<Ctx<H>>,
     max_execution_time: Duration,
     experimental_features: Features,
+    fuel_metering: bool,
+    fuel_budget: Option<u64>,
+    usage: ResourceUsage,
 }
 
 impl<H> Debug for Component<H>
@@ -292,6 +316,7 @@ where
             .field("claims", &self.claims)
             .field("runtime", &"wasmtime")
             .field("max_execution_time", &self.max_execution_time)
+            .field("fuel_budget", &self.fuel_budget)
             .finish_non_exhaustive()
     }
 }
@@ -300,6 +325,8 @@ fn new_store<H: Handler>(
     engine: &wasmtime::Engine,
     handler: H,
     max_execution_time: Duration,
+    fuel: Option<u64>,
+    usage: &ResourceUsage,
 ) -> wasmtime::Store<Ctx<H>> {
     let table = ResourceTable::new();
     let wasi = WasiCtxBuilder::new()
@@ -317,12 +344,92 @@ fn new_store<H: Handler>(
             shared_resources: SharedResourceTable::default(),
             timeout: max_execution_time,
             parent_context: None,
+            memory_high_water: MemoryHighWater(Arc::clone(&usage.memory_high_water)),
+            fuel: None,
         },
     );
     store.set_epoch_deadline(max_execution_time.as_secs());
+    store.limiter(|ctx| &mut ctx.memory_high_water);
+    if let Some(fuel) = fuel {
+        match store.set_fuel(fuel) {
+            Ok(()) => {
+                store.data_mut().fuel = Some(FuelMeter {
+                    budget: fuel,
+                    remaining: fuel,
+                    consumed: Arc::clone(&usage.fuel_consumed),
+                });
+                // Fuel can only be read from the store, so take note of the remaining fuel every
+                // time the component stops executing
+                store.call_hook(|mut store, hook| {
+                    if matches!(hook, CallHook::CallingHost | CallHook::ReturningFromWasm) {
+                        let remaining = store.get_fuel()?;
+                        if let Some(fuel) = store.data_mut().fuel.as_mut() {
+                            fuel.remaining = remaining;
+                        }
+                    }
+                    Ok(())
+                });
+            }
+            Err(err) => warn!(?err, "failed to set fuel budget"),
+        }
+    }
     store
 }
 
+/// Resources used by all instances of a [Component]
+#[derive(Clone, Debug, Default)]
+struct ResourceUsage {
+    /// Largest linear memory size in bytes
+    memory_high_water: Arc<AtomicUsize>,
+    /// Total fuel consumed
+    fuel_consumed: Arc<AtomicU64>,
+}
+
+/// Fuel budget of a single invocation, which adds the fuel consumed by the invocation to the
+/// total of the [Component] when the store is dropped
+struct FuelMeter {
+    budget: u64,
+    remaining: u64,
+    consumed: Arc<AtomicU64>,
+}
+
+impl Drop for FuelMeter {
+    fn drop(&mut self) {
+        self.consumed.fetch_add(
+            self.budget.saturating_sub(self.remaining),
+            Ordering::Relaxed,
+        );
+    }
+}
+
+/// [`wasmtime::ResourceLimiter`] recording the largest linear memory size of all instances of a
+/// [Component], it does not impose any limits in addition to the ones configured in the engine
+struct MemoryHighWater(Arc<AtomicUsize>);
+
+impl wasmtime::ResourceLimiter for MemoryHighWater {
+    fn memory_growing(
+        &mut self,
+        _current: usize,
+        desired: usize,
+        maximum: Option<usize>,
+    ) -> anyhow::Result<bool> {
+        if maximum.is_some_and(|maximum| desired > maximum) {
+            return Ok(false);
+        }
+        self.0.fetch_max(desired, Ordering::Relaxed);
+        Ok(true)
+    }
+
+    fn table_growing(
+        &mut self,
+        _current: usize,
+        desired: usize,
+        maximum: Option<usize>,
+    ) -> anyhow::Result<bool> {
+        Ok(maximum.is_none_or(|maximum| desired <= maximum))
+    }
+}
+
 /// Events sent by [`Component::serve_wrpc`]
 #[derive(Clone, Debug)]
 pub enum WrpcServeEvent<C> {
@@ -332,6 +439,8 @@ pub enum WrpcServeEvent<C> {
         context: C,
         /// Whether the invocation was successfully handled
         success: bool,
+        /// Kind of the error the invocation failed with, if any
+        error: Option<InvocationErrorKind>,
     },
     /// `wasmcloud:messaging/handler.handle-message` return event
     MessagingHandlerHandleMessageReturned {
@@ -339,6 +448,8 @@ pub enum WrpcServeEvent<C> {
         context: C,
         /// Whether the invocation was successfully handled
         success: bool,
+        /// Kind of the error the invocation failed with, if any
+        error: Option<InvocationErrorKind>,
     },
     /// dynamic export return event
     DynamicExportReturned {
@@ -346,6 +457,8 @@ pub enum WrpcServeEvent<C> {
         context: C,
         /// Whether the invocation was successfully handled
         success: bool,
+        /// Kind of the error the invocation failed with, if any
+        error: Option<InvocationErrorKind>,
     },
 }
 
@@ -413,6 +526,8 @@ where
             .context("failed to link `wasi:keyvalue/store`")?;
         capability::keyvalue::batch::add_to_linker(&mut linker, |ctx| ctx)
             .context("failed to link `wasi:keyvalue/batch`")?;
+        capability::keyvalue_ext::ttl::add_to_linker(&mut linker, |ctx| ctx)
+            .context("failed to link `wasmcloud:keyvalue/ttl`")?;
         capability::logging::logging::add_to_linker(&mut linker, |ctx| ctx)
             .context("failed to link `wasi:logging/logging`")?;
         capability::unversioned_logging::logging::add_to_linker(&mut linker, |ctx| ctx)
@@ -462,6 +577,9 @@ where
             instance_pre,
             max_execution_time: rt.max_execution_time,
             experimental_features: rt.experimental_features,
+            fuel_metering: rt.fuel_metering,
+            fuel_budget: None,
+            usage: ResourceUsage::default(),
         })
     }
 
@@ -473,6 +591,21 @@ where
         self
     }
 
+    /// Sets the maximum amount of fuel a single invocation of this component can consume.
+    /// Invocations exceeding it trap with [`InvocationErrorKind::OutOfFuel`].
+    /// This has no effect unless fuel metering is enabled in the [Runtime].
+    #[instrument(level = "trace", skip_all)]
+    pub fn set_fuel_budget(&mut self, fuel_budget: Option<u64>) -> &mut Self {
+        self.fuel_budget = fuel_budget;
+        self
+    }
+
+    /// Fuel each invocation starts with, `None` if fuel metering is disabled
+    fn fuel(&self) -> Option<u64> {
+        self.fuel_metering
+            .then(|| self.fuel_budget.unwrap_or(u64::MAX))
+    }
+
     /// Reads the WebAssembly binary asynchronously and calls [Component::new].
     ///
     /// # Errors
@@ -505,6 +638,19 @@ where
         self.claims.as_ref()
     }
 
+    /// Largest linear memory size in bytes reached by any instance of this [Component] so far.
+    #[must_use]
+    pub fn memory_high_water(&self) -> usize {
+        self.usage.memory_high_water.load(Ordering::Relaxed)
+    }
+
+    /// Total fuel consumed by all invocations of this [Component] so far, always `0` if fuel
+    /// metering is disabled.
+    #[must_use]
+    pub fn fuel_consumed(&self) -> u64 {
+        self.usage.fuel_consumed.load(Ordering::Relaxed)
+    }
+
     /// Instantiates the component given a handler and event channel
     pub fn instantiate<C>(
         &self,
@@ -518,6 +664,8 @@ where
             max_execution_time: self.max_execution_time,
             events,
             experimental_features: self.experimental_features,
+            fuel: self.fuel(),
+            usage: self.usage.clone(),
         }
     }
 
@@ -539,6 +687,7 @@ where
         S::Context: Deref<Target = tracing::Span>,
     {
         let max_execution_time = self.max_execution_time;
+        let fuel = self.fuel();
         let mut invocations = vec![];
         let instance = self.instantiate(handler.clone(), events.clone());
         for (name, ty) in self
@@ -578,13 +727,19 @@ where
                     let engine = self.engine.clone();
                     let handler = handler.clone();
                     let pre = self.instance_pre.clone();
+                    let usage = self.usage.clone();
                     debug!(?name, "serving root function");
                     let func = srv
                         .serve_function(
                             move || {
                                 let span = info_span!("call_instance_function");
-                                let mut store =
-                                    new_store(&engine, handler.clone(), max_execution_time);
+                                let mut store = new_store(
+                                    &engine,
+                                    handler.clone(),
+                                    max_execution_time,
+                                    fuel,
+                                    &usage,
+                                );
                                 store.data_mut().parent_context = Some(span.context());
                                 store
                             },
@@ -604,10 +759,13 @@ where
                                 let res =
                                     res.instrument(info_span!("handle_instance_function")).await;
                                 let success = res.is_ok();
+                                let error =
+                                    res.as_ref().err().map(InvocationErrorKind::of_export_error);
                                 if let Err(err) =
                                     events.try_send(WrpcServeEvent::DynamicExportReturned {
                                         context: cx,
                                         success,
+                                        error,
                                     })
                                 {
                                     warn!(
@@ -638,6 +796,7 @@ where
                                 let engine = self.engine.clone();
                                 let handler = handler.clone();
                                 let pre = self.instance_pre.clone();
+                                let usage = self.usage.clone();
                                 debug!(?instance_name, ?name, "serving instance function");
                                 let func = srv
                                     .serve_function(
@@ -647,6 +806,8 @@ where
                                                 &engine,
                                                 handler.clone(),
                                                 max_execution_time,
+                                                fuel,
+                                                &usage,
                                             );
                                             store.data_mut().parent_context = Some(span.context());
                                             store
@@ -666,10 +827,15 @@ where
                                         async move {
                                             let res = res.await;
                                             let success = res.is_ok();
+                                            let error = res
+                                                .as_ref()
+                                                .err()
+                                                .map(InvocationErrorKind::of_export_error);
                                             if let Err(err) = events.try_send(
                                                 WrpcServeEvent::DynamicExportReturned {
                                                     context: cx,
                                                     success,
+                                                    error,
                                                 },
                                             ) {
                                                 warn!(
@@ -741,6 +907,8 @@ where
     max_execution_time: Duration,
     events: mpsc::Sender<WrpcServeEvent<C>>,
     experimental_features: Features,
+    fuel: Option<u64>,
+    usage: ResourceUsage,
 }
 
 impl<H, C> Clone for Instance<H, C>
@@ -755,6 +923,8 @@ where
             max_execution_time: self.max_execution_time,
             events: self.events.clone(),
             experimental_features: self.experimental_features,
+            fuel: self.fuel,
+            usage: self.usage.clone(),
         }
     }
 }
@@ -781,6 +951,8 @@ where
             &self.engine,
             self.handler.clone(),
             self.max_execution_time,
+            self.fuel,
+            &self.usage,
         );
 
         // Instantiate the component
@@ -898,6 +1070,8 @@ where
     shared_resources: SharedResourceTable,
     timeout: Duration,
     parent_context: Option<opentelemetry::Context>,
+    memory_high_water: MemoryHighWater,
+    fuel: Option<FuelMeter>,
 }
 
 impl<H: Handler> WasiView for Ctx<H> {
@@ -27,6 +27,7 @@ pub struct RuntimeBuilder {
     component_config: ComponentConfig,
     force_pooling_allocator: bool,
     experimental_features: Features,
+    fuel_metering: bool,
 }
 
 impl RuntimeBuilder {
@@ -49,6 +50,7 @@ impl RuntimeBuilder {
             component_config: ComponentConfig::default(),
             force_pooling_allocator: false,
             experimental_features: Features::default(),
+            fuel_metering: false,
         }
     }
 
@@ -99,6 +101,19 @@ impl RuntimeBuilder {
         }
     }
 
+    /// Enables fuel metering, which deterministically accounts for the CPU time spent by components
+    /// and allows limiting it per invocation with
+    /// [`Component::set_fuel_budget`](crate::Component::set_fuel_budget).
+    /// Metering slows down the execution of components. Defaults to disabled.
+    #[must_use]
+    pub fn fuel_metering(mut self, fuel_metering: bool) -> Self {
+        self.engine_config.consume_fuel(fuel_metering);
+        Self {
+            fuel_metering,
+            ..self
+        }
+    }
+
     /// Forces the use of the pooling allocator. This may cause the runtime to fail if there isn't enough memory for the pooling allocator
     #[must_use]
     pub fn force_pooling_allocator(self) -> Self {
@@ -192,6 +207,7 @@ impl RuntimeBuilder {
                 component_config: self.component_config,
                 max_execution_time: self.max_execution_time,
                 experimental_features: self.experimental_features,
+                fuel_metering: self.fuel_metering,
             },
             epoch,
         ))
@@ -213,6 +229,7 @@ pub struct Runtime {
     pub(crate) component_config: ComponentConfig,
     pub(crate) max_execution_time: Duration,
     pub(crate) experimental_features: Features,
+    pub(crate) fuel_metering: bool,
 }
 
 impl Debug for Runtime {
@@ -221,6 +238,7 @@ impl Debug for Runtime {
             .field("component_config", &self.component_config)
             .field("runtime", &"wasmtime")
             .field("max_execution_time", &"max_execution_time")
+            .field("fuel_metering", &self.fuel_metering)
             .finish_non_exhaustive()
     }
 }
@@ -0,0 +1,303 @@
+use core::pin::Pin;
+use core::task::{Context, Poll};
+
+use std::io::Cursor;
+use std::sync::Arc;
+
+use anyhow::{bail, Context as _};
+use async_trait::async_trait;
+use bytes::Bytes;
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tokio::sync::mpsc;
+use wasmcloud_runtime::capability::logging::logging;
+use wasmcloud_runtime::capability::{messaging0_2_0, messaging0_3_0, secrets, CallTargetInterface};
+use wasmcloud_runtime::component::{
+    Bus, Config, InvocationErrorIntrospect, InvocationErrorKind, Logging, Messaging0_2,
+    Messaging0_3, MessagingClient0_3, MessagingHostMessage0_3, ReplacedInstanceTarget, Secrets,
+};
+use wasmcloud_runtime::{Component, Runtime};
+
+/// A component exporting `spin: func(iterations: u32)`, which loops `iterations` times
+const SPIN_WAT: &str = r#"
+(component
+  (core module $m
+    (func (export "spin") (param $n i32)
+      (block $done
+        (loop $loop
+          (br_if $done (i32.eqz (local.get $n)))
+          (local.set $n (i32.sub (local.get $n) (i32.const 1)))
+          (br $loop)))))
+  (core instance $i (instantiate $m))
+  (func (export "spin") (param "iterations" u32) (canon lift (core func $i "spin"))))
+"#;
+
+/// Parameters of an invocation
+struct Params(Cursor<Vec<u8>>);
+
+impl wrpc_transport::Index<Self> for Params {
+    fn index(&self, path: &[usize]) -> anyhow::Result<Self> {
+        bail!("params cannot be indexed with path {path:?}")
+    }
+}
+
+impl AsyncRead for Params {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.0).poll_read(cx, buf)
+    }
+}
+
+/// Results of an invocation, which are discarded
+struct Results;
+
+impl wrpc_transport::Index<Self> for Results {
+    fn index(&self, path: &[usize]) -> anyhow::Result<Self> {
+        bail!("results cannot be indexed with path {path:?}")
+    }
+}
+
+impl AsyncWrite for Results {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<std::io::Result<usize>> {
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+}
+
+/// Handler of a component without imports, which is never called
+#[derive(Clone)]
+struct Handler;
+
+impl wrpc_transport::Invoke for Handler {
+    type Context = Option<ReplacedInstanceTarget>;
+    type Outgoing = Results;
+    type Incoming = Params;
+
+    async fn invoke<P>(
+        &self,
+        _cx: Self::Context,
+        instance: &str,
+        func: &str,
+        _params: Bytes,
+        _paths: impl AsRef<[P]> + Send,
+    ) -> anyhow::Result<(Self::Outgoing, Self::Incoming)>
+    where
+        P: AsRef<[Option<usize>]> + Send + Sync,
+    {
+        bail!("unexpected invocation of `{instance}.{func}`")
+    }
+}
+
+#[async_trait]
+impl Bus for Handler {
+    async fn set_link_name(
+        &self,
+        _link_name: String,
+        _interfaces: Vec<Arc<CallTargetInterface>>,
+    ) -> anyhow::Result<Result<(), String>> {
+        bail!("unexpected call to `set_link_name`")
+    }
+}
+
+#[async_trait]
+impl Config for Handler {
+    async fn get(
+        &self,
+        _key: &str,
+    ) -> anyhow::Result<Result<Option<String>, wasmcloud_runtime::capability::config::store::Error>>
+    {
+        bail!("unexpected call to `get`")
+    }
+
+    async fn get_all(
+        &self,
+    ) -> anyhow::Result<
+        Result<Vec<(String, String)>, wasmcloud_runtime::capability::config::store::Error>,
+    > {
+        bail!("unexpected call to `get_all`")
+    }
+}
+
+#[async_trait]
+impl Logging for Handler {
+    async fn log(
+        &self,
+        _level: logging::Level,
+        _context: String,
+        _message: String,
+    ) -> anyhow::Result<()> {
+        bail!("unexpected call to `log`")
+    }
+}
+
+#[async_trait]
+impl Secrets for Handler {
+    async fn get(
+        &self,
+        _key: &str,
+    ) -> anyhow::Result<Result<secrets::store::Secret, secrets::store::SecretsError>> {
+        bail!("unexpected call to `get`")
+    }
+
+    async fn reveal(
+        &self,
+        _secret: secrets::reveal::Secret,
+    ) -> anyhow::Result<secrets::reveal::SecretValue> {
+        bail!("unexpected call to `reveal`")
+    }
+}
+
+impl Messaging0_2 for Handler {
+    async fn request(
+        &self,
+        _subject: String,
+        _body: Vec<u8>,
+        _timeout_ms: u32,
+    ) -> anyhow::Result<Result<messaging0_2_0::types::BrokerMessage, String>> {
+        bail!("unexpected call to `request`")
+    }
+
+    async fn publish(
+        &self,
+        _msg: messaging0_2_0::types::BrokerMessage,
+    ) -> anyhow::Result<Result<(), String>> {
+        bail!("unexpected call to `publish`")
+    }
+}
+
+impl Messaging0_3 for Handler {
+    async fn connect(
+        &self,
+        _name: String,
+    ) -> anyhow::Result<
+        Result<Box<dyn MessagingClient0_3 + Send + Sync>, messaging0_3_0::types::Error>,
+    > {
+        bail!("unexpected call to `connect`")
+    }
+
+    async fn send(
+        &self,
+        _client: &(dyn MessagingClient0_3 + Send + Sync),
+        _topic: messaging0_3_0::types::Topic,
+        _message: messaging0_3_0::types::Message,
+    ) -> anyhow::Result<Result<(), messaging0_3_0::types::Error>> {
+        bail!("unexpected call to `send`")
+    }
+
+    async fn request(
+        &self,
+        _client: &(dyn MessagingClient0_3 + Send + Sync),
+        _topic: messaging0_3_0::types::Topic,
+        _message: &messaging0_3_0::types::Message,
+        _options: Option<messaging0_3_0::request_reply::RequestOptions>,
+    ) -> anyhow::Result<
+        Result<Vec<Box<dyn MessagingHostMessage0_3 + Send + Sync>>, messaging0_3_0::types::Error>,
+    > {
+        bail!("unexpected call to `request`")
+    }
+
+    async fn reply(
+        &self,
+        _reply_to: &messaging0_3_0::types::Message,
+        _message: messaging0_3_0::types::Message,
+    ) -> anyhow::Result<Result<(), messaging0_3_0::types::Error>> {
+        bail!("unexpected call to `reply`")
+    }
+}
+
+impl InvocationErrorIntrospect for Handler {
+    fn invocation_error_kind(&self, _err: &anyhow::Error) -> InvocationErrorKind {
+        InvocationErrorKind::Trap
+    }
+}
+
+/// Compiles the spinning component in a runtime with fuel metering enabled or disabled
+fn spin_component(fuel_metering: bool) -> anyhow::Result<Component<Handler>> {
+    let (rt, _epoch) = Runtime::builder().fuel_metering(fuel_metering).build()?;
+    let wasm = wat::parse_str(SPIN_WAT).context("failed to parse component")?;
+    Component::new(&rt, &wasm)
+}
+
+/// Invokes `spin` on a new instance of `component`
+async fn spin(component: &Component<Handler>, iterations: u32) -> anyhow::Result<()> {
+    let (events, _) = mpsc::channel::<wasmcloud_runtime::component::WrpcServeEvent<()>>(1);
+    let mut params = Vec::new();
+    leb128_encode(iterations, &mut params);
+    component
+        .instantiate(Handler, events)
+        .call("", "spin", Params(Cursor::new(params)), Results)
+        .await
+}
+
+fn leb128_encode(mut v: u32, buf: &mut Vec<u8>) {
+    loop {
+        let b = (v & 0x7f) as u8;
+        v >>= 7;
+        if v == 0 {
+            buf.push(b);
+            return;
+        }
+        buf.push(b | 0x80);
+    }
+}
+
+#[tokio::test]
+async fn invocations_over_budget_run_out_of_fuel() -> anyhow::Result<()> {
+    let mut component = spin_component(true)?;
+    component.set_fuel_budget(Some(100_000));
+
+    spin(&component, 100).await?;
+    let consumed = component.fuel_consumed();
+    assert!(consumed > 0, "fuel consumed by invocations is accounted");
+    assert!(consumed < 100_000);
+
+    // Each invocation starts with the full budget
+    spin(&component, 100).await?;
+    assert_eq!(component.fuel_consumed(), 2 * consumed);
+
+    let err = spin(&component, u32::MAX)
+        .await
+        .expect_err("invocation exceeding the fuel budget should fail");
+    assert_eq!(
+        InvocationErrorKind::of_export_error(&err),
+        InvocationErrorKind::OutOfFuel
+    );
+    // The invocation consumed all of its budget
+    assert_eq!(component.fuel_consumed(), 2 * consumed + 100_000);
+    Ok(())
+}
+
+#[tokio::test]
+async fn invocations_without_budget_are_metered() -> anyhow::Result<()> {
+    let component = spin_component(true)?;
+    spin(&component, 100).await?;
+    let consumed = component.fuel_consumed();
+    assert!(consumed > 0);
+
+    // Consumed fuel grows with the work done by the invocation
+    spin(&component, 10_000).await?;
+    assert!(component.fuel_consumed() - consumed > 10 * consumed);
+    Ok(())
+}
+
+#[tokio::test]
+async fn budget_is_ignored_without_fuel_metering() -> anyhow::Result<()> {
+    let mut component = spin_component(false)?;
+    component.set_fuel_budget(Some(1));
+    spin(&component, 100).await?;
+    assert_eq!(component.fuel_consumed(), 0);
+    Ok(())
+}
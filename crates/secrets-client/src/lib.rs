@@ -1,7 +1,8 @@
 use async_nats::HeaderMap;
 use nkeys::XKey;
 use wasmcloud_secrets_types::{
-    Secret, SecretRequest, SecretResponse, RESPONSE_XKEY, WASMCLOUD_HOST_XKEY,
+    GetSecretError, Secret, SecretRequest, SecretResponse, RESPONSE_XKEY, SECRET_UPDATES_OPERATION,
+    WASMCLOUD_HOST_XKEY,
 };
 
 /// Default API version of the secrets API implementation in wasmCloud
@@ -32,8 +33,26 @@ pub enum SecretClientError {
     DeserializeSecretResponse(serde_json::error::Error),
     #[error("server error: {0}")]
     Server(String),
+    #[error("server error: {0}")]
+    Backend(GetSecretError),
     #[error("missing secret: {0}")]
     MissingSecret(String),
+    #[error("failed to subscribe to secret updates: {0}")]
+    SubscribeUpdates(async_nats::SubscribeError),
+}
+
+impl SecretClientError {
+    /// Returns whether the error was caused by the backend, or the store behind it, being
+    /// unavailable rather than by the request itself. Only these errors may succeed when retried
+    /// and never indicate that the requestor is no longer allowed to access a secret.
+    pub fn is_unavailable(&self) -> bool {
+        matches!(
+            self,
+            Self::RequestServerXkey(..)
+                | Self::SendSecretRequest(..)
+                | Self::Backend(GetSecretError::UpstreamError(..))
+        )
+    }
 }
 
 /// Topic on which secrets can be requested.
@@ -56,6 +75,10 @@ impl SecretsTopic {
     pub fn server_xkey(&self) -> String {
         format!("{}.{}", self.0, "server_xkey")
     }
+
+    pub fn updates(&self) -> String {
+        format!("{}.{}", self.0, SECRET_UPDATES_OPERATION)
+    }
 }
 
 /// NATS client that can be used to interact with secrets
@@ -140,7 +163,7 @@ impl Client {
                 .map_err(SecretClientError::DeserializeSecretResponse)?;
 
             if let Some(error) = sr.error {
-                return Err(SecretClientError::Server(error.to_string()));
+                return Err(SecretClientError::Backend(error));
             }
             return Err(SecretClientError::Server(
                 "unhandled server error (the server errored without explanation)".into(),
@@ -165,6 +188,18 @@ impl Client {
         })
     }
 
+    /// Subscribe to the notifications published by the backend whenever a secret is written.
+    ///
+    /// Every message received on the returned subscriber carries a JSON-encoded
+    /// [`SecretUpdate`](wasmcloud_secrets_types::SecretUpdate). Notifications never contain
+    /// secret material, the new value must be retrieved using [`Client::get`].
+    pub async fn subscribe_updates(&self) -> Result<async_nats::Subscriber, SecretClientError> {
+        self.client
+            .subscribe(self.topic.updates())
+            .await
+            .map_err(SecretClientError::SubscribeUpdates)
+    }
+
     /// Generate NATS request headers
     fn request_headers(&self, pubkey: String) -> HeaderMap {
         let mut headers = HeaderMap::new();
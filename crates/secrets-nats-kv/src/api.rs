@@ -1,8 +1,9 @@
+use anyhow::Context as _;
 use async_nats::{
     jetstream::{
         self,
         context::KeyValueError,
-        kv::{Config, Entry, History, Store},
+        kv::{Config, Entry, History, Operation, Store},
         publish::PublishAck,
         response::Response,
         stream::{Config as StreamConfig, DiscardPolicy, StorageType},
@@ -12,8 +13,9 @@ use async_nats::{
 use async_trait::async_trait;
 use bytes::Bytes;
 use exponential_backoff::Backoff;
-use futures::StreamExt;
+use futures::{StreamExt, TryStreamExt};
 use nkeys::XKey;
+use serde::de::DeserializeOwned;
 use std::{collections::HashSet, time::Duration};
 use tracing::{debug, error, info, warn};
 use wascap::jwt::{CapabilityProvider, Host};
@@ -47,6 +49,9 @@ pub struct Api {
     queue_base: String,
     /// The version of the secrets API that this backend implements.
     api_version: String,
+    /// The subject to publish an [`AuditEvent`] to for every operation managing secrets or
+    /// mappings. Audit events are not published if this is not set.
+    audit_subject: Option<String>,
 }
 
 impl Api {
@@ -64,6 +69,11 @@ impl Api {
         format!("{}.{}.{}", self.subject_base, self.api_version, self.name)
     }
 
+    /// The subject on which a [`SecretUpdate`] is published whenever a secret is written
+    pub fn updates_subject(&self) -> String {
+        format!("{}.{}", self.subject(), SECRET_UPDATES_OPERATION)
+    }
+
     pub fn state_bucket_name(&self) -> String {
         format!("SECRETS_{}_state", self.name)
     }
@@ -184,18 +194,303 @@ impl Api {
             return;
         };
 
-        match store.put(secret.key, encrypted_value.into()).await {
+        let mut event = AuditEvent::new("put_secret");
+        event.key = Some(secret.key.clone());
+        match store.put(&secret.key, encrypted_value.into()).await {
             Ok(revision) => {
                 let resp = PutSecretResponse::from(revision);
                 let _ = self
                     .client
                     .publish(reply, serde_json::to_string(&resp).unwrap().into())
                     .await;
+                event.revision = Some(revision);
+                self.notify_update(secret.key, revision).await;
             }
             Err(e) => {
+                event.error = Some(e.to_string());
                 let _ = self.client.publish(reply, e.to_string().into()).await;
             }
         };
+        self.audit(event).await;
+    }
+
+    /// Decrypt and deserialize a request encrypted with the transit xkey of the backend, the same
+    /// way `put_secret` requests are. Returns the request along with the public xkey of the
+    /// requester.
+    fn open_request<T: DeserializeOwned>(&self, msg: &Message) -> Result<(T, XKey), String> {
+        if msg.payload.is_empty() {
+            return Err(PutSecretError::InvalidPayload.to_string());
+        }
+        let Some(headers) = &msg.headers else {
+            return Err(PutSecretError::InvalidHeaders.to_string());
+        };
+        let k = headers
+            .get(WASMCLOUD_HOST_XKEY)
+            .and_then(|key| XKey::from_public_key(key.as_str()).ok())
+            .ok_or_else(|| PutSecretError::InvalidXKey.to_string())?;
+        let payload = self
+            .server_transit_xkey
+            .open(&msg.payload, &k)
+            .map_err(|_| PutSecretError::DecryptionError.to_string())?;
+        let request = serde_json::from_slice(&payload).map_err(|e| e.to_string())?;
+        Ok((request, k))
+    }
+
+    async fn handle_delete_secret(&self, msg: &Message, reply: Subject, purge: bool) {
+        let (request, _): (DeleteSecretRequest, _) = match self.open_request(msg) {
+            Ok(r) => r,
+            Err(e) => {
+                let _ = self
+                    .client
+                    .publish(
+                        reply,
+                        SecretOperationResponse {
+                            error: Some(e),
+                            ..Default::default()
+                        }
+                        .into(),
+                    )
+                    .await;
+                return;
+            }
+        };
+
+        let mut event = AuditEvent::new(if purge {
+            "purge_secret"
+        } else {
+            "delete_secret"
+        });
+        event.key = Some(request.key.clone());
+        let resp = match self.delete_secret(&request.key, purge).await {
+            Ok(()) => SecretOperationResponse::default(),
+            Err(e) => SecretOperationResponse {
+                error: Some(e.to_string()),
+                ..Default::default()
+            },
+        };
+        event.error.clone_from(&resp.error);
+        let _ = self.client.publish(reply, resp.into()).await;
+        self.audit(event).await;
+    }
+
+    /// Delete a secret. Deleted secrets can no longer be read by entities, but their previous
+    /// versions are kept and can be rolled back to, unless the secret is purged.
+    async fn delete_secret(&self, key: &str, purge: bool) -> anyhow::Result<()> {
+        let js = jetstream::new(self.client.clone());
+        let store = js.get_key_value(&self.bucket).await?;
+        if purge {
+            // Purging removes all versions, including those of secrets that were deleted before
+            store.purge(key).await?;
+        } else {
+            anyhow::ensure!(store.get(key).await?.is_some(), "secret not found");
+            store.delete(key).await?;
+        }
+        Ok(())
+    }
+
+    async fn handle_rollback_secret(&self, msg: &Message, reply: Subject) {
+        let (request, _): (RollbackSecretRequest, _) = match self.open_request(msg) {
+            Ok(r) => r,
+            Err(e) => {
+                let _ = self
+                    .client
+                    .publish(
+                        reply,
+                        SecretOperationResponse {
+                            error: Some(e),
+                            ..Default::default()
+                        }
+                        .into(),
+                    )
+                    .await;
+                return;
+            }
+        };
+
+        let mut event = AuditEvent::new("rollback_secret");
+        event.key = Some(request.key.clone());
+        let resp = match self.rollback_secret(&request.key, request.version).await {
+            Ok(revision) => {
+                event.revision = Some(revision);
+                self.notify_update(request.key, revision).await;
+                SecretOperationResponse {
+                    revision: Some(revision),
+                    ..Default::default()
+                }
+            }
+            Err(e) => SecretOperationResponse {
+                error: Some(e.to_string()),
+                ..Default::default()
+            },
+        };
+        event.error.clone_from(&resp.error);
+        let _ = self.client.publish(reply, resp.into()).await;
+        self.audit(event).await;
+    }
+
+    /// Roll a secret back to `version` by writing the value of that version as a new version,
+    /// returning the new revision. The value is copied as-is, so it is never decrypted.
+    async fn rollback_secret(&self, key: &str, version: u64) -> anyhow::Result<u64> {
+        let js = jetstream::new(self.client.clone());
+        let store = js.get_key_value(&self.bucket).await?;
+        let mut history = store.history(key).await?;
+        let entry = find_key_rev(&mut history, version)
+            .await
+            .filter(|entry| entry.operation == Operation::Put)
+            .with_context(|| format!("version {version} of secret not found"))?;
+        Ok(store.put(key, entry.value).await?)
+    }
+
+    async fn handle_list_secrets(&self, msg: &Message, reply: Subject) {
+        let (request, host_key): (ListSecretsRequest, _) = match self.open_request(msg) {
+            Ok(r) => r,
+            Err(e) => {
+                let resp = ListSecretsResponse {
+                    error: Some(e),
+                    ..Default::default()
+                };
+                let _ = self.client.publish(reply, resp.into()).await;
+                return;
+            }
+        };
+
+        let mut event = AuditEvent::new("list_secrets");
+        event.entity.clone_from(&request.entity);
+        let resp = match self.list_secrets(request.entity.as_deref()).await {
+            Ok(secrets) => ListSecretsResponse {
+                secrets,
+                error: None,
+            },
+            Err(e) => ListSecretsResponse {
+                error: Some(e.to_string()),
+                ..Default::default()
+            },
+        };
+        event.error.clone_from(&resp.error);
+        if let Err(e) = self
+            .publish_sealed(reply.clone(), resp.into(), &host_key)
+            .await
+        {
+            let resp = ListSecretsResponse {
+                error: Some(e),
+                ..Default::default()
+            };
+            let _ = self.client.publish(reply, resp.into()).await;
+        }
+        self.audit(event).await;
+    }
+
+    async fn handle_list_versions(&self, msg: &Message, reply: Subject) {
+        let (request, host_key): (ListVersionsRequest, _) = match self.open_request(msg) {
+            Ok(r) => r,
+            Err(e) => {
+                let resp = ListVersionsResponse {
+                    error: Some(e),
+                    ..Default::default()
+                };
+                let _ = self.client.publish(reply, resp.into()).await;
+                return;
+            }
+        };
+
+        let mut event = AuditEvent::new("list_versions");
+        event.key = Some(request.key.clone());
+        let resp = match self.list_versions(&request.key).await {
+            Ok(versions) => ListVersionsResponse {
+                versions,
+                error: None,
+            },
+            Err(e) => ListVersionsResponse {
+                error: Some(e.to_string()),
+                ..Default::default()
+            },
+        };
+        event.error.clone_from(&resp.error);
+        if let Err(e) = self
+            .publish_sealed(reply.clone(), resp.into(), &host_key)
+            .await
+        {
+            let resp = ListVersionsResponse {
+                error: Some(e),
+                ..Default::default()
+            };
+            let _ = self.client.publish(reply, resp.into()).await;
+        }
+        self.audit(event).await;
+    }
+
+    /// Publish a response encrypted for the requester with `host_key`, the same way responses to
+    /// `get` requests are
+    async fn publish_sealed(
+        &self,
+        reply: Subject,
+        response: Bytes,
+        host_key: &XKey,
+    ) -> Result<(), String> {
+        let encryption_key = XKey::new();
+        let encrypted = encryption_key
+            .seal(&response, host_key)
+            .map_err(|_| GetSecretError::EncryptionError.to_string())?;
+        let mut headers = async_nats::HeaderMap::new();
+        headers.insert(RESPONSE_XKEY, encryption_key.public_key().as_str());
+        let _ = self
+            .client
+            .publish_with_headers(reply, headers, encrypted.into())
+            .await;
+        Ok(())
+    }
+
+    /// List the names of all secrets, or of the secrets `entity` is allowed to access
+    async fn list_secrets(&self, entity: Option<&str>) -> anyhow::Result<Vec<String>> {
+        let mut secrets: Vec<String> = match entity {
+            Some(entity) => match self.state_bucket().await?.get(entity).await? {
+                Some(values) => serde_json::from_slice::<HashSet<String>>(&values)?
+                    .into_iter()
+                    .collect(),
+                None => Vec::new(),
+            },
+            None => {
+                let js = jetstream::new(self.client.clone());
+                let store = js.get_key_value(&self.bucket).await?;
+                store.keys().await?.try_collect().await?
+            }
+        };
+        secrets.sort();
+        Ok(secrets)
+    }
+
+    /// List the versions of a secret that are kept in the store, without their values
+    async fn list_versions(&self, key: &str) -> anyhow::Result<Vec<SecretVersion>> {
+        let js = jetstream::new(self.client.clone());
+        let store = js.get_key_value(&self.bucket).await?;
+        let mut history = store.history(key).await?;
+        let mut versions = Vec::new();
+        while let Some(entry) = history.next().await {
+            let entry = entry?;
+            versions.push(SecretVersion {
+                version: entry.revision,
+                created: entry.created.unix_timestamp(),
+                deleted: entry.operation != Operation::Put,
+            });
+        }
+        Ok(versions)
+    }
+
+    /// Publish an audit event to the audit subject, if one is configured
+    async fn audit(&self, event: AuditEvent) {
+        let Some(subject) = &self.audit_subject else {
+            return;
+        };
+        let payload = match serde_json::to_vec(&event) {
+            Ok(payload) => payload,
+            Err(e) => {
+                error!(error = %e, operation = event.operation, "failed to serialize audit event");
+                return;
+            }
+        };
+        if let Err(e) = self.client.publish(subject.clone(), payload.into()).await {
+            warn!(error = %e, operation = event.operation, "failed to publish audit event");
+        }
     }
 
     async fn handle_get_secret(&self, msg: &Message, reply: Subject) {
@@ -301,6 +596,30 @@ impl Api {
         }
     }
 
+    /// Notify subscribers, i.e. wasmCloud hosts, that a new revision of a secret was written so
+    /// that they can fetch it. Notifications are published without a reply subject, so they are
+    /// ignored by the listener of this backend.
+    async fn notify_update(&self, key: String, revision: u64) {
+        let update = SecretUpdate {
+            key,
+            version: Some(revision.to_string()),
+        };
+        let payload = match serde_json::to_vec(&update) {
+            Ok(payload) => payload,
+            Err(e) => {
+                error!(error = %e, key = update.key, "failed to serialize secret update");
+                return;
+            }
+        };
+        if let Err(e) = self
+            .client
+            .publish(self.updates_subject(), payload.into())
+            .await
+        {
+            warn!(error = %e, key = update.key, "failed to publish secret update");
+        }
+    }
+
     /// Run the secrets backend. This function will block until the NATS connection is closed.
     pub async fn run(&self) -> anyhow::Result<()> {
         let queue_name = self.queue_name();
@@ -401,14 +720,19 @@ impl Api {
                             continue;
                         }
                     };
+                    let mut event = AuditEvent::new("add_mapping");
+                    event.entity = Some(entity.to_string());
+                    event.secrets = values.iter().cloned().collect();
                     match self.add_mapping(entity.to_string(), values).await {
                         Ok(_) => {
                             let _ = self.client.publish(reply, "ok".into()).await;
                         }
                         Err(e) => {
+                            event.error = Some(e.to_string());
                             let _ = self.client.publish(reply, e.to_string().into()).await;
                         }
                     }
+                    self.audit(event).await;
                 }
                 "remove_mapping" => {
                     let entity = match parts.get(OPERATION_INDEX + 1) {
@@ -433,18 +757,40 @@ impl Api {
                             continue;
                         }
                     };
+                    let mut event = AuditEvent::new("remove_mapping");
+                    event.entity = Some(entity.to_string());
+                    event.secrets = values.iter().cloned().collect();
                     match self.remove_mapping(entity.to_string(), values).await {
                         Ok(_) => {
                             let _ = self.client.publish(reply, "ok".into()).await;
                         }
                         Err(e) => {
+                            event.error = Some(e.to_string());
                             let _ = self.client.publish(reply, e.to_string().into()).await;
                         }
                     }
+                    self.audit(event).await;
                 }
                 "put_secret" => {
                     self.handle_put_secret(&msg, reply).await;
                 }
+                "delete_secret" => {
+                    self.handle_delete_secret(&msg, reply, false).await;
+                }
+                "purge_secret" => {
+                    self.handle_delete_secret(&msg, reply, true).await;
+                }
+                "rollback_secret" => {
+                    self.handle_rollback_secret(&msg, reply).await;
+                }
+                // Listing operations only ever return the names and versions of secrets, never
+                // their values
+                "list_secrets" => {
+                    self.handle_list_secrets(&msg, reply).await;
+                }
+                "list_versions" => {
+                    self.handle_list_versions(&msg, reply).await;
+                }
                 o => {
                     let _ = self
                         .client
@@ -548,7 +894,7 @@ impl Api {
             None => HashSet::new(),
         };
 
-        if !map.contains(&entity) {
+        if map.is_empty() {
             let s = c.get_stream(&self.lock_stream_name()).await?;
             s.delete_message(seq).await?;
             return Ok(());
@@ -576,6 +922,7 @@ impl Api {
         max_secret_history: usize,
         queue_base: String,
         api_version: String,
+        audit_subject: Option<String>,
     ) -> Self {
         Self {
             server_transit_xkey: server_xkey,
@@ -587,6 +934,7 @@ impl Api {
             max_secret_history,
             queue_base,
             api_version,
+            audit_subject,
         }
     }
 }
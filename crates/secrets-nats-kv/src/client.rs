@@ -6,7 +6,11 @@ use wasmcloud_secrets_types::Secret;
 
 pub const SECRETS_API_VERSION: &str = "v1alpha1";
 
-use crate::{find_key_rev, PutSecretError, PutSecretRequest, PutSecretResponse};
+use crate::{
+    find_key_rev, DeleteSecretRequest, ListSecretsRequest, ListSecretsResponse,
+    ListVersionsRequest, ListVersionsResponse, PutSecretError, PutSecretRequest, PutSecretResponse,
+    RollbackSecretRequest, SecretOperationResponse, SecretVersion,
+};
 
 /// Helper function wrapper around [`put_secret`] that allows putting multiple secrets in the secret store.
 /// See the documentation for [`put_secret`] for more information.
@@ -192,3 +196,195 @@ pub async fn remove_mapping(
 
     Ok(())
 }
+
+/// List the names of secrets in the secret store. Secret values are never returned.
+///
+/// # Arguments
+/// - `nats_client` - the NATS client connected to a server that the secret store is listening on
+/// - `subject_base` - the base subject to use for requests to the secret store
+/// - `transit_xkey` - the transit key to use to encrypt the request. Can be constructed from a seed or public key
+/// - `public_key` - if provided, only the secrets the entity with this public key is allowed to access are listed
+pub async fn list_secrets(
+    nats_client: &async_nats::Client,
+    subject_base: &str,
+    transit_xkey: &nkeys::XKey,
+    public_key: Option<&str>,
+) -> anyhow::Result<Vec<String>> {
+    let request = ListSecretsRequest {
+        entity: public_key.map(String::from),
+    };
+    let response: ListSecretsResponse = sealed_request(
+        nats_client,
+        subject_base,
+        transit_xkey,
+        "list_secrets",
+        &request,
+    )
+    .await?;
+    match response.error {
+        Some(e) => bail!(e),
+        None => Ok(response.secrets),
+    }
+}
+
+/// List the versions of a secret kept in the secret store, oldest first. Secret values are never returned.
+///
+/// # Arguments
+/// - `nats_client` - the NATS client connected to a server that the secret store is listening on
+/// - `subject_base` - the base subject to use for requests to the secret store
+/// - `transit_xkey` - the transit key to use to encrypt the request. Can be constructed from a seed or public key
+/// - `name` - the name of the secret to list the versions of
+pub async fn list_versions(
+    nats_client: &async_nats::Client,
+    subject_base: &str,
+    transit_xkey: &nkeys::XKey,
+    name: &str,
+) -> anyhow::Result<Vec<SecretVersion>> {
+    let request = ListVersionsRequest {
+        key: name.to_string(),
+    };
+    let response: ListVersionsResponse = sealed_request(
+        nats_client,
+        subject_base,
+        transit_xkey,
+        "list_versions",
+        &request,
+    )
+    .await?;
+    match response.error {
+        Some(e) => bail!(e),
+        None => Ok(response.versions),
+    }
+}
+
+/// Delete a secret from the NATS KV backed secret store
+///
+/// Deleted secrets can no longer be read, but their previous versions are kept and can be rolled back to
+/// using [`rollback_secret`]. Purging a secret removes all of its versions, which cannot be undone.
+///
+/// # Arguments
+/// - `nats_client` - the NATS client connected to a server that the secret store is listening on
+/// - `subject_base` - the base subject to use for requests to the secret store
+/// - `transit_xkey` - the transit key to use to encrypt the request. Can be constructed from a seed or public key
+/// - `name` - the name of the secret to delete
+/// - `purge` - whether to remove all versions of the secret
+pub async fn delete_secret(
+    nats_client: &async_nats::Client,
+    subject_base: &str,
+    transit_xkey: &nkeys::XKey,
+    name: &str,
+    purge: bool,
+) -> anyhow::Result<()> {
+    let operation = if purge {
+        "purge_secret"
+    } else {
+        "delete_secret"
+    };
+    let request = DeleteSecretRequest {
+        key: name.to_string(),
+    };
+    secret_operation(nats_client, subject_base, transit_xkey, operation, &request).await?;
+    Ok(())
+}
+
+/// Roll a secret back to a previous version by writing the value of that version as a new version
+///
+/// Returns the new version of the secret.
+///
+/// # Arguments
+/// - `nats_client` - the NATS client connected to a server that the secret store is listening on
+/// - `subject_base` - the base subject to use for requests to the secret store
+/// - `transit_xkey` - the transit key to use to encrypt the request. Can be constructed from a seed or public key
+/// - `name` - the name of the secret to roll back
+/// - `version` - the version of the secret to roll back to
+pub async fn rollback_secret(
+    nats_client: &async_nats::Client,
+    subject_base: &str,
+    transit_xkey: &nkeys::XKey,
+    name: &str,
+    version: u64,
+) -> anyhow::Result<u64> {
+    let request = RollbackSecretRequest {
+        key: name.to_string(),
+        version,
+    };
+    secret_operation(
+        nats_client,
+        subject_base,
+        transit_xkey,
+        "rollback_secret",
+        &request,
+    )
+    .await?
+    .revision
+    .context("rollback response did not contain the new version of the secret")
+}
+
+/// Send a request for an operation modifying a secret, encrypted with the transit key the same way
+/// [`put_secret`] requests are
+async fn secret_operation(
+    nats_client: &async_nats::Client,
+    subject_base: &str,
+    transit_xkey: &nkeys::XKey,
+    operation: &str,
+    request: &impl serde::Serialize,
+) -> anyhow::Result<SecretOperationResponse> {
+    let response: SecretOperationResponse =
+        sealed_request(nats_client, subject_base, transit_xkey, operation, request).await?;
+    match response.error {
+        Some(e) => bail!(e),
+        None => Ok(response),
+    }
+}
+
+/// Send a request for `operation` encrypted with the transit key, decrypting the response if the
+/// backend encrypted it for this request
+async fn sealed_request<T: serde::de::DeserializeOwned>(
+    nats_client: &async_nats::Client,
+    subject_base: &str,
+    transit_xkey: &nkeys::XKey,
+    operation: &str,
+    request: &impl serde::Serialize,
+) -> anyhow::Result<T> {
+    ensure!(!subject_base.is_empty(), "subject base cannot be empty");
+
+    let request_xkey = nkeys::XKey::new();
+    let mut headers = async_nats::HeaderMap::new();
+    headers.insert(
+        wasmcloud_secrets_types::WASMCLOUD_HOST_XKEY,
+        request_xkey
+            .public_key()
+            .parse::<async_nats::HeaderValue>()
+            .context("could not parse request xkey public key as header value")?,
+    );
+
+    let value = serde_json::to_vec(request).context("failed to serialize request")?;
+    let v = request_xkey
+        .seal(&value, transit_xkey)
+        .context("failed to encrypt request")?;
+    let response = nats_client
+        .request_with_headers(
+            format!("{subject_base}.{SECRETS_API_VERSION}.nats-kv.{operation}"),
+            headers,
+            v.into(),
+        )
+        .await?;
+
+    // Errors about the request itself are returned without encryption
+    let payload = match response
+        .headers
+        .as_ref()
+        .and_then(|headers| headers.get(wasmcloud_secrets_types::RESPONSE_XKEY))
+    {
+        Some(response_xkey) => {
+            let response_xkey = nkeys::XKey::from_public_key(response_xkey.as_str())
+                .context("invalid response xkey")?;
+            request_xkey
+                .open(&response.payload, &response_xkey)
+                .with_context(|| format!("failed to decrypt {operation} response"))?
+        }
+        None => response.payload.to_vec(),
+    };
+    serde_json::from_slice(&payload)
+        .with_context(|| format!("failed to deserialize {operation} response"))
+}
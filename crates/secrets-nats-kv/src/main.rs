@@ -37,6 +37,14 @@ enum Command {
     AddMapping(AddSecretMappingCommand),
     /// Remove a secret mapping from the NATS KV secrets backend
     RemoveMapping(RemoveSecretMappingCommand),
+    /// List the names of secrets in the NATS KV secrets backend
+    List(ListCommand),
+    /// List the versions of a secret in the NATS KV secrets backend
+    Versions(VersionsCommand),
+    /// Delete a secret from the NATS KV secrets backend
+    Delete(DeleteCommand),
+    /// Roll a secret in the NATS KV secrets backend back to a previous version
+    Rollback(RollbackCommand),
 }
 
 #[derive(Parser)]
@@ -68,6 +76,9 @@ struct RunCommand {
     /// The API version to use for the secrets backend
     #[clap(long, default_value = SECRETS_API_VERSION)]
     secrets_api_version: String,
+    /// The subject to publish audit events to for every operation managing secrets or mappings
+    #[clap(long, env = "AUDIT_SUBJECT")]
+    audit_subject: Option<String>,
 
     #[command(flatten)]
     global: GlobalOpts,
@@ -175,6 +186,84 @@ struct RemoveSecretMappingCommand {
     global: GlobalOpts,
 }
 
+#[derive(Parser, Debug, Clone)]
+struct ListCommand {
+    /// The server's transit XKey, used to encrypt the request sent to the server.
+    #[clap(short, long, env = "TRANSIT_XKEY_SEED")]
+    transit_xkey_seed: String,
+    /// The NATS address to connect to where the backend is running
+    #[clap(long, default_value = "127.0.0.1:4222")]
+    nats_address: String,
+    /// The subject prefix to use for all requests to the secrets backend, defaults to `wasmcloud.secrets`
+    #[clap(short, long, default_value = "wasmcloud.secrets")]
+    subject_base: String,
+    /// If provided, only list the secrets the entity with this public key is allowed to access
+    public_key: Option<String>,
+
+    #[command(flatten)]
+    global: GlobalOpts,
+}
+
+#[derive(Parser, Debug, Clone)]
+struct VersionsCommand {
+    /// The server's transit XKey, used to encrypt the request sent to the server.
+    #[clap(short, long, env = "TRANSIT_XKEY_SEED")]
+    transit_xkey_seed: String,
+    /// The NATS address to connect to where the backend is running
+    #[clap(long, default_value = "127.0.0.1:4222")]
+    nats_address: String,
+    /// The subject prefix to use for all requests to the secrets backend, defaults to `wasmcloud.secrets`
+    #[clap(short, long, default_value = "wasmcloud.secrets")]
+    subject_base: String,
+    /// The name of the secret to list the versions of
+    name: String,
+
+    #[command(flatten)]
+    global: GlobalOpts,
+}
+
+#[derive(Parser, Debug, Clone)]
+struct DeleteCommand {
+    /// The server's transit XKey, used to encrypt the request sent to the server.
+    #[clap(short, long, env = "TRANSIT_XKEY_SEED")]
+    transit_xkey_seed: String,
+    /// The subject prefix to use for all requests to the secrets backend, defaults to `wasmcloud.secrets`
+    #[clap(short, long, default_value = "wasmcloud.secrets")]
+    subject_base: String,
+    /// The NATS address to connect to where the backend is running
+    #[clap(long, default_value = "127.0.0.1:4222")]
+    nats_address: String,
+    /// The name of the secret to delete from the backend
+    name: String,
+    /// Remove all versions of the secret. Purged secrets cannot be rolled back
+    #[clap(long)]
+    purge: bool,
+
+    #[command(flatten)]
+    global: GlobalOpts,
+}
+
+#[derive(Parser, Debug, Clone)]
+struct RollbackCommand {
+    /// The server's transit XKey, used to encrypt the request sent to the server.
+    #[clap(short, long, env = "TRANSIT_XKEY_SEED")]
+    transit_xkey_seed: String,
+    /// The subject prefix to use for all requests to the secrets backend, defaults to `wasmcloud.secrets`
+    #[clap(short, long, default_value = "wasmcloud.secrets")]
+    subject_base: String,
+    /// The NATS address to connect to where the backend is running
+    #[clap(long, default_value = "127.0.0.1:4222")]
+    nats_address: String,
+    /// The name of the secret to roll back
+    name: String,
+    /// The version of the secret to roll back to
+    #[clap(long = "secret-version")]
+    version: u64,
+
+    #[command(flatten)]
+    global: GlobalOpts,
+}
+
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
     tracing_subscriber::fmt::init();
@@ -187,6 +276,10 @@ async fn main() -> anyhow::Result<()> {
         Command::Get(args) => get(args).await,
         Command::AddMapping(args) => add_mapping(args).await,
         Command::RemoveMapping(args) => remove_mapping(args).await,
+        Command::List(args) => list(args).await,
+        Command::Versions(args) => versions(args).await,
+        Command::Delete(args) => delete(args).await,
+        Command::Rollback(args) => rollback(args).await,
     }
 }
 
@@ -227,6 +320,7 @@ async fn run(args: RunCommand) -> anyhow::Result<()> {
         args.max_secret_history,
         args.nats_queue_base,
         args.secrets_api_version,
+        args.audit_subject,
     );
 
     println!("Starting secrets backend '{}'", args.name);
@@ -417,3 +511,103 @@ async fn remove_mapping(args: RemoveSecretMappingCommand) -> anyhow::Result<()>
     );
     Ok(())
 }
+
+async fn list(args: ListCommand) -> anyhow::Result<()> {
+    let server_xkey = XKey::from_seed(&args.transit_xkey_seed)
+        .context("failed to create server key from seed")?;
+    let nats_client = connect(&args.nats_address, args.global.nats_creds_file).await?;
+
+    let secrets = client::list_secrets(
+        &nats_client,
+        &args.subject_base,
+        &server_xkey,
+        args.public_key.as_deref(),
+    )
+    .await?;
+    for secret in secrets {
+        println!("{secret}");
+    }
+    Ok(())
+}
+
+async fn versions(args: VersionsCommand) -> anyhow::Result<()> {
+    let server_xkey = XKey::from_seed(&args.transit_xkey_seed)
+        .context("failed to create server key from seed")?;
+    let nats_client = connect(&args.nats_address, args.global.nats_creds_file).await?;
+
+    let versions =
+        client::list_versions(&nats_client, &args.subject_base, &server_xkey, &args.name).await?;
+    ensure!(!versions.is_empty(), "secret '{}' not found", args.name);
+    for version in versions {
+        if version.deleted {
+            println!("{}\t{}\tdeleted", version.version, version.created);
+        } else {
+            println!("{}\t{}", version.version, version.created);
+        }
+    }
+    Ok(())
+}
+
+async fn delete(args: DeleteCommand) -> anyhow::Result<()> {
+    let server_xkey = XKey::from_seed(&args.transit_xkey_seed)
+        .context("failed to create server key from seed")?;
+    let nats_client = connect(&args.nats_address, args.global.nats_creds_file).await?;
+
+    client::delete_secret(
+        &nats_client,
+        &args.subject_base,
+        &server_xkey,
+        &args.name,
+        args.purge,
+    )
+    .await?;
+    if args.purge {
+        println!("Secret '{}' purged successfully", args.name);
+    } else {
+        println!("Secret '{}' deleted successfully", args.name);
+    }
+    Ok(())
+}
+
+async fn rollback(args: RollbackCommand) -> anyhow::Result<()> {
+    let server_xkey = XKey::from_seed(&args.transit_xkey_seed)
+        .context("failed to create server key from seed")?;
+    let nats_client = connect(&args.nats_address, args.global.nats_creds_file).await?;
+
+    let version = client::rollback_secret(
+        &nats_client,
+        &args.subject_base,
+        &server_xkey,
+        &args.name,
+        args.version,
+    )
+    .await?;
+    println!(
+        "Secret '{}' rolled back to version {} as version {version}",
+        args.name, args.version
+    );
+    Ok(())
+}
+
+/// Connect to NATS at `nats_address`, using the credentials file if provided
+async fn connect(
+    nats_address: &str,
+    creds_file: Option<String>,
+) -> anyhow::Result<async_nats::Client> {
+    match creds_file {
+        Some(creds_file) => async_nats::ConnectOptions::new()
+            .credentials_file(creds_file.clone())
+            .await
+            .with_context(|| format!("failed to read NATS credentials file '{creds_file}'"))?
+            .connect(nats_address)
+            .await
+            .with_context(|| {
+                format!(
+                    "failed to connect to NATS at {nats_address} with credentials file '{creds_file}'"
+                )
+            }),
+        None => async_nats::connect(nats_address)
+            .await
+            .with_context(|| format!("failed to connect to NATS at {nats_address}")),
+    }
+}
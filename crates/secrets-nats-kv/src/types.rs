@@ -56,3 +56,137 @@ impl From<u64> for PutSecretResponse {
         }
     }
 }
+
+/// A request to delete or purge a secret. Like a [`PutSecretRequest`], it must be encrypted with
+/// the transit xkey of the backend.
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct DeleteSecretRequest {
+    pub key: String,
+}
+
+/// A request to roll a secret back to a previous version, by writing the value of that version as
+/// a new version. Like a [`PutSecretRequest`], it must be encrypted with the transit xkey of the
+/// backend.
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct RollbackSecretRequest {
+    pub key: String,
+    pub version: u64,
+}
+
+/// A request to list the names of secrets. Like a [`PutSecretRequest`], it must be encrypted with
+/// the transit xkey of the backend, and the response is encrypted for the requester the same way
+/// responses to `get` requests are.
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct ListSecretsRequest {
+    /// If set, only the secrets the entity with this public key is allowed to access are listed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub entity: Option<String>,
+}
+
+/// A request to list the versions of a secret. Like a [`ListSecretsRequest`], both the request and
+/// the response are encrypted.
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct ListVersionsRequest {
+    pub key: String,
+}
+
+/// The response to a `delete_secret`, `purge_secret` or `rollback_secret` operation.
+/// For rollbacks, this response contains the revision number of the secret that was written.
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct SecretOperationResponse {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub revision: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// The response to a `list_secrets` operation. It only ever contains the names of secrets.
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct ListSecretsResponse {
+    pub secrets: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// A version of a secret in the store
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct SecretVersion {
+    /// The version, which is the revision of the secret in the KV bucket
+    pub version: u64,
+    /// The time the version was written, in seconds since the Unix epoch
+    pub created: i64,
+    /// Whether this version marks the secret as deleted
+    pub deleted: bool,
+}
+
+/// The response to a `list_versions` operation. It never contains the values of the versions.
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct ListVersionsResponse {
+    pub versions: Vec<SecretVersion>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// An event published to the audit subject of the backend, if configured, for every operation
+/// managing secrets or mappings. Audit events never contain secret values.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct AuditEvent {
+    /// The operation that was performed, e.g. `put_secret`
+    pub operation: String,
+    /// The key of the secret the operation applied to, if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub key: Option<String>,
+    /// The public key of the entity whose mapping the operation applied to, if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub entity: Option<String>,
+    /// The names of the secrets the mapping operation applied to, if any
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub secrets: Vec<String>,
+    /// The revision written by the operation, if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub revision: Option<u64>,
+    /// The time the operation was performed, in seconds since the Unix epoch
+    pub timestamp: u64,
+    /// The error the operation failed with, if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl AuditEvent {
+    /// Create an audit event for `operation`, performed now
+    pub fn new(operation: &str) -> Self {
+        Self {
+            operation: operation.to_string(),
+            key: None,
+            entity: None,
+            secrets: Vec::new(),
+            revision: None,
+            timestamp: std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or_default(),
+            error: None,
+        }
+    }
+}
+
+impl From<SecretOperationResponse> for Bytes {
+    fn from(resp: SecretOperationResponse) -> Self {
+        let encoded = serde_json::to_vec(&resp).unwrap();
+        Bytes::from(encoded)
+    }
+}
+
+impl From<ListSecretsResponse> for Bytes {
+    fn from(resp: ListSecretsResponse) -> Self {
+        let encoded = serde_json::to_vec(&resp).unwrap();
+        Bytes::from(encoded)
+    }
+}
+
+impl From<ListVersionsResponse> for Bytes {
+    fn from(resp: ListVersionsResponse) -> Self {
+        let encoded = serde_json::to_vec(&resp).unwrap();
+        Bytes::from(encoded)
+    }
+}
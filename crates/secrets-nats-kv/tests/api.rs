@@ -1,12 +1,19 @@
 use std::collections::HashSet;
 
 use async_nats::{jetstream, Client};
+use futures::StreamExt;
 use nkeys::{KeyPair, XKey};
 use rand::{distributions::Alphanumeric, thread_rng, Rng};
-use secrets_nats_kv::{Api, PutSecretRequest, PutSecretResponse};
+use secrets_nats_kv::{
+    Api, AuditEvent, DeleteSecretRequest, ListSecretsRequest, ListSecretsResponse,
+    ListVersionsRequest, ListVersionsResponse, PutSecretRequest, PutSecretResponse,
+    RollbackSecretRequest, SecretOperationResponse,
+};
 use std::collections::HashMap;
 use wascap::jwt::{Claims, ClaimsBuilder, Component, Host};
-use wasmcloud_secrets_types::{Application, Context, SecretRequest, WASMCLOUD_HOST_XKEY};
+use wasmcloud_secrets_types::{
+    Application, Context, SecretRequest, SecretUpdate, RESPONSE_XKEY, WASMCLOUD_HOST_XKEY,
+};
 
 const SUBJECT_BASE: &str = "kvstore_test";
 const NAME_BASE: &str = "nats-kv";
@@ -142,12 +149,13 @@ async fn integration_test_kvstore_put_secret() -> anyhow::Result<()> {
         .with_metadata(Host::new("test".to_string(), HashMap::new()))
         .build();
 
-    let request = SecretRequest {
+    let host_jwt = claims.encode(&account)?;
+    let request = || SecretRequest {
         key: "test".to_string(),
         field: None,
         context: Context {
-            entity_jwt: encoded,
-            host_jwt: claims.encode(&account)?,
+            entity_jwt: encoded.clone(),
+            host_jwt: host_jwt.clone(),
             application: Application {
                 name: Some("test".to_string()),
                 policy: "".to_string(),
@@ -164,9 +172,19 @@ async fn integration_test_kvstore_put_secret() -> anyhow::Result<()> {
     )
     .await?;
 
-    let resp = secrets_client.get(request, request_key).await?;
+    let resp = secrets_client.get(request(), request_key).await?;
     assert_eq!(resp.string_secret.unwrap(), "value");
 
+    // Removing the mapping revokes access to the secret
+    let response = client
+        .request(
+            format!("{base_sub}.remove_mapping.{}", component_key.public_key()),
+            serde_json::to_string(&v).unwrap().into(),
+        )
+        .await?;
+    assert_eq!(response.payload.to_vec(), b"ok");
+    assert!(secrets_client.get(request(), XKey::new()).await.is_err());
+
     Ok(())
 }
 
@@ -281,6 +299,245 @@ async fn integration_test_kvstore_version() -> anyhow::Result<()> {
     Ok(())
 }
 
+#[tokio::test]
+async fn integration_test_kvstore_put_secret_notifies() -> anyhow::Result<()> {
+    let client = async_nats::connect("127.0.0.1:4222").await?;
+
+    let encryption_xkey = XKey::new();
+    let server_xkey = XKey::new();
+    let request_key = XKey::new();
+
+    let (api, name) = setup_api(
+        client.clone(),
+        encryption_xkey.seed().unwrap(),
+        server_xkey.seed().unwrap(),
+    );
+
+    let base_sub = api.subject();
+    let _suite = Suite { name: name.clone() };
+    tokio::spawn(async move {
+        api.run().await.unwrap();
+    });
+    // Give the server some time to start
+    tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+
+    let secrets_client = wasmcloud_secrets_client::Client::new_with_version(
+        &name,
+        SUBJECT_BASE,
+        client.clone(),
+        Some(TEST_API_VERSION),
+    )
+    .await?;
+    let mut updates = secrets_client.subscribe_updates().await?;
+
+    let mut headers = async_nats::HeaderMap::new();
+    headers.insert(WASMCLOUD_HOST_XKEY, request_key.public_key().as_str());
+    for (revision, value) in [(1, "first"), (2, "second")] {
+        let value = PutSecretRequest {
+            key: "rotated".to_string(),
+            string_secret: Some(value.to_string()),
+            ..Default::default()
+        };
+        let value = serde_json::to_string(&value).unwrap();
+        let v = request_key.seal(value.as_bytes(), &server_xkey).unwrap();
+        client
+            .request_with_headers(format!("{base_sub}.put_secret"), headers.clone(), v.into())
+            .await?;
+
+        let msg = tokio::time::timeout(tokio::time::Duration::from_secs(5), updates.next())
+            .await?
+            .expect("subscription should not end");
+        let update: SecretUpdate = serde_json::from_slice(&msg.payload)?;
+        assert_eq!(
+            update,
+            SecretUpdate {
+                key: "rotated".to_string(),
+                version: Some(revision.to_string()),
+            }
+        );
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn integration_test_kvstore_manage_secrets() -> anyhow::Result<()> {
+    let client = async_nats::connect("127.0.0.1:4222").await?;
+
+    let encryption_xkey = XKey::new();
+    let server_xkey = XKey::new();
+    let request_key = XKey::new();
+
+    let (api, name) = setup_api(
+        client.clone(),
+        encryption_xkey.seed().unwrap(),
+        server_xkey.seed().unwrap(),
+    );
+
+    let base_sub = api.subject();
+    let _suite = Suite { name: name.clone() };
+    let mut audit = client.subscribe(audit_subject(&name)).await?;
+    tokio::spawn(async move {
+        api.run().await.unwrap();
+    });
+    // Give the server some time to start
+    tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+
+    let mut headers = async_nats::HeaderMap::new();
+    headers.insert(WASMCLOUD_HOST_XKEY, request_key.public_key().as_str());
+
+    for (key, value) in [("rotated", "first"), ("rotated", "second"), ("other", "x")] {
+        let value = PutSecretRequest {
+            key: key.to_string(),
+            string_secret: Some(value.to_string()),
+            ..Default::default()
+        };
+        client
+            .request_with_headers(
+                format!("{base_sub}.put_secret"),
+                headers.clone(),
+                seal_request(&value, &request_key, &server_xkey),
+            )
+            .await?;
+    }
+
+    let resp = client
+        .request_with_headers(
+            format!("{base_sub}.list_secrets"),
+            headers.clone(),
+            seal_request(&ListSecretsRequest::default(), &request_key, &server_xkey),
+        )
+        .await?;
+    let list: ListSecretsResponse = serde_json::from_slice(&open_response(&resp, &request_key))?;
+    assert_eq!(list.secrets, vec!["other", "rotated"]);
+
+    // Listing requests must be encrypted like any other request managing secrets
+    let resp = client
+        .request(format!("{base_sub}.list_secrets"), "{}".into())
+        .await?;
+    let list: ListSecretsResponse = serde_json::from_slice(&resp.payload)?;
+    assert!(list.error.is_some());
+    assert!(list.secrets.is_empty());
+
+    let resp = client
+        .request_with_headers(
+            format!("{base_sub}.rollback_secret"),
+            headers.clone(),
+            seal_request(
+                &RollbackSecretRequest {
+                    key: "rotated".to_string(),
+                    version: 1,
+                },
+                &request_key,
+                &server_xkey,
+            ),
+        )
+        .await?;
+    let rollback: SecretOperationResponse = serde_json::from_slice(&resp.payload)?;
+    assert_eq!(rollback.error, None);
+    let revision = rollback
+        .revision
+        .expect("rollback should return a revision");
+
+    let resp = client
+        .request_with_headers(
+            format!("{base_sub}.list_versions"),
+            headers.clone(),
+            seal_request(
+                &ListVersionsRequest {
+                    key: "rotated".to_string(),
+                },
+                &request_key,
+                &server_xkey,
+            ),
+        )
+        .await?;
+    let versions: ListVersionsResponse =
+        serde_json::from_slice(&open_response(&resp, &request_key))?;
+    assert_eq!(
+        versions
+            .versions
+            .iter()
+            .map(|v| v.version)
+            .collect::<Vec<_>>(),
+        vec![1, 2, revision]
+    );
+
+    let resp = client
+        .request_with_headers(
+            format!("{base_sub}.delete_secret"),
+            headers.clone(),
+            seal_request(
+                &DeleteSecretRequest {
+                    key: "other".to_string(),
+                },
+                &request_key,
+                &server_xkey,
+            ),
+        )
+        .await?;
+    let delete: SecretOperationResponse = serde_json::from_slice(&resp.payload)?;
+    assert_eq!(delete.error, None);
+
+    let resp = client
+        .request_with_headers(
+            format!("{base_sub}.list_secrets"),
+            headers.clone(),
+            seal_request(&ListSecretsRequest::default(), &request_key, &server_xkey),
+        )
+        .await?;
+    let list: ListSecretsResponse = serde_json::from_slice(&open_response(&resp, &request_key))?;
+    assert_eq!(list.secrets, vec!["rotated"]);
+
+    let mut operations = Vec::new();
+    while operations.len() < 8 {
+        let msg = tokio::time::timeout(tokio::time::Duration::from_secs(5), audit.next())
+            .await?
+            .expect("subscription should not end");
+        let event: AuditEvent = serde_json::from_slice(&msg.payload)?;
+        assert!(event.error.is_none());
+        operations.push(event.operation);
+    }
+    assert_eq!(
+        operations,
+        vec![
+            "put_secret",
+            "put_secret",
+            "put_secret",
+            "list_secrets",
+            "rollback_secret",
+            "list_versions",
+            "delete_secret",
+            "list_secrets",
+        ]
+    );
+
+    Ok(())
+}
+
+fn seal_request(
+    request: &impl serde::Serialize,
+    request_key: &XKey,
+    server_xkey: &XKey,
+) -> bytes::Bytes {
+    let request = serde_json::to_vec(request).unwrap();
+    request_key.seal(&request, server_xkey).unwrap().into()
+}
+
+fn open_response(msg: &async_nats::Message, request_key: &XKey) -> Vec<u8> {
+    let response_key = msg
+        .headers
+        .as_ref()
+        .and_then(|headers| headers.get(RESPONSE_XKEY))
+        .expect("response should be encrypted");
+    let response_key = XKey::from_public_key(response_key.as_str()).unwrap();
+    request_key.open(&msg.payload, &response_key).unwrap()
+}
+
+fn audit_subject(name: &str) -> String {
+    format!("{SUBJECT_BASE}.audit.{name}")
+}
+
 fn setup_api(client: Client, enc_seed: String, server_seed: String) -> (Api, String) {
     let server_xkey = XKey::from_seed(&server_seed).unwrap();
     let encryption_key = XKey::from_seed(&enc_seed).unwrap();
@@ -303,6 +560,7 @@ fn setup_api(client: Client, enc_seed: String, server_seed: String) -> (Api, Str
             64,
             "wasmcloud_secrets_test".to_string(),
             TEST_API_VERSION.to_string(),
+            Some(audit_subject(&name)),
         ),
         name,
     )
@@ -25,6 +25,10 @@ pub const SECRET_TYPE: &str = "secret.wasmcloud.dev/v1alpha1";
 /// This is primarily used to version the policy properties format.
 pub const SECRET_POLICY_PROPERTIES_TYPE: &str = "properties.secret.wasmcloud.dev/v1alpha1";
 
+/// The operation of the subject on which secrets backends publish a [`SecretUpdate`] whenever a
+/// secret is written, e.g. `wasmcloud.secrets.v1alpha1.nats-kv.updates`.
+pub const SECRET_UPDATES_OPERATION: &str = "updates";
+
 /// The prefix for all secret keys in the config store
 pub const SECRET_PREFIX: &str = "SECRET";
 
@@ -150,6 +154,18 @@ pub struct Secret {
     pub binary_secret: Option<Vec<u8>>,
 }
 
+/// A notification that a secret was written to a secrets backend, published on the `updates`
+/// subject of the backend. It never contains the secret itself: subscribers are expected to
+/// request the new value using the `get` operation.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SecretUpdate {
+    /// The key of the secret that was written.
+    pub key: String,
+    /// The version of the secret that was written, if the backend versions secrets.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+}
+
 /// The representation of a secret reference in the config store.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct SecretConfig {
@@ -346,6 +362,16 @@ impl Policy {
             ..Default::default()
         }
     }
+
+    /// Returns the type of the policy, used to version the format of its properties
+    pub fn policy_type(&self) -> &str {
+        &self.policy_type
+    }
+
+    /// Returns the properties of the policy, which are interpreted by the secrets backend
+    pub fn properties(&self) -> &HashMap<String, serde_json::Value> {
+        &self.properties
+    }
 }
 
 #[async_trait]
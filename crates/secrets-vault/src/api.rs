@@ -0,0 +1,396 @@
+use std::collections::HashMap;
+
+use async_nats::{Message, Subject};
+use async_trait::async_trait;
+use bytes::Bytes;
+use futures::StreamExt;
+use nkeys::XKey;
+use tracing::{debug, info, warn};
+use vaultrs::api::kv2::requests::ReadSecretRequest;
+use vaultrs::api::token::requests::CreateRoleTokenRequest;
+use vaultrs::client::{VaultClient, VaultClientSettings};
+use vaultrs::error::ClientError;
+use wascap::jwt::{CapabilityProvider, Host};
+use wascap::prelude::{Claims, Component};
+use wasmcloud_secrets_types::*;
+
+use crate::{RoleMappings, VaultPolicy};
+
+const OPERATION_INDEX: usize = 3;
+
+/// The `Api` struct implements the functionality of this secrets backend.
+pub struct Api {
+    /// The server's public XKey, used to decrypt secret requests sent to the server.
+    server_transit_xkey: XKey,
+    /// The NATS client used to communicate with wasmCloud hosts.
+    pub client: async_nats::Client,
+    /// The Vault client used to create tokens for reading secrets. Its token must be allowed to
+    /// create tokens for every role referenced by application policies.
+    vault: VaultClient,
+    /// The base subject for all secrets operations. Should default to `wasmcloud.secrets`.
+    subject_base: String,
+    /// The name of this backend. It must be unique for every {subject_base} + name combination.
+    pub name: String,
+    /// The mount path of the KV v2 secrets engine used if the policy does not specify one.
+    default_mount: String,
+    /// The TTL of the tokens created to read secrets, e.g. `30s`.
+    token_ttl: String,
+    /// The allow-list of the roles that entities and accounts may use, managed by administrators
+    role_mappings: RoleMappings,
+    /// The prefix to use for the name of the queue subscription group that this backend belongs
+    /// to.
+    queue_base: String,
+    /// The version of the secrets API that this backend implements.
+    api_version: String,
+}
+
+impl Api {
+    // The name of the queue group to use for this backend
+    fn queue_name(&self) -> String {
+        format!("{}.{}", self.queue_base, self.name)
+    }
+
+    pub fn subject(&self) -> String {
+        format!("{}.{}.{}", self.subject_base, self.api_version, self.name)
+    }
+
+    async fn handle_get_secret(&self, msg: &Message, reply: Subject) {
+        let payload = msg.payload.clone();
+        if payload.is_empty() {
+            let _ = self
+                .client
+                .publish(
+                    reply,
+                    SecretResponse::from(GetSecretError::InvalidPayload).into(),
+                )
+                .await;
+            return;
+        }
+
+        let Some(headers) = msg.headers.clone() else {
+            let _ = self
+                .client
+                .publish(
+                    reply,
+                    SecretResponse::from(GetSecretError::InvalidHeaders).into(),
+                )
+                .await;
+            return;
+        };
+
+        let Some(k) = headers
+            .get(WASMCLOUD_HOST_XKEY)
+            .and_then(|key| XKey::from_public_key(key.as_str()).ok())
+        else {
+            let _ = self
+                .client
+                .publish(
+                    reply,
+                    SecretResponse::from(GetSecretError::InvalidXKey).into(),
+                )
+                .await;
+            return;
+        };
+
+        let payload = match self.server_transit_xkey.open(&payload, &k) {
+            Ok(p) => p,
+            Err(_e) => {
+                let _ = self
+                    .client
+                    .publish(
+                        reply,
+                        SecretResponse::from(GetSecretError::DecryptionError).into(),
+                    )
+                    .await;
+                return;
+            }
+        };
+        let secret_req: SecretRequest = match serde_json::from_slice(&payload) {
+            Ok(r) => r,
+            Err(_) => {
+                let _ = self
+                    .client
+                    .publish(
+                        reply,
+                        SecretResponse::from(GetSecretError::InvalidRequest).into(),
+                    )
+                    .await;
+                return;
+            }
+        };
+
+        match self.get(secret_req).await {
+            Ok(resp) => {
+                let encoded: Bytes = resp.into();
+                let encryption_key = XKey::new();
+                let encrypted = match encryption_key.seal(&encoded, &k) {
+                    Ok(e) => e,
+                    Err(_e) => {
+                        let _ = self
+                            .client
+                            .publish(
+                                reply,
+                                SecretResponse::from(GetSecretError::EncryptionError).into(),
+                            )
+                            .await;
+                        return;
+                    }
+                };
+
+                let mut headers = async_nats::HeaderMap::new();
+                headers.insert(RESPONSE_XKEY, encryption_key.public_key().as_str());
+
+                let _ = self
+                    .client
+                    .publish_with_headers(reply, headers, encrypted.into())
+                    .await;
+            }
+            Err(e) => {
+                let _ = self
+                    .client
+                    .publish(reply, SecretResponse::from(e).into())
+                    .await;
+            }
+        }
+    }
+
+    /// Run the secrets backend. This function will block until the NATS connection is closed.
+    pub async fn run(&self) -> anyhow::Result<()> {
+        let queue_name = self.queue_name();
+        let subject = format!("{}.>", self.subject());
+        info!(subject, "Starting listener");
+        let mut sub = self
+            .client
+            .queue_subscribe(subject.clone(), queue_name)
+            .await?;
+
+        while let Some(msg) = sub.next().await {
+            let reply = match &msg.reply {
+                Some(reply) => reply.clone(),
+                None => continue,
+            };
+
+            let parts: Vec<&str> = msg
+                .subject
+                .trim_start_matches(&self.subject_base)
+                .split('.')
+                .collect();
+            if parts.len() < OPERATION_INDEX + 1 {
+                let _ = self.client.publish(reply, "invalid subject".into()).await;
+                continue;
+            }
+
+            // Secrets and access to them are managed in Vault, so this backend only implements
+            // the operations of the wasmCloud secrets spec
+            match parts[OPERATION_INDEX] {
+                "server_xkey" => {
+                    let _ = self
+                        .client
+                        .publish(reply, self.server_xkey().public_key().into())
+                        .await;
+                }
+                "get" => {
+                    self.handle_get_secret(&msg, reply).await;
+                }
+                o => {
+                    let _ = self
+                        .client
+                        .publish(reply, format!("unknown operation {o}").into())
+                        .await;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Create a single-use token for `policy.role_name`, carrying the identity of the requestor
+    /// as metadata so that reads show up attributed in the Vault audit log.
+    async fn role_client(
+        &self,
+        policy: &VaultPolicy,
+        entity: &str,
+        host: &str,
+        application: Option<&String>,
+    ) -> Result<VaultClient, GetSecretError> {
+        let mut meta = HashMap::from([
+            ("wasmcloud_entity".to_string(), entity.to_string()),
+            ("wasmcloud_host".to_string(), host.to_string()),
+        ]);
+        if let Some(application) = application {
+            meta.insert("wasmcloud_application".to_string(), application.clone());
+        }
+        let auth = vaultrs::token::new_role(
+            &self.vault,
+            &policy.role_name,
+            Some(
+                CreateRoleTokenRequest::builder()
+                    .display_name(format!("wasmcloud-{entity}"))
+                    .meta(meta)
+                    .num_uses(1_u64)
+                    .renewable(false)
+                    .ttl(self.token_ttl.clone()),
+            ),
+        )
+        .await
+        .map_err(vault_error)?;
+
+        VaultClient::new(VaultClientSettings {
+            token: auth.client_token,
+            ..self.vault.settings.clone()
+        })
+        .map_err(|e| GetSecretError::Other(e.to_string()))
+    }
+
+    /// Check that `role` is mapped to the entity itself or to the account that issued it. Without
+    /// this, any entity could reference any role that the backend can create tokens for.
+    async fn authorize(
+        &self,
+        role: &str,
+        entity: &str,
+        issuer: &str,
+    ) -> Result<(), GetSecretError> {
+        for key in [entity, issuer] {
+            let mapping = self
+                .role_mappings
+                .get(&self.vault, key)
+                .await
+                .map_err(|e| GetSecretError::UpstreamError(e.to_string()))?;
+            if mapping.roles.contains(role) {
+                return Ok(());
+            }
+        }
+        warn!(
+            entity,
+            issuer, role, "role is not mapped to entity or its issuer"
+        );
+        Err(GetSecretError::Unauthorized)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        server_xkey: XKey,
+        client: async_nats::Client,
+        vault: VaultClient,
+        subject_base: String,
+        name: String,
+        default_mount: String,
+        token_ttl: String,
+        role_mappings: RoleMappings,
+        queue_base: String,
+        api_version: String,
+    ) -> Self {
+        Self {
+            server_transit_xkey: server_xkey,
+            client,
+            vault,
+            subject_base,
+            name,
+            default_mount,
+            token_ttl,
+            role_mappings,
+            queue_base,
+            api_version,
+        }
+    }
+}
+
+#[async_trait]
+impl SecretsServer for Api {
+    async fn get(&self, request: SecretRequest) -> Result<SecretResponse, GetSecretError> {
+        // First validate the entity and host JWTs
+        request.context.valid_claims().map_err(|e| match e {
+            ContextValidationError::InvalidHostJWT(e) => GetSecretError::InvalidHostJWT(e),
+            e => GetSecretError::InvalidEntityJWT(e.to_string()),
+        })?;
+
+        let host_claims: Claims<Host> = Claims::decode(&request.context.host_jwt)
+            .map_err(|e| GetSecretError::InvalidHostJWT(e.to_string()))?;
+        if host_claims.issuer.starts_with('N') {
+            warn!("Host JWT issued by a non-account key");
+        }
+
+        let component_claims: wascap::Result<Claims<Component>> =
+            Claims::decode(&request.context.entity_jwt);
+        let provider_claims: wascap::Result<Claims<CapabilityProvider>> =
+            Claims::decode(&request.context.entity_jwt);
+        let (entity, issuer) = match (component_claims, provider_claims) {
+            (Ok(c), _) => (c.subject, c.issuer),
+            (_, Ok(p)) => (p.subject, p.issuer),
+            (Err(e), _) => return Err(GetSecretError::InvalidEntityJWT(e.to_string())),
+        };
+
+        // Access to secrets is determined by the Vault role referenced by the policy
+        let policy = VaultPolicy::from_context(&request.context)?;
+        self.authorize(&policy.role_name, &entity, &issuer).await?;
+        let mount = policy.mount_path.as_deref().unwrap_or(&self.default_mount);
+        let version = request
+            .version
+            .as_deref()
+            .map(str::parse::<u64>)
+            .transpose()
+            .map_err(|_| GetSecretError::InvalidRequest)?;
+        debug!(
+            entity,
+            role = policy.role_name,
+            mount,
+            key = request.key,
+            ?version,
+            "reading secret"
+        );
+
+        let client = self
+            .role_client(
+                &policy,
+                &entity,
+                &host_claims.subject,
+                request.context.application.name.as_ref(),
+            )
+            .await?;
+        let endpoint = ReadSecretRequest::builder()
+            .mount(mount)
+            .path(&request.key)
+            .version(version)
+            .build()
+            .map_err(|e| GetSecretError::Other(e.to_string()))?;
+        let res = vaultrs::api::exec_with_result(&client, endpoint)
+            .await
+            .map_err(vault_error)?;
+
+        let value = match (request.field, res.data) {
+            (Some(field), serde_json::Value::Object(mut data)) => {
+                data.remove(&field).ok_or(GetSecretError::SecretNotFound)?
+            }
+            (Some(_), _) => return Err(GetSecretError::SecretNotFound),
+            (None, data) => data,
+        };
+        // String values are returned as-is, anything else (including the whole secret, if no
+        // field was requested) as JSON
+        let string_secret = match value {
+            serde_json::Value::String(s) => s,
+            value => value.to_string(),
+        };
+
+        Ok(SecretResponse {
+            secret: Some(Secret {
+                version: res.metadata.version.to_string(),
+                string_secret: Some(string_secret),
+                binary_secret: None,
+            }),
+            ..Default::default()
+        })
+    }
+
+    fn server_xkey(&self) -> XKey {
+        XKey::from_public_key(self.server_transit_xkey.public_key().as_str()).unwrap()
+    }
+}
+
+/// Map an error returned by Vault to the corresponding [`GetSecretError`]
+fn vault_error(err: ClientError) -> GetSecretError {
+    match err {
+        ClientError::APIError { code: 403, .. } => GetSecretError::Unauthorized,
+        ClientError::APIError { code: 404, .. } => GetSecretError::SecretNotFound,
+        err => GetSecretError::UpstreamError(err.to_string()),
+    }
+}
@@ -0,0 +1,8 @@
+pub mod api;
+pub use api::*;
+
+pub mod mapping;
+pub use mapping::*;
+
+pub mod policy;
+pub use policy::*;
@@ -0,0 +1,206 @@
+use anyhow::Context;
+use clap::{Parser, Subcommand};
+use nkeys::XKey;
+use secrets_vault::{Api, RoleMappings};
+use url::Url;
+use vaultrs::client::{VaultClient, VaultClientSettingsBuilder};
+use wasmcloud_secrets_types::SECRET_API_VERSION;
+
+#[derive(Parser)]
+#[command(about, version, name = "secrets-vault")]
+/// A secrets backend for wasmCloud that reads secrets from the KV v2 secrets engine of Hashicorp
+/// Vault
+struct Args {
+    #[command(name = "command", subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Run the Vault secrets backend
+    Run(RunCommand),
+    /// Allow an entity, or all entities issued by an account, to use Vault roles
+    AddMapping(AddRoleMappingCommand),
+    /// Stop allowing an entity or account to use Vault roles
+    RemoveMapping(RemoveRoleMappingCommand),
+}
+
+#[derive(Parser)]
+struct RunCommand {
+    /// The server's transit XKey, used to decrypt secret requests sent to the server.
+    #[clap(short, long, env = "TRANSIT_XKEY_SEED")]
+    transit_xkey_seed: String,
+    /// The subject prefix to use for all requests to the secrets backend, defaults to `wasmcloud.secrets`
+    #[clap(short, long, default_value = "wasmcloud.secrets")]
+    subject_base: String,
+    /// The name of the secrets backend, defaults to `vault`
+    #[clap(short = 'n', long, default_value = "vault")]
+    name: String,
+    /// The NATS queue group to use for running multiple instances of the secrets backend
+    #[clap(long, default_value = "wasmcloud_secrets")]
+    nats_queue_base: String,
+    /// The NATS address to connect to where the backend is running
+    #[clap(long, default_value = "127.0.0.1:4222")]
+    nats_address: String,
+    /// The NATS credentials file to use when connecting to NATS
+    #[clap(long, env = "NATS_CREDSFILE")]
+    nats_creds_file: Option<String>,
+    /// The API version to use for the secrets backend
+    #[clap(long, default_value = SECRET_API_VERSION)]
+    secrets_api_version: String,
+    #[command(flatten)]
+    vault: VaultOpts,
+    /// The mount path of the KV v2 secrets engine used if the policy does not specify one
+    #[clap(long, default_value = "secret")]
+    default_mount: String,
+    /// The TTL of the single-use tokens created to read each secret
+    #[clap(long, default_value = "30s")]
+    token_ttl: String,
+    #[command(flatten)]
+    mappings: MappingOpts,
+}
+
+#[derive(Parser)]
+struct AddRoleMappingCommand {
+    #[command(flatten)]
+    vault: VaultOpts,
+    #[command(flatten)]
+    mappings: MappingOpts,
+    /// The public key of the entity, or of the account issuing entities, that is allowed to use
+    /// the roles
+    public_key: String,
+    /// The names of the Vault token roles that the public key is allowed to use. Can be
+    /// specified multiple times.
+    #[clap(long = "role", required = true)]
+    roles: Vec<String>,
+}
+
+#[derive(Parser)]
+struct RemoveRoleMappingCommand {
+    #[command(flatten)]
+    vault: VaultOpts,
+    #[command(flatten)]
+    mappings: MappingOpts,
+    /// The public key of the entity or account
+    public_key: String,
+    /// The names of the Vault token roles that the public key should no longer be able to use.
+    /// Can be specified multiple times.
+    #[clap(long = "role", required = true)]
+    roles: Vec<String>,
+}
+
+#[derive(Parser)]
+struct VaultOpts {
+    /// The address of the Vault server
+    #[clap(long, env = "VAULT_ADDR", default_value = "http://127.0.0.1:8200")]
+    vault_address: Url,
+    /// The Vault token to use. The token of the backend must be allowed to create tokens for
+    /// the roles referenced by application policies, and to read role mappings
+    #[clap(long, env = "VAULT_TOKEN", hide_env_values = true)]
+    vault_token: String,
+}
+
+impl VaultOpts {
+    fn client(self) -> anyhow::Result<VaultClient> {
+        VaultClient::new(
+            VaultClientSettingsBuilder::default()
+                .address(self.vault_address.as_str())
+                .token(self.vault_token)
+                .build()
+                .context("failed to build Vault client settings")?,
+        )
+        .context("failed to create Vault client")
+    }
+}
+
+#[derive(Parser)]
+struct MappingOpts {
+    /// The mount path of the KV v2 secrets engine storing the mappings of public keys to the
+    /// Vault roles they are allowed to use
+    #[clap(long, default_value = "secret")]
+    mappings_mount: String,
+    /// The path below which role mappings are stored. Application policies must not grant
+    /// access to it
+    #[clap(long, default_value = "wasmcloud/role-mappings")]
+    mappings_path: String,
+}
+
+impl From<MappingOpts> for RoleMappings {
+    fn from(opts: MappingOpts) -> Self {
+        RoleMappings::new(opts.mappings_mount, opts.mappings_path)
+    }
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    tracing_subscriber::fmt::init();
+
+    let args = Args::parse();
+
+    match args.command {
+        Command::Run(args) => run(args).await,
+        Command::AddMapping(args) => add_mapping(args).await,
+        Command::RemoveMapping(args) => remove_mapping(args).await,
+    }
+}
+
+async fn run(args: RunCommand) -> anyhow::Result<()> {
+    let server_xkey = XKey::from_seed(&args.transit_xkey_seed)
+        .context("failed to create server key from seed")?;
+
+    let nats_client = match args.nats_creds_file {
+        Some(creds_file) => async_nats::ConnectOptions::new()
+            .credentials_file(creds_file.clone())
+            .await
+            .with_context(|| format!("failed to read NATS credentials file '{creds_file}'"))?
+            .connect(&args.nats_address)
+            .await
+            .with_context(|| {
+                format!(
+                    "failed to connect to NATS at {} with credentials file '{creds_file}'",
+                    args.nats_address
+                )
+            })?,
+        None => async_nats::connect(&args.nats_address)
+            .await
+            .with_context(|| format!("failed to connect to NATS at {}", args.nats_address))?,
+    };
+
+    let vault = args.vault.client()?;
+
+    let api = Api::new(
+        server_xkey,
+        nats_client,
+        vault,
+        args.subject_base,
+        args.name.clone(),
+        args.default_mount,
+        args.token_ttl,
+        args.mappings.into(),
+        args.nats_queue_base,
+        args.secrets_api_version,
+    );
+
+    println!("Starting secrets backend '{}'", args.name);
+    api.run().await
+}
+
+async fn add_mapping(args: AddRoleMappingCommand) -> anyhow::Result<()> {
+    let vault = args.vault.client()?;
+    let mapping = RoleMappings::from(args.mappings)
+        .add(&vault, &args.public_key, args.roles)
+        .await
+        .context("failed to add role mapping")?;
+    println!("{} may use roles {:?}", args.public_key, mapping.roles);
+    Ok(())
+}
+
+async fn remove_mapping(args: RemoveRoleMappingCommand) -> anyhow::Result<()> {
+    let vault = args.vault.client()?;
+    let mapping = RoleMappings::from(args.mappings)
+        .remove(&vault, &args.public_key, args.roles)
+        .await
+        .context("failed to remove role mapping")?;
+    println!("{} may use roles {:?}", args.public_key, mapping.roles);
+    Ok(())
+}
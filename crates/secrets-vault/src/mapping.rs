@@ -0,0 +1,105 @@
+use std::collections::BTreeSet;
+
+use serde::{Deserialize, Serialize};
+use vaultrs::api::kv2::requests::SetSecretRequestOptions;
+use vaultrs::client::VaultClient;
+use vaultrs::error::ClientError;
+
+/// The Vault token roles that an entity, or all entities issued by an account, are allowed to
+/// use. Mappings are stored as secrets in a KV v2 secrets engine, keyed by public key, and can
+/// only be changed by administrators with write access to them.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RoleMapping {
+    #[serde(default)]
+    pub roles: BTreeSet<String>,
+}
+
+/// The location of the role mappings in Vault
+#[derive(Debug, Clone)]
+pub struct RoleMappings {
+    /// The mount path of the KV v2 secrets engine storing the mappings
+    pub mount: String,
+    /// The path below which mappings are stored, one secret per public key
+    pub path: String,
+}
+
+impl RoleMappings {
+    pub fn new(mount: impl Into<String>, path: impl Into<String>) -> Self {
+        Self {
+            mount: mount.into(),
+            path: path.into(),
+        }
+    }
+
+    fn key_path(&self, public_key: &str) -> String {
+        format!("{}/{public_key}", self.path.trim_end_matches('/'))
+    }
+
+    /// Get the roles mapped to `public_key`, which are empty if no mapping exists
+    pub async fn get(
+        &self,
+        vault: &VaultClient,
+        public_key: &str,
+    ) -> Result<RoleMapping, ClientError> {
+        match vaultrs::kv2::read(vault, &self.mount, &self.key_path(public_key)).await {
+            Ok(mapping) => Ok(mapping),
+            Err(ClientError::APIError { code: 404, .. }) => Ok(RoleMapping::default()),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Allow `public_key` to use `roles`, in addition to the roles it is already mapped to
+    pub async fn add(
+        &self,
+        vault: &VaultClient,
+        public_key: &str,
+        roles: impl IntoIterator<Item = String>,
+    ) -> Result<RoleMapping, ClientError> {
+        self.update(vault, public_key, |mapping| mapping.roles.extend(roles))
+            .await
+    }
+
+    /// Stop allowing `public_key` to use `roles`
+    pub async fn remove(
+        &self,
+        vault: &VaultClient,
+        public_key: &str,
+        roles: impl IntoIterator<Item = String>,
+    ) -> Result<RoleMapping, ClientError> {
+        self.update(vault, public_key, |mapping| {
+            for role in roles {
+                mapping.roles.remove(&role);
+            }
+        })
+        .await
+    }
+
+    /// Read, modify and write the mapping of `public_key`. The write is a check-and-set against
+    /// the version that was read, so concurrent changes fail rather than overwrite each other.
+    async fn update(
+        &self,
+        vault: &VaultClient,
+        public_key: &str,
+        f: impl FnOnce(&mut RoleMapping),
+    ) -> Result<RoleMapping, ClientError> {
+        let path = self.key_path(public_key);
+        let cas = match vaultrs::kv2::read_metadata(vault, &self.mount, &path).await {
+            Ok(metadata) => metadata.current_version,
+            Err(ClientError::APIError { code: 404, .. }) => 0,
+            Err(e) => return Err(e),
+        };
+        let mut mapping = self.get(vault, public_key).await?;
+        f(&mut mapping);
+        vaultrs::kv2::set_with_options(
+            vault,
+            &self.mount,
+            &path,
+            &mapping,
+            SetSecretRequestOptions {
+                cas: cas.try_into().unwrap_or(u32::MAX),
+            },
+        )
+        .await?;
+        Ok(mapping)
+    }
+}
@@ -0,0 +1,109 @@
+use serde::Deserialize;
+use wasmcloud_secrets_types::{Context, GetSecretError, Policy, SECRET_POLICY_PROPERTIES_TYPE};
+
+/// The policy properties understood by this backend, which map a secret request to the Vault
+/// role and KV v2 mount used to read the secret.
+///
+/// ```json
+/// {
+///   "type": "properties.secret.wasmcloud.dev/v1alpha1",
+///   "properties": {
+///     "role_name": "my-app",
+///     "mount_path": "secret"
+///   }
+/// }
+/// ```
+#[derive(Debug, Deserialize, PartialEq, Eq)]
+pub struct VaultPolicy {
+    /// The name of the Vault token role used to read secrets on behalf of the requesting entity.
+    /// The policies allowed by the role determine which secrets the entity can read.
+    pub role_name: String,
+    /// The mount path of the KV v2 secrets engine. If not supplied, the mount configured for the
+    /// backend will be used.
+    #[serde(default)]
+    pub mount_path: Option<String>,
+}
+
+impl VaultPolicy {
+    /// Parses the policy of the application the requesting entity belongs to.
+    pub fn from_context(context: &Context) -> Result<Self, GetSecretError> {
+        if context.application.policy.is_empty() {
+            return Err(GetSecretError::PolicyError(
+                "no policy provided, a `role_name` property is required".to_string(),
+            ));
+        }
+        let policy: Policy = serde_json::from_str(&context.application.policy)
+            .map_err(|e| GetSecretError::PolicyError(e.to_string()))?;
+        if policy.policy_type() != SECRET_POLICY_PROPERTIES_TYPE {
+            return Err(GetSecretError::PolicyError(format!(
+                "unsupported policy type `{}`",
+                policy.policy_type()
+            )));
+        }
+        let properties = serde_json::Value::Object(
+            policy
+                .properties()
+                .iter()
+                .map(|(k, v)| (k.clone(), v.clone()))
+                .collect(),
+        );
+        serde_json::from_value(properties).map_err(|e| GetSecretError::PolicyError(e.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use wasmcloud_secrets_types::{Application, SecretConfig};
+
+    use super::*;
+
+    fn context(properties: HashMap<String, serde_json::Value>) -> Context {
+        SecretConfig::new(
+            "name".to_string(),
+            "vault".to_string(),
+            "key".to_string(),
+            None,
+            None,
+            properties,
+        )
+        .try_into_request("entity", "host", None)
+        .expect("should be able to create request")
+        .context
+    }
+
+    #[test]
+    fn test_policy_from_context() {
+        let policy = VaultPolicy::from_context(&context(HashMap::from([
+            ("role_name".to_string(), "app".into()),
+            ("mount_path".to_string(), "kv".into()),
+        ])))
+        .expect("should parse policy");
+        assert_eq!(
+            policy,
+            VaultPolicy {
+                role_name: "app".to_string(),
+                mount_path: Some("kv".to_string()),
+            }
+        );
+
+        let policy = VaultPolicy::from_context(&context(HashMap::from([(
+            "role_name".to_string(),
+            "app".into(),
+        )])))
+        .expect("should parse policy");
+        assert_eq!(policy.mount_path, None);
+
+        assert!(VaultPolicy::from_context(&context(HashMap::new())).is_err());
+        assert!(VaultPolicy::from_context(&Context {
+            application: Application {
+                policy: r#"{"type":"other","properties":{"role_name":"app"}}"#.to_string(),
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+        .is_err());
+        assert!(VaultPolicy::from_context(&Context::default()).is_err());
+    }
+}
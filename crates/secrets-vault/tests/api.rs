@@ -0,0 +1,265 @@
+use std::collections::HashMap;
+
+use nkeys::{KeyPair, XKey};
+use rand::{distributions::Alphanumeric, thread_rng, Rng};
+use secrets_vault::{Api, RoleMappings};
+use vaultrs::api::token::requests::SetTokenRoleRequest;
+use vaultrs::client::{VaultClient, VaultClientSettingsBuilder};
+use wascap::jwt::{Claims, ClaimsBuilder, Component, Host};
+use wasmcloud_secrets_types::{GetSecretError, SecretConfig, SecretRequest};
+
+const SUBJECT_BASE: &str = "vault_test";
+const NAME_BASE: &str = "vault";
+const TEST_API_VERSION: &str = "test";
+
+/// Policy allowing to read the secrets written by [`setup_vault`]
+const APP_POLICY: &str = r#"path "secret/data/app/*" { capabilities = ["read"] }"#;
+
+fn vault_client() -> anyhow::Result<VaultClient> {
+    Ok(VaultClient::new(
+        VaultClientSettingsBuilder::default()
+            .address(std::env::var("VAULT_ADDR").unwrap_or("http://127.0.0.1:8200".to_string()))
+            .token(std::env::var("VAULT_TOKEN").unwrap_or("root".to_string()))
+            .build()?,
+    )?)
+}
+
+/// Write secrets for the `app` role, and a secret it is not allowed to read
+async fn setup_vault(vault: &VaultClient) -> anyhow::Result<()> {
+    vaultrs::kv2::set(
+        vault,
+        "secret",
+        "app/db",
+        &HashMap::from([("user", "app"), ("password", "first")]),
+    )
+    .await?;
+    vaultrs::kv2::set(
+        vault,
+        "secret",
+        "app/db",
+        &HashMap::from([("user", "app"), ("password", "second")]),
+    )
+    .await?;
+    vaultrs::kv2::set(
+        vault,
+        "secret",
+        "other/db",
+        &HashMap::from([("password", "other")]),
+    )
+    .await?;
+    vaultrs::sys::policy::set(vault, "wasmcloud-app", APP_POLICY).await?;
+    vaultrs::token::role::set(
+        vault,
+        "wasmcloud-app",
+        Some(SetTokenRoleRequest::builder().allowed_policies(vec!["wasmcloud-app".to_string()])),
+    )
+    .await?;
+    Ok(())
+}
+
+fn request(
+    account: &wascap::prelude::KeyPair,
+    key: &str,
+    field: Option<&str>,
+    version: Option<&str>,
+) -> anyhow::Result<SecretRequest> {
+    let component_key = KeyPair::new_module();
+    let claims: Claims<Component> = ClaimsBuilder::new()
+        .issuer(account.public_key().as_str())
+        .subject(component_key.public_key().as_str())
+        .build();
+    let host_key = KeyPair::new_server();
+    let host_claims: Claims<Host> = ClaimsBuilder::new()
+        .issuer(account.public_key().as_str())
+        .subject(host_key.public_key().as_str())
+        .with_metadata(Host::new("test".to_string(), HashMap::new()))
+        .build();
+
+    SecretConfig::new(
+        "db".to_string(),
+        NAME_BASE.to_string(),
+        key.to_string(),
+        field.map(String::from),
+        version.map(String::from),
+        HashMap::from([("role_name".to_string(), "wasmcloud-app".into())]),
+    )
+    .try_into_request(
+        &claims.encode(account)?,
+        &host_claims.encode(account)?,
+        Some(&"test".to_string()),
+    )
+}
+
+#[tokio::test]
+async fn integration_test_vault_get_secret() -> anyhow::Result<()> {
+    let vault = vault_client()?;
+    setup_vault(&vault).await?;
+
+    // TODO remove this once wasmcloud uses the latest version of nkeys
+    let account = wascap::prelude::KeyPair::new_account();
+    let mappings = RoleMappings::new("secret", mappings_path());
+
+    let client = async_nats::connect("127.0.0.1:4222").await?;
+    let server_xkey = XKey::new();
+    let (api, name) = setup_api(
+        client.clone(),
+        vault_client()?,
+        server_xkey.seed().unwrap(),
+        mappings.clone(),
+    );
+    tokio::spawn(async move {
+        api.run().await.unwrap();
+    });
+    // Give the server some time to start
+    tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+
+    let resp = client
+        .request(
+            format!("{SUBJECT_BASE}.{TEST_API_VERSION}.{name}.server_xkey"),
+            "".into(),
+        )
+        .await?;
+    assert_eq!(
+        std::str::from_utf8(&resp.payload)?,
+        server_xkey.public_key()
+    );
+
+    let secrets_client = wasmcloud_secrets_client::Client::new_with_version(
+        &name,
+        SUBJECT_BASE,
+        client,
+        Some(TEST_API_VERSION),
+    )
+    .await?;
+
+    // Entities are not allowed to use roles that are not mapped to them or their account
+    let Err(err) = secrets_client
+        .get(
+            request(&account, "app/db", Some("password"), None)?,
+            XKey::new(),
+        )
+        .await
+    else {
+        panic!("unmapped entity should not be allowed to use the role");
+    };
+    assert!(err
+        .to_string()
+        .contains(&GetSecretError::Unauthorized.to_string()));
+
+    mappings
+        .add(&vault, &account.public_key(), ["wasmcloud-app".to_string()])
+        .await?;
+
+    let secret = secrets_client
+        .get(
+            request(&account, "app/db", Some("password"), None)?,
+            XKey::new(),
+        )
+        .await?;
+    assert_eq!(secret.string_secret.as_deref(), Some("second"));
+    assert_eq!(secret.version, "2");
+
+    let secret = secrets_client
+        .get(
+            request(&account, "app/db", Some("password"), Some("1"))?,
+            XKey::new(),
+        )
+        .await?;
+    assert_eq!(secret.string_secret.as_deref(), Some("first"));
+    assert_eq!(secret.version, "1");
+
+    // Without a field, the whole secret is returned as JSON
+    let secret = secrets_client
+        .get(request(&account, "app/db", None, None)?, XKey::new())
+        .await?;
+    let value: HashMap<String, String> = serde_json::from_str(&secret.string_secret.unwrap())?;
+    assert_eq!(value.get("user").map(String::as_str), Some("app"));
+
+    let Err(err) = secrets_client
+        .get(
+            request(&account, "app/db", Some("missing"), None)?,
+            XKey::new(),
+        )
+        .await
+    else {
+        panic!("missing field should not be returned");
+    };
+    assert!(err
+        .to_string()
+        .contains(&GetSecretError::SecretNotFound.to_string()));
+
+    // The role is not allowed to read other secrets
+    let Err(err) = secrets_client
+        .get(
+            request(&account, "other/db", Some("password"), None)?,
+            XKey::new(),
+        )
+        .await
+    else {
+        panic!("secret should not be readable by the role");
+    };
+    assert!(err
+        .to_string()
+        .contains(&GetSecretError::Unauthorized.to_string()));
+
+    // Removing the mapping revokes access again
+    mappings
+        .remove(&vault, &account.public_key(), ["wasmcloud-app".to_string()])
+        .await?;
+    let Err(err) = secrets_client
+        .get(
+            request(&account, "app/db", Some("password"), None)?,
+            XKey::new(),
+        )
+        .await
+    else {
+        panic!("entity should not be allowed to use the role after removing the mapping");
+    };
+    assert!(err
+        .to_string()
+        .contains(&GetSecretError::Unauthorized.to_string()));
+
+    Ok(())
+}
+
+/// A unique path for the role mappings of a test, so that tests don't share mappings
+fn mappings_path() -> String {
+    format!(
+        "wasmcloud/role-mappings-{}",
+        thread_rng()
+            .sample_iter(&Alphanumeric)
+            .take(8)
+            .map(char::from)
+            .collect::<String>()
+    )
+}
+
+fn setup_api(
+    client: async_nats::Client,
+    vault: VaultClient,
+    server_xkey: String,
+    mappings: RoleMappings,
+) -> (Api, String) {
+    let name = format!(
+        "{}-{}",
+        NAME_BASE,
+        thread_rng()
+            .sample_iter(&Alphanumeric)
+            .take(8)
+            .map(char::from)
+            .collect::<String>()
+    );
+    let api = Api::new(
+        XKey::from_seed(&server_xkey).unwrap(),
+        client,
+        vault,
+        SUBJECT_BASE.to_string(),
+        name.clone(),
+        "secret".to_string(),
+        "30s".to_string(),
+        mappings,
+        "wasmcloud_secrets".to_string(),
+        TEST_API_VERSION.to_string(),
+    );
+    (api, name)
+}
@@ -110,6 +110,28 @@ impl WasmCloudTestHost {
         if let Some(psc) = policy_service_config {
             host_config.policy_service_config = psc;
         }
+        Self::start_with_config(Some(cluster_key), host_config).await
+    }
+
+    /// Start a test wasmCloud [`Host`] with a fully custom [`HostConfig`]
+    ///
+    /// # Arguments
+    ///
+    /// * `cluster_key` - An optional `nkeys::KeyPair` to use for the lattice. If not specified, one is generated.
+    /// * `host_config` - Configuration of the host. If it does not specify a host key, one is generated.
+    pub async fn start_with_config(
+        cluster_key: Option<Arc<KeyPair>>,
+        mut host_config: HostConfig,
+    ) -> Result<Self> {
+        let cluster_key = cluster_key.unwrap_or_else(|| Arc::new(KeyPair::new_cluster()));
+        let host_key = Arc::clone(
+            host_config
+                .host_key
+                .get_or_insert_with(|| Arc::new(KeyPair::new_server())),
+        );
+        let nats_url = ServerAddr::from_url(host_config.rpc_nats_url.clone())
+            .context("failed to build NATS server address from URL")?;
+        let lattice_name = host_config.lattice.to_string();
 
         let (host, shutdown_hook) = Host::new(host_config)
             .await
@@ -118,9 +140,8 @@ impl WasmCloudTestHost {
         Ok(Self {
             cluster_key,
             host_key,
-            nats_url: ServerAddr::from_url(nats_url.clone())
-                .context("failed to build NATS server address from URL")?,
-            lattice_name: lattice_name.into(),
+            nats_url,
+            lattice_name,
             host,
             shutdown_hook: Box::pin(shutdown_hook),
         })
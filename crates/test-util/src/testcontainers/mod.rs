@@ -1,4 +1,8 @@
-pub use testcontainers::{core::Mount, runners::AsyncRunner, ContainerAsync, ImageExt};
+pub use testcontainers::{
+    core::{Host, Mount},
+    runners::AsyncRunner,
+    ContainerAsync, ImageExt,
+};
 
 pub mod azurite;
 pub use azurite::*;
@@ -9,5 +13,14 @@ pub use localstack::*;
 pub mod nats_server;
 pub use nats_server::*;
 
+pub mod pebble;
+pub use pebble::*;
+
+pub mod postgres;
+pub use postgres::*;
+
 pub mod squid_proxy;
 pub use squid_proxy::*;
+
+pub mod vault;
+pub use vault::*;
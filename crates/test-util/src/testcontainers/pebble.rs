@@ -0,0 +1,93 @@
+use std::borrow::Cow;
+use std::path::Path;
+
+use testcontainers::{
+    core::{Mount, WaitFor},
+    Image,
+};
+
+/// Directory in the container holding the configuration and certificate of [`Pebble`]
+const CONFIG_DIR: &str = "/pebble";
+
+/// Port on which [`Pebble`] validates HTTP-01 challenges
+pub const PEBBLE_HTTP_01_PORT: u16 = 5002;
+
+/// Port on which [`Pebble`] validates TLS-ALPN-01 challenges
+pub const PEBBLE_TLS_ALPN_01_PORT: u16 = 5001;
+
+/// Pebble ACME test server, serving its directory at `https://<host>:14000/dir`
+///
+/// Challenges are validated against the address each domain resolves to in the container, on
+/// [`PEBBLE_HTTP_01_PORT`] and [`PEBBLE_TLS_ALPN_01_PORT`]. Use [`ImageExt::with_host`] to
+/// resolve the domains to [`Host::HostGateway`] when answering challenges from the host. The
+/// directory is served with the PEM-encoded `cert.pem` and `key.pem` in the directory passed to
+/// [`Pebble::new`].
+///
+/// [`ImageExt::with_host`]: testcontainers::ImageExt::with_host
+/// [`Host::HostGateway`]: testcontainers::core::Host::HostGateway
+#[derive(Debug, Clone)]
+pub struct Pebble {
+    mounts: [Mount; 1],
+}
+
+impl Pebble {
+    /// Serve the directory with the certificate and key in `dir`, writing the Pebble
+    /// configuration to it
+    pub fn new(dir: impl AsRef<Path>) -> std::io::Result<Self> {
+        let dir = dir.as_ref();
+        std::fs::write(
+            dir.join("pebble-config.json"),
+            serde_json::json!({
+                "pebble": {
+                    "listenAddress": "0.0.0.0:14000",
+                    "managementListenAddress": "0.0.0.0:15000",
+                    "certificate": format!("{CONFIG_DIR}/cert.pem"),
+                    "privateKey": format!("{CONFIG_DIR}/key.pem"),
+                    "httpPort": PEBBLE_HTTP_01_PORT,
+                    "tlsPort": PEBBLE_TLS_ALPN_01_PORT,
+                    "ocspResponderURL": "",
+                    "externalAccountBindingRequired": false,
+                }
+            })
+            .to_string(),
+        )?;
+        Ok(Self {
+            mounts: [Mount::bind_mount(dir.to_string_lossy(), CONFIG_DIR)],
+        })
+    }
+}
+
+impl Image for Pebble {
+    fn name(&self) -> &str {
+        "ghcr.io/letsencrypt/pebble"
+    }
+
+    fn tag(&self) -> &str {
+        "2.6.0"
+    }
+
+    fn ready_conditions(&self) -> Vec<WaitFor> {
+        vec![WaitFor::message_on_stdout("ACME directory available at")]
+    }
+
+    fn env_vars(
+        &self,
+    ) -> impl IntoIterator<Item = (impl Into<Cow<'_, str>>, impl Into<Cow<'_, str>>)> {
+        [
+            ("PEBBLE_VA_NOSLEEP", "1"),
+            // Nonces are otherwise rejected at random, to test client retries
+            ("PEBBLE_WFE_NONCEREJECT", "0"),
+        ]
+    }
+
+    fn mounts(&self) -> impl IntoIterator<Item = &Mount> {
+        &self.mounts
+    }
+
+    fn cmd(&self) -> impl IntoIterator<Item = impl Into<Cow<'_, str>>> {
+        [
+            "-config".to_string(),
+            format!("{CONFIG_DIR}/pebble-config.json"),
+        ]
+    }
+}
@@ -0,0 +1,36 @@
+use std::borrow::Cow;
+
+use testcontainers::{core::WaitFor, Image};
+
+/// Password of the `postgres` superuser of the server started by [`Postgres`]
+pub const POSTGRES_PASSWORD: &str = "postgres";
+
+/// Postgres server listening on port 5432, with a `postgres` superuser and database
+#[derive(Default, Debug, Clone)]
+pub struct Postgres {
+    _priv: (),
+}
+
+impl Image for Postgres {
+    fn name(&self) -> &str {
+        "postgres"
+    }
+
+    fn tag(&self) -> &str {
+        "16-alpine"
+    }
+
+    fn ready_conditions(&self) -> Vec<WaitFor> {
+        // The server is started twice, once to initialize the database and once to serve it
+        vec![
+            WaitFor::message_on_stderr("database system is ready to accept connections"),
+            WaitFor::message_on_stderr("database system is ready to accept connections"),
+        ]
+    }
+
+    fn env_vars(
+        &self,
+    ) -> impl IntoIterator<Item = (impl Into<Cow<'_, str>>, impl Into<Cow<'_, str>>)> {
+        [("POSTGRES_PASSWORD", POSTGRES_PASSWORD)]
+    }
+}
@@ -0,0 +1,37 @@
+use std::borrow::Cow;
+
+use testcontainers::{core::WaitFor, Image};
+
+/// Root token of the dev-mode Vault server started by [`Vault`]
+pub const VAULT_ROOT_TOKEN: &str = "root";
+
+/// Hashicorp Vault running in dev mode, listening on port 8200 with [`VAULT_ROOT_TOKEN`]
+#[derive(Default, Debug, Clone)]
+pub struct Vault {
+    _priv: (),
+}
+
+impl Image for Vault {
+    fn name(&self) -> &str {
+        "hashicorp/vault"
+    }
+
+    fn tag(&self) -> &str {
+        "1.18"
+    }
+
+    fn ready_conditions(&self) -> Vec<WaitFor> {
+        vec![WaitFor::message_on_stdout("Vault server started!")]
+    }
+
+    fn env_vars(
+        &self,
+    ) -> impl IntoIterator<Item = (impl Into<Cow<'_, str>>, impl Into<Cow<'_, str>>)> {
+        [
+            ("VAULT_DEV_ROOT_TOKEN_ID", VAULT_ROOT_TOKEN),
+            ("VAULT_DEV_LISTEN_ADDRESS", "0.0.0.0:8200"),
+            // The dev server keeps everything in memory, so the `IPC_LOCK` capability is not needed
+            ("SKIP_SETCAP", "true"),
+        ]
+    }
+}
@@ -5,7 +5,7 @@ use heck::ToKebabCase;
 #[cfg(feature = "otel")]
 pub use opentelemetry::{
     global,
-    metrics::{Counter, Histogram, Meter, Unit},
+    metrics::{AsyncInstrument, Counter, Histogram, Meter, ObservableGauge, Unit, UpDownCounter},
     KeyValue,
 };
 use wasmcloud_core::logging::Level;
@@ -23,6 +23,9 @@ pub use traces::FlushGuard;
 
 mod metrics;
 
+#[cfg(feature = "otel")]
+pub use metrics::PrometheusReader;
+
 #[cfg(not(feature = "otel"))]
 pub fn configure_observability(
     _: &str,
@@ -42,7 +45,8 @@ pub fn configure_observability(
     )
 }
 
-/// Configures observability for each type of signal
+/// Configures observability for each type of signal. Metrics are also collected by the
+/// `prometheus_reader`, if provided, whether or not they are exported.
 #[cfg(feature = "otel")]
 pub fn configure_observability(
     service_name: &str,
@@ -51,11 +55,12 @@ pub fn configure_observability(
     flame_graph: Option<impl AsRef<Path>>,
     log_level_override: Option<&Level>,
     trace_level_override: Option<&Level>,
+    prometheus_reader: Option<&PrometheusReader>,
 ) -> anyhow::Result<(tracing::Dispatch, traces::FlushGuard)> {
     let normalized_service_name = service_name.to_kebab_case();
 
-    if otel_config.metrics_enabled() {
-        metrics::configure_metrics(&normalized_service_name, otel_config)?;
+    if otel_config.metrics_enabled() || prometheus_reader.is_some() {
+        metrics::configure_metrics(&normalized_service_name, otel_config, prometheus_reader)?;
     }
 
     traces::configure_tracing(
@@ -1,5 +1,18 @@
+#[cfg(feature = "otel")]
+use std::collections::BTreeMap;
+#[cfg(feature = "otel")]
+use std::sync::{Arc, Weak};
+
 #[cfg(feature = "otel")]
 use anyhow::Context;
+#[cfg(feature = "otel")]
+use opentelemetry_sdk::metrics::data::{self, ResourceMetrics, Temporality};
+#[cfg(feature = "otel")]
+use opentelemetry_sdk::metrics::reader::{AggregationSelector, MetricReader, TemporalitySelector};
+#[cfg(feature = "otel")]
+use opentelemetry_sdk::metrics::{Aggregation, InstrumentKind, ManualReader, Pipeline};
+#[cfg(feature = "otel")]
+use prometheus::proto::{self, MetricFamily, MetricType};
 
 #[cfg(feature = "otel")]
 #[allow(clippy::missing_errors_doc)]
@@ -7,42 +20,53 @@ use anyhow::Context;
 pub fn configure_metrics(
     service_name: &str,
     otel_config: &wasmcloud_core::OtelConfig,
+    prometheus_reader: Option<&PrometheusReader>,
 ) -> anyhow::Result<()> {
     use opentelemetry_otlp::{MetricsExporterBuilder, WithExportConfig};
+    use opentelemetry_sdk::metrics::{PeriodicReader, SdkMeterProvider};
     use wasmcloud_core::OtelProtocol;
 
-    let builder: MetricsExporterBuilder = match otel_config.protocol {
-        OtelProtocol::Http => {
-            let client = crate::get_http_client(otel_config)
-                .context("failed to get an http client for otel metrics exporter")?;
-            opentelemetry_otlp::new_exporter()
-                .http()
-                .with_protocol(opentelemetry_otlp::Protocol::HttpBinary)
-                .with_http_client(client)
-                .with_endpoint(otel_config.metrics_endpoint())
-                .into()
-        }
-        OtelProtocol::Grpc => {
-            // TODO(joonas): Configure tonic::transport::ClientTlsConfig via .with_tls_config(...), passing in additional certificates.
-            opentelemetry_otlp::new_exporter()
-                .tonic()
-                .with_endpoint(otel_config.metrics_endpoint())
-                .into()
-        }
-    };
-
-    opentelemetry_otlp::new_pipeline()
-        .metrics(opentelemetry_sdk::runtime::Tokio)
-        .with_exporter(builder)
-        .with_resource(opentelemetry_sdk::Resource::new(vec![
+    let mut provider =
+        SdkMeterProvider::builder().with_resource(opentelemetry_sdk::Resource::new(vec![
             opentelemetry::KeyValue::new("service.name", service_name.to_string()),
-        ]))
-        .with_aggregation_selector(ExponentialHistogramAggregationSelector::new())
-        .with_temporality_selector(
-            opentelemetry_sdk::metrics::reader::DefaultTemporalitySelector::new(),
-        )
-        .build()
-        .context("failed to create OTEL metrics provider")?;
+        ]));
+
+    if otel_config.metrics_enabled() {
+        let builder: MetricsExporterBuilder = match otel_config.protocol {
+            OtelProtocol::Http => {
+                let client = crate::get_http_client(otel_config)
+                    .context("failed to get an http client for otel metrics exporter")?;
+                opentelemetry_otlp::new_exporter()
+                    .http()
+                    .with_protocol(opentelemetry_otlp::Protocol::HttpBinary)
+                    .with_http_client(client)
+                    .with_endpoint(otel_config.metrics_endpoint())
+                    .into()
+            }
+            OtelProtocol::Grpc => {
+                // TODO(joonas): Configure tonic::transport::ClientTlsConfig via .with_tls_config(...), passing in additional certificates.
+                opentelemetry_otlp::new_exporter()
+                    .tonic()
+                    .with_endpoint(otel_config.metrics_endpoint())
+                    .into()
+            }
+        };
+        let exporter = builder
+            .build_metrics_exporter(
+                Box::new(opentelemetry_sdk::metrics::reader::DefaultTemporalitySelector::new()),
+                Box::new(ExponentialHistogramAggregationSelector::new()),
+            )
+            .context("failed to create OTEL metrics exporter")?;
+        provider = provider.with_reader(
+            PeriodicReader::builder(exporter, opentelemetry_sdk::runtime::Tokio).build(),
+        );
+    }
+
+    if let Some(reader) = prometheus_reader {
+        provider = provider.with_reader(reader.clone());
+    }
+
+    opentelemetry::global::set_meter_provider(provider.build());
 
     Ok(())
 }
@@ -93,3 +117,276 @@ impl opentelemetry_sdk::metrics::reader::AggregationSelector
         }
     }
 }
+
+/// A [`MetricReader`] collecting the metrics recorded with the OpenTelemetry SDK on demand, for
+/// them to be scraped in the Prometheus text exposition format.
+///
+/// Metrics are collected with cumulative temporality, so the SDK keeps every series it has seen,
+/// up to its cardinality limit per instrument. Measurements with new attributes beyond that limit
+/// are aggregated into a single overflow series, which bounds the memory used by series keyed by
+/// unbounded attributes such as component IDs.
+#[cfg(feature = "otel")]
+#[derive(Clone, Debug)]
+pub struct PrometheusReader(Arc<ManualReader>);
+
+#[cfg(feature = "otel")]
+impl Default for PrometheusReader {
+    fn default() -> Self {
+        Self(Arc::new(
+            ManualReader::builder()
+                .with_aggregation_selector(PrometheusAggregationSelector)
+                .build(),
+        ))
+    }
+}
+
+#[cfg(feature = "otel")]
+impl PrometheusReader {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Collect the current value of all metrics and encode them in the Prometheus text format
+    #[allow(clippy::missing_errors_doc)]
+    pub fn encode(&self) -> anyhow::Result<String> {
+        let mut metrics = ResourceMetrics {
+            resource: opentelemetry_sdk::Resource::empty(),
+            scope_metrics: Vec::default(),
+        };
+        self.0
+            .collect(&mut metrics)
+            .context("failed to collect metrics")?;
+        let mut families = BTreeMap::<String, MetricFamily>::new();
+        for metric in metrics
+            .scope_metrics
+            .iter()
+            .flat_map(|scope| &scope.metrics)
+        {
+            let Some((kind, series)) = prometheus_series(metric.data.as_any()) else {
+                continue;
+            };
+            let mut name = prometheus_name(&metric.name);
+            let unit = prometheus_name(metric.unit.as_str());
+            if !unit.is_empty() && !name.ends_with(&unit) {
+                name = format!("{name}_{unit}");
+            }
+            if kind == MetricType::COUNTER {
+                name.push_str("_total");
+            }
+            let family = families.entry(name.clone()).or_insert_with(|| {
+                let mut family = MetricFamily::default();
+                family.set_name(name);
+                family.set_help(metric.description.to_string());
+                family.set_field_type(kind);
+                family
+            });
+            family.mut_metric().extend(series);
+        }
+        let families: Vec<_> = families
+            .into_values()
+            .filter(|family| !family.get_metric().is_empty())
+            .collect();
+        prometheus::TextEncoder::new()
+            .encode_to_string(&families)
+            .context("failed to encode metrics")
+    }
+}
+
+#[cfg(feature = "otel")]
+impl TemporalitySelector for PrometheusReader {
+    fn temporality(&self, kind: InstrumentKind) -> Temporality {
+        self.0.temporality(kind)
+    }
+}
+
+#[cfg(feature = "otel")]
+impl AggregationSelector for PrometheusReader {
+    fn aggregation(&self, kind: InstrumentKind) -> Aggregation {
+        self.0.aggregation(kind)
+    }
+}
+
+#[cfg(feature = "otel")]
+impl MetricReader for PrometheusReader {
+    fn register_pipeline(&self, pipeline: Weak<Pipeline>) {
+        self.0.register_pipeline(pipeline);
+    }
+
+    fn collect(&self, rm: &mut ResourceMetrics) -> opentelemetry::metrics::Result<()> {
+        self.0.collect(rm)
+    }
+
+    fn force_flush(&self) -> opentelemetry::metrics::Result<()> {
+        self.0.force_flush()
+    }
+
+    fn shutdown(&self) -> opentelemetry::metrics::Result<()> {
+        self.0.shutdown()
+    }
+}
+
+/// Aggregates histograms, which record durations in nanoseconds, into explicit buckets from a
+/// microsecond to 100 seconds, since exponential histograms cannot be represented in the
+/// Prometheus text format.
+#[cfg(feature = "otel")]
+#[derive(Clone, Copy, Debug)]
+struct PrometheusAggregationSelector;
+
+#[cfg(feature = "otel")]
+impl AggregationSelector for PrometheusAggregationSelector {
+    fn aggregation(&self, kind: InstrumentKind) -> Aggregation {
+        match kind {
+            InstrumentKind::Histogram => Aggregation::ExplicitBucketHistogram {
+                boundaries: (3..=10)
+                    .flat_map(|exp| [1.0, 2.5, 5.0].map(|m| m * 10_f64.powi(exp)))
+                    .chain([1e11])
+                    .collect(),
+                record_min_max: false,
+            },
+            kind => opentelemetry_sdk::metrics::reader::DefaultAggregationSelector::new()
+                .aggregation(kind),
+        }
+    }
+}
+
+/// Convert an OpenTelemetry name to a valid Prometheus metric or label name
+#[cfg(feature = "otel")]
+fn prometheus_name(name: &str) -> String {
+    name.replace(|c: char| !c.is_ascii_alphanumeric() && c != '_', "_")
+}
+
+#[cfg(feature = "otel")]
+fn prometheus_labels(attributes: &opentelemetry_sdk::AttributeSet) -> Vec<proto::LabelPair> {
+    attributes
+        .iter()
+        .map(|(key, value)| {
+            let mut label = proto::LabelPair::default();
+            label.set_name(prometheus_name(key.as_str()));
+            label.set_value(value.as_str().into_owned());
+            label
+        })
+        .collect()
+}
+
+#[cfg(feature = "otel")]
+fn prometheus_value_series<T: Copy>(
+    data_points: &[data::DataPoint<T>],
+    kind: MetricType,
+    f64_of: impl Fn(T) -> f64,
+) -> Vec<proto::Metric> {
+    data_points
+        .iter()
+        .map(|point| {
+            let mut metric = proto::Metric::default();
+            metric.set_label(prometheus_labels(&point.attributes));
+            let value = f64_of(point.value);
+            if kind == MetricType::COUNTER {
+                let mut counter = proto::Counter::default();
+                counter.set_value(value);
+                metric.set_counter(counter);
+            } else {
+                let mut gauge = proto::Gauge::default();
+                gauge.set_value(value);
+                metric.set_gauge(gauge);
+            }
+            metric
+        })
+        .collect()
+}
+
+#[cfg(feature = "otel")]
+fn prometheus_histogram_series<T: Copy>(
+    data_points: &[data::HistogramDataPoint<T>],
+    f64_of: impl Fn(T) -> f64,
+) -> Vec<proto::Metric> {
+    data_points
+        .iter()
+        .map(|point| {
+            let mut cumulative_count = 0;
+            let buckets = point
+                .bounds
+                .iter()
+                .zip(&point.bucket_counts)
+                .map(|(bound, count)| {
+                    cumulative_count += count;
+                    let mut bucket = proto::Bucket::default();
+                    bucket.set_upper_bound(*bound);
+                    bucket.set_cumulative_count(cumulative_count);
+                    bucket
+                })
+                .collect();
+            let mut histogram = proto::Histogram::default();
+            histogram.set_bucket(buckets);
+            histogram.set_sample_count(point.count);
+            histogram.set_sample_sum(f64_of(point.sum));
+            let mut metric = proto::Metric::default();
+            metric.set_label(prometheus_labels(&point.attributes));
+            metric.set_histogram(histogram);
+            metric
+        })
+        .collect()
+}
+
+/// Convert the aggregated data of a metric to Prometheus series, returning `None` for
+/// aggregations which cannot be represented in the Prometheus text format
+#[cfg(feature = "otel")]
+#[allow(clippy::cast_precision_loss)]
+fn prometheus_series(data: &dyn std::any::Any) -> Option<(MetricType, Vec<proto::Metric>)> {
+    fn sum_series<T: Copy>(
+        sum: &data::Sum<T>,
+        f64_of: impl Fn(T) -> f64,
+    ) -> (MetricType, Vec<proto::Metric>) {
+        let kind = if sum.is_monotonic {
+            MetricType::COUNTER
+        } else {
+            MetricType::GAUGE
+        };
+        (
+            kind,
+            prometheus_value_series(&sum.data_points, kind, f64_of),
+        )
+    }
+
+    fn gauge_series<T: Copy>(
+        gauge: &data::Gauge<T>,
+        f64_of: impl Fn(T) -> f64,
+    ) -> (MetricType, Vec<proto::Metric>) {
+        let kind = MetricType::GAUGE;
+        (
+            kind,
+            prometheus_value_series(&gauge.data_points, kind, f64_of),
+        )
+    }
+
+    fn histogram_series<T: Copy>(
+        histogram: &data::Histogram<T>,
+        f64_of: impl Fn(T) -> f64,
+    ) -> (MetricType, Vec<proto::Metric>) {
+        (
+            MetricType::HISTOGRAM,
+            prometheus_histogram_series(&histogram.data_points, f64_of),
+        )
+    }
+
+    if let Some(sum) = data.downcast_ref::<data::Sum<u64>>() {
+        Some(sum_series(sum, |v| v as f64))
+    } else if let Some(sum) = data.downcast_ref::<data::Sum<i64>>() {
+        Some(sum_series(sum, |v| v as f64))
+    } else if let Some(sum) = data.downcast_ref::<data::Sum<f64>>() {
+        Some(sum_series(sum, |v| v))
+    } else if let Some(gauge) = data.downcast_ref::<data::Gauge<u64>>() {
+        Some(gauge_series(gauge, |v| v as f64))
+    } else if let Some(gauge) = data.downcast_ref::<data::Gauge<i64>>() {
+        Some(gauge_series(gauge, |v| v as f64))
+    } else if let Some(gauge) = data.downcast_ref::<data::Gauge<f64>>() {
+        Some(gauge_series(gauge, |v| v))
+    } else if let Some(histogram) = data.downcast_ref::<data::Histogram<u64>>() {
+        Some(histogram_series(histogram, |v| v as f64))
+    } else if let Some(histogram) = data.downcast_ref::<data::Histogram<i64>>() {
+        Some(histogram_series(histogram, |v| v as f64))
+    } else {
+        data.downcast_ref::<data::Histogram<f64>>()
+            .map(|histogram| histogram_series(histogram, |v| v))
+    }
+}
@@ -1,21 +1,26 @@
+use std::collections::{HashMap, HashSet};
 use std::{path::PathBuf, time::Duration};
 
-use anyhow::Result;
+use anyhow::{Context as _, Result};
 use async_nats::jetstream::{
     consumer::{pull::Config as ConsumerConfig, AckPolicy, DeliverPolicy},
     stream::Config,
 };
 use clap::{Parser, Subcommand};
-use futures::TryStreamExt;
+use futures::{StreamExt as _, TryStreamExt};
 use tokio::io::{stdin, stdout, AsyncReadExt, AsyncWriteExt};
 use tokio::time::Instant;
 use tracing::debug;
+use wasmcloud_core::rpc::{INVOCATION_CLAIMS_HEADER, INVOCATION_DELEGATION_HEADER};
+use wrpc_transport::Invoke as _;
 
 use super::{CliConnectionOpts, CommandOutput};
 use crate::config::WashConnectionOptions;
+use crate::registry::OciPullOptions;
+use crate::wrpc_value::{ComponentWit, WitFunction};
 use crate::{
-    capture::{ReadCapture, WriteCapture},
-    spier::{ObservedInvocation, ObservedMessage},
+    capture::{ReadCapture, SerializableMessage, WriteCapture},
+    spier::{InvocationSubject, ObservedInvocation, ObservedKind, ObservedMessage},
 };
 
 pub const CAPTURE_STREAM_NAME: &str = "wash-capture";
@@ -61,88 +66,403 @@ pub struct CaptureReplayCommand {
     pub target_id: Option<String>,
 
     /// Whether or not to step through the replay one message at a time
-    #[clap(name = "interactive", long = "interactive")]
+    #[clap(name = "interactive", long = "interactive", conflicts_with = "invoke")]
     pub interactive: bool,
 
+    /// Re-invoke the captured invocations on the lattice and report the ones whose results do not
+    /// match the captured results
+    #[clap(name = "invoke", long = "invoke")]
+    pub invoke: bool,
+
+    /// A component ID to send all re-invoked invocations to, rather than the component or provider
+    /// they were captured for
+    #[clap(name = "invoke_target", long = "invoke-target", requires = "invoke")]
+    pub invoke_target: Option<String>,
+
+    /// Re-invoke the captured invocations as fast as possible, rather than preserving their
+    /// relative timing
+    #[clap(name = "max_speed", long = "max-speed", requires = "invoke")]
+    pub max_speed: bool,
+
+    #[clap(flatten)]
+    pub opts: CliConnectionOpts,
+
     /// The file path to the capture file to read from
     #[clap(name = "capturefile")]
     pub capture_file_path: PathBuf,
 }
 
+/// The maximum number of captured invocations re-invoked concurrently
+const MAX_CONCURRENT_REPLAYS: usize = 64;
+
 pub async fn handle_replay_command(cmd: CaptureReplayCommand) -> Result<CommandOutput> {
-    let capture = ReadCapture::load(cmd.capture_file_path).await?;
-
-    let filtered = capture.messages.into_iter().filter_map(|msg| {
-        // lattice.component.wrpc.0.0.1.operation.function
-        let subject_parts = msg.subject.split('.').collect::<Vec<_>>();
-        let component_id = subject_parts.get(1);
-        let operation = subject_parts.get(6);
-        let function = subject_parts.get(7);
-
-        if component_id.is_none() || operation.is_none() || function.is_none() {
-            debug!("Received invocation with invalid subject: {}", msg.subject);
-            return None;
+    let capture = ReadCapture::load(&cmd.capture_file_path).await?;
+
+    // Results are published on the `.results` subject of the reply inbox of the invocation, so
+    // collect them keyed by the reply subject to correlate them with the invocations
+    let mut results: HashMap<&str, (time::OffsetDateTime, Vec<u8>)> = HashMap::new();
+    for msg in &capture.messages {
+        if let Some(reply) = msg.subject.strip_suffix(".results") {
+            results
+                .entry(reply)
+                .or_insert_with(|| (msg.published, Vec::new()))
+                .1
+                .extend_from_slice(&msg.payload);
         }
-        let target = component_id.unwrap();
-        let operation = format!("{}.{}", operation.unwrap(), function.unwrap());
-
-        let source = msg
-            .headers
-            .and_then(|headers| {
-                headers
-                    .get("source-id")
-                    .map(std::string::ToString::to_string)
-            })
-            .unwrap_or_default();
+    }
 
-        let from = match cmd.source_id {
-            Some(ref id) if id == source.as_str() => id.to_string(),
-            Some(_) => {
+    let filtered = capture
+        .messages
+        .iter()
+        .filter_map(|msg| {
+            let Some(subject) = InvocationSubject::parse(&msg.subject) else {
+                if !msg.subject.ends_with(".results") {
+                    debug!("Received invocation with invalid subject: {}", msg.subject);
+                }
                 return None;
+            };
+
+            let source = msg
+                .headers
+                .as_ref()
+                .and_then(|headers| {
+                    headers
+                        .get("source-id")
+                        .map(std::string::ToString::to_string)
+                })
+                .unwrap_or_default();
+
+            match cmd.source_id {
+                Some(ref id) if *id != source => return None,
+                _ => {}
             }
-            None => source,
-        };
-
-        let to = match cmd.target_id {
-            Some(ref id) if id == target => id.to_string(),
-            Some(_) => {
-                return None;
+            match cmd.target_id {
+                Some(ref id) if id != subject.target => return None,
+                _ => {}
             }
-            None => (*target).to_string(),
-        };
+            Some((subject, source, msg))
+        })
+        .collect::<Vec<_>>();
 
-        Some((
+    let wits = get_component_wits(
+        &capture.inventory,
+        filtered
+            .iter()
+            .flat_map(|(subject, source, _)| [subject.target, source.as_str()]),
+    )
+    .await;
+
+    if cmd.invoke {
+        return replay(&cmd, filtered, &results, &wits).await;
+    }
+
+    let mut out = stdout();
+    for (subject, source, msg) in filtered {
+        let func = captured_function(&wits, &subject, &source);
+        let mut observed = vec![(
+            msg.published,
             ObservedInvocation {
                 timestamp: chrono::Local::now(),
-                from,
-                to,
-                operation,
-                message: ObservedMessage::parse(msg.payload.to_vec()),
+                from: source.clone(),
+                to: subject.target.to_string(),
+                operation: subject.operation(),
+                reply: msg.reply.clone(),
+                kind: ObservedKind::Params,
+                message: ObservedMessage::decode(
+                    func.as_ref(),
+                    ObservedKind::Params,
+                    msg.payload.to_vec(),
+                ),
             },
-            msg.published,
-        ))
-    });
-
-    let mut out = stdout();
-    for (msg, published) in filtered {
-        println!(
-            r#"
+        )];
+        if let Some((published, payload)) =
+            msg.reply.as_deref().and_then(|reply| results.remove(reply))
+        {
+            observed.push((
+                published,
+                ObservedInvocation {
+                    timestamp: chrono::Local::now(),
+                    from: subject.target.to_string(),
+                    to: source,
+                    operation: subject.operation(),
+                    reply: msg.reply.clone(),
+                    kind: ObservedKind::Results,
+                    message: ObservedMessage::decode(func.as_ref(), ObservedKind::Results, payload),
+                },
+            ));
+        }
+        for (published, msg) in observed {
+            println!(
+                r#"
 [{}]
 From: {}  To: {}
 
-Operation: {}
+Operation: {} ({})
+Reply: {}
 Message: {}"#,
-            published, msg.from, msg.to, msg.operation, msg.message
-        );
-        if cmd.interactive {
-            out.write_all(b"Press Enter to continue...").await.unwrap();
-            out.flush().await.unwrap();
-            stdin().read_exact(&mut [0]).await.unwrap();
+                published,
+                msg.from,
+                msg.to,
+                msg.operation,
+                msg.kind,
+                msg.reply.as_deref().unwrap_or("none"),
+                msg.message
+            );
+            if cmd.interactive {
+                out.write_all(b"Press Enter to continue...").await.unwrap();
+                out.flush().await.unwrap();
+                stdin().read_exact(&mut [0]).await.unwrap();
+            }
         }
     }
     Ok(CommandOutput::default())
 }
 
+/// Looks up the function invoked by a captured invocation in the WIT of the target, which exports
+/// it, or the WIT of the source, which imports it
+fn captured_function<'a>(
+    wits: &'a HashMap<String, ComponentWit>,
+    subject: &InvocationSubject<'_>,
+    source: &str,
+) -> Option<WitFunction<'a>> {
+    wits.get(subject.target)
+        .and_then(|wit| wit.export(subject.instance, subject.func))
+        .or_else(|| {
+            wits.get(source)
+                .and_then(|wit| wit.import(subject.instance, subject.func))
+        })
+}
+
+/// The outcome of re-invoking a captured invocation
+enum ReplayOutcome {
+    /// The results match the captured results
+    Match,
+    /// The results differ from the captured results
+    Mismatch {
+        captured: ObservedMessage,
+        replayed: ObservedMessage,
+    },
+    /// The invocation failed
+    Failed(anyhow::Error),
+    /// No results were captured for the invocation, so the results cannot be compared
+    Unverified,
+}
+
+impl ReplayOutcome {
+    /// Compares the results of a replayed invocation with the captured results, decoding them
+    /// using the WIT of the invoked function if they differ
+    fn classify(
+        captured: Option<&[u8]>,
+        replayed: Result<Vec<u8>>,
+        func: Option<&WitFunction<'_>>,
+    ) -> Self {
+        match (captured, replayed) {
+            (_, Err(err)) => Self::Failed(err),
+            (None, Ok(_)) => Self::Unverified,
+            (Some(captured), Ok(replayed)) if captured == replayed => Self::Match,
+            (Some(captured), Ok(replayed)) => Self::Mismatch {
+                captured: ObservedMessage::decode(func, ObservedKind::Results, captured.to_vec()),
+                replayed: ObservedMessage::decode(func, ObservedKind::Results, replayed),
+            },
+        }
+    }
+
+    /// Describes why the replayed invocation did not succeed, if it did not
+    fn details(&self) -> Option<String> {
+        match self {
+            Self::Match | Self::Unverified => None,
+            Self::Failed(err) => Some(format!("Invocation failed: {err:#}")),
+            Self::Mismatch { captured, replayed } => Some(format!(
+                "Captured results: {captured}\nReplayed results: {replayed}"
+            )),
+        }
+    }
+}
+
+/// Re-invokes the captured invocations on the lattice, comparing their results with the captured
+/// results
+async fn replay(
+    cmd: &CaptureReplayCommand,
+    invocations: Vec<(InvocationSubject<'_>, String, &SerializableMessage)>,
+    results: &HashMap<&str, (time::OffsetDateTime, Vec<u8>)>,
+    wits: &HashMap<String, ComponentWit>,
+) -> Result<CommandOutput> {
+    let wco: WashConnectionOptions = cmd.opts.clone().try_into()?;
+    let lattice = wco.lattice.clone().unwrap_or_else(|| "default".to_string());
+    let timeout = Duration::from_millis(wco.timeout_ms);
+    let nats_client = wco.into_nats_client().await?;
+
+    // Create a client per target up front, each one subscribes to its own inbox
+    let mut clients = HashMap::new();
+    for (subject, ..) in &invocations {
+        let target = cmd.invoke_target.as_deref().unwrap_or(subject.target);
+        if !clients.contains_key(target) {
+            let client = wrpc_transport_nats::Client::new(
+                nats_client.clone(),
+                format!("{lattice}.{target}"),
+                None,
+            )
+            .await
+            .with_context(|| format!("failed to create wRPC client for `{target}`"))?;
+            clients.insert(target.to_string(), client);
+        }
+    }
+
+    let first_published = invocations.first().map(|(_, _, msg)| msg.published);
+    let start = Instant::now();
+    let outcomes = futures::stream::iter(invocations.iter().map(|(subject, source, msg)| {
+        let target = cmd.invoke_target.as_deref().unwrap_or(subject.target);
+        let client = &clients[target];
+        let delay = first_published
+            .filter(|_| !cmd.max_speed)
+            .and_then(|first| Duration::try_from(msg.published - first).ok())
+            .unwrap_or_default();
+        async move {
+            tokio::time::sleep_until(start + delay).await;
+            let replayed = tokio::time::timeout(
+                timeout,
+                invoke_captured(client, subject, msg.headers.as_ref(), msg.payload.clone()),
+            )
+            .await
+            .context("timed out waiting for results")
+            .and_then(|res| res);
+            let captured = msg
+                .reply
+                .as_deref()
+                .and_then(|reply| results.get(reply))
+                .map(|(_, payload)| payload.as_slice());
+            let func = captured_function(wits, subject, source);
+            ReplayOutcome::classify(captured, replayed, func.as_ref())
+        }
+    }))
+    .buffered(MAX_CONCURRENT_REPLAYS)
+    .collect::<Vec<_>>()
+    .await;
+
+    let (mut matched, mut mismatched, mut failed, mut unverified) =
+        (0usize, 0usize, 0usize, 0usize);
+    let mut details = Vec::new();
+    for ((subject, source, msg), outcome) in invocations.iter().zip(outcomes) {
+        match outcome {
+            ReplayOutcome::Match => matched += 1,
+            ReplayOutcome::Unverified => unverified += 1,
+            ReplayOutcome::Failed(..) => failed += 1,
+            ReplayOutcome::Mismatch { .. } => mismatched += 1,
+        }
+        if let Some(outcome) = outcome.details() {
+            let target = cmd.invoke_target.as_deref().unwrap_or(subject.target);
+            details.push(format!(
+                "[{}]\nFrom: {source}  To: {target}\n\nOperation: {}\n{outcome}",
+                msg.published,
+                subject.operation()
+            ));
+        }
+    }
+
+    let summary = format!(
+        "Replayed {} invocations: {matched} matched, {mismatched} mismatched, {failed} failed, {unverified} without captured results",
+        invocations.len()
+    );
+    // Each invocation that did not succeed is a cause of the error, so that JSON output lists them
+    // in the error chain
+    let mut details = details.into_iter().rev();
+    if let Some(last) = details.next() {
+        let err = details.fold(anyhow::anyhow!(last), anyhow::Error::context);
+        return Err(err.context(summary));
+    }
+    Ok(CommandOutput::new(
+        summary,
+        [
+            ("matched".to_string(), matched.into()),
+            ("mismatched".to_string(), mismatched.into()),
+            ("failed".to_string(), failed.into()),
+            ("unverified".to_string(), unverified.into()),
+        ]
+        .into(),
+    ))
+}
+
+/// Invokes the function of a captured invocation with the captured parameters, returning the
+/// encoded results
+async fn invoke_captured(
+    client: &wrpc_transport_nats::Client,
+    subject: &InvocationSubject<'_>,
+    headers: Option<&async_nats::HeaderMap>,
+    params: bytes::Bytes,
+) -> Result<Vec<u8>> {
+    // Invocation claims are bound to the original invocation and would fail verification
+    let headers = headers.map(|headers| {
+        let mut replayed = async_nats::HeaderMap::new();
+        for (name, values) in headers.iter() {
+            let name_str: &str = name.as_ref();
+            if name_str == INVOCATION_CLAIMS_HEADER || name_str == INVOCATION_DELEGATION_HEADER {
+                continue;
+            }
+            for value in values {
+                replayed.append(name.clone(), value.clone());
+            }
+        }
+        replayed
+    });
+    let (mut outgoing, mut incoming) = client
+        .invoke(
+            headers,
+            subject.instance,
+            subject.func,
+            params,
+            &[] as &[&[Option<usize>]],
+        )
+        .await
+        .context("failed to invoke function")?;
+    outgoing
+        .shutdown()
+        .await
+        .context("failed to shutdown parameter channel")?;
+    let mut results = Vec::new();
+    incoming
+        .read_to_end(&mut results)
+        .await
+        .context("failed to receive results")?;
+    Ok(results)
+}
+
+/// Fetches the WIT of the components in `inventory` with the given ids. Components whose WIT
+/// cannot be resolved are skipped, their messages are not decoded
+async fn get_component_wits<'a>(
+    inventory: &[wasmcloud_control_interface::HostInventory],
+    ids: impl IntoIterator<Item = &'a str>,
+) -> HashMap<String, ComponentWit> {
+    let ids: HashSet<_> = ids.into_iter().collect();
+    let image_refs: HashMap<_, _> = inventory
+        .iter()
+        .flat_map(|host| host.components())
+        .filter(|component| ids.contains(component.id()))
+        .map(|component| {
+            (
+                component.id().to_string(),
+                component.image_ref().to_string(),
+            )
+        })
+        .collect();
+    futures::future::join_all(image_refs.into_iter().map(|(id, image_ref)| async move {
+        let options = OciPullOptions {
+            allow_latest: true,
+            ..Default::default()
+        };
+        match ComponentWit::fetch(&image_ref, options).await {
+            Ok(wit) => Some((id, wit)),
+            Err(err) => {
+                debug!(?err, id, image_ref, "failed to get component WIT");
+                None
+            }
+        }
+    }))
+    .await
+    .into_iter()
+    .flatten()
+    .collect()
+}
+
 /// Handles the spy command, printing all output to stdout until the command is interrupted
 pub async fn handle_command(cmd: CaptureCommand) -> Result<CommandOutput> {
     let wco: WashConnectionOptions = cmd.opts.try_into()?;
@@ -192,7 +512,12 @@ pub async fn enable(
         max_age: window_size,
         // This needs to be set or it breaks invocations
         no_ack: true,
-        subjects: vec![format!("wasmbus.rpc.{}.>", lattice_id)],
+        // wRPC invocations and their results, which are published on the `.results` subject of
+        // the reply inbox of the invocation
+        subjects: vec![
+            format!("{lattice_id}.*.wrpc.>"),
+            "_INBOX.*.*.results".to_string(),
+        ],
         ..Default::default()
     })
     .await
@@ -339,3 +664,47 @@ async fn get_all_inventory(
 fn stream_name(lattice_id: &str) -> String {
     format!("{CAPTURE_STREAM_NAME}-{lattice_id}")
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_replay_outcome() {
+        assert!(matches!(
+            ReplayOutcome::classify(Some(b"ok"), Ok(b"ok".to_vec()), None),
+            ReplayOutcome::Match
+        ));
+        assert!(matches!(
+            ReplayOutcome::classify(None, Ok(b"ok".to_vec()), None),
+            ReplayOutcome::Unverified
+        ));
+        assert!(ReplayOutcome::Match.details().is_none());
+        assert!(ReplayOutcome::Unverified.details().is_none());
+
+        let outcome = ReplayOutcome::classify(Some(b"ok"), Ok(b"not ok".to_vec()), None);
+        let ReplayOutcome::Mismatch { captured, replayed } = &outcome else {
+            panic!("results that differ should be a mismatch");
+        };
+        assert_eq!(captured.to_string(), "ok");
+        assert_eq!(replayed.to_string(), "not ok");
+        assert_eq!(
+            outcome.details().as_deref(),
+            Some("Captured results: ok\nReplayed results: not ok")
+        );
+
+        // Failed invocations are failures whether or not results were captured
+        for captured in [Some(&b"ok"[..]), None] {
+            let outcome = ReplayOutcome::classify(
+                captured,
+                Err(anyhow::anyhow!("timed out waiting for results")),
+                None,
+            );
+            assert!(matches!(outcome, ReplayOutcome::Failed(..)));
+            assert_eq!(
+                outcome.details().as_deref(),
+                Some("Invocation failed: timed out waiting for results")
+            );
+        }
+    }
+}
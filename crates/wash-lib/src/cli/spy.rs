@@ -25,6 +25,9 @@ pub async fn handle_command(cmd: SpyCommand) -> Result<CommandOutput> {
     let mut spier = Spier::new(&cmd.component_id, &ctl_client, &nats_client).await?;
 
     println!("Spying on component {}\n", spier.component_id());
+    if !spier.decodes_messages() {
+        println!("Unable to resolve the WIT of the component, messages will not be decoded\n");
+    }
 
     while let Some(msg) = spier.next().await {
         println!(
@@ -32,9 +35,16 @@ pub async fn handle_command(cmd: SpyCommand) -> Result<CommandOutput> {
 [{}]
 From: {:<25} To: {:<25}
 
-Operation: {}
+Operation: {} ({})
+Reply: {}
 Message: {}"#,
-            msg.timestamp, msg.from, msg.to, msg.operation, msg.message
+            msg.timestamp,
+            msg.from,
+            msg.to,
+            msg.operation,
+            msg.kind,
+            msg.reply.as_deref().unwrap_or("none"),
+            msg.message
         );
     }
 
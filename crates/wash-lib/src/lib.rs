@@ -46,6 +46,7 @@ pub mod registry;
 pub mod spier;
 #[cfg(feature = "nats")]
 pub mod wait;
+pub mod wrpc_value;
 
 #[cfg(feature = "plugin")]
 pub mod plugin;
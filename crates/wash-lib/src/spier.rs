@@ -1,10 +1,26 @@
-use std::task::Poll;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 use anyhow::Result;
 use chrono::{DateTime, Local};
 use futures::{Stream, StreamExt};
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tokio_stream::wrappers::ReceiverStream;
 use tracing::debug;
 
+use crate::registry::OciPullOptions;
+use crate::wrpc_value::{ComponentWit, WitFunction};
+
+/// The maximum amount of time to wait for the results of an observed invocation
+const RESULTS_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// The subject that the results of wRPC invocations are published on, which is the `.results`
+/// subject of the `_INBOX.<client>.<invocation>` reply inbox of the invocation. Results of
+/// invocations sent by clients using a custom inbox prefix are not observed
+const RESULTS_SUBJECT: &str = "_INBOX.*.*.results";
+
 /// A struct that represents an invocation that was observed by the spier.
 #[derive(Debug)]
 pub struct ObservedInvocation {
@@ -16,11 +32,32 @@ pub struct ObservedInvocation {
     pub to: String,
     /// The operation that was invoked
     pub operation: String,
-    /// The inner message that was received. We will attempt to parse the inner message from CBOR
-    /// and JSON into a JSON string and fall back to the raw bytes if we are unable to do so
+    /// The reply subject of the invocation. Results are published on a subject derived from it, so
+    /// it correlates results with the invocation they belong to
+    pub reply: Option<String>,
+    /// Whether the message contains the parameters or the results of the invocation
+    pub kind: ObservedKind,
+    /// The inner message that was received. We will attempt to decode the inner message using the
+    /// WIT of the component into JSON and fall back to the raw bytes if we are unable to do so
     pub message: ObservedMessage,
 }
 
+/// The part of an invocation that an [`ObservedInvocation`] contains
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ObservedKind {
+    Params,
+    Results,
+}
+
+impl std::fmt::Display for ObservedKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ObservedKind::Params => write!(f, "params"),
+            ObservedKind::Results => write!(f, "results"),
+        }
+    }
+}
+
 /// A inner message that we've seen in an invocation message. This will either be a raw bytes or a
 /// parsed value if it was a format we recognized.
 ///
@@ -30,6 +67,8 @@ pub struct ObservedInvocation {
 pub enum ObservedMessage {
     Raw(Vec<u8>),
     Parsed(String),
+    /// Value decoded using the WIT of the invoked function
+    Decoded(serde_json::Value),
 }
 
 impl std::fmt::Display for ObservedMessage {
@@ -39,6 +78,7 @@ impl std::fmt::Display for ObservedMessage {
             ObservedMessage::Parsed(v) => {
                 write!(f, "{v}")
             }
+            ObservedMessage::Decoded(v) => write!(f, "{v:#}"),
         }
     }
 }
@@ -48,18 +88,109 @@ impl ObservedMessage {
     pub fn parse(data: Vec<u8>) -> Self {
         Self::Parsed(String::from_utf8_lossy(&data).to_string())
     }
+
+    /// Decodes the given invocation message using the WIT of the invoked function, falling back to
+    /// [`ObservedMessage::parse`] if the function is unknown or the message cannot be decoded
+    #[must_use]
+    pub fn decode(func: Option<&WitFunction<'_>>, kind: ObservedKind, data: Vec<u8>) -> Self {
+        let Some(func) = func else {
+            return Self::parse(data);
+        };
+        let decoded = match kind {
+            ObservedKind::Params => func.decode_params(&data),
+            ObservedKind::Results => func.decode_results(&data),
+        };
+        match decoded {
+            Ok(v) => Self::Decoded(v),
+            Err(err) => {
+                debug!(?err, %kind, "failed to decode invocation message");
+                Self::parse(data)
+            }
+        }
+    }
+}
+
+/// The parts of a wRPC invocation subject, `<lattice>.<target>.wrpc.0.0.1.<instance>.<function>`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InvocationSubject<'a> {
+    /// The id of the component or provider that is invoked
+    pub target: &'a str,
+    /// The invoked instance, e.g. `wasi:keyvalue/store@0.2.0-draft`. Empty for functions exported
+    /// or imported by a component directly
+    pub instance: &'a str,
+    /// The invoked function
+    pub func: &'a str,
+}
+
+impl<'a> InvocationSubject<'a> {
+    /// Parses a wRPC invocation subject, returning `None` if it is not one
+    #[must_use]
+    pub fn parse(subject: &'a str) -> Option<Self> {
+        let mut parts = subject.splitn(3, '.');
+        let _lattice = parts.next()?;
+        let target = parts.next()?;
+        let operation = parts.next()?.strip_prefix("wrpc.0.0.1.")?;
+        // Instances contain dots in their version and resource function names contain dots
+        // (e.g. `[method]bucket.get`), but resource function names always start with `[`
+        let (instance, func) = if operation.starts_with('[') {
+            ("", operation)
+        } else if let Some(i) = operation.find(".[") {
+            (&operation[..i], &operation[i + 1..])
+        } else {
+            operation.rsplit_once('.').unwrap_or(("", operation))
+        };
+        if target.is_empty() || func.is_empty() {
+            return None;
+        }
+        Some(Self {
+            target,
+            instance,
+            func,
+        })
+    }
+
+    /// Returns the operation in the `<instance>.<function>` format
+    #[must_use]
+    pub fn operation(&self) -> String {
+        if self.instance.is_empty() {
+            self.func.to_string()
+        } else {
+            format!("{}.{}", self.instance, self.func)
+        }
+    }
+}
+
+/// Looks up the function invoked by `subject` in the WIT of the component with id `component_id`.
+/// The component exports the function if it is the target of the invocation and imports it
+/// otherwise
+#[must_use]
+pub fn invoked_function<'a>(
+    wit: &'a ComponentWit,
+    component_id: &str,
+    subject: &InvocationSubject<'_>,
+) -> Option<WitFunction<'a>> {
+    if subject.target == component_id {
+        wit.export(subject.instance, subject.func)
+    } else {
+        wit.import(subject.instance, subject.func)
+    }
 }
 
 /// A struct that can spy on the RPC messages sent to and from an component, consumable as a stream
 pub struct Spier {
-    stream: futures::stream::SelectAll<async_nats::Subscriber>,
+    stream: ReceiverStream<ObservedInvocation>,
+    task: JoinHandle<()>,
     component_id: String,
     friendly_name: Option<String>,
+    decodes: bool,
 }
 
 impl Spier {
     /// Creates a new Spier instance for the given component. Will return an error if the component cannot
     /// be found or if there are connection issues
+    ///
+    /// The WIT of the component is fetched using its image reference to decode the messages. If
+    /// that fails, messages are not decoded
     pub async fn new(
         component_id: &str,
         ctl_client: &wasmcloud_control_interface::Client,
@@ -67,6 +198,10 @@ impl Spier {
     ) -> Result<Self> {
         let linked_component = get_linked_components(component_id, ctl_client).await?;
 
+        // Subscribe to results before any invocation can be observed, so that results published
+        // right after an invocation are not missed
+        let results = nats_client.subscribe(RESULTS_SUBJECT).await?;
+
         let lattice = ctl_client.lattice();
         let rpc_topic = format!("{lattice}.{component_id}.wrpc.>");
         let component_stream = nats_client.subscribe(rpc_topic).await?;
@@ -82,10 +217,24 @@ impl Spier {
 
         let stream = futures::stream::select_all(subs);
 
+        let wit = match get_component_wit(component_id, ctl_client).await {
+            Ok(wit) => Some(Arc::new(wit)),
+            Err(err) => {
+                debug!(?err, component_id, "failed to get component WIT");
+                None
+            }
+        };
+        let decodes = wit.is_some();
+
+        let (tx, rx) = mpsc::channel(64);
+        let task = tokio::spawn(spy(stream, results, component_id.to_string(), wit, tx));
+
         Ok(Self {
-            stream,
+            stream: ReceiverStream::new(rx),
+            task,
             component_id: component_id.to_string(),
             friendly_name: None,
+            decodes,
         })
     }
 
@@ -95,6 +244,17 @@ impl Spier {
             .as_deref()
             .unwrap_or_else(|| self.component_id.as_ref())
     }
+
+    /// Returns whether the WIT of the component could be resolved to decode messages
+    pub fn decodes_messages(&self) -> bool {
+        self.decodes
+    }
+}
+
+impl Drop for Spier {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
 }
 
 impl Stream for Spier {
@@ -103,52 +263,197 @@ impl Stream for Spier {
         mut self: std::pin::Pin<&mut Self>,
         cx: &mut std::task::Context<'_>,
     ) -> std::task::Poll<Option<Self::Item>> {
-        match self.stream.poll_next_unpin(cx) {
-            Poll::Ready(None) => Poll::Ready(None),
-            Poll::Ready(Some(msg)) => {
-                // <lattice>.<component>.wrpc.0.0.1.<operation>@<versionX.Y.Z>.<function>
-                let mut subject_parts = msg.subject.split('.');
-                subject_parts.next(); // Skip the lattice
-                let component_id = subject_parts.next();
-                // Skip "wrpc.0.0.1", collect the rest
-                let operation = subject_parts.skip(4).collect::<Vec<_>>();
-
-                // The length assertion is to ensure that at least the `operation.function` is present since the
-                // version is technically optional.
-                if component_id.is_none() || operation.len() < 2 {
+        self.stream.poll_next_unpin(cx)
+    }
+}
+
+/// Results of an invocation that are being collected, keyed by the reply subject of the invocation
+struct PendingResults {
+    /// When the first params or results message of the invocation was observed
+    since: Instant,
+    /// The results to send along with the subject of the invocation, set once the params of the
+    /// invocation have been observed
+    invocation: Option<(ObservedInvocation, String)>,
+    payload: Vec<u8>,
+    /// Whether the terminating empty message of the results stream has been received
+    complete: bool,
+}
+
+impl PendingResults {
+    fn new() -> Self {
+        Self {
+            since: Instant::now(),
+            invocation: None,
+            payload: Vec::new(),
+            complete: false,
+        }
+    }
+}
+
+/// Observes the invocations received on `invocations` and their results received on `results`
+///
+/// Results are matched to invocations using the reply subject. Since `results` is subscribed to
+/// before any invocation is observed, results can arrive before their invocation and are kept
+/// until it is observed or [`RESULTS_TIMEOUT`] passes, which also drops the results of invocations
+/// of other components
+async fn spy(
+    mut invocations: futures::stream::SelectAll<async_nats::Subscriber>,
+    mut results: async_nats::Subscriber,
+    component_id: String,
+    wit: Option<Arc<ComponentWit>>,
+    tx: mpsc::Sender<ObservedInvocation>,
+) {
+    let mut pending: HashMap<String, PendingResults> = HashMap::new();
+    let mut prune = tokio::time::interval(RESULTS_TIMEOUT);
+    loop {
+        let reply = tokio::select! {
+            Some(msg) = invocations.next() => {
+                let Some(subject) = InvocationSubject::parse(&msg.subject) else {
                     debug!("Received invocation with invalid subject: {}", msg.subject);
-                    cx.waker().wake_by_ref();
-                    return Poll::Pending;
-                }
-                let component_id = component_id.unwrap();
+                    continue;
+                };
 
-                let (from, to) = if component_id == self.component_id {
+                let (from, to) = if subject.target == component_id {
                     // Attempt to get the source from the message header
                     let from = msg
                         .headers
+                        .as_ref()
                         .and_then(|headers| headers.get("source-id").map(ToString::to_string))
                         .unwrap_or_else(|| "linked component".to_string());
-                    (from, (*component_id).to_string())
+                    (from, subject.target.to_string())
                 } else {
-                    (self.component_id.to_string(), (*component_id).to_string())
+                    (component_id.to_string(), subject.target.to_string())
                 };
 
-                // NOTE(thomastaylor312): Ideally we'd consume `msg.payload` above with a
-                // `Cursor` and `from_reader` and then manually reconstruct the acking using the
-                // message context, but I didn't want to waste time optimizing yet
-                Poll::Ready(Some(ObservedInvocation {
+                let func = wit
+                    .as_deref()
+                    .and_then(|wit| invoked_function(wit, &component_id, &subject));
+                let reply = msg.reply.as_ref().map(ToString::to_string);
+                let invocation = ObservedInvocation {
                     timestamp: Local::now(),
-                    from,
-                    to,
-                    operation: operation.join("."),
-                    message: ObservedMessage::parse(msg.payload.to_vec()),
-                }))
+                    from: from.clone(),
+                    to: to.clone(),
+                    operation: subject.operation(),
+                    reply: reply.clone(),
+                    kind: ObservedKind::Params,
+                    message: ObservedMessage::decode(
+                        func.as_ref(),
+                        ObservedKind::Params,
+                        msg.payload.to_vec(),
+                    ),
+                };
+                if tx.send(invocation).await.is_err() {
+                    return;
+                }
+                let Some(reply) = reply else {
+                    continue;
+                };
+                let results = ObservedInvocation {
+                    timestamp: Local::now(),
+                    from: to,
+                    to: from,
+                    operation: subject.operation(),
+                    reply: Some(reply.clone()),
+                    kind: ObservedKind::Results,
+                    message: ObservedMessage::Raw(Vec::default()),
+                };
+                pending
+                    .entry(reply.clone())
+                    .or_insert_with(PendingResults::new)
+                    .invocation = Some((results, msg.subject.to_string()));
+                reply
             }
-            Poll::Pending => Poll::Pending,
+            Some(msg) = results.next() => {
+                let Some(reply) = msg.subject.strip_suffix(".results") else {
+                    continue;
+                };
+                // The results are a wRPC stream, which is terminated by an empty message
+                let results = pending
+                    .entry(reply.to_string())
+                    .or_insert_with(PendingResults::new);
+                if msg.payload.is_empty() {
+                    results.complete = true;
+                } else {
+                    results.payload.extend_from_slice(&msg.payload);
+                }
+                reply.to_string()
+            }
+            _ = prune.tick() => {
+                pending.retain(|reply, results| {
+                    let keep = results.since.elapsed() < RESULTS_TIMEOUT;
+                    if !keep && results.invocation.is_some() {
+                        debug!(reply, "no results observed for invocation");
+                    }
+                    keep
+                });
+                continue;
+            }
+            else => return,
+        };
+
+        if !pending
+            .get(&reply)
+            .is_some_and(|results| results.complete && results.invocation.is_some())
+        {
+            continue;
+        }
+        let Some(PendingResults {
+            invocation: Some((mut results, subject)),
+            payload,
+            ..
+        }) = pending.remove(&reply)
+        else {
+            continue;
+        };
+        let func = wit.as_deref().and_then(|wit| {
+            InvocationSubject::parse(&subject)
+                .and_then(|subject| invoked_function(wit, &component_id, &subject))
+        });
+        results.timestamp = Local::now();
+        results.message = ObservedMessage::decode(func.as_ref(), ObservedKind::Results, payload);
+        if tx.send(results).await.is_err() {
+            return;
         }
     }
 }
 
+/// Fetches the WIT of the component with the given id, using its image reference from the host
+/// inventories
+async fn get_component_wit(
+    component_id: &str,
+    ctl_client: &wasmcloud_control_interface::Client,
+) -> Result<ComponentWit> {
+    let hosts = ctl_client
+        .get_hosts()
+        .await
+        .map_err(|e| anyhow::anyhow!("Unable to get hosts: {e:?}"))?;
+    for host in hosts.into_iter().filter_map(|host| host.into_data()) {
+        let Some(inventory) = ctl_client
+            .get_host_inventory(host.id())
+            .await
+            .map_err(|e| anyhow::anyhow!("Unable to get host inventory: {e:?}"))?
+            .into_data()
+        else {
+            continue;
+        };
+        if let Some(component) = inventory
+            .components()
+            .iter()
+            .find(|component| component.id() == component_id)
+        {
+            return ComponentWit::fetch(
+                component.image_ref(),
+                OciPullOptions {
+                    allow_latest: true,
+                    ..Default::default()
+                },
+            )
+            .await;
+        }
+    }
+    anyhow::bail!("component `{component_id}` not found in any host inventory")
+}
+
 #[derive(Debug)]
 struct ProviderDetails {
     id: String,
@@ -186,3 +491,42 @@ async fn get_linked_components(
 
     Ok(details)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::InvocationSubject;
+
+    #[test]
+    fn parse_invocation_subject() {
+        assert_eq!(
+            InvocationSubject::parse("default.kv.wrpc.0.0.1.wasi:keyvalue/store@0.2.0-draft.get"),
+            Some(InvocationSubject {
+                target: "kv",
+                instance: "wasi:keyvalue/store@0.2.0-draft",
+                func: "get",
+            })
+        );
+        assert_eq!(
+            InvocationSubject::parse(
+                "default.blobs.wrpc.0.0.1.wasi:blobstore/container@0.2.0-draft.[method]container.name"
+            ),
+            Some(InvocationSubject {
+                target: "blobs",
+                instance: "wasi:blobstore/container@0.2.0-draft",
+                func: "[method]container.name",
+            })
+        );
+        assert_eq!(
+            InvocationSubject::parse("default.component.wrpc.0.0.1.run"),
+            Some(InvocationSubject {
+                target: "component",
+                instance: "",
+                func: "run",
+            })
+        );
+        assert_eq!(
+            InvocationSubject::parse("wasmbus.ctl.v1.default.host.ping"),
+            None
+        );
+    }
+}
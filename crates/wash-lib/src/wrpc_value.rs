@@ -0,0 +1,419 @@
+//! Decoding of wRPC-encoded invocation parameters and results into JSON, using the WIT of the
+//! component that is either the target or the source of the invocation
+
+use core::fmt::Write as _;
+
+use std::path::Path;
+
+use anyhow::{bail, ensure, Context as _, Result};
+use serde_json::{Map, Number, Value};
+use wit_parser::{
+    Function, Handle, Resolve, Results, Type, TypeDefKind, WorldId, WorldItem, WorldKey,
+};
+
+use crate::registry::{get_oci_artifact, OciPullOptions};
+
+/// The WIT world of a component, used to look up the types of the functions it imports and exports
+pub struct ComponentWit {
+    resolve: Resolve,
+    world: WorldId,
+}
+
+impl ComponentWit {
+    /// Decodes the WIT world embedded in the given component binary
+    pub fn from_component(wasm: &[u8]) -> Result<Self> {
+        match wit_component::decode(wasm).context("failed to decode WIT from component")? {
+            wit_component::DecodedWasm::Component(resolve, world) => Ok(Self { resolve, world }),
+            wit_component::DecodedWasm::WitPackage(..) => {
+                bail!("artifact is a WIT package, not a component")
+            }
+        }
+    }
+
+    /// Fetches the component from the given image reference, which may either be a `file://` URL
+    /// or an OCI reference, and decodes its WIT world
+    pub async fn fetch(image_ref: &str, options: OciPullOptions) -> Result<Self> {
+        let url_or_file = image_ref.strip_prefix("file://").unwrap_or(image_ref);
+        let wasm = if Path::new(url_or_file).is_file() {
+            tokio::fs::read(url_or_file)
+                .await
+                .with_context(|| format!("failed to read component from [{url_or_file}]"))?
+        } else {
+            get_oci_artifact(url_or_file.to_string(), None, options)
+                .await
+                .with_context(|| format!("failed to fetch component [{image_ref}]"))?
+        };
+        Self::from_component(&wasm)
+    }
+
+    /// Returns the function exported by the component for the given wRPC instance and function
+    /// name, if any
+    #[must_use]
+    pub fn export(&self, instance: &str, func: &str) -> Option<WitFunction<'_>> {
+        let world = &self.resolve.worlds[self.world];
+        self.find(world.exports.iter(), instance, func)
+    }
+
+    /// Returns the function imported by the component for the given wRPC instance and function
+    /// name, if any
+    #[must_use]
+    pub fn import(&self, instance: &str, func: &str) -> Option<WitFunction<'_>> {
+        let world = &self.resolve.worlds[self.world];
+        self.find(world.imports.iter(), instance, func)
+    }
+
+    fn find<'a>(
+        &'a self,
+        mut items: impl Iterator<Item = (&'a WorldKey, &'a WorldItem)>,
+        instance: &str,
+        func: &str,
+    ) -> Option<WitFunction<'a>> {
+        // `wasi:http` handlers are translated to `wrpc:http` by the host, so the types in the WIT
+        // of the component do not describe the values on the wire
+        if instance.starts_with("wasi:http/") {
+            return None;
+        }
+        items.find_map(|(key, item)| match item {
+            WorldItem::Interface { id, .. } if self.resolve.name_world_key(key) == instance => {
+                self.resolve.interfaces[*id]
+                    .functions
+                    .get(func)
+                    .map(|func| WitFunction {
+                        resolve: &self.resolve,
+                        func,
+                    })
+            }
+            WorldItem::Function(f) if instance.is_empty() && f.name == func => Some(WitFunction {
+                resolve: &self.resolve,
+                func: f,
+            }),
+            _ => None,
+        })
+    }
+}
+
+/// A function of a [`ComponentWit`], which can decode the values of its invocations
+pub struct WitFunction<'a> {
+    resolve: &'a Resolve,
+    func: &'a Function,
+}
+
+impl WitFunction<'_> {
+    /// Decodes wRPC-encoded parameters of the function into a JSON object keyed by parameter name
+    pub fn decode_params(&self, mut buf: &[u8]) -> Result<Value> {
+        let mut params = Map::with_capacity(self.func.params.len());
+        for (name, ty) in &self.func.params {
+            let v = decode_value(self.resolve, ty, &mut buf)
+                .with_context(|| format!("failed to decode parameter `{name}`"))?;
+            params.insert(name.clone(), v);
+        }
+        ensure!(buf.is_empty(), "{} trailing parameter bytes", buf.len());
+        Ok(Value::Object(params))
+    }
+
+    /// Decodes wRPC-encoded results of the function. A single anonymous result is decoded as is,
+    /// named results are decoded into a JSON object keyed by result name
+    pub fn decode_results(&self, mut buf: &[u8]) -> Result<Value> {
+        let v = match &self.func.results {
+            Results::Anon(ty) => {
+                decode_value(self.resolve, ty, &mut buf).context("failed to decode result")?
+            }
+            Results::Named(results) if results.is_empty() => Value::Null,
+            Results::Named(results) => {
+                let mut vs = Map::with_capacity(results.len());
+                for (name, ty) in results {
+                    let v = decode_value(self.resolve, ty, &mut buf)
+                        .with_context(|| format!("failed to decode result `{name}`"))?;
+                    vs.insert(name.clone(), v);
+                }
+                Value::Object(vs)
+            }
+        };
+        ensure!(buf.is_empty(), "{} trailing result bytes", buf.len());
+        Ok(v)
+    }
+}
+
+fn read_bytes<'a>(buf: &mut &'a [u8], n: usize) -> Result<&'a [u8]> {
+    ensure!(buf.len() >= n, "unexpected end of value");
+    let (v, rest) = buf.split_at(n);
+    *buf = rest;
+    Ok(v)
+}
+
+fn read_u8(buf: &mut &[u8]) -> Result<u8> {
+    Ok(read_bytes(buf, 1)?[0])
+}
+
+fn read_uleb128(buf: &mut &[u8], bits: u32) -> Result<u64> {
+    let mut v = 0u64;
+    let mut shift = 0;
+    loop {
+        let b = read_u8(buf)?;
+        ensure!(shift < bits, "LEB128-encoded integer overflows {bits} bits");
+        v |= u64::from(b & 0x7f) << shift;
+        shift += 7;
+        if b & 0x80 == 0 {
+            ensure!(
+                bits == 64 || v >> bits == 0,
+                "LEB128-encoded integer overflows {bits} bits"
+            );
+            return Ok(v);
+        }
+    }
+}
+
+fn read_sleb128(buf: &mut &[u8], bits: u32) -> Result<i64> {
+    let mut v = 0i64;
+    let mut shift = 0;
+    loop {
+        let b = read_u8(buf)?;
+        ensure!(shift < bits, "LEB128-encoded integer overflows {bits} bits");
+        v |= i64::from(b & 0x7f) << shift;
+        shift += 7;
+        if b & 0x80 == 0 {
+            if shift < 64 && b & 0x40 != 0 {
+                v |= -1 << shift;
+            }
+            return Ok(v);
+        }
+    }
+}
+
+fn read_len(buf: &mut &[u8]) -> Result<usize> {
+    let n = read_uleb128(buf, 32)?;
+    usize::try_from(n).context("length does not fit in usize")
+}
+
+fn decode_float(v: f64) -> Value {
+    Number::from_f64(v).map_or_else(|| Value::String(v.to_string()), Value::Number)
+}
+
+/// Decodes a single wRPC-encoded value of type `ty`, advancing `buf` past it
+///
+/// `list<u8>` values are decoded as arrays of numbers like any other list, so that they cannot be
+/// mistaken for strings, resource handles are decoded as hex strings
+fn decode_value(resolve: &Resolve, ty: &Type, buf: &mut &[u8]) -> Result<Value> {
+    match ty {
+        Type::Bool => match read_u8(buf)? {
+            0 => Ok(Value::Bool(false)),
+            1 => Ok(Value::Bool(true)),
+            n => bail!("invalid bool value byte `{n}`"),
+        },
+        Type::U8 => Ok(read_u8(buf)?.into()),
+        Type::S8 => Ok(i8::from_le_bytes([read_u8(buf)?]).into()),
+        Type::U16 => Ok(read_uleb128(buf, 16)?.into()),
+        Type::U32 => Ok(read_uleb128(buf, 32)?.into()),
+        Type::U64 => Ok(read_uleb128(buf, 64)?.into()),
+        Type::S16 => Ok(read_sleb128(buf, 16)?.into()),
+        Type::S32 => Ok(read_sleb128(buf, 32)?.into()),
+        Type::S64 => Ok(read_sleb128(buf, 64)?.into()),
+        Type::F32 => {
+            let v = read_bytes(buf, 4)?;
+            let v = f32::from_le_bytes(v.try_into().context("invalid f32")?);
+            Ok(decode_float(v.into()))
+        }
+        Type::F64 => {
+            let v = read_bytes(buf, 8)?;
+            Ok(decode_float(f64::from_le_bytes(
+                v.try_into().context("invalid f64")?,
+            )))
+        }
+        Type::Char => {
+            let n = match buf.first() {
+                Some(b) if b & 0x80 == 0 => 1,
+                Some(b) if b & 0xe0 == 0xc0 => 2,
+                Some(b) if b & 0xf0 == 0xe0 => 3,
+                Some(b) if b & 0xf8 == 0xf0 => 4,
+                Some(b) => bail!("invalid UTF-8 leading byte `{b:#04x}`"),
+                None => bail!("unexpected end of value"),
+            };
+            let v = core::str::from_utf8(read_bytes(buf, n)?).context("invalid char")?;
+            Ok(Value::String(v.to_string()))
+        }
+        Type::String => {
+            let n = read_len(buf)?;
+            let v = core::str::from_utf8(read_bytes(buf, n)?).context("invalid string")?;
+            Ok(Value::String(v.to_string()))
+        }
+        Type::Id(id) => match &resolve.types[*id].kind {
+            TypeDefKind::Type(ty) => decode_value(resolve, ty, buf),
+            TypeDefKind::List(ty) => {
+                let n = read_len(buf)?;
+                // Do not trust the encoded length for the allocation, each element is at least a byte
+                let mut vs = Vec::with_capacity(n.min(buf.len()));
+                for _ in 0..n {
+                    vs.push(decode_value(resolve, ty, buf)?);
+                }
+                Ok(Value::Array(vs))
+            }
+            TypeDefKind::Record(ty) => {
+                let mut vs = Map::with_capacity(ty.fields.len());
+                for field in &ty.fields {
+                    let v = decode_value(resolve, &field.ty, buf).with_context(|| {
+                        format!("failed to decode record field `{}`", field.name)
+                    })?;
+                    vs.insert(field.name.clone(), v);
+                }
+                Ok(Value::Object(vs))
+            }
+            TypeDefKind::Tuple(ty) => ty
+                .types
+                .iter()
+                .map(|ty| decode_value(resolve, ty, buf))
+                .collect::<Result<_>>()
+                .map(Value::Array),
+            TypeDefKind::Variant(ty) => {
+                let discriminant = read_len(buf)?;
+                let case = ty
+                    .cases
+                    .get(discriminant)
+                    .with_context(|| format!("unknown variant discriminant `{discriminant}`"))?;
+                if let Some(ty) = &case.ty {
+                    let v = decode_value(resolve, ty, buf)?;
+                    Ok(Value::Object(
+                        [(case.name.clone(), v)].into_iter().collect(),
+                    ))
+                } else {
+                    Ok(Value::String(case.name.clone()))
+                }
+            }
+            TypeDefKind::Enum(ty) => {
+                let discriminant = read_len(buf)?;
+                let case = ty
+                    .cases
+                    .get(discriminant)
+                    .with_context(|| format!("unknown enum discriminant `{discriminant}`"))?;
+                Ok(Value::String(case.name.clone()))
+            }
+            TypeDefKind::Option(ty) => match read_u8(buf)? {
+                0 => Ok(Value::Null),
+                1 => decode_value(resolve, ty, buf),
+                n => bail!("invalid option status byte value `{n}`"),
+            },
+            TypeDefKind::Result(ty) => {
+                let (name, ty) = match read_u8(buf)? {
+                    0 => ("ok", ty.ok),
+                    1 => ("err", ty.err),
+                    n => bail!("invalid result status byte value `{n}`"),
+                };
+                let v = match ty {
+                    Some(ty) => decode_value(resolve, &ty, buf)?,
+                    None => Value::Null,
+                };
+                Ok(Value::Object([(name.to_string(), v)].into_iter().collect()))
+            }
+            TypeDefKind::Flags(ty) => {
+                let v = read_bytes(buf, ty.flags.len().div_ceil(8))?;
+                Ok(Value::Array(
+                    ty.flags
+                        .iter()
+                        .enumerate()
+                        .filter(|(i, _)| v[i / 8] & (1 << (i % 8)) != 0)
+                        .map(|(_, flag)| Value::String(flag.name.clone()))
+                        .collect(),
+                ))
+            }
+            TypeDefKind::Handle(Handle::Own(_) | Handle::Borrow(_)) => {
+                let n = read_len(buf)?;
+                let v = read_bytes(buf, n)?;
+                let v = v
+                    .iter()
+                    .fold(String::with_capacity(v.len() * 2), |mut s, b| {
+                        let _ = write!(s, "{b:02x}");
+                        s
+                    });
+                Ok(Value::String(v))
+            }
+            TypeDefKind::Future(..) | TypeDefKind::Stream(..) => {
+                bail!("decoding asynchronous values is not supported")
+            }
+            TypeDefKind::Resource | TypeDefKind::Unknown => {
+                bail!(
+                    "type `{}` cannot be decoded",
+                    resolve.types[*id].name.as_deref().unwrap_or("<anonymous>")
+                )
+            }
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WIT: &str = r#"
+package test:spy;
+
+interface types {
+    record point {
+        x: s32,
+        y: s32,
+    }
+    variant shape {
+        circle(u32),
+        empty,
+    }
+    flags perms {
+        read,
+        write,
+    }
+    draw: func(name: string, points: list<point>, shape: shape, perms: perms, data: list<u8>) -> result<option<f64>, string>;
+}
+
+world component {
+    export types;
+}
+"#;
+
+    #[test]
+    fn decode_params_and_results() -> Result<()> {
+        let mut resolve = Resolve::default();
+        let pkg = resolve.push_str("test.wit", WIT)?;
+        let world = resolve.select_world(pkg, Some("component"))?;
+        let wit = ComponentWit { resolve, world };
+        assert!(wit.import("test:spy/types", "draw").is_none());
+        let func = wit
+            .export("test:spy/types", "draw")
+            .context("exported function not found")?;
+
+        let params = [
+            &[2][..],
+            b"hi",
+            // points
+            &[1, 0x7f, 0x80, 0x01],
+            // shape
+            &[0, 0xe5, 0x8e, 0x26],
+            // perms
+            &[0b10],
+            // data
+            &[2, b'o', b'k'],
+        ]
+        .concat();
+        assert_eq!(
+            func.decode_params(&params)?,
+            serde_json::json!({
+                "name": "hi",
+                "points": [{ "x": -1, "y": 128 }],
+                "shape": { "circle": 624_485 },
+                "perms": ["write"],
+                "data": [111, 107],
+            })
+        );
+        assert!(func.decode_params(&params[..params.len() - 1]).is_err());
+        assert!(func
+            .decode_params(&[params.as_slice(), &[0]].concat())
+            .is_err());
+
+        let results = [&[0, 1][..], &1.5f64.to_le_bytes()].concat();
+        assert_eq!(
+            func.decode_results(&results)?,
+            serde_json::json!({ "ok": 1.5 })
+        );
+        assert_eq!(
+            func.decode_results(&[1, 3, b'b', b'a', b'd'])?,
+            serde_json::json!({ "err": "bad" })
+        );
+        Ok(())
+    }
+}
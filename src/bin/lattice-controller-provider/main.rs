@@ -0,0 +1,12 @@
+//! Lattice control interface implementation for wasmcloud:lattice-control.
+
+use anyhow::Context as _;
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    wasmcloud_provider_lattice_controller::run()
+        .await
+        .context("failed to run provider")?;
+    eprintln!("Lattice controller provider exiting");
+    Ok(())
+}
@@ -21,7 +21,7 @@ use wasmcloud_host::url::Url;
 use wasmcloud_host::wasmbus::host_config::PolicyService as PolicyServiceConfig;
 use wasmcloud_host::wasmbus::Features;
 use wasmcloud_host::WasmbusHostConfig;
-use wasmcloud_tracing::configure_observability;
+use wasmcloud_tracing::{configure_observability, PrometheusReader};
 
 #[derive(Debug, Parser)]
 #[allow(clippy::struct_excessive_bools)]
@@ -217,6 +217,9 @@ struct Args {
         env = "WASMCLOUD_MAX_COMPONENTS"
     )]
     max_components: u32,
+    /// If provided, meters the fuel consumed by components, which allows limiting the fuel a single invocation can consume with the `wasmcloud.dev/fuel-budget` annotation
+    #[clap(long = "fuel-metering", env = "WASMCLOUD_FUEL_METERING")]
+    fuel_metering: bool,
     /// If provided, allows setting a custom timeout for requesting policy decisions. Defaults to one second. Requires `policy_topic` to be set.
     #[clap(
         long = "policy-timeout-ms",
@@ -230,6 +233,27 @@ struct Args {
     #[clap(long = "secrets-topic", env = "WASMCLOUD_SECRETS_TOPIC")]
     secrets_topic_prefix: Option<String>,
 
+    /// If provided, caches secrets fetched from secrets backends for the given number of seconds. Expired secrets are still used if their backend is unavailable.
+    #[clap(long = "secrets-cache-ttl-seconds", env = "WASMCLOUD_SECRETS_CACHE_TTL", value_parser = parse_duration_secs)]
+    secrets_cache_ttl: Option<Duration>,
+
+    /// The number of seconds that expired secrets are still used for if their backend is unavailable, after which they are evicted from the cache
+    #[clap(long = "secrets-cache-max-stale-seconds", env = "WASMCLOUD_SECRETS_CACHE_MAX_STALE", default_value = "3600", value_parser = parse_duration_secs)]
+    secrets_cache_max_stale: Duration,
+
+    /// If provided, components and providers on this host sign the invocations they send over the lattice with the host key
+    #[clap(long = "sign-invocations", env = "WASMCLOUD_SIGN_INVOCATIONS")]
+    sign_invocations: bool,
+
+    /// A comma-separated list of public keys whose signed invocations are accepted. If provided, components and providers on this host reject invocations which are not signed by, or on behalf of, one of these keys or this host. Requires invocations to be signed
+    #[clap(
+        long = "invocation-issuer",
+        env = "WASMCLOUD_INVOCATION_ISSUERS",
+        value_delimiter = ',',
+        requires = "sign_invocations"
+    )]
+    invocation_issuers: Vec<String>,
+
     /// Used in tandem with `oci_user` and `oci_password` to override credentials for a specific OCI registry.
     #[clap(
         long = "oci-registry",
@@ -350,6 +374,14 @@ struct Args {
     #[clap(long = "http-admin", env = "WASMCLOUD_HTTP_ADMIN")]
     /// HTTP administration endpoint address
     http_admin: Option<SocketAddr>,
+
+    /// If provided, serves host metrics in the Prometheus text format on `/metrics` of the HTTP administration endpoint
+    #[clap(
+        long = "http-admin-metrics",
+        env = "WASMCLOUD_HTTP_ADMIN_METRICS",
+        requires = "http_admin"
+    )]
+    http_admin_metrics: bool,
 }
 
 const DEFAULT_SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(10);
@@ -385,6 +417,7 @@ async fn main() -> anyhow::Result<()> {
         ..Default::default()
     };
     let log_level = WasmcloudLogLevel::from(args.log_level);
+    let http_admin_metrics = args.http_admin_metrics.then(PrometheusReader::new);
 
     let _guard = match configure_observability(
         "wasmcloud-host",
@@ -393,6 +426,7 @@ async fn main() -> anyhow::Result<()> {
         args.flame_graph,
         Some(&log_level),
         Some(&otel_config.trace_level),
+        http_admin_metrics.as_ref(),
     ) {
         Ok((dispatch, guard)) => {
             dispatch
@@ -508,15 +542,21 @@ async fn main() -> anyhow::Result<()> {
         otel_config,
         policy_service_config,
         secrets_topic_prefix: args.secrets_topic_prefix,
+        secrets_cache_ttl: args.secrets_cache_ttl,
+        secrets_cache_max_stale: args.secrets_cache_max_stale,
+        sign_invocations: args.sign_invocations,
+        invocation_issuers: args.invocation_issuers,
         version: env!("CARGO_PKG_VERSION").to_string(),
         max_execution_time: args.max_execution_time,
         max_linear_memory: args.max_linear_memory,
         max_component_size: args.max_component_size,
         max_components: args.max_components,
+        fuel_metering: args.fuel_metering,
         heartbeat_interval: args.heartbeat_interval,
         // NOTE(brooks): Summing the feature flags "OR"s the multiple flags together.
         experimental_features: args.experimental_features.into_iter().sum(),
         http_admin: args.http_admin,
+        http_admin_metrics,
     }))
     .await
     .context("failed to initialize host")?;
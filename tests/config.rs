@@ -278,7 +278,25 @@ async fn config_e2e() -> anyhow::Result<()> {
     .await
     .expect("should advertise link");
 
-    assert_incoming_http(&wrpc_client).await?;
+    assert_incoming_http(&wrpc_client, "sup3rs3cr3t-v4lu3").await?;
+
+    // Rotate the secret, the backend notifies the host which fetches it again for the component
+    nats_kv_secrets_backend
+        .put_secret(PutSecretRequest {
+            key: "ponger".to_string(),
+            string_secret: Some("r0t4t3d-v4lu3".to_string()),
+            ..Default::default()
+        })
+        .await?;
+    let mut rotated = Err(anyhow!("secret was not rotated"));
+    for _ in 0..10 {
+        tokio::time::sleep(Duration::from_millis(500)).await;
+        rotated = assert_incoming_http(&wrpc_client, "r0t4t3d-v4lu3").await;
+        if rotated.is_ok() {
+            break;
+        }
+    }
+    rotated.context("component should see the rotated secret")?;
 
     secrets_backend_server
         .stop()
@@ -295,6 +313,7 @@ async fn config_e2e() -> anyhow::Result<()> {
 #[instrument(skip_all, ret)]
 async fn assert_incoming_http(
     wrpc_client: &Arc<wrpc_transport_nats::Client>,
+    expected_secret: &str,
 ) -> anyhow::Result<()> {
     let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0))
         .await
@@ -354,7 +373,7 @@ async fn assert_incoming_http(
             } = serde_json::from_str(&http_res).context("failed to decode body as JSON")?;
             ensure!(pong == "config", "pong value was not correct");
             ensure!(
-                pong_secret == "sup3rs3cr3t-v4lu3",
+                pong_secret == expected_secret,
                 "pong_secret value was not correct"
             );
             ensure!(
@@ -0,0 +1,219 @@
+#![cfg(all(feature = "provider-http-server", feature = "provider-keyvalue-redis",))]
+
+use core::str;
+use core::time::Duration;
+
+use std::net::Ipv4Addr;
+use std::sync::Arc;
+
+use anyhow::Context as _;
+use nkeys::KeyPair;
+use tokio::time::sleep;
+use tokio::{join, try_join};
+use tracing_subscriber::prelude::*;
+use wasmcloud_core::tls::NativeRootsExt as _;
+use wasmcloud_host::wasmbus::HostConfig;
+use wasmcloud_test_util::lattice::config::assert_config_put;
+use wasmcloud_test_util::provider::{assert_start_provider, StartProviderArgs};
+use wasmcloud_test_util::{
+    component::assert_scale_component, host::WasmCloudTestHost,
+    lattice::link::assert_advertise_link,
+};
+
+use test_components::RUST_HTTP_KEYVALUE_COUNTER;
+
+pub mod common;
+use common::free_port;
+use common::nats::start_nats;
+use common::providers;
+use common::redis::start_redis;
+
+const LATTICE: &str = "default";
+const COMPONENT_ID: &str = "http_keyvalue_counter_signed";
+
+async fn assert_increment(
+    client: &reqwest::Client,
+    port: u16,
+    path: &str,
+) -> anyhow::Result<String> {
+    client
+        .get(format!("http://localhost:{port}{path}"))
+        .send()
+        .await
+        .context("failed to connect to server")?
+        .text()
+        .await
+        .context("failed to get response text")
+}
+
+/// Ensure that invocations between a host and its providers are signed and verified in both
+/// directions when the host signs invocations and only accepts claims of specific issuers:
+///
+/// - the HTTP server provider invokes the component with claims signed by the key the host
+///   delegated to it
+/// - the component invokes the keyvalue provider with claims signed by the host
+#[tokio::test]
+async fn invocation_claims_round_trip() -> anyhow::Result<()> {
+    tracing_subscriber::registry()
+        .with(tracing_subscriber::fmt::layer().compact().without_time())
+        .with(
+            tracing_subscriber::EnvFilter::try_from_default_env().unwrap_or_else(|_| {
+                tracing_subscriber::EnvFilter::new("info,cranelift_codegen=warn,wasmcloud=trace")
+            }),
+        )
+        .init();
+
+    let ((nats_server, nats_url, nats_client), (redis_server, redis_url)) = try_join!(
+        async { start_nats().await.context("failed to start NATS") },
+        async { start_redis().await.context("failed to start Redis") },
+    )?;
+
+    // Build client for interacting with the lattice
+    let ctl_client = wasmcloud_control_interface::ClientBuilder::new(nats_client)
+        .lattice(LATTICE.to_string())
+        .build();
+    // Build the host
+    // Build a host that signs invocations and only accepts claims issued by itself, or by the
+    // unrelated issuer, directly or delegated to its providers
+    let host = WasmCloudTestHost::start_with_config(
+        None,
+        HostConfig {
+            ctl_nats_url: nats_url.clone(),
+            rpc_nats_url: nats_url.clone(),
+            lattice: LATTICE.into(),
+            host_key: Some(Arc::new(KeyPair::new_server())),
+            provider_shutdown_delay: Some(Duration::from_millis(300)),
+            allow_file_load: true,
+            sign_invocations: true,
+            invocation_issuers: vec![KeyPair::new_server().public_key()],
+            ..Default::default()
+        },
+    )
+    .await
+    .context("failed to start test host")?;
+
+    let http_port = free_port().await?;
+
+    let http_server_config_name = "http-server".to_string();
+    let keyvalue_redis_config_name = "keyvalue-redis".to_string();
+
+    let (rust_http_server, rust_keyvalue_redis) = join!(
+        providers::rust_http_server(),
+        providers::rust_keyvalue_redis(),
+    );
+
+    let rust_http_server_id = rust_http_server.subject.public_key();
+    let rust_keyvalue_redis_id = rust_keyvalue_redis.subject.public_key();
+
+    try_join!(
+        async {
+            try_join!(
+                assert_config_put(
+                    &ctl_client,
+                    &http_server_config_name,
+                    [(
+                        "ADDRESS".to_string(),
+                        format!("{}:{http_port}", Ipv4Addr::LOCALHOST),
+                    )],
+                ),
+                assert_config_put(
+                    &ctl_client,
+                    &keyvalue_redis_config_name,
+                    [("URL".to_string(), redis_url.to_string())],
+                ),
+            )
+            .context("failed to put configuration")
+        },
+        async {
+            let host_key = host.host_key();
+            let rust_http_server_url = rust_http_server.url();
+            let rust_keyvalue_redis_url = rust_keyvalue_redis.url();
+            let host_id = host_key.public_key();
+            try_join!(
+                assert_start_provider(StartProviderArgs {
+                    client: &ctl_client,
+                    host_id: &host_id,
+                    provider_id: &rust_http_server_id,
+                    provider_ref: rust_http_server_url.as_str(),
+                    config: vec![],
+                }),
+                assert_start_provider(StartProviderArgs {
+                    client: &ctl_client,
+                    host_id: &host_id,
+                    provider_id: &rust_keyvalue_redis_id,
+                    provider_ref: rust_keyvalue_redis_url.as_str(),
+                    config: vec![],
+                }),
+            )
+            .context("failed to start providers")
+        },
+        async {
+            assert_scale_component(
+                &ctl_client,
+                host.host_key().public_key(),
+                format!("file://{RUST_HTTP_KEYVALUE_COUNTER}"),
+                COMPONENT_ID,
+                None,
+                5,
+                Vec::new(),
+                Duration::from_secs(10),
+            )
+            .await
+            .context("failed to scale `rust-http-keyvalue-counter` component")
+        }
+    )?;
+
+    assert_advertise_link(
+        &ctl_client,
+        &rust_http_server_id,
+        COMPONENT_ID,
+        "default",
+        "wasi",
+        "http",
+        vec!["incoming-handler".to_string()],
+        vec![http_server_config_name],
+        vec![],
+    )
+    .await
+    .context("failed to advertise link")?;
+
+    assert_advertise_link(
+        &ctl_client,
+        COMPONENT_ID,
+        &rust_keyvalue_redis_id,
+        "default",
+        "wasi",
+        "keyvalue",
+        vec!["atomics".to_string(), "store".to_string()],
+        vec![],
+        vec![keyvalue_redis_config_name],
+    )
+    .await
+    .context("failed to advertise link")?;
+
+    let http_client = reqwest::Client::builder()
+        .with_native_certificates()
+        .timeout(Duration::from_secs(20))
+        .connect_timeout(Duration::from_secs(20))
+        .build()
+        .context("failed to build HTTP client")?;
+
+    // Wait for data to be propagated across lattice
+    sleep(Duration::from_secs(1)).await;
+
+    assert_eq!(
+        assert_increment(&http_client, http_port, "").await?,
+        "Counter /: 1\n"
+    );
+
+    assert_eq!(
+        assert_increment(&http_client, http_port, "/").await?,
+        "Counter /: 2\n"
+    );
+
+    try_join!(
+        async { nats_server.stop().await.context("failed to stop NATS") },
+        async { redis_server.stop().await.context("failed to stop Redis") },
+    )?;
+    Ok(())
+}
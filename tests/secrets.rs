@@ -0,0 +1,273 @@
+#![cfg(feature = "wasmcloud")]
+
+use std::collections::{HashMap, HashSet};
+use std::net::Ipv4Addr;
+use std::time::Duration;
+
+use anyhow::{ensure, Context, Result};
+use async_nats::jetstream;
+use futures::StreamExt;
+use secrets_nats_kv::PutSecretRequest;
+use wasmcloud_core::InterfaceLinkDefinition;
+use wasmcloud_host::secrets::{BackendSecretUpdate, Manager};
+use wasmcloud_secrets_types::{SecretConfig, SecretUpdate};
+use wasmcloud_test_util::host::WasmCloudTestHost;
+use wasmcloud_test_util::lattice::config::{assert_config_put, assert_put_secret_reference};
+use wasmcloud_test_util::lattice::link::assert_advertise_link;
+use wasmcloud_test_util::provider::{assert_start_provider, StartProviderArgs};
+
+pub mod common;
+use common::free_port;
+use common::nats::start_nats;
+use common::providers;
+use common::secrets::NatsKvSecretsBackend;
+
+const LATTICE: &str = "secrets";
+
+async fn put_secret_reference(
+    store: &jetstream::kv::Store,
+    name: &str,
+    backend: &str,
+    key: &str,
+    version: Option<&str>,
+) -> Result<()> {
+    let config = SecretConfig::new(
+        name.to_string(),
+        backend.to_string(),
+        key.to_string(),
+        None,
+        version.map(String::from),
+        HashMap::new(),
+    );
+    store
+        .put(
+            format!("SECRET_{name}"),
+            serde_json::to_vec(&config)?.into(),
+        )
+        .await
+        .context("failed to put secret reference")
+        .map(|_| ())
+}
+
+fn update(backend: &str, key: &str) -> BackendSecretUpdate {
+    BackendSecretUpdate {
+        backend: backend.to_string(),
+        update: SecretUpdate {
+            key: key.to_string(),
+            version: Some("2".to_string()),
+        },
+    }
+}
+
+#[tokio::test]
+async fn secret_references_affected_by_updates() -> Result<()> {
+    let (nats_server, _, nats_client) = start_nats()
+        .await
+        .context("failed to start backing services")?;
+
+    let store = jetstream::new(nats_client.clone())
+        .create_key_value(jetstream::kv::Config {
+            bucket: "CONFIGDATA".into(),
+            ..Default::default()
+        })
+        .await
+        .context("Unable to set up NATS KV store for test")?;
+    put_secret_reference(&store, "latest", "nats-kv", "db", None).await?;
+    put_secret_reference(&store, "pinned", "nats-kv", "db", Some("1")).await?;
+    put_secret_reference(&store, "other", "vault", "db", None).await?;
+    store.put("config", "{}".into()).await?;
+
+    let manager = Manager::new(&store, None, &nats_client);
+    let names = |names: &[&str]| names.iter().map(|n| n.to_string()).collect::<Vec<_>>();
+
+    // References to the latest version of the updated key in the same backend are affected
+    assert!(
+        manager
+            .is_affected_by(&names(&["SECRET_latest"]), &update("nats-kv", "db"))
+            .await
+    );
+    assert!(
+        manager
+            .is_affected_by(
+                &names(&["SECRET_pinned", "SECRET_latest"]),
+                &update("nats-kv", "db")
+            )
+            .await
+    );
+    // References pinned to a version, to other keys or to other backends are not
+    assert!(
+        !manager
+            .is_affected_by(&names(&["SECRET_pinned"]), &update("nats-kv", "db"))
+            .await
+    );
+    assert!(
+        !manager
+            .is_affected_by(&names(&["SECRET_latest"]), &update("nats-kv", "other"))
+            .await
+    );
+    assert!(
+        !manager
+            .is_affected_by(&names(&["SECRET_other"]), &update("nats-kv", "db"))
+            .await
+    );
+    // Missing and invalid references are ignored
+    assert!(
+        !manager
+            .is_affected_by(
+                &names(&["SECRET_missing", "config"]),
+                &update("nats-kv", "db")
+            )
+            .await
+    );
+    assert!(!manager.is_affected_by(&[], &update("nats-kv", "db")).await);
+
+    let _ = nats_server.stop().await;
+    Ok(())
+}
+
+/// Wait for the next link put published to a provider for the link from `source_id` to `target`
+async fn next_link_put(
+    link_puts: &mut async_nats::Subscriber,
+    source_id: &str,
+    target: &str,
+) -> Result<InterfaceLinkDefinition> {
+    loop {
+        let msg = tokio::time::timeout(Duration::from_secs(10), link_puts.next())
+            .await
+            .context("timed out waiting for link put")?
+            .context("link put subscription ended")?;
+        let link: InterfaceLinkDefinition =
+            serde_json::from_slice(&msg.payload).context("failed to deserialize link put")?;
+        if link.source_id == source_id && link.target == target {
+            return Ok(link);
+        }
+    }
+}
+
+#[tokio::test]
+async fn link_secrets_are_put_again_on_rotation() -> Result<()> {
+    let (nats_server, nats_url, nats_client) = start_nats()
+        .await
+        .context("failed to start backing services")?;
+
+    let ctl_client = wasmcloud_control_interface::ClientBuilder::new(nats_client.clone())
+        .lattice(LATTICE.to_string())
+        .build();
+    let host = WasmCloudTestHost::start_custom(
+        &nats_url,
+        LATTICE,
+        None,
+        None,
+        None,
+        Some("wasmcloud.secrets".to_string()),
+    )
+    .await
+    .context("failed to start test host")?;
+
+    let secrets_backend = NatsKvSecretsBackend::new(
+        "wasmcloud.secrets".to_string(),
+        "TEST_SECRET_links".to_string(),
+        nats_url.to_string(),
+    )
+    .await?;
+    secrets_backend.ensure_build().await?;
+    let secrets_backend_server = secrets_backend.start().await?;
+    secrets_backend
+        .put_secret(PutSecretRequest {
+            key: "link-token".to_string(),
+            string_secret: Some("first".to_string()),
+            ..Default::default()
+        })
+        .await?;
+
+    let rust_http_server = providers::rust_http_server().await;
+    let rust_http_server_id = rust_http_server.subject.public_key();
+    // Link secrets are fetched on behalf of the provider the link is put to
+    secrets_backend
+        .add_mapping(
+            &rust_http_server_id,
+            HashSet::from(["link-token".to_string()]),
+        )
+        .await?;
+
+    let http_port = free_port().await?;
+    assert_config_put(
+        &ctl_client,
+        "http-server",
+        [
+            (
+                "default_address".to_string(),
+                format!("{}:{http_port}", Ipv4Addr::LOCALHOST),
+            ),
+            ("routing_mode".to_string(), "path".to_string()),
+        ],
+    )
+    .await?;
+    assert_config_put(
+        &ctl_client,
+        "link-path",
+        [("path".to_string(), "/secret".to_string())],
+    )
+    .await?;
+    assert_put_secret_reference(
+        &ctl_client,
+        "link-token",
+        "link-token",
+        "nats-kv",
+        None,
+        None,
+        HashMap::new(),
+    )
+    .await?;
+    assert_start_provider(StartProviderArgs {
+        client: &ctl_client,
+        host_id: &host.host_key().public_key(),
+        provider_id: &rust_http_server_id,
+        provider_ref: rust_http_server.url().as_str(),
+        config: vec!["http-server".to_string()],
+    })
+    .await?;
+
+    let mut link_puts = nats_client
+        .subscribe(format!("wasmbus.rpc.{LATTICE}.*.linkdefs.put"))
+        .await?;
+    assert_advertise_link(
+        &ctl_client,
+        &rust_http_server_id,
+        "component",
+        "default",
+        "wasi",
+        "http",
+        vec!["incoming-handler".to_string()],
+        vec!["link-path".to_string(), "SECRET_link-token".to_string()],
+        vec![],
+    )
+    .await?;
+    let link = next_link_put(&mut link_puts, &rust_http_server_id, "component").await?;
+    ensure!(
+        link.source_secrets.is_some(),
+        "link should be put with its secrets"
+    );
+
+    // Rotating the secret puts the link to the provider again, with the secrets fetched again
+    secrets_backend
+        .put_secret(PutSecretRequest {
+            key: "link-token".to_string(),
+            string_secret: Some("second".to_string()),
+            ..Default::default()
+        })
+        .await?;
+    let rotated = next_link_put(&mut link_puts, &rust_http_server_id, "component").await?;
+    ensure!(
+        rotated.source_secrets.is_some(),
+        "link should be put again with its secrets"
+    );
+    assert_eq!(rotated.source_config, link.source_config);
+
+    secrets_backend_server
+        .stop()
+        .await
+        .context("failed to stop secrets backend")?;
+    let _ = nats_server.stop().await;
+    Ok(())
+}